@@ -1,11 +1,76 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, PoisonError, RwLock};
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+mod plural;
+
+pub use plural::{PluralCategory, plural_category};
+
+/// A catalog entry: one string, or CLDR plural variants of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Plural(BTreeMap<PluralCategory, String>),
+}
+
+impl Message {
+    /// The variant for `category`, else the `other` variant.
+    fn variant(&self, category: PluralCategory) -> Option<&str> {
+        match self {
+            Message::Text(text) => Some(text),
+            Message::Plural(variants) => variants
+                .get(&category)
+                .or_else(|| variants.get(&PluralCategory::Other))
+                .map(String::as_str),
+        }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::Text(text.to_string())
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::Text(text)
+    }
+}
+
+/// Messages of one locale, keyed by message key.
+pub type Catalog = HashMap<String, Message>;
+
+/// Catalogs by canonical locale tag (`de`, `de-at`); `en` starts with the built-in
+/// English strings.
+static CATALOGS: LazyLock<RwLock<HashMap<String, Catalog>>> = LazyLock::new(|| {
+    let mut english = ENGLISH_MESSAGES
+        .iter()
+        .map(|(key, message)| (key.to_string(), Message::from(*message)))
+        .collect::<Catalog>();
+    for (key, one, other) in ENGLISH_PLURALS {
+        let variants = BTreeMap::from([
+            (PluralCategory::One, one.to_string()),
+            (PluralCategory::Other, other.to_string()),
+        ]);
+        english.insert(key.to_string(), Message::Plural(variants));
+    }
+    RwLock::new(HashMap::from([("en".to_string(), english)]))
+});
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct I18nText {
     pub message_key: String,
     pub fallback: String,
+    /// Values for the `{name}` placeholders of the message and the fallback.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub args: BTreeMap<String, String>,
 }
 
 impl I18nText {
@@ -13,8 +78,26 @@ impl I18nText {
         Self {
             message_key: message_key.into(),
             fallback: fallback.into(),
+            args: BTreeMap::new(),
         }
     }
+
+    pub fn with_arg(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.args.insert(name.into(), value.into());
+        self
+    }
+
+    /// The fallback with its placeholders filled in.
+    pub fn fallback_message(&self) -> String {
+        interpolate(&self.fallback, &self.arg_pairs())
+    }
+
+    fn arg_pairs(&self) -> Vec<(&str, &str)> {
+        self.args
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
 }
 
 pub fn normalize_locale(value: &str) -> String {
@@ -26,11 +109,126 @@ pub fn normalize_locale(value: &str) -> String {
     }
 }
 
+/// Lowercase `value` with `-` separators, dropping an encoding or modifier
+/// (`de_AT.UTF-8` -> `de-at`).
+fn canonical_locale(value: &str) -> String {
+    let tag = value.trim().split(['.', '@']).next().unwrap_or_default();
+    tag.replace('_', "-").to_ascii_lowercase()
+}
+
+/// Catalogs consulted for `locale`, most specific first: `de-AT` gives `de-at`, `de`.
+pub fn locale_chain(locale: &str) -> Vec<String> {
+    let tag = canonical_locale(locale);
+    if tag.is_empty() {
+        return vec!["en".to_string()];
+    }
+    let mut chain = vec![tag.clone()];
+    let mut rest = tag.as_str();
+    while let Some((parent, _)) = rest.rsplit_once('-') {
+        if !parent.is_empty() {
+            chain.push(parent.to_string());
+        }
+        rest = parent;
+    }
+    chain
+}
+
+/// Add `messages` to the catalog of `locale`, replacing keys it already has.
+pub fn register_catalog(locale: &str, messages: Catalog) {
+    let mut catalogs = CATALOGS.write().unwrap_or_else(PoisonError::into_inner);
+    catalogs
+        .entry(canonical_locale(locale))
+        .or_default()
+        .extend(messages);
+}
+
+/// Locales with a registered catalog, including the built-in `en`.
+pub fn registered_locales() -> Vec<String> {
+    let catalogs = CATALOGS.read().unwrap_or_else(PoisonError::into_inner);
+    let mut locales = catalogs.keys().cloned().collect::<Vec<_>>();
+    locales.sort();
+    locales
+}
+
+/// Register every `{locale}.json` file in `dir`, each an object of message key to
+/// translated string, or to an object of plural variants
+/// (`{"one": "...", "other": "..."}`). Returns the locales loaded.
+#[cfg(feature = "catalogs")]
+pub fn load_catalog_dir(dir: &std::path::Path) -> std::io::Result<Vec<String>> {
+    use std::io::{Error, ErrorKind};
+
+    let mut loaded = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let bytes = std::fs::read(&path)?;
+        let invalid = |err: String| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid catalog {}: {err}", path.display()),
+            )
+        };
+        let entries: HashMap<String, serde_json::Value> =
+            serde_json::from_slice(&bytes).map_err(|err| invalid(err.to_string()))?;
+        let messages = entries
+            .into_iter()
+            .map(|(key, value)| {
+                let message = message_from_json(value).map_err(|err| format!("`{key}`: {err}"))?;
+                Ok((key, message))
+            })
+            .collect::<Result<Catalog, String>>()
+            .map_err(invalid)?;
+        register_catalog(locale, messages);
+        loaded.push(canonical_locale(locale));
+    }
+    loaded.sort();
+    Ok(loaded)
+}
+
+#[cfg(feature = "catalogs")]
+fn message_from_json(value: serde_json::Value) -> Result<Message, String> {
+    match value {
+        serde_json::Value::String(text) => Ok(Message::Text(text)),
+        serde_json::Value::Object(variants) => variants
+            .into_iter()
+            .map(|(name, text)| {
+                let category = PluralCategory::from_name(&name)
+                    .ok_or_else(|| format!("unknown plural category `{name}`"))?;
+                match text {
+                    serde_json::Value::String(text) => Ok((category, text)),
+                    _ => Err(format!("plural variant `{name}` must be a string")),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Message::Plural),
+        _ => Err("expected a string or an object of plural variants".to_string()),
+    }
+}
+
 pub fn select_locale_with_sources(
     cli_locale: Option<&str>,
     explicit: Option<&str>,
     env_locale: Option<&str>,
     system_locale: Option<&str>,
+) -> String {
+    select_locale_with_candidates(cli_locale, explicit, &[], &[], env_locale, system_locale)
+}
+
+/// [`select_locale_with_sources`] with the weighted `candidates` of a client (e.g. from
+/// `Accept-Language`) between the explicit locale and the environment; they count only
+/// when one of them negotiates to a `supported` locale.
+pub fn select_locale_with_candidates(
+    cli_locale: Option<&str>,
+    explicit: Option<&str>,
+    candidates: &[(&str, f32)],
+    supported: &[&str],
+    env_locale: Option<&str>,
+    system_locale: Option<&str>,
 ) -> String {
     if let Some(value) = cli_locale.map(str::trim).filter(|value| !value.is_empty()) {
         return normalize_locale(value);
@@ -38,6 +236,9 @@ pub fn select_locale_with_sources(
     if let Some(value) = explicit.map(str::trim).filter(|value| !value.is_empty()) {
         return normalize_locale(value);
     }
+    if let Some(locale) = negotiate(candidates, supported) {
+        return locale;
+    }
     if let Some(value) = env_locale.map(str::trim).filter(|value| !value.is_empty()) {
         return normalize_locale(value);
     }
@@ -50,51 +251,333 @@ pub fn select_locale_with_sources(
     "en".to_string()
 }
 
+/// Parse an `Accept-Language` value into `(tag, q)` pairs in header order; entries with
+/// an unreadable weight are dropped.
+pub fn parse_accept_language(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';').map(str::trim);
+            let tag = parts.next().filter(|tag| !tag.is_empty())?;
+            let mut q = 1.0;
+            for param in parts {
+                if let Some(value) = param.strip_prefix("q=") {
+                    q = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|q| (0.0..=1.0).contains(q))?;
+                }
+            }
+            Some((tag.to_string(), q))
+        })
+        .collect()
+}
+
+/// The `supported` locale best matching the weighted `candidates`, or `en`.
+///
+/// Candidates are tried by descending `q` (ties keep their order) with RFC 4647 lookup:
+/// `de-AT` matches a supported `de-at`, else `de`. `*` takes `en`, or the first supported
+/// locale, that no `q=0` candidate rules out.
+pub fn negotiate_locale(candidates: &[(&str, f32)], supported: &[&str]) -> String {
+    negotiate(candidates, supported).unwrap_or_else(|| "en".to_string())
+}
+
+fn negotiate(candidates: &[(&str, f32)], supported: &[&str]) -> Option<String> {
+    let supported = supported
+        .iter()
+        .map(|locale| canonical_locale(locale))
+        .collect::<Vec<_>>();
+    let excluded = candidates
+        .iter()
+        .filter(|(_, q)| *q <= 0.0)
+        .map(|(tag, _)| canonical_locale(tag))
+        .collect::<Vec<_>>();
+    let acceptable = |locale: &String| supported.contains(locale) && !excluded.contains(locale);
+    let mut ranked = candidates
+        .iter()
+        .filter(|(_, q)| *q > 0.0)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|left, right| right.1.total_cmp(&left.1));
+    ranked.into_iter().find_map(|(tag, _)| {
+        if tag.trim() == "*" {
+            std::iter::once("en".to_string())
+                .chain(supported.iter().cloned())
+                .find(acceptable)
+        } else {
+            locale_chain(tag).into_iter().find(acceptable)
+        }
+    })
+}
+
 pub fn resolve_text(text: &I18nText, locale: &str) -> String {
-    resolve_message(&text.message_key, &text.fallback, locale)
+    resolve_message_with_args(&text.message_key, &text.fallback, &text.arg_pairs(), locale)
 }
 
+/// The message for `key` from the most specific catalog of `locale` that has it, or
+/// `fallback`.
 pub fn resolve_message(key: &str, fallback: &str, locale: &str) -> String {
-    let normalized = normalize_locale(locale);
-    match normalized.as_str() {
-        "en" => english_message(key).unwrap_or(fallback).to_string(),
-        _ => fallback.to_string(),
+    let catalogs = CATALOGS.read().unwrap_or_else(PoisonError::into_inner);
+    locale_chain(locale)
+        .iter()
+        .find_map(|tag| catalogs.get(tag)?.get(key)?.variant(PluralCategory::Other))
+        .unwrap_or(fallback)
+        .to_string()
+}
+
+/// The variant of `key` for `count` under the plural rules of the catalog that has it,
+/// with `{count}` and `args` filled in. English is tried after the locale chain, then the
+/// key itself is returned.
+pub fn resolve_plural(key: &str, count: u64, args: &[(&str, &str)], locale: &str) -> String {
+    let count_text = count.to_string();
+    let mut args = args.to_vec();
+    if !args.iter().any(|(name, _)| *name == "count") {
+        args.push(("count", &count_text));
     }
+    let mut chain = locale_chain(locale);
+    if !chain.iter().any(|tag| tag == "en") {
+        chain.push("en".to_string());
+    }
+    let catalogs = CATALOGS.read().unwrap_or_else(PoisonError::into_inner);
+    let message = chain.iter().find_map(|tag| {
+        catalogs
+            .get(tag)?
+            .get(key)?
+            .variant(plural_category(tag, count))
+    });
+    interpolate(message.unwrap_or(key), &args)
 }
 
-fn english_message(key: &str) -> Option<&'static str> {
-    match key {
-        "runner.operator.schema_hash_mismatch" => {
-            Some("schema hash mismatch between request and resolved contract")
-        }
-        "runner.operator.contract_introspection_failed" => {
-            Some("failed to introspect component contract")
-        }
-        "runner.operator.schema_ref_not_found" => Some("referenced schema not found in pack"),
-        "runner.operator.schema_load_failed" => Some("failed to load referenced schema"),
-        "runner.operator.new_state_schema_missing" => {
-            Some("missing config schema required for new_state validation")
-        }
-        "runner.operator.new_state_schema_load_failed" => {
-            Some("failed to load config schema for new_state validation")
-        }
-        "runner.operator.new_state_schema_unavailable" => {
-            Some("new_state schema unavailable in strict mode")
-        }
-        "runner.operator.tenant_mismatch" => Some("request tenant does not match routed tenant"),
-        "runner.operator.missing_provider_selector" => {
-            Some("request must include provider_id or provider_type")
+/// [`resolve_message`], with the `{name}` placeholders of the message filled from `args`.
+pub fn resolve_message_with_args(
+    key: &str,
+    fallback: &str,
+    args: &[(&str, &str)],
+    locale: &str,
+) -> String {
+    interpolate(&resolve_message(key, fallback, locale), args)
+}
+
+/// Replace each `{name}` in `template` with its value in `args`; placeholders without
+/// a value are kept as written.
+pub fn interpolate(template: &str, args: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let name = &after[..end];
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
         }
-        "runner.operator.provider_not_found" => Some("provider not found"),
-        "runner.operator.op_not_found" => Some("operation not found"),
-        "runner.operator.resolve_error" => Some("failed to resolve provider operation"),
-        "runner.schema.unsupported_constraint" => Some("schema includes unsupported constraint"),
-        "runner.schema.invalid_schema" => Some("invalid schema document"),
-        "runner.schema.validation_failed" => Some("schema validation failed"),
-        _ => None,
     }
+    out.push_str(rest);
+    out
+}
+
+/// Keys of the built-in English messages, plain and plural. Catalogs of other locales
+/// are linted against these.
+pub fn known_message_keys() -> Vec<&'static str> {
+    ENGLISH_MESSAGES
+        .iter()
+        .map(|(key, _)| *key)
+        .chain(ENGLISH_PLURALS.iter().map(|(key, _, _)| *key))
+        .collect()
 }
 
+/// How a registered locale's catalog compares with the known message keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CatalogLint {
+    pub locale: String,
+    /// Known keys that no catalog in the locale's chain translates, English aside.
+    pub missing: Vec<String>,
+    /// Keys of the locale's own catalog that are not known.
+    pub extra: Vec<String>,
+}
+
+impl CatalogLint {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Lint every registered catalog against `known`, ordered by locale. A regional catalog
+/// (`de-at`) counts keys its language catalog (`de`) translates as present.
+pub fn lint_catalogs(known: &[&str]) -> Vec<CatalogLint> {
+    let catalogs = CATALOGS.read().unwrap_or_else(PoisonError::into_inner);
+    let mut locales = catalogs.keys().collect::<Vec<_>>();
+    locales.sort();
+    locales
+        .into_iter()
+        .map(|locale| {
+            let chain = locale_chain(locale);
+            let translated = |key: &str| {
+                chain.iter().any(|tag| {
+                    catalogs
+                        .get(tag)
+                        .is_some_and(|catalog| catalog.contains_key(key))
+                })
+            };
+            let mut missing = known
+                .iter()
+                .filter(|key| !translated(key))
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            missing.sort();
+            let mut extra = catalogs[locale]
+                .keys()
+                .filter(|key| !known.contains(&key.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            extra.sort();
+            CatalogLint {
+                locale: locale.clone(),
+                missing,
+                extra,
+            }
+        })
+        .collect()
+}
+
+/// Plural messages of the embedded `en` catalog: key, `one` and `other` variants.
+const ENGLISH_PLURALS: &[(&str, &str, &str)] = &[(
+    "runner.operator.schema_validation_summary",
+    "{target} failed schema validation: {count} issue found",
+    "{target} failed schema validation: {count} issues found",
+)];
+
+/// The embedded `en` catalog.
+const ENGLISH_MESSAGES: &[(&str, &str)] = &[
+    (
+        "runner.operator.schema_hash_mismatch",
+        "schema hash mismatch for op `{op}`: expected `{expected}`, got `{provided}`",
+    ),
+    (
+        "runner.operator.contract_introspection_failed",
+        "failed to introspect component contract",
+    ),
+    (
+        "runner.operator.schema_ref_not_found",
+        "referenced schema not found in pack",
+    ),
+    (
+        "runner.operator.schema_load_failed",
+        "failed to load referenced schema",
+    ),
+    (
+        "runner.operator.new_state_schema_missing",
+        "missing config schema required for new_state validation",
+    ),
+    (
+        "runner.operator.new_state_schema_load_failed",
+        "failed to load config schema for new_state validation",
+    ),
+    (
+        "runner.operator.new_state_schema_unavailable",
+        "new_state schema unavailable in strict mode",
+    ),
+    (
+        "runner.operator.tenant_mismatch",
+        "request tenant does not match routed tenant",
+    ),
+    (
+        "runner.operator.missing_provider_selector",
+        "request must include provider_id or provider_type",
+    ),
+    ("runner.operator.provider_not_found", "provider not found"),
+    (
+        "runner.operator.op_not_found",
+        "operation `{op}` not found for provider `{provider}`",
+    ),
+    (
+        "runner.operator.resolve_error",
+        "failed to resolve provider operation",
+    ),
+    (
+        "runner.operator.resource_exhausted",
+        "component exceeded a resource limit",
+    ),
+    (
+        "runner.operator.state_quota_exceeded",
+        "tenant state quota exceeded",
+    ),
+    (
+        "runner.operator.pool_exhausted",
+        "component exceeded the instance pool limits",
+    ),
+    (
+        "runner.operator.cpu_deadline",
+        "component exceeded its cpu deadline",
+    ),
+    (
+        "runner.operator.wall_clock_timeout",
+        "request timeout elapsed during component execution",
+    ),
+    (
+        "runner.operator.invoke_timeout",
+        "invocation exceeded its {timeout_ms}ms timeout",
+    ),
+    (
+        "runner.operator.timeout_clamped",
+        "requested timeout of {requested}ms was cut to the {max}ms maximum",
+    ),
+    (
+        "runner.operator.capability_denied",
+        "op `{op}` declares capability `{capability}`, which tenant {tenant} does not permit",
+    ),
+    (
+        "runner.operator.rate_limited",
+        "rate limit of op `{op}` of `{provider}` exceeded; retry in {retry_after_ms} ms",
+    ),
+    (
+        "runner.operator.idempotency_conflict",
+        "idempotency key `{key}` was already used for a different payload",
+    ),
+    (
+        "runner.operator.idempotent_replay",
+        "replayed the response stored for idempotency key `{key}`",
+    ),
+    ("runner.operator.cancelled", "invocation was cancelled"),
+    (
+        "runner.operator.invoke_trap",
+        "component trapped during invoke",
+    ),
+    ("runner.operator.invoke_trap_hint", "trapped at {frames}"),
+    (
+        "runner.operator.host_failure",
+        "internal host failure during invoke",
+    ),
+    (
+        "runner.schema.unsupported_constraint",
+        "schema includes unsupported constraint",
+    ),
+    (
+        "runner.schema.unsupported_constraint_hint",
+        "pass the `{flag}` flag to skip unsupported constraints",
+    ),
+    ("runner.schema.invalid_schema", "invalid schema document"),
+    (
+        "runner.schema.validation_failed",
+        "schema validation failed",
+    ),
+    (
+        "runner.template.available_keys",
+        "available context keys: {keys}",
+    ),
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,9 +590,223 @@ mod tests {
 
     #[test]
     fn resolve_text_uses_key_and_fallback() {
-        let text = I18nText::new("runner.operator.op_not_found", "fallback");
+        let text = I18nText::new("runner.operator.provider_not_found", "fallback");
         let message = resolve_text(&text, "en");
-        assert_eq!(message, "operation not found");
+        assert_eq!(message, "provider not found");
+    }
+
+    #[test]
+    fn resolve_text_fills_placeholders() {
+        let text = I18nText::new("runner.operator.op_not_found", "op `{op}` missing")
+            .with_arg("op", "echo")
+            .with_arg("provider", "demo");
+        assert_eq!(
+            resolve_text(&text, "en"),
+            "operation `echo` not found for provider `demo`"
+        );
+        assert_eq!(resolve_text(&text, "xx"), "op `echo` missing");
+    }
+
+    #[test]
+    fn interpolate_keeps_unknown_placeholders() {
+        assert_eq!(
+            interpolate("{a} and {b} {unclosed", &[("a", "1")]),
+            "1 and {b} {unclosed"
+        );
+        assert_eq!(interpolate("{}{{a}}", &[("a", "x")]), "{}{x}");
+    }
+
+    #[test]
+    fn registered_catalogs_fall_back_along_the_locale_chain() {
+        register_catalog(
+            "de",
+            Catalog::from([(
+                "runner.operator.op_not_found".to_string(),
+                "Operation nicht gefunden".into(),
+            )]),
+        );
+        register_catalog(
+            "de_AT",
+            Catalog::from([(
+                "runner.operator.provider_not_found".to_string(),
+                "Provider ned gfunden".into(),
+            )]),
+        );
+        assert_eq!(locale_chain("de_AT.UTF-8"), ["de-at", "de"]);
+        assert_eq!(
+            resolve_message("runner.operator.provider_not_found", "fb", "de-AT"),
+            "Provider ned gfunden"
+        );
+        assert_eq!(
+            resolve_message("runner.operator.op_not_found", "fb", "de-AT"),
+            "Operation nicht gefunden"
+        );
+        assert_eq!(
+            resolve_message("runner.operator.cancelled", "fb", "de-AT"),
+            "fb"
+        );
+        assert_eq!(
+            resolve_message("runner.operator.cancelled", "fb", "en-GB"),
+            "invocation was cancelled"
+        );
+        assert!(registered_locales().contains(&"de-at".to_string()));
+    }
+
+    #[test]
+    fn lint_reports_missing_and_extra_keys() {
+        register_catalog(
+            "fi",
+            Catalog::from([
+                ("runner.operator.cancelled".to_string(), "peruttu".into()),
+                ("runner.operator.retired".to_string(), "vanha".into()),
+            ]),
+        );
+        register_catalog(
+            "fi-ax",
+            Catalog::from([("runner.operator.host_failure".to_string(), "fel".into())]),
+        );
+        let known = [
+            "runner.operator.cancelled",
+            "runner.operator.host_failure",
+            "runner.operator.op_not_found",
+        ];
+        let report = lint_catalogs(&known);
+        let lint = |locale| report.iter().find(|lint| lint.locale == locale).unwrap();
+        assert_eq!(
+            lint("fi").missing,
+            [
+                "runner.operator.host_failure",
+                "runner.operator.op_not_found"
+            ]
+        );
+        assert_eq!(lint("fi").extra, ["runner.operator.retired"]);
+        assert_eq!(lint("fi-ax").missing, ["runner.operator.op_not_found"]);
+        assert!(lint("fi-ax").extra.is_empty());
+        assert!(!lint("fi").is_complete());
+    }
+
+    #[test]
+    fn english_catalog_covers_the_known_keys() {
+        let known = known_message_keys();
+        assert!(known.contains(&"runner.operator.schema_validation_summary"));
+        let report = lint_catalogs(&known);
+        let english = report.iter().find(|lint| lint.locale == "en").unwrap();
+        assert!(english.is_complete(), "{english:?}");
+    }
+
+    #[test]
+    fn plural_categories_follow_the_language() {
+        let categories = |locale| [1, 2, 5, 22, 11].map(|count| plural_category(locale, count));
+        use PluralCategory::*;
+        assert_eq!(categories("en-US"), [One, Other, Other, Other, Other]);
+        assert_eq!(categories("ru"), [One, Few, Many, Few, Many]);
+        assert_eq!(categories("pl"), [One, Few, Many, Few, Many]);
+        assert_eq!(categories("cs"), [One, Few, Other, Other, Other]);
+        assert_eq!(plural_category("fr", 0), One);
+        assert_eq!(plural_category("ja", 1), Other);
+    }
+
+    #[test]
+    fn resolve_plural_picks_the_variant_for_the_count() {
+        let key = "runner.operator.schema_validation_summary";
+        let summary = |count, locale| resolve_plural(key, count, &[("target", "input")], locale);
+        assert_eq!(
+            summary(1, "en"),
+            "input failed schema validation: 1 issue found"
+        );
+        assert_eq!(
+            summary(2, "en"),
+            "input failed schema validation: 2 issues found"
+        );
+        assert_eq!(
+            summary(5, "en"),
+            "input failed schema validation: 5 issues found"
+        );
+
+        register_catalog(
+            "uk",
+            Catalog::from([(
+                key.to_string(),
+                Message::Plural(BTreeMap::from([
+                    (PluralCategory::One, "{count} помилка".to_string()),
+                    (PluralCategory::Few, "{count} помилки".to_string()),
+                    (PluralCategory::Many, "{count} помилок".to_string()),
+                ])),
+            )]),
+        );
+        assert_eq!(summary(1, "uk-UA"), "1 помилка");
+        assert_eq!(summary(2, "uk-UA"), "2 помилки");
+        assert_eq!(summary(5, "uk-UA"), "5 помилок");
+        // No `other` variant: the plain resolver moves on to the fallback.
+        assert_eq!(resolve_message(key, "fb", "uk"), "fb");
+        assert_eq!(
+            resolve_plural("runner.unknown", 3, &[], "uk"),
+            "runner.unknown"
+        );
+    }
+
+    #[test]
+    fn negotiation_follows_quality_values() {
+        let supported = ["en", "de"];
+        let header = parse_accept_language("en;q=0.1, de;q=0.9");
+        let candidates = header
+            .iter()
+            .map(|(tag, q)| (tag.as_str(), *q))
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, [("en", 0.1), ("de", 0.9)]);
+        assert_eq!(negotiate_locale(&candidates, &supported), "de");
+        assert_eq!(
+            negotiate_locale(&[("de-AT", 0.8), ("en", 0.8)], &supported),
+            "de"
+        );
+        assert_eq!(
+            parse_accept_language("de;q=2, fr"),
+            [("fr".to_string(), 1.0)]
+        );
+    }
+
+    #[test]
+    fn negotiation_skips_unsupported_locales() {
+        let supported = ["en", "nl"];
+        assert_eq!(
+            negotiate_locale(&[("fr-CA", 1.0), ("nl-BE", 0.5), ("en", 0.4)], &supported),
+            "nl"
+        );
+        assert_eq!(
+            negotiate_locale(&[("fr", 1.0), ("it", 0.5)], &supported),
+            "en"
+        );
+        assert_eq!(negotiate_locale(&[("nl", 0.0)], &supported), "en");
+    }
+
+    #[test]
+    fn negotiation_wildcard_takes_any_supported_locale() {
+        assert_eq!(
+            negotiate_locale(&[("fr", 1.0), ("*", 0.1)], &["de", "en"]),
+            "en"
+        );
+        assert_eq!(
+            negotiate_locale(&[("*", 1.0), ("en", 0.0)], &["en", "de"]),
+            "de"
+        );
+    }
+
+    #[test]
+    fn select_locale_negotiates_candidates_after_explicit() {
+        let candidates = [("de-DE", 0.9), ("en", 0.5)];
+        let supported = ["en", "de"];
+        assert_eq!(
+            select_locale_with_candidates(None, Some("it"), &candidates, &supported, None, None),
+            "it"
+        );
+        assert_eq!(
+            select_locale_with_candidates(None, None, &candidates, &supported, Some("fr"), None),
+            "de"
+        );
+        assert_eq!(
+            select_locale_with_candidates(None, None, &[("pt", 1.0)], &supported, Some("fr"), None),
+            "fr"
+        );
     }
 
     #[test]
@@ -0,0 +1,86 @@
+//! CLDR cardinal plural categories for integer counts.
+
+use crate::normalize_locale;
+
+/// CLDR plural category selecting a variant of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zero" => Some(PluralCategory::Zero),
+            "one" => Some(PluralCategory::One),
+            "two" => Some(PluralCategory::Two),
+            "few" => Some(PluralCategory::Few),
+            "many" => Some(PluralCategory::Many),
+            "other" => Some(PluralCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Category of `count` under the rules of `locale`'s language. Languages without a
+/// rule here use the English one (`one` for 1, `other` otherwise).
+pub fn plural_category(locale: &str, count: u64) -> PluralCategory {
+    let ones = count % 10;
+    let tens = count % 100;
+    let slavic_few = (2..=4).contains(&ones) && !(12..=14).contains(&tens);
+    match normalize_locale(locale).as_str() {
+        "ja" | "ko" | "zh" | "th" | "vi" | "id" | "ms" => PluralCategory::Other,
+        "fr" | "pt" if count <= 1 => PluralCategory::One,
+        "fr" | "pt" => PluralCategory::Other,
+        "ru" | "uk" | "be" => {
+            if ones == 1 && tens != 11 {
+                PluralCategory::One
+            } else if slavic_few {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        "hr" | "sr" | "bs" => {
+            if ones == 1 && tens != 11 {
+                PluralCategory::One
+            } else if slavic_few {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Other
+            }
+        }
+        "pl" => {
+            if count == 1 {
+                PluralCategory::One
+            } else if slavic_few {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        "cs" | "sk" => match count {
+            1 => PluralCategory::One,
+            2..=4 => PluralCategory::Few,
+            _ => PluralCategory::Other,
+        },
+        _ if count == 1 => PluralCategory::One,
+        _ => PluralCategory::Other,
+    }
+}
@@ -1,20 +1,26 @@
 use anyhow::{Context, Result, anyhow};
 use greentic_pack::reader::open_pack;
 use greentic_runner_host::RunnerWasiPolicy;
+use greentic_runner_host::cancel::{self, CancelReason, InvocationCancel};
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, DeterministicConfig, FlowRetryConfig, HostConfig, LifecycleConfig,
+    OperatorPolicy, RateLimits, RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy,
+    TenantWasiConfig, WasmEngineConfig, WasmLimits, WebhookPolicy,
+};
+use greentic_runner_host::pack::{
+    CacheRefresh, ComponentResolution, FlowDescriptor, PackMetadata, PackRuntime, read_pack_flows,
 };
-use greentic_runner_host::pack::{ComponentResolution, FlowDescriptor, PackMetadata, PackRuntime};
 use greentic_runner_host::runner::engine::{ExecutionObserver, FlowContext, FlowEngine, NodeEvent};
 pub use greentic_runner_host::runner::mocks::{
     HttpMock, HttpMockMode, KvMock, MocksConfig, SecretsMock, TelemetryMock, TimeMock, ToolsMock,
 };
 use greentic_runner_host::runner::mocks::{MockEventSink, MockLayer};
-use greentic_runner_host::secrets::default_manager;
-use greentic_runner_host::storage::{new_session_store, new_state_store};
+use greentic_runner_host::secrets::{DynSecretsManager, MemorySecretsManager, keyring};
+use greentic_runner_host::stdio::{self, StdioSink};
+use greentic_runner_host::storage::state::{seed_state, snapshot_state};
+use greentic_runner_host::storage::{DynStateStore, new_session_store, new_state_store};
 use greentic_runner_host::trace::TraceConfig;
-use greentic_runner_host::validate::ValidationConfig;
+use greentic_runner_host::validate::{ValidationConfig, ValidationIssue};
 use parking_lot::Mutex;
 use runner_core::normalize_under_root;
 use serde::{Deserialize, Serialize};
@@ -25,18 +31,64 @@ use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 use tokio::runtime::Runtime;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+mod watch;
+
+pub use watch::{PackWatch, WATCH_DEBOUNCE, run_pack_watch};
+
 const PROVIDER_ID_DEV: &str = "greentic-dev";
 
+/// How long a timed-out run waits for its cancelled components to unwind.
+const CANCEL_DRAIN: Duration = Duration::from_secs(1);
+
 /// Hook invoked for each transcript event.
 pub type TranscriptHook = Arc<dyn Fn(&Value) + Send + Sync>;
 
+/// Hook receiving [`RunEvent`]s as the run progresses.
+pub type RunEventHook = Arc<dyn Fn(RunEvent) + Send + Sync>;
+
+/// Progress of a run, delivered to [`RunOptions::on_event`] in the order it happens.
+/// Events own their data, so a hook may send them to another thread.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    NodeStarted {
+        node_id: String,
+        component: String,
+    },
+    NodeFinished {
+        node_id: String,
+        component: String,
+        status: NodeStatus,
+        duration_ms: u64,
+    },
+    /// A line the node's component wrote to stdout.
+    Stdout {
+        node_id: String,
+        line: String,
+    },
+    /// A line the node's component wrote to stderr.
+    Stderr {
+        node_id: String,
+        line: String,
+    },
+    /// Payload of an `emit.*` node.
+    Egress {
+        node_id: String,
+        payload: Value,
+    },
+    Warning {
+        node_id: Option<String>,
+        message: String,
+    },
+}
+
 /// Configuration for emitting OTLP telemetry.
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct OtlpHook {
@@ -108,8 +160,12 @@ pub struct RunOptions {
     pub profile: Profile,
     pub entry_flow: Option<String>,
     pub input: Value,
+    /// Input for particular flows by id, used instead of `input` when they run.
+    pub flow_inputs: BTreeMap<String, Value>,
     pub ctx: TenantContext,
     pub transcript: Option<TranscriptHook>,
+    /// Receives node, output and egress events while the run is in progress.
+    pub on_event: Option<RunEventHook>,
     pub otlp: Option<OtlpHook>,
     pub mocks: MocksConfig,
     pub artifacts_dir: Option<PathBuf>,
@@ -119,6 +175,17 @@ pub struct RunOptions {
     pub dist_offline: bool,
     pub dist_cache_dir: Option<PathBuf>,
     pub allow_missing_hash: bool,
+    /// Run components with fixed clocks and seeded randomness so repeated runs match.
+    pub deterministic: Option<DeterministicConfig>,
+    /// Abort the run with [`RunError::TimedOut`] once it has taken this long; unlimited
+    /// when `None`.
+    pub timeout: Option<Duration>,
+    /// Secrets served to the pack by key, ahead of the configured backend.
+    pub secrets: BTreeMap<String, String>,
+    /// State documents written for the tenant before the flow starts.
+    pub initial_state: BTreeMap<String, Value>,
+    /// Return the tenant's state as the run left it in [`RunResult::state`].
+    pub capture_state: bool,
 }
 
 impl Default for RunOptions {
@@ -133,8 +200,10 @@ impl fmt::Debug for RunOptions {
             .field("profile", &self.profile)
             .field("entry_flow", &self.entry_flow)
             .field("input", &self.input)
+            .field("flow_inputs", &self.flow_inputs)
             .field("ctx", &self.ctx)
             .field("transcript", &self.transcript.is_some())
+            .field("on_event", &self.on_event.is_some())
             .field("otlp", &self.otlp)
             .field("mocks", &self.mocks)
             .field("artifacts_dir", &self.artifacts_dir)
@@ -144,6 +213,14 @@ impl fmt::Debug for RunOptions {
             .field("dist_offline", &self.dist_offline)
             .field("dist_cache_dir", &self.dist_cache_dir)
             .field("allow_missing_hash", &self.allow_missing_hash)
+            .field("deterministic", &self.deterministic)
+            .field("timeout", &self.timeout)
+            .field("secrets", &self.secrets.keys().collect::<Vec<_>>())
+            .field(
+                "initial_state",
+                &self.initial_state.keys().collect::<Vec<_>>(),
+            )
+            .field("capture_state", &self.capture_state)
             .finish()
     }
 }
@@ -177,6 +254,11 @@ impl Runner {
         self
     }
 
+    pub fn deterministic(mut self, config: DeterministicConfig) -> Self {
+        self.base.deterministic = Some(config);
+        self
+    }
+
     pub fn configure(mut self, f: impl FnOnce(&mut RunOptions)) -> Self {
         f(&mut self.base);
         self
@@ -209,6 +291,24 @@ impl Runner {
         f(&mut opts);
         run_pack_with_options_async(pack_path, opts).await
     }
+
+    /// Run several flows of the pack with the configured options; see [`run_flows`].
+    pub fn run_flows<P: AsRef<Path>>(
+        &self,
+        pack_path: P,
+        flow_ids: &[String],
+    ) -> Result<Vec<(String, Result<RunResult>)>> {
+        run_flows(pack_path, flow_ids, self.base.clone())
+    }
+
+    /// Watch the pack with the configured options; see [`run_pack_watch`].
+    pub fn watch_pack<P, F>(&self, pack_path: P, on_result: F) -> Result<PackWatch>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Result<RunResult>) + Send + 'static,
+    {
+        run_pack_watch(pack_path, self.base.clone(), on_result)
+    }
 }
 
 impl Default for Runner {
@@ -220,7 +320,7 @@ impl Default for Runner {
 /// Execute a pack with the provided options.
 pub fn run_pack_with_options<P: AsRef<Path>>(pack_path: P, opts: RunOptions) -> Result<RunResult> {
     let runtime = Runtime::new().context("failed to create tokio runtime")?;
-    runtime.block_on(run_pack_async(pack_path.as_ref(), opts))
+    runtime.block_on(run_pack_async(pack_path.as_ref(), opts, CacheRefresh::None))
 }
 
 /// Execute a pack with the provided options using the caller's async runtime.
@@ -228,7 +328,60 @@ pub async fn run_pack_with_options_async<P: AsRef<Path>>(
     pack_path: P,
     opts: RunOptions,
 ) -> Result<RunResult> {
-    run_pack_async(pack_path.as_ref(), opts).await
+    run_pack_async(pack_path.as_ref(), opts, CacheRefresh::None).await
+}
+
+/// A flow declared by a pack, as reported by [`list_flows`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FlowSummary {
+    pub id: String,
+    /// Flow kind, e.g. `messaging` or `http`.
+    pub kind: String,
+    pub description: Option<String>,
+}
+
+impl From<FlowDescriptor> for FlowSummary {
+    fn from(flow: FlowDescriptor) -> Self {
+        Self {
+            id: flow.id,
+            kind: flow.flow_type,
+            description: flow.description,
+        }
+    }
+}
+
+/// List the flows of a `.gtpack` or pack directory from its manifest, without loading
+/// components.
+pub fn list_flows<P: AsRef<Path>>(pack_path: P) -> Result<Vec<FlowSummary>> {
+    let pack_path = normalize_pack_path(pack_path.as_ref())?;
+    let (_, flows) = read_pack_flows(&pack_path)
+        .with_context(|| format!("failed to read flows of {}", pack_path.display()))?;
+    Ok(flows.into_iter().map(FlowSummary::from).collect())
+}
+
+/// Load the pack once and run `flow_ids`, or every flow when empty, one after the other.
+///
+/// Components are compiled once for all runs and state carries over from one flow to
+/// the next. Each flow is recorded under `flows/<flow id>/` of the run directory and
+/// gets its own outcome, so one failing flow does not stop the rest; the outer error
+/// is reserved for a pack that cannot be loaded. `entry_flow` is ignored, and
+/// `flow_inputs` picks each flow's input.
+pub fn run_flows<P: AsRef<Path>>(
+    pack_path: P,
+    flow_ids: &[String],
+    opts: RunOptions,
+) -> Result<Vec<(String, Result<RunResult>)>> {
+    let runtime = Runtime::new().context("failed to create tokio runtime")?;
+    runtime.block_on(run_flows_async(pack_path.as_ref(), flow_ids, opts))
+}
+
+/// [`run_flows`] on the caller's async runtime.
+pub async fn run_flows_with_options_async<P: AsRef<Path>>(
+    pack_path: P,
+    flow_ids: &[String],
+    opts: RunOptions,
+) -> Result<Vec<(String, Result<RunResult>)>> {
+    run_flows_async(pack_path.as_ref(), flow_ids, opts).await
 }
 
 /// Reasonable defaults for local desktop invocations.
@@ -244,8 +397,10 @@ pub fn desktop_defaults() -> RunOptions {
         profile: Profile::Dev(DevProfile::default()),
         entry_flow: None,
         input: json!({}),
+        flow_inputs: BTreeMap::new(),
         ctx: TenantContext::default_local(),
         transcript: None,
+        on_event: None,
         otlp,
         mocks: MocksConfig::default(),
         artifacts_dir: None,
@@ -255,158 +410,335 @@ pub fn desktop_defaults() -> RunOptions {
         dist_offline: false,
         dist_cache_dir: None,
         allow_missing_hash: false,
+        deterministic: None,
+        timeout: None,
+        secrets: BTreeMap::new(),
+        initial_state: BTreeMap::new(),
+        capture_state: false,
     }
 }
 
-async fn run_pack_async(pack_path: &Path, opts: RunOptions) -> Result<RunResult> {
+async fn run_pack_async(
+    pack_path: &Path,
+    opts: RunOptions,
+    cache_refresh: CacheRefresh,
+) -> Result<RunResult> {
     let pack_path = normalize_pack_path(pack_path)?;
-    let resolved_profile = resolve_profile(&opts.profile, &opts.ctx);
-    if let Some(otlp) = &opts.otlp {
-        apply_otlp_hook(otlp);
-    }
-
+    let profile = resolve_profile(&opts.profile, &opts.ctx);
     let directories = prepare_run_dirs(opts.artifacts_dir.clone())?;
     info!(run_dir = %directories.root.display(), "prepared desktop run directory");
+    let recorder = Arc::new(RunRecorder::new(
+        directories.clone(),
+        &profile,
+        None,
+        PackMetadata::fallback(&pack_path),
+        opts.transcript.clone(),
+        opts.on_event.clone(),
+    )?);
 
-    let mock_layer = Arc::new(MockLayer::new(opts.mocks.clone(), &directories.root)?);
+    let loaded = LoadedPack::load(
+        pack_path,
+        profile,
+        &directories,
+        &recorder,
+        &opts,
+        cache_refresh,
+    )
+    .await?;
+    let entry_flow_id = resolve_entry_flow(
+        opts.entry_flow.clone(),
+        loaded.pack.metadata(),
+        &loaded.flows,
+    )?;
+    loaded.run(&recorder, &entry_flow_id, &opts).await
+}
 
+async fn run_flows_async(
+    pack_path: &Path,
+    flow_ids: &[String],
+    opts: RunOptions,
+) -> Result<Vec<(String, Result<RunResult>)>> {
+    let pack_path = normalize_pack_path(pack_path)?;
+    let profile = resolve_profile(&opts.profile, &opts.ctx);
+    let directories = prepare_run_dirs(opts.artifacts_dir.clone())?;
+    info!(run_dir = %directories.root.display(), "prepared desktop run directory");
+    // Records pack verification; every flow gets a recorder of its own.
     let recorder = Arc::new(RunRecorder::new(
         directories.clone(),
-        &resolved_profile,
+        &profile,
         None,
         PackMetadata::fallback(&pack_path),
         opts.transcript.clone(),
+        opts.on_event.clone(),
     )?);
 
-    let mock_sink: Arc<dyn MockEventSink> = recorder.clone();
-    mock_layer.register_sink(mock_sink);
-
-    if pack_path.is_file() {
-        match open_pack(&pack_path, to_reader_policy(opts.signing)) {
-            Ok(load) => {
-                let meta = &load.manifest.meta;
-                recorder.update_pack_metadata(PackMetadata {
-                    pack_id: meta.pack_id.clone(),
-                    version: meta.version.to_string(),
-                    entry_flows: meta.entry_flows.clone(),
-                    secret_requirements: Vec::new(),
-                });
-            }
-            Err(err) => {
-                recorder.record_verify_event("error", &err.message)?;
-                if opts.signing == SigningPolicy::DevOk && is_signature_error(&err.message) {
-                    warn!(error = %err.message, "continuing despite signature error (dev policy)");
-                } else {
-                    return Err(anyhow!("pack verification failed: {}", err.message));
+    let loaded = LoadedPack::load(
+        pack_path,
+        profile,
+        &directories,
+        &recorder,
+        &opts,
+        CacheRefresh::None,
+    )
+    .await?;
+    let selected = if flow_ids.is_empty() {
+        loaded.flows.iter().map(|flow| flow.id.clone()).collect()
+    } else {
+        flow_ids.to_vec()
+    };
+    let mut outcomes = Vec::with_capacity(selected.len());
+    for flow_id in selected {
+        let outcome = loaded.run_in(&directories, &flow_id, &opts).await;
+        outcomes.push((flow_id, outcome));
+    }
+    Ok(outcomes)
+}
+
+/// A pack loaded, with its components resolved, ready to run any of its flows.
+struct LoadedPack {
+    profile: ResolvedProfile,
+    host_config: Arc<HostConfig>,
+    mock_layer: Arc<MockLayer>,
+    state_store: DynStateStore,
+    pack: Arc<PackRuntime>,
+    engine: FlowEngine,
+    flows: Vec<FlowDescriptor>,
+}
+
+impl LoadedPack {
+    /// Verify and load the pack at `pack_path`, recording verification on `recorder`.
+    async fn load(
+        pack_path: PathBuf,
+        profile: ResolvedProfile,
+        directories: &RunDirectories,
+        recorder: &RunRecorder,
+        opts: &RunOptions,
+        cache_refresh: CacheRefresh,
+    ) -> Result<Self> {
+        if let Some(otlp) = &opts.otlp {
+            apply_otlp_hook(otlp);
+        }
+        let mock_layer = Arc::new(MockLayer::new(opts.mocks.clone(), &directories.root)?);
+
+        if pack_path.is_file() {
+            match open_pack(&pack_path, to_reader_policy(opts.signing)) {
+                Ok(load) => {
+                    let meta = &load.manifest.meta;
+                    recorder.update_pack_metadata(PackMetadata {
+                        pack_id: meta.pack_id.clone(),
+                        version: meta.version.to_string(),
+                        entry_flows: meta.entry_flows.clone(),
+                        secret_requirements: Vec::new(),
+                    });
+                }
+                Err(err) => {
+                    recorder.record_verify_event("error", &err.message)?;
+                    if opts.signing == SigningPolicy::DevOk && is_signature_error(&err.message) {
+                        warn!(error = %err.message, "continuing despite signature error (dev policy)");
+                        recorder.emit(RunEvent::Warning {
+                            node_id: None,
+                            message: format!(
+                                "continuing despite signature error (dev policy): {}",
+                                err.message
+                            ),
+                        });
+                    } else {
+                        return Err(anyhow!("pack verification failed: {}", err.message));
+                    }
                 }
             }
+        } else {
+            tracing::debug!(
+                path = %pack_path.display(),
+                "skipping pack verification for directory input"
+            );
         }
-    } else {
-        tracing::debug!(
-            path = %pack_path.display(),
-            "skipping pack verification for directory input"
+
+        let host_config = Arc::new(build_host_config(&profile, directories, opts.deterministic));
+        let mut component_resolution = ComponentResolution::default();
+        if let Some(dir) = opts.components_dir.clone() {
+            component_resolution.materialized_root = Some(dir);
+        } else if pack_path.is_dir() {
+            component_resolution.materialized_root = Some(pack_path.clone());
+        }
+        component_resolution.overrides = opts.components_map.clone();
+        component_resolution.dist_offline = opts.dist_offline;
+        component_resolution.dist_cache_dir = opts.dist_cache_dir.clone();
+        component_resolution.allow_missing_hash = opts.allow_missing_hash;
+        component_resolution.cache_refresh = cache_refresh;
+        let archive_source = if pack_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("gtpack"))
+            .unwrap_or(false)
+        {
+            Some(&pack_path)
+        } else {
+            None
+        };
+
+        let session_store = new_session_store();
+        let state_store = new_state_store();
+        let mut secrets_manager = local_secrets_manager()?;
+        if !opts.secrets.is_empty() {
+            secrets_manager =
+                Arc::new(MemorySecretsManager::new(&opts.secrets).with_fallback(secrets_manager));
+        }
+        seed_state(&state_store, &host_config.tenant, &opts.initial_state)
+            .map_err(|err| anyhow!("failed to seed initial state: {err}"))?;
+        let pack = Arc::new(
+            PackRuntime::load(
+                &pack_path,
+                Arc::clone(&host_config),
+                Some(Arc::clone(&mock_layer)),
+                archive_source.map(|p| p as &Path),
+                Some(Arc::clone(&session_store)),
+                Some(Arc::clone(&state_store)),
+                Arc::new(RunnerWasiPolicy::default()),
+                secrets_manager,
+                host_config.oauth_broker_config(),
+                false,
+                component_resolution,
+            )
+            .await
+            .with_context(|| format!("failed to load pack {}", pack_path.display()))?,
         );
+        recorder.update_pack_metadata(pack.metadata().clone());
+
+        let flows = pack
+            .list_flows()
+            .await
+            .context("failed to enumerate flows")?;
+        let engine = FlowEngine::new(vec![Arc::clone(&pack)], Arc::clone(&host_config))
+            .await
+            .context("failed to prime flow engine")?;
+
+        Ok(Self {
+            profile,
+            host_config,
+            mock_layer,
+            state_store,
+            pack,
+            engine,
+            flows,
+        })
     }
 
-    let host_config = Arc::new(build_host_config(&resolved_profile, &directories));
-    let mut component_resolution = ComponentResolution::default();
-    if let Some(dir) = opts.components_dir.clone() {
-        component_resolution.materialized_root = Some(dir);
-    } else if pack_path.is_dir() {
-        component_resolution.materialized_root = Some(pack_path.clone());
+    /// Run `flow_id` with its artifacts under `<root>/flows/<flow_id>/`.
+    async fn run_in(
+        &self,
+        root: &RunDirectories,
+        flow_id: &str,
+        opts: &RunOptions,
+    ) -> Result<RunResult> {
+        if !self.flows.iter().any(|flow| flow.id == flow_id) {
+            return Err(anyhow!("pack does not declare flow {flow_id}"));
+        }
+        let directories =
+            prepare_run_dirs(Some(root.root.join("flows").join(sanitize_id(flow_id))))?;
+        let recorder = Arc::new(RunRecorder::new(
+            directories,
+            &self.profile,
+            Some(flow_id.to_string()),
+            self.pack.metadata().clone(),
+            opts.transcript.clone(),
+            opts.on_event.clone(),
+        )?);
+        self.run(&recorder, flow_id, opts).await
     }
-    component_resolution.overrides = opts.components_map.clone();
-    component_resolution.dist_offline = opts.dist_offline;
-    component_resolution.dist_cache_dir = opts.dist_cache_dir.clone();
-    component_resolution.allow_missing_hash = opts.allow_missing_hash;
-    let archive_source = if pack_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("gtpack"))
-        .unwrap_or(false)
-    {
-        Some(&pack_path)
-    } else {
-        None
-    };
 
-    let session_store = new_session_store();
-    let state_store = new_state_store();
-    let secrets_manager = default_manager().context("failed to initialise secrets backend")?;
-    let pack = Arc::new(
-        PackRuntime::load(
-            &pack_path,
-            Arc::clone(&host_config),
-            Some(Arc::clone(&mock_layer)),
-            archive_source.map(|p| p as &Path),
-            Some(Arc::clone(&session_store)),
-            Some(Arc::clone(&state_store)),
-            Arc::new(RunnerWasiPolicy::default()),
-            secrets_manager,
-            host_config.oauth_broker_config(),
-            false,
-            component_resolution,
-        )
-        .await
-        .with_context(|| format!("failed to load pack {}", pack_path.display()))?,
-    );
-    recorder.update_pack_metadata(pack.metadata().clone());
-
-    let flows = pack
-        .list_flows()
-        .await
-        .context("failed to enumerate flows")?;
-    let entry_flow_id = resolve_entry_flow(opts.entry_flow.clone(), pack.metadata(), &flows)?;
-    recorder.set_flow_id(&entry_flow_id);
-
-    let engine = FlowEngine::new(vec![Arc::clone(&pack)], Arc::clone(&host_config))
-        .await
-        .context("failed to prime flow engine")?;
-
-    let started_at = OffsetDateTime::now_utc();
-    let tenant_str = host_config.tenant.clone();
-    let session_id_owned = resolved_profile.session_id.clone();
-    let provider_id_owned = resolved_profile.provider_id.clone();
-    let recorder_ref: &RunRecorder = &recorder;
-    let mock_ref: &MockLayer = &mock_layer;
-    let ctx = FlowContext {
-        tenant: &tenant_str,
-        pack_id: pack.metadata().pack_id.as_str(),
-        flow_id: &entry_flow_id,
-        node_id: None,
-        tool: None,
-        action: Some("run_pack"),
-        session_id: Some(session_id_owned.as_str()),
-        provider_id: Some(provider_id_owned.as_str()),
-        retry_config: host_config.retry_config().into(),
-        attempt: 1,
-        observer: Some(recorder_ref),
-        mocks: Some(mock_ref),
-    };
+    /// Run `flow_id`, recording it on `recorder`, whose directory receives `run.json`.
+    async fn run(
+        &self,
+        recorder: &Arc<RunRecorder>,
+        flow_id: &str,
+        opts: &RunOptions,
+    ) -> Result<RunResult> {
+        let mock_sink: Arc<dyn MockEventSink> = recorder.clone();
+        self.mock_layer.register_sink(mock_sink);
+        recorder.update_pack_metadata(self.pack.metadata().clone());
+        recorder.set_flow_id(flow_id);
+        let input = opts.flow_inputs.get(flow_id).unwrap_or(&opts.input);
+
+        let started_at = OffsetDateTime::now_utc();
+        let tenant_str = self.host_config.tenant.clone();
+        let session_id_owned = self.profile.session_id.clone();
+        let provider_id_owned = self.profile.provider_id.clone();
+        let recorder_ref: &RunRecorder = recorder;
+        let mock_ref: &MockLayer = &self.mock_layer;
+        let ctx = FlowContext {
+            tenant: &tenant_str,
+            pack_id: self.pack.metadata().pack_id.as_str(),
+            flow_id,
+            node_id: None,
+            tool: None,
+            action: Some("run_pack"),
+            session_id: Some(session_id_owned.as_str()),
+            provider_id: Some(provider_id_owned.as_str()),
+            retry_config: self.host_config.retry_config().into(),
+            attempt: 1,
+            observer: Some(recorder_ref),
+            mocks: Some(mock_ref),
+        };
 
-    let execution = engine.execute(ctx, opts.input.clone()).await;
-    let finished_at = OffsetDateTime::now_utc();
-
-    let status = match execution {
-        Ok(result) => match result.status {
-            greentic_runner_host::runner::engine::FlowStatus::Completed => RunCompletion::Ok,
-            greentic_runner_host::runner::engine::FlowStatus::Waiting(wait) => {
-                let reason = wait
-                    .reason
-                    .unwrap_or_else(|| "flow paused unexpectedly".to_string());
-                RunCompletion::Err(anyhow::anyhow!(reason))
+        let run = async {
+            match recorder.stdio.clone() {
+                Some(sink) => stdio::scope(sink, self.engine.execute(ctx, input.clone())).await,
+                None => self.engine.execute(ctx, input.clone()).await,
             }
-        },
-        Err(err) => RunCompletion::Err(err),
-    };
+        };
+        let invocation = InvocationCancel::new();
+        let mut run = std::pin::pin!(cancel::scope(invocation.clone(), run));
+        let mut timed_out = None;
+        let execution = match opts.timeout {
+            None => run.await,
+            Some(limit) => match tokio::time::timeout(limit, run.as_mut()).await {
+                Ok(execution) => execution,
+                Err(_) => {
+                    // Running components see `should-cancel` and are trapped after the
+                    // grace period; wait for that rather than leave a store running.
+                    invocation.cancel(CancelReason::Deadline);
+                    let _ = tokio::time::timeout(CANCEL_DRAIN, run.as_mut()).await;
+                    let err = RunError::TimedOut {
+                        timeout: limit,
+                        last_node: recorder.last_node(),
+                    };
+                    timed_out = Some(err.clone());
+                    Err(anyhow::Error::new(err))
+                }
+            },
+        };
+        let finished_at = OffsetDateTime::now_utc();
+
+        let status = match execution {
+            Ok(result) => match result.status {
+                greentic_runner_host::runner::engine::FlowStatus::Completed => RunCompletion::Ok,
+                greentic_runner_host::runner::engine::FlowStatus::Waiting(wait) => {
+                    let reason = wait
+                        .reason
+                        .unwrap_or_else(|| "flow paused unexpectedly".to_string());
+                    RunCompletion::Err(anyhow::anyhow!(reason))
+                }
+            },
+            Err(err) => RunCompletion::Err(err),
+        };
 
-    let result = recorder.finalise(status, started_at, finished_at)?;
+        let mut result = recorder.finalise(status, started_at, finished_at)?;
+        if opts.capture_state {
+            let state = snapshot_state(&self.state_store, &self.host_config.tenant)
+                .map_err(|err| anyhow!("failed to capture final state: {err}"))?;
+            result.state = Some(state);
+        }
 
-    let run_json_path = directories.root.join("run.json");
-    fs::write(&run_json_path, serde_json::to_vec_pretty(&result)?)
-        .with_context(|| format!("failed to write run summary {}", run_json_path.display()))?;
+        let run_json_path = recorder.directories.root.join("run.json");
+        fs::write(&run_json_path, serde_json::to_vec_pretty(&result)?)
+            .with_context(|| format!("failed to write run summary {}", run_json_path.display()))?;
 
-    Ok(result)
+        if let Some(err) = timed_out {
+            return Err(err.into());
+        }
+        Ok(result)
+    }
 }
 
 fn apply_otlp_hook(hook: &OtlpHook) {
@@ -479,6 +811,20 @@ fn prepare_run_dirs(root_override: Option<PathBuf>) -> Result<RunDirectories> {
     })
 }
 
+/// `SECRETS_BACKEND` when set, otherwise the OS keyring, falling back to environment
+/// variables when the keyring cannot be reached (e.g. headless Linux).
+fn local_secrets_manager() -> Result<DynSecretsManager> {
+    let requested = std::env::var("SECRETS_BACKEND").ok();
+    let chosen = keyring::local_backend(requested.as_deref(), keyring::probe)?;
+    if let Some(reason) = &chosen.fallback {
+        warn!(%reason, "OS keyring unavailable; reading secrets from environment variables");
+    }
+    chosen
+        .backend
+        .build_manager()
+        .context("failed to initialise secrets backend")
+}
+
 fn resolve_entry_flow(
     override_id: Option<String>,
     metadata: &PackMetadata,
@@ -527,7 +873,11 @@ fn resolve_profile(profile: &Profile, ctx: &TenantContext) -> ResolvedProfile {
     }
 }
 
-fn build_host_config(profile: &ResolvedProfile, dirs: &RunDirectories) -> HostConfig {
+fn build_host_config(
+    profile: &ResolvedProfile,
+    dirs: &RunDirectories,
+    deterministic: Option<DeterministicConfig>,
+) -> HostConfig {
     HostConfig {
         tenant: profile.tenant_id.clone(),
         bindings_path: dirs.resolved.join("dev.bindings.yaml"),
@@ -537,6 +887,8 @@ fn build_host_config(profile: &ResolvedProfile, dirs: &RunDirectories) -> HostCo
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -546,6 +898,16 @@ fn build_host_config(profile: &ResolvedProfile, dirs: &RunDirectories) -> HostCo
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig {
+            deterministic,
+            ..TenantWasiConfig::default()
+        },
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -574,8 +936,39 @@ pub struct RunResult {
     pub node_summaries: Vec<NodeSummary>,
     pub failures: BTreeMap<String, NodeFailure>,
     pub artifacts_dir: PathBuf,
+    /// Tenant state at the end of the run, when [`RunOptions::capture_state`] is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<String, Value>>,
+}
+
+/// Typed failures of a run; callers match on them through `anyhow::Error::downcast_ref`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunError {
+    /// The run exceeded [`RunOptions::timeout`]. `last_node` is the last node that
+    /// started, if any did.
+    TimedOut {
+        timeout: Duration,
+        last_node: Option<String>,
+    },
 }
 
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::TimedOut {
+                timeout,
+                last_node: Some(node),
+            } => write!(f, "run timed out after {timeout:?} in node `{node}`"),
+            RunError::TimedOut {
+                timeout,
+                last_node: None,
+            } => write!(f, "run timed out after {timeout:?} before any node started"),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum RunStatus {
     Success,
@@ -626,6 +1019,9 @@ struct RunRecorder {
     pack_meta: Mutex<PackMetadata>,
     transcript: Mutex<TranscriptWriter>,
     state: Mutex<RunRecorderState>,
+    on_event: Option<RunEventHook>,
+    /// Collects component output between node events; only set with `on_event`.
+    stdio: Option<StdioSink>,
 }
 
 impl RunRecorder {
@@ -635,6 +1031,7 @@ impl RunRecorder {
         flow_id: Option<String>,
         pack_meta: PackMetadata,
         hook: Option<TranscriptHook>,
+        on_event: Option<RunEventHook>,
     ) -> Result<Self> {
         let transcript_path = dirs.root.join("transcript.jsonl");
         let file = File::create(&transcript_path)
@@ -646,9 +1043,47 @@ impl RunRecorder {
             pack_meta: Mutex::new(pack_meta),
             transcript: Mutex::new(TranscriptWriter::new(BufWriter::new(file), hook)),
             state: Mutex::new(RunRecorderState::default()),
+            stdio: on_event.as_ref().map(|_| StdioSink::new()),
+            on_event,
         })
     }
 
+    fn emit(&self, event: RunEvent) {
+        if let Some(hook) = &self.on_event {
+            hook(event);
+        }
+    }
+
+    /// Forward the component output recorded since the last node finished, line by line.
+    fn emit_output(&self, node_id: &str) {
+        let Some(sink) = &self.stdio else {
+            return;
+        };
+        let output = sink.take();
+        for line in output.stdout.lines() {
+            self.emit(RunEvent::Stdout {
+                node_id: node_id.to_string(),
+                line: line.to_string(),
+            });
+        }
+        for line in output.stderr.lines() {
+            self.emit(RunEvent::Stderr {
+                node_id: node_id.to_string(),
+                line: line.to_string(),
+            });
+        }
+    }
+
+    fn emit_node_finished(&self, event: &NodeEvent<'_>, status: NodeStatus, duration_ms: u64) {
+        self.emit_output(event.node_id);
+        self.emit(RunEvent::NodeFinished {
+            node_id: event.node_id.to_string(),
+            component: event.node.component.clone(),
+            status,
+            duration_ms,
+        });
+    }
+
     fn finalise(
         &self,
         completion: RunCompletion,
@@ -744,6 +1179,7 @@ impl RunRecorder {
             node_summaries: summaries,
             failures,
             artifacts_dir: self.directories.root.clone(),
+            state: None,
         })
     }
 
@@ -759,6 +1195,10 @@ impl RunRecorder {
         self.flow_id.lock().clone()
     }
 
+    fn last_node(&self) -> Option<String> {
+        self.state.lock().order.last().cloned()
+    }
+
     fn record_verify_event(&self, status: &str, message: &str) -> Result<()> {
         let timestamp = OffsetDateTime::now_utc();
         let event = json!({
@@ -843,6 +1283,11 @@ impl RunRecorder {
         entry.start_instant = Some(Instant::now());
         entry.status = NodeStatus::Ok;
         entry.transcript_start = Some(start_offset);
+        drop(state);
+        self.emit(RunEvent::NodeStarted {
+            node_id: event.node_id.to_string(),
+            component: event.node.component.clone(),
+        });
         Ok(())
     }
 
@@ -866,11 +1311,21 @@ impl RunRecorder {
         self.transcript.lock().write(&event_json)?;
 
         let mut state = self.state.lock();
+        let mut duration_ms = 0;
         if let Some(entry) = state.nodes.get_mut(event.node_id)
             && let Some(started) = entry.start_instant.take()
         {
-            entry.duration_ms = Some(started.elapsed().as_millis() as u64);
+            duration_ms = started.elapsed().as_millis() as u64;
+            entry.duration_ms = Some(duration_ms);
         }
+        drop(state);
+        if event.node.is_emit() {
+            self.emit(RunEvent::Egress {
+                node_id: event.node_id.to_string(),
+                payload: output.clone(),
+            });
+        }
+        self.emit_node_finished(event, NodeStatus::Ok, duration_ms);
         Ok(())
     }
 
@@ -923,6 +1378,13 @@ impl RunRecorder {
             }
             entry.log_paths.push(log_path);
         }
+        let duration_ms = state
+            .nodes
+            .get(event.node_id)
+            .and_then(|entry| entry.duration_ms)
+            .unwrap_or(0);
+        drop(state);
+        self.emit_node_finished(event, NodeStatus::Error, duration_ms);
         Ok(())
     }
 }
@@ -945,6 +1407,15 @@ impl ExecutionObserver for RunRecorder {
             warn!(node = event.node_id, error = %err, "failed to record node error");
         }
     }
+
+    fn on_validation(&self, event: &NodeEvent<'_>, issues: &[ValidationIssue]) {
+        for issue in issues {
+            self.emit(RunEvent::Warning {
+                node_id: Some(event.node_id.to_string()),
+                message: format!("{} at {}: {}", issue.code, issue.path, issue.message),
+            });
+        }
+    }
 }
 
 impl MockEventSink for RunRecorder {
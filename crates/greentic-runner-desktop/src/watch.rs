@@ -0,0 +1,235 @@
+//! Re-running a pack while it is being edited.
+//!
+//! [`run_pack_watch`] runs the pack once, then again each time the pack directory (or the
+//! `.gtpack` file), `components_dir` or a `components_map` entry changes and the files
+//! have been quiet for [`WATCH_DEBOUNCE`]. Every outcome, failures included, is handed
+//! to the callback and the loop carries on. Components whose wasm changed have their
+//! cached compilations dropped before the next run, so rebuilt code runs even when the
+//! pack still declares the old digest. Run artifacts, under `.greentic/` or
+//! `artifacts_dir`, are never watched.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use greentic_runner_host::pack::CacheRefresh;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio::runtime::Runtime;
+use tracing::{debug, warn};
+
+use crate::{RunOptions, RunResult, run_pack_async};
+
+/// How long the watched files must be quiet before a change starts a run.
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+enum WatchMessage {
+    Changed(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// A running [`run_pack_watch`] loop. Dropping it stops the loop too; neither may
+/// happen from inside the callback, which runs on the loop's thread.
+pub struct PackWatch {
+    tx: Sender<WatchMessage>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PackWatch {
+    /// End the loop once the run in progress, if any, has finished.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.tx.send(WatchMessage::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PackWatch {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Run the pack with `opts` now and after every change to it, passing each outcome to
+/// `on_result` on a dedicated thread until the returned handle is stopped.
+pub fn run_pack_watch<P, F>(pack_path: P, opts: RunOptions, mut on_result: F) -> Result<PackWatch>
+where
+    P: AsRef<Path>,
+    F: FnMut(Result<RunResult>) + Send + 'static,
+{
+    let pack_path = pack_path.as_ref().to_path_buf();
+    let plan = WatchPlan::new(&pack_path, &opts)?;
+    let (tx, rx) = mpsc::channel();
+    let events = tx.clone();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = events.send(WatchMessage::Changed(event));
+    })
+    .context("failed to create pack watcher")?;
+    for (dir, mode) in plan.watched_dirs() {
+        watcher
+            .watch(&dir, mode)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+    }
+    let runtime = Runtime::new().context("failed to create tokio runtime")?;
+
+    let thread = std::thread::Builder::new()
+        .name("greentic-pack-watch".into())
+        .spawn(move || {
+            let _watcher = watcher;
+            let mut refresh = CacheRefresh::None;
+            loop {
+                on_result(runtime.block_on(run_pack_async(&pack_path, opts.clone(), refresh)));
+                let Some(changed) = next_change(&rx, &plan) else {
+                    return;
+                };
+                refresh = plan.cache_refresh(&changed);
+                debug!(files = changed.len(), refresh = ?refresh, "pack changed; running again");
+            }
+        })
+        .context("failed to spawn pack watcher")?;
+
+    Ok(PackWatch {
+        tx,
+        thread: Some(thread),
+    })
+}
+
+/// Wait for a relevant change and for the files to settle; `None` once stopped.
+fn next_change(rx: &Receiver<WatchMessage>, plan: &WatchPlan) -> Option<HashSet<PathBuf>> {
+    let mut changed = HashSet::new();
+    loop {
+        let message = if changed.is_empty() {
+            rx.recv().ok()?
+        } else {
+            match rx.recv_timeout(WATCH_DEBOUNCE) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => return Some(changed),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        };
+        match message {
+            WatchMessage::Stop => return None,
+            WatchMessage::Changed(Err(err)) => warn!(error = %err, "pack watcher error"),
+            WatchMessage::Changed(Ok(event)) => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                changed.extend(
+                    event
+                        .paths
+                        .into_iter()
+                        .filter(|path| plan.is_relevant(path)),
+                );
+            }
+        }
+    }
+}
+
+/// What a watch looks at, with paths resolved the way the watcher reports them.
+struct WatchPlan {
+    /// Directories watched with everything below them.
+    trees: Vec<PathBuf>,
+    /// Files watched through their parent directory, which survives editors
+    /// replacing the file.
+    files: HashSet<PathBuf>,
+    /// The `.gtpack` being run, if the pack is an archive.
+    archive: Option<PathBuf>,
+    /// `components_map` entries by wasm path.
+    mapped: HashMap<PathBuf, String>,
+    /// Run artifacts.
+    ignored: Vec<PathBuf>,
+}
+
+impl WatchPlan {
+    fn new(pack_path: &Path, opts: &RunOptions) -> Result<Self> {
+        let pack = resolve(pack_path);
+        anyhow::ensure!(pack.exists(), "pack {} does not exist", pack_path.display());
+        let mut plan = Self {
+            trees: Vec::new(),
+            files: HashSet::new(),
+            archive: None,
+            mapped: HashMap::new(),
+            ignored: opts.artifacts_dir.iter().map(|dir| resolve(dir)).collect(),
+        };
+        if pack.is_dir() {
+            plan.trees.push(pack);
+        } else {
+            plan.files.insert(pack.clone());
+            plan.archive = Some(pack);
+        }
+        if let Some(dir) = &opts.components_dir {
+            plan.trees.push(resolve(dir));
+        }
+        for (id, path) in &opts.components_map {
+            let path = resolve(path);
+            plan.files.insert(path.clone());
+            plan.mapped.insert(path, id.clone());
+        }
+        Ok(plan)
+    }
+
+    fn watched_dirs(&self) -> BTreeMap<PathBuf, RecursiveMode> {
+        let mut dirs = BTreeMap::new();
+        for file in &self.files {
+            if let Some(parent) = file.parent()
+                && !self.trees.iter().any(|tree| parent.starts_with(tree))
+            {
+                dirs.insert(parent.to_path_buf(), RecursiveMode::NonRecursive);
+            }
+        }
+        for tree in &self.trees {
+            dirs.insert(tree.clone(), RecursiveMode::Recursive);
+        }
+        dirs
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        if path
+            .components()
+            .any(|component| component.as_os_str() == ".greentic")
+            || self.ignored.iter().any(|dir| path.starts_with(dir))
+        {
+            return false;
+        }
+        self.files.contains(path) || self.trees.iter().any(|tree| path.starts_with(tree))
+    }
+
+    /// Every component when the archive changed, otherwise those whose wasm did: a
+    /// `components_map` entry or a `components/<id>.wasm` file.
+    fn cache_refresh(&self, changed: &HashSet<PathBuf>) -> CacheRefresh {
+        if let Some(archive) = &self.archive
+            && changed.contains(archive)
+        {
+            return CacheRefresh::All;
+        }
+        let ids: HashSet<String> = changed
+            .iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+            .filter_map(|path| match self.mapped.get(path) {
+                Some(id) => Some(id.clone()),
+                None => path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned()),
+            })
+            .collect();
+        if ids.is_empty() {
+            CacheRefresh::None
+        } else {
+            CacheRefresh::Components(ids)
+        }
+    }
+}
+
+/// `path` as the watcher reports it: canonical when it exists, absolute otherwise.
+fn resolve(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,4 +1,8 @@
-use greentic_runner_desktop::{RunOptions, run_pack_with_options, run_pack_with_options_async};
+use std::sync::{Arc, Mutex};
+
+use greentic_runner_desktop::{
+    RunEvent, RunOptions, run_pack_with_options, run_pack_with_options_async,
+};
 use serde_json::json;
 
 #[tokio::test]
@@ -6,9 +10,12 @@ async fn async_runner_matches_sync_runner() {
     let pack_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
         .join("../../tests/fixtures/packs/runner-components");
 
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
     let opts = RunOptions {
         entry_flow: Some("demo.flow".to_string()),
         input: json!({}),
+        on_event: Some(Arc::new(move |event| sink.lock().unwrap().push(event))),
         ..RunOptions::default()
     };
 
@@ -19,11 +26,40 @@ async fn async_runner_matches_sync_runner() {
     })
     .await
     .expect("sync join failed");
+    let sync_events = std::mem::take(&mut *events.lock().unwrap());
     let async_res = run_pack_with_options_async(&pack_path, opts)
         .await
         .expect("async run failed");
+    let async_events = std::mem::take(&mut *events.lock().unwrap());
 
     assert_eq!(sync.pack_id, async_res.pack_id);
     assert_eq!(sync.flow_id, async_res.flow_id);
     assert_eq!(sync.status, async_res.status);
+
+    for events in [&sync_events, &async_events] {
+        let started = events
+            .iter()
+            .position(|event| matches!(event, RunEvent::NodeStarted { .. }))
+            .expect("no node started event");
+        let RunEvent::NodeStarted { node_id, .. } = &events[started] else {
+            unreachable!()
+        };
+        let finished = events
+            .iter()
+            .position(|event| {
+                matches!(event, RunEvent::NodeFinished { node_id: id, .. } if id == node_id)
+            })
+            .expect("no node finished event");
+        assert!(started < finished, "{events:?}");
+    }
+    let nodes = |events: &[RunEvent]| {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                RunEvent::NodeFinished { node_id, .. } => Some(node_id.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(nodes(&sync_events), nodes(&async_events));
 }
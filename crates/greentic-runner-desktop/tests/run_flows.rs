@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use greentic_runner_desktop::{RunOptions, RunStatus, list_flows, run_flows_with_options_async};
+use greentic_types::{
+    ComponentCapabilities, ComponentManifest, ComponentProfiles, Flow, FlowComponentRef, FlowId,
+    FlowKind, FlowMetadata, InputMapping, Node, NodeId, OutputMapping, PackFlowEntry, PackKind,
+    PackManifest, ResourceHints, Routing, TelemetryHints, encode_pack_manifest,
+};
+use parking_lot::Mutex;
+use semver::Version;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use zip::write::FileOptions;
+
+fn build_component(krate: &str) -> Result<PathBuf> {
+    let workspace =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures/runner-components");
+    let manifest = workspace.join(krate).join("Cargo.toml");
+    let mut args = vec![
+        "build",
+        "--manifest-path",
+        manifest.to_str().context("non-utf8 manifest path")?,
+        "--target",
+        "wasm32-wasip2",
+        "--release",
+    ];
+    if std::env::var("CARGO_NET_OFFLINE").as_deref() == Ok("true") {
+        args.insert(1, "--offline");
+    }
+    let status = std::process::Command::new("cargo")
+        .current_dir(&workspace)
+        .args(args)
+        .status()
+        .with_context(|| format!("failed to build {krate} component"))?;
+    anyhow::ensure!(status.success(), "component build failed for {krate}");
+    Ok(workspace.join(format!("target/wasm32-wasip2/release/{krate}.wasm")))
+}
+
+/// A flow with a single `qa.process` node calling `operation` on the flow input.
+fn single_node_flow(flow_id: &str, operation: &str) -> Result<PackFlowEntry> {
+    let node_id = NodeId::from_str("qa")?;
+    let flow = Flow {
+        schema_version: "1.0".into(),
+        id: FlowId::from_str(flow_id)?,
+        kind: FlowKind::Messaging,
+        entrypoints: BTreeMap::from([("default".into(), Value::String(node_id.to_string()))]),
+        nodes: [(
+            node_id.clone(),
+            Node {
+                id: node_id,
+                component: FlowComponentRef {
+                    id: "qa.process".parse()?,
+                    pack_alias: None,
+                    operation: Some(operation.into()),
+                },
+                input: InputMapping {
+                    mapping: json!({ "text": "{{entry.text}}" }),
+                },
+                output: OutputMapping {
+                    mapping: Value::Null,
+                },
+                routing: Routing::End,
+                telemetry: TelemetryHints::default(),
+            },
+        )]
+        .into_iter()
+        .collect(),
+        metadata: FlowMetadata::default(),
+    };
+    Ok(PackFlowEntry {
+        id: flow.id.clone(),
+        kind: flow.kind,
+        flow,
+        tags: Vec::new(),
+        entrypoints: vec!["default".into()],
+    })
+}
+
+/// `echo` and `shout` process their input; `broken` asks for an op the component lacks.
+fn build_multi_flow_pack(pack_path: &Path) -> Result<()> {
+    let component = build_component("qa_process")?;
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "desktop.flows.test".parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: "qa.process".parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities::default(),
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: vec![
+            single_node_flow("echo", "process")?,
+            single_node_flow("broken", "missing")?,
+            single_node_flow("shout", "process")?,
+        ],
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(pack_path).context("create pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file("manifest.cbor", options)?;
+    zip.write_all(&encode_pack_manifest(&manifest)?)?;
+    zip.start_file("components/qa.process.wasm", options)?;
+    std::io::copy(&mut File::open(&component)?, &mut zip)?;
+    zip.finish().context("finalise pack archive")?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn runs_every_flow_and_reports_each_outcome() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("flows.gtpack");
+    build_multi_flow_pack(&pack_path)?;
+
+    let listed = list_flows(&pack_path)?;
+    let ids: Vec<&str> = listed.iter().map(|flow| flow.id.as_str()).collect();
+    assert_eq!(ids, ["echo", "broken", "shout"]);
+    assert!(listed.iter().all(|flow| flow.kind == "messaging"));
+
+    let outputs = Arc::new(Mutex::new(BTreeMap::new()));
+    let sink = Arc::clone(&outputs);
+    let opts = RunOptions {
+        input: json!({ "text": "hello" }),
+        flow_inputs: BTreeMap::from([("shout".into(), json!({ "text": "HELLO" }))]),
+        artifacts_dir: Some(temp.path().join("run")),
+        transcript: Some(Arc::new(move |event: &Value| {
+            if event["phase"] == "end"
+                && let Some(flow_id) = event["flow_id"].as_str()
+            {
+                sink.lock()
+                    .insert(flow_id.to_string(), event["outputs"].clone());
+            }
+        })),
+        ..RunOptions::default()
+    };
+    let outcomes = run_flows_with_options_async(&pack_path, &[], opts).await?;
+
+    let statuses: Vec<(&str, RunStatus)> = outcomes
+        .iter()
+        .map(|(flow_id, outcome)| {
+            let result = outcome.as_ref().expect("every flow produces a result");
+            assert_eq!(&result.flow_id, flow_id);
+            (flow_id.as_str(), result.status.clone())
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("echo", RunStatus::Success),
+            ("broken", RunStatus::Failure),
+            ("shout", RunStatus::Success),
+        ]
+    );
+    let outputs = outputs.lock();
+    assert_eq!(outputs.get("echo"), Some(&json!({ "text": "hello" })));
+    assert_eq!(outputs.get("shout"), Some(&json!({ "text": "HELLO" })));
+    for flow_id in ["echo", "broken", "shout"] {
+        assert!(
+            temp.path()
+                .join("run/flows")
+                .join(flow_id)
+                .join("run.json")
+                .is_file()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_flows_fail_without_stopping_the_rest() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("flows.gtpack");
+    build_multi_flow_pack(&pack_path)?;
+
+    let opts = RunOptions {
+        artifacts_dir: Some(temp.path().join("run")),
+        ..RunOptions::default()
+    };
+    let outcomes =
+        run_flows_with_options_async(&pack_path, &["nope".into(), "echo".into()], opts).await?;
+
+    assert_eq!(outcomes.len(), 2);
+    let err = outcomes[0].1.as_ref().expect_err("unknown flow");
+    assert!(err.to_string().contains("nope"), "{err:#}");
+    assert_eq!(outcomes[1].0, "echo");
+    assert_eq!(
+        outcomes[1].1.as_ref().expect("echo runs").status,
+        RunStatus::Success
+    );
+    Ok(())
+}
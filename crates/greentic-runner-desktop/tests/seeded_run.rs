@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use greentic_runner_desktop::{RunOptions, RunStatus, run_pack_with_options_async};
+use greentic_types::{
+    ComponentCapabilities, ComponentManifest, ComponentProfiles, Flow, FlowComponentRef, FlowId,
+    FlowKind, FlowMetadata, HostCapabilities, InputMapping, Node, NodeId, OutputMapping,
+    PackFlowEntry, PackKind, PackManifest, ResourceHints, Routing, StateCapabilities,
+    TelemetryHints, encode_pack_manifest,
+};
+use parking_lot::Mutex;
+use semver::Version;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use zip::write::FileOptions;
+
+const TOKEN: &str = "tok-12345";
+
+fn build_component(krate: &str) -> Result<PathBuf> {
+    let workspace =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures/runner-components");
+    let manifest = workspace.join(krate).join("Cargo.toml");
+    let mut args = vec![
+        "build",
+        "--manifest-path",
+        manifest.to_str().context("non-utf8 manifest path")?,
+        "--target",
+        "wasm32-wasip2",
+        "--release",
+    ];
+    if std::env::var("CARGO_NET_OFFLINE").as_deref() == Ok("true") {
+        args.insert(1, "--offline");
+    }
+    let status = std::process::Command::new("cargo")
+        .current_dir(&workspace)
+        .args(args)
+        .status()
+        .with_context(|| format!("failed to build {krate} component"))?;
+    anyhow::ensure!(status.success(), "component build failed for {krate}");
+    Ok(workspace.join(format!("target/wasm32-wasip2/release/{krate}.wasm")))
+}
+
+/// A pack whose only node reads an injected secret and state document.
+fn build_seeded_pack(pack_path: &Path) -> Result<()> {
+    let component = build_component("seeded_reader")?;
+    let node_id = NodeId::from_str("greet")?;
+    let flow = Flow {
+        schema_version: "1.0".into(),
+        id: FlowId::from_str("seeded.flow")?,
+        kind: FlowKind::Messaging,
+        entrypoints: BTreeMap::from([("default".into(), Value::String(node_id.to_string()))]),
+        nodes: [(
+            node_id.clone(),
+            Node {
+                id: node_id,
+                component: FlowComponentRef {
+                    id: "seeded.reader".parse()?,
+                    pack_alias: None,
+                    operation: Some("greet".into()),
+                },
+                input: InputMapping { mapping: json!({}) },
+                output: OutputMapping {
+                    mapping: Value::Null,
+                },
+                routing: Routing::End,
+                telemetry: TelemetryHints::default(),
+            },
+        )]
+        .into_iter()
+        .collect(),
+        metadata: FlowMetadata::default(),
+    };
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "desktop.seeded.test".parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: "seeded.reader".parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities {
+                host: HostCapabilities {
+                    state: Some(StateCapabilities {
+                        read: true,
+                        write: true,
+                    }),
+                    ..HostCapabilities::default()
+                },
+                ..ComponentCapabilities::default()
+            },
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: vec![PackFlowEntry {
+            id: flow.id.clone(),
+            kind: flow.kind,
+            flow,
+            tags: Vec::new(),
+            entrypoints: vec!["default".into()],
+        }],
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(pack_path).context("create pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file("manifest.cbor", options)?;
+    zip.write_all(&encode_pack_manifest(&manifest)?)?;
+    zip.start_file("components/seeded.reader.wasm", options)?;
+    std::io::copy(&mut File::open(&component)?, &mut zip)?;
+    zip.finish().context("finalise pack archive")?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn injected_secrets_and_state_reach_the_flow() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("seeded.gtpack");
+    build_seeded_pack(&pack_path)?;
+
+    let outputs = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&outputs);
+    let opts = RunOptions {
+        entry_flow: Some("seeded.flow".into()),
+        artifacts_dir: Some(temp.path().join("run")),
+        transcript: Some(Arc::new(move |event: &Value| {
+            if event["phase"] == "end" {
+                sink.lock().push(event["outputs"].clone());
+            }
+        })),
+        secrets: BTreeMap::from([("GREETING_TOKEN".into(), TOKEN.into())]),
+        initial_state: BTreeMap::from([("profile".into(), json!({ "name": "ada" }))]),
+        capture_state: true,
+        ..RunOptions::default()
+    };
+    assert!(!format!("{opts:?}").contains(TOKEN));
+
+    let result = run_pack_with_options_async(&pack_path, opts).await?;
+    assert_eq!(result.status, RunStatus::Success, "{:?}", result.error);
+
+    let greeting = json!({ "user": "ada", "key_len": TOKEN.len() });
+    assert_eq!(*outputs.lock(), vec![greeting.clone()]);
+    let state = result.state.expect("state captured");
+    assert_eq!(state.get("greeting"), Some(&greeting));
+    assert_eq!(state.get("profile"), Some(&json!({ "name": "ada" })));
+
+    for artifact in ["run.json", "transcript.jsonl"] {
+        let written = fs::read_to_string(temp.path().join("run").join(artifact))?;
+        assert!(!written.contains(TOKEN), "{artifact} leaks the secret");
+    }
+    Ok(())
+}
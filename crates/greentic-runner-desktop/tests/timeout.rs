@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use greentic_runner_desktop::{RunError, RunOptions, run_pack_with_options_async};
+use greentic_types::{
+    ComponentCapabilities, ComponentManifest, ComponentProfiles, Flow, FlowComponentRef, FlowId,
+    FlowKind, FlowMetadata, InputMapping, Node, NodeId, OutputMapping, PackFlowEntry, PackKind,
+    PackManifest, ResourceHints, Routing, TelemetryHints, encode_pack_manifest,
+};
+use semver::Version;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use zip::write::FileOptions;
+
+fn build_component(krate: &str) -> Result<PathBuf> {
+    let workspace =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures/runner-components");
+    let manifest = workspace.join(krate).join("Cargo.toml");
+    let mut args = vec![
+        "build",
+        "--manifest-path",
+        manifest.to_str().context("non-utf8 manifest path")?,
+        "--target",
+        "wasm32-wasip2",
+        "--release",
+    ];
+    if std::env::var("CARGO_NET_OFFLINE").as_deref() == Ok("true") {
+        args.insert(1, "--offline");
+    }
+    let status = std::process::Command::new("cargo")
+        .current_dir(&workspace)
+        .args(args)
+        .status()
+        .with_context(|| format!("failed to build {krate} component"))?;
+    anyhow::ensure!(status.success(), "component build failed for {krate}");
+    Ok(workspace.join(format!("target/wasm32-wasip2/release/{krate}.wasm")))
+}
+
+/// A pack whose only node counts until the host cancels it.
+fn build_endless_pack(pack_path: &Path) -> Result<()> {
+    let component = build_component("cancel_aware")?;
+    let node_id = NodeId::from_str("count")?;
+    let flow = Flow {
+        schema_version: "1.0".into(),
+        id: FlowId::from_str("endless.flow")?,
+        kind: FlowKind::Messaging,
+        entrypoints: BTreeMap::from([("default".into(), Value::String(node_id.to_string()))]),
+        nodes: [(
+            node_id.clone(),
+            Node {
+                id: node_id,
+                component: FlowComponentRef {
+                    id: "cancel.aware".parse()?,
+                    pack_alias: None,
+                    operation: Some("count".into()),
+                },
+                input: InputMapping { mapping: json!({}) },
+                output: OutputMapping {
+                    mapping: Value::Null,
+                },
+                routing: Routing::End,
+                telemetry: TelemetryHints::default(),
+            },
+        )]
+        .into_iter()
+        .collect(),
+        metadata: FlowMetadata::default(),
+    };
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "desktop.timeout.test".parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: "cancel.aware".parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities::default(),
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: vec![PackFlowEntry {
+            id: flow.id.clone(),
+            kind: flow.kind,
+            flow,
+            tags: Vec::new(),
+            entrypoints: vec!["default".into()],
+        }],
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(pack_path).context("create pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file("manifest.cbor", options)?;
+    zip.write_all(&encode_pack_manifest(&manifest)?)?;
+    zip.start_file("components/cancel.aware.wasm", options)?;
+    std::io::copy(&mut File::open(&component)?, &mut zip)?;
+    zip.finish().context("finalise pack archive")?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn endless_run_fails_fast_with_timed_out() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("endless.gtpack");
+    build_endless_pack(&pack_path)?;
+
+    let opts = RunOptions {
+        entry_flow: Some("endless.flow".into()),
+        artifacts_dir: Some(temp.path().join("run")),
+        timeout: Some(Duration::from_secs(1)),
+        ..RunOptions::default()
+    };
+    let started = Instant::now();
+    let err = run_pack_with_options_async(&pack_path, opts)
+        .await
+        .expect_err("endless run should time out");
+    let elapsed = started.elapsed();
+
+    match err.downcast_ref::<RunError>() {
+        Some(RunError::TimedOut { timeout, last_node }) => {
+            assert_eq!(*timeout, Duration::from_secs(1));
+            assert_eq!(last_node.as_deref(), Some("count"));
+        }
+        None => panic!("expected TimedOut, got {err:#}"),
+    }
+    assert!(elapsed < Duration::from_secs(5), "run took {elapsed:?}");
+    assert!(temp.path().join("run/run.json").is_file());
+    Ok(())
+}
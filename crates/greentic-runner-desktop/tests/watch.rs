@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use greentic_runner_desktop::{RunOptions, RunResult, RunStatus, run_pack_watch};
+use greentic_types::{
+    ComponentCapabilities, ComponentManifest, ComponentProfiles, Flow, FlowComponentRef, FlowId,
+    FlowKind, FlowMetadata, InputMapping, Node, NodeId, OutputMapping, PackFlowEntry, PackKind,
+    PackManifest, ResourceHints, Routing, TelemetryHints, encode_pack_manifest,
+};
+use semver::Version;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+fn build_component(krate: &str) -> Result<PathBuf> {
+    let workspace =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures/runner-components");
+    let manifest = workspace.join(krate).join("Cargo.toml");
+    let mut args = vec![
+        "build",
+        "--manifest-path",
+        manifest.to_str().context("non-utf8 manifest path")?,
+        "--target",
+        "wasm32-wasip2",
+        "--release",
+    ];
+    if std::env::var("CARGO_NET_OFFLINE").as_deref() == Ok("true") {
+        args.insert(1, "--offline");
+    }
+    let status = std::process::Command::new("cargo")
+        .current_dir(&workspace)
+        .args(args)
+        .status()
+        .with_context(|| format!("failed to build {krate} component"))?;
+    anyhow::ensure!(status.success(), "component build failed for {krate}");
+    Ok(workspace.join(format!("target/wasm32-wasip2/release/{krate}.wasm")))
+}
+
+fn process_node(id: &NodeId, routing: Routing) -> Result<Node> {
+    Ok(Node {
+        id: id.clone(),
+        component: FlowComponentRef {
+            id: "qa.process".parse()?,
+            pack_alias: None,
+            operation: Some("process".into()),
+        },
+        input: InputMapping {
+            mapping: json!({ "text": "hello" }),
+        },
+        output: OutputMapping {
+            mapping: Value::Null,
+        },
+        routing,
+        telemetry: TelemetryHints::default(),
+    })
+}
+
+/// Write the manifest of a materialized pack whose flow runs `qa`, then `again` when
+/// `with_second_node` is set.
+fn write_manifest(pack_dir: &Path, with_second_node: bool) -> Result<()> {
+    let first = NodeId::from_str("qa")?;
+    let second = NodeId::from_str("again")?;
+    let mut nodes = Vec::new();
+    if with_second_node {
+        nodes.push(process_node(
+            &first,
+            Routing::Next {
+                node_id: second.clone(),
+            },
+        )?);
+        nodes.push(process_node(&second, Routing::End)?);
+    } else {
+        nodes.push(process_node(&first, Routing::End)?);
+    }
+    let flow = Flow {
+        schema_version: "1.0".into(),
+        id: FlowId::from_str("watch.flow")?,
+        kind: FlowKind::Messaging,
+        entrypoints: BTreeMap::from([("default".into(), Value::String(first.to_string()))]),
+        nodes: nodes
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect(),
+        metadata: FlowMetadata::default(),
+    };
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "desktop.watch.test".parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: "qa.process".parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities::default(),
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: vec![PackFlowEntry {
+            id: flow.id.clone(),
+            kind: flow.kind,
+            flow,
+            tags: Vec::new(),
+            entrypoints: vec!["default".into()],
+        }],
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+    std::fs::write(
+        pack_dir.join("manifest.cbor"),
+        encode_pack_manifest(&manifest)?,
+    )?;
+    Ok(())
+}
+
+fn node_ids(result: &RunResult) -> Vec<&str> {
+    result
+        .node_summaries
+        .iter()
+        .map(|summary| summary.node_id.as_str())
+        .collect()
+}
+
+#[test]
+fn editing_the_flow_triggers_another_run() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_dir = temp.path().join("pack");
+    std::fs::create_dir_all(pack_dir.join("components"))?;
+    std::fs::copy(
+        build_component("qa_process")?,
+        pack_dir.join("components/qa.process.wasm"),
+    )?;
+    write_manifest(&pack_dir, false)?;
+
+    let opts = RunOptions {
+        entry_flow: Some("watch.flow".into()),
+        artifacts_dir: Some(temp.path().join("runs")),
+        ..RunOptions::default()
+    };
+    let (tx, rx) = mpsc::channel();
+    let watch = run_pack_watch(&pack_dir, opts, move |result| {
+        let _ = tx.send(result.map_err(|err| format!("{err:#}")));
+    })?;
+
+    let first = rx
+        .recv_timeout(Duration::from_secs(120))
+        .context("no result from the initial run")?
+        .map_err(anyhow::Error::msg)?;
+    assert_eq!(first.status, RunStatus::Success, "{:?}", first.error);
+    assert_eq!(node_ids(&first), ["qa"]);
+
+    write_manifest(&pack_dir, true)?;
+    let second = rx
+        .recv_timeout(Duration::from_secs(60))
+        .context("editing the flow did not trigger a run")?
+        .map_err(anyhow::Error::msg)?;
+    assert_eq!(second.status, RunStatus::Success, "{:?}", second.error);
+    assert_eq!(node_ids(&second), ["qa", "again"]);
+
+    watch.stop();
+    assert!(
+        rx.recv_timeout(Duration::from_millis(500)).is_err(),
+        "no runs after stop"
+    );
+    Ok(())
+}
@@ -0,0 +1,15 @@
+-- Runner state store (`PostgresStateStore`).
+CREATE TABLE IF NOT EXISTS runner_state (
+    tenant TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value JSONB NOT NULL,
+    version BIGINT NOT NULL DEFAULT 1,
+    expires_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (tenant, prefix, key)
+);
+
+CREATE INDEX IF NOT EXISTS runner_state_expires_at
+    ON runner_state (expires_at)
+    WHERE expires_at IS NOT NULL;
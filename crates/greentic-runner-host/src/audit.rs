@@ -0,0 +1,408 @@
+//! Audit trail for security-relevant host actions.
+//!
+//! Events go to an [`AuditSink`]; the default sink logs them through `tracing` under
+//! the `greentic.audit` target. Events never carry secret values, only key names; free
+//! text recorded during an invocation (e.g. an impersonation reason) is scrubbed by its
+//! redaction scope anyway.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+
+use crate::redact::{self, Redactor};
+
+/// Minimum gap between two `allowed` events for the same secret key.
+pub const DEFAULT_SECRET_AUDIT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    SecretAccess(SecretAccessEvent),
+    Impersonation(ImpersonationEvent),
+    RedeliveryExhausted(RedeliveryExhaustedEvent),
+}
+
+impl AuditEvent {
+    fn redact(&mut self, redactor: &Redactor) {
+        let (requester, actor) = match self {
+            AuditEvent::SecretAccess(event) => (
+                [&mut event.component_ref, &mut event.op, &mut event.flow_id],
+                event.actor_id.as_mut(),
+            ),
+            AuditEvent::Impersonation(event) => {
+                if let Some(reason) = &mut event.reason {
+                    redactor.scrub_string(reason);
+                }
+                (
+                    [&mut event.component_ref, &mut event.op, &mut event.flow_id],
+                    Some(&mut event.actor_id),
+                )
+            }
+            AuditEvent::RedeliveryExhausted(event) => {
+                redactor.scrub_string(&mut event.error);
+                return;
+            }
+        };
+        for text in requester.into_iter().flatten().chain(actor) {
+            redactor.scrub_string(text);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SecretAccessEvent {
+    pub tenant: String,
+    pub key: String,
+    pub decision: SecretDecision,
+    pub component_ref: Option<String>,
+    pub op: Option<String>,
+    pub flow_id: Option<String>,
+    /// Support actor impersonating the tenant, if any.
+    pub actor_id: Option<String>,
+    pub timestamp_unix_ms: u64,
+    /// `allowed` events for this key dropped by the rate limit since the last one.
+    pub suppressed: u64,
+}
+
+/// An operator request made on a tenant's behalf; recorded whether or not the policy
+/// allowed it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ImpersonationEvent {
+    pub tenant: String,
+    pub actor_id: String,
+    pub reason: Option<String>,
+    pub allowed: bool,
+    pub component_ref: Option<String>,
+    pub op: Option<String>,
+    pub flow_id: Option<String>,
+    pub timestamp_unix_ms: u64,
+}
+
+/// An ingress envelope whose flow still failed on the last delivery its tenant's
+/// `retry.redelivery` allows.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RedeliveryExhaustedEvent {
+    pub tenant: String,
+    pub flow_id: String,
+    pub activity_id: Option<String>,
+    pub attempts: u32,
+    /// Error of the last delivery.
+    pub error: String,
+    pub timestamp_unix_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretDecision {
+    Allowed,
+    Denied,
+}
+
+/// Who asked for a secret; fields the caller does not know stay `None`.
+#[derive(Clone, Debug, Default)]
+pub struct SecretRequester {
+    pub component_ref: Option<String>,
+    pub op: Option<String>,
+    pub flow_id: Option<String>,
+    pub actor_id: Option<String>,
+}
+
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent);
+}
+
+/// Logs events at info level under the `greentic.audit` target.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        match event {
+            AuditEvent::SecretAccess(access) => tracing::info!(
+                target: "greentic.audit",
+                tenant = %access.tenant,
+                key = %access.key,
+                decision = ?access.decision,
+                component_ref = access.component_ref.as_deref().unwrap_or(""),
+                op = access.op.as_deref().unwrap_or(""),
+                flow_id = access.flow_id.as_deref().unwrap_or(""),
+                timestamp_unix_ms = access.timestamp_unix_ms,
+                suppressed = access.suppressed,
+                actor_id = access.actor_id.as_deref().unwrap_or(""),
+                "secret access"
+            ),
+            AuditEvent::Impersonation(impersonation) => tracing::info!(
+                target: "greentic.audit",
+                tenant = %impersonation.tenant,
+                actor_id = %impersonation.actor_id,
+                reason = impersonation.reason.as_deref().unwrap_or(""),
+                allowed = impersonation.allowed,
+                component_ref = impersonation.component_ref.as_deref().unwrap_or(""),
+                op = impersonation.op.as_deref().unwrap_or(""),
+                flow_id = impersonation.flow_id.as_deref().unwrap_or(""),
+                timestamp_unix_ms = impersonation.timestamp_unix_ms,
+                "impersonation"
+            ),
+            AuditEvent::RedeliveryExhausted(exhausted) => tracing::info!(
+                target: "greentic.audit",
+                tenant = %exhausted.tenant,
+                flow_id = %exhausted.flow_id,
+                activity_id = exhausted.activity_id.as_deref().unwrap_or(""),
+                attempts = exhausted.attempts,
+                error = %exhausted.error,
+                timestamp_unix_ms = exhausted.timestamp_unix_ms,
+                "redelivery exhausted"
+            ),
+        }
+    }
+}
+
+/// Keeps events in memory; useful for embedders that forward them elsewhere and
+/// for tests.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl MemoryAuditSink {
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().clone()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        self.events.lock().push(event.clone());
+    }
+}
+
+/// Tenant audit log: the swappable sink plus the per-key rate limit for secret reads.
+pub struct AuditLog {
+    sink: RwLock<Arc<dyn AuditSink>>,
+    interval: Duration,
+    secret_keys: Mutex<HashMap<String, KeyWindow>>,
+}
+
+struct KeyWindow {
+    last_emitted: Instant,
+    suppressed: u64,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(Arc::new(TracingAuditSink), DEFAULT_SECRET_AUDIT_INTERVAL)
+    }
+}
+
+impl AuditLog {
+    pub fn new(sink: Arc<dyn AuditSink>, interval: Duration) -> Self {
+        Self {
+            sink: RwLock::new(sink),
+            interval,
+            secret_keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_sink(&self, sink: Arc<dyn AuditSink>) {
+        *self.sink.write() = sink;
+    }
+
+    /// Record a secret lookup. Denials are always emitted; `allowed` events are
+    /// limited to one per key per interval, with the dropped count carried on the
+    /// next emitted event.
+    pub fn secret_access(
+        &self,
+        tenant: &str,
+        key: &str,
+        decision: SecretDecision,
+        requester: &SecretRequester,
+    ) {
+        let suppressed = match decision {
+            SecretDecision::Denied => 0,
+            SecretDecision::Allowed => match self.admit(key) {
+                Some(suppressed) => suppressed,
+                None => return,
+            },
+        };
+        self.record(AuditEvent::SecretAccess(SecretAccessEvent {
+            tenant: tenant.to_string(),
+            key: key.to_string(),
+            decision,
+            component_ref: requester.component_ref.clone(),
+            op: requester.op.clone(),
+            flow_id: requester.flow_id.clone(),
+            actor_id: requester.actor_id.clone(),
+            timestamp_unix_ms: now_unix_ms(),
+            suppressed,
+        }));
+    }
+
+    /// Record an impersonated request; never rate limited. `requester.actor_id` is
+    /// ignored in favour of `actor_id`.
+    pub fn impersonation(
+        &self,
+        tenant: &str,
+        actor_id: &str,
+        reason: Option<&str>,
+        allowed: bool,
+        requester: &SecretRequester,
+    ) {
+        self.record(AuditEvent::Impersonation(ImpersonationEvent {
+            tenant: tenant.to_string(),
+            actor_id: actor_id.to_string(),
+            reason: reason.map(str::to_string),
+            allowed,
+            component_ref: requester.component_ref.clone(),
+            op: requester.op.clone(),
+            flow_id: requester.flow_id.clone(),
+            timestamp_unix_ms: now_unix_ms(),
+        }));
+    }
+
+    /// Record an ingress envelope given up on after `attempts` failed deliveries.
+    pub fn redelivery_exhausted(
+        &self,
+        tenant: &str,
+        flow_id: &str,
+        activity_id: Option<&str>,
+        attempts: u32,
+        error: &str,
+    ) {
+        self.record(AuditEvent::RedeliveryExhausted(RedeliveryExhaustedEvent {
+            tenant: tenant.to_string(),
+            flow_id: flow_id.to_string(),
+            activity_id: activity_id.map(str::to_string),
+            attempts,
+            error: error.to_string(),
+            timestamp_unix_ms: now_unix_ms(),
+        }));
+    }
+
+    fn record(&self, mut event: AuditEvent) {
+        if let Some(redactor) = redact::current() {
+            event.redact(&redactor);
+        }
+        let sink = Arc::clone(&self.sink.read());
+        sink.record(&event);
+    }
+
+    /// `Some(suppressed)` when an `allowed` event for `key` may be emitted now.
+    fn admit(&self, key: &str) -> Option<u64> {
+        let now = Instant::now();
+        let mut keys = self.secret_keys.lock();
+        match keys.get_mut(key) {
+            Some(window) if now.duration_since(window.last_emitted) < self.interval => {
+                window.suppressed += 1;
+                None
+            }
+            Some(window) => {
+                window.last_emitted = now;
+                Some(std::mem::take(&mut window.suppressed))
+            }
+            None => {
+                keys.insert(
+                    key.to_string(),
+                    KeyWindow {
+                        last_emitted: now,
+                        suppressed: 0,
+                    },
+                );
+                Some(0)
+            }
+        }
+    }
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decisions(sink: &MemoryAuditSink) -> Vec<(String, SecretDecision, u64)> {
+        sink.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                AuditEvent::SecretAccess(event) => {
+                    Some((event.key, event.decision, event.suppressed))
+                }
+                AuditEvent::Impersonation(_) | AuditEvent::RedeliveryExhausted(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn allowed_events_are_rate_limited_per_key_but_denials_are_not() {
+        let sink = Arc::new(MemoryAuditSink::default());
+        let log = AuditLog::new(sink.clone(), Duration::from_secs(3600));
+        let requester = SecretRequester::default();
+        for _ in 0..3 {
+            log.secret_access("acme", "API_TOKEN", SecretDecision::Allowed, &requester);
+            log.secret_access("acme", "DB_PASSWORD", SecretDecision::Denied, &requester);
+        }
+        log.secret_access("acme", "OTHER", SecretDecision::Allowed, &requester);
+
+        let events = decisions(&sink);
+        let allowed = events
+            .iter()
+            .filter(|(key, decision, _)| key == "API_TOKEN" && *decision == SecretDecision::Allowed)
+            .count();
+        let denied = events
+            .iter()
+            .filter(|(_, decision, _)| *decision == SecretDecision::Denied)
+            .count();
+        assert_eq!(allowed, 1);
+        assert_eq!(denied, 3);
+        assert!(events.iter().any(|(key, _, _)| key == "OTHER"));
+    }
+
+    #[test]
+    fn suppressed_count_carries_to_next_window() {
+        let sink = Arc::new(MemoryAuditSink::default());
+        let log = AuditLog::new(sink.clone(), Duration::from_millis(20));
+        let requester = SecretRequester::default();
+        for _ in 0..3 {
+            log.secret_access("acme", "API_TOKEN", SecretDecision::Allowed, &requester);
+        }
+        std::thread::sleep(Duration::from_millis(30));
+        log.secret_access("acme", "API_TOKEN", SecretDecision::Allowed, &requester);
+        assert_eq!(
+            decisions(&sink),
+            vec![
+                ("API_TOKEN".to_string(), SecretDecision::Allowed, 0),
+                ("API_TOKEN".to_string(), SecretDecision::Allowed, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn impersonation_events_are_never_rate_limited() {
+        let sink = Arc::new(MemoryAuditSink::default());
+        let log = AuditLog::new(sink.clone(), Duration::from_secs(3600));
+        let requester = SecretRequester {
+            op: Some("send".into()),
+            ..SecretRequester::default()
+        };
+        log.impersonation("acme", "support-7", Some("ticket 42"), true, &requester);
+        log.impersonation("acme", "support-7", None, false, &requester);
+
+        let events = sink.events();
+        assert_eq!(events.len(), 2);
+        let AuditEvent::Impersonation(first) = &events[0] else {
+            panic!("expected impersonation event, got {:?}", events[0]);
+        };
+        assert_eq!(first.actor_id, "support-7");
+        assert_eq!(first.reason.as_deref(), Some("ticket 42"));
+        assert!(first.allowed);
+        assert_eq!(first.op.as_deref(), Some("send"));
+        assert!(matches!(&events[1], AuditEvent::Impersonation(event) if !event.allowed));
+    }
+}
@@ -6,19 +6,64 @@ use crate::http::health::HealthState;
 #[cfg(feature = "telemetry")]
 use greentic_telemetry::init_telemetry_from_config;
 #[cfg(feature = "telemetry")]
-use tracing::info;
+use tracing::{info, warn};
 
 /// Initialise host-level subsystems (telemetry, health markers).
+///
+/// Telemetry fails softly: when the pipeline cannot start or the collector does not
+/// answer, the host logs why and keeps running without exporting spans.
 pub fn init(health: &HealthState, _telemetry: Option<&TelemetryCfg>) -> Result<()> {
     #[cfg(feature = "telemetry")]
     if let Some(cfg) = _telemetry {
         info!(
             service = cfg.config.service_name,
+            endpoint = ?cfg.export.endpoint,
             "initialising telemetry pipeline"
         );
-        init_telemetry_from_config(cfg.config.clone(), cfg.export.clone())?;
+        for (key, value) in &cfg.otel_env {
+            if std::env::var_os(key).is_none() {
+                // SAFETY: set during boot, before the exporter reads its environment.
+                unsafe {
+                    std::env::set_var(key, value);
+                }
+            }
+        }
+        match init_telemetry_from_config(cfg.config.clone(), cfg.export.clone()) {
+            Ok(_) => {
+                if let Some(endpoint) = cfg.export.endpoint.clone() {
+                    std::thread::spawn(move || probe_collector(&endpoint));
+                }
+            }
+            Err(err) => warn!(
+                error = %err,
+                "telemetry pipeline failed to start; continuing without span export"
+            ),
+        }
     }
 
     health.set_ready();
     Ok(())
 }
+
+/// Warn when nothing listens at the collector endpoint; the exporter keeps retrying.
+#[cfg(feature = "telemetry")]
+fn probe_collector(endpoint: &str) {
+    use std::net::{TcpStream, ToSocketAddrs};
+    use std::time::Duration;
+
+    let Some(addr) = url::Url::parse(endpoint).ok().and_then(|url| {
+        let host = url.host_str()?.to_string();
+        let port = url.port_or_known_default()?;
+        (host.as_str(), port).to_socket_addrs().ok()?.next()
+    }) else {
+        warn!(endpoint, "telemetry collector endpoint does not resolve");
+        return;
+    };
+    if let Err(err) = TcpStream::connect_timeout(&addr, Duration::from_secs(2)) {
+        warn!(
+            endpoint,
+            error = %err,
+            "telemetry collector unreachable; spans are dropped until it answers"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct CacheConfig {
@@ -6,6 +7,8 @@ pub struct CacheConfig {
     pub disk_enabled: bool,
     pub memory_enabled: bool,
     pub disk_max_bytes: Option<u64>,
+    /// Disk entries not read for this long are pruned whatever the byte budget.
+    pub disk_max_age: Option<Duration>,
     pub memory_max_bytes: u64,
     pub lfu_protect_hits: u64,
 }
@@ -28,6 +31,11 @@ impl Default for CacheConfig {
             disk_enabled: !cache_disabled,
             memory_enabled: !cache_disabled,
             disk_max_bytes: Some(5 * 1024 * 1024 * 1024),
+            disk_max_age: std::env::var("GREENTIC_CACHE_MAX_AGE_SECS")
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
             memory_max_bytes: 512 * 1024 * 1024,
             lfu_protect_hits: 3,
         }
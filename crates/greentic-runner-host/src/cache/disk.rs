@@ -1,19 +1,55 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
 
-use crate::cache::PruneReport;
 use crate::cache::engine_profile::EngineProfile;
 use crate::cache::keys::ArtifactKey;
 use crate::cache::metadata::ArtifactMetadata;
+use crate::cache::{CacheDoctorReport, PruneReport};
+
+/// How many recent misses [`DiskCache::is_known_missing`] remembers.
+pub const NEGATIVE_CACHE_CAPACITY: usize = 1024;
 
 #[derive(Clone, Debug)]
 pub struct DiskCache {
     root: PathBuf,
     profile: EngineProfile,
     disk_max_bytes: Option<u64>,
+    disk_max_age: Option<Duration>,
+    misses: Arc<Mutex<NegativeCache>>,
+}
+
+/// Keys recently found missing on disk, oldest first out once full.
+#[derive(Debug, Default)]
+struct NegativeCache {
+    keys: HashSet<ArtifactKey>,
+    order: VecDeque<ArtifactKey>,
+}
+
+impl NegativeCache {
+    fn insert(&mut self, key: &ArtifactKey) {
+        if !self.keys.insert(key.clone()) {
+            return;
+        }
+        self.order.push_back(key.clone());
+        while self.order.len() > NEGATIVE_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &ArtifactKey) {
+        if self.keys.remove(key)
+            && let Some(pos) = self.order.iter().position(|item| item == key)
+        {
+            self.order.remove(pos);
+        }
+    }
 }
 
 impl DiskCache {
@@ -22,14 +58,42 @@ impl DiskCache {
             root,
             profile,
             disk_max_bytes,
+            disk_max_age: None,
+            misses: Arc::new(Mutex::new(NegativeCache::default())),
         }
     }
 
+    /// Prune entries not read for `max_age`, on top of the byte limit.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.disk_max_age = max_age;
+        self
+    }
+
     pub fn root(&self) -> &Path {
         &self.root
     }
 
+    /// Whether the last [`Self::try_read`] of `key` missed and nothing was written for
+    /// it since. Only this process's writes are seen, so callers about to compile
+    /// should read the disk again rather than trust it.
+    pub fn is_known_missing(&self, key: &ArtifactKey) -> bool {
+        self.misses
+            .lock()
+            .is_ok_and(|misses| misses.keys.contains(key))
+    }
+
     pub fn try_read(&self, key: &ArtifactKey) -> Result<Option<Vec<u8>>> {
+        let read = self.read_entry(key)?;
+        if let Ok(mut misses) = self.misses.lock() {
+            match read {
+                Some(_) => misses.remove(key),
+                None => misses.insert(key),
+            }
+        }
+        Ok(read)
+    }
+
+    fn read_entry(&self, key: &ArtifactKey) -> Result<Option<Vec<u8>>> {
         let paths = self.paths_for(key)?;
         if !paths.meta_path.exists() {
             if paths.artifact_path.exists() {
@@ -108,6 +172,9 @@ impl DiskCache {
             .with_context(|| format!("failed to rename {}", paths.artifact_path.display()))?;
         fs::rename(&tmp_meta, &paths.meta_path)
             .with_context(|| format!("failed to rename {}", paths.meta_path.display()))?;
+        if let Ok(mut misses) = self.misses.lock() {
+            misses.remove(key);
+        }
         Ok(())
     }
 
@@ -131,19 +198,17 @@ impl DiskCache {
         Ok(total)
     }
 
+    /// Remove entries older than the max age, then the least recently read ones
+    /// until the rest fits the byte limit. Entries whose access time does not parse
+    /// count as the oldest.
     pub fn prune_to_limit(&self, dry_run: bool) -> Result<PruneReport> {
-        let Some(limit) = self.disk_max_bytes else {
-            return Ok(PruneReport {
-                removed_entries: 0,
-                removed_bytes: 0,
-            });
-        };
+        let mut report = PruneReport::default();
+        if self.disk_max_bytes.is_none() && self.disk_max_age.is_none() {
+            return Ok(report);
+        }
         let artifacts_dir = self.root.join("artifacts");
         if !artifacts_dir.exists() {
-            return Ok(PruneReport {
-                removed_entries: 0,
-                removed_bytes: 0,
-            });
+            return Ok(report);
         }
         let mut entries = Vec::new();
         let mut total_bytes = 0u64;
@@ -172,25 +237,29 @@ impl DiskCache {
         entries.sort_by_key(|(access, _, _, _, _)| {
             access.map(|ts| ts.timestamp()).unwrap_or(i64::MIN)
         });
-        let mut removed_entries = 0u64;
-        let mut removed_bytes = 0u64;
+        let cutoff = self
+            .disk_max_age
+            .and_then(|max_age| chrono::Duration::from_std(max_age).ok())
+            .map(|max_age| chrono::Utc::now() - max_age);
         let mut remaining = total_bytes;
-        for (_access, _meta, artifact_path, meta_path, size) in entries {
-            if remaining <= limit {
+        for (access, _meta, artifact_path, meta_path, size) in entries {
+            let expired = cutoff.is_some_and(|cutoff| access.is_none_or(|ts| ts < cutoff));
+            if expired {
+                report.removed_by_age = report.removed_by_age.saturating_add(1);
+            } else if self.disk_max_bytes.is_some_and(|limit| remaining > limit) {
+                report.removed_by_size = report.removed_by_size.saturating_add(1);
+            } else {
                 break;
             }
             if !dry_run {
                 let _ = fs::remove_file(&artifact_path);
                 let _ = fs::remove_file(&meta_path);
             }
-            removed_entries = removed_entries.saturating_add(1);
-            removed_bytes = removed_bytes.saturating_add(size);
+            report.removed_entries = report.removed_entries.saturating_add(1);
+            report.removed_bytes = report.removed_bytes.saturating_add(size);
             remaining = remaining.saturating_sub(size);
         }
-        Ok(PruneReport {
-            removed_entries,
-            removed_bytes,
-        })
+        Ok(report)
     }
 
     pub fn artifact_count(&self) -> Result<u64> {
@@ -211,6 +280,81 @@ impl DiskCache {
         Ok(count)
     }
 
+    /// Classify every entry under `artifacts/`; with `repair`, delete broken ones.
+    /// Only the entry counts of the report are filled in.
+    pub fn doctor(&self, repair: bool) -> Result<CacheDoctorReport> {
+        let mut report = CacheDoctorReport::default();
+        let artifacts_dir = self.root.join("artifacts");
+        if !artifacts_dir.exists() {
+            return Ok(report);
+        }
+        let mut entries: BTreeMap<String, (bool, bool)> = BTreeMap::new();
+        for entry in fs::read_dir(&artifacts_dir)
+            .with_context(|| format!("failed to read {}", artifacts_dir.display()))?
+        {
+            let path = entry?.path();
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let found = entries.entry(name.to_string()).or_default();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("cwasm") => found.0 = true,
+                Some("json") => found.1 = true,
+                _ => {}
+            }
+        }
+        for (name, (has_artifact, has_meta)) in entries {
+            if !has_artifact && !has_meta {
+                continue;
+            }
+            report.entries_checked += 1;
+            let paths = DiskPaths {
+                artifact_path: artifacts_dir.join(format!("{name}.cwasm")),
+                meta_path: artifacts_dir.join(format!("{name}.json")),
+                artifacts_dir: artifacts_dir.clone(),
+                tmp_dir: self.root.join("tmp"),
+            };
+            let counter = match (has_artifact, has_meta) {
+                (true, false) => &mut report.orphaned_artifacts,
+                (false, _) => &mut report.orphaned_metadata,
+                (true, true) => match self.check_entry(&name, &paths) {
+                    EntryCheck::Valid => {
+                        report.valid_entries += 1;
+                        continue;
+                    }
+                    EntryCheck::InvalidMetadata => &mut report.invalid_metadata,
+                    EntryCheck::ProfileMismatch => &mut report.profile_mismatches,
+                    EntryCheck::SizeMismatch => &mut report.size_mismatches,
+                },
+            };
+            *counter += 1;
+            if repair {
+                self.delete_entry(&paths)?;
+                report.repaired_entries += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    fn check_entry(&self, name: &str, paths: &DiskPaths) -> EntryCheck {
+        let meta = fs::read_to_string(&paths.meta_path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<ArtifactMetadata>(&raw).ok());
+        let Some(meta) = meta else {
+            return EntryCheck::InvalidMetadata;
+        };
+        if digest_to_filename(&meta.wasm_digest) != name {
+            return EntryCheck::InvalidMetadata;
+        }
+        if meta.validate_for_profile(&self.profile).is_err() {
+            return EntryCheck::ProfileMismatch;
+        }
+        match fs::metadata(&paths.artifact_path) {
+            Ok(artifact) if artifact.len() == meta.artifact_bytes => EntryCheck::Valid,
+            _ => EntryCheck::SizeMismatch,
+        }
+    }
+
     pub fn delete(&self, key: &ArtifactKey) -> Result<()> {
         let paths = self.paths_for(key)?;
         self.delete_entry(&paths)
@@ -250,6 +394,13 @@ impl DiskCache {
     }
 }
 
+enum EntryCheck {
+    Valid,
+    InvalidMetadata,
+    ProfileMismatch,
+    SizeMismatch,
+}
+
 struct DiskPaths {
     artifacts_dir: PathBuf,
     tmp_dir: PathBuf,
@@ -0,0 +1,94 @@
+use std::num::NonZeroUsize;
+
+use anyhow::Result;
+use lru::LruCache;
+use parking_lot::Mutex;
+use wasmtime::component::InstancePre;
+
+const DEFAULT_INSTANCE_PRE_CACHE_ENTRIES: usize = 128;
+
+/// Identifies a linked component: the same component linked against a different set of
+/// host imports needs its own `InstancePre`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InstancePreKey {
+    pub component_ref: String,
+    pub world: String,
+    /// Describes the linker the component was pre-instantiated against.
+    pub linker: &'static str,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InstancePreStats {
+    pub hits: u64,
+    /// Times a component had to be linked because no cached `InstancePre` matched.
+    pub links: u64,
+    pub entries: u64,
+}
+
+/// Entry-bounded LRU of pre-instantiated components, so an invoke only pays for
+/// store-specific instantiation. Owned by a single pack runtime; reloading the pack
+/// builds a new runtime and with it an empty cache.
+pub struct InstancePreCache<T: 'static> {
+    state: Mutex<InstancePreState<T>>,
+}
+
+struct InstancePreState<T: 'static> {
+    entries: LruCache<InstancePreKey, InstancePre<T>>,
+    hits: u64,
+    links: u64,
+}
+
+impl<T: 'static> InstancePreCache<T> {
+    pub fn new(max_entries: usize) -> Self {
+        let capacity = NonZeroUsize::new(max_entries.max(1)).expect("capacity is non-zero");
+        Self {
+            state: Mutex::new(InstancePreState {
+                entries: LruCache::new(capacity),
+                hits: 0,
+                links: 0,
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let max_entries = std::env::var("GREENTIC_INSTANCE_PRE_CACHE_ENTRIES")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_INSTANCE_PRE_CACHE_ENTRIES);
+        Self::new(max_entries)
+    }
+
+    /// Return the cached `InstancePre` for `key`, linking it with `link` on a miss.
+    /// Linking runs outside the lock; concurrent misses may both link.
+    pub fn get_or_link(
+        &self,
+        key: InstancePreKey,
+        link: impl FnOnce() -> Result<InstancePre<T>>,
+    ) -> Result<InstancePre<T>> {
+        {
+            let mut state = self.state.lock();
+            if let Some(pre) = state.entries.get(&key).cloned() {
+                state.hits = state.hits.saturating_add(1);
+                return Ok(pre);
+            }
+        }
+        let pre = link()?;
+        let mut state = self.state.lock();
+        state.links = state.links.saturating_add(1);
+        state.entries.put(key, pre.clone());
+        Ok(pre)
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().entries.clear();
+    }
+
+    pub fn stats(&self) -> InstancePreStats {
+        let state = self.state.lock();
+        InstancePreStats {
+            hits: state.hits,
+            links: state.links,
+            entries: state.entries.len() as u64,
+        }
+    }
+}
@@ -46,6 +46,8 @@ pub struct MemoryStats {
     pub evictions: u64,
     pub entries: u64,
     pub total_bytes: u64,
+    /// Part of `total_bytes` held by pinned entries, which are never evicted.
+    pub pinned_bytes: u64,
 }
 
 impl MemoryCache {
@@ -103,6 +105,20 @@ impl MemoryCache {
         self.evict_if_needed(&mut state);
     }
 
+    /// Drop `key`, pinned or not; true when it was cached.
+    pub fn remove(&self, key: &ArtifactKey) -> bool {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return false,
+        };
+        let Some(existing) = state.entries.remove(key) else {
+            return false;
+        };
+        state.total_bytes = state.total_bytes.saturating_sub(existing.bytes_estimate);
+        remove_lru(&mut state.lru, key);
+        true
+    }
+
     pub fn stats(&self) -> MemoryStats {
         let state = match self.state.lock() {
             Ok(state) => state,
@@ -114,6 +130,12 @@ impl MemoryCache {
             evictions: state.evictions,
             entries: state.entries.len() as u64,
             total_bytes: state.total_bytes,
+            pinned_bytes: state
+                .entries
+                .values()
+                .filter(|entry| entry.pinned)
+                .map(|entry| entry.bytes_estimate)
+                .sum(),
         }
     }
 
@@ -8,16 +8,20 @@ use wasmtime::component::Component;
 pub mod config;
 pub mod disk;
 pub mod engine_profile;
+pub mod instance_pre;
 pub mod keys;
 pub mod memory;
 pub mod metadata;
+pub mod secrets;
 pub mod singleflight;
 
 pub use config::CacheConfig;
 pub use engine_profile::{CpuPolicy, EngineProfile};
+pub use instance_pre::{InstancePreCache, InstancePreKey, InstancePreStats};
 pub use keys::ArtifactKey;
 pub use memory::MemoryStats;
 pub use metadata::ArtifactMetadata;
+pub use secrets::{SecretsCache, SecretsCacheConfig, SecretsCacheKey, SecretsCacheStats};
 
 use disk::DiskCache;
 use memory::MemoryCache;
@@ -38,6 +42,7 @@ struct CacheMetrics {
     memory_hits: AtomicU64,
     disk_hits: AtomicU64,
     disk_reads: AtomicU64,
+    negative_hits: AtomicU64,
     compiles: AtomicU64,
 }
 
@@ -46,6 +51,8 @@ pub struct CacheMetricsSnapshot {
     pub memory_hits: u64,
     pub disk_hits: u64,
     pub disk_reads: u64,
+    /// Disk reads skipped because the key recently missed.
+    pub negative_hits: u64,
     pub compiles: u64,
 }
 
@@ -61,12 +68,13 @@ impl CacheManager {
         let memory_max_bytes = config.memory_max_bytes;
         let lfu_protect_hits = config.lfu_protect_hits;
         let disk_max_bytes = config.disk_max_bytes;
+        let disk_max_age = config.disk_max_age;
         let memory = MemoryCache::new(memory_max_bytes, lfu_protect_hits);
         Self {
             config,
             profile: profile.clone(),
             memory,
-            disk: DiskCache::new(disk_root, profile, disk_max_bytes),
+            disk: DiskCache::new(disk_root, profile, disk_max_bytes).with_max_age(disk_max_age),
             singleflight: Singleflight::new(),
             metrics: Arc::new(CacheMetrics::default()),
         }
@@ -81,6 +89,7 @@ impl CacheManager {
             memory_hits: self.metrics.memory_hits.load(Ordering::Relaxed),
             disk_hits: self.metrics.disk_hits.load(Ordering::Relaxed),
             disk_reads: self.metrics.disk_reads.load(Ordering::Relaxed),
+            negative_hits: self.metrics.negative_hits.load(Ordering::Relaxed),
             compiles: self.metrics.compiles.load(Ordering::Relaxed),
         }
     }
@@ -99,6 +108,16 @@ impl CacheManager {
         })
     }
 
+    /// Drop the compiled artifact of `key` from memory and disk, so the next
+    /// [`Self::get_component`] compiles it again.
+    pub fn invalidate(&self, key: &ArtifactKey) -> Result<()> {
+        self.memory.remove(key);
+        if self.config.disk_enabled {
+            self.disk.delete(key)?;
+        }
+        Ok(())
+    }
+
     #[allow(unsafe_code)]
     pub async fn get_component(
         &self,
@@ -112,7 +131,9 @@ impl CacheManager {
             self.metrics.memory_hits.fetch_add(1, Ordering::Relaxed);
             return Ok(component);
         }
-        if self.config.disk_enabled {
+        if self.config.disk_enabled && self.disk.is_known_missing(key) {
+            self.metrics.negative_hits.fetch_add(1, Ordering::Relaxed);
+        } else if self.config.disk_enabled {
             self.metrics.disk_reads.fetch_add(1, Ordering::Relaxed);
             if let Some(serialized) = self.disk.try_read(key)? {
                 // Safety: serialized components are only loaded within the same engine profile.
@@ -138,6 +159,8 @@ impl CacheManager {
         }
 
         let _guard = self.singleflight.acquire(key.clone()).await;
+        // Read the disk even after a negative hit: another caller may have written the
+        // artifact while we waited for the lock.
         if self.config.memory_enabled
             && let Some(component) = self.memory.get(key)
         {
@@ -202,12 +225,17 @@ impl CacheManager {
         })
     }
 
-    pub fn doctor(&self) -> CacheDoctorReport {
-        CacheDoctorReport {
-            disk_enabled: self.config.disk_enabled,
-            memory_enabled: self.config.memory_enabled,
-            entries_checked: 0,
-        }
+    /// Check every disk entry against the engine profile; with `repair`, delete the
+    /// files of entries that would never be read.
+    pub fn doctor(&self, repair: bool) -> Result<CacheDoctorReport> {
+        let mut report = if self.config.disk_enabled {
+            self.disk.doctor(repair)?
+        } else {
+            CacheDoctorReport::default()
+        };
+        report.disk_enabled = self.config.disk_enabled;
+        report.memory_enabled = self.config.memory_enabled;
+        Ok(report)
     }
 
     pub async fn prune_disk(&self, dry_run: bool) -> Result<PruneReport> {
@@ -232,17 +260,45 @@ pub struct WarmupReport {
     pub skipped: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct CacheDoctorReport {
     pub disk_enabled: bool,
     pub memory_enabled: bool,
+    /// Distinct artifact names found on disk, broken or not.
     pub entries_checked: u64,
+    pub valid_entries: u64,
+    /// `.cwasm` files without their `.json` metadata.
+    pub orphaned_artifacts: u64,
+    /// `.json` metadata without its `.cwasm` file.
+    pub orphaned_metadata: u64,
+    /// Metadata that does not parse or names another digest.
+    pub invalid_metadata: u64,
+    /// Entries compiled under another engine profile.
+    pub profile_mismatches: u64,
+    /// Entries whose artifact length differs from the metadata's `artifact_bytes`.
+    pub size_mismatches: u64,
+    /// Broken entries whose files were deleted.
+    pub repaired_entries: u64,
 }
 
-#[derive(Clone, Debug)]
+impl CacheDoctorReport {
+    pub fn broken_entries(&self) -> u64 {
+        self.orphaned_artifacts
+            + self.orphaned_metadata
+            + self.invalid_metadata
+            + self.profile_mismatches
+            + self.size_mismatches
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct PruneReport {
     pub removed_entries: u64,
     pub removed_bytes: u64,
+    /// Entries not read within `disk_max_age`.
+    pub removed_by_age: u64,
+    /// Entries evicted to get under `disk_max_bytes`.
+    pub removed_by_size: u64,
 }
 
 #[cfg(test)]
@@ -0,0 +1,357 @@
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+use greentic_secrets_lib::SecretError;
+use lru::LruCache;
+use parking_lot::Mutex;
+use zeroize::Zeroizing;
+
+use crate::secrets::{RotationListener, SecretRotation};
+
+const DEFAULT_SECRETS_CACHE_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_SECRETS_CACHE_NEGATIVE_TTL: Duration = Duration::from_secs(5);
+const DEFAULT_SECRETS_CACHE_ENTRIES: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecretsCacheConfig {
+    /// How long a resolved value is served without asking the backend; zero disables
+    /// caching of values.
+    pub ttl: Duration,
+    /// How long a `NotFound` answer is remembered; zero disables negative caching.
+    pub negative_ttl: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for SecretsCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_SECRETS_CACHE_TTL,
+            negative_ttl: DEFAULT_SECRETS_CACHE_NEGATIVE_TTL,
+            max_entries: DEFAULT_SECRETS_CACHE_ENTRIES,
+        }
+    }
+}
+
+impl SecretsCacheConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            ttl: env_secs("GREENTIC_SECRETS_CACHE_TTL_SECS").unwrap_or(defaults.ttl),
+            negative_ttl: env_secs("GREENTIC_SECRETS_CACHE_NEGATIVE_TTL_SECS")
+                .unwrap_or(defaults.negative_ttl),
+            max_entries: std::env::var("GREENTIC_SECRETS_CACHE_ENTRIES")
+                .ok()
+                .and_then(|raw| raw.trim().parse::<usize>().ok())
+                .unwrap_or(defaults.max_entries),
+        }
+    }
+}
+
+fn env_secs(name: &str) -> Option<Duration> {
+    std::env::var(name)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SecretsCacheKey {
+    pub tenant: String,
+    pub key: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SecretsCacheStats {
+    pub hits: u64,
+    /// Hits on a remembered `NotFound`.
+    pub negative_hits: u64,
+    /// Lookups that had to go to the secrets backend.
+    pub reads: u64,
+    pub entries: u64,
+}
+
+/// Entry-bounded LRU of resolved runner secrets. Values live in zeroizing buffers, so
+/// evicted, expired and invalidated entries are wiped when dropped. Only `NotFound`
+/// is cached negatively; other backend errors are always retried.
+pub struct SecretsCache {
+    config: SecretsCacheConfig,
+    state: Mutex<SecretsCacheState>,
+}
+
+struct SecretsCacheState {
+    entries: LruCache<SecretsCacheKey, CachedSecret>,
+    hits: u64,
+    negative_hits: u64,
+    reads: u64,
+}
+
+struct CachedSecret {
+    /// `None` records a key the backend reported as missing.
+    value: Option<Zeroizing<Vec<u8>>>,
+    expires_at: Instant,
+}
+
+impl Default for SecretsCache {
+    fn default() -> Self {
+        Self::new(SecretsCacheConfig::default())
+    }
+}
+
+impl SecretsCache {
+    pub fn new(config: SecretsCacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.max_entries.max(1)).expect("capacity is non-zero");
+        Self {
+            config,
+            state: Mutex::new(SecretsCacheState {
+                entries: LruCache::new(capacity),
+                hits: 0,
+                negative_hits: 0,
+                reads: 0,
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(SecretsCacheConfig::from_env())
+    }
+
+    pub fn config(&self) -> SecretsCacheConfig {
+        self.config
+    }
+
+    /// Return the cached value for `tenant`/`key`, calling `read` when there is no live
+    /// entry. The read runs outside the lock; concurrent misses may both hit the backend.
+    pub async fn get_or_read<F, Fut>(
+        &self,
+        tenant: &str,
+        key: &str,
+        read: F,
+    ) -> Result<Vec<u8>, SecretError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, SecretError>>,
+    {
+        let cache_key = SecretsCacheKey {
+            tenant: tenant.to_string(),
+            key: key.to_string(),
+        };
+        {
+            let mut state = self.state.lock();
+            let now = Instant::now();
+            let cached = match state.entries.get(&cache_key) {
+                Some(entry) if entry.expires_at > now => {
+                    Some(entry.value.as_ref().map(|value| value.to_vec()))
+                }
+                Some(_) => {
+                    state.entries.pop(&cache_key);
+                    None
+                }
+                None => None,
+            };
+            match cached {
+                Some(Some(value)) => {
+                    state.hits = state.hits.saturating_add(1);
+                    return Ok(value);
+                }
+                Some(None) => {
+                    state.negative_hits = state.negative_hits.saturating_add(1);
+                    return Err(SecretError::NotFound(key.to_string()));
+                }
+                None => {}
+            }
+        }
+
+        let result = read().await;
+        let mut state = self.state.lock();
+        state.reads = state.reads.saturating_add(1);
+        let entry = match &result {
+            Ok(value) if !self.config.ttl.is_zero() => Some(CachedSecret {
+                value: Some(Zeroizing::new(value.clone())),
+                expires_at: Instant::now() + self.config.ttl,
+            }),
+            Err(SecretError::NotFound(_)) if !self.config.negative_ttl.is_zero() => {
+                Some(CachedSecret {
+                    value: None,
+                    expires_at: Instant::now() + self.config.negative_ttl,
+                })
+            }
+            _ => None,
+        };
+        if let Some(entry) = entry {
+            state.entries.put(cache_key, entry);
+        }
+        result
+    }
+
+    pub fn invalidate(&self, tenant: &str, key: &str) -> bool {
+        let cache_key = SecretsCacheKey {
+            tenant: tenant.to_string(),
+            key: key.to_string(),
+        };
+        self.state.lock().entries.pop(&cache_key).is_some()
+    }
+
+    /// Drop every entry for `tenant`, returning how many were removed.
+    pub fn invalidate_tenant(&self, tenant: &str) -> usize {
+        let mut state = self.state.lock();
+        let stale = state
+            .entries
+            .iter()
+            .filter(|(cache_key, _)| cache_key.tenant == tenant)
+            .map(|(cache_key, _)| cache_key.clone())
+            .collect::<Vec<_>>();
+        for cache_key in &stale {
+            state.entries.pop(cache_key);
+        }
+        stale.len()
+    }
+
+    /// Drop the entries of `tenant` naming the rotated secret `key`; for the `shared`
+    /// namespace, every tenant's entries for it.
+    pub fn invalidate_rotated(&self, tenant: &str, key: &str) -> usize {
+        let rotation = SecretRotation::new(tenant, key);
+        let mut state = self.state.lock();
+        let stale = state
+            .entries
+            .iter()
+            .filter(|(cache_key, _)| rotation.applies_to(&cache_key.tenant, &cache_key.key))
+            .map(|(cache_key, _)| cache_key.clone())
+            .collect::<Vec<_>>();
+        for cache_key in &stale {
+            state.entries.pop(cache_key);
+        }
+        stale.len()
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().entries.clear();
+    }
+
+    pub fn stats(&self) -> SecretsCacheStats {
+        let state = self.state.lock();
+        SecretsCacheStats {
+            hits: state.hits,
+            negative_hits: state.negative_hits,
+            reads: state.reads,
+            entries: state.entries.len() as u64,
+        }
+    }
+}
+
+impl RotationListener for SecretsCache {
+    fn secret_rotated(&self, rotation: &SecretRotation) {
+        self.invalidate_rotated(&rotation.tenant, &rotation.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn cache(ttl: Duration, negative_ttl: Duration) -> SecretsCache {
+        SecretsCache::new(SecretsCacheConfig {
+            ttl,
+            negative_ttl,
+            max_entries: 8,
+        })
+    }
+
+    async fn lookup(
+        cache: &SecretsCache,
+        calls: &AtomicUsize,
+        key: &str,
+        value: Option<&[u8]>,
+    ) -> Result<Vec<u8>, SecretError> {
+        cache
+            .get_or_read("acme", key, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                value
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| SecretError::NotFound(key.to_string()))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn values_are_served_from_cache_until_expiry() {
+        let cache = cache(Duration::from_millis(50), Duration::from_millis(50));
+        let calls = AtomicUsize::new(0);
+        for _ in 0..3 {
+            let value = lookup(&cache, &calls, "API_TOKEN", Some(b"v1")).await;
+            assert_eq!(value.expect("value"), b"v1".to_vec());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        let value = lookup(&cache, &calls, "API_TOKEN", Some(b"v2")).await;
+        assert_eq!(value.expect("value"), b"v2".to_vec());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.reads), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn missing_keys_are_cached_with_negative_ttl() {
+        let cache = cache(Duration::from_secs(3600), Duration::from_millis(30));
+        let calls = AtomicUsize::new(0);
+        for _ in 0..2 {
+            let err = lookup(&cache, &calls, "MISSING", None).await;
+            assert!(matches!(err, Err(SecretError::NotFound(_))));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().negative_hits, 1);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let value = lookup(&cache, &calls, "MISSING", Some(b"created")).await;
+        assert_eq!(value.expect("value"), b"created".to_vec());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidation_forces_a_backend_read() {
+        let cache = cache(Duration::from_secs(3600), Duration::from_secs(3600));
+        let calls = AtomicUsize::new(0);
+        lookup(&cache, &calls, "A", Some(b"a")).await.expect("a");
+        lookup(&cache, &calls, "B", Some(b"b")).await.expect("b");
+        assert!(cache.invalidate("acme", "A"));
+        assert!(!cache.invalidate("acme", "A"));
+        lookup(&cache, &calls, "A", Some(b"a")).await.expect("a");
+        lookup(&cache, &calls, "B", Some(b"b")).await.expect("b");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        assert_eq!(cache.invalidate_tenant("acme"), 2);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn rotations_drop_entries_by_path_name() {
+        let cache = cache(Duration::from_secs(3600), Duration::from_secs(3600));
+        let calls = AtomicUsize::new(0);
+        lookup(&cache, &calls, "api/token", Some(b"a"))
+            .await
+            .expect("a");
+        lookup(&cache, &calls, "OTHER", Some(b"b"))
+            .await
+            .expect("b");
+        assert_eq!(cache.invalidate_rotated("globex", "api.token"), 0);
+        cache.secret_rotated(&SecretRotation::new("acme", "api.token"));
+        assert_eq!(cache.stats().entries, 1);
+        lookup(&cache, &calls, "api/token", Some(b"a2"))
+            .await
+            .expect("a2");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_disables_caching() {
+        let cache = cache(Duration::ZERO, Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+        lookup(&cache, &calls, "A", Some(b"a")).await.expect("a");
+        lookup(&cache, &calls, "A", Some(b"a")).await.expect("a");
+        let _ = lookup(&cache, &calls, "B", None).await;
+        let _ = lookup(&cache, &calls, "B", None).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
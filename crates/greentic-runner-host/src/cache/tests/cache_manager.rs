@@ -183,3 +183,103 @@ async fn warmup_persists_and_hits_disk() {
     assert!(after.disk_hits > before.disk_hits);
     assert_eq!(after.compiles, before.compiles);
 }
+
+#[tokio::test]
+async fn invalidate_forces_a_recompile() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = Arc::new(wasmtime::Engine::default());
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let config = CacheConfig {
+        root: temp.path().to_path_buf(),
+        disk_enabled: true,
+        memory_enabled: true,
+        memory_max_bytes: 1024 * 1024,
+        ..CacheConfig::default()
+    };
+    let cache = CacheManager::new(config, profile);
+    let key = build_key(&engine);
+    let bytes = fixture_bytes();
+
+    for _ in 0..2 {
+        let _ = cache
+            .get_component(engine.as_ref(), &key, {
+                let bytes = bytes.clone();
+                move || Ok(bytes)
+            })
+            .await
+            .expect("component");
+    }
+    assert_eq!(cache.metrics().compiles, 1);
+
+    cache.invalidate(&key).expect("invalidate");
+    let _ = cache
+        .get_component(engine.as_ref(), &key, {
+            let bytes = bytes.clone();
+            move || Ok(bytes)
+        })
+        .await
+        .expect("component");
+    assert_eq!(cache.metrics().compiles, 2);
+}
+
+#[tokio::test]
+async fn repeated_misses_read_disk_once_per_lookup() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = Arc::new(wasmtime::Engine::default());
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let config = CacheConfig {
+        root: temp.path().to_path_buf(),
+        disk_enabled: true,
+        memory_enabled: false,
+        ..CacheConfig::default()
+    };
+    let cache = CacheManager::new(config, profile);
+    let key = build_key(&engine);
+
+    // A lookup whose wasm cannot be fetched leaves the key missing on disk.
+    for _ in 0..3 {
+        let missing = cache
+            .get_component(engine.as_ref(), &key, || anyhow::bail!("wasm unavailable"))
+            .await;
+        assert!(missing.is_err());
+    }
+    let metrics = cache.metrics();
+    // The first lookup reads before and under the singleflight lock; later ones only under it.
+    assert_eq!(metrics.disk_reads, 4);
+    assert_eq!(metrics.negative_hits, 2);
+    assert_eq!(metrics.compiles, 0);
+
+    let bytes = fixture_bytes();
+    for _ in 0..2 {
+        let _ = cache
+            .get_component(engine.as_ref(), &key, {
+                let bytes = bytes.clone();
+                move || Ok(bytes)
+            })
+            .await
+            .expect("component");
+    }
+    let metrics = cache.metrics();
+    assert_eq!(metrics.compiles, 1);
+    assert_eq!(metrics.negative_hits, 3);
+    assert_eq!(metrics.disk_hits, 1);
+}
+
+#[test]
+fn doctor_skips_disabled_disk() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let config = CacheConfig {
+        root: temp.path().to_path_buf(),
+        disk_enabled: false,
+        memory_enabled: true,
+        ..CacheConfig::default()
+    };
+    let report = CacheManager::new(config, profile)
+        .doctor(true)
+        .expect("doctor");
+    assert!(!report.disk_enabled);
+    assert!(report.memory_enabled);
+    assert_eq!(report.entries_checked, 0);
+}
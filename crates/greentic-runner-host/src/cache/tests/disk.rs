@@ -5,7 +5,7 @@ use chrono::{Duration, Utc};
 use serde_json;
 use tempfile::TempDir;
 
-use crate::cache::disk::DiskCache;
+use crate::cache::disk::{DiskCache, NEGATIVE_CACHE_CAPACITY};
 use crate::cache::engine_profile::{CpuPolicy, EngineProfile};
 use crate::cache::keys::ArtifactKey;
 use crate::cache::metadata::ArtifactMetadata;
@@ -95,6 +95,120 @@ fn corrupt_artifact_is_deleted_and_misses() {
     assert!(!meta_path.exists());
 }
 
+#[test]
+fn miss_is_remembered_until_written() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let root = build_cache_root(&temp, &profile);
+    let cache = DiskCache::new(root, profile.clone(), None);
+
+    let digest = "sha256:abcd".to_string();
+    let key = ArtifactKey::new(profile.id().to_string(), digest.clone());
+    assert!(!cache.is_known_missing(&key));
+    assert!(cache.try_read(&key).expect("read").is_none());
+    assert!(cache.is_known_missing(&key));
+
+    let bytes = b"hello".to_vec();
+    let meta = ArtifactMetadata::new(&profile, digest, bytes.len() as u64);
+    cache.write_atomic(&key, &bytes, &meta).expect("write");
+    assert!(!cache.is_known_missing(&key));
+    assert_eq!(cache.try_read(&key).expect("read"), Some(bytes));
+}
+
+#[test]
+fn negative_cache_is_bounded() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let cache = DiskCache::new(build_cache_root(&temp, &profile), profile.clone(), None);
+
+    let key = |n: usize| ArtifactKey::new(profile.id().to_string(), format!("sha256:{n}"));
+    for n in 0..=NEGATIVE_CACHE_CAPACITY {
+        assert!(cache.try_read(&key(n)).expect("read").is_none());
+    }
+    assert!(!cache.is_known_missing(&key(0)));
+    assert!(cache.is_known_missing(&key(1)));
+    assert!(cache.is_known_missing(&key(NEGATIVE_CACHE_CAPACITY)));
+}
+
+#[test]
+fn doctor_categorises_broken_entries() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let root = build_cache_root(&temp, &profile);
+    let cache = DiskCache::new(root.clone(), profile.clone(), None);
+
+    let write = |digest: &str| {
+        let key = ArtifactKey::new(profile.id().to_string(), digest.to_string());
+        let bytes = b"hello".to_vec();
+        let meta = ArtifactMetadata::new(&profile, digest.to_string(), bytes.len() as u64);
+        cache.write_atomic(&key, &bytes, &meta).expect("write");
+        artifact_paths(&root, digest)
+    };
+    write("sha256:valid");
+    let (truncated, _) = write("sha256:truncated");
+    fs::write(&truncated, b"hel").expect("truncate artifact");
+    let (_, foreign_meta) = write("sha256:foreign");
+    let mut foreign: ArtifactMetadata =
+        serde_json::from_str(&fs::read_to_string(&foreign_meta).expect("read meta"))
+            .expect("parse meta");
+    foreign.config_fingerprint = "sha256:other".to_string();
+    fs::write(
+        &foreign_meta,
+        serde_json::to_string_pretty(&foreign).expect("meta json"),
+    )
+    .expect("rewrite meta");
+    let (_, metaless) = write("sha256:metaless");
+    fs::remove_file(&metaless).expect("remove meta");
+    let (artifactless, _) = write("sha256:artifactless");
+    fs::remove_file(&artifactless).expect("remove artifact");
+    let (_, garbled) = write("sha256:garbled");
+    fs::write(&garbled, "{").expect("garble meta");
+
+    let report = cache.doctor(false).expect("doctor");
+    assert_eq!(report.entries_checked, 6);
+    assert_eq!(report.valid_entries, 1);
+    assert_eq!(report.size_mismatches, 1);
+    assert_eq!(report.profile_mismatches, 1);
+    assert_eq!(report.orphaned_artifacts, 1);
+    assert_eq!(report.orphaned_metadata, 1);
+    assert_eq!(report.invalid_metadata, 1);
+    assert_eq!(report.broken_entries(), 5);
+    assert_eq!(report.repaired_entries, 0);
+    assert!(truncated.exists());
+}
+
+#[test]
+fn doctor_repair_deletes_broken_entries() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let root = build_cache_root(&temp, &profile);
+    let cache = DiskCache::new(root.clone(), profile.clone(), None);
+
+    for digest in ["sha256:valid", "sha256:truncated"] {
+        let key = ArtifactKey::new(profile.id().to_string(), digest.to_string());
+        let bytes = b"hello".to_vec();
+        let meta = ArtifactMetadata::new(&profile, digest.to_string(), bytes.len() as u64);
+        cache.write_atomic(&key, &bytes, &meta).expect("write");
+    }
+    let (truncated, truncated_meta) = artifact_paths(&root, "sha256:truncated");
+    fs::write(&truncated, b"hel").expect("truncate artifact");
+
+    let report = cache.doctor(true).expect("doctor");
+    assert_eq!(report.size_mismatches, 1);
+    assert_eq!(report.repaired_entries, 1);
+    assert!(!truncated.exists());
+    assert!(!truncated_meta.exists());
+
+    let report = cache.doctor(false).expect("doctor");
+    assert_eq!(report.entries_checked, 1);
+    assert_eq!(report.valid_entries, 1);
+    assert_eq!(report.broken_entries(), 0);
+}
+
 #[test]
 fn prune_removes_oldest_entries() {
     let temp = TempDir::new().expect("temp dir");
@@ -121,3 +235,64 @@ fn prune_removes_oldest_entries() {
 
     let _ = fs::metadata(root);
 }
+
+#[test]
+fn prune_removes_expired_entries_before_sizing() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let root = build_cache_root(&temp, &profile);
+    let cache = DiskCache::new(root.clone(), profile.clone(), Some(12))
+        .with_max_age(Some(std::time::Duration::from_secs(3600)));
+
+    let now = Utc::now();
+    let entries = [
+        ("sha256:unparseable", 4usize, None),
+        ("sha256:stale", 5, Some(now - Duration::days(30))),
+        ("sha256:older", 6, Some(now - Duration::seconds(20))),
+        ("sha256:recent", 7, Some(now - Duration::seconds(10))),
+    ];
+    for (digest, size, access) in entries {
+        let key = ArtifactKey::new(profile.id().to_string(), digest.to_string());
+        let mut meta = ArtifactMetadata::new(&profile, digest.to_string(), size as u64);
+        meta.last_access_at = access.map_or_else(|| "yesterday".to_string(), |ts| ts.to_rfc3339());
+        meta.created_at = meta.last_access_at.clone();
+        cache
+            .write_atomic(&key, &vec![0u8; size], &meta)
+            .expect("write");
+    }
+
+    let report = cache.prune_to_limit(true).expect("dry run");
+    assert_eq!(report.removed_by_age, 2);
+    assert_eq!(report.removed_by_size, 1);
+    assert_eq!(report.removed_entries, 3);
+    assert_eq!(report.removed_bytes, 4 + 5 + 6);
+    assert_eq!(cache.artifact_count().expect("count"), 4);
+
+    let report = cache.prune_to_limit(false).expect("prune");
+    assert_eq!((report.removed_by_age, report.removed_by_size), (2, 1));
+    let (recent, _) = artifact_paths(&root, "sha256:recent");
+    assert!(recent.exists());
+    assert_eq!(cache.artifact_count().expect("count"), 1);
+}
+
+#[test]
+fn prune_by_age_ignores_byte_budget() {
+    let temp = TempDir::new().expect("temp dir");
+    let engine = wasmtime::Engine::default();
+    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let cache = DiskCache::new(build_cache_root(&temp, &profile), profile.clone(), None)
+        .with_max_age(Some(std::time::Duration::from_secs(3600)));
+
+    for (digest, age) in [("sha256:stale", 7200), ("sha256:fresh", 60)] {
+        let key = ArtifactKey::new(profile.id().to_string(), digest.to_string());
+        let mut meta = ArtifactMetadata::new(&profile, digest.to_string(), 5);
+        meta.last_access_at = (Utc::now() - Duration::seconds(age)).to_rfc3339();
+        cache.write_atomic(&key, b"hello", &meta).expect("write");
+    }
+
+    let report = cache.prune_to_limit(false).expect("prune");
+    assert_eq!(report.removed_by_age, 1);
+    assert_eq!(report.removed_by_size, 0);
+    assert_eq!(cache.artifact_count().expect("count"), 1);
+}
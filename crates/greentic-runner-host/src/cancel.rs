@@ -0,0 +1,278 @@
+//! Cooperative cancellation for component invocations.
+//!
+//! Every store carries an [`InvocationCancel`] that backs the `control.should-cancel`
+//! import. It is cancelled when the invocation deadline passes, when the HTTP client
+//! goes away, or when an admin aborts the invocation through the tenant's
+//! [`InvocationRegistry`]. Stores check it on every epoch tick, so a component polling
+//! `should-cancel` sees `true` within one tick of cancellation. A component that keeps
+//! running is trapped [`CANCEL_GRACE`] later.
+//!
+//! Host code hands the token to the pack runtime with [`scope`]; invocations outside a
+//! scope get a fresh token that only the deadline can cancel.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// How long a cancelled component may keep running before it is trapped.
+pub const CANCEL_GRACE: Duration = Duration::from_millis(50);
+
+tokio::task_local! {
+    static CURRENT: InvocationCancel;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    Deadline,
+    ClientDisconnected,
+    AdminAbort,
+}
+
+impl CancelReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelReason::Deadline => "deadline passed",
+            CancelReason::ClientDisconnected => "client disconnected",
+            CancelReason::AdminAbort => "aborted by admin",
+        }
+    }
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A component was trapped after ignoring a cancellation for [`CANCEL_GRACE`].
+/// Deadline cancellations surface as [`DeadlineExceeded`](crate::wasm_limits::DeadlineExceeded)
+/// instead.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invocation cancelled: {reason}")]
+pub struct Cancelled {
+    pub reason: CancelReason,
+}
+
+/// Cancellation state shared by everything working on one invocation.
+#[derive(Clone, Debug, Default)]
+pub struct InvocationCancel {
+    token: CancellationToken,
+    reason: Arc<OnceLock<CancelReason>>,
+}
+
+impl InvocationCancel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the invocation; the first reason recorded wins.
+    pub fn cancel(&self, reason: CancelReason) {
+        let _ = self.reason.set(reason);
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub fn reason(&self) -> Option<CancelReason> {
+        self.reason.get().copied()
+    }
+
+    /// Guard that cancels with [`CancelReason::ClientDisconnected`] when dropped;
+    /// disarm it once the response has been produced.
+    pub fn disconnect_guard(&self) -> DisconnectGuard {
+        DisconnectGuard {
+            cancel: Some(self.clone()),
+        }
+    }
+}
+
+pub struct DisconnectGuard {
+    cancel: Option<InvocationCancel>,
+}
+
+impl DisconnectGuard {
+    pub fn disarm(mut self) {
+        self.cancel = None;
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel(CancelReason::ClientDisconnected);
+        }
+    }
+}
+
+/// Run `future` with `cancel` as the cancellation token for component invocations it
+/// makes.
+pub async fn scope<F: Future>(cancel: InvocationCancel, future: F) -> F::Output {
+    CURRENT.scope(cancel, future).await
+}
+
+/// Token of the enclosing [`scope`], if any.
+pub fn current() -> Option<InvocationCancel> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Per-store view of an [`InvocationCancel`] driven by epoch ticks.
+#[derive(Debug)]
+pub struct CancelWatch {
+    cancel: InvocationCancel,
+    started_at: Instant,
+    cancelled_at: Option<Instant>,
+}
+
+impl Default for CancelWatch {
+    fn default() -> Self {
+        Self::new(InvocationCancel::default())
+    }
+}
+
+impl CancelWatch {
+    pub fn new(cancel: InvocationCancel) -> Self {
+        Self {
+            cancel,
+            started_at: Instant::now(),
+            cancelled_at: None,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Called on every epoch tick: cancels once `deadline` has elapsed and returns the
+    /// reason to trap with when the grace period after cancellation is over.
+    pub fn on_tick(&mut self, deadline: Duration) -> Option<CancelReason> {
+        let now = Instant::now();
+        if now.duration_since(self.started_at) >= deadline {
+            self.cancel.cancel(CancelReason::Deadline);
+        }
+        if !self.cancel.is_cancelled() {
+            return None;
+        }
+        let cancelled_at = *self.cancelled_at.get_or_insert(now);
+        if now.duration_since(cancelled_at) < CANCEL_GRACE {
+            return None;
+        }
+        Some(self.cancel.reason().unwrap_or(CancelReason::Deadline))
+    }
+}
+
+/// In-flight invocations of a tenant that carry a correlation id, so admins can abort
+/// them.
+#[derive(Default)]
+pub struct InvocationRegistry {
+    inflight: Mutex<HashMap<String, InvocationCancel>>,
+}
+
+impl InvocationRegistry {
+    /// Track `cancel` under `id` until the returned guard is dropped. A later
+    /// registration under the same id replaces the earlier one.
+    pub fn register(self: &Arc<Self>, id: &str, cancel: InvocationCancel) -> Registration {
+        self.inflight.lock().insert(id.to_string(), cancel.clone());
+        Registration {
+            registry: Arc::clone(self),
+            id: id.to_string(),
+            cancel,
+        }
+    }
+
+    /// Cancel the invocation registered under `id`; `false` when none is in flight.
+    pub fn abort(&self, id: &str) -> bool {
+        match self.inflight.lock().get(id) {
+            Some(cancel) => {
+                cancel.cancel(CancelReason::AdminAbort);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inflight.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub struct Registration {
+    registry: Arc<InvocationRegistry>,
+    id: String,
+    cancel: InvocationCancel,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut inflight = self.registry.inflight.lock();
+        // Leave a newer registration under the same id in place.
+        if inflight
+            .get(&self.id)
+            .is_some_and(|live| Arc::ptr_eq(&live.reason, &self.cancel.reason))
+        {
+            inflight.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_cancels_at_deadline_and_traps_after_grace() {
+        let cancel = InvocationCancel::new();
+        let mut watch = CancelWatch::new(cancel.clone());
+        assert_eq!(watch.on_tick(Duration::from_secs(60)), None);
+        assert!(!watch.is_cancelled());
+
+        assert_eq!(watch.on_tick(Duration::ZERO), None);
+        assert!(watch.is_cancelled());
+        assert_eq!(cancel.reason(), Some(CancelReason::Deadline));
+        std::thread::sleep(CANCEL_GRACE);
+        assert_eq!(watch.on_tick(Duration::ZERO), Some(CancelReason::Deadline));
+    }
+
+    #[test]
+    fn first_reason_wins() {
+        let cancel = InvocationCancel::new();
+        cancel.disconnect_guard().disarm();
+        assert!(!cancel.is_cancelled());
+        cancel.cancel(CancelReason::AdminAbort);
+        drop(cancel.disconnect_guard());
+        assert_eq!(cancel.reason(), Some(CancelReason::AdminAbort));
+    }
+
+    #[test]
+    fn registry_aborts_registered_invocations_until_they_finish() {
+        let registry = Arc::new(InvocationRegistry::default());
+        let cancel = InvocationCancel::new();
+        let registration = registry.register("corr-1", cancel.clone());
+        assert!(!registry.abort("corr-2"));
+        assert!(registry.abort("corr-1"));
+        assert_eq!(cancel.reason(), Some(CancelReason::AdminAbort));
+
+        drop(registration);
+        assert!(registry.is_empty());
+        assert!(!registry.abort("corr-1"));
+    }
+
+    #[tokio::test]
+    async fn scope_exposes_the_current_token() {
+        assert!(current().is_none());
+        let cancel = InvocationCancel::new();
+        let seen = scope(cancel.clone(), async { current() }).await;
+        cancel.cancel(CancelReason::ClientDisconnected);
+        assert!(seen.expect("token in scope").is_cancelled());
+    }
+}
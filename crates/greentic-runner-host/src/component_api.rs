@@ -1,3 +1,7 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+
 pub mod v0_4 {
     wasmtime::component::bindgen!({
         inline: r#"
@@ -203,6 +207,17 @@ pub mod node {
         pub deadline_unix_ms: Option<u64>,
         pub attempt: u32,
         pub idempotency_key: Option<String>,
+        /// Key/value pairs forwarded to v0.5+ components; see [`super::tenant_attributes`].
+        pub attributes: Vec<(String, String)>,
+        /// Set when a support actor invokes on the tenant's behalf; v0.5+ only.
+        pub impersonation: Option<Impersonation>,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+    pub struct Impersonation {
+        pub actor_id: String,
+        #[serde(default)]
+        pub reason: Option<String>,
     }
 
     #[derive(Clone, Debug)]
@@ -227,6 +242,56 @@ pub mod node {
         Ok(Json),
         Err(NodeError),
     }
+
+    #[derive(Clone, Debug)]
+    pub enum StreamEvent {
+        Data(Json),
+        Progress(u8),
+        Done,
+        Error(String),
+    }
+}
+
+/// Most attributes a tenant context may carry.
+pub const MAX_TENANT_ATTRIBUTES: usize = 32;
+pub const MAX_TENANT_ATTRIBUTE_KEY_BYTES: usize = 64;
+pub const MAX_TENANT_ATTRIBUTE_VALUE_BYTES: usize = 256;
+
+/// Merge tenant attributes from the bindings file with those sent on a request.
+///
+/// Binding values win on key clashes, so callers cannot override attributes the
+/// operator pinned for the tenant (plan tier, region). The result is sorted by key and
+/// rejected when it exceeds the `MAX_TENANT_ATTRIBUTE*` limits.
+pub fn tenant_attributes(
+    bindings: &BTreeMap<String, String>,
+    request: &BTreeMap<String, String>,
+) -> Result<Vec<(String, String)>> {
+    let mut merged = request.clone();
+    merged.extend(
+        bindings
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    if merged.len() > MAX_TENANT_ATTRIBUTES {
+        bail!(
+            "{} tenant attributes exceed the limit of {MAX_TENANT_ATTRIBUTES}",
+            merged.len()
+        );
+    }
+    for (key, value) in &merged {
+        if key.is_empty() {
+            bail!("tenant attribute keys must not be empty");
+        }
+        if key.len() > MAX_TENANT_ATTRIBUTE_KEY_BYTES {
+            bail!("tenant attribute key `{key}` exceeds {MAX_TENANT_ATTRIBUTE_KEY_BYTES} bytes");
+        }
+        if value.len() > MAX_TENANT_ATTRIBUTE_VALUE_BYTES {
+            bail!(
+                "tenant attribute `{key}` value exceeds {MAX_TENANT_ATTRIBUTE_VALUE_BYTES} bytes"
+            );
+        }
+    }
+    Ok(merged.into_iter().collect())
 }
 
 pub fn exec_ctx_v0_4(ctx: &node::ExecCtx) -> v0_4::exports::greentic::component::node::ExecCtx {
@@ -266,7 +331,7 @@ pub fn exec_ctx_v0_5(ctx: &node::ExecCtx) -> v0_5::exports::greentic::component:
             trace_id: ctx.tenant.trace_id.clone(),
             i18n_id: ctx.tenant.i18n_id.clone(),
             correlation_id: ctx.tenant.correlation_id.clone(),
-            attributes: Vec::new(),
+            attributes: ctx.tenant.attributes.clone(),
             session_id: ctx.tenant.correlation_id.clone(),
             flow_id: Some(ctx.flow_id.clone()),
             node_id: ctx.node_id.clone(),
@@ -274,7 +339,12 @@ pub fn exec_ctx_v0_5(ctx: &node::ExecCtx) -> v0_5::exports::greentic::component:
             deadline_ms,
             attempt: ctx.tenant.attempt,
             idempotency_key: ctx.tenant.idempotency_key.clone(),
-            impersonation: None,
+            impersonation: ctx.tenant.impersonation.as_ref().map(|imp| {
+                v0_5::exports::greentic::component::node::Impersonation {
+                    actor_id: imp.actor_id.clone(),
+                    reason: imp.reason.clone(),
+                }
+            }),
         },
         i18n_id: ctx.i18n_id.clone(),
         flow_id: ctx.flow_id.clone(),
@@ -320,6 +390,30 @@ pub fn invoke_result_from_v0_5(
     }
 }
 
+pub fn stream_event_from_v0_4(
+    event: v0_4::exports::greentic::component::node::StreamEvent,
+) -> node::StreamEvent {
+    use v0_4::exports::greentic::component::node::StreamEvent;
+    match event {
+        StreamEvent::Data(body) => node::StreamEvent::Data(body),
+        StreamEvent::Progress(percent) => node::StreamEvent::Progress(percent),
+        StreamEvent::Done => node::StreamEvent::Done,
+        StreamEvent::Error(message) => node::StreamEvent::Error(message),
+    }
+}
+
+pub fn stream_event_from_v0_5(
+    event: v0_5::exports::greentic::component::node::StreamEvent,
+) -> node::StreamEvent {
+    use v0_5::exports::greentic::component::node::StreamEvent;
+    match event {
+        StreamEvent::Data(body) => node::StreamEvent::Data(body),
+        StreamEvent::Progress(percent) => node::StreamEvent::Progress(percent),
+        StreamEvent::Done => node::StreamEvent::Done,
+        StreamEvent::Error(message) => node::StreamEvent::Error(message),
+    }
+}
+
 /// Convert v0.6 `component-runtime::run()` output to the canonical InvokeResult.
 /// Decodes CBOR output bytes to a JSON string.
 pub fn invoke_result_from_v0_6_run(
@@ -334,3 +428,45 @@ pub fn invoke_result_from_v0_6_run(
     };
     node::InvokeResult::Ok(json_str)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn binding_attributes_override_request_attributes() {
+        let merged = tenant_attributes(
+            &map(&[("plan", "enterprise")]),
+            &map(&[("plan", "free"), ("region", "eu-west")]),
+        )
+        .expect("attributes");
+        assert_eq!(
+            merged,
+            vec![
+                ("plan".to_string(), "enterprise".to_string()),
+                ("region".to_string(), "eu-west".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_attributes_are_rejected() {
+        let many = (0..=MAX_TENANT_ATTRIBUTES)
+            .map(|idx| (format!("k{idx}"), "v".to_string()))
+            .collect::<BTreeMap<_, _>>();
+        assert!(tenant_attributes(&BTreeMap::new(), &many).is_err());
+
+        let long_value = "x".repeat(MAX_TENANT_ATTRIBUTE_VALUE_BYTES + 1);
+        let err = tenant_attributes(&BTreeMap::new(), &map(&[("plan", &long_value)]))
+            .expect_err("value too long");
+        assert!(err.to_string().contains("plan"));
+        assert!(tenant_attributes(&map(&[("", "v")]), &BTreeMap::new()).is_err());
+    }
+}
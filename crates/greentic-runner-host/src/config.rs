@@ -1,16 +1,31 @@
+pub use crate::deterministic::DeterministicConfig;
 use crate::gtbind::PackBinding;
 use crate::gtbind::TenantBindings;
+pub use crate::lifecycle::LifecycleConfig;
+use crate::lifecycle::LifecycleStart;
 use crate::oauth::OAuthBrokerConfig;
+pub use crate::redact::{RedactionConfig, RedactionPolicy};
 use crate::runner::mocks::MocksConfig;
-use crate::trace::TraceConfig;
+pub use crate::secrets::policy::{
+    Decision, SecretRule, SecretRuleEffect, SecretsPolicy, SecretsPolicyConfig,
+};
+pub use crate::storage::encryption::StateEncryption;
+pub use crate::storage::quota::StateQuota;
+use crate::trace::{RunTraceConfig, TraceConfig};
 use crate::validate::ValidationConfig;
-use anyhow::{Context, Result};
+pub use crate::wasi::TenantWasiConfig;
+pub use crate::wasm_engine::WasmEngineConfig;
+pub use crate::wasm_limits::WasmLimits;
+use anyhow::{Context, Result, bail};
 use serde::Deserialize;
+use serde_json::Value;
 use serde_yaml_bw as serde_yaml;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct HostConfig {
@@ -22,6 +37,9 @@ pub struct HostConfig {
     pub http_enabled: bool,
     pub secrets_policy: SecretsPolicy,
     pub state_store_policy: StateStorePolicy,
+    pub session_policy: SessionPolicy,
+    /// Patterns scrubbed from operator errors, diagnostics and logs.
+    pub redaction: RedactionPolicy,
     pub webhook_policy: WebhookPolicy,
     pub timers: Vec<TimerBinding>,
     pub oauth: Option<OAuthConfig>,
@@ -31,6 +49,15 @@ pub struct HostConfig {
     pub trace: TraceConfig,
     pub validation: ValidationConfig,
     pub operator_policy: OperatorPolicy,
+    pub wasm_limits: WasmLimits,
+    pub wasm_engine: WasmEngineConfig,
+    pub wasi: TenantWasiConfig,
+    /// Tenant attributes forwarded to v0.5 components; win over request attributes.
+    pub attributes: BTreeMap<String, String>,
+    pub lifecycle: LifecycleConfig,
+    pub component_loading: ComponentLoading,
+    /// Components compiled at once during eager loading; `None` uses the CPU count.
+    pub compile_concurrency: Option<usize>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -51,7 +78,29 @@ pub struct BindingsFile {
     #[serde(default)]
     pub state_store: StateStorePolicy,
     #[serde(default)]
+    pub sessions: SessionPolicy,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub run_traces: RunTraceConfig,
+    #[serde(default)]
+    pub secrets: SecretsPolicyConfig,
+    #[serde(default)]
     pub operator: OperatorPolicyConfig,
+    #[serde(default)]
+    pub wasm_limits: WasmLimits,
+    #[serde(default)]
+    pub wasm_engine: WasmEngineConfig,
+    #[serde(default)]
+    pub wasi: TenantWasiConfig,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub lifecycle: LifecycleConfig,
+    #[serde(default)]
+    pub component_loading: ComponentLoading,
+    #[serde(default)]
+    pub compile_concurrency: Option<usize>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -69,12 +118,44 @@ pub struct RateLimits {
     pub messaging_send_qps: u32,
     #[serde(default = "default_messaging_burst")]
     pub messaging_burst: u32,
+    /// Max concurrent HTTP requests for the tenant; excess requests get 429.
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// Max nodes of one flow run at once when their inputs allow; unset runs flows in
+    /// order.
+    #[serde(default)]
+    pub max_parallel_nodes: Option<usize>,
+    /// Ingress limits keyed by flow id or flow type; a flow id entry wins.
+    #[serde(default)]
+    pub flows: HashMap<String, FlowRateLimit>,
+    /// Operator invoke limits keyed by `{provider_type}/{op_id}` or provider type; an
+    /// op entry wins.
+    #[serde(default)]
+    pub operators: HashMap<String, OperatorRateLimit>,
 }
 
-#[derive(Debug, Clone)]
-pub struct SecretsPolicy {
-    allowed: HashSet<String>,
-    allow_all: bool,
+/// Token bucket an ingress flow draws from, see [`crate::engine::rate_limit`].
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub struct FlowRateLimit {
+    /// Envelopes admitted per second.
+    pub per_second: f64,
+    /// Envelopes admitted at once after a quiet spell; `per_second` rounded up when unset.
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Envelopes that may wait for a token instead of being rejected.
+    #[serde(default)]
+    pub queue: u32,
+}
+
+/// Token bucket an operator op draws from, see [`crate::runner::rate_limit`].
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub struct OperatorRateLimit {
+    /// Invocations admitted per second.
+    pub per_second: f64,
+    /// Invocations admitted at once after a quiet spell; `per_second` rounded up when
+    /// unset.
+    #[serde(default)]
+    pub burst: Option<u32>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -83,21 +164,100 @@ pub struct OperatorPolicyConfig {
     pub allowed_providers: Vec<String>,
     #[serde(default)]
     pub allowed_ops: HashMap<String, Vec<String>>,
+    /// Accept operator requests that carry an `impersonation` block.
+    #[serde(default)]
+    pub allow_impersonation: bool,
+    /// Longest `timeout` an operator request may ask for; longer ones are cut to it.
+    /// Defaults to [`DEFAULT_OPERATOR_MAX_TIMEOUT_MS`].
+    #[serde(default)]
+    pub max_timeout_ms: Option<u64>,
+    /// How long successful responses are kept for replay by idempotency key; `0` turns
+    /// replay off. Defaults to [`DEFAULT_OPERATOR_IDEMPOTENCY_TTL_SECS`].
+    #[serde(default)]
+    pub idempotency_ttl_secs: Option<u64>,
+    /// Capabilities whose ops are refused, whatever else allows them.
+    #[serde(default)]
+    pub denied_capabilities: Vec<String>,
+    /// Capabilities ops may declare when `default_deny_capabilities` is set.
+    #[serde(default)]
+    pub allowed_capabilities: Vec<String>,
+    /// Refuse ops declaring any capability not in `allowed_capabilities`.
+    #[serde(default)]
+    pub default_deny_capabilities: bool,
 }
 
+/// Default cap on the `timeout` of an operator request (five minutes).
+pub const DEFAULT_OPERATOR_MAX_TIMEOUT_MS: u64 = 300_000;
+
+/// Default time operator responses stay replayable by idempotency key (one day).
+pub const DEFAULT_OPERATOR_IDEMPOTENCY_TTL_SECS: u64 = 86_400;
+
 #[derive(Debug, Clone)]
 pub struct OperatorPolicy {
     allow_all: bool,
     allowed_providers: HashSet<String>,
     allowed_ops: HashMap<String, HashSet<String>>,
+    allow_impersonation: bool,
+    max_timeout_ms: u64,
+    idempotency_ttl_secs: u64,
+    denied_capabilities: HashSet<String>,
+    allowed_capabilities: HashSet<String>,
+    default_deny_capabilities: bool,
 }
 
+/// Retry policy for failing nodes; flows override it per node in their metadata.
 #[derive(Debug, Clone, Deserialize)]
 pub struct FlowRetryConfig {
     #[serde(default = "default_retry_attempts")]
     pub max_attempts: u32,
-    #[serde(default = "default_retry_base_delay_ms")]
+    /// Delay before the first retry; it doubles with each further attempt.
+    #[serde(default = "default_retry_base_delay_ms", alias = "initial_backoff_ms")]
     pub base_delay_ms: u64,
+    #[serde(default = "default_retry_max_delay_ms", alias = "max_backoff_ms")]
+    pub max_delay_ms: u64,
+    /// Add a random delay of up to a second so that retries of concurrent runs spread out.
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: bool,
+    /// Error codes retried even when the component did not mark the error retryable.
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<String>,
+    /// Run ingress envelopes whose flow failed with a retryable error again later, see
+    /// [`crate::runner::redelivery`]; unset leaves failures to the provider.
+    #[serde(default)]
+    pub redelivery: Option<RedeliveryConfig>,
+}
+
+/// `retry.redelivery` bindings block.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct RedeliveryConfig {
+    /// Deliveries of an envelope, the first one included.
+    #[serde(default = "default_redelivery_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first redelivery; it doubles with each further one.
+    #[serde(default = "default_redelivery_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_redelivery_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl Default for RedeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_redelivery_attempts(),
+            base_delay_ms: default_redelivery_base_delay_ms(),
+            max_delay_ms: default_redelivery_max_delay_ms(),
+        }
+    }
+}
+
+impl RedeliveryConfig {
+    /// Delay before delivery `attempt + 1`, after `attempt` failed ones.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(20));
+        Duration::from_millis(delay.min(self.max_delay_ms.max(self.base_delay_ms)))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -110,6 +270,47 @@ pub struct WebhookPolicy {
 pub struct StateStorePolicy {
     #[serde(default = "default_state_store_allow")]
     pub allow: bool,
+    #[serde(default)]
+    pub quota: StateQuota,
+    #[serde(default)]
+    pub encryption: Option<StateEncryption>,
+}
+
+/// `sessions` bindings block.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct SessionPolicy {
+    /// How long a waiting flow stays resumable; `0` keeps waits until cleared.
+    #[serde(default = "default_resume_ttl_secs")]
+    pub resume_ttl_secs: u64,
+    /// Answer ingress envelopes that repeat an `activity_id` without running the flow.
+    #[serde(default = "default_dedupe_activities")]
+    pub dedupe_activities: bool,
+    /// How long a seen `activity_id` is remembered; `0` turns deduplication off.
+    #[serde(default = "default_dedupe_ttl_secs")]
+    pub dedupe_ttl_secs: u64,
+}
+
+impl SessionPolicy {
+    pub fn resume_ttl(&self) -> Option<Duration> {
+        (self.resume_ttl_secs > 0).then(|| Duration::from_secs(self.resume_ttl_secs))
+    }
+
+    /// How long seen activities are remembered, or `None` when deduplication is off.
+    pub fn dedupe_ttl(&self) -> Option<Duration> {
+        (self.dedupe_activities && self.dedupe_ttl_secs > 0)
+            .then(|| Duration::from_secs(self.dedupe_ttl_secs))
+    }
+}
+
+/// When a pack's component bytes are read and compiled.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentLoading {
+    /// Compile every component while the pack loads.
+    #[default]
+    Eager,
+    /// Only check that component entries exist at load; compile on first use.
+    Lazy,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -120,12 +321,39 @@ pub struct WebhookBindingConfig {
     pub deny_paths: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct TimerBinding {
     pub flow_id: String,
-    pub cron: String,
+    /// Needed when several packs of the tenant declare `flow_id`.
+    #[serde(default)]
+    pub pack_id: Option<String>,
+    /// Cron expression, with or without a seconds field.
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// Fixed interval between runs; set instead of `cron`.
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
     #[serde(default)]
     pub schedule_id: Option<String>,
+    /// Object merged into each run's payload next to `now` and `schedule_id`.
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default)]
+    pub overlap: TimerOverlap,
+    /// Run once on start when a tick was missed while no host was running.
+    #[serde(default)]
+    pub catch_up: bool,
+}
+
+/// What a timer does with a tick that arrives while its previous run is still going.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerOverlap {
+    /// Drop the tick.
+    #[default]
+    Skip,
+    /// Run once after the current run, for all ticks that arrived meanwhile.
+    Coalesce,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -147,7 +375,8 @@ impl HostConfig {
         let bindings: BindingsFile = serde_yaml::from_str(&content)
             .with_context(|| format!("failed to parse bindings file {path:?}"))?;
 
-        let secrets_policy = SecretsPolicy::from_bindings(&bindings);
+        let secrets_policy = secrets_policy_from_bindings(&bindings)
+            .with_context(|| format!("invalid secrets in bindings file {path:?}"))?;
         let http_enabled = bindings.flow_type_bindings.contains_key("messaging");
         let webhook_policy = bindings
             .flow_type_bindings
@@ -162,6 +391,21 @@ impl HostConfig {
                     .ok()
             })
             .unwrap_or_default();
+        crate::component_api::tenant_attributes(&bindings.attributes, &BTreeMap::new())
+            .with_context(|| format!("invalid attributes in bindings file {path:?}"))?;
+        let redaction = RedactionPolicy::from_config(&bindings.redaction)
+            .with_context(|| format!("invalid redaction in bindings file {path:?}"))?;
+        let mut schedule_ids = HashSet::new();
+        for timer in &bindings.timers {
+            crate::runner::adapt_timer::TimerSchedule::parse(timer)
+                .with_context(|| format!("invalid timers in bindings file {path:?}"))?;
+            if !schedule_ids.insert(timer.schedule_id()) {
+                bail!(
+                    "duplicate timer schedule_id {} in bindings file {path:?}",
+                    timer.schedule_id()
+                );
+            }
+        }
 
         Ok(Self {
             tenant: bindings.tenant.clone(),
@@ -172,15 +416,24 @@ impl HostConfig {
             http_enabled,
             secrets_policy,
             state_store_policy: bindings.state_store.clone(),
+            session_policy: bindings.sessions,
+            redaction,
             webhook_policy,
             timers: bindings.timers.clone(),
             oauth: bindings.oauth.clone(),
             mocks: bindings.mocks.clone(),
             pack_bindings: Vec::new(),
             env_passthrough: Vec::new(),
-            trace: TraceConfig::from_env(),
+            trace: TraceConfig::from_env().with_runs(bindings.run_traces),
             validation: ValidationConfig::from_env(),
             operator_policy: OperatorPolicy::from_config(bindings.operator.clone()),
+            wasm_limits: bindings.wasm_limits,
+            wasm_engine: bindings.wasm_engine,
+            wasi: wasi_relative_to(bindings.wasi, path),
+            attributes: bindings.attributes,
+            lifecycle: bindings.lifecycle,
+            component_loading: bindings.component_loading,
+            compile_concurrency: bindings.compile_concurrency,
         })
     }
 
@@ -194,6 +447,8 @@ impl HostConfig {
             http_enabled: false,
             secrets_policy: SecretsPolicy::allow_all(),
             state_store_policy: StateStorePolicy::default(),
+            session_policy: SessionPolicy::default(),
+            redaction: RedactionPolicy::default(),
             webhook_policy: WebhookPolicy::default(),
             timers: Vec::new(),
             oauth: None,
@@ -203,6 +458,13 @@ impl HostConfig {
             trace: TraceConfig::from_env(),
             validation: ValidationConfig::from_env(),
             operator_policy: OperatorPolicy::allow_all(),
+            wasm_limits: WasmLimits::default(),
+            wasm_engine: WasmEngineConfig::default(),
+            wasi: TenantWasiConfig::default(),
+            attributes: BTreeMap::new(),
+            lifecycle: LifecycleConfig::default(),
+            component_loading: ComponentLoading::default(),
+            compile_concurrency: None,
         }
     }
 
@@ -210,6 +472,24 @@ impl HostConfig {
         self.flow_type_bindings.get("messaging")
     }
 
+    /// Components run `on-start` right before their first invocation, either because
+    /// `lifecycle.start` is lazy or because lazy loading defers compiling them.
+    pub fn starts_components_lazily(&self) -> bool {
+        self.lifecycle.start == LifecycleStart::Lazy
+            || self.component_loading == ComponentLoading::Lazy
+    }
+
+    /// Limit for concurrent component compiles, at least 1.
+    pub fn compile_concurrency_limit(&self) -> usize {
+        self.compile_concurrency
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(NonZeroUsize::get)
+                    .unwrap_or(1)
+            })
+            .max(1)
+    }
+
     pub fn retry_config(&self) -> FlowRetryConfig {
         self.retry.clone()
     }
@@ -240,29 +520,20 @@ impl HostConfig {
     }
 }
 
-impl SecretsPolicy {
-    fn from_bindings(bindings: &BindingsFile) -> Self {
-        let allowed = bindings
-            .flow_type_bindings
-            .values()
-            .flat_map(|binding| binding.secrets.iter().cloned())
-            .collect::<HashSet<_>>();
-        Self {
-            allowed,
-            allow_all: false,
-        }
-    }
-
-    pub fn is_allowed(&self, key: &str) -> bool {
-        self.allow_all || self.allowed.contains(key)
-    }
-
-    pub fn allow_all() -> Self {
-        Self {
-            allowed: HashSet::new(),
-            allow_all: true,
+/// The `secrets` block, plus an exact allow rule for every secret a flow binding lists.
+fn secrets_policy_from_bindings(bindings: &BindingsFile) -> Result<SecretsPolicy> {
+    let mut policy = SecretsPolicy::from_config(&bindings.secrets)?;
+    let mut flow_types = bindings.flow_type_bindings.iter().collect::<Vec<_>>();
+    flow_types.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (flow_type, binding) in flow_types {
+        for key in &binding.secrets {
+            policy = policy.with_rule(SecretRule::allow(
+                format!("flow_type_bindings.{flow_type}"),
+                key.clone(),
+            ));
         }
     }
+    Ok(policy)
 }
 
 impl OperatorPolicy {
@@ -278,17 +549,64 @@ impl OperatorPolicy {
             allow_all,
             allowed_providers,
             allowed_ops,
+            allow_impersonation: config.allow_impersonation,
+            max_timeout_ms: config
+                .max_timeout_ms
+                .unwrap_or(DEFAULT_OPERATOR_MAX_TIMEOUT_MS),
+            idempotency_ttl_secs: config
+                .idempotency_ttl_secs
+                .unwrap_or(DEFAULT_OPERATOR_IDEMPOTENCY_TTL_SECS),
+            denied_capabilities: config.denied_capabilities.into_iter().collect(),
+            allowed_capabilities: config.allowed_capabilities.into_iter().collect(),
+            default_deny_capabilities: config.default_deny_capabilities,
         }
     }
 
+    /// Allows every provider and op; impersonation still needs [`Self::with_impersonation`].
     pub fn allow_all() -> Self {
         Self {
             allow_all: true,
             allowed_providers: HashSet::new(),
             allowed_ops: HashMap::new(),
+            allow_impersonation: false,
+            max_timeout_ms: DEFAULT_OPERATOR_MAX_TIMEOUT_MS,
+            idempotency_ttl_secs: DEFAULT_OPERATOR_IDEMPOTENCY_TTL_SECS,
+            denied_capabilities: HashSet::new(),
+            allowed_capabilities: HashSet::new(),
+            default_deny_capabilities: false,
         }
     }
 
+    pub fn with_impersonation(mut self, allow: bool) -> Self {
+        self.allow_impersonation = allow;
+        self
+    }
+
+    pub fn allows_impersonation(&self) -> bool {
+        self.allow_impersonation
+    }
+
+    pub fn with_max_timeout_ms(mut self, max_timeout_ms: u64) -> Self {
+        self.max_timeout_ms = max_timeout_ms;
+        self
+    }
+
+    /// Longest request `timeout` honoured, in milliseconds.
+    pub fn max_timeout_ms(&self) -> u64 {
+        self.max_timeout_ms
+    }
+
+    pub fn with_idempotency_ttl_secs(mut self, ttl_secs: u64) -> Self {
+        self.idempotency_ttl_secs = ttl_secs;
+        self
+    }
+
+    /// How long responses stay replayable by idempotency key, or `None` when replay is
+    /// off.
+    pub fn idempotency_ttl(&self) -> Option<Duration> {
+        (self.idempotency_ttl_secs > 0).then(|| Duration::from_secs(self.idempotency_ttl_secs))
+    }
+
     pub fn allows_provider(&self, provider_id: Option<&str>, provider_type: &str) -> bool {
         if self.allow_all {
             return true;
@@ -311,6 +629,19 @@ impl OperatorPolicy {
         }
         self.allows_provider(provider_id, provider_type)
     }
+
+    /// The first of an op's `capabilities` the policy refuses: a denied one, or with
+    /// `default_deny_capabilities` one that is not allowed.
+    pub fn refused_capability<'a>(&self, capabilities: &'a [String]) -> Option<&'a str> {
+        capabilities
+            .iter()
+            .find(|capability| {
+                self.denied_capabilities.contains(*capability)
+                    || (self.default_deny_capabilities
+                        && !self.allowed_capabilities.contains(*capability))
+            })
+            .map(String::as_str)
+    }
 }
 
 impl Default for RateLimits {
@@ -318,6 +649,10 @@ impl Default for RateLimits {
         Self {
             messaging_send_qps: default_messaging_qps(),
             messaging_burst: default_messaging_burst(),
+            max_in_flight: None,
+            max_parallel_nodes: None,
+            flows: HashMap::new(),
+            operators: HashMap::new(),
         }
     }
 }
@@ -326,10 +661,33 @@ impl Default for StateStorePolicy {
     fn default() -> Self {
         Self {
             allow: default_state_store_allow(),
+            quota: StateQuota::default(),
+            encryption: None,
+        }
+    }
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            resume_ttl_secs: default_resume_ttl_secs(),
+            dedupe_activities: default_dedupe_activities(),
+            dedupe_ttl_secs: default_dedupe_ttl_secs(),
         }
     }
 }
 
+/// Resolve relative preopen host paths against the bindings file's directory.
+fn wasi_relative_to(mut wasi: TenantWasiConfig, bindings_path: &Path) -> TenantWasiConfig {
+    let base = bindings_path.parent().unwrap_or_else(|| Path::new("."));
+    for spec in &mut wasi.preopens {
+        if spec.host_path.is_relative() {
+            spec.host_path = base.join(&spec.host_path);
+        }
+    }
+    wasi
+}
+
 fn default_messaging_qps() -> u32 {
     10
 }
@@ -342,6 +700,18 @@ fn default_state_store_allow() -> bool {
     true
 }
 
+fn default_resume_ttl_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_dedupe_activities() -> bool {
+    true
+}
+
+fn default_dedupe_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
 impl From<WebhookBindingConfig> for WebhookPolicy {
     fn from(value: WebhookBindingConfig) -> Self {
         Self {
@@ -382,6 +752,10 @@ impl Default for FlowRetryConfig {
         Self {
             max_attempts: default_retry_attempts(),
             base_delay_ms: default_retry_base_delay_ms(),
+            max_delay_ms: default_retry_max_delay_ms(),
+            jitter: default_retry_jitter(),
+            retry_on: default_retry_on(),
+            redelivery: None,
         }
     }
 }
@@ -390,6 +764,7 @@ impl Default for FlowRetryConfig {
 mod operator_policy_tests {
     use super::{OperatorPolicy, OperatorPolicyConfig};
     use std::collections::HashMap;
+    use std::time::Duration;
 
     #[test]
     fn policy_allows_configured_provider_op() {
@@ -398,6 +773,12 @@ mod operator_policy_tests {
         let config = OperatorPolicyConfig {
             allowed_providers: vec!["provider.allowed".into()],
             allowed_ops,
+            allow_impersonation: false,
+            max_timeout_ms: None,
+            idempotency_ttl_secs: None,
+            denied_capabilities: Vec::new(),
+            allowed_capabilities: Vec::new(),
+            default_deny_capabilities: false,
         };
         let policy = OperatorPolicy::from_config(config);
         assert!(policy.allows_provider(Some("provider.allowed"), "provider.allowed"));
@@ -412,6 +793,89 @@ mod operator_policy_tests {
         assert!(policy.allows_provider(None, "any"));
         assert!(policy.allows_op(None, "any", "op"));
     }
+
+    #[test]
+    fn impersonation_is_denied_unless_enabled() {
+        assert!(!OperatorPolicy::allow_all().allows_impersonation());
+        assert!(
+            !OperatorPolicy::from_config(OperatorPolicyConfig::default()).allows_impersonation()
+        );
+        let config = OperatorPolicyConfig {
+            allow_impersonation: true,
+            ..OperatorPolicyConfig::default()
+        };
+        assert!(OperatorPolicy::from_config(config).allows_impersonation());
+        assert!(
+            OperatorPolicy::allow_all()
+                .with_impersonation(true)
+                .allows_impersonation()
+        );
+    }
+
+    #[test]
+    fn max_timeout_defaults_to_five_minutes() {
+        assert_eq!(
+            OperatorPolicy::from_config(OperatorPolicyConfig::default()).max_timeout_ms(),
+            300_000
+        );
+        let config = OperatorPolicyConfig {
+            max_timeout_ms: Some(2_000),
+            ..OperatorPolicyConfig::default()
+        };
+        assert_eq!(OperatorPolicy::from_config(config).max_timeout_ms(), 2_000);
+    }
+
+    #[test]
+    fn capabilities_are_refused_when_denied_or_not_allowed_under_default_deny() {
+        let caps = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        let policy = OperatorPolicy::allow_all();
+        assert_eq!(policy.refused_capability(&caps(&["network-egress"])), None);
+
+        let policy = OperatorPolicy::from_config(OperatorPolicyConfig {
+            denied_capabilities: caps(&["network-egress"]),
+            ..OperatorPolicyConfig::default()
+        });
+        assert_eq!(
+            policy.refused_capability(&caps(&["filesystem", "network-egress"])),
+            Some("network-egress")
+        );
+        assert_eq!(policy.refused_capability(&caps(&["filesystem"])), None);
+
+        let policy = OperatorPolicy::from_config(OperatorPolicyConfig {
+            allowed_capabilities: caps(&["filesystem", "network-egress"]),
+            denied_capabilities: caps(&["network-egress"]),
+            default_deny_capabilities: true,
+            ..OperatorPolicyConfig::default()
+        });
+        assert_eq!(policy.refused_capability(&caps(&["filesystem"])), None);
+        assert_eq!(
+            policy.refused_capability(&caps(&["secrets"])),
+            Some("secrets")
+        );
+        assert_eq!(
+            policy.refused_capability(&caps(&["network-egress"])),
+            Some("network-egress")
+        );
+        assert_eq!(policy.refused_capability(&[]), None);
+    }
+
+    #[test]
+    fn idempotency_ttl_defaults_to_a_day_and_zero_disables() {
+        assert_eq!(
+            OperatorPolicy::allow_all().idempotency_ttl(),
+            Some(Duration::from_secs(86_400))
+        );
+        let config = OperatorPolicyConfig {
+            idempotency_ttl_secs: Some(0),
+            ..OperatorPolicyConfig::default()
+        };
+        assert_eq!(OperatorPolicy::from_config(config).idempotency_ttl(), None);
+    }
 }
 
 fn default_retry_attempts() -> u32 {
@@ -422,6 +886,30 @@ fn default_retry_base_delay_ms() -> u64 {
     250
 }
 
+fn default_retry_max_delay_ms() -> u64 {
+    10_000
+}
+
+fn default_retry_jitter() -> bool {
+    true
+}
+
+fn default_retry_on() -> Vec<String> {
+    vec!["unavailable".into(), "timeout".into()]
+}
+
+fn default_redelivery_attempts() -> u32 {
+    3
+}
+
+fn default_redelivery_base_delay_ms() -> u64 {
+    1_000
+}
+
+fn default_redelivery_max_delay_ms() -> u64 {
+    300_000
+}
+
 #[cfg(test)]
 #[allow(clippy::items_after_test_module)]
 mod tests {
@@ -439,6 +927,8 @@ mod tests {
             http_enabled: false,
             secrets_policy: SecretsPolicy::allow_all(),
             state_store_policy: StateStorePolicy::default(),
+            session_policy: SessionPolicy::default(),
+            redaction: RedactionPolicy::default(),
             webhook_policy: WebhookPolicy::default(),
             timers: Vec::new(),
             oauth,
@@ -448,6 +938,13 @@ mod tests {
             trace: TraceConfig::from_env(),
             validation: ValidationConfig::from_env(),
             operator_policy: OperatorPolicy::allow_all(),
+            wasm_limits: WasmLimits::default(),
+            wasm_engine: WasmEngineConfig::default(),
+            wasi: TenantWasiConfig::default(),
+            attributes: BTreeMap::new(),
+            lifecycle: LifecycleConfig::default(),
+            component_loading: ComponentLoading::default(),
+            compile_concurrency: None,
         }
     }
 
@@ -472,4 +969,12 @@ mod tests {
         assert_eq!(broker.default_provider.as_deref(), Some("demo"));
         assert_eq!(broker.team.as_deref(), Some("ops"));
     }
+
+    #[test]
+    fn lazy_component_loading_defers_component_start() {
+        let mut cfg = host_config_with_oauth(None);
+        assert!(!cfg.starts_components_lazily());
+        cfg.component_loading = ComponentLoading::Lazy;
+        assert!(cfg.starts_components_lazily());
+    }
 }
@@ -0,0 +1,169 @@
+//! Deterministic clocks and randomness for reproducible component runs.
+//!
+//! Tenants that set `wasi.deterministic` get a WASI context whose wall and monotonic
+//! clocks start at a configured instant and advance by a fixed step on every read, and
+//! whose secure and insecure random sources replay a stream derived from the seed.
+//! Every store starts from the same state, so an invocation with the same input sees
+//! the same times and random bytes on every run. Tenants without the block keep the
+//! host clocks and OS randomness.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::Span;
+use wasmtime_wasi::random::Deterministic;
+use wasmtime_wasi::{HostMonotonicClock, HostWallClock, WasiCtxBuilder};
+
+/// Length of the random stream replayed by deterministic stores; reads past it wrap
+/// around.
+pub const RANDOM_STREAM_BYTES: usize = 64 * 1024;
+
+/// `wasi.deterministic` bindings block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeterministicConfig {
+    /// Seed for the random streams.
+    pub seed: u64,
+    /// Wall-clock time reported by the first read, in Unix milliseconds.
+    pub start_unix_ms: u64,
+    /// How far both clocks advance on every read; `0` freezes them.
+    pub step_ms: u64,
+}
+
+impl Default for DeterministicConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            // 2024-01-01T00:00:00Z
+            start_unix_ms: 1_704_067_200_000,
+            step_ms: 0,
+        }
+    }
+}
+
+impl DeterministicConfig {
+    /// Replace the builder's clocks and random sources with deterministic ones.
+    pub(crate) fn install(&self, builder: &mut WasiCtxBuilder) {
+        let step = Duration::from_millis(self.step_ms);
+        builder
+            .wall_clock(LogicalClock::new(
+                Duration::from_millis(self.start_unix_ms),
+                step,
+            ))
+            .monotonic_clock(LogicalClock::new(Duration::ZERO, step))
+            .secure_random(Deterministic::new(random_stream(self.seed)))
+            .insecure_random(Deterministic::new(random_stream(!self.seed)))
+            .insecure_random_seed(u128::from(self.seed));
+    }
+
+    /// Record the seed and clock start on `span` (declared with `deterministic_seed` and
+    /// `deterministic_start_ms` fields) so a run can be reproduced from its trace.
+    pub fn record(&self, span: &Span) {
+        span.record("deterministic_seed", self.seed);
+        span.record("deterministic_start_ms", self.start_unix_ms);
+    }
+}
+
+/// Clock that reports `start`, then moves forward by `step` on every read.
+#[derive(Debug)]
+pub struct LogicalClock {
+    start: Duration,
+    step: Duration,
+    reads: AtomicU64,
+}
+
+impl LogicalClock {
+    pub fn new(start: Duration, step: Duration) -> Self {
+        Self {
+            start,
+            step,
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    fn tick(&self) -> Duration {
+        let reads = self.reads.fetch_add(1, Ordering::Relaxed);
+        let elapsed = self
+            .step
+            .saturating_mul(u32::try_from(reads).unwrap_or(u32::MAX));
+        self.start.saturating_add(elapsed)
+    }
+
+    fn tick_size(&self) -> Duration {
+        if self.step.is_zero() {
+            Duration::from_millis(1)
+        } else {
+            self.step
+        }
+    }
+}
+
+impl HostWallClock for LogicalClock {
+    fn resolution(&self) -> Duration {
+        self.tick_size()
+    }
+
+    fn now(&self) -> Duration {
+        self.tick()
+    }
+}
+
+impl HostMonotonicClock for LogicalClock {
+    fn resolution(&self) -> u64 {
+        self.tick_size().as_nanos() as u64
+    }
+
+    fn now(&self) -> u64 {
+        self.tick().as_nanos() as u64
+    }
+}
+
+/// [`RANDOM_STREAM_BYTES`] bytes of SplitMix64 output for `seed`.
+fn random_stream(seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    let mut bytes = Vec::with_capacity(RANDOM_STREAM_BYTES);
+    while bytes.len() < RANDOM_STREAM_BYTES {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        bytes.extend_from_slice(&(z ^ (z >> 31)).to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clocks_start_at_the_configured_instant_and_step_per_read() {
+        let frozen = LogicalClock::new(Duration::from_millis(1_000), Duration::ZERO);
+        assert_eq!(HostWallClock::now(&frozen), Duration::from_millis(1_000));
+        assert_eq!(HostWallClock::now(&frozen), Duration::from_millis(1_000));
+
+        let stepping = LogicalClock::new(Duration::ZERO, Duration::from_millis(5));
+        assert_eq!(HostMonotonicClock::now(&stepping), 0);
+        assert_eq!(HostMonotonicClock::now(&stepping), 5_000_000);
+        assert_eq!(HostMonotonicClock::resolution(&stepping), 5_000_000);
+    }
+
+    #[test]
+    fn random_stream_depends_only_on_the_seed() {
+        assert_eq!(random_stream(7), random_stream(7));
+        assert_ne!(random_stream(7), random_stream(8));
+        assert_eq!(random_stream(7).len(), RANDOM_STREAM_BYTES);
+    }
+
+    #[test]
+    fn bindings_block_fills_in_defaults() {
+        let config: DeterministicConfig = serde_json::from_str(r#"{"seed": 42}"#).unwrap();
+        assert_eq!(config.seed, 42);
+        assert_eq!(
+            config.start_unix_ms,
+            DeterministicConfig::default().start_unix_ms
+        );
+        assert_eq!(config.step_ms, 0);
+    }
+}
@@ -31,6 +31,9 @@ pub struct RunFlowRequest {
 pub struct RunFlowResult {
     /// Outcome is expressed using standard greentic-types semantics (Done/Pending/Error).
     pub outcome: serde_json::Value,
+    /// Id of the run's stored execution trace, when it was traced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
 }
 
 #[async_trait::async_trait]
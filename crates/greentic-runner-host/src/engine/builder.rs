@@ -99,6 +99,9 @@ impl RunnerApi for Runner {
                 req.input.clone(),
             )
             .await?;
-        Ok(RunFlowResult { outcome })
+        Ok(RunFlowResult {
+            outcome,
+            run_id: None,
+        })
     }
 }
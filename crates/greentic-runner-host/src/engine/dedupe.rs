@@ -0,0 +1,135 @@
+//! Ingress deduplication by `activity_id`.
+//!
+//! Messaging providers redeliver webhooks they think were lost. Each `(tenant, provider,
+//! activity_id)` an envelope carries is claimed in the state store before its flow runs
+//! and then holds the flow's response until the TTL passes, so a redelivery is answered
+//! without running the flow again. Envelopes without an `activity_id` are never
+//! deduplicated. A claim and its check are separate store calls, so two deliveries
+//! racing on different hosts may both run.
+//!
+//! The process remembers the last [`DEDUPE_CAPACITY`] activities it claimed and deletes
+//! older ones from the store early, which bounds what a burst of traffic leaves behind.
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+use super::error::GResult;
+use super::host::{SessionKey, StateHost};
+use super::runtime::IngressEnvelope;
+
+/// Activities per tenant runtime kept before the oldest are forgotten.
+pub const DEDUPE_CAPACITY: usize = 10_000;
+
+const DEDUPE_PACK: &str = "_ingress";
+const STATUS_IN_PROGRESS: &str = "in_progress";
+const STATUS_DONE: &str = "done";
+/// `status` of the value returned for a redelivered envelope.
+pub const STATUS_DUPLICATE: &str = "duplicate";
+
+pub struct ActivityDedupe {
+    state: Arc<dyn StateHost>,
+    ttl: Duration,
+    claimed: Mutex<LruCache<String, SessionKey>>,
+}
+
+/// Result of [`ActivityDedupe::claim`].
+pub enum Claim {
+    /// First delivery; record the outcome under this key.
+    New(SessionKey),
+    /// Redelivery; answer with this value instead of running the flow.
+    Duplicate(Value),
+    /// The envelope has no `activity_id`.
+    Untracked,
+}
+
+impl ActivityDedupe {
+    pub fn new(state: Arc<dyn StateHost>, ttl: Duration) -> Self {
+        Self {
+            state,
+            ttl,
+            claimed: Mutex::new(LruCache::new(
+                NonZeroUsize::new(DEDUPE_CAPACITY).expect("capacity is non-zero"),
+            )),
+        }
+    }
+
+    /// Key the envelope's activity is claimed under; `None` without an `activity_id`.
+    pub fn key(&self, envelope: &IngressEnvelope) -> Option<SessionKey> {
+        let activity_id = envelope.activity_id.as_deref()?;
+        let provider = envelope.provider.as_deref().unwrap_or("provider");
+        Some(SessionKey::new(
+            &envelope.tenant_ctx(),
+            DEDUPE_PACK,
+            provider,
+            Some(activity_id.to_string()),
+        ))
+    }
+
+    /// Claim the envelope's activity, or report what an earlier delivery recorded.
+    pub async fn claim(&self, envelope: &IngressEnvelope) -> GResult<Claim> {
+        let Some(key) = self.key(envelope) else {
+            return Ok(Claim::Untracked);
+        };
+        let provider = envelope.provider.as_deref().unwrap_or("provider");
+        let activity_id = envelope.activity_id.as_deref().unwrap_or_default();
+        if let Some(record) = self.state.get_json(&key).await? {
+            let response = if record["status"] == STATUS_DONE {
+                record["response"].clone()
+            } else {
+                Value::Null
+            };
+            return Ok(Claim::Duplicate(json!({
+                "status": STATUS_DUPLICATE,
+                "provider": provider,
+                "activity_id": activity_id,
+                "response": response,
+            })));
+        }
+        self.write(&key, json!({ "status": STATUS_IN_PROGRESS }))
+            .await?;
+        let evicted = self
+            .claimed
+            .lock()
+            .push(dedupe_id(&key), key.clone())
+            .filter(|(id, _)| *id != dedupe_id(&key));
+        if let Some((_, oldest)) = evicted {
+            self.state.del(&oldest).await?;
+        }
+        Ok(Claim::New(key))
+    }
+
+    /// Keep the response of a completed run for redeliveries.
+    pub async fn record(&self, key: &SessionKey, response: &Value) -> GResult<()> {
+        self.write(key, json!({ "status": STATUS_DONE, "response": response }))
+            .await
+    }
+
+    /// Drop the claim of a failed run so a redelivery runs the flow again.
+    pub async fn release(&self, key: &SessionKey) -> GResult<()> {
+        self.claimed.lock().pop(&dedupe_id(key));
+        self.state.del(key).await
+    }
+
+    async fn write(&self, key: &SessionKey, record: Value) -> GResult<()> {
+        self.state.set_json_ttl(key, record, self.ttl).await
+    }
+}
+
+/// Whether `value`, as returned by `StateMachineRuntime::handle`, answers a redelivery.
+pub fn is_duplicate(value: &Value) -> bool {
+    value["status"] == STATUS_DUPLICATE
+}
+
+fn dedupe_id(key: &SessionKey) -> String {
+    format!(
+        "{}/{}/{}",
+        key.tenant_key,
+        key.flow_id,
+        key.session_hint.as_deref().unwrap_or_default()
+    )
+}
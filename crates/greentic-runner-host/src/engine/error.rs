@@ -12,6 +12,10 @@ pub enum RunnerError {
     #[error("adapter call failed: {reason}")]
     AdapterCall { reason: String },
 
+    /// An adapter call failed in a way a later call may not.
+    #[error("adapter call failed: {reason}")]
+    Transient { reason: String },
+
     #[error("session error: {reason}")]
     Session { reason: String },
 
@@ -31,5 +35,12 @@ pub enum RunnerError {
     Serialization { reason: String },
 }
 
+impl RunnerError {
+    /// Whether the same call may succeed when made again later.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Transient { .. })
+    }
+}
+
 /// Result alias for runner operations.
 pub type GResult<T> = Result<T, RunnerError>;
@@ -133,8 +133,29 @@ pub trait SessionHost: Send + Sync {
 pub trait StateHost: Send + Sync {
     async fn get_json(&self, key: &SessionKey) -> GResult<Option<Value>>;
     async fn set_json(&self, key: &SessionKey, value: Value) -> GResult<()>;
+    /// Like [`Self::set_json`], but the value reads as absent once `ttl` has passed.
+    async fn set_json_ttl(&self, key: &SessionKey, value: Value, ttl: Duration) -> GResult<()>;
     async fn del(&self, key: &SessionKey) -> GResult<()>;
     async fn del_prefix(&self, key_prefix: &str) -> GResult<()>;
+
+    /// Read several keys at once; results follow `keys`, with `None` for misses.
+    async fn get_many(&self, keys: &[SessionKey]) -> GResult<Vec<Option<Value>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(self.get_json(key).await?);
+        }
+        Ok(values)
+    }
+
+    /// Write several keys; one result per entry, in order. A failed entry does not stop
+    /// the ones after it.
+    async fn set_many(&self, entries: Vec<(SessionKey, Value)>) -> GResult<Vec<GResult<()>>> {
+        let mut results = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            results.push(self.set_json(&key, value).await);
+        }
+        Ok(results)
+    }
 }
 
 pub struct HostBundle {
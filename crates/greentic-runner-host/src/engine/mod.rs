@@ -1,9 +1,11 @@
 pub mod api;
 pub mod builder;
+pub mod dedupe;
 pub mod error;
 pub mod glue;
 pub mod host;
 pub mod policy;
+pub mod rate_limit;
 pub mod registry;
 pub mod runtime;
 pub mod shims;
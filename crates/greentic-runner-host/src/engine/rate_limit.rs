@@ -0,0 +1,235 @@
+//! Ingress rate limits from the `rate_limits.flows` bindings block.
+//!
+//! Every envelope takes a token from the bucket of its tenant and flow before the flow
+//! runs; an entry for the envelope's `flow_id` wins over one for its `flow_type`. A
+//! bucket refills at `per_second` up to `burst`. Without a token, an envelope waits for
+//! one while at most `queue` others already do; past that it fails with [`RateLimited`],
+//! whose [`outcome`](RateLimited::outcome) providers answer with a retry-later response.
+//!
+//! Buckets are process-wide, so every listener and every reload of a tenant draws from
+//! the same ones; a reload that changes a limit keeps the bucket's level.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use super::runtime::IngressEnvelope;
+use crate::config::FlowRateLimit;
+
+/// `status` of the outcome of a rate-limited envelope.
+pub const STATUS_RATE_LIMITED: &str = "rate_limited";
+
+/// Refill rate used for limits of zero envelopes per second.
+const MIN_RATE: f64 = 1e-3;
+
+static LIMITS: Lazy<IngressRateLimits> = Lazy::new(IngressRateLimits::default);
+
+/// The buckets of every tenant in the process.
+pub fn ingress_limits() -> &'static IngressRateLimits {
+    &LIMITS
+}
+
+/// The `rate_limits.flows` of one tenant.
+#[derive(Clone, Debug)]
+pub struct TenantRateLimits {
+    tenant: String,
+    flows: HashMap<String, FlowRateLimit>,
+}
+
+impl TenantRateLimits {
+    /// `None` when the tenant limits no flow.
+    pub fn new(tenant: &str, flows: &HashMap<String, FlowRateLimit>) -> Option<Self> {
+        (!flows.is_empty()).then(|| Self {
+            tenant: tenant.to_string(),
+            flows: flows.clone(),
+        })
+    }
+
+    /// Take a token for `envelope`, waiting in the queue when there is room.
+    pub async fn admit(&self, envelope: &IngressEnvelope) -> Result<(), RateLimited> {
+        let limit = self.flows.get_key_value(&envelope.flow_id).or_else(|| {
+            let flow_type = envelope.flow_type.as_ref()?;
+            self.flows.get_key_value(flow_type)
+        });
+        let Some((key, limit)) = limit else {
+            return Ok(());
+        };
+        let wait = ingress_limits().reserve(&self.tenant, key, *limit)?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct IngressRateLimits {
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+struct Bucket {
+    limit: FlowRateLimit,
+    /// Below zero while envelopes wait: each holds the token it will get.
+    tokens: f64,
+    refilled: Instant,
+    admitted: u64,
+    queued: u64,
+    rejected: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.refilled = now;
+        self.tokens = (self.tokens + elapsed * rate(&self.limit)).min(capacity(&self.limit));
+    }
+}
+
+/// Level of one bucket, as the tenant stats report it.
+#[derive(Clone, Debug, Serialize)]
+pub struct BucketLevel {
+    /// The flow id or flow type the limit is configured under.
+    pub flow: String,
+    pub per_second: f64,
+    pub burst: f64,
+    /// Tokens available now.
+    pub tokens: f64,
+    /// Envelopes waiting for a token now.
+    pub waiting: u64,
+    pub admitted: u64,
+    /// Envelopes that waited before they were admitted.
+    pub queued: u64,
+    pub rejected: u64,
+}
+
+impl IngressRateLimits {
+    /// Take a token from the bucket of `tenant`'s `flow`; returns how long to wait for it.
+    fn reserve(
+        &self,
+        tenant: &str,
+        flow: &str,
+        limit: FlowRateLimit,
+    ) -> Result<Duration, RateLimited> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets
+            .entry((tenant.to_string(), flow.to_string()))
+            .or_insert_with(|| Bucket {
+                limit,
+                tokens: capacity(&limit),
+                refilled: now,
+                admitted: 0,
+                queued: 0,
+                rejected: 0,
+            });
+        bucket.refill(now);
+        bucket.limit = limit;
+        let rate = rate(&limit);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.admitted += 1;
+            return Ok(Duration::ZERO);
+        }
+        if bucket.tokens - 1.0 >= -f64::from(limit.queue) {
+            bucket.tokens -= 1.0;
+            bucket.admitted += 1;
+            bucket.queued += 1;
+            return Ok(Duration::from_secs_f64(-bucket.tokens / rate));
+        }
+        bucket.rejected += 1;
+        Err(RateLimited {
+            tenant: tenant.to_string(),
+            flow: flow.to_string(),
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / rate),
+        })
+    }
+
+    /// Levels of the buckets of `tenant`, by flow.
+    pub fn tenant(&self, tenant: &str) -> Vec<BucketLevel> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let mut levels = buckets
+            .iter_mut()
+            .filter(|((name, _), _)| name == tenant)
+            .map(|((_, flow), bucket)| {
+                bucket.refill(now);
+                BucketLevel {
+                    flow: flow.clone(),
+                    per_second: bucket.limit.per_second,
+                    burst: capacity(&bucket.limit),
+                    tokens: bucket.tokens.max(0.0),
+                    waiting: (-bucket.tokens).max(0.0).ceil() as u64,
+                    admitted: bucket.admitted,
+                    queued: bucket.queued,
+                    rejected: bucket.rejected,
+                }
+            })
+            .collect::<Vec<_>>();
+        levels.sort_by(|a, b| a.flow.cmp(&b.flow));
+        levels
+    }
+}
+
+fn rate(limit: &FlowRateLimit) -> f64 {
+    limit.per_second.max(MIN_RATE)
+}
+
+fn capacity(limit: &FlowRateLimit) -> f64 {
+    limit
+        .burst
+        .map(f64::from)
+        .unwrap_or(limit.per_second.ceil())
+        .max(1.0)
+}
+
+/// An envelope refused by the rate limit of its flow.
+#[derive(Clone, Debug)]
+pub struct RateLimited {
+    pub tenant: String,
+    /// The flow id or flow type the limit is configured under.
+    pub flow: String,
+    /// When the bucket will have a token again.
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// `retry_after` in whole seconds, at least one, for a `Retry-After` header.
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs_f64().ceil().max(1.0) as u64
+    }
+
+    /// The `status: "rate_limited"` outcome handed back to the provider.
+    pub fn outcome(&self) -> Value {
+        json!({
+            "status": STATUS_RATE_LIMITED,
+            "tenant": self.tenant,
+            "flow": self.flow,
+            "retry_after_ms": self.retry_after.as_millis() as u64,
+        })
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rate limit of flow {} exceeded for tenant {}; retry in {} ms",
+            self.flow,
+            self.tenant,
+            self.retry_after.as_millis()
+        )
+    }
+}
+
+impl StdError for RateLimited {}
+
+/// The [`RateLimited`] refusal `err` carries, if it is one.
+pub fn rate_limited(err: &anyhow::Error) -> Option<&RateLimited> {
+    err.downcast_ref::<RateLimited>()
+}
@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use greentic_session::{SessionData, SessionKey as StoreSessionKey};
 use greentic_types::{
-    EnvId, FlowId, GreenticError, PackId, ReplyScope, SessionCursor as TypesSessionCursor,
-    TenantCtx, TenantId, UserId,
+    EnvId, ErrorCode, FlowId, GreenticError, PackId, ReplyScope,
+    SessionCursor as TypesSessionCursor, TenantCtx, TenantId, UserId,
 };
+use parking_lot::Mutex;
 use rand::{RngExt, rng};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -16,38 +18,79 @@ use sha2::{Digest, Sha256};
 
 use super::api::{RunFlowRequest, RunnerApi};
 use super::builder::{Runner, RunnerBuilder};
+use super::dedupe::{ActivityDedupe, Claim};
 use super::error::{GResult, RunnerError};
 use super::glue::{FnSecretsHost, FnTelemetryHost};
 use super::host::{HostBundle, SecretsHost, SessionHost, StateHost};
 use super::policy::Policy;
+use super::rate_limit::{RateLimited, TenantRateLimits};
 use super::registry::{Adapter, AdapterCall, AdapterRegistry};
 use super::shims::{InMemorySessionHost, InMemoryStateHost};
 use super::state_machine::{FlowDefinition, FlowStep, PAYLOAD_FROM_LAST_INPUT};
 
-use crate::config::{HostConfig, SecretsPolicy};
+use crate::config::{HostConfig, RedeliveryConfig, SecretsPolicy};
 use crate::pack::FlowDescriptor;
-use crate::runner::engine::{FlowContext, FlowEngine, FlowSnapshot, FlowStatus, FlowWait};
+use crate::redact::{self, Redactor};
+use crate::runner::engine::{
+    ExecutionObserver, FlowContext, FlowEngine, FlowSnapshot, FlowStatus, FlowWait, RetryConfig,
+};
 use crate::runner::mocks::MockLayer;
+use crate::runner::progress::{self, ObserverSet};
+use crate::runner::redelivery::{self, RedeliveryQueue};
 use crate::secrets::{DynSecretsManager, read_secret_blocking};
+use crate::storage::expiry::{self, ExpirySweep};
+use crate::storage::metrics::store_metrics;
 use crate::storage::session::DynSessionStore;
-use crate::trace::{PackTraceInfo, TraceContext, TraceMode, TraceRecorder};
+use crate::trace::run::{self, RunTraceRequest};
+use crate::trace::{
+    PackTraceInfo, RunStatus, RunTrace, RunTraceRecorder, RunTraceStore, TraceContext, TraceMode,
+    TraceRecorder,
+};
 
 const DEFAULT_ENV: &str = "local";
 const PACK_FLOW_ADAPTER: &str = "pack_flow";
 
+/// How long a saved wait stays resumable unless the tenant sets `sessions.resume_ttl_secs`.
+pub const DEFAULT_RESUME_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Waiting flow snapshots, kept in the session store.
+///
+/// Each saved wait records when it expires, and the TTL is also handed to the session
+/// store so backends with native expiry (Redis) drop the wait themselves. An expired
+/// wait reads exactly like a missing one and is cleared when fetched; waits saved through this store are also
+/// cleared by [`FlowResumeStore::clear_expired`], which the host's expiry sweeper
+/// calls. Waits saved by another process are only dropped when fetched here.
 #[derive(Clone)]
 pub struct FlowResumeStore {
     store: DynSessionStore,
+    ttl: Option<Duration>,
+    waits: Arc<PendingWaits>,
 }
 
 impl FlowResumeStore {
     pub fn new(store: DynSessionStore) -> Self {
-        Self { store }
+        let waits = Arc::new(PendingWaits {
+            store: Arc::clone(&store),
+            waits: Mutex::new(HashMap::new()),
+        });
+        expiry::watch(&waits);
+        Self {
+            store,
+            ttl: Some(DEFAULT_RESUME_TTL),
+            waits,
+        }
+    }
+
+    /// Expire saved waits after `ttl`; `None` keeps them until cleared.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
     }
 
     pub fn fetch(&self, envelope: &IngressEnvelope) -> GResult<Option<FlowSnapshot>> {
-        let (mut ctx, user, _, scope) = build_store_ctx(envelope)?;
+        let (mut ctx, user, hint, scope) = build_store_ctx(envelope)?;
         ctx = ctx.with_user(Some(user.clone()));
+        let now = unix_millis();
 
         let mut scopes = vec![scope.clone()];
         if scope.correlation.is_some() {
@@ -57,6 +100,18 @@ impl FlowResumeStore {
         }
 
         for lookup in scopes {
+            // Counted here even when the backend has already expired the wait itself.
+            if self
+                .waits
+                .take_expired(&format!("{hint}::{}", lookup.scope_hash()), now)
+                .is_some()
+            {
+                self.store
+                    .clear_wait(&ctx, &user, &lookup)
+                    .map_err(map_store_error)?;
+                store_metrics().record_expired_waits(&envelope.tenant, 1);
+                continue;
+            }
             if let Some(key) = self
                 .store
                 .find_wait_by_scope(&ctx, &user, &lookup)
@@ -71,6 +126,14 @@ impl FlowResumeStore {
                             reason: format!("failed to decode flow resume snapshot: {err}"),
                         }
                     })?;
+                if record.is_expired(now) {
+                    self.store
+                        .clear_wait(&ctx, &user, &lookup)
+                        .map_err(map_store_error)?;
+                    self.waits.forget(key.as_str());
+                    store_metrics().record_expired_waits(&envelope.tenant, 1);
+                    continue;
+                }
                 if record.snapshot.flow_id == envelope.flow_id {
                     if let Some(pack_id) = envelope.pack_id.as_deref()
                         && record.snapshot.pack_id != pack_id
@@ -92,9 +155,13 @@ impl FlowResumeStore {
 
     pub fn save(&self, envelope: &IngressEnvelope, wait: &FlowWait) -> GResult<ReplyScope> {
         let (ctx, user, hint, scope) = build_store_ctx(envelope)?;
+        let expires_at_ms = self
+            .ttl
+            .map(|ttl| unix_millis().saturating_add(ttl.as_millis() as u64));
         let record = FlowResumeRecord {
             snapshot: wait.snapshot.clone(),
             reason: wait.reason.clone(),
+            expires_at_ms,
         };
         let data = record_to_session_data(&record, ctx.clone(), &user, &hint)?;
         let mut reply_scope = scope.clone();
@@ -105,13 +172,26 @@ impl FlowResumeStore {
         store_scope.correlation = None;
         let session_key = StoreSessionKey::new(format!("{hint}::{}", store_scope.scope_hash()));
         self.store
-            .register_wait(&ctx, &user, &store_scope, &session_key, data, None)
+            .register_wait(&ctx, &user, &store_scope, &session_key, data, self.ttl)
             .map_err(map_store_error)?;
+        match expires_at_ms {
+            Some(expires_at_ms) => self.waits.remember(
+                session_key.as_str(),
+                PendingWait {
+                    tenant: envelope.tenant.clone(),
+                    ctx,
+                    user,
+                    scope: store_scope,
+                    expires_at_ms,
+                },
+            ),
+            None => self.waits.forget(session_key.as_str()),
+        }
         Ok(reply_scope)
     }
 
     pub fn clear(&self, envelope: &IngressEnvelope) -> GResult<()> {
-        let (ctx, user, _, scope) = build_store_ctx(envelope)?;
+        let (ctx, user, hint, scope) = build_store_ctx(envelope)?;
         let mut scopes = vec![scope.clone()];
         if scope.correlation.is_some() {
             let mut base = scope;
@@ -122,9 +202,81 @@ impl FlowResumeStore {
             self.store
                 .clear_wait(&ctx, &user, &lookup)
                 .map_err(map_store_error)?;
+            self.waits
+                .forget(&format!("{hint}::{}", lookup.scope_hash()));
         }
         Ok(())
     }
+
+    /// Clear every wait saved through this store whose TTL has passed; returns how
+    /// many were removed.
+    pub fn clear_expired(&self) -> GResult<u64> {
+        self.waits.clear_expired()
+    }
+}
+
+struct PendingWait {
+    tenant: String,
+    ctx: TenantCtx,
+    user: UserId,
+    scope: ReplyScope,
+    expires_at_ms: u64,
+}
+
+/// Expiring waits saved through one [`FlowResumeStore`], keyed by session key.
+struct PendingWaits {
+    store: DynSessionStore,
+    waits: Mutex<HashMap<String, PendingWait>>,
+}
+
+impl PendingWaits {
+    fn remember(&self, key: &str, wait: PendingWait) {
+        self.waits.lock().insert(key.to_string(), wait);
+    }
+
+    fn forget(&self, key: &str) {
+        self.waits.lock().remove(key);
+    }
+
+    fn take_expired(&self, key: &str, now_ms: u64) -> Option<PendingWait> {
+        let mut waits = self.waits.lock();
+        if waits.get(key)?.expires_at_ms <= now_ms {
+            waits.remove(key)
+        } else {
+            None
+        }
+    }
+
+    fn clear_expired(&self) -> GResult<u64> {
+        let now = unix_millis();
+        let expired = {
+            let mut waits = self.waits.lock();
+            let keys = waits
+                .iter()
+                .filter(|(_, wait)| wait.expires_at_ms <= now)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            keys.into_iter()
+                .filter_map(|key| waits.remove(&key))
+                .collect::<Vec<_>>()
+        };
+        let mut removed = 0;
+        for wait in expired {
+            self.store
+                .clear_wait(&wait.ctx, &wait.user, &wait.scope)
+                .map_err(map_store_error)?;
+            store_metrics().record_expired_waits(&wait.tenant, 1);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+impl ExpirySweep for PendingWaits {
+    fn sweep_expired(&self) -> greentic_types::GResult<u64> {
+        self.clear_expired()
+            .map_err(|err| GreenticError::new(ErrorCode::Unavailable, err.to_string()))
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -132,6 +284,23 @@ struct FlowResumeRecord {
     snapshot: FlowSnapshot,
     #[serde(default)]
     reason: Option<String>,
+    /// Unix time in milliseconds after which the wait reads as missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+impl FlowResumeRecord {
+    fn is_expired(&self, now_ms: u64) -> bool {
+        self.expires_at_ms
+            .is_some_and(|expires_at_ms| expires_at_ms <= now_ms)
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
 }
 
 fn build_store_ctx(envelope: &IngressEnvelope) -> GResult<(TenantCtx, UserId, String, ReplyScope)> {
@@ -298,6 +467,51 @@ mod tests {
         Ok(())
     }
 
+    fn expired_waits_for(tenant: &str) -> u64 {
+        store_metrics()
+            .expired_waits()
+            .into_iter()
+            .find(|(name, _)| name == tenant)
+            .map(|(_, count)| count)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn expired_wait_reads_as_missing() -> GResult<()> {
+        let store =
+            FlowResumeStore::new(new_session_store()).with_ttl(Some(Duration::from_millis(20)));
+        let mut envelope = sample_envelope();
+        envelope.tenant = "resume-ttl-fetch".into();
+        let _ = store.save(&envelope, &sample_wait())?;
+        assert!(store.fetch(&envelope)?.is_some());
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(store.fetch(&envelope)?.is_none());
+        assert!(store.fetch(&envelope)?.is_none());
+        assert_eq!(expired_waits_for("resume-ttl-fetch"), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn clear_expired_removes_only_expired_waits() -> GResult<()> {
+        let short =
+            FlowResumeStore::new(new_session_store()).with_ttl(Some(Duration::from_millis(20)));
+        let mut envelope = sample_envelope();
+        envelope.tenant = "resume-ttl-sweep".into();
+        let _ = short.save(&envelope, &sample_wait())?;
+        let kept = FlowResumeStore::new(new_session_store()).with_ttl(None);
+        let _ = kept.save(&envelope, &sample_wait())?;
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(short.clear_expired()?, 1);
+        assert_eq!(short.clear_expired()?, 0);
+        assert_eq!(kept.clear_expired()?, 0);
+        assert!(short.fetch(&envelope)?.is_none());
+        assert!(kept.fetch(&envelope)?.is_some());
+        assert_eq!(expired_waits_for("resume-ttl-sweep"), 1);
+        Ok(())
+    }
+
     #[test]
     fn canonicalize_populates_defaults() {
         let envelope = IngressEnvelope {
@@ -330,6 +544,10 @@ mod tests {
 
 pub struct StateMachineRuntime {
     runner: Runner,
+    dedupe: Option<ActivityDedupe>,
+    rate_limits: Option<TenantRateLimits>,
+    redelivery: Option<(Arc<RedeliveryQueue>, RedeliveryConfig)>,
+    run_traces: Option<RunTraceStore>,
 }
 
 impl StateMachineRuntime {
@@ -356,7 +574,13 @@ impl StateMachineRuntime {
             builder = builder.with_flow(flow);
         }
         let runner = builder.build()?;
-        Ok(Self { runner })
+        Ok(Self {
+            runner,
+            dedupe: None,
+            rate_limits: None,
+            redelivery: None,
+            run_traces: None,
+        })
     }
 
     /// Build a state-machine runtime that proxies pack flows through the legacy FlowEngine.
@@ -370,6 +594,7 @@ impl StateMachineRuntime {
         state_host: Arc<dyn StateHost>,
         secrets_manager: DynSecretsManager,
         mocks: Option<Arc<MockLayer>>,
+        redelivery: Arc<RedeliveryQueue>,
     ) -> Result<Self> {
         let policy = Arc::new(config.secrets_policy.clone());
         let tenant_ctx = config.tenant_ctx();
@@ -378,8 +603,16 @@ impl StateMachineRuntime {
             tracing::debug!(?span, ?fields, "telemetry emit");
             Ok(())
         }));
+        let dedupe = config
+            .session_policy
+            .dedupe_ttl()
+            .map(|ttl| ActivityDedupe::new(Arc::clone(&state_host), ttl));
+        let rate_limits = TenantRateLimits::new(&config.tenant, &config.rate_limits.flows);
+        let redelivery = config.retry.redelivery.map(|policy| (redelivery, policy));
+        let run_traces = RunTraceStore::new(Arc::clone(&state_host), &config.trace.runs);
         let host = HostBundle::new(secrets, telemetry, session_host, state_host);
-        let resume_store = FlowResumeStore::new(session_store);
+        let resume_store =
+            FlowResumeStore::new(session_store).with_ttl(config.session_policy.resume_ttl());
 
         let mut adapters = AdapterRegistry::default();
         adapters.register(
@@ -389,26 +622,162 @@ impl StateMachineRuntime {
                 Arc::clone(&engine),
                 pack_trace,
                 resume_store,
+                run_traces.clone(),
                 mocks,
             )),
         );
 
         let flows = build_flow_definitions(engine.flows());
+        let mut policy = Policy::default();
+        if redelivery.is_some() {
+            // A failed run is redelivered later instead of run again at once.
+            policy.retry.max_attempts = 1;
+        }
         let mut builder = RunnerBuilder::new()
             .with_host(host)
             .with_adapters(adapters)
-            .with_policy(Policy::default());
+            .with_policy(policy);
         for flow in flows {
             builder = builder.with_flow(flow);
         }
         let runner = builder
             .build()
             .map_err(|err| anyhow!("state machine init failed: {err}"))?;
-        Ok(Self { runner })
+        Ok(Self {
+            runner,
+            dedupe,
+            rate_limits,
+            redelivery,
+            run_traces: Some(run_traces),
+        })
+    }
+
+    /// The runner behind this runtime, for callers speaking [`RunnerApi`] directly.
+    pub fn runner(&self) -> &Runner {
+        &self.runner
+    }
+
+    /// The execution trace stored for `run_id`, if it is still kept.
+    pub async fn run_trace(&self, tenant: &TenantCtx, run_id: &str) -> GResult<Option<RunTrace>> {
+        match &self.run_traces {
+            Some(store) => store.load(tenant, run_id).await,
+            None => Ok(None),
+        }
     }
 
     /// Execute the flow associated with the provided ingress event.
+    ///
+    /// When the tenant deduplicates activities, an envelope repeating an `activity_id`
+    /// already seen from its provider does not run the flow; the returned value then has
+    /// `status: "duplicate"` and the first delivery's `response` (see
+    /// [`super::dedupe::is_duplicate`]). An envelope over the rate limit of its flow
+    /// fails with [`RateLimited`] before anything else happens. When the tenant
+    /// redelivers, a run that fails with a retryable error is stored for a later
+    /// delivery and the returned value has `status: "redelivery_scheduled"` (see
+    /// [`crate::runner::redelivery`]).
     pub async fn handle(&self, envelope: IngressEnvelope) -> Result<Value> {
+        if let Some(limits) = &self.rate_limits
+            && let Err(limited) = limits.admit(&envelope).await
+        {
+            tracing::warn!(
+                flow_id = %envelope.flow_id,
+                flow = %limited.flow,
+                retry_after_ms = limited.retry_after.as_millis() as u64,
+                "ingress rate limit exceeded"
+            );
+            return Err(limited.into());
+        }
+        let claim = match &self.dedupe {
+            Some(dedupe) => match dedupe.claim(&envelope).await {
+                Ok(Claim::Duplicate(previous)) => {
+                    tracing::info!(
+                        flow_id = %envelope.flow_id,
+                        activity_id = ?envelope.activity_id,
+                        "skipping redelivered activity"
+                    );
+                    return Ok(previous);
+                }
+                Ok(Claim::New(key)) => Some((dedupe, key)),
+                Ok(Claim::Untracked) => None,
+                Err(err) => {
+                    tracing::warn!(error = %err, "activity dedupe unavailable; running flow");
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut result = self.run_envelope(&envelope).await;
+        let mut scheduled = false;
+        if let Err(err) = &result
+            && let Some(outcome) = self.schedule_redelivery(&envelope, err).await
+        {
+            // The claim stays in progress until a redelivery settles the activity.
+            result = Ok(outcome);
+            scheduled = true;
+        }
+        if let Some((dedupe, key)) = claim.filter(|_| !scheduled) {
+            let stored = match &result {
+                Ok(response) => dedupe.record(&key, response).await,
+                Err(_) => dedupe.release(&key).await,
+            };
+            if let Err(err) = stored {
+                tracing::warn!(error = %err, "failed to update activity dedupe record");
+            }
+        }
+        result
+    }
+
+    /// Deliver `envelope` again as delivery `attempt`, past its rate limit and its
+    /// activity's claim, which the first delivery holds. A success is recorded as the
+    /// activity's response.
+    pub async fn redeliver(&self, envelope: &IngressEnvelope, attempt: u32) -> Result<Value> {
+        let result = redelivery::scope(attempt, self.run_envelope(envelope)).await;
+        if let Ok(response) = &result
+            && let Some(dedupe) = &self.dedupe
+            && let Some(key) = dedupe.key(envelope)
+            && let Err(err) = dedupe.record(&key, response).await
+        {
+            tracing::warn!(error = %err, "failed to update activity dedupe record");
+        }
+        result
+    }
+
+    /// Drop the claim of `envelope`'s activity once its redeliveries gave up, so the
+    /// provider's next delivery runs the flow.
+    pub async fn release_activity(&self, envelope: &IngressEnvelope) {
+        if let Some(dedupe) = &self.dedupe
+            && let Some(key) = dedupe.key(envelope)
+            && let Err(err) = dedupe.release(&key).await
+        {
+            tracing::warn!(error = %err, "failed to update activity dedupe record");
+        }
+    }
+
+    /// Store `envelope` for a redelivery when the tenant redelivers and `err` is
+    /// retryable; returns the value to answer its delivery with.
+    async fn schedule_redelivery(
+        &self,
+        envelope: &IngressEnvelope,
+        err: &anyhow::Error,
+    ) -> Option<Value> {
+        let (queue, policy) = self.redelivery.as_ref()?;
+        if policy.max_attempts < 2 || !redelivery::is_retryable(err) {
+            return None;
+        }
+        match queue.schedule(envelope, 1, err, policy).await {
+            Ok(record) => Some(record.outcome()),
+            Err(store_err) => {
+                tracing::warn!(
+                    flow_id = %envelope.flow_id,
+                    error = %store_err,
+                    "failed to store redelivery; failing the delivery"
+                );
+                None
+            }
+        }
+    }
+
+    async fn run_envelope(&self, envelope: &IngressEnvelope) -> Result<Value> {
         let tenant_ctx = envelope.tenant_ctx();
         let session_hint = envelope
             .session_hint
@@ -418,7 +787,7 @@ impl StateMachineRuntime {
             anyhow!("pack_id missing; ingress must specify pack_id for multi-pack flows")
         })?;
         let input =
-            serde_json::to_value(&envelope).context("failed to serialise ingress envelope")?;
+            serde_json::to_value(envelope).context("failed to serialise ingress envelope")?;
         let request = RunFlowRequest {
             tenant: tenant_ctx,
             pack_id,
@@ -426,16 +795,24 @@ impl StateMachineRuntime {
             input,
             session_hint: Some(session_hint),
         };
-        let result: super::api::RunFlowResult = self
-            .runner
-            .run_flow(request)
-            .await
-            .map_err(|err| anyhow!("flow execution failed: {err}"))?;
+        let result: super::api::RunFlowResult =
+            self.runner.run_flow(request).await.map_err(flow_failure)?;
         let outcome = result.outcome;
         Ok(outcome.get("response").cloned().unwrap_or(outcome))
     }
 }
 
+/// The error of a failed run; transient failures keep their [`RunnerError`] so that
+/// [`redelivery::is_retryable`] sees them.
+fn flow_failure(err: RunnerError) -> anyhow::Error {
+    let message = format!("flow execution failed: {err}");
+    if err.is_transient() {
+        anyhow::Error::new(err).context(message)
+    } else {
+        anyhow!(message)
+    }
+}
+
 struct PolicySecretsHost {
     policy: Arc<SecretsPolicy>,
     manager: DynSecretsManager,
@@ -457,13 +834,15 @@ const POLICY_SECRETS_PACK_ID: &str = "_runner";
 #[async_trait]
 impl SecretsHost for PolicySecretsHost {
     async fn get(&self, name: &str) -> GResult<String> {
-        if !self.policy.is_allowed(name) {
+        let decision = self.policy.explain(name);
+        if !decision.allowed {
             return Err(RunnerError::Secrets {
-                reason: format!("secret {name} denied by policy"),
+                reason: format!("secret {name} denied by policy (rule `{}`)", decision.rule),
             });
         }
         let bytes = read_secret_blocking(
             &self.manager,
+            &self.policy,
             &self.tenant_ctx,
             POLICY_SECRETS_PACK_ID,
             name,
@@ -511,6 +890,7 @@ struct PackFlowAdapter {
     engine: Arc<FlowEngine>,
     pack_trace: HashMap<String, PackTraceInfo>,
     resume: FlowResumeStore,
+    run_traces: RunTraceStore,
     mocks: Option<Arc<MockLayer>>,
 }
 
@@ -520,6 +900,7 @@ impl PackFlowAdapter {
         engine: Arc<FlowEngine>,
         pack_trace: HashMap<String, PackTraceInfo>,
         resume: FlowResumeStore,
+        run_traces: RunTraceStore,
         mocks: Option<Arc<MockLayer>>,
     ) -> Self {
         Self {
@@ -528,9 +909,31 @@ impl PackFlowAdapter {
             engine,
             pack_trace,
             resume,
+            run_traces,
             mocks,
         }
     }
+
+    async fn save_run_trace(&self, request: &RunTraceRequest, trace: RunTrace) {
+        match self
+            .run_traces
+            .save(&self.config.tenant_ctx(), &trace)
+            .await
+        {
+            Ok(()) => {
+                request.mark_recorded();
+                tracing::info!(
+                    run_id = %trace.run_id,
+                    flow_id = %trace.flow_id,
+                    nodes = trace.nodes.len(),
+                    "stored run trace"
+                );
+            }
+            Err(err) => {
+                tracing::warn!(run_id = %trace.run_id, error = %err, "failed to store run trace")
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -551,7 +954,7 @@ impl Adapter for PackFlowAdapter {
             .unwrap_or_else(|| envelope.canonical_session_hint());
         let provider_owned = envelope.provider.clone();
         let payload = envelope.payload.clone();
-        let retry_config = self.config.retry_config().into();
+        let retry_config: RetryConfig = self.config.retry_config().into();
 
         let pack_id = if let Some(pack_id) = envelope.pack_id.as_deref() {
             let found = self.engine.flow_by_key(pack_id, &flow_id).is_some();
@@ -594,8 +997,42 @@ impl Adapter for PackFlowAdapter {
         } else {
             Some(TraceRecorder::new(trace_config, trace_ctx))
         };
+        let run_trace = run::current()
+            .or_else(|| {
+                self.config
+                    .trace
+                    .runs
+                    .enabled
+                    .then(RunTraceRequest::generate)
+            })
+            .map(|request| {
+                let redactor = redact::current()
+                    .unwrap_or_else(|| Redactor::new(self.config.redaction.clone()));
+                let recorder = RunTraceRecorder::new(
+                    &request,
+                    &self.tenant,
+                    pack_id,
+                    &flow_id,
+                    &payload,
+                    self.config.trace.runs,
+                    redactor,
+                );
+                (request, recorder)
+            });
 
         let mocks = self.mocks.as_deref();
+        let progress = progress::current();
+        let observers = ObserverSet::new(
+            trace
+                .iter()
+                .map(|recorder| recorder as &dyn ExecutionObserver)
+                .chain(progress.as_deref())
+                .chain(
+                    run_trace
+                        .as_ref()
+                        .map(|(_, recorder)| recorder as &dyn ExecutionObserver),
+                ),
+        );
         let ctx = FlowContext {
             tenant: &self.tenant,
             pack_id,
@@ -605,24 +1042,40 @@ impl Adapter for PackFlowAdapter {
             action: action_owned.as_deref(),
             session_id: Some(session_owned.as_str()),
             provider_id: provider_owned.as_deref(),
-            retry_config,
-            attempt: 1,
-            observer: trace
-                .as_ref()
-                .map(|recorder| recorder as &dyn crate::runner::engine::ExecutionObserver),
+            retry_config: retry_config.clone(),
+            attempt: redelivery::current_attempt(),
+            observer: observers.as_observer(),
             mocks,
         };
 
-        let execution = if let Some(snapshot) = self.resume.fetch(&envelope)? {
-            let resume_pack_id = snapshot.pack_id.clone();
-            let resume_ctx = FlowContext {
-                pack_id: resume_pack_id.as_str(),
-                ..ctx
-            };
-            self.engine.resume(resume_ctx, snapshot, payload).await
-        } else {
-            self.engine.execute(ctx, payload).await
+        let snapshot = self.resume.fetch(&envelope)?;
+        let flow_run = async {
+            if let Some(snapshot) = snapshot {
+                let resume_pack_id = snapshot.pack_id.clone();
+                let resume_ctx = FlowContext {
+                    pack_id: resume_pack_id.as_str(),
+                    ..ctx
+                };
+                self.engine.resume(resume_ctx, snapshot, payload).await
+            } else {
+                self.engine.execute(ctx, payload).await
+            }
         };
+        let execution = match &run_trace {
+            // Secrets the run resolves are registered with the recorder's redactor.
+            Some((_, recorder)) => redact::scope(recorder.redactor().clone(), flow_run).await,
+            None => flow_run.await,
+        };
+        if let Some((request, recorder)) = &run_trace {
+            let trace = match &execution {
+                Ok(execution) => match execution.status {
+                    FlowStatus::Completed => recorder.finish(RunStatus::Completed, None),
+                    FlowStatus::Waiting(_) => recorder.finish(RunStatus::Waiting, None),
+                },
+                Err(err) => recorder.finish(RunStatus::Failed, Some(&err.to_string())),
+            };
+            self.save_run_trace(request, trace).await;
+        }
         let execution = match execution {
             Ok(execution) => {
                 if let Some(recorder) = trace.as_ref()
@@ -638,8 +1091,11 @@ impl Adapter for PackFlowAdapter {
                 {
                     tracing::warn!(error = %write_err, "failed to write trace");
                 }
-                return Err(RunnerError::AdapterCall {
-                    reason: err.to_string(),
+                let reason = err.to_string();
+                return Err(if retry_config.redelivers(&err) {
+                    RunnerError::Transient { reason }
+                } else {
+                    RunnerError::AdapterCall { reason }
                 });
             }
         };
@@ -4,16 +4,19 @@ use async_trait::async_trait;
 use parking_lot::RwLock;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 #[derive(Default)]
 pub struct InMemoryStateHost {
     store: RwLock<HashMap<String, Value>>,
+    expiries: RwLock<HashMap<String, Instant>>,
 }
 
 impl InMemoryStateHost {
     pub fn new() -> Self {
         Self {
             store: RwLock::new(HashMap::new()),
+            expiries: RwLock::new(HashMap::new()),
         }
     }
 
@@ -28,16 +31,40 @@ impl InMemoryStateHost {
 #[async_trait]
 impl StateHost for InMemoryStateHost {
     async fn get_json(&self, key: &SessionKey) -> GResult<Option<Value>> {
-        Ok(self.store.read().get(&Self::key_of(key)).cloned())
+        let key = Self::key_of(key);
+        let expired = self
+            .expiries
+            .read()
+            .get(&key)
+            .is_some_and(|deadline| *deadline <= Instant::now());
+        if expired {
+            self.expiries.write().remove(&key);
+            self.store.write().remove(&key);
+            return Ok(None);
+        }
+        Ok(self.store.read().get(&key).cloned())
     }
 
     async fn set_json(&self, key: &SessionKey, value: Value) -> GResult<()> {
-        self.store.write().insert(Self::key_of(key), value);
+        let key = Self::key_of(key);
+        self.expiries.write().remove(&key);
+        self.store.write().insert(key, value);
+        Ok(())
+    }
+
+    async fn set_json_ttl(&self, key: &SessionKey, value: Value, ttl: Duration) -> GResult<()> {
+        let key = Self::key_of(key);
+        self.expiries
+            .write()
+            .insert(key.clone(), Instant::now() + ttl);
+        self.store.write().insert(key, value);
         Ok(())
     }
 
     async fn del(&self, key: &SessionKey) -> GResult<()> {
-        self.store.write().remove(&Self::key_of(key));
+        let key = Self::key_of(key);
+        self.expiries.write().remove(&key);
+        self.store.write().remove(&key);
         Ok(())
     }
 
@@ -48,7 +75,9 @@ impl StateHost for InMemoryStateHost {
             .filter(|k| k.starts_with(key_prefix))
             .cloned()
             .collect();
+        let mut expiries = self.expiries.write();
         for key in keys {
+            expiries.remove(&key);
             guard.remove(&key);
         }
         Ok(())
@@ -12,7 +12,8 @@ use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use serde_json::Value;
 
-use crate::storage::state::DynStateStore;
+use crate::storage::listing::Cursor;
+use crate::storage::state::{self, DynStateStore, HostStateStore};
 
 #[derive(Clone, Debug)]
 pub struct FaultConfig {
@@ -242,6 +243,49 @@ impl StateStore for FaultyStateStore {
     }
 }
 
+impl HostStateStore for FaultyStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        // Faults apply per key, so batches go through the single-key paths.
+        state::get_each(self, tenant, prefix, keys)
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        Ok(state::set_each(self, tenant, prefix, entries, ttl_secs))
+    }
+}
+
 fn should_fail(seed: u64, step: u64, rate: FaultRate) -> bool {
     if rate.denominator == 0 || rate.numerator == 0 {
         return false;
@@ -0,0 +1,439 @@
+//! Reading and extracting `.gtpack` archives with the compression, size and path
+//! checks shared by [`crate::pack::PackRuntime`] and the `gen-bindings` tooling.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use zip::{SUPPORTED_COMPRESSION_METHODS, ZipArchive};
+
+/// Largest uncompressed entry read or extracted from a gtpack by default.
+pub const DEFAULT_MAX_ENTRY_BYTES: u64 = 512 * 1024 * 1024;
+/// Most bytes extracted from one gtpack by default.
+pub const DEFAULT_MAX_TOTAL_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Caps applied to entries read from a gtpack, so a crafted archive cannot expand into
+/// more memory or disk than a real pack needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    /// Refuse entries whose declared (or actual) uncompressed size exceeds this.
+    pub max_entry_bytes: u64,
+    /// Stop extracting once the entries written add up to more than this.
+    pub max_total_bytes: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entry_bytes: DEFAULT_MAX_ENTRY_BYTES,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    /// Defaults, overridden by `GREENTIC_GTPACK_MAX_ENTRY_BYTES` and
+    /// `GREENTIC_GTPACK_MAX_TOTAL_BYTES`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_entry_bytes: env_bytes("GREENTIC_GTPACK_MAX_ENTRY_BYTES")
+                .unwrap_or(defaults.max_entry_bytes),
+            max_total_bytes: env_bytes("GREENTIC_GTPACK_MAX_TOTAL_BYTES")
+                .unwrap_or(defaults.max_total_bytes),
+        }
+    }
+}
+
+fn env_bytes(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+}
+
+/// Why an entry was left out of an extraction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Rooted at `/`, `\` or a drive letter.
+    Absolute,
+    /// Resolves outside the extraction dir, e.g. through `..`.
+    Escapes,
+    Symlink,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RejectReason::Absolute => "absolute path",
+            RejectReason::Escapes => "escapes the extraction dir",
+            RejectReason::Symlink => "symlink",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedEntry {
+    pub name: String,
+    pub reason: RejectReason,
+}
+
+/// What [`extract_to_dir`] wrote and what it refused to.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractionReport {
+    /// Files and directories written, relative to the extraction dir.
+    pub extracted: Vec<PathBuf>,
+    pub total_bytes: u64,
+    /// Unsafe entries that were not written.
+    pub rejected: Vec<RejectedEntry>,
+}
+
+impl ExtractionReport {
+    pub fn is_clean(&self) -> bool {
+        self.rejected.is_empty()
+    }
+
+    /// Fail with every rejected entry listed, if there are any.
+    pub fn ensure_clean(&self) -> Result<()> {
+        if self.is_clean() {
+            return Ok(());
+        }
+        let list = self
+            .rejected
+            .iter()
+            .map(|entry| format!("{} ({})", entry.name, entry.reason))
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!(
+            "refused {} unsafe entr{}: {list}",
+            self.rejected.len(),
+            if self.rejected.len() == 1 { "y" } else { "ies" }
+        );
+    }
+}
+
+pub fn open(path: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    ZipArchive::new(file).with_context(|| format!("{} is not a valid gtpack", path.display()))
+}
+
+/// Check entry `index` without decompressing it: its compression method must be one
+/// this build can decode and its declared size must fit `limits`.
+pub fn check_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    limits: &ArchiveLimits,
+) -> Result<()> {
+    let entry = archive
+        .by_index_raw(index)
+        .with_context(|| format!("failed to read entry #{index}"))?;
+    let method = entry.compression();
+    if !SUPPORTED_COMPRESSION_METHODS.contains(&method) {
+        bail!(
+            "entry {} uses unsupported compression method {method}",
+            entry.name()
+        );
+    }
+    if entry.size() > limits.max_entry_bytes {
+        bail!(
+            "entry {} declares {} bytes uncompressed, over the {} byte limit",
+            entry.name(),
+            entry.size(),
+            limits.max_entry_bytes
+        );
+    }
+    Ok(())
+}
+
+/// Read entry `name` into memory after [`check_entry`].
+pub fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    limits: &ArchiveLimits,
+) -> Result<Vec<u8>> {
+    let index = archive
+        .index_for_name(name)
+        .with_context(|| format!("entry {name} missing from archive"))?;
+    check_entry(archive, index, limits)?;
+    let entry = archive
+        .by_index(index)
+        .with_context(|| format!("failed to read entry {name}"))?;
+    let mut bytes = Vec::new();
+    copy_limited(entry, &mut bytes, name, limits)?;
+    Ok(bytes)
+}
+
+/// Copy a decompressing reader to `out`, failing once more than
+/// `limits.max_entry_bytes` come out, whatever size the entry declared.
+pub fn copy_limited<R: Read, W: io::Write>(
+    entry: R,
+    out: &mut W,
+    name: &str,
+    limits: &ArchiveLimits,
+) -> Result<u64> {
+    let mut limited = entry.take(limits.max_entry_bytes.saturating_add(1));
+    let written =
+        io::copy(&mut limited, out).with_context(|| format!("failed to decompress {name}"))?;
+    if written > limits.max_entry_bytes {
+        bail!(
+            "entry {name} expands past the {} byte limit",
+            limits.max_entry_bytes
+        );
+    }
+    Ok(written)
+}
+
+/// Extract every entry of the gtpack at `path` below `out_dir`.
+///
+/// Absolute paths, paths escaping `out_dir` and symlinks are not written but listed in
+/// the report's `rejected`, leaving the caller to decide whether the rest is usable.
+/// Entries breaking `limits` fail the extraction.
+pub fn extract_to_dir(
+    path: &Path,
+    out_dir: &Path,
+    limits: &ArchiveLimits,
+) -> Result<ExtractionReport> {
+    extract_matching(path, out_dir, limits, |_| true)
+}
+
+/// [`extract_to_dir`] restricted to the entries whose name satisfies `filter`.
+pub fn extract_matching(
+    path: &Path,
+    out_dir: &Path,
+    limits: &ArchiveLimits,
+    filter: impl Fn(&str) -> bool,
+) -> Result<ExtractionReport> {
+    let mut archive = open(path)?;
+    let mut report = ExtractionReport::default();
+    for index in 0..archive.len() {
+        if !archive.name_for_index(index).is_some_and(&filter) {
+            continue;
+        }
+        check_entry(&mut archive, index, limits)
+            .with_context(|| format!("refusing to extract {}", path.display()))?;
+        let entry = archive
+            .by_index(index)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let name = entry.name().to_string();
+        let enclosed = if entry.is_symlink() {
+            Err(RejectReason::Symlink)
+        } else if is_absolute(&name) {
+            Err(RejectReason::Absolute)
+        } else {
+            entry.enclosed_name().ok_or(RejectReason::Escapes)
+        };
+        let relative = match enclosed {
+            Ok(relative) => relative,
+            Err(reason) => {
+                report.rejected.push(RejectedEntry { name, reason });
+                continue;
+            }
+        };
+        let out_path = out_dir.join(&relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)
+                .with_context(|| format!("failed to create {}", out_path.display()))?;
+            report.extracted.push(relative);
+            continue;
+        }
+
+        let remaining = limits.max_total_bytes.saturating_sub(report.total_bytes);
+        if entry.size() > remaining {
+            bail!(
+                "extracting {name} would take {} past its {} byte budget",
+                path.display(),
+                limits.max_total_bytes
+            );
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let mut outfile = File::create(&out_path)
+            .with_context(|| format!("failed to create {}", out_path.display()))?;
+        let entry_limits = ArchiveLimits {
+            max_entry_bytes: limits.max_entry_bytes.min(remaining),
+            ..*limits
+        };
+        report.total_bytes += copy_limited(entry, &mut outfile, &name, &entry_limits)
+            .with_context(|| format!("failed to write {}", out_path.display()))?;
+        report.extracted.push(relative);
+    }
+    Ok(report)
+}
+
+fn is_absolute(name: &str) -> bool {
+    name.starts_with('/') || name.starts_with('\\') || name.as_bytes().get(1) == Some(&b':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::CompressionMethod;
+    use zip::ZipWriter;
+    use zip::write::FileOptions;
+
+    fn write_zip(path: &Path, method: CompressionMethod, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = FileOptions::<()>::default().compression_method(method);
+        for (name, content) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn zstd_entries_round_trip() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("zstd.gtpack");
+        let payload = b"name: demo\n".repeat(64);
+        write_zip(
+            &gtpack,
+            CompressionMethod::Zstd,
+            &[("pack.yaml", payload.as_slice())],
+        );
+
+        let mut archive = open(&gtpack)?;
+        assert_eq!(
+            read_entry(&mut archive, "pack.yaml", &ArchiveLimits::default())?,
+            payload
+        );
+        let out = temp.path().join("out");
+        extract_to_dir(&gtpack, &out, &ArchiveLimits::default())?;
+        assert_eq!(fs::read(out.join("pack.yaml"))?, payload);
+        Ok(())
+    }
+
+    #[test]
+    fn entries_over_the_size_cap_are_refused() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("big.gtpack");
+        write_zip(
+            &gtpack,
+            CompressionMethod::Deflated,
+            &[("components/big.wasm", &[0u8; 4096][..])],
+        );
+        let limits = ArchiveLimits {
+            max_entry_bytes: 1024,
+            ..ArchiveLimits::default()
+        };
+
+        let mut archive = open(&gtpack)?;
+        let err = read_entry(&mut archive, "components/big.wasm", &limits).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("components/big.wasm declares 4096 bytes uncompressed"),
+            "{err:#}"
+        );
+        let out = temp.path().join("out");
+        let err = extract_to_dir(&gtpack, &out, &limits).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("over the 1024 byte limit"),
+            "{err:#}"
+        );
+        assert!(!out.join("components/big.wasm").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_compression_names_entry_and_method() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("odd.gtpack");
+        write_zip(
+            &gtpack,
+            CompressionMethod::Stored,
+            &[("pack.yaml", &b"name"[..])],
+        );
+        // Rewrite the method in the local and central headers to an unassigned id.
+        let mut bytes = fs::read(&gtpack)?;
+        for (signature, offset) in [(b"PK\x03\x04", 8), (b"PK\x01\x02", 10)] {
+            let at = bytes
+                .windows(4)
+                .position(|window| window == signature)
+                .expect("zip header");
+            bytes[at + offset..at + offset + 2].copy_from_slice(&77u16.to_le_bytes());
+        }
+        fs::write(&gtpack, bytes)?;
+
+        let mut archive = open(&gtpack)?;
+        let err = read_entry(&mut archive, "pack.yaml", &ArchiveLimits::default()).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("entry pack.yaml uses unsupported compression method"),
+            "{err:#}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unsafe_entries_are_rejected_and_reported() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("slip.gtpack");
+        let mut zip = ZipWriter::new(File::create(&gtpack)?);
+        let options = FileOptions::<()>::default();
+        for name in ["pack.yaml", "../escape.txt", "/etc/absolute.txt"] {
+            zip.start_file(name, options)?;
+            zip.write_all(b"data")?;
+        }
+        zip.add_symlink("assets/link", "/etc/passwd", options)?;
+        zip.finish()?;
+
+        let out = temp.path().join("out");
+        let report = extract_to_dir(&gtpack, &out, &ArchiveLimits::default())?;
+        assert_eq!(report.extracted, vec![PathBuf::from("pack.yaml")]);
+        assert_eq!(
+            report.rejected,
+            vec![
+                RejectedEntry {
+                    name: "../escape.txt".into(),
+                    reason: RejectReason::Escapes,
+                },
+                RejectedEntry {
+                    name: "/etc/absolute.txt".into(),
+                    reason: RejectReason::Absolute,
+                },
+                RejectedEntry {
+                    name: "assets/link".into(),
+                    reason: RejectReason::Symlink,
+                },
+            ]
+        );
+        assert!(!temp.path().join("escape.txt").exists());
+        assert!(fs::symlink_metadata(out.join("assets/link")).is_err());
+
+        let err = report.ensure_clean().unwrap_err().to_string();
+        assert!(err.starts_with("refused 3 unsafe entries"), "{err}");
+        assert!(
+            err.contains("../escape.txt (escapes the extraction dir)"),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extraction_stops_at_the_total_budget() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("budget.gtpack");
+        write_zip(
+            &gtpack,
+            CompressionMethod::Deflated,
+            &[("a.bin", &[1u8; 600][..]), ("b.bin", &[2u8; 600][..])],
+        );
+        let limits = ArchiveLimits {
+            max_total_bytes: 1000,
+            ..ArchiveLimits::default()
+        };
+
+        let err = extract_to_dir(&gtpack, &temp.path().join("out"), &limits).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("extracting b.bin would take"),
+            "{err:#}"
+        );
+        assert!(
+            format!("{err:#}").contains("past its 1000 byte budget"),
+            "{err:#}"
+        );
+        Ok(())
+    }
+}
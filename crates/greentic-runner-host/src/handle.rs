@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::host::RunnerHost;
+use crate::http::health;
+use crate::runner::ListenAddr;
+
+/// Running HTTP host returned by [`crate::start`].
+///
+/// Dropping the handle leaves the server running; call [`RunnerHandle::stop`] (or
+/// trigger shutdown and [`RunnerHandle::join`]) to tear it down.
+pub struct RunnerHandle {
+    addrs: Vec<ListenAddr>,
+    host: Arc<RunnerHost>,
+    shutdown: ShutdownTrigger,
+    task: JoinHandle<Result<()>>,
+}
+
+/// Cloneable trigger that asks a [`RunnerHandle`] to shut down.
+#[derive(Clone, Default)]
+pub struct ShutdownTrigger {
+    notify: Arc<Notify>,
+}
+
+impl ShutdownTrigger {
+    pub fn trigger(&self) {
+        // `notify_one` stores a permit, so a trigger before the server waits is not lost.
+        self.notify.notify_one();
+    }
+
+    pub(crate) async fn triggered(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl RunnerHandle {
+    pub(crate) fn new(
+        addrs: Vec<ListenAddr>,
+        host: Arc<RunnerHost>,
+        shutdown: ShutdownTrigger,
+        task: JoinHandle<Result<()>>,
+    ) -> Self {
+        Self {
+            addrs,
+            host,
+            shutdown,
+            task,
+        }
+    }
+
+    /// Bound listener addresses, in listener order (port `0` resolved).
+    pub fn local_addrs(&self) -> &[ListenAddr] {
+        &self.addrs
+    }
+
+    pub fn host(&self) -> &Arc<RunnerHost> {
+        &self.host
+    }
+
+    pub fn shutdown_trigger(&self) -> ShutdownTrigger {
+        self.shutdown.clone()
+    }
+
+    /// Every configured tenant is loaded and passed preflight.
+    pub fn is_ready(&self) -> bool {
+        let readiness = health::readiness(&self.host.active_packs(), &self.host.health_state());
+        readiness.is_ready()
+            && self
+                .host
+                .tenant_configs()
+                .keys()
+                .all(|tenant| readiness.tenants.contains_key(tenant))
+    }
+
+    /// Wait until [`RunnerHandle::is_ready`] holds, failing after `timeout`.
+    pub async fn wait_ready(&self, timeout: Duration) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.is_ready() {
+            if self.task.is_finished() {
+                bail!("runner stopped before becoming ready");
+            }
+            if tokio::time::Instant::now() >= deadline {
+                bail!("runner not ready after {timeout:?}");
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    }
+
+    /// Wait for the server to exit, either from a listener error or a shutdown trigger.
+    pub async fn join(self) -> Result<()> {
+        self.task.await.context("runner task failed")?
+    }
+
+    pub async fn stop(self) -> Result<()> {
+        self.shutdown.trigger();
+        self.join().await
+    }
+}
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow, bail};
+use arc_swap::ArcSwap;
+use parking_lot::Mutex;
 use serde_json::Value;
+use tokio::task::JoinHandle;
 
 use crate::activity::Activity;
 use crate::boot;
@@ -12,10 +16,13 @@ use crate::engine::host::{SessionHost, StateHost};
 use crate::engine::runtime::IngressEnvelope;
 use crate::http::health::HealthState;
 use crate::pack::PackRuntime;
-use crate::runner::adapt_timer;
 use crate::runner::engine::FlowEngine;
 use crate::runtime::{ActivePacks, TenantRuntime};
-use crate::secrets::{DynSecretsManager, default_manager};
+use crate::secrets::health::{self, AlwaysHealthy, health_interval_from_env};
+use crate::secrets::{DynSecretsHealthCheck, DynSecretsManager, default_manager};
+use crate::storage::expiry::{DEFAULT_SWEEP_INTERVAL, spawn_sweeper};
+use crate::storage::metrics::{StoreMetricsConfig, instrument_session, instrument_state};
+use crate::storage::versioned::{StateMigrations, apply_migrations};
 use crate::storage::{
     DynSessionStore, DynStateStore, new_session_store, new_state_store, session_host_from,
     state_host_from,
@@ -27,6 +34,33 @@ use crate::wasi::RunnerWasiPolicy;
 pub struct TelemetryCfg {
     pub config: greentic_telemetry::TelemetryConfig,
     pub export: greentic_telemetry::export::ExportConfig,
+    /// `OTEL_*` variables set for the exporter unless the process already has them.
+    pub otel_env: Vec<(String, String)>,
+}
+
+#[cfg(feature = "telemetry")]
+impl TelemetryCfg {
+    /// Pipeline for the `[trace]` section, or `None` when it exports nothing.
+    pub fn from_trace_export(trace: &crate::trace::TraceExportConfig) -> Option<Self> {
+        use crate::trace::TraceExporter;
+        use greentic_telemetry::export::{ExportConfig, ExportMode};
+
+        let mut export = ExportConfig::json_default();
+        export.mode = match trace.exporter {
+            TraceExporter::None => return None,
+            TraceExporter::Stdout => ExportMode::JsonStdout,
+            TraceExporter::OtlpGrpc => ExportMode::OtlpGrpc,
+            TraceExporter::OtlpHttp => ExportMode::OtlpHttp,
+        };
+        export.endpoint = trace.traces_endpoint();
+        Some(Self {
+            config: greentic_telemetry::TelemetryConfig {
+                service_name: trace.service_name().to_string(),
+            },
+            export,
+            otel_env: trace.otel_env(),
+        })
+    }
 }
 #[cfg(not(feature = "telemetry"))]
 #[derive(Clone, Debug)]
@@ -39,6 +73,13 @@ pub struct HostBuilder {
     telemetry: Option<TelemetryCfg>,
     wasi_policy: RunnerWasiPolicy,
     secrets: Option<DynSecretsManager>,
+    secrets_health: Option<DynSecretsHealthCheck>,
+    secrets_health_interval: Option<Duration>,
+    session_store: Option<DynSessionStore>,
+    state_store: Option<DynStateStore>,
+    state_migrations: StateMigrations,
+    store_metrics: StoreMetricsConfig,
+    state_sweep_interval: Duration,
 }
 
 impl HostBuilder {
@@ -49,6 +90,13 @@ impl HostBuilder {
             telemetry: None,
             wasi_policy: RunnerWasiPolicy::default(),
             secrets: None,
+            secrets_health: None,
+            secrets_health_interval: health_interval_from_env(),
+            session_store: None,
+            state_store: None,
+            state_migrations: StateMigrations::default(),
+            store_metrics: StoreMetricsConfig::default(),
+            state_sweep_interval: DEFAULT_SWEEP_INTERVAL,
         }
     }
 
@@ -73,26 +121,77 @@ impl HostBuilder {
         self
     }
 
+    /// Probe the secrets backend with `check`; without one the backend counts as healthy.
+    pub fn with_secrets_health_check(mut self, check: DynSecretsHealthCheck) -> Self {
+        self.secrets_health = Some(check);
+        self
+    }
+
+    /// How often the secrets backend is probed after start; `None` probes only at start.
+    pub fn with_secrets_health_interval(mut self, interval: Option<Duration>) -> Self {
+        self.secrets_health_interval = interval;
+        self
+    }
+
+    /// Keep sessions in `store` instead of the default in-memory store.
+    pub fn with_session_store(mut self, store: DynSessionStore) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    /// Share `store` across tenants instead of the default in-memory store.
+    pub fn with_state_store(mut self, store: DynStateStore) -> Self {
+        self.state_store = Some(store);
+        self
+    }
+
+    /// Version state documents and upgrade older ones on read with `migrations`.
+    pub fn with_state_migrations(mut self, migrations: StateMigrations) -> Self {
+        self.state_migrations = migrations;
+        self
+    }
+
+    /// Configure store latency metrics and the slow-operation log threshold.
+    pub fn with_store_metrics(mut self, config: StoreMetricsConfig) -> Self {
+        self.store_metrics = config;
+        self
+    }
+
+    /// How often expired state keys are swept from stores without native expiry.
+    pub fn with_state_sweep_interval(mut self, interval: Duration) -> Self {
+        self.state_sweep_interval = interval;
+        self
+    }
+
     pub fn build(self) -> Result<RunnerHost> {
         if self.configs.is_empty() {
             bail!("at least one tenant configuration is required");
         }
         let wasi_policy = Arc::new(self.wasi_policy);
-        let configs = self
+        let configs: HashMap<String, Arc<HostConfig>> = self
             .configs
             .into_iter()
             .map(|(tenant, cfg)| (tenant, Arc::new(cfg)))
             .collect();
-        let session_store = new_session_store();
-        let session_host = session_host_from(Arc::clone(&session_store));
-        let state_store = new_state_store();
+        let session_store = self.session_store.unwrap_or_else(new_session_store);
+        let session_host = instrument_session(
+            session_host_from(Arc::clone(&session_store)),
+            &self.store_metrics,
+        );
+        let state_store = instrument_state(
+            apply_migrations(
+                self.state_store.unwrap_or_else(new_state_store),
+                self.state_migrations,
+            ),
+            &self.store_metrics,
+        );
         let state_host = state_host_from(Arc::clone(&state_store));
         let secrets = match self.secrets {
             Some(manager) => manager,
             None => default_manager().context("failed to initialise default secrets backend")?,
         };
         Ok(RunnerHost {
-            configs,
+            configs: ArcSwap::from_pointee(configs),
             active: Arc::new(ActivePacks::new()),
             health: Arc::new(HealthState::new()),
             session_store,
@@ -101,6 +200,13 @@ impl HostBuilder {
             state_host,
             wasi_policy,
             secrets_manager: secrets,
+            secrets_health: self
+                .secrets_health
+                .unwrap_or_else(|| Arc::new(AlwaysHealthy)),
+            secrets_health_interval: self.secrets_health_interval,
+            secrets_monitor: Mutex::new(None),
+            state_sweep_interval: self.state_sweep_interval,
+            state_sweeper: Mutex::new(None),
             #[cfg(feature = "telemetry")]
             telemetry: self.telemetry,
         })
@@ -115,7 +221,7 @@ impl Default for HostBuilder {
 
 /// Runtime host that manages tenant-bound packs and flow execution.
 pub struct RunnerHost {
-    configs: HashMap<String, Arc<HostConfig>>,
+    configs: ArcSwap<HashMap<String, Arc<HostConfig>>>,
     active: Arc<ActivePacks>,
     health: Arc<HealthState>,
     session_store: DynSessionStore,
@@ -124,6 +230,11 @@ pub struct RunnerHost {
     state_host: Arc<dyn StateHost>,
     wasi_policy: Arc<RunnerWasiPolicy>,
     secrets_manager: DynSecretsManager,
+    secrets_health: DynSecretsHealthCheck,
+    secrets_health_interval: Option<Duration>,
+    secrets_monitor: Mutex<Option<JoinHandle<()>>>,
+    state_sweep_interval: Duration,
+    state_sweeper: Mutex<Option<JoinHandle<()>>>,
     #[cfg(feature = "telemetry")]
     telemetry: Option<TelemetryCfg>,
 }
@@ -144,11 +255,40 @@ impl RunnerHost {
         {
             boot::init(&self.health, None)?;
         }
+        // A failing backend does not stop the host; it is reported as not ready.
+        let _ = self.check_secrets().await;
+        if let Some(interval) = self.secrets_health_interval {
+            let mut monitor = self.secrets_monitor.lock();
+            if monitor.is_none() {
+                *monitor = Some(health::spawn_monitor(
+                    Arc::clone(&self.secrets_health),
+                    Arc::clone(&self.health),
+                    interval,
+                ));
+            }
+        }
+        let mut sweeper = self.state_sweeper.lock();
+        if sweeper.is_none() {
+            *sweeper = Some(spawn_sweeper(self.state_sweep_interval));
+        }
         Ok(())
     }
 
+    /// Probe the secrets backend now and record the outcome for `/readyz` and metrics.
+    pub async fn check_secrets(&self) -> Result<()> {
+        health::probe(self.secrets_health.as_ref(), &self.health)
+            .await
+            .map_err(|err| anyhow!("secrets backend health check failed: {err}"))
+    }
+
     pub async fn stop(&self) -> Result<()> {
-        self.active.replace(HashMap::new());
+        if let Some(monitor) = self.secrets_monitor.lock().take() {
+            monitor.abort();
+        }
+        if let Some(sweeper) = self.state_sweeper.lock().take() {
+            sweeper.abort();
+        }
+        self.active.shutdown().await;
         Ok(())
     }
 
@@ -164,7 +304,7 @@ impl RunnerHost {
             .with_context(|| format!("failed to load tenant {tenant}"))?;
         let mut next = (*self.active.snapshot()).clone();
         next.insert(tenant.to_string(), runtime);
-        self.active.replace(next);
+        self.active.replace_and_stop(next).await;
         tracing::info!(tenant, pack = %pack_path.display(), "pack loaded");
         Ok(())
     }
@@ -255,7 +395,17 @@ impl RunnerHost {
     }
 
     pub fn tenant_configs(&self) -> HashMap<String, Arc<HostConfig>> {
-        self.configs.clone()
+        self.configs.load().as_ref().clone()
+    }
+
+    /// Swap the tenant configurations used by subsequent pack loads and reloads.
+    ///
+    /// Returns the previous set so callers can roll back when a reload fails.
+    pub fn replace_tenant_configs(
+        &self,
+        configs: HashMap<String, Arc<HostConfig>>,
+    ) -> HashMap<String, Arc<HostConfig>> {
+        self.configs.swap(Arc::new(configs)).as_ref().clone()
     }
 
     async fn prepare_runtime(
@@ -266,6 +416,7 @@ impl RunnerHost {
     ) -> Result<Arc<TenantRuntime>> {
         let config = self
             .configs
+            .load()
             .get(tenant)
             .cloned()
             .with_context(|| format!("tenant {tenant} not registered"))?;
@@ -289,8 +440,6 @@ impl RunnerHost {
             self.secrets_manager(),
         )
         .await?;
-        let timers = adapt_timer::spawn_timers(Arc::clone(&runtime))?;
-        runtime.register_timers(timers);
         Ok(runtime)
     }
 }
@@ -1,12 +1,22 @@
+use std::path::PathBuf;
+
 use axum::Json;
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use serde_json::json;
+use serde::Deserialize;
+use serde_json::{Value, json};
 use time::format_description::well_known::Rfc3339;
 
+use crate::engine::rate_limit::ingress_limits;
 use crate::http::auth::AdminGuard;
+use crate::http::health::secrets_health_json;
 use crate::runner::ServerState;
+use crate::runtime::TenantRuntime;
+use crate::secrets::health::SecretsHealthSnapshot;
+use crate::secrets::rotation::{self, SecretRotation};
+use crate::secrets::secret_name;
+use crate::storage::metrics::store_metrics;
 
 pub async fn status(AdminGuard: AdminGuard, State(state): State<ServerState>) -> impl IntoResponse {
     let snapshot = state.active.snapshot();
@@ -78,3 +88,337 @@ pub async fn reload(AdminGuard: AdminGuard, State(state): State<ServerState>) ->
         )
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ReloadPackRequest {
+    /// Pack archive or directory replacing the tenant's pack with the same pack id.
+    pub path: PathBuf,
+    /// Digest reported for the new pack; defaults to the archive's sha256.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+pub async fn reload_pack(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+    Path(tenant): Path<String>,
+    Json(request): Json<ReloadPackRequest>,
+) -> impl IntoResponse {
+    if state.active.load(&tenant).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("tenant {tenant} is not loaded") })),
+        );
+    }
+    match state
+        .active
+        .reload_pack(&tenant, &request.path, request.digest)
+        .await
+    {
+        Ok(reloaded) => {
+            let metadata = reloaded.pack.metadata();
+            tracing::info!(
+                tenant = %tenant,
+                pack_id = %metadata.pack_id,
+                "pack.reload_pack.completed"
+            );
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "tenant": tenant,
+                    "pack_id": metadata.pack_id,
+                    "version": metadata.version,
+                    "digest": reloaded.digest,
+                })),
+            )
+        }
+        Err(err) => {
+            tracing::warn!(
+                tenant = %tenant,
+                error = %format!("{err:#}"),
+                "pack.reload_pack.failed"
+            );
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": format!("{err:#}") })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct InvalidateSecretsRequest {
+    /// Secret key to drop; every cached secret for the tenant when omitted.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+pub async fn invalidate_secrets(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+    Path(tenant): Path<String>,
+    request: Option<Json<InvalidateSecretsRequest>>,
+) -> impl IntoResponse {
+    let Some(runtime) = state.active.load(&tenant) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("tenant {tenant} is not loaded") })),
+        );
+    };
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let cache = runtime.secrets_cache();
+    let invalidated = match request.key.as_deref() {
+        Some(key) => usize::from(cache.invalidate(&tenant, key)),
+        None => cache.invalidate_tenant(&tenant),
+    };
+    tracing::info!(
+        tenant = %tenant,
+        key = request.key.as_deref().unwrap_or("*"),
+        invalidated,
+        "secrets.cache.invalidated"
+    );
+    (
+        StatusCode::OK,
+        Json(json!({ "tenant": tenant, "invalidated": invalidated })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecretRotatedRequest {
+    /// Secret key whose value changed.
+    pub key: String,
+    /// Tenant owning the secret; every loaded tenant when omitted.
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+/// Announce that a secret was rotated outside the runner, so cached values and keys
+/// derived from it are dropped before their TTL.
+pub async fn secret_rotated(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+    Json(request): Json<SecretRotatedRequest>,
+) -> impl IntoResponse {
+    let key = request.key.trim();
+    if key.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "key must not be empty" })),
+        );
+    }
+    let tenants = match request.tenant {
+        Some(tenant) if state.active.load(&tenant).is_none() => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": format!("tenant {tenant} is not loaded") })),
+            );
+        }
+        Some(tenant) => vec![tenant],
+        None => {
+            let mut tenants = state.active.snapshot().keys().cloned().collect::<Vec<_>>();
+            tenants.sort();
+            tenants
+        }
+    };
+    for tenant in &tenants {
+        rotation::publish(SecretRotation::new(tenant.as_str(), secret_name(key)));
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "key": key, "tenants": tenants })),
+    )
+}
+
+/// Cancel an in-flight operator invocation by the `correlation_id` it was sent with.
+/// The component sees `should-cancel` flip on its next epoch tick.
+pub async fn abort_invocation(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+    Path((tenant, invocation_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let Some(runtime) = state.active.load(&tenant) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("tenant {tenant} is not loaded") })),
+        );
+    };
+    if !runtime.invocations().abort(&invocation_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": format!("no invocation {invocation_id} in flight for tenant {tenant}")
+            })),
+        );
+    }
+    tracing::info!(
+        tenant = %tenant,
+        invocation_id = %invocation_id,
+        "invocation.abort.requested"
+    );
+    (
+        StatusCode::OK,
+        Json(json!({ "tenant": tenant, "invocation_id": invocation_id, "aborted": true })),
+    )
+}
+
+/// One line per loaded tenant; `/admin/tenants/{tenant}/stats` has the details.
+pub async fn tenants(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+) -> impl IntoResponse {
+    let snapshot = state.active.snapshot();
+    let mut tenants = snapshot
+        .iter()
+        .map(|(tenant, runtime)| {
+            let packs = runtime.packs().len();
+            let components = runtime
+                .packs()
+                .iter()
+                .map(|pack| pack.component_count())
+                .sum::<usize>();
+            let operator = runtime.operator_metrics().snapshot();
+            json!({
+                "tenant": tenant,
+                "packs": packs,
+                "components": components,
+                "invocations": operator.invoke_attempts,
+                "inflight_invocations": operator.inflight,
+                "loaded_at": runtime.loaded_at().format(&Rfc3339).ok(),
+            })
+        })
+        .collect::<Vec<_>>();
+    tenants.sort_by(|a, b| a["tenant"].as_str().cmp(&b["tenant"].as_str()));
+    Json(json!({ "tenants": tenants }))
+}
+
+/// Capacity-planning numbers for one tenant: its packs, component and memory cache
+/// usage, operator counters, contract cache stats, in-flight work, the levels of its
+/// ingress rate limit buckets and the health of the secrets backend it reads from.
+pub async fn tenant_stats(
+    AdminGuard: AdminGuard,
+    State(state): State<ServerState>,
+    Path(tenant): Path<String>,
+) -> impl IntoResponse {
+    let Some(runtime) = state.active.load(&tenant) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("tenant {tenant} is not loaded") })),
+        );
+    };
+    let http_in_flight = state
+        .http_metrics
+        .in_flight()
+        .into_iter()
+        .find(|(name, _)| *name == tenant)
+        .map(|(_, count)| count)
+        .unwrap_or_default();
+    (
+        StatusCode::OK,
+        Json(tenant_stats_document(
+            &runtime,
+            http_in_flight,
+            &state.health.secrets_health(),
+        )),
+    )
+}
+
+fn tenant_stats_document(
+    runtime: &TenantRuntime,
+    http_in_flight: usize,
+    secrets: &SecretsHealthSnapshot,
+) -> Value {
+    let mut components = 0;
+    let mut compiled_components = 0;
+    let mut cache_bytes = 0;
+    let mut pinned_bytes = 0;
+    let packs = runtime
+        .packs()
+        .iter()
+        .zip(runtime.pack_digests())
+        .map(|(pack, digest)| {
+            let metadata = pack.metadata();
+            let memory = pack.cache().memory_stats();
+            components += pack.component_count();
+            compiled_components += pack.compiled_component_count();
+            cache_bytes += memory.total_bytes;
+            pinned_bytes += memory.pinned_bytes;
+            json!({
+                "pack_id": metadata.pack_id,
+                "version": metadata.version,
+                "digest": digest,
+                "components": pack.component_count(),
+                "compiled_components": pack.compiled_component_count(),
+                "memory_cache": {
+                    "entries": memory.entries,
+                    "total_bytes": memory.total_bytes,
+                    "pinned_bytes": memory.pinned_bytes,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    let operator = runtime.operator_metrics().snapshot();
+    let contracts = runtime.contract_cache_stats();
+    let mut stores = serde_json::Map::new();
+    for sample in store_metrics().tenant(runtime.tenant()) {
+        let store = stores.entry(sample.store).or_insert_with(|| json!({}));
+        store[sample.op.as_str()] = json!({
+            "calls": sample.metrics.calls,
+            "hits": sample.metrics.hits,
+            "misses": sample.metrics.misses,
+            "errors": sample.metrics.errors,
+            "slow": sample.metrics.slow,
+            "latency": {
+                "count": sample.metrics.latency.count,
+                "sum_seconds": sample.metrics.latency.sum_seconds,
+            },
+        });
+    }
+    let quota = runtime.config().state_store_policy.quota;
+    // The usage ledger is only maintained for tenants with a quota.
+    let state = quota.is_limited().then(|| {
+        json!({
+            "quota": quota,
+            "usage": runtime.state_usage().ok(),
+        })
+    });
+    json!({
+        "tenant": runtime.tenant(),
+        "loaded_at": runtime.loaded_at().format(&Rfc3339).ok(),
+        "packs": packs,
+        "components": components,
+        "compiled_components": compiled_components,
+        "memory_cache": {
+            "total_bytes": cache_bytes,
+            "pinned_bytes": pinned_bytes,
+        },
+        "operator": {
+            "resolve_attempts": operator.resolve_attempts,
+            "resolve_errors": operator.resolve_errors,
+            "invoke_attempts": operator.invoke_attempts,
+            "invoke_errors": operator.invoke_errors,
+            "invoke_timeouts": operator.invoke_timeouts,
+            "throttled": operator.throttled,
+            "cbor_decode_errors": operator.cbor_decode_errors,
+            "invoke_latency": {
+                "count": operator.invoke_latency.count,
+                "sum_seconds": operator.invoke_latency.sum_seconds,
+            },
+        },
+        "contract_cache": {
+            "hits": contracts.hits,
+            "misses": contracts.misses,
+            "evictions": contracts.evictions,
+            "entries": contracts.entries,
+            "total_bytes": contracts.total_bytes,
+        },
+        "inflight": {
+            "invocations": operator.inflight,
+            "abortable_invocations": runtime.invocations().len(),
+            "http_requests": http_in_flight,
+        },
+        "state": state,
+        "stores": stores,
+        "ingress_rate_limits": ingress_limits().tenant(runtime.tenant()),
+        "secrets_backend": secrets_health_json(secrets),
+    })
+}
@@ -1,13 +1,19 @@
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::Error;
 use axum::Json;
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::response::IntoResponse;
+use greentic_secrets_lib::SecretError;
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 
+use crate::preflight::{PreflightReport, TenantPreflight};
 use crate::runner::ServerState;
+use crate::runtime::ActivePacks;
+use crate::secrets::health::SecretsHealthSnapshot;
 
 #[derive(Default)]
 pub struct HealthState {
@@ -20,6 +26,8 @@ pub struct HealthState {
 struct HealthMeta {
     last_reload: Option<OffsetDateTime>,
     last_error: Option<String>,
+    preflight: Option<PreflightReport>,
+    secrets: SecretsHealthSnapshot,
 }
 
 impl HealthState {
@@ -35,8 +43,10 @@ impl HealthState {
         self.telemetry_ready.store(true, Ordering::SeqCst);
     }
 
+    /// Mark secrets ready unless the last backend probe failed.
     pub fn mark_secrets_ready(&self) {
-        self.secrets_ready.store(true, Ordering::SeqCst);
+        let healthy = self.meta.lock().secrets.is_healthy();
+        self.secrets_ready.store(healthy, Ordering::SeqCst);
     }
 
     /// Mark all readiness checks as healthy.
@@ -56,6 +66,28 @@ impl HealthState {
         meta.last_error = Some(err.to_string());
     }
 
+    /// Store the latest preflight report; tenants with failures report not-ready.
+    pub fn record_preflight(&self, report: PreflightReport) {
+        self.meta.lock().preflight = Some(report);
+    }
+
+    pub fn preflight(&self) -> Option<PreflightReport> {
+        self.meta.lock().preflight.clone()
+    }
+
+    /// Record a secrets backend probe; a failure marks secrets not ready until the
+    /// next successful one.
+    pub fn record_secrets_check(&self, result: Result<(), &SecretError>) {
+        let mut meta = self.meta.lock();
+        meta.secrets.record(result);
+        self.secrets_ready
+            .store(meta.secrets.is_healthy(), Ordering::SeqCst);
+    }
+
+    pub fn secrets_health(&self) -> SecretsHealthSnapshot {
+        self.meta.lock().secrets.clone()
+    }
+
     pub fn snapshot(&self) -> HealthSnapshot {
         let meta = self.meta.lock().clone();
         HealthSnapshot {
@@ -92,3 +124,77 @@ pub async fn handler(State(state): State<ServerState>) -> impl IntoResponse {
         "last_error": snapshot.last_error,
     }))
 }
+
+/// Per-tenant readiness derived from the latest preflight report, plus the secrets
+/// backend health every tenant depends on.
+pub struct Readiness {
+    pub tenants: BTreeMap<String, TenantPreflight>,
+    pub secrets: SecretsHealthSnapshot,
+}
+
+impl Readiness {
+    /// At least one tenant is loaded, no loaded tenant failed preflight and the last
+    /// secrets backend probe succeeded.
+    pub fn is_ready(&self) -> bool {
+        !self.tenants.is_empty()
+            && self.tenants.values().all(TenantPreflight::is_ready)
+            && self.secrets.is_healthy()
+    }
+}
+
+/// JSON view of a secrets backend probe history.
+pub fn secrets_health_json(secrets: &SecretsHealthSnapshot) -> serde_json::Value {
+    serde_json::json!({
+        "healthy": secrets.is_healthy(),
+        "checks": secrets.checks,
+        "last_success": secrets.last_success.and_then(|ts| ts.format(&Rfc3339).ok()),
+        "consecutive_failures": secrets.consecutive_failures,
+        "last_error": secrets.last_error,
+    })
+}
+
+/// Loaded tenants without a preflight entry (e.g. before the first report) count as ready.
+pub fn readiness(active: &ActivePacks, health: &HealthState) -> Readiness {
+    let mut report = health.preflight().unwrap_or_default();
+    let tenants = active
+        .snapshot()
+        .keys()
+        .map(|tenant| {
+            let result = report.tenants.remove(tenant).unwrap_or_default();
+            (tenant.clone(), result)
+        })
+        .collect();
+    Readiness {
+        tenants,
+        secrets: health.secrets_health(),
+    }
+}
+
+pub async fn ready_handler(State(state): State<ServerState>) -> impl IntoResponse {
+    let readiness = readiness(&state.active, &state.health);
+    let ready = readiness.is_ready();
+    let tenants = readiness
+        .tenants
+        .iter()
+        .map(|(tenant, result)| {
+            let value = serde_json::json!({
+                "ready": result.is_ready(),
+                "failures": result.failures,
+            });
+            (tenant.clone(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "tenants": tenants,
+            "secrets": secrets_health_json(&readiness.secrets),
+        })),
+    )
+}
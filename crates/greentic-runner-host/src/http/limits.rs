@@ -0,0 +1,52 @@
+//! Per-tenant in-flight limits for tenant-facing routes.
+//!
+//! The limit is read from the tenant's current runtime config on every request, so a
+//! reload that changes `rate_limits.max_in_flight` applies to the next request.
+
+use axum::Json;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+
+use crate::runner::ServerState;
+
+const RETRY_AFTER_SECS: &str = "1";
+
+pub async fn limit_in_flight(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let tenant = state.routing.resolve(&parts, &state.active);
+    let request = Request::from_parts(parts, body);
+    // Unroutable requests and unloaded tenants are rejected by the handler's tenant
+    // extractor; only loaded tenants get an in-flight entry.
+    let Ok(tenant) = tenant else {
+        return next.run(request).await;
+    };
+    let Some(limit) = state
+        .active
+        .load(&tenant)
+        .map(|runtime| runtime.config().rate_limits.max_in_flight)
+    else {
+        return next.run(request).await;
+    };
+    let Some(_guard) = state.http_metrics.try_begin(&tenant, limit) else {
+        tracing::warn!(tenant = %tenant, ?limit, "tenant in-flight limit reached");
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, RETRY_AFTER_SECS)],
+            Json(json!({
+                "error": format!("too many in-flight requests for tenant {tenant}"),
+                "tenant": tenant,
+                "max_in_flight": limit,
+            })),
+        )
+            .into_response();
+    };
+    next.run(request).await
+}
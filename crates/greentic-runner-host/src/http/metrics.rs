@@ -0,0 +1,796 @@
+//! Prometheus exposition for runner metrics served on `/metrics`.
+//!
+//! Metric and label names below are part of the scrape contract; do not rename
+//! them without a deprecation window.
+//!
+//! | metric | type | labels |
+//! |---|---|---|
+//! | `greentic_operator_resolve_attempts_total` | counter | `tenant` |
+//! | `greentic_operator_resolve_errors_total` | counter | `tenant` |
+//! | `greentic_operator_invoke_attempts_total` | counter | `tenant` |
+//! | `greentic_operator_invoke_errors_total` | counter | `tenant` |
+//! | `greentic_operator_invoke_timeouts_total` | counter | `tenant` |
+//! | `greentic_operator_throttled_total` | counter | `tenant` |
+//! | `greentic_operator_cbor_decode_errors_total` | counter | `tenant` |
+//! | `greentic_operator_invoke_duration_seconds` | histogram | `tenant` |
+//! | `greentic_cache_memory_hits_total` | counter | `tenant`, `pack` |
+//! | `greentic_cache_disk_hits_total` | counter | `tenant`, `pack` |
+//! | `greentic_cache_disk_reads_total` | counter | `tenant`, `pack` |
+//! | `greentic_cache_negative_hits_total` | counter | `tenant`, `pack` |
+//! | `greentic_cache_compiles_total` | counter | `tenant`, `pack` |
+//! | `greentic_cache_memory_bytes` | gauge | `tenant`, `pack` |
+//! | `greentic_cache_memory_entries` | gauge | `tenant`, `pack` |
+//! | `greentic_cache_disk_bytes` | gauge | `tenant`, `pack` |
+//! | `greentic_contract_cache_hits_total` | counter | `tenant` |
+//! | `greentic_contract_cache_misses_total` | counter | `tenant` |
+//! | `greentic_contract_cache_evictions_total` | counter | `tenant` |
+//! | `greentic_contract_cache_entries` | gauge | `tenant` |
+//! | `greentic_contract_cache_bytes` | gauge | `tenant` |
+//! | `greentic_store_operations_total` | counter | `store`, `tenant`, `op` |
+//! | `greentic_store_errors_total` | counter | `store`, `tenant`, `op` |
+//! | `greentic_store_slow_operations_total` | counter | `store`, `tenant`, `op` |
+//! | `greentic_store_hits_total` | counter | `store`, `tenant` |
+//! | `greentic_store_misses_total` | counter | `store`, `tenant` |
+//! | `greentic_store_operation_duration_seconds` | histogram | `store`, `tenant`, `op` |
+//! | `greentic_resume_waits_expired_total` | counter | `tenant` |
+//! | `greentic_flow_redeliveries_total` | counter | `tenant`, `outcome` |
+//! | `greentic_secrets_backend_last_success_timestamp_seconds` | gauge | |
+//! | `greentic_secrets_backend_consecutive_failures` | gauge | |
+//! | `greentic_http_requests_total` | counter | `route`, `status` |
+//! | `greentic_http_in_flight_requests` | gauge | `tenant` |
+//!
+//! `/metrics/cache` answers for the routed tenant only: a JSON document with its
+//! component cache, contract cache and operator counters, or with
+//! `?format=prometheus` the operator and cache series of the table above.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{MatchedPath, Query, Request, State};
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::http::health::HealthState;
+use crate::routing::TenantRuntimeHandle;
+use crate::runner::ServerState;
+use crate::runner::redelivery::redelivery_metrics;
+use crate::runtime::{ActivePacks, TenantRuntime};
+use crate::storage::metrics::{StoreOp, store_metrics};
+
+const CONTENT_TYPE_PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Request counters keyed by matched route template and response status, plus
+/// per-tenant in-flight gauges.
+#[derive(Default)]
+pub struct HttpMetrics {
+    requests: Mutex<BTreeMap<(String, u16), u64>>,
+    in_flight: Mutex<BTreeMap<String, usize>>,
+}
+
+/// Marks one tenant request as in flight until dropped.
+pub struct InFlightGuard {
+    metrics: Arc<HttpMetrics>,
+    tenant: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut in_flight = self.metrics.in_flight.lock();
+        if let Some(count) = in_flight.get_mut(&self.tenant) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                in_flight.remove(&self.tenant);
+            }
+        }
+    }
+}
+
+impl HttpMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, route: &str, status: StatusCode) {
+        let mut requests = self.requests.lock();
+        *requests
+            .entry((route.to_string(), status.as_u16()))
+            .or_default() += 1;
+    }
+
+    /// Count a request for `tenant` as in flight unless `limit` is already reached. The
+    /// tenant's entry lives as long as it has requests in flight.
+    pub fn try_begin(
+        self: &Arc<Self>,
+        tenant: &str,
+        limit: Option<usize>,
+    ) -> Option<InFlightGuard> {
+        let mut in_flight = self.in_flight.lock();
+        let count = in_flight.entry(tenant.to_string()).or_default();
+        if limit.is_some_and(|limit| *count >= limit) {
+            return None;
+        }
+        *count += 1;
+        Some(InFlightGuard {
+            metrics: Arc::clone(self),
+            tenant: tenant.to_string(),
+        })
+    }
+
+    pub fn in_flight(&self) -> Vec<(String, usize)> {
+        self.in_flight
+            .lock()
+            .iter()
+            .map(|(tenant, count)| (tenant.clone(), *count))
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<(String, u16, u64)> {
+        self.requests
+            .lock()
+            .iter()
+            .map(|((route, status), count)| (route.clone(), *status, *count))
+            .collect()
+    }
+}
+
+/// Access-log middleware: counts every routed request by route template and status.
+pub async fn track_requests(
+    State(state): State<ServerState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().clone();
+    let response = next.run(request).await;
+    tracing::debug!(%method, route = %route, status = response.status().as_u16(), "http.access");
+    state.http_metrics.record(&route, response.status());
+    response
+}
+
+pub async fn handler(State(state): State<ServerState>) -> impl IntoResponse {
+    let body = render(&state.active, &state.http_metrics, &state.health);
+    ([(CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)], body)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CacheStatsQuery {
+    /// `json` (the default) or `prometheus`.
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Cache and operator counters of the routed tenant.
+pub async fn tenant_cache(
+    TenantRuntimeHandle { tenant, runtime }: TenantRuntimeHandle,
+    Query(query): Query<CacheStatsQuery>,
+) -> Response {
+    match query.format.as_deref() {
+        None | Some("json") => Json(cache_stats_document(&runtime)).into_response(),
+        Some("prometheus") => {
+            let mut out = String::new();
+            write_runtime_metrics(&mut out, &[(tenant.as_str(), runtime.as_ref())]);
+            ([(CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)], out).into_response()
+        }
+        Some(other) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("unknown format `{other}`; expected `json` or `prometheus`"),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+fn cache_stats_document(runtime: &TenantRuntime) -> Value {
+    let mut packs = vec![runtime.pack()];
+    packs.extend(runtime.overlays());
+    let components = packs
+        .iter()
+        .map(|pack| {
+            let cache = pack.cache();
+            let metrics = cache.metrics();
+            let memory = cache.memory_stats();
+            let disk = cache.disk_stats().unwrap_or_default();
+            json!({
+                "pack_id": pack.metadata().pack_id,
+                "memory_hits": metrics.memory_hits,
+                "disk_hits": metrics.disk_hits,
+                "disk_reads": metrics.disk_reads,
+                "negative_hits": metrics.negative_hits,
+                "compiles": metrics.compiles,
+                "memory": {
+                    "hits": memory.hits,
+                    "misses": memory.misses,
+                    "evictions": memory.evictions,
+                    "entries": memory.entries,
+                    "total_bytes": memory.total_bytes,
+                    "pinned_bytes": memory.pinned_bytes,
+                },
+                "disk": {
+                    "artifact_count": disk.artifact_count,
+                    "artifact_bytes": disk.artifact_bytes,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    let contracts = runtime.contract_cache_stats();
+    let operator = runtime.operator_metrics().snapshot();
+    json!({
+        "tenant": runtime.tenant(),
+        "component_cache": components,
+        "contract_cache": {
+            "hits": contracts.hits,
+            "misses": contracts.misses,
+            "evictions": contracts.evictions,
+            "entries": contracts.entries,
+            "total_bytes": contracts.total_bytes,
+        },
+        "operator": {
+            "resolve_attempts": operator.resolve_attempts,
+            "resolve_errors": operator.resolve_errors,
+            "invoke_attempts": operator.invoke_attempts,
+            "invoke_errors": operator.invoke_errors,
+            "invoke_timeouts": operator.invoke_timeouts,
+            "throttled": operator.throttled,
+            "cbor_decode_errors": operator.cbor_decode_errors,
+            "inflight": operator.inflight,
+            "invoke_latency": {
+                "count": operator.invoke_latency.count,
+                "sum_seconds": operator.invoke_latency.sum_seconds,
+            },
+        },
+    })
+}
+
+/// Render all runner metrics in Prometheus text exposition format.
+pub fn render(active: &ActivePacks, http: &HttpMetrics, health: &HealthState) -> String {
+    let snapshot = active.snapshot();
+    let mut tenants = snapshot
+        .iter()
+        .map(|(tenant, runtime)| (tenant.as_str(), runtime.as_ref()))
+        .collect::<Vec<_>>();
+    tenants.sort_by(|a, b| a.0.cmp(b.0));
+    let mut out = String::new();
+    write_runtime_metrics(&mut out, &tenants);
+
+    let stores = store_metrics()
+        .snapshot()
+        .into_iter()
+        .map(|sample| {
+            (
+                labels(&[
+                    ("store", sample.store),
+                    ("tenant", sample.tenant.as_str()),
+                    ("op", sample.op.as_str()),
+                ]),
+                sample,
+            )
+        })
+        .collect::<Vec<_>>();
+    for (name, help, pick) in [
+        (
+            "greentic_store_operations_total",
+            "State and session store operations.",
+            0usize,
+        ),
+        (
+            "greentic_store_errors_total",
+            "State and session store operations that failed.",
+            1,
+        ),
+        (
+            "greentic_store_slow_operations_total",
+            "Store operations slower than the slow-operation threshold.",
+            2,
+        ),
+    ] {
+        write_header(&mut out, name, "counter", help);
+        for (labels, sample) in &stores {
+            let value = match pick {
+                0 => sample.metrics.calls,
+                1 => sample.metrics.errors,
+                _ => sample.metrics.slow,
+            };
+            write_sample(&mut out, name, labels, value);
+        }
+    }
+    for (name, help, hits) in [
+        (
+            "greentic_store_hits_total",
+            "Store reads that found a value.",
+            true,
+        ),
+        (
+            "greentic_store_misses_total",
+            "Store reads that found nothing.",
+            false,
+        ),
+    ] {
+        write_header(&mut out, name, "counter", help);
+        for (_, sample) in stores
+            .iter()
+            .filter(|(_, sample)| sample.op == StoreOp::Get)
+        {
+            let value = if hits {
+                sample.metrics.hits
+            } else {
+                sample.metrics.misses
+            };
+            write_sample(
+                &mut out,
+                name,
+                &labels(&[("store", sample.store), ("tenant", sample.tenant.as_str())]),
+                value,
+            );
+        }
+    }
+
+    let name = "greentic_store_operation_duration_seconds";
+    write_header(
+        &mut out,
+        name,
+        "histogram",
+        "State and session store operation latency.",
+    );
+    for (store_labels, sample) in &stores {
+        let latency = &sample.metrics.latency;
+        for (bound, count) in &latency.buckets {
+            let le = bound.to_string();
+            write_sample(
+                &mut out,
+                &format!("{name}_bucket"),
+                &extend_labels(store_labels, "le", &le),
+                count,
+            );
+        }
+        write_sample(
+            &mut out,
+            &format!("{name}_bucket"),
+            &extend_labels(store_labels, "le", "+Inf"),
+            latency.count,
+        );
+        write_sample(
+            &mut out,
+            &format!("{name}_sum"),
+            store_labels,
+            latency.sum_seconds,
+        );
+        write_sample(
+            &mut out,
+            &format!("{name}_count"),
+            store_labels,
+            latency.count,
+        );
+    }
+
+    let name = "greentic_resume_waits_expired_total";
+    write_header(
+        &mut out,
+        name,
+        "counter",
+        "Flow resume waits dropped because their TTL passed.",
+    );
+    for (tenant, count) in store_metrics().expired_waits() {
+        write_sample(
+            &mut out,
+            name,
+            &labels(&[("tenant", tenant.as_str())]),
+            count,
+        );
+    }
+
+    let name = "greentic_flow_redeliveries_total";
+    write_header(
+        &mut out,
+        name,
+        "counter",
+        "Ingress redeliveries by outcome: scheduled, succeeded, exhausted or failed.",
+    );
+    for (tenant, outcome, count) in redelivery_metrics().snapshot() {
+        write_sample(
+            &mut out,
+            name,
+            &labels(&[("tenant", tenant.as_str()), ("outcome", outcome.as_str())]),
+            count,
+        );
+    }
+
+    let secrets = health.secrets_health();
+    let name = "greentic_secrets_backend_last_success_timestamp_seconds";
+    write_header(
+        &mut out,
+        name,
+        "gauge",
+        "Unix time of the last successful secrets backend health check.",
+    );
+    if let Some(last_success) = secrets.last_success {
+        write_sample(&mut out, name, "", last_success.unix_timestamp());
+    }
+    let name = "greentic_secrets_backend_consecutive_failures";
+    write_header(
+        &mut out,
+        name,
+        "gauge",
+        "Secrets backend health checks failed since the last success.",
+    );
+    write_sample(&mut out, name, "", secrets.consecutive_failures);
+
+    let name = "greentic_http_requests_total";
+    write_header(
+        &mut out,
+        name,
+        "counter",
+        "HTTP requests handled, by route template and status.",
+    );
+    for (route, status, count) in http.snapshot() {
+        let status = status.to_string();
+        write_sample(
+            &mut out,
+            name,
+            &labels(&[("route", route.as_str()), ("status", status.as_str())]),
+            count,
+        );
+    }
+
+    let name = "greentic_http_in_flight_requests";
+    write_header(
+        &mut out,
+        name,
+        "gauge",
+        "Tenant HTTP requests currently being handled.",
+    );
+    for (tenant, count) in http.in_flight() {
+        write_sample(
+            &mut out,
+            name,
+            &labels(&[("tenant", tenant.as_str())]),
+            count,
+        );
+    }
+
+    out
+}
+
+/// Operator, component cache and contract cache series of `tenants`.
+fn write_runtime_metrics(out: &mut String, tenants: &[(&str, &TenantRuntime)]) {
+    let operators = tenants
+        .iter()
+        .map(|&(tenant, runtime)| {
+            (
+                labels(&[("tenant", tenant)]),
+                runtime.operator_metrics().snapshot(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let contracts = tenants
+        .iter()
+        .map(|&(tenant, runtime)| {
+            (
+                labels(&[("tenant", tenant)]),
+                runtime.contract_cache_stats(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let caches = tenants
+        .iter()
+        .flat_map(|&(tenant, runtime)| {
+            let mut packs = vec![runtime.pack()];
+            packs.extend(runtime.overlays());
+            packs.into_iter().map(move |pack| {
+                let cache = pack.cache();
+                (
+                    labels(&[
+                        ("tenant", tenant),
+                        ("pack", pack.metadata().pack_id.as_str()),
+                    ]),
+                    cache.metrics(),
+                    cache.memory_stats(),
+                    cache.disk_stats().unwrap_or_default(),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (name, help, pick) in [
+        (
+            "greentic_operator_resolve_attempts_total",
+            "Operator op resolutions attempted.",
+            0usize,
+        ),
+        (
+            "greentic_operator_resolve_errors_total",
+            "Operator op resolutions that failed.",
+            1,
+        ),
+        (
+            "greentic_operator_invoke_attempts_total",
+            "Operator component invocations attempted.",
+            2,
+        ),
+        (
+            "greentic_operator_invoke_errors_total",
+            "Operator component invocations that failed.",
+            3,
+        ),
+        (
+            "greentic_operator_cbor_decode_errors_total",
+            "Operator payloads rejected because CBOR decoding failed.",
+            4,
+        ),
+        (
+            "greentic_operator_invoke_timeouts_total",
+            "Operator component invocations cut off at their request timeout.",
+            5,
+        ),
+        (
+            "greentic_operator_throttled_total",
+            "Operator requests refused by the tenant's operator rate limits.",
+            6,
+        ),
+    ] {
+        write_header(out, name, "counter", help);
+        for (labels, metrics) in &operators {
+            let value = match pick {
+                0 => metrics.resolve_attempts,
+                1 => metrics.resolve_errors,
+                2 => metrics.invoke_attempts,
+                3 => metrics.invoke_errors,
+                4 => metrics.cbor_decode_errors,
+                5 => metrics.invoke_timeouts,
+                _ => metrics.throttled,
+            };
+            write_sample(out, name, labels, value);
+        }
+    }
+
+    let name = "greentic_operator_invoke_duration_seconds";
+    write_header(
+        out,
+        name,
+        "histogram",
+        "End-to-end operator invoke latency.",
+    );
+    for (tenant_labels, metrics) in &operators {
+        let latency = &metrics.invoke_latency;
+        for (bound, count) in &latency.buckets {
+            let le = bound.to_string();
+            write_sample(
+                out,
+                &format!("{name}_bucket"),
+                &extend_labels(tenant_labels, "le", &le),
+                count,
+            );
+        }
+        write_sample(
+            out,
+            &format!("{name}_bucket"),
+            &extend_labels(tenant_labels, "le", "+Inf"),
+            latency.count,
+        );
+        write_sample(
+            out,
+            &format!("{name}_sum"),
+            tenant_labels,
+            latency.sum_seconds,
+        );
+        write_sample(out, &format!("{name}_count"), tenant_labels, latency.count);
+    }
+
+    for (name, kind, help, pick) in [
+        (
+            "greentic_cache_memory_hits_total",
+            "counter",
+            "Component lookups served from the in-memory cache.",
+            0usize,
+        ),
+        (
+            "greentic_cache_disk_hits_total",
+            "counter",
+            "Component lookups served from the on-disk cache.",
+            1,
+        ),
+        (
+            "greentic_cache_disk_reads_total",
+            "counter",
+            "On-disk cache reads attempted.",
+            2,
+        ),
+        (
+            "greentic_cache_negative_hits_total",
+            "counter",
+            "On-disk cache reads skipped because the artifact recently missed.",
+            3,
+        ),
+        (
+            "greentic_cache_compiles_total",
+            "counter",
+            "Components compiled because no cached artifact was usable.",
+            4,
+        ),
+        (
+            "greentic_cache_memory_bytes",
+            "gauge",
+            "Estimated bytes held by the in-memory component cache.",
+            5,
+        ),
+        (
+            "greentic_cache_memory_entries",
+            "gauge",
+            "Entries held by the in-memory component cache.",
+            6,
+        ),
+        (
+            "greentic_cache_disk_bytes",
+            "gauge",
+            "Bytes of serialized artifacts in the on-disk component cache.",
+            7,
+        ),
+    ] {
+        write_header(out, name, kind, help);
+        for (labels, metrics, memory, disk) in &caches {
+            let value = match pick {
+                0 => metrics.memory_hits,
+                1 => metrics.disk_hits,
+                2 => metrics.disk_reads,
+                3 => metrics.negative_hits,
+                4 => metrics.compiles,
+                5 => memory.total_bytes,
+                6 => memory.entries,
+                _ => disk.artifact_bytes,
+            };
+            write_sample(out, name, labels, value);
+        }
+    }
+
+    for (name, kind, help, pick) in [
+        (
+            "greentic_contract_cache_hits_total",
+            "counter",
+            "Contract cache lookups that found a snapshot.",
+            0usize,
+        ),
+        (
+            "greentic_contract_cache_misses_total",
+            "counter",
+            "Contract cache lookups that missed.",
+            1,
+        ),
+        (
+            "greentic_contract_cache_evictions_total",
+            "counter",
+            "Contract snapshots evicted to honour the byte budget.",
+            2,
+        ),
+        (
+            "greentic_contract_cache_entries",
+            "gauge",
+            "Contract snapshots currently cached.",
+            3,
+        ),
+        (
+            "greentic_contract_cache_bytes",
+            "gauge",
+            "Estimated bytes held by the contract cache.",
+            4,
+        ),
+    ] {
+        write_header(out, name, kind, help);
+        for (labels, stats) in &contracts {
+            let value = match pick {
+                0 => stats.hits,
+                1 => stats.misses,
+                2 => stats.evictions,
+                3 => stats.entries,
+                _ => stats.total_bytes,
+            };
+            write_sample(out, name, labels, value);
+        }
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "{name}{labels} {value}");
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let rendered = pairs
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", escape_label(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{{rendered}}}")
+}
+
+fn extend_labels(existing: &str, key: &str, value: &str) -> String {
+    let inner = existing.trim_start_matches('{').trim_end_matches('}');
+    let extra = format!("{key}=\"{}\"", escape_label(value));
+    if inner.is_empty() {
+        format!("{{{extra}}}")
+    } else {
+        format!("{{{inner},{extra}}}")
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_requests_render_with_route_and_status() {
+        let active = ActivePacks::new();
+        let http = HttpMetrics::new();
+        http.record("/operator/op/invoke", StatusCode::OK);
+        http.record("/operator/op/invoke", StatusCode::OK);
+        let body = render(&active, &http, &HealthState::new());
+        assert!(body.contains("# TYPE greentic_http_requests_total counter"));
+        assert!(body.contains(
+            "greentic_http_requests_total{route=\"/operator/op/invoke\",status=\"200\"} 2"
+        ));
+    }
+
+    #[test]
+    fn in_flight_limit_is_per_tenant_and_released_on_drop() {
+        let http = Arc::new(HttpMetrics::new());
+        let first = http
+            .try_begin("a", Some(1))
+            .expect("first request admitted");
+        assert!(http.try_begin("a", Some(1)).is_none());
+        let _other = http
+            .try_begin("b", Some(1))
+            .expect("other tenant unaffected");
+        assert!(
+            render(&ActivePacks::new(), &http, &HealthState::new())
+                .contains("greentic_http_in_flight_requests{tenant=\"a\"} 1")
+        );
+        drop(first);
+        assert_eq!(http.in_flight(), [("b".to_string(), 1)]);
+        assert!(http.try_begin("a", Some(1)).is_some());
+    }
+
+    #[test]
+    fn secrets_backend_health_is_exported() {
+        let health = HealthState::new();
+        let err = greentic_secrets_lib::SecretError::Backend("down".into());
+        health.record_secrets_check(Err(&err));
+        let body = render(&ActivePacks::new(), &HttpMetrics::new(), &health);
+        assert!(body.contains("greentic_secrets_backend_consecutive_failures 1"));
+        assert!(!body.contains("\ngreentic_secrets_backend_last_success_timestamp_seconds "));
+
+        health.record_secrets_check(Ok(()));
+        let body = render(&ActivePacks::new(), &HttpMetrics::new(), &health);
+        assert!(body.contains("greentic_secrets_backend_consecutive_failures 0"));
+        assert!(body.contains("\ngreentic_secrets_backend_last_success_timestamp_seconds "));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(labels(&[("tenant", "a\"b")]), "{tenant=\"a\\\"b\"}");
+        assert_eq!(
+            extend_labels("{tenant=\"demo\"}", "le", "+Inf"),
+            "{tenant=\"demo\",le=\"+Inf\"}"
+        );
+    }
+}
@@ -1,3 +1,5 @@
 pub mod admin;
 pub mod auth;
 pub mod health;
+pub mod limits;
+pub mod metrics;
@@ -4,7 +4,7 @@
 //! This crate owns tenant bindings, pack ingestion/watchers, ingress adapters,
 //! Wasmtime glue, session/state storage, and the HTTP server used by the
 //! `greentic-runner` CLI. Downstream crates embed it either through
-//! [`RunnerConfig`] + [`run`] (HTTP host) or [`HostBuilder`] (direct API access).
+//! [`RunnerConfig`] + [`run`]/[`start`] (HTTP host) or [`HostBuilder`] (direct API access).
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
@@ -13,6 +13,9 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use crate::secrets::SecretsBackend;
+use crate::storage::metrics::StoreMetricsConfig;
+use crate::storage::versioned::StateMigrations;
+use crate::storage::{SessionBackend, StateBackend};
 use anyhow::{Context, Result, anyhow};
 use greentic_config::ResolvedConfig;
 #[cfg(feature = "telemetry")]
@@ -26,43 +29,58 @@ use runner_core::env::PackConfig;
 use serde_json::json;
 use tokio::signal;
 
+pub mod audit;
 pub mod boot;
 pub mod cache;
+pub mod cancel;
 pub mod component_api;
 pub mod config;
+pub mod deterministic;
 pub mod engine;
 pub mod fault;
 pub mod gtbind;
+pub mod gtpack;
 pub mod http;
 pub mod ingress;
+pub mod lifecycle;
 pub mod operator_metrics;
 pub mod operator_registry;
 pub mod pack;
+pub mod preflight;
 pub mod provider;
 pub mod provider_core;
 pub mod provider_core_only;
+pub mod redact;
+pub mod reload;
 pub mod routing;
 pub mod runner;
 pub mod runtime;
 pub mod runtime_wasmtime;
 pub mod secrets;
+pub mod stdio;
 pub mod storage;
+pub mod stream;
 pub mod telemetry;
 #[cfg(feature = "fault-injection")]
 pub mod testing;
 pub mod trace;
+pub mod trap;
 pub mod validate;
 pub mod verify;
 pub mod wasi;
+pub mod wasm_engine;
+pub mod wasm_limits;
 pub mod watcher;
 
 mod activity;
+mod handle;
 mod host;
 pub mod oauth;
 
 pub use activity::{Activity, ActivityKind};
 pub use config::HostConfig;
 pub use gtbind::{PackBinding, TenantBindings};
+pub use handle::{RunnerHandle, ShutdownTrigger};
 pub use host::TelemetryCfg;
 pub use host::{HostBuilder, RunnerHost, TenantHandle};
 pub use wasi::{PreopenSpec, RunnerWasiPolicy};
@@ -72,7 +90,7 @@ pub use greentic_types::{EnvId, FlowId, PackId, TenantCtx, TenantId};
 pub use http::auth::AdminAuth;
 pub use routing::RoutingConfig;
 use routing::TenantRouting;
-pub use runner::HostServer;
+pub use runner::{HostServer, ListenAddr, ListenerConfig, RouteGroup};
 
 /// User-facing configuration for running the unified host.
 #[derive(Clone)]
@@ -80,17 +98,36 @@ pub struct RunnerConfig {
     pub tenant_bindings: HashMap<String, TenantBindings>,
     pub pack: PackConfig,
     pub port: u16,
+    /// Explicit listeners; when empty a single listener on `0.0.0.0:port` serves all routes.
+    pub listeners: Vec<ListenerConfig>,
     pub refresh_interval: Duration,
     pub routing: RoutingConfig,
     pub admin: AdminAuth,
     pub telemetry: Option<TelemetryCfg>,
     pub secrets_backend: SecretsBackend,
+    pub session_backend: SessionBackend,
+    pub state_backend: StateBackend,
+    /// Upgrades for state documents written at older schema versions.
+    pub state_migrations: StateMigrations,
+    /// Latency metrics and slow-operation logging for the state and session stores.
+    pub store_metrics: StoreMetricsConfig,
     pub wasi_policy: RunnerWasiPolicy,
     pub resolved_config: ResolvedConfig,
     pub trace: trace::TraceConfig,
     pub validation: validate::ValidationConfig,
+    /// gtbind files the tenant bindings were loaded from; re-read on SIGHUP.
+    pub binding_paths: Vec<PathBuf>,
+    /// Rebuilds the config on SIGHUP. Defaults to re-reading `binding_paths` against
+    /// `resolved_config`; only tenant bindings and the pack index are re-applied.
+    pub reload_source: Option<ReloadSource>,
+    /// Abort startup when any tenant fails preflight instead of marking it not-ready.
+    /// Also compiles components of lazily loading tenants before serving.
+    pub strict_preflight: bool,
 }
 
+/// Re-runs config resolution for a SIGHUP reload.
+pub type ReloadSource = Arc<dyn Fn() -> Result<RunnerConfig> + Send + Sync>;
+
 impl RunnerConfig {
     /// Build a [`RunnerConfig`] from a resolved greentic-config and the provided binding files.
     pub fn from_config(resolved_config: ResolvedConfig, bindings: Vec<PathBuf>) -> Result<Self> {
@@ -116,8 +153,7 @@ impl RunnerConfig {
             .config
             .dev
             .as_ref()
-            .map(|dev| dev.default_tenant.clone())
-            .unwrap_or_else(|| "demo".into());
+            .map(|dev| dev.default_tenant.clone());
         let routing = RoutingConfig::from_env_with_default(default_tenant);
         let paths = &resolved_config.config.paths;
         ensure_paths_exist(paths)?;
@@ -141,15 +177,25 @@ impl RunnerConfig {
             tenant_bindings,
             pack,
             port,
+            listeners: Vec::new(),
             refresh_interval: refresh,
             routing,
             admin,
             telemetry: telemetry_from(&resolved_config.config.telemetry),
             secrets_backend,
+            session_backend: SessionBackend::from_env()?,
+            state_backend: StateBackend::from_env()?,
+            state_migrations: StateMigrations::default(),
+            store_metrics: StoreMetricsConfig::from_env()?,
             wasi_policy,
             resolved_config,
             trace: trace::TraceConfig::from_env(),
             validation: validate::ValidationConfig::from_env(),
+            binding_paths: bindings,
+            reload_source: None,
+            strict_preflight: std::env::var("GREENTIC_STRICT_PREFLIGHT")
+                .map(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
         })
     }
 
@@ -159,10 +205,63 @@ impl RunnerConfig {
         self
     }
 
+    /// Serve route groups on separate listeners instead of the single `port` listener.
+    pub fn with_listeners(mut self, listeners: Vec<ListenerConfig>) -> Self {
+        self.listeners = listeners;
+        self
+    }
+
     pub fn with_wasi_policy(mut self, policy: RunnerWasiPolicy) -> Self {
         self.wasi_policy = policy;
         self
     }
+
+    pub fn with_session_backend(mut self, backend: SessionBackend) -> Self {
+        self.session_backend = backend;
+        self
+    }
+
+    pub fn with_state_backend(mut self, backend: StateBackend) -> Self {
+        self.state_backend = backend;
+        self
+    }
+
+    pub fn with_state_migrations(mut self, migrations: StateMigrations) -> Self {
+        self.state_migrations = migrations;
+        self
+    }
+
+    pub fn with_store_metrics(mut self, config: StoreMetricsConfig) -> Self {
+        self.store_metrics = config;
+        self
+    }
+
+    /// Override how the config is rebuilt on SIGHUP (e.g. to re-run the config resolver).
+    pub fn with_reload_source(mut self, source: ReloadSource) -> Self {
+        self.reload_source = Some(source);
+        self
+    }
+
+    pub fn with_strict_preflight(mut self, strict: bool) -> Self {
+        self.strict_preflight = strict;
+        self
+    }
+}
+
+fn host_configs_from(
+    tenant_bindings: HashMap<String, TenantBindings>,
+    trace: &trace::TraceConfig,
+    validation: &validate::ValidationConfig,
+) -> Vec<HostConfig> {
+    tenant_bindings
+        .into_values()
+        .map(|bindings| {
+            let mut host_config = HostConfig::from_gtbind(bindings);
+            host_config.trace = trace.clone();
+            host_config.validation = validation.clone();
+            host_config
+        })
+        .collect()
 }
 
 fn maybe_write_gtbind_index(
@@ -259,7 +358,8 @@ fn default_wasi_policy(paths: &PathsConfig) -> RunnerWasiPolicy {
     policy = policy
         .with_preopen(PreopenSpec::new(&paths.state_dir, "/state"))
         .with_preopen(PreopenSpec::new(&paths.cache_dir, "/cache"))
-        .with_preopen(PreopenSpec::new(&paths.logs_dir, "/logs"));
+        .with_preopen(PreopenSpec::new(&paths.logs_dir, "/logs"))
+        .allow_tenant_preopen_dirs(paths.greentic_root.join("tenants"));
     policy
 }
 
@@ -304,6 +404,7 @@ fn pack_config_from(
             cache_dir,
             public_key,
             network: Some(network.clone()),
+            max_concurrent_fetches: runner_core::env::DEFAULT_MAX_CONCURRENT_FETCHES,
         });
     }
     let mut cfg = PackConfig::default_for_paths(paths)?;
@@ -329,6 +430,7 @@ fn telemetry_from(cfg: &TelemetryConfig) -> Option<TelemetryCfg> {
             service_name: "greentic-runner".into(),
         },
         export,
+        otel_env: Vec::new(),
     })
 }
 
@@ -337,70 +439,157 @@ fn telemetry_from(_cfg: &TelemetryConfig) -> Option<TelemetryCfg> {
     None
 }
 
-/// Run the unified Greentic runner host until shutdown.
+/// Run the unified Greentic runner host until Ctrl-C or a listener fails.
 pub async fn run(cfg: RunnerConfig) -> Result<()> {
+    let handle = start(cfg).await?;
+    let shutdown = handle.shutdown_trigger();
+    let ctrl_c = tokio::spawn(async move {
+        let _ = signal::ctrl_c().await;
+        tracing::info!("received shutdown signal");
+        shutdown.trigger();
+    });
+    let result = handle.join().await;
+    ctrl_c.abort();
+    result
+}
+
+/// Start the host and its listeners in the background.
+///
+/// Returns once packs are loaded and every listener is bound; the handle exposes the
+/// bound addresses, readiness and shutdown.
+pub async fn start(cfg: RunnerConfig) -> Result<RunnerHandle> {
     let RunnerConfig {
         tenant_bindings,
         pack,
         port,
+        listeners,
         refresh_interval,
         routing,
         admin,
         telemetry,
         secrets_backend,
+        session_backend,
+        state_backend,
+        state_migrations,
+        store_metrics,
         wasi_policy,
-        resolved_config: _resolved_config,
+        resolved_config,
         trace,
         validation,
+        binding_paths,
+        reload_source,
+        strict_preflight,
     } = cfg;
     #[cfg(not(feature = "telemetry"))]
     let _ = telemetry;
+    runner::i18n::load_catalogs_from_env()?;
+    trace::sampling::configure(trace.export.sampling.clone());
 
     let mut builder = HostBuilder::new();
-    for bindings in tenant_bindings.into_values() {
-        let mut host_config = HostConfig::from_gtbind(bindings);
-        host_config.trace = trace.clone();
-        host_config.validation = validation.clone();
+    for host_config in host_configs_from(tenant_bindings, &trace, &validation) {
         builder = builder.with_config(host_config);
     }
+    // The `[trace]` section wins over the greentic-config telemetry block.
     #[cfg(feature = "telemetry")]
-    if let Some(telemetry) = telemetry.clone() {
+    if let Some(telemetry) = TelemetryCfg::from_trace_export(&trace.export).or(telemetry.clone()) {
         builder = builder.with_telemetry(telemetry);
     }
+    let (secrets_manager, secrets_health) = secrets_backend
+        .build_manager_with_health()
+        .context("failed to initialise secrets backend")?;
     builder = builder
         .with_wasi_policy(wasi_policy.clone())
-        .with_secrets_manager(
-            secrets_backend
-                .build_manager()
-                .context("failed to initialise secrets backend")?,
-        );
+        .with_secrets_manager(secrets_manager)
+        .with_secrets_health_check(secrets_health)
+        .with_session_store(
+            session_backend
+                .build_store()
+                .context("failed to initialise session store")?,
+        )
+        .with_state_store(
+            state_backend
+                .build_store()
+                .context("failed to initialise state store")?,
+        )
+        .with_state_migrations(state_migrations)
+        .with_store_metrics(store_metrics);
 
     let host = Arc::new(builder.build()?);
     host.start().await?;
 
     let (watcher, reload_handle) =
         watcher::start_pack_watcher(Arc::clone(&host), pack.clone(), refresh_interval).await?;
+    // The watcher's initial load already ran preflight and recorded the report.
+    if let Some(report) = host.health_state().preflight() {
+        report.enforce(strict_preflight)?;
+    }
+    // `start` already probed the secrets backend; strict preflight refuses to serve
+    // without it.
+    if strict_preflight && let Some(err) = host.health_state().secrets_health().last_error {
+        return Err(anyhow!(
+            "strict preflight failed: secrets backend health check failed: {err}"
+        ));
+    }
+    if strict_preflight {
+        preflight::compile_lazy_components(&host.active_packs()).await?;
+    }
 
+    // Trace/validation keep their startup values so CLI overrides survive a reload;
+    // only trace sampling follows the reloaded config.
+    let config_source: reload::ConfigSource = Arc::new(move || {
+        let next = match &reload_source {
+            Some(source) => source()?,
+            None => RunnerConfig::from_config(resolved_config.clone(), binding_paths.clone())?,
+        };
+        trace::sampling::configure(next.trace.export.sampling);
+        Ok(host_configs_from(next.tenant_bindings, &trace, &validation))
+    });
+    let reloader = reload::spawn_reload_listener(
+        reload::ConfigReloader::new(Arc::clone(&host), reload_handle.clone(), config_source),
+        reload::ReloadSignals::sighup()?,
+    );
+
+    let listeners = if listeners.is_empty() {
+        vec![ListenerConfig::all_on_port(port)]
+    } else {
+        listeners
+    };
     let routing = TenantRouting::new(routing.clone());
-    let server = HostServer::new(
-        port,
+    if let Some(default_tenant) = routing.default_tenant()
+        && host.active_packs().load(default_tenant).is_none()
+    {
+        tracing::warn!(
+            default_tenant,
+            "default tenant does not match any loaded tenant"
+        );
+    }
+    let server = HostServer::with_listeners(
+        listeners,
         host.active_packs(),
         routing,
         host.health_state(),
         Some(reload_handle),
         admin.clone(),
-    )?;
+    )?
+    .bind()
+    .await?;
 
-    tokio::select! {
-        result = server.serve() => {
-            result?;
-        }
-        _ = signal::ctrl_c() => {
-            tracing::info!("received shutdown signal");
-        }
-    }
+    let addrs = server.local_addrs()?;
 
-    drop(watcher);
-    host.stop().await?;
-    Ok(())
+    let shutdown = ShutdownTrigger::default();
+    let task = tokio::spawn({
+        let host = Arc::clone(&host);
+        let shutdown = shutdown.clone();
+        async move {
+            let result = tokio::select! {
+                result = server.serve() => result,
+                _ = shutdown.triggered() => Ok(()),
+            };
+            reloader.abort();
+            drop(watcher);
+            host.stop().await?;
+            result
+        }
+    });
+    Ok(RunnerHandle::new(addrs, host, shutdown, task))
 }
@@ -0,0 +1,83 @@
+//! When component `on-start` / `on-stop` exports run.
+//!
+//! Each pack runtime remembers which components it has started, so `on-stop` reaches
+//! exactly those components when the pack is retired by a reload, tenant removal or
+//! host shutdown. Components that do not export node@0.5 or node@0.4 are skipped.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Reason passed to `on-stop` when a pack is replaced by a reload.
+pub const STOP_REASON_RELOAD: &str = "reload";
+/// Reason passed to `on-stop` when a tenant is no longer served.
+pub const STOP_REASON_TENANT_REMOVED: &str = "tenant-removed";
+/// Reason passed to `on-stop` when the host shuts down.
+pub const STOP_REASON_SHUTDOWN: &str = "shutdown";
+
+/// `lifecycle` bindings block.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct LifecycleConfig {
+    #[serde(default)]
+    pub start: LifecycleStart,
+    #[serde(default)]
+    pub on_start_error: StartErrorPolicy,
+    /// Wall-clock budget for each component's `on-stop`.
+    #[serde(default = "default_stop_timeout_ms")]
+    pub stop_timeout_ms: u64,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            start: LifecycleStart::default(),
+            on_start_error: StartErrorPolicy::default(),
+            stop_timeout_ms: default_stop_timeout_ms(),
+        }
+    }
+}
+
+impl LifecycleConfig {
+    pub fn stop_timeout(&self) -> Duration {
+        Duration::from_millis(self.stop_timeout_ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleStart {
+    /// Start every component when the tenant runtime loads the pack.
+    #[default]
+    Eager,
+    /// Start a component right before its first invocation.
+    Lazy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartErrorPolicy {
+    /// Fail the tenant load (eager) or the invocation (lazy).
+    Fail,
+    /// Log a warning and serve the component anyway.
+    #[default]
+    Continue,
+}
+
+fn default_stop_timeout_ms() -> u64 {
+    5_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml_bw as serde_yaml;
+
+    #[test]
+    fn bindings_block_overrides_defaults() {
+        let config: LifecycleConfig =
+            serde_yaml::from_str("start: lazy\non_start_error: fail\n").expect("lifecycle");
+        assert_eq!(config.start, LifecycleStart::Lazy);
+        assert_eq!(config.on_start_error, StartErrorPolicy::Fail);
+        assert_eq!(config.stop_timeout(), Duration::from_secs(5));
+    }
+}
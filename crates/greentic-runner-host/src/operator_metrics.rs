@@ -1,4 +1,9 @@
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds (in seconds) of the operator invoke latency histogram buckets.
+pub const INVOKE_LATENCY_BUCKETS: [f64; 10] =
+    [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
 
 #[derive(Debug)]
 pub struct OperatorMetrics {
@@ -6,7 +11,14 @@ pub struct OperatorMetrics {
     pub resolve_errors: AtomicU64,
     pub invoke_attempts: AtomicU64,
     pub invoke_errors: AtomicU64,
+    /// Invocations cut off at their request `timeout`.
+    pub invoke_timeouts: AtomicU64,
+    /// Requests refused by `rate_limits.operators`.
+    pub throttled: AtomicU64,
     pub cbor_decode_errors: AtomicU64,
+    pub invoke_latency: LatencyHistogram,
+    /// Operator invocations currently running.
+    pub inflight: AtomicU64,
 }
 
 #[derive(Clone, Debug)]
@@ -15,7 +27,11 @@ pub struct OperatorMetricsSnapshot {
     pub resolve_errors: u64,
     pub invoke_attempts: u64,
     pub invoke_errors: u64,
+    pub invoke_timeouts: u64,
+    pub throttled: u64,
     pub cbor_decode_errors: u64,
+    pub invoke_latency: LatencySnapshot,
+    pub inflight: u64,
 }
 
 impl Default for OperatorMetrics {
@@ -25,7 +41,11 @@ impl Default for OperatorMetrics {
             resolve_errors: AtomicU64::new(0),
             invoke_attempts: AtomicU64::new(0),
             invoke_errors: AtomicU64::new(0),
+            invoke_timeouts: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
             cbor_decode_errors: AtomicU64::new(0),
+            invoke_latency: LatencyHistogram::default(),
+            inflight: AtomicU64::new(0),
         }
     }
 }
@@ -37,7 +57,85 @@ impl OperatorMetrics {
             resolve_errors: self.resolve_errors.load(Ordering::Relaxed),
             invoke_attempts: self.invoke_attempts.load(Ordering::Relaxed),
             invoke_errors: self.invoke_errors.load(Ordering::Relaxed),
+            invoke_timeouts: self.invoke_timeouts.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
             cbor_decode_errors: self.cbor_decode_errors.load(Ordering::Relaxed),
+            invoke_latency: self.invoke_latency.snapshot(),
+            inflight: self.inflight.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Count an invocation as in flight until the returned guard is dropped.
+    pub fn track_inflight(&self) -> InflightGuard<'_> {
+        self.inflight.fetch_add(1, Ordering::Relaxed);
+        InflightGuard { metrics: self }
+    }
+}
+
+pub struct InflightGuard<'a> {
+    metrics: &'a OperatorMetrics,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.inflight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Fixed-bucket latency histogram backed by atomics.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; INVOKE_LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencySnapshot {
+    /// Cumulative counts per bucket upper bound, matching Prometheus `le` semantics.
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(idx) = INVOKE_LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+        {
+            self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let mut cumulative = 0u64;
+        let buckets = INVOKE_LATENCY_BUCKETS
+            .iter()
+            .zip(self.buckets.iter())
+            .map(|(bound, counter)| {
+                cumulative = cumulative.saturating_add(counter.load(Ordering::Relaxed));
+                (*bound, cumulative)
+            })
+            .collect();
+        LatencySnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
         }
     }
 }
@@ -1,11 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use anyhow::Result;
+use serde::Serialize;
 
 use greentic_types::provider::ProviderRuntimeRef;
 
+use crate::config::OperatorPolicy;
 use crate::pack::PackRuntime;
+use crate::runtime::PackPreference;
 
 #[derive(Clone, Debug)]
 pub struct OperatorBinding {
@@ -22,6 +25,42 @@ pub struct OperatorBinding {
     pub pack_priority: usize,
 }
 
+impl OperatorBinding {
+    /// Resolve the binding's component in the pack that declared it.
+    pub fn pack_preference(&self) -> PackPreference<'_> {
+        PackPreference {
+            pack_ref: Some(&self.pack_ref),
+            pack_digest: self.pack_digest.as_deref(),
+        }
+    }
+}
+
+/// A provider op a tenant may invoke, as listed by `GET /operator/ops`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OperatorOpDescriptor {
+    pub provider_id: Option<String>,
+    pub provider_type: String,
+    pub op_id: String,
+    pub pack_ref: String,
+    pub capabilities: Vec<String>,
+    pub config_schema_ref: Option<String>,
+    pub docs_ref: Option<String>,
+}
+
+impl From<&OperatorBinding> for OperatorOpDescriptor {
+    fn from(binding: &OperatorBinding) -> Self {
+        Self {
+            provider_id: binding.provider_id.clone(),
+            provider_type: binding.provider_type.clone(),
+            op_id: binding.op_id.clone(),
+            pack_ref: binding.pack_ref.clone(),
+            capabilities: binding.capabilities.clone(),
+            config_schema_ref: binding.config_schema_ref.clone(),
+            docs_ref: binding.docs_ref.clone(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum OperatorResolveError {
     ProviderNotFound,
@@ -111,4 +150,43 @@ impl OperatorRegistry {
         }
         Err(OperatorResolveError::ProviderNotFound)
     }
+
+    /// Every registered binding, once per provider type and op.
+    pub fn bindings(&self) -> impl Iterator<Item = &OperatorBinding> {
+        self.per_provider_type.values().flat_map(HashMap::values)
+    }
+
+    /// The ops `policy` lets a tenant invoke, sorted by provider type, provider id and op.
+    /// Each op is listed once per provider as [`Self::resolve`] finds it, so ops a later
+    /// pack re-declares name that pack.
+    pub fn list_operations(&self, policy: &OperatorPolicy) -> Vec<OperatorOpDescriptor> {
+        let mut listed: BTreeMap<(&str, Option<&str>, &str), &OperatorBinding> = BTreeMap::new();
+        let reachable = self
+            .per_provider_id
+            .values()
+            .chain(self.per_provider_type.values())
+            .flat_map(HashMap::values);
+        for binding in reachable {
+            let provider_id = binding.provider_id.as_deref();
+            let provider_type = binding.provider_type.as_str();
+            if !policy.allows_provider(provider_id, provider_type)
+                || !policy.allows_op(provider_id, provider_type, &binding.op_id)
+                || policy.refused_capability(&binding.capabilities).is_some()
+            {
+                continue;
+            }
+            listed
+                .entry((provider_type, provider_id, binding.op_id.as_str()))
+                .and_modify(|current| {
+                    if binding.pack_priority > current.pack_priority {
+                        *current = binding;
+                    }
+                })
+                .or_insert(binding);
+        }
+        listed
+            .into_values()
+            .map(OperatorOpDescriptor::from)
+            .collect()
+    }
 }
@@ -1,14 +1,16 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::cache::{ArtifactKey, CacheConfig, CacheManager, CpuPolicy, EngineProfile};
+use crate::cache::{
+    ArtifactKey, CacheConfig, CacheManager, InstancePreCache, InstancePreKey, InstancePreStats,
+};
 use crate::component_api::{
     self, node::ExecCtx as ComponentExecCtx, node::InvokeResult, node::NodeError,
+    node::TenantCtx as ComponentTenantCtx,
 };
 use crate::oauth::{OAuthBrokerConfig, OAuthBrokerHost, OAuthHostContext};
 use crate::provider::{ProviderBinding, ProviderRegistry};
@@ -59,7 +61,7 @@ use host_v1::http_client::{
 };
 use indexmap::IndexMap;
 use once_cell::sync::Lazy;
-use parking_lot::{Mutex, RwLock};
+use parking_lot::RwLock;
 use reqwest::blocking::Client as BlockingClient;
 use runner_core::normalize_under_root;
 use serde::{Deserialize, Serialize};
@@ -69,7 +71,7 @@ use sha2::Digest;
 use tempfile::TempDir;
 use tokio::fs;
 use wasmparser::{Parser, Payload};
-use wasmtime::{Store, StoreContextMut};
+use wasmtime::{Store, StoreContextMut, UpdateDeadline};
 use zip::ZipArchive;
 
 use crate::runner::engine::{FlowContext, FlowEngine, FlowStatus};
@@ -78,19 +80,39 @@ use crate::runner::mocks::{HttpDecision, HttpMockRequest, HttpMockResponse, Mock
 #[cfg(feature = "fault-injection")]
 use crate::testing::fault_injection::{FaultContext, FaultPoint, maybe_fail};
 
-use crate::config::HostConfig;
+use crate::cancel::{self, CancelReason, CancelWatch, Cancelled, InvocationCancel};
+use crate::config::{ComponentLoading, HostConfig};
 use crate::fault;
+use crate::gtpack::{self, ArchiveLimits};
+use crate::lifecycle::StartErrorPolicy;
+use crate::redact::{self, Redactor};
 use crate::secrets::{DynSecretsManager, read_secret_blocking, write_secret_blocking};
+use crate::stdio::{self, StdioCapture, StdioSink};
+use crate::storage::encryption;
+use crate::storage::quota::{self, StateQuotaExceeded};
 use crate::storage::state::STATE_PREFIX;
 use crate::storage::{DynSessionStore, DynStateStore};
+use crate::stream::{StreamEvent, StreamObserver, StreamUnsupported};
+use crate::trap;
 use crate::verify;
 use crate::wasi::{PreopenSpec, RunnerWasiPolicy};
+use crate::wasm_engine;
+use crate::wasm_limits::{WasmLimiter, WasmLimits};
 use tracing::warn;
 use wasmtime_wasi::p2::add_to_linker_sync as add_wasi_to_linker;
 use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
 
 use greentic_flow::model::FlowDoc;
 
+/// `InstancePreKey::world` for components invoked through the node interface.
+const NODE_WORLD: &str = "greentic:component/node";
+/// `InstancePreKey::world` for v0.6 descriptor introspection.
+const DESCRIPTOR_WORLD: &str = "greentic:component/component-descriptor";
+/// `InstancePreKey::linker` values: host imports from [`register_all`] plus component
+/// control, with or without the state-store interface.
+const LINKER_DEFAULT: &str = "host-v1+control";
+const LINKER_WITH_STATE_STORE: &str = "host-v1+state-store+control";
+
 #[allow(dead_code)]
 pub struct PackRuntime {
     /// Component artifact path (wasm file).
@@ -106,8 +128,10 @@ pub struct PackRuntime {
     mocks: Option<Arc<MockLayer>>,
     flows: Option<PackFlows>,
     components: HashMap<String, PackComponent>,
+    /// sha256 of each component's wasm, computed and verified at load.
+    component_digests: BTreeMap<String, String>,
     http_client: Arc<BlockingClient>,
-    pre_cache: Mutex<HashMap<String, InstancePre<ComponentState>>>,
+    pre_cache: InstancePreCache<ComponentState>,
     session_store: Option<DynSessionStore>,
     state_store: Option<DynStateStore>,
     wasi_policy: Arc<RunnerWasiPolicy>,
@@ -116,6 +140,23 @@ pub struct PackRuntime {
     secrets: DynSecretsManager,
     oauth_config: Option<OAuthBrokerConfig>,
     cache: CacheManager,
+    /// Components whose `on-start` has run; `on-stop` is sent only to these.
+    started_components: tokio::sync::Mutex<BTreeSet<String>>,
+}
+
+/// Lifecycle export to call on a node component.
+enum LifecyclePhase {
+    Start,
+    Stop(String),
+}
+
+impl LifecyclePhase {
+    fn export(&self) -> &'static str {
+        match self {
+            LifecyclePhase::Start => "on-start",
+            LifecyclePhase::Stop(_) => "on-stop",
+        }
+    }
 }
 
 struct PackComponent {
@@ -123,7 +164,116 @@ struct PackComponent {
     name: String,
     #[allow(dead_code)]
     version: String,
-    component: Arc<Component>,
+    /// Set at load, or on first use when `component_loading` is lazy.
+    component: tokio::sync::OnceCell<Arc<Component>>,
+    /// Bytes to compile on first use; `None` for components compiled at load.
+    deferred: Option<ComponentBytes>,
+}
+
+impl PackComponent {
+    fn compiled(name: String, version: String, component: Arc<Component>) -> Self {
+        Self {
+            name,
+            version,
+            component: tokio::sync::OnceCell::from(component),
+            deferred: None,
+        }
+    }
+
+    fn deferred(name: String, version: String, bytes: ComponentBytes) -> Self {
+        Self {
+            name,
+            version,
+            component: tokio::sync::OnceCell::new(),
+            deferred: Some(bytes),
+        }
+    }
+}
+
+/// Where a located component's wasm bytes are read from.
+#[derive(Clone, Debug)]
+enum ComponentLocation {
+    File(PathBuf),
+    ArchiveEntry { archive: PathBuf, entry: String },
+}
+
+/// Check the bytes must pass before they are compiled.
+#[derive(Clone, Debug)]
+enum DigestCheck {
+    None,
+    WasmSha256(String),
+    Digest(String),
+    /// Bundled without `wasm_sha256`, tolerated by `allow_missing_hash`.
+    Skipped,
+}
+
+/// A component found in one of the pack's sources, not necessarily read yet.
+#[derive(Clone, Debug)]
+struct ComponentBytes {
+    component_id: String,
+    location: ComponentLocation,
+    /// Digest used for the artifact cache key; computed from the bytes when absent.
+    cache_digest: Option<String>,
+    check: DigestCheck,
+}
+
+impl ComponentBytes {
+    fn read(&self) -> Result<Vec<u8>> {
+        let component_id = &self.component_id;
+        let bytes = match &self.location {
+            ComponentLocation::File(path) => std::fs::read(path).with_context(|| {
+                format!(
+                    "failed to read component {component_id} from {}",
+                    path.display()
+                )
+            })?,
+            ComponentLocation::ArchiveEntry { archive, entry } => {
+                let mut zip = gtpack::open(archive)?;
+                read_entry(&mut zip, entry).with_context(|| {
+                    format!("component {component_id} missing at {entry} in pack archive")
+                })?
+            }
+        };
+        match &self.check {
+            DigestCheck::None => {}
+            DigestCheck::WasmSha256(expected) => {
+                verify_wasm_sha256(component_id, expected, &bytes)?
+            }
+            DigestCheck::Digest(expected) => {
+                verify_component_digest(component_id, expected, &bytes)?
+            }
+            DigestCheck::Skipped => {
+                let actual = compute_sha256_digest_for(&bytes);
+                warn!(
+                    component_id = %component_id,
+                    digest = %actual,
+                    "bundled component missing wasm_sha256; allowing due to flag"
+                );
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Drop the compilation cached under the declared digest; without one the cache is
+    /// keyed by the bytes and cannot go stale.
+    fn invalidate(&self, cache: &CacheManager) -> Result<()> {
+        match self.cache_digest.as_deref() {
+            Some(digest) => cache.invalidate(&build_artifact_key(cache, Some(digest), &[])),
+            None => Ok(()),
+        }
+    }
+
+    /// Read and verify the bytes, returning their sha256.
+    fn digest(&self) -> Result<String> {
+        self.read().map(|bytes| compute_sha256_digest_for(&bytes))
+    }
+
+    async fn compile(&self, cache: &CacheManager, engine: &Engine) -> Result<Arc<Component>> {
+        let bytes = self.read()?;
+        compile_component_with_cache(cache, engine, self.cache_digest.as_deref(), bytes)
+            .await
+            .with_context(|| format!("failed to compile component {}", self.component_id))
+    }
 }
 
 fn run_on_wasi_thread<F, T>(task_name: &'static str, task: F) -> Result<T>
@@ -163,6 +313,18 @@ where
         .and_then(|res| res)
 }
 
+/// [`run_on_wasi_thread`] without blocking the calling task, so that concurrent flow
+/// nodes run their components side by side.
+async fn spawn_on_wasi_thread<F, T>(task_name: &'static str, task: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || run_on_wasi_thread(task_name, task))
+        .await
+        .context("failed to join Wasmtime thread")?
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ComponentResolution {
     /// Root of a materialized pack directory containing `manifest.cbor` and `components/`.
@@ -175,6 +337,29 @@ pub struct ComponentResolution {
     pub dist_cache_dir: Option<PathBuf>,
     /// Allow bundled components without wasm_sha256 (dev-only escape hatch).
     pub allow_missing_hash: bool,
+    /// Compiled components to drop from the cache before loading.
+    pub cache_refresh: CacheRefresh,
+}
+
+/// Components whose cached compilation [`PackRuntime::load`] discards, so wasm rebuilt
+/// behind an unchanged declared digest is compiled again. Components keyed by the
+/// digest of their bytes never need it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum CacheRefresh {
+    #[default]
+    None,
+    All,
+    Components(HashSet<String>),
+}
+
+impl CacheRefresh {
+    fn includes(&self, component_id: &str) -> bool {
+        match self {
+            CacheRefresh::None => false,
+            CacheRefresh::All => true,
+            CacheRefresh::Components(ids) => ids.contains(component_id),
+        }
+    }
 }
 
 fn build_blocking_client() -> BlockingClient {
@@ -249,6 +434,10 @@ pub struct HostState {
     exec_ctx: Option<ComponentExecCtx>,
     component_ref: Option<String>,
     provider_core_component: bool,
+    /// First state write the tenant's quota rejected during this invocation.
+    state_quota_exceeded: Option<StateQuotaExceeded>,
+    /// Redaction scope of the invocation; secrets the component reads are added to it.
+    redactor: Option<Redactor>,
 }
 
 impl HostState {
@@ -282,19 +471,19 @@ impl HostState {
             exec_ctx,
             component_ref,
             provider_core_component,
+            state_quota_exceeded: None,
+            redactor: None,
         })
     }
 
     fn instantiate_component_result(
-        linker: &mut Linker<ComponentState>,
+        pre_instance: &InstancePre<ComponentState>,
         store: &mut Store<ComponentState>,
-        component: &Component,
         ctx: &ComponentExecCtx,
         operation: &str,
         input_json: &str,
     ) -> Result<InvokeResult> {
-        let pre_instance = linker.instantiate_pre(component)?;
-        match component_api::v0_5::ComponentPre::new(pre_instance) {
+        match component_api::v0_5::ComponentPre::new(pre_instance.clone()) {
             Ok(pre) => {
                 let result = block_on(async {
                     let bindings = pre.instantiate_async(&mut *store).await?;
@@ -308,8 +497,7 @@ impl HostState {
             }
             Err(err) => {
                 if is_missing_node_export(&err, "0.5.0") {
-                    let pre_instance = linker.instantiate_pre(component)?;
-                    match component_api::v0_4::ComponentPre::new(pre_instance) {
+                    match component_api::v0_4::ComponentPre::new(pre_instance.clone()) {
                         Ok(pre) => {
                             let result = block_on(async {
                                 let bindings = pre.instantiate_async(&mut *store).await?;
@@ -328,9 +516,7 @@ impl HostState {
                         }
                         Err(err_v04) => {
                             if is_missing_node_export(&err_v04, "0.4.0") {
-                                Self::try_v06_runtime(
-                                    linker, store, component, input_json,
-                                )
+                                Self::try_v06_runtime(pre_instance, store, input_json)
                             } else {
                                 Err(err_v04)
                             }
@@ -343,16 +529,109 @@ impl HostState {
         }
     }
 
+    /// Call `invoke-stream` on a v0.5 or v0.4 node component. v0.6 components have no
+    /// streaming export and fail with [`StreamUnsupported`].
+    fn instantiate_component_stream(
+        pre_instance: &InstancePre<ComponentState>,
+        store: &mut Store<ComponentState>,
+        component_ref: &str,
+        ctx: &ComponentExecCtx,
+        operation: &str,
+        input_json: &str,
+    ) -> Result<Vec<component_api::node::StreamEvent>> {
+        match component_api::v0_5::ComponentPre::new(pre_instance.clone()) {
+            Ok(pre) => {
+                let events = block_on(async {
+                    let bindings = pre.instantiate_async(&mut *store).await?;
+                    let node = bindings.greentic_component_node();
+                    let ctx_v05 = component_api::exec_ctx_v0_5(ctx);
+                    node.call_invoke_stream(&mut *store, &ctx_v05, operation, input_json)
+                })?;
+                Ok(events
+                    .into_iter()
+                    .map(component_api::stream_event_from_v0_5)
+                    .collect())
+            }
+            Err(err) if is_missing_node_export(&err, "0.5.0") => {
+                let pre = match component_api::v0_4::ComponentPre::new(pre_instance.clone()) {
+                    Ok(pre) => pre,
+                    Err(err) if is_missing_node_export(&err, "0.4.0") => {
+                        return Err(StreamUnsupported {
+                            component_ref: component_ref.to_string(),
+                        }
+                        .into());
+                    }
+                    Err(err) => return Err(err),
+                };
+                let events = block_on(async {
+                    let bindings = pre.instantiate_async(&mut *store).await?;
+                    let node = bindings.greentic_component_node();
+                    let ctx_v04 = component_api::exec_ctx_v0_4(ctx);
+                    node.call_invoke_stream(&mut *store, &ctx_v04, operation, input_json)
+                })?;
+                Ok(events
+                    .into_iter()
+                    .map(component_api::stream_event_from_v0_4)
+                    .collect())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Call `on-start` or `on-stop` on a v0.5 or v0.4 node component. Returns `false`
+    /// without instantiating when the component exports neither node interface.
+    fn instantiate_component_lifecycle(
+        pre_instance: &InstancePre<ComponentState>,
+        store: &mut Store<ComponentState>,
+        ctx: &ComponentExecCtx,
+        phase: &LifecyclePhase,
+    ) -> Result<bool> {
+        let outcome = match component_api::v0_5::ComponentPre::new(pre_instance.clone()) {
+            Ok(pre) => block_on(async {
+                let bindings = pre.instantiate_async(&mut *store).await?;
+                let node = bindings.greentic_component_node();
+                let ctx_v05 = component_api::exec_ctx_v0_5(ctx);
+                let outcome = match phase {
+                    LifecyclePhase::Start => node.call_on_start(&mut *store, &ctx_v05)?,
+                    LifecyclePhase::Stop(reason) => {
+                        node.call_on_stop(&mut *store, &ctx_v05, reason)?
+                    }
+                };
+                Ok::<_, anyhow::Error>(outcome.map(|_| ()))
+            })?,
+            Err(err) if is_missing_node_export(&err, "0.5.0") => {
+                let pre = match component_api::v0_4::ComponentPre::new(pre_instance.clone()) {
+                    Ok(pre) => pre,
+                    Err(err) if is_missing_node_export(&err, "0.4.0") => return Ok(false),
+                    Err(err) => return Err(err),
+                };
+                block_on(async {
+                    let bindings = pre.instantiate_async(&mut *store).await?;
+                    let node = bindings.greentic_component_node();
+                    let ctx_v04 = component_api::exec_ctx_v0_4(ctx);
+                    let outcome = match phase {
+                        LifecyclePhase::Start => node.call_on_start(&mut *store, &ctx_v04)?,
+                        LifecyclePhase::Stop(reason) => {
+                            node.call_on_stop(&mut *store, &ctx_v04, reason)?
+                        }
+                    };
+                    Ok::<_, anyhow::Error>(outcome.map(|_| ()))
+                })?
+            }
+            Err(err) => return Err(err),
+        };
+        outcome.map_err(|message| anyhow!("component returned an error: {message}"))?;
+        Ok(true)
+    }
+
     /// Fallback for v0.6 components that export `component-runtime::run(input, state)`
     /// instead of the legacy `node::invoke(ctx, op, input)`.
     fn try_v06_runtime(
-        linker: &mut Linker<ComponentState>,
+        pre_instance: &InstancePre<ComponentState>,
         store: &mut Store<ComponentState>,
-        component: &Component,
         input_json: &str,
     ) -> Result<InvokeResult> {
-        let pre_instance = linker.instantiate_pre(component)?;
-        let pre = component_api::v0_6_runtime::ComponentV0V6RuntimePre::new(pre_instance)
+        let pre = component_api::v0_6_runtime::ComponentV0V6RuntimePre::new(pre_instance.clone())
             .context("component exports neither node@0.5/0.4 nor component-runtime@0.6")?;
 
         let result = block_on(async {
@@ -360,13 +639,11 @@ impl HostState {
             let runtime = bindings.greentic_component_component_runtime();
 
             // Encode input as CBOR — the component's run() expects CBOR bytes.
-            let input_value: Value =
-                serde_json::from_str(input_json).unwrap_or(Value::Null);
+            let input_value: Value = serde_json::from_str(input_json).unwrap_or(Value::Null);
             let input_cbor =
                 serde_cbor::to_vec(&input_value).context("encode input as CBOR for v0.6")?;
-            let empty_state =
-                serde_cbor::to_vec(&Value::Object(Default::default()))
-                    .context("encode empty state")?;
+            let empty_state = serde_cbor::to_vec(&Value::Object(Default::default()))
+                .context("encode empty state")?;
 
             let run_result = runtime
                 .call_run(&mut *store, &input_cbor, &empty_state)
@@ -433,12 +710,25 @@ impl HostState {
             return Ok(value);
         }
         let ctx = self.config.tenant_ctx();
-        let bytes = read_secret_blocking(&self.secrets, &ctx, &self.pack_id, key)
-            .context("failed to read secret from manager")?;
+        let bytes = read_secret_blocking(
+            &self.secrets,
+            &self.config.secrets_policy,
+            &ctx,
+            &self.pack_id,
+            key,
+        )
+        .context("failed to read secret from manager")?;
         let value = String::from_utf8(bytes).context("secret value is not valid UTF-8")?;
+        self.remember_secret(key, value.as_bytes());
         Ok(value)
     }
 
+    fn remember_secret(&self, key: &str, value: &[u8]) {
+        if let Some(redactor) = &self.redactor {
+            redactor.register_bytes(key, value);
+        }
+    }
+
     fn allows_secret_write_in_provider_core_only(&self) -> bool {
         self.provider_core_component || self.component_ref.is_none()
     }
@@ -662,6 +952,58 @@ mod canonicalize_tests {
     }
 }
 
+/// Add `config_json` to an object input as `_config`, leaving inputs that are not objects,
+/// or already carry `_config`, untouched.
+fn with_provider_config(input_json: Vec<u8>, config_json: Option<&str>) -> Vec<u8> {
+    let Some(config_json) = config_json else {
+        return input_json;
+    };
+    let Ok(Value::Object(mut input)) = serde_json::from_slice::<Value>(&input_json) else {
+        return input_json;
+    };
+    if input.contains_key("_config") {
+        return input_json;
+    }
+    let Ok(config) = serde_json::from_str::<Value>(config_json) else {
+        return input_json;
+    };
+    input.insert("_config".into(), config);
+    serde_json::to_vec(&input).unwrap_or(input_json)
+}
+
+#[cfg(test)]
+mod provider_config_tests {
+    use super::with_provider_config;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn config_is_added_to_object_input() {
+        let merged = with_provider_config(
+            br#"{"message":"ping"}"#.to_vec(),
+            Some(r#"{"region":"eu"}"#),
+        );
+        let value: Value = serde_json::from_slice(&merged).unwrap();
+        assert_eq!(
+            value,
+            json!({"message": "ping", "_config": {"region": "eu"}})
+        );
+    }
+
+    #[test]
+    fn caller_config_and_scalar_inputs_are_left_alone() {
+        let explicit = br#"{"_config":{"region":"us"}}"#.to_vec();
+        assert_eq!(
+            with_provider_config(explicit.clone(), Some(r#"{"region":"eu"}"#)),
+            explicit
+        );
+        let scalar = br#""ping""#.to_vec();
+        assert_eq!(
+            with_provider_config(scalar.clone(), Some(r#"{"region":"eu"}"#)),
+            scalar
+        );
+    }
+}
+
 impl SecretsStoreHost for HostState {
     fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, SecretsError> {
         if provider_core_only::is_enabled() {
@@ -678,8 +1020,17 @@ impl SecretsStoreHost for HostState {
         }
         let ctx = self.config.tenant_ctx();
         let canonical_key = canonicalize_wasm_secret_key(&key);
-        match read_secret_blocking(&self.secrets, &ctx, &self.pack_id, &canonical_key) {
-            Ok(bytes) => Ok(Some(bytes)),
+        match read_secret_blocking(
+            &self.secrets,
+            &self.config.secrets_policy,
+            &ctx,
+            &self.pack_id,
+            &canonical_key,
+        ) {
+            Ok(bytes) => {
+                self.remember_secret(&key, &bytes);
+                Ok(Some(bytes))
+            }
             Err(err) => {
                 warn!(secret = %key, canonical = %canonical_key, error = %err, "secret lookup failed");
                 Err(SecretsError::NotFound)
@@ -704,8 +1055,17 @@ impl SecretsStoreHostV1_1 for HostState {
         }
         let ctx = self.config.tenant_ctx();
         let canonical_key = canonicalize_wasm_secret_key(&key);
-        match read_secret_blocking(&self.secrets, &ctx, &self.pack_id, &canonical_key) {
-            Ok(bytes) => Ok(Some(bytes)),
+        match read_secret_blocking(
+            &self.secrets,
+            &self.config.secrets_policy,
+            &ctx,
+            &self.pack_id,
+            &canonical_key,
+        ) {
+            Ok(bytes) => {
+                self.remember_secret(&key, &bytes);
+                Ok(Some(bytes))
+            }
             Err(err) => {
                 warn!(secret = %key, canonical = %canonical_key, error = %err, "secret lookup failed");
                 Err(SecretsErrorV1_1::NotFound)
@@ -726,15 +1086,20 @@ impl SecretsStoreHostV1_1 for HostState {
             );
             panic!("secret write denied for key {key}: provider-core-only mode");
         }
-        if !self.config.secrets_policy.is_allowed(&key) {
+        if !self.config.secrets_policy.is_write_allowed(&key) {
             warn!(secret = %key, "secret write denied by bindings policy");
             panic!("secret write denied for key {key}: policy");
         }
         let ctx = self.config.tenant_ctx();
         let canonical_key = canonicalize_wasm_secret_key(&key);
-        if let Err(err) =
-            write_secret_blocking(&self.secrets, &ctx, &self.pack_id, &canonical_key, &value)
-        {
+        if let Err(err) = write_secret_blocking(
+            &self.secrets,
+            &self.config.secrets_policy,
+            &ctx,
+            &self.pack_id,
+            &canonical_key,
+            &value,
+        ) {
             warn!(secret = %key, canonical = %canonical_key, error = %err, "secret write failed");
             panic!("secret write failed for key {key}");
         }
@@ -911,10 +1276,20 @@ impl StateStoreHost for HostState {
             .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string()));
         match store.set_json(&tenant_ctx, STATE_PREFIX, &key, None, &value, None) {
             Ok(()) => Ok(StateOpAck::Ok),
-            Err(err) => Err(StateError {
-                code: "internal".into(),
-                message: err.to_string(),
-            }),
+            Err(err) => match quota::breach(&err) {
+                Some(hit) => {
+                    let message = hit.to_string();
+                    self.state_quota_exceeded.get_or_insert_with(|| hit.clone());
+                    Err(StateError {
+                        code: "quota_exceeded".into(),
+                        message,
+                    })
+                }
+                None => Err(StateError {
+                    code: "internal".into(),
+                    message: err.to_string(),
+                }),
+            },
         }
     }
 
@@ -1030,8 +1405,7 @@ enum ManifestLoad {
 }
 
 fn load_manifest_and_flows(path: &Path) -> Result<ManifestLoad> {
-    let mut archive = ZipArchive::new(File::open(path)?)
-        .with_context(|| format!("{} is not a valid gtpack", path.display()))?;
+    let mut archive = gtpack::open(path)?;
     let bytes = read_entry(&mut archive, "manifest.cbor")
         .with_context(|| format!("missing manifest.cbor in {}", path.display()))?;
     match decode_pack_manifest(&bytes) {
@@ -1088,6 +1462,18 @@ fn load_manifest_and_flows_from_dir(root: &Path) -> Result<ManifestLoad> {
     }
 }
 
+/// Metadata and flows of a `.gtpack` or materialized pack directory, read from its
+/// manifest without resolving or compiling components.
+pub fn read_pack_flows(path: &Path) -> Result<(PackMetadata, Vec<FlowDescriptor>)> {
+    let load = if path.is_dir() {
+        load_manifest_and_flows_from_dir(path)?
+    } else {
+        load_manifest_and_flows(path)?
+    };
+    let (ManifestLoad::New { flows, .. } | ManifestLoad::Legacy { flows, .. }) = load;
+    Ok((flows.metadata, flows.descriptors))
+}
+
 fn load_legacy_flows_from_dir(
     root: &Path,
     manifest: &legacy_pack::PackManifest,
@@ -1169,30 +1555,123 @@ pub struct ComponentState {
     pub host: HostState,
     wasi_ctx: WasiCtx,
     resource_table: ResourceTable,
+    limiter: WasmLimiter,
+    cancel: CancelWatch,
+    stdio: StdioCapture,
+    stdio_sink: Option<StdioSink>,
 }
 
 impl ComponentState {
     pub fn new(host: HostState, policy: Arc<RunnerWasiPolicy>) -> Result<Self> {
+        let stdio = StdioCapture::new();
         let wasi_ctx = policy
-            .instantiate()
+            .instantiate(&stdio)
             .context("failed to build WASI context")?;
         Ok(Self {
             host,
             wasi_ctx,
             resource_table: ResourceTable::new(),
+            limiter: WasmLimiter::default(),
+            cancel: CancelWatch::default(),
+            stdio,
+            stdio_sink: None,
         })
     }
 
+    /// Apply `limits`, capping the CPU deadline at the request deadline when given.
+    pub fn with_limits(mut self, limits: WasmLimits, deadline_unix_ms: Option<u64>) -> Self {
+        self.limiter = WasmLimiter::new(limits, deadline_unix_ms);
+        self
+    }
+
+    /// Back `control.should-cancel` with `cancel` instead of a token only the deadline
+    /// can trip.
+    pub fn with_cancel(mut self, cancel: InvocationCancel) -> Self {
+        self.cancel = CancelWatch::new(cancel);
+        self
+    }
+
+    /// Publish the component's stdout/stderr to `sink` when the store is dropped; without
+    /// one the output is logged.
+    pub fn with_stdio(mut self, sink: Option<StdioSink>) -> Self {
+        self.stdio_sink = sink;
+        self
+    }
+
+    /// Register secrets the component reads with the invocation's `redactor`.
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.host.redactor = redactor;
+        self
+    }
+
+    /// Store for invoking a component with this state's memory/table limits and epoch
+    /// deadline installed.
+    ///
+    /// The store checks its cancellation state on every epoch tick: once the deadline
+    /// passes or the invocation is cancelled, `should-cancel` reports `true`, and a
+    /// component still running [`cancel::CANCEL_GRACE`] later is trapped.
+    pub fn into_store(self, engine: &Engine) -> Store<Self> {
+        let mut store = Store::new(engine, self);
+        store.limiter(|state| &mut state.limiter);
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(|mut ctx| {
+            let state = ctx.data_mut();
+            let budget = state.limiter.deadline().budget;
+            match state.cancel.on_tick(budget) {
+                None => Ok(UpdateDeadline::Continue(1)),
+                Some(CancelReason::Deadline) => Err(state.limiter.interrupt().into()),
+                Some(reason) => Err(Cancelled { reason }.into()),
+            }
+        });
+        store
+    }
+
+    /// Attach any recorded limit hit or state quota breach, and trap details for
+    /// `export`, to an invocation error.
+    pub fn explain_error(&self, err: anyhow::Error, export: &str) -> anyhow::Error {
+        let err =
+            wasm_engine::explain_pool_error(self.limiter.explain(trap::explain_trap(err, export)));
+        match &self.host.state_quota_exceeded {
+            Some(hit) => err.context(hit.clone()),
+            None => err,
+        }
+    }
+
     fn host_mut(&mut self) -> &mut HostState {
         &mut self.host
     }
 
     fn should_cancel_host(&mut self) -> bool {
-        false
+        self.cancel.is_cancelled()
     }
 
     fn yield_now_host(&mut self) {
-        // no-op cooperative yield
+        // Components run on a dedicated thread, so yielding the OS thread is the only
+        // scheduling point available.
+        std::thread::yield_now();
+    }
+}
+
+impl Drop for ComponentState {
+    fn drop(&mut self) {
+        match &self.stdio_sink {
+            Some(sink) => sink.record(&self.stdio),
+            None => {
+                let mut output = self.stdio.output();
+                if let Some(redactor) = &self.host.redactor {
+                    output = redactor.scrub_stdio(output);
+                }
+                if !output.is_empty() {
+                    tracing::info!(
+                        component = ?self.host.component_ref,
+                        stdout = %output.stdout,
+                        stderr = %output.stderr,
+                        truncated_bytes = output.truncated_bytes,
+                        "component stdio"
+                    );
+                }
+            }
+        }
     }
 }
 
@@ -1404,6 +1883,37 @@ unsafe impl Send for ComponentState {}
 unsafe impl Sync for ComponentState {}
 
 impl PackRuntime {
+    /// Linked `InstancePre` for `component_ref`, reused across invocations so only
+    /// store-specific instantiation runs per call.
+    async fn instance_pre(
+        &self,
+        component_ref: &str,
+        world: &str,
+    ) -> Result<InstancePre<ComponentState>> {
+        let component = self.component(component_ref).await?;
+        let allow_state_store = self.allows_state_store(component_ref);
+        let key = InstancePreKey {
+            component_ref: component_ref.to_string(),
+            world: world.to_string(),
+            linker: if allow_state_store {
+                LINKER_WITH_STATE_STORE
+            } else {
+                LINKER_DEFAULT
+            },
+        };
+        self.pre_cache.get_or_link(key, || {
+            let mut linker = Linker::new(&self.engine);
+            register_all(&mut linker, allow_state_store)?;
+            add_component_control_to_linker(&mut linker)?;
+            Ok(linker.instantiate_pre(component.as_ref())?)
+        })
+    }
+
+    /// Hit/link counters for this pack's `InstancePre` cache.
+    pub fn instance_pre_stats(&self) -> InstancePreStats {
+        self.pre_cache.stats()
+    }
+
     fn allows_state_store(&self, component_ref: &str) -> bool {
         if self.state_store.is_none() {
             return false;
@@ -1427,6 +1937,57 @@ impl PackRuntime {
         self.components.contains_key(component_ref)
     }
 
+    /// Components shipped by the pack.
+    pub fn component_count(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Components compiled so far; lower than [`Self::component_count`] while lazily
+    /// loaded components have not been used yet.
+    pub fn compiled_component_count(&self) -> usize {
+        self.components
+            .values()
+            .filter(|entry| entry.component.initialized())
+            .count()
+    }
+
+    /// Compiled `component_ref`, compiling it through the artifact cache on first use
+    /// when loading is lazy.
+    async fn component(&self, component_ref: &str) -> Result<Arc<Component>> {
+        let entry = self
+            .components
+            .get(component_ref)
+            .with_context(|| format!("component '{component_ref}' not found in pack"))?;
+        let component = entry
+            .component
+            .get_or_try_init(|| async {
+                let bytes = entry.deferred.as_ref().with_context(|| {
+                    format!("component '{component_ref}' has no compiled artifact")
+                })?;
+                tracing::debug!(
+                    pack_id = %self.metadata.pack_id,
+                    component_ref,
+                    "compiling lazily loaded component"
+                );
+                bytes.compile(&self.cache, &self.engine).await
+            })
+            .await?;
+        Ok(Arc::clone(component))
+    }
+
+    /// Compile every component deferred by lazy loading, e.g. when strict preflight
+    /// wants load-time failures.
+    pub async fn compile_components(&self) -> Result<()> {
+        precompile_components(
+            &self.cache,
+            &self.engine,
+            &self.metadata.pack_id,
+            &self.components,
+            self.config.compile_concurrency_limit(),
+        )
+        .await
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn load(
         path: impl AsRef<Path>,
@@ -1441,6 +2002,13 @@ impl PackRuntime {
         verify_archive: bool,
         component_resolution: ComponentResolution,
     ) -> Result<Self> {
+        let state_store = state_store.map(|store| {
+            encryption::apply_encryption(
+                quota::apply_quota(store, config.state_store_policy.quota),
+                config.state_store_policy.encryption.as_ref(),
+                Arc::clone(&secrets),
+            )
+        });
         let path = path.as_ref();
         let (_pack_root, safe_path) = normalize_pack_path(path)?;
         let path_meta = std::fs::metadata(&safe_path).ok();
@@ -1476,9 +2044,8 @@ impl PackRuntime {
                 tracing::debug!("skipping archive verification (no archive source)");
             }
         }
-        let engine = Engine::default();
-        let engine_profile =
-            EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+        let engine = wasm_engine::new_engine(&config.wasm_engine)?;
+        let engine_profile = wasm_engine::engine_profile(&engine, &config.wasm_engine);
         let cache = CacheManager::new(CacheConfig::default(), engine_profile);
         let mut metadata = PackMetadata::fallback(&safe_path);
         let mut manifest = None;
@@ -1593,6 +2160,7 @@ impl PackRuntime {
         } else {
             component_sources_table(component_sources_payload.as_ref())?
         };
+        let mut component_digests = BTreeMap::new();
         let components = if is_component {
             let wasm_bytes = fs::read(&safe_path).await?;
             metadata = PackMetadata::from_wasm(&wasm_bytes)
@@ -1601,16 +2169,26 @@ impl PackRuntime {
                 .file_stem()
                 .map(|s| s.to_string_lossy().to_string())
                 .unwrap_or_else(|| "component".to_string());
-            let component = compile_component_with_cache(&cache, &engine, None, wasm_bytes).await?;
+            component_digests.insert(name.clone(), compute_sha256_digest_for(&wasm_bytes));
+            let component = match config.component_loading {
+                ComponentLoading::Eager => PackComponent::compiled(
+                    name.clone(),
+                    metadata.version.clone(),
+                    compile_component_with_cache(&cache, &engine, None, wasm_bytes).await?,
+                ),
+                ComponentLoading::Lazy => PackComponent::deferred(
+                    name.clone(),
+                    metadata.version.clone(),
+                    ComponentBytes {
+                        component_id: name.clone(),
+                        location: ComponentLocation::File(safe_path.clone()),
+                        cache_digest: None,
+                        check: DigestCheck::None,
+                    },
+                ),
+            };
             let mut map = HashMap::new();
-            map.insert(
-                name.clone(),
-                PackComponent {
-                    name,
-                    version: metadata.version.clone(),
-                    component,
-                },
-            );
+            map.insert(name, component);
             map
         } else {
             let specs = component_specs(
@@ -1629,21 +2207,16 @@ impl PackRuntime {
 
                 if !component_resolution.overrides.is_empty() {
                     load_components_from_overrides(
-                        &cache,
-                        &engine,
                         &component_resolution.overrides,
                         &specs,
                         &mut missing,
                         &mut loaded,
-                    )
-                    .await?;
+                    )?;
                     searched.push("override map".to_string());
                 }
 
                 if let Some(component_sources) = component_sources.as_ref() {
                     load_components_from_sources(
-                        &cache,
-                        &engine,
                         component_sources,
                         &component_resolution,
                         &specs,
@@ -1657,28 +2230,12 @@ impl PackRuntime {
                 }
 
                 if let Some(root) = materialized_root.as_ref() {
-                    load_components_from_dir(
-                        &cache,
-                        &engine,
-                        root,
-                        &specs,
-                        &mut missing,
-                        &mut loaded,
-                    )
-                    .await?;
+                    load_components_from_dir(root, &specs, &mut missing, &mut loaded);
                     searched.push(format!("components dir {}", root.display()));
                 }
 
                 if let Some(archive_path) = archive_hint {
-                    load_components_from_archive(
-                        &cache,
-                        &engine,
-                        archive_path,
-                        &specs,
-                        &mut missing,
-                        &mut loaded,
-                    )
-                    .await?;
+                    load_components_from_archive(archive_path, &specs, &mut missing, &mut loaded)?;
                     searched.push(format!("archive {}", archive_path.display()));
                 }
 
@@ -1695,6 +2252,24 @@ impl PackRuntime {
                         sources
                     );
                 }
+                for (component_ref, component) in &loaded {
+                    if let Some(bytes) = component.deferred.as_ref()
+                        && component_resolution.cache_refresh.includes(component_ref)
+                    {
+                        bytes.invalidate(&cache)?;
+                    }
+                }
+                component_digests = digest_components(&metadata.pack_id, &loaded).await?;
+                if config.component_loading == ComponentLoading::Eager {
+                    precompile_components(
+                        &cache,
+                        &engine,
+                        &metadata.pack_id,
+                        &loaded,
+                        config.compile_concurrency_limit(),
+                    )
+                    .await?;
+                }
 
                 loaded
             }
@@ -1712,6 +2287,11 @@ impl PackRuntime {
             pack_policy =
                 pack_policy.with_preopen(PreopenSpec::new(dir, "/assets").read_only(true));
         }
+        let mut pack_policy =
+            pack_policy.with_tenant_preopens(&config.tenant, &config.wasi.preopens)?;
+        if let Some(deterministic) = config.wasi.deterministic {
+            pack_policy = pack_policy.with_deterministic(deterministic);
+        }
         let wasi_policy = Arc::new(pack_policy);
         Ok(Self {
             path: safe_path,
@@ -1725,8 +2305,9 @@ impl PackRuntime {
             mocks,
             flows,
             components,
+            component_digests,
             http_client,
-            pre_cache: Mutex::new(HashMap::new()),
+            pre_cache: InstancePreCache::from_env(),
             session_store,
             state_store,
             wasi_policy,
@@ -1735,6 +2316,7 @@ impl PackRuntime {
             secrets,
             oauth_config,
             cache,
+            started_components: tokio::sync::Mutex::new(BTreeSet::new()),
         })
     }
 
@@ -1815,6 +2397,138 @@ impl PackRuntime {
         }
     }
 
+    /// Run `on-start` for every component not started yet. A failure fails the call
+    /// only when `lifecycle.on_start_error` is `fail`.
+    pub async fn start_components(&self) -> Result<()> {
+        let mut component_refs = self.components.keys().cloned().collect::<Vec<_>>();
+        component_refs.sort();
+        for component_ref in component_refs {
+            self.ensure_started(&component_ref).await?;
+        }
+        Ok(())
+    }
+
+    /// Run `on-stop` with `reason` for every started component, each bounded by
+    /// `lifecycle.stop_timeout_ms`. Failures are logged and the pack counts as stopped.
+    pub async fn stop_components(&self, reason: &str) {
+        let started = std::mem::take(&mut *self.started_components.lock().await);
+        let deadline_unix_ms = now_unix_ms().saturating_add(self.config.lifecycle.stop_timeout_ms);
+        for component_ref in started {
+            let phase = LifecyclePhase::Stop(reason.to_string());
+            if let Err(err) = self
+                .call_lifecycle(&component_ref, phase, Some(deadline_unix_ms))
+                .await
+            {
+                warn!(
+                    pack_id = %self.metadata.pack_id,
+                    component_ref = %component_ref,
+                    reason,
+                    error = %err,
+                    "component on-stop failed"
+                );
+            }
+        }
+    }
+
+    async fn ensure_started(&self, component_ref: &str) -> Result<()> {
+        let mut started = self.started_components.lock().await;
+        if started.contains(component_ref) {
+            return Ok(());
+        }
+        if let Err(err) = self
+            .call_lifecycle(component_ref, LifecyclePhase::Start, None)
+            .await
+        {
+            let err = err.context(format!("component {component_ref} on-start failed"));
+            if self.config.lifecycle.on_start_error == StartErrorPolicy::Fail {
+                return Err(err);
+            }
+            warn!(
+                pack_id = %self.metadata.pack_id,
+                component_ref,
+                error = %format!("{err:#}"),
+                "continuing after component on-start failure"
+            );
+        }
+        started.insert(component_ref.to_string());
+        Ok(())
+    }
+
+    async fn call_lifecycle(
+        &self,
+        component_ref: &str,
+        phase: LifecyclePhase,
+        deadline_unix_ms: Option<u64>,
+    ) -> Result<()> {
+        let pre_instance = self.instance_pre(component_ref, NODE_WORLD).await?;
+        let engine = self.engine.clone();
+        let config = Arc::clone(&self.config);
+        let http_client = Arc::clone(&self.http_client);
+        let mocks = self.mocks.clone();
+        let session_store = self.session_store.clone();
+        let state_store = self.state_store.clone();
+        let secrets = Arc::clone(&self.secrets);
+        let oauth_config = self.oauth_config.clone();
+        let wasi_policy = Arc::clone(&self.wasi_policy);
+        let pack_id = self.metadata().pack_id.clone();
+        let component_ref_owned = component_ref.to_string();
+        let ctx = self.lifecycle_exec_ctx(deadline_unix_ms);
+        let limits = config.wasm_limits;
+        let cancel = cancel::current().unwrap_or_default();
+        let stdio_sink = stdio::current();
+        let redactor = redact::current();
+
+        run_on_wasi_thread("component.lifecycle", move || {
+            let host_state = HostState::new(
+                pack_id,
+                config,
+                http_client,
+                mocks,
+                session_store,
+                state_store,
+                secrets,
+                oauth_config,
+                Some(ctx.clone()),
+                Some(component_ref_owned),
+                false,
+            )?;
+            let store_state = ComponentState::new(host_state, wasi_policy)?
+                .with_limits(limits, deadline_unix_ms)
+                .with_cancel(cancel)
+                .with_stdio(stdio_sink)
+                .with_redactor(redactor);
+            let mut store = store_state.into_store(&engine);
+            HostState::instantiate_component_lifecycle(&pre_instance, &mut store, &ctx, &phase)
+                .map(|_| ())
+                .map_err(|err| store.data().explain_error(err, phase.export()))
+        })
+    }
+
+    /// Context for lifecycle calls: the tenant and its configured attributes, no flow.
+    fn lifecycle_exec_ctx(&self, deadline_unix_ms: Option<u64>) -> ComponentExecCtx {
+        let attributes =
+            component_api::tenant_attributes(&self.config.attributes, &BTreeMap::new())
+                .unwrap_or_default();
+        ComponentExecCtx {
+            tenant: ComponentTenantCtx {
+                tenant: self.config.tenant.clone(),
+                team: None,
+                user: None,
+                trace_id: None,
+                i18n_id: None,
+                correlation_id: None,
+                deadline_unix_ms,
+                attempt: 1,
+                idempotency_key: None,
+                attributes,
+                impersonation: None,
+            },
+            i18n_id: None,
+            flow_id: String::new(),
+            node_id: None,
+        }
+    }
+
     pub async fn invoke_component(
         &self,
         component_ref: &str,
@@ -1823,10 +2537,10 @@ impl PackRuntime {
         _config_json: Option<String>,
         input_json: String,
     ) -> Result<Value> {
-        let pack_component = self
-            .components
-            .get(component_ref)
-            .with_context(|| format!("component '{component_ref}' not found in pack"))?;
+        if self.config.starts_components_lazily() {
+            self.ensure_started(component_ref).await?;
+        }
+        let pre_instance = self.instance_pre(component_ref, NODE_WORLD).await?;
         let engine = self.engine.clone();
         let config = Arc::clone(&self.config);
         let http_client = Arc::clone(&self.http_client);
@@ -1837,18 +2551,17 @@ impl PackRuntime {
         let oauth_config = self.oauth_config.clone();
         let wasi_policy = Arc::clone(&self.wasi_policy);
         let pack_id = self.metadata().pack_id.clone();
-        let allow_state_store = self.allows_state_store(component_ref);
-        let component = pack_component.component.clone();
         let component_ref_owned = component_ref.to_string();
         let operation_owned = operation.to_string();
         let input_owned = input_json;
+        let deadline_unix_ms = ctx.tenant.deadline_unix_ms;
         let ctx_owned = ctx;
+        let limits = config.wasm_limits;
+        let cancel = cancel::current().unwrap_or_default();
+        let stdio_sink = stdio::current();
+        let redactor = redact::current();
 
-        run_on_wasi_thread("component.invoke", move || {
-            let mut linker = Linker::new(&engine);
-            register_all(&mut linker, allow_state_store)?;
-            add_component_control_to_linker(&mut linker)?;
-
+        spawn_on_wasi_thread("component.invoke", move || {
             let host_state = HostState::new(
                 pack_id.clone(),
                 config,
@@ -1862,19 +2575,106 @@ impl PackRuntime {
                 Some(component_ref_owned.clone()),
                 false,
             )?;
-            let store_state = ComponentState::new(host_state, wasi_policy)?;
-            let mut store = wasmtime::Store::new(&engine, store_state);
+            let store_state = ComponentState::new(host_state, wasi_policy)?
+                .with_limits(limits, deadline_unix_ms)
+                .with_cancel(cancel)
+                .with_stdio(stdio_sink)
+                .with_redactor(redactor);
+            let mut store = store_state.into_store(&engine);
 
             let invoke_result = HostState::instantiate_component_result(
-                &mut linker,
+                &pre_instance,
                 &mut store,
-                &component,
                 &ctx_owned,
                 &operation_owned,
                 &input_owned,
-            )?;
+            )
+            .map_err(|err| store.data().explain_error(err, "invoke"))?;
             HostState::convert_invoke_result(invoke_result)
         })
+        .await
+    }
+
+    /// Like [`Self::invoke_component`], but calls the component's `invoke-stream` export and
+    /// hands every event to `observer` in order. The WIT returns the events as one list, so
+    /// the observer sees them when the call returns, before this future resolves.
+    ///
+    /// Resolves to the last `data` payload (`null` when there is none); an `error` event
+    /// fails the invocation.
+    pub async fn invoke_component_stream(
+        &self,
+        component_ref: &str,
+        ctx: ComponentExecCtx,
+        operation: &str,
+        input_json: String,
+        observer: Arc<dyn StreamObserver>,
+    ) -> Result<Value> {
+        if self.config.starts_components_lazily() {
+            self.ensure_started(component_ref).await?;
+        }
+        let pre_instance = self.instance_pre(component_ref, NODE_WORLD).await?;
+        let engine = self.engine.clone();
+        let config = Arc::clone(&self.config);
+        let http_client = Arc::clone(&self.http_client);
+        let mocks = self.mocks.clone();
+        let session_store = self.session_store.clone();
+        let state_store = self.state_store.clone();
+        let secrets = Arc::clone(&self.secrets);
+        let oauth_config = self.oauth_config.clone();
+        let wasi_policy = Arc::clone(&self.wasi_policy);
+        let pack_id = self.metadata().pack_id.clone();
+        let component_ref_owned = component_ref.to_string();
+        let operation_owned = operation.to_string();
+        let deadline_unix_ms = ctx.tenant.deadline_unix_ms;
+        let limits = config.wasm_limits;
+        let cancel = cancel::current().unwrap_or_default();
+        let stdio_sink = stdio::current();
+        let redactor = redact::current();
+
+        spawn_on_wasi_thread("component.invoke_stream", move || {
+            let host_state = HostState::new(
+                pack_id.clone(),
+                config,
+                http_client,
+                mocks,
+                session_store,
+                state_store,
+                secrets,
+                oauth_config,
+                Some(ctx.clone()),
+                Some(component_ref_owned.clone()),
+                false,
+            )?;
+            let store_state = ComponentState::new(host_state, wasi_policy)?
+                .with_limits(limits, deadline_unix_ms)
+                .with_cancel(cancel)
+                .with_stdio(stdio_sink)
+                .with_redactor(redactor);
+            let mut store = store_state.into_store(&engine);
+
+            let events = HostState::instantiate_component_stream(
+                &pre_instance,
+                &mut store,
+                &component_ref_owned,
+                &ctx,
+                &operation_owned,
+                &input_json,
+            )
+            .map_err(|err| store.data().explain_error(err, "invoke-stream"))?;
+            let mut output = Value::Null;
+            for event in events.into_iter().map(StreamEvent::from) {
+                observer.on_event(&event);
+                match event {
+                    StreamEvent::Data(data) => output = data,
+                    StreamEvent::Error(message) => {
+                        bail!("component {component_ref_owned} stream failed: {message}")
+                    }
+                    StreamEvent::Progress(_) | StreamEvent::Done => {}
+                }
+            }
+            Ok(output)
+        })
+        .await
     }
 
     pub fn resolve_provider(
@@ -1886,6 +2686,17 @@ impl PackRuntime {
         registry.resolve(provider_id, provider_type)
     }
 
+    /// Config of the stored provider instance `provider_id`; `None` when the pack has no
+    /// manifest, no such instance exists or it carries no config.
+    pub fn provider_instance_config(&self, provider_id: &str) -> Result<Option<Value>> {
+        match self.provider_registry_optional()? {
+            Some(registry) => registry.instance_config(provider_id),
+            None => Ok(None),
+        }
+    }
+
+    /// Invoke provider `op`; `binding.config_json`, when set, reaches the component as
+    /// the `_config` field of an object input.
     pub async fn invoke_provider(
         &self,
         binding: &ProviderBinding,
@@ -1894,10 +2705,11 @@ impl PackRuntime {
         input_json: Vec<u8>,
     ) -> Result<Value> {
         let component_ref_owned = binding.component_ref.clone();
-        let pack_component = self.components.get(&component_ref_owned).with_context(|| {
-            format!("provider component '{component_ref_owned}' not found in pack")
-        })?;
-        let component = pack_component.component.clone();
+        if !self.components.contains_key(&component_ref_owned) {
+            bail!("provider component '{component_ref_owned}' not found in pack");
+        }
+        let world = binding.world.clone();
+        let pre_instance = self.instance_pre(&component_ref_owned, &world).await?;
 
         let engine = self.engine.clone();
         let config = Arc::clone(&self.config);
@@ -1909,17 +2721,16 @@ impl PackRuntime {
         let oauth_config = self.oauth_config.clone();
         let wasi_policy = Arc::clone(&self.wasi_policy);
         let pack_id = self.metadata().pack_id.clone();
-        let allow_state_store = self.allows_state_store(&component_ref_owned);
-        let input_owned = input_json;
+        let input_owned = with_provider_config(input_json, binding.config_json.as_deref());
         let op_owned = op.to_string();
+        let deadline_unix_ms = ctx.tenant.deadline_unix_ms;
         let ctx_owned = ctx;
-        let world = binding.world.clone();
+        let limits = config.wasm_limits;
+        let cancel = cancel::current().unwrap_or_default();
+        let stdio_sink = stdio::current();
+        let redactor = redact::current();
 
-        run_on_wasi_thread("provider.invoke", move || {
-            let mut linker = Linker::new(&engine);
-            register_all(&mut linker, allow_state_store)?;
-            add_component_control_to_linker(&mut linker)?;
-            let mut pre_instance = Some(linker.instantiate_pre(component.as_ref())?);
+        spawn_on_wasi_thread("provider.invoke", move || {
             let host_state = HostState::new(
                 pack_id.clone(),
                 config,
@@ -1933,31 +2744,33 @@ impl PackRuntime {
                 Some(component_ref_owned.clone()),
                 true,
             )?;
-            let store_state = ComponentState::new(host_state, wasi_policy)?;
-            let mut store = wasmtime::Store::new(&engine, store_state);
+            let store_state = ComponentState::new(host_state, wasi_policy)?
+                .with_limits(limits, deadline_unix_ms)
+                .with_cancel(cancel)
+                .with_stdio(stdio_sink)
+                .with_redactor(redactor);
+            let mut store = store_state.into_store(&engine);
             let use_schema_core =
                 world.contains("provider-schema-core") || world.contains("provider/schema-core");
-            let result = if use_schema_core {
-                let pre_instance = pre_instance
-                    .take()
-                    .ok_or_else(|| anyhow!("provider pre_instance already consumed"))?;
-                let pre: SchemaSchemaCorePre<ComponentState> =
-                    SchemaSchemaCorePre::new(pre_instance)?;
-                let bindings = block_on(async { pre.instantiate_async(&mut store).await })?;
-                let provider = bindings.greentic_provider_schema_core_schema_core_api();
-                provider.call_invoke(&mut store, &op_owned, &input_owned)?
-            } else {
-                let pre_instance = pre_instance
-                    .take()
-                    .ok_or_else(|| anyhow!("provider pre_instance already consumed"))?;
-                let pre: LegacySchemaCorePre<ComponentState> =
-                    LegacySchemaCorePre::new(pre_instance)?;
-                let bindings = block_on(async { pre.instantiate_async(&mut store).await })?;
-                let provider = bindings.greentic_provider_core_schema_core_api();
-                provider.call_invoke(&mut store, &op_owned, &input_owned)?
+            let invoke = || -> Result<Vec<u8>> {
+                if use_schema_core {
+                    let pre: SchemaSchemaCorePre<ComponentState> =
+                        SchemaSchemaCorePre::new(pre_instance)?;
+                    let bindings = block_on(async { pre.instantiate_async(&mut store).await })?;
+                    let provider = bindings.greentic_provider_schema_core_schema_core_api();
+                    Ok(provider.call_invoke(&mut store, &op_owned, &input_owned)?)
+                } else {
+                    let pre: LegacySchemaCorePre<ComponentState> =
+                        LegacySchemaCorePre::new(pre_instance)?;
+                    let bindings = block_on(async { pre.instantiate_async(&mut store).await })?;
+                    let provider = bindings.greentic_provider_core_schema_core_api();
+                    Ok(provider.call_invoke(&mut store, &op_owned, &input_owned)?)
+                }
             };
+            let result = invoke().map_err(|err| store.data().explain_error(err, "invoke"))?;
             deserialize_json_bytes(result)
         })
+        .await
     }
 
     pub(crate) fn provider_registry(&self) -> Result<ProviderRegistry> {
@@ -2009,6 +2822,15 @@ impl PackRuntime {
         &self.metadata
     }
 
+    pub(crate) fn wasi_policy(&self) -> Arc<RunnerWasiPolicy> {
+        Arc::clone(&self.wasi_policy)
+    }
+
+    /// Component artifact cache backing this pack's compiled components.
+    pub fn cache(&self) -> &CacheManager {
+        &self.cache
+    }
+
     /// Read an asset file from the pack's assets directory.
     ///
     /// Accepts paths like `assets/cards/card-a.json` or `cards/card-a.json`
@@ -2028,8 +2850,7 @@ impl PackRuntime {
         // Try materialized directory.
         let full = self.path.join("assets").join(normalized);
         if full.exists() {
-            return std::fs::read(&full)
-                .with_context(|| format!("read asset {}", full.display()));
+            return std::fs::read(&full).with_context(|| format!("read asset {}", full.display()));
         }
         bail!("asset not found: {}", asset_path)
     }
@@ -2038,11 +2859,28 @@ impl PackRuntime {
         self.component_manifests.get(component_ref)
     }
 
-    pub fn describe_component_contract_v0_6(&self, component_ref: &str) -> Result<Option<Value>> {
-        let pack_component = self
-            .components
+    /// `sha256:<hex>` of the component's wasm as verified at load.
+    pub fn component_digest(&self, component_ref: &str) -> Option<&str> {
+        self.component_digests
             .get(component_ref)
-            .with_context(|| format!("component '{component_ref}' not found in pack"))?;
+            .map(String::as_str)
+    }
+
+    /// Digest over every component id and wasm digest, identifying the pack's code
+    /// when no artifact digest is known (e.g. packs loaded from a directory).
+    pub fn content_digest(&self) -> String {
+        let mut hasher = sha2::Sha256::new();
+        for (component_ref, digest) in &self.component_digests {
+            hasher.update(component_ref.as_bytes());
+            hasher.update(b"=");
+            hasher.update(digest.as_bytes());
+            hasher.update(b"\n");
+        }
+        format!("sha256:{:x}", hasher.finalize())
+    }
+
+    pub fn describe_component_contract_v0_6(&self, component_ref: &str) -> Result<Option<Value>> {
+        let pre_instance = block_on(self.instance_pre(component_ref, DESCRIPTOR_WORLD))?;
         let engine = self.engine.clone();
         let config = Arc::clone(&self.config);
         let http_client = Arc::clone(&self.http_client);
@@ -2053,15 +2891,14 @@ impl PackRuntime {
         let oauth_config = self.oauth_config.clone();
         let wasi_policy = Arc::clone(&self.wasi_policy);
         let pack_id = self.metadata().pack_id.clone();
-        let allow_state_store = self.allows_state_store(component_ref);
-        let component = pack_component.component.clone();
         let component_ref_owned = component_ref.to_string();
+        let limits = config.wasm_limits;
+        let deadline_unix_ms = None;
+        let cancel = cancel::current().unwrap_or_default();
+        let stdio_sink = stdio::current();
+        let redactor = redact::current();
 
         run_on_wasi_thread("component.describe", move || {
-            let mut linker = Linker::new(&engine);
-            register_all(&mut linker, allow_state_store)?;
-            add_component_control_to_linker(&mut linker)?;
-
             let host_state = HostState::new(
                 pack_id.clone(),
                 config,
@@ -2075,9 +2912,12 @@ impl PackRuntime {
                 Some(component_ref_owned),
                 false,
             )?;
-            let store_state = ComponentState::new(host_state, wasi_policy)?;
-            let mut store = wasmtime::Store::new(&engine, store_state);
-            let pre_instance = linker.instantiate_pre(&component)?;
+            let store_state = ComponentState::new(host_state, wasi_policy)?
+                .with_limits(limits, deadline_unix_ms)
+                .with_cancel(cancel)
+                .with_stdio(stdio_sink)
+                .with_redactor(redactor);
+            let mut store = store_state.into_store(&engine);
             let pre = match component_api::v0_6_descriptor::ComponentV0V6V0Pre::new(pre_instance) {
                 Ok(pre) => pre,
                 Err(_) => return Ok(None),
@@ -2126,28 +2966,21 @@ impl PackRuntime {
             .as_ref()
             .or_else(|| path_is_gtpack(&self.path).then_some(&self.path))
         {
-            let file = File::open(archive_path)
-                .with_context(|| format!("failed to open {}", archive_path.display()))?;
-            let mut archive = ZipArchive::new(file)
-                .with_context(|| format!("failed to read pack {}", archive_path.display()))?;
-            match archive.by_name(&rel) {
-                Ok(mut entry) => {
-                    let mut bytes = Vec::new();
-                    entry.read_to_end(&mut bytes)?;
-                    let value = serde_json::from_slice::<Value>(&bytes).with_context(|| {
-                        format!("invalid schema JSON in {}:{}", archive_path.display(), rel)
-                    })?;
-                    Ok(Some(value))
-                }
-                Err(zip::result::ZipError::FileNotFound) => Ok(None),
-                Err(err) => Err(anyhow!(err)).with_context(|| {
-                    format!(
-                        "failed to read schema `{}` from {}",
-                        rel,
-                        archive_path.display()
-                    )
-                }),
+            let mut archive = gtpack::open(archive_path)?;
+            if archive.index_for_name(&rel).is_none() {
+                return Ok(None);
             }
+            let bytes = read_entry(&mut archive, &rel).with_context(|| {
+                format!(
+                    "failed to read schema `{}` from {}",
+                    rel,
+                    archive_path.display()
+                )
+            })?;
+            let value = serde_json::from_slice::<Value>(&bytes).with_context(|| {
+                format!("invalid schema JSON in {}:{}", archive_path.display(), rel)
+            })?;
+            Ok(Some(value))
         } else {
             Ok(None)
         }
@@ -2184,6 +3017,7 @@ impl PackRuntime {
                 let ctx = self.config.tenant_ctx();
                 read_secret_blocking(
                     &self.secrets,
+                    &self.config.secrets_policy,
                     &ctx,
                     &self.metadata.pack_id,
                     req.key.as_str(),
@@ -2200,27 +3034,24 @@ impl PackRuntime {
         pack_id: &str,
         config: Arc<HostConfig>,
     ) -> Result<Self> {
-        let engine = Engine::default();
-        let engine_profile =
-            EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+        let engine = wasm_engine::new_engine(&config.wasm_engine)?;
+        let engine_profile = wasm_engine::engine_profile(&engine, &config.wasm_engine);
         let cache = CacheManager::new(CacheConfig::default(), engine_profile);
         let mut component_map = HashMap::new();
+        let mut component_digests = BTreeMap::new();
         for (name, path) in components {
             if !path.exists() {
                 bail!("component artifact missing: {}", path.display());
             }
             let wasm_bytes = std::fs::read(&path)?;
+            component_digests.insert(name.clone(), compute_sha256_digest_for(&wasm_bytes));
             let component = Arc::new(
                 Component::from_binary(&engine, &wasm_bytes)
                     .with_context(|| format!("failed to compile component {}", path.display()))?,
             );
             component_map.insert(
                 name.clone(),
-                PackComponent {
-                    name,
-                    version: "0.0.0".into(),
-                    component,
-                },
+                PackComponent::compiled(name, "0.0.0".into(), component),
             );
         }
 
@@ -2264,8 +3095,9 @@ impl PackRuntime {
             mocks: None,
             flows: Some(flows_cache),
             components: component_map,
+            component_digests,
             http_client: Arc::clone(&HTTP_CLIENT),
-            pre_cache: Mutex::new(HashMap::new()),
+            pre_cache: InstancePreCache::from_env(),
             session_store: None,
             state_store: None,
             wasi_policy: Arc::new(RunnerWasiPolicy::new()),
@@ -2274,6 +3106,7 @@ impl PackRuntime {
             secrets: crate::secrets::default_manager()?,
             oauth_config: None,
             cache,
+            started_components: tokio::sync::Mutex::new(BTreeSet::new()),
         })
     }
 }
@@ -2312,6 +3145,13 @@ fn path_is_gtpack(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 fn is_missing_node_export(err: &wasmtime::Error, version: &str) -> bool {
     let message = err.to_string();
     message.contains("no exported instance named")
@@ -2544,12 +3384,7 @@ fn flow_kind_to_str(kind: greentic_types::FlowKind) -> &'static str {
 }
 
 fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
-    let mut file = archive
-        .by_name(name)
-        .with_context(|| format!("entry {name} missing from archive"))?;
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
-    Ok(buf)
+    gtpack::read_entry(archive, name, &ArchiveLimits::from_env())
 }
 
 fn normalize_flow_doc(mut doc: FlowDoc) -> FlowDoc {
@@ -3034,6 +3869,124 @@ async fn compile_component_with_cache(
     cache.get_component(engine, &key, || Ok(bytes)).await
 }
 
+/// Compile every component of `components` not compiled yet, `concurrency` at a time
+/// on the blocking pool. Each component is attempted even when others fail; the
+/// failures come back as one error listing them all.
+async fn precompile_components(
+    cache: &CacheManager,
+    engine: &Engine,
+    pack_id: &str,
+    components: &HashMap<String, PackComponent>,
+    concurrency: usize,
+) -> Result<()> {
+    let started = Instant::now();
+    let permits = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (component_ref, component) in components {
+        if component.component.initialized() {
+            continue;
+        }
+        let Some(bytes) = component.deferred.clone() else {
+            continue;
+        };
+        let permit = Arc::clone(&permits)
+            .acquire_owned()
+            .await
+            .expect("compile semaphore is never closed");
+        let cache = cache.clone();
+        let engine = engine.clone();
+        let component_ref = component_ref.clone();
+        tasks.spawn_blocking(move || {
+            let _permit = permit;
+            let started = Instant::now();
+            let result = block_on(bytes.compile(&cache, &engine));
+            (component_ref, started.elapsed(), result)
+        });
+    }
+
+    let mut compiled = 0usize;
+    let mut failures = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (component_ref, elapsed, result) = joined.context("component compile task failed")?;
+        match result {
+            Ok(artifact) => {
+                tracing::info!(
+                    pack_id,
+                    component_ref = %component_ref,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    "component compiled"
+                );
+                let _ = components[&component_ref].component.set(artifact);
+                compiled += 1;
+            }
+            Err(err) => failures.push(format!("{component_ref}: {err:#}")),
+        }
+    }
+    tracing::info!(
+        pack_id,
+        compiled,
+        failed = failures.len(),
+        concurrency,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "pack components compiled"
+    );
+    if !failures.is_empty() {
+        failures.sort();
+        bail!(
+            "failed to compile {} component(s) of pack {pack_id}: {}",
+            failures.len(),
+            failures.join("; ")
+        );
+    }
+    Ok(())
+}
+
+/// Read every deferred component of `components` on the blocking pool, verifying it
+/// against its recorded digest, and return each component's sha256. Mismatches come
+/// back together as one error.
+async fn digest_components(
+    pack_id: &str,
+    components: &HashMap<String, PackComponent>,
+) -> Result<BTreeMap<String, String>> {
+    let deferred = components
+        .iter()
+        .filter_map(|(component_ref, component)| {
+            component
+                .deferred
+                .clone()
+                .map(|bytes| (component_ref.clone(), bytes))
+        })
+        .collect::<Vec<_>>();
+    let results = tokio::task::spawn_blocking(move || {
+        deferred
+            .into_iter()
+            .map(|(component_ref, bytes)| (component_ref, bytes.digest()))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .context("component digest task failed")?;
+
+    let mut digests = BTreeMap::new();
+    let mut failures = Vec::new();
+    for (component_ref, result) in results {
+        match result {
+            Ok(digest) => {
+                digests.insert(component_ref, digest);
+            }
+            Err(err) => failures.push(format!("{err:#}")),
+        }
+    }
+    if !failures.is_empty() {
+        failures.sort();
+        bail!(
+            "failed to verify {} component(s) of pack {pack_id}: {}",
+            failures.len(),
+            failures.join("; ")
+        );
+    }
+    Ok(digests)
+}
+
 fn verify_component_digest(component_id: &str, expected: &str, bytes: &[u8]) -> Result<()> {
     let normalized_expected = normalize_digest(expected);
     let actual = compute_digest_for(bytes, &normalized_expected)?;
@@ -3090,9 +4043,9 @@ mod pack_lock_tests {
     fn bundled_hash_mismatch_errors() {
         let rt = tokio::runtime::Runtime::new().expect("runtime");
         let temp = TempDir::new().expect("temp dir");
-        let engine = Engine::default();
-        let engine_profile =
-            EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+        let settings = wasm_engine::WasmEngineConfig::default();
+        let engine = wasm_engine::new_engine(&settings).expect("engine");
+        let engine_profile = wasm_engine::engine_profile(&engine, &settings);
         let cache_config = CacheConfig {
             root: temp.path().join("cache"),
             ..CacheConfig::default()
@@ -3127,17 +4080,19 @@ mod pack_lock_tests {
         );
 
         let mut loaded = HashMap::new();
-        let result = rt.block_on(load_components_from_sources(
-            &cache,
-            &engine,
-            &sources,
-            &ComponentResolution::default(),
-            &[spec],
-            &mut missing,
-            &mut loaded,
-            Some(temp.path()),
-            None,
-        ));
+        let result = rt.block_on(async {
+            load_components_from_sources(
+                &sources,
+                &ComponentResolution::default(),
+                &[spec],
+                &mut missing,
+                &mut loaded,
+                Some(temp.path()),
+                None,
+            )
+            .await?;
+            precompile_components(&cache, &engine, "test.pack", &loaded, 1).await
+        });
         let err = result.unwrap_err();
         assert!(
             err.to_string().contains("bundled digest mismatch"),
@@ -3558,37 +4513,18 @@ fn locate_pack_assets(
 }
 
 fn extract_assets_from_archive(path: &Path) -> Result<Option<(TempDir, PathBuf)>> {
-    let file =
-        File::open(path).with_context(|| format!("failed to open pack {}", path.display()))?;
-    let mut archive =
-        ZipArchive::new(file).with_context(|| format!("failed to read pack {}", path.display()))?;
     let temp = TempDir::new().context("failed to create temporary assets directory")?;
-    let mut found = false;
-    for idx in 0..archive.len() {
-        let mut entry = archive.by_index(idx)?;
-        let name = entry.name();
-        if !name.starts_with("assets/") {
-            continue;
-        }
-        let dest = temp.path().join(name);
-        if name.ends_with('/') {
-            std::fs::create_dir_all(&dest)?;
-            found = true;
-            continue;
-        }
-        if let Some(parent) = dest.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        let mut outfile = std::fs::File::create(&dest)?;
-        std::io::copy(&mut entry, &mut outfile)?;
-        found = true;
-    }
-    if found {
-        let assets_path = temp.path().join("assets");
-        Ok(Some((temp, assets_path)))
-    } else {
-        Ok(None)
+    let report = gtpack::extract_matching(path, temp.path(), &ArchiveLimits::from_env(), |name| {
+        name.starts_with("assets/")
+    })?;
+    report
+        .ensure_clean()
+        .with_context(|| format!("failed to extract assets from {}", path.display()))?;
+    if report.extracted.is_empty() {
+        return Ok(None);
     }
+    let assets_path = temp.path().join("assets");
+    Ok(Some((temp, assets_path)))
 }
 
 fn dist_options_from(component_resolution: &ComponentResolution) -> DistOptions {
@@ -3607,8 +4543,6 @@ fn dist_options_from(component_resolution: &ComponentResolution) -> DistOptions
 
 #[allow(clippy::too_many_arguments)]
 async fn load_components_from_sources(
-    cache: &CacheManager,
-    engine: &Engine,
     component_sources: &HashMap<String, ComponentSourceInfo>,
     component_resolution: &ComponentResolution,
     specs: &[ComponentSpec],
@@ -3618,10 +4552,7 @@ async fn load_components_from_sources(
     archive_hint: Option<&Path>,
 ) -> Result<()> {
     let mut archive = if let Some(path) = archive_hint {
-        Some(
-            ZipArchive::new(File::open(path)?)
-                .with_context(|| format!("{} is not a valid gtpack", path.display()))?,
-        )
+        Some(gtpack::open(path)?)
     } else {
         None
     };
@@ -3635,39 +4566,25 @@ async fn load_components_from_sources(
             continue;
         };
 
-        let bytes = match &source.artifact {
+        let location = match &source.artifact {
             ComponentArtifactLocation::Inline { wasm_path } => {
-                if let Some(root) = materialized_root {
-                    let path = root.join(wasm_path);
-                    if path.exists() {
-                        std::fs::read(&path).with_context(|| {
-                            format!(
-                                "failed to read inline component {} from {}",
-                                spec.id,
-                                path.display()
-                            )
-                        })?
-                    } else if archive.is_none() {
-                        bail!("inline component {} missing at {}", spec.id, path.display());
-                    } else {
-                        read_entry(
-                            archive.as_mut().expect("archive present when needed"),
-                            wasm_path,
-                        )
-                        .with_context(|| {
-                            format!(
-                                "inline component {} missing at {} in pack archive",
-                                spec.id, wasm_path
-                            )
-                        })?
-                    }
-                } else if let Some(archive) = archive.as_mut() {
-                    read_entry(archive, wasm_path).with_context(|| {
+                let root_path = materialized_root.map(|root| root.join(wasm_path));
+                if let Some(path) = root_path.as_ref().filter(|path| path.exists()) {
+                    ComponentLocation::File(path.clone())
+                } else if let (Some(archive), Some(archive_path)) = (archive.as_mut(), archive_hint)
+                {
+                    archive.by_name(wasm_path).with_context(|| {
                         format!(
                             "inline component {} missing at {} in pack archive",
                             spec.id, wasm_path
                         )
-                    })?
+                    })?;
+                    ComponentLocation::ArchiveEntry {
+                        archive: archive_path.to_path_buf(),
+                        entry: wasm_path.clone(),
+                    }
+                } else if let Some(path) = root_path {
+                    bail!("inline component {} missing at {}", spec.id, path.display());
                 } else {
                     bail!(
                         "inline component {} missing and no pack source available",
@@ -3724,25 +4641,14 @@ async fn load_components_from_sources(
                         )
                     })?
                 };
-                std::fs::read(&cache_path).with_context(|| {
-                    format!(
-                        "failed to read cached component {} from {}",
-                        spec.id,
-                        cache_path.display()
-                    )
-                })?
+                ComponentLocation::File(cache_path)
             }
         };
 
-        if let Some(expected) = source.expected_wasm_sha256.as_deref() {
-            verify_wasm_sha256(&spec.id, expected, &bytes)?;
+        let check = if let Some(expected) = source.expected_wasm_sha256.as_deref() {
+            DigestCheck::WasmSha256(expected.to_string())
         } else if source.skip_digest_verification {
-            let actual = compute_sha256_digest_for(&bytes);
-            warn!(
-                component_id = %spec.id,
-                digest = %actual,
-                "bundled component missing wasm_sha256; allowing due to flag"
-            );
+            DigestCheck::Skipped
         } else {
             let expected = source.digest.as_deref().ok_or_else(|| {
                 anyhow!(
@@ -3750,19 +4656,17 @@ async fn load_components_from_sources(
                     spec.id
                 )
             })?;
-            verify_component_digest(&spec.id, expected, &bytes)?;
-        }
-        let component =
-            compile_component_with_cache(cache, engine, source.digest.as_deref(), bytes)
-                .await
-                .with_context(|| format!("failed to compile component {}", spec.id))?;
+            DigestCheck::Digest(expected.to_string())
+        };
+        let bytes = ComponentBytes {
+            component_id: spec.id.clone(),
+            location,
+            cache_digest: source.digest.clone(),
+            check,
+        };
         into.insert(
             spec.id.clone(),
-            PackComponent {
-                name: spec.id.clone(),
-                version: spec.version.clone(),
-                component,
-            },
+            PackComponent::deferred(spec.id.clone(), spec.version.clone(), bytes),
         );
         missing.remove(&spec.id);
     }
@@ -3799,9 +4703,7 @@ fn dist_error_for_component(err: DistError, component_id: &str, reference: &str)
     }
 }
 
-async fn load_components_from_overrides(
-    cache: &CacheManager,
-    engine: &Engine,
+fn load_components_from_overrides(
     overrides: &HashMap<String, PathBuf>,
     specs: &[ComponentSpec],
     missing: &mut HashSet<String>,
@@ -3814,38 +4716,30 @@ async fn load_components_from_overrides(
         let Some(path) = overrides.get(&spec.id) else {
             continue;
         };
-        let bytes = std::fs::read(path)
-            .with_context(|| format!("failed to read override component {}", path.display()))?;
-        let component = compile_component_with_cache(cache, engine, None, bytes)
-            .await
-            .with_context(|| {
-                format!(
-                    "failed to compile component {} from override {}",
-                    spec.id,
-                    path.display()
-                )
-            })?;
+        if !path.is_file() {
+            bail!("failed to read override component {}", path.display());
+        }
+        let bytes = ComponentBytes {
+            component_id: spec.id.clone(),
+            location: ComponentLocation::File(path.clone()),
+            cache_digest: None,
+            check: DigestCheck::None,
+        };
         into.insert(
             spec.id.clone(),
-            PackComponent {
-                name: spec.id.clone(),
-                version: spec.version.clone(),
-                component,
-            },
+            PackComponent::deferred(spec.id.clone(), spec.version.clone(), bytes),
         );
         missing.remove(&spec.id);
     }
     Ok(())
 }
 
-async fn load_components_from_dir(
-    cache: &CacheManager,
-    engine: &Engine,
+fn load_components_from_dir(
     root: &Path,
     specs: &[ComponentSpec],
     missing: &mut HashSet<String>,
     into: &mut HashMap<String, PackComponent>,
-) -> Result<()> {
+) {
     for spec in specs {
         if !missing.contains(&spec.id) {
             continue;
@@ -3855,40 +4749,27 @@ async fn load_components_from_dir(
             tracing::debug!(component = %spec.id, path = %path.display(), "materialized component missing; will try other sources");
             continue;
         }
-        let bytes = std::fs::read(&path)
-            .with_context(|| format!("failed to read component {}", path.display()))?;
-        let component = compile_component_with_cache(cache, engine, None, bytes)
-            .await
-            .with_context(|| {
-                format!(
-                    "failed to compile component {} from {}",
-                    spec.id,
-                    path.display()
-                )
-            })?;
+        let bytes = ComponentBytes {
+            component_id: spec.id.clone(),
+            location: ComponentLocation::File(path),
+            cache_digest: None,
+            check: DigestCheck::None,
+        };
         into.insert(
             spec.id.clone(),
-            PackComponent {
-                name: spec.id.clone(),
-                version: spec.version.clone(),
-                component,
-            },
+            PackComponent::deferred(spec.id.clone(), spec.version.clone(), bytes),
         );
         missing.remove(&spec.id);
     }
-    Ok(())
 }
 
-async fn load_components_from_archive(
-    cache: &CacheManager,
-    engine: &Engine,
+fn load_components_from_archive(
     path: &Path,
     specs: &[ComponentSpec],
     missing: &mut HashSet<String>,
     into: &mut HashMap<String, PackComponent>,
 ) -> Result<()> {
-    let mut archive = ZipArchive::new(File::open(path)?)
-        .with_context(|| format!("{} is not a valid gtpack", path.display()))?;
+    let mut archive = gtpack::open(path)?;
     for spec in specs {
         if !missing.contains(&spec.id) {
             continue;
@@ -3897,23 +4778,22 @@ async fn load_components_from_archive(
             .legacy_path
             .clone()
             .unwrap_or_else(|| format!("components/{}.wasm", spec.id));
-        let bytes = match read_entry(&mut archive, &file_name) {
-            Ok(bytes) => bytes,
-            Err(err) => {
-                warn!(component = %spec.id, pack = %path.display(), error = %err, "component entry missing in pack archive");
-                continue;
-            }
+        if let Err(err) = archive.by_name(&file_name) {
+            warn!(component = %spec.id, pack = %path.display(), error = %err, "component entry missing in pack archive");
+            continue;
+        }
+        let bytes = ComponentBytes {
+            component_id: spec.id.clone(),
+            location: ComponentLocation::ArchiveEntry {
+                archive: path.to_path_buf(),
+                entry: file_name,
+            },
+            cache_digest: None,
+            check: DigestCheck::None,
         };
-        let component = compile_component_with_cache(cache, engine, None, bytes)
-            .await
-            .with_context(|| format!("failed to compile component {}", spec.id))?;
         into.insert(
             spec.id.clone(),
-            PackComponent {
-                name: spec.id.clone(),
-                version: spec.version.clone(),
-                component,
-            },
+            PackComponent::deferred(spec.id.clone(), spec.version.clone(), bytes),
         );
         missing.remove(&spec.id);
     }
@@ -3973,6 +4853,81 @@ mod tests {
         let input = payload.get("input").unwrap();
         assert_eq!(input, &json!({ "template": "Hi {{name}}" }));
     }
+
+    /// `bytes` plus a trailing custom section, giving each copy its own digest.
+    fn with_custom_section(mut bytes: Vec<u8>, payload: &str) -> Vec<u8> {
+        let name = b"test-copy";
+        let size = 1 + name.len() + payload.len();
+        assert!(size < 0x80, "section size must fit one LEB128 byte");
+        bytes.extend_from_slice(&[0, size as u8, name.len() as u8]);
+        bytes.extend_from_slice(name);
+        bytes.extend_from_slice(payload.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn precompile_caches_every_component_and_reports_failures_together() {
+        let rt = tokio::runtime::Runtime::new().expect("runtime");
+        let temp = TempDir::new().expect("temp dir");
+        let settings = wasm_engine::WasmEngineConfig::default();
+        let engine = wasm_engine::new_engine(&settings).expect("engine");
+        let engine_profile = wasm_engine::engine_profile(&engine, &settings);
+        let cache_config = CacheConfig {
+            root: temp.path().join("cache"),
+            ..CacheConfig::default()
+        };
+        let cache = CacheManager::new(cache_config, engine_profile);
+        let fixture = std::fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../tests/fixtures/packs/secrets_store_smoke/components/echo_secret.wasm"),
+        )
+        .expect("read fixture wasm");
+
+        let root = temp.path().join("pack");
+        std::fs::create_dir_all(root.join("components")).expect("components dir");
+        let ids = ["echo.a", "echo.b", "echo.c", "echo.d", "broken"];
+        for id in ids {
+            let bytes = if id == "broken" {
+                b"not a wasm component".to_vec()
+            } else {
+                with_custom_section(fixture.clone(), id)
+            };
+            std::fs::write(root.join("components").join(format!("{id}.wasm")), bytes)
+                .expect("write component");
+        }
+        let specs = ids
+            .iter()
+            .map(|id| ComponentSpec {
+                id: id.to_string(),
+                version: "0.0.0".to_string(),
+                legacy_path: None,
+            })
+            .collect::<Vec<_>>();
+        let mut missing = ids.iter().map(ToString::to_string).collect::<HashSet<_>>();
+        let mut loaded = HashMap::new();
+        load_components_from_dir(&root, &specs, &mut missing, &mut loaded);
+        assert!(missing.is_empty());
+
+        let err = rt
+            .block_on(precompile_components(
+                &cache,
+                &engine,
+                "test.pack",
+                &loaded,
+                2,
+            ))
+            .expect_err("broken component must fail");
+        let message = err.to_string();
+        assert!(
+            message.starts_with("failed to compile 1 component(s) of pack test.pack: broken: "),
+            "unexpected error: {message}"
+        );
+        for id in ["echo.a", "echo.b", "echo.c", "echo.d"] {
+            assert!(loaded[id].component.initialized(), "{id} was not compiled");
+        }
+        assert!(!loaded["broken"].component.initialized());
+        assert_eq!(cache.memory_stats().entries, 4);
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
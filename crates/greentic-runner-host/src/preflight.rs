@@ -0,0 +1,194 @@
+//! Startup preflight checks for loaded tenants.
+//!
+//! After tenant runtimes are built the host verifies, per tenant, that bound flows
+//! exist in the loaded packs, provider component refs resolve, referenced schema
+//! files load and required secrets are readable. Failures either abort startup
+//! (`strict_preflight`) or mark the tenant not-ready on `/readyz`. Strict startup
+//! also compiles components that lazy loading deferred, so a broken component fails
+//! the host instead of its first invocation.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::config::ComponentLoading;
+use crate::runtime::{ActivePacks, ComponentResolveError, TenantRuntime};
+
+/// Category of a preflight check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheck {
+    Flow,
+    Component,
+    Schema,
+    Secret,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PreflightFailure {
+    pub check: PreflightCheck,
+    pub detail: String,
+}
+
+/// Preflight outcome for a single tenant.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TenantPreflight {
+    pub checks: usize,
+    pub failures: Vec<PreflightFailure>,
+}
+
+impl TenantPreflight {
+    pub fn is_ready(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn check(&mut self, check: PreflightCheck, failure: Option<String>) {
+        self.checks += 1;
+        if let Some(detail) = failure {
+            self.failures.push(PreflightFailure { check, detail });
+        }
+    }
+}
+
+/// Structured preflight report keyed by tenant.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PreflightReport {
+    pub tenants: BTreeMap<String, TenantPreflight>,
+}
+
+impl PreflightReport {
+    pub fn is_ok(&self) -> bool {
+        self.tenants.values().all(TenantPreflight::is_ready)
+    }
+
+    pub fn failed_tenants(&self) -> Vec<&str> {
+        self.tenants
+            .iter()
+            .filter(|(_, tenant)| !tenant.is_ready())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn log(&self) {
+        for (tenant, result) in &self.tenants {
+            if result.is_ready() {
+                tracing::info!(tenant = %tenant, checks = result.checks, "preflight passed");
+                continue;
+            }
+            for failure in &result.failures {
+                tracing::warn!(
+                    tenant = %tenant,
+                    check = ?failure.check,
+                    detail = %failure.detail,
+                    "preflight check failed"
+                );
+            }
+        }
+    }
+
+    /// In strict mode any failing tenant is an error; otherwise failures are tolerated
+    /// and surface through readiness only.
+    pub fn enforce(&self, strict: bool) -> Result<()> {
+        let failed = self.failed_tenants();
+        if strict && !failed.is_empty() {
+            bail!("preflight failed for tenant(s): {}", failed.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// Run preflight checks against every active tenant. Secret lookups block, so call
+/// this from a blocking task when inside the runtime.
+pub fn check_all(active: &ActivePacks) -> PreflightReport {
+    let tenants = active
+        .snapshot()
+        .iter()
+        .map(|(tenant, runtime)| (tenant.clone(), check_tenant(runtime)))
+        .collect();
+    PreflightReport { tenants }
+}
+
+/// Compile every component deferred by `component_loading: lazy`, failing on the
+/// first one that does not load.
+pub async fn compile_lazy_components(active: &ActivePacks) -> Result<()> {
+    for (tenant, runtime) in active.snapshot().iter() {
+        if runtime.config().component_loading != ComponentLoading::Lazy {
+            continue;
+        }
+        for pack in std::iter::once(runtime.pack()).chain(runtime.overlays()) {
+            pack.compile_components().await.with_context(|| {
+                format!(
+                    "failed to compile components of pack {} for tenant {tenant}",
+                    pack.metadata().pack_id
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+pub fn check_tenant(runtime: &TenantRuntime) -> TenantPreflight {
+    let mut result = TenantPreflight::default();
+
+    for binding in &runtime.config().pack_bindings {
+        for flow in &binding.flows {
+            let missing = runtime
+                .engine()
+                .flow_by_key(&binding.pack_id, flow)
+                .is_none()
+                .then(|| format!("flow {}/{flow} is bound but not loaded", binding.pack_id));
+            result.check(PreflightCheck::Flow, missing);
+        }
+    }
+
+    let mut seen_components = BTreeSet::new();
+    let mut seen_schemas = BTreeSet::new();
+    for binding in runtime.operator_registry().bindings() {
+        let component_ref = binding.runtime.component_ref.as_str();
+        let resolved = runtime.resolve_component(component_ref, binding.pack_preference());
+        if seen_components.insert(component_ref.to_string()) {
+            let failure = match &resolved {
+                Ok(_) => None,
+                Err(ComponentResolveError::NotFound { .. }) => Some(format!(
+                    "provider {} references unknown component {component_ref}",
+                    binding.provider_type
+                )),
+                Err(err) => Some(format!("provider {}: {err}", binding.provider_type)),
+            };
+            result.check(PreflightCheck::Component, failure);
+        }
+        let Ok(resolved) = resolved else {
+            continue;
+        };
+        let schema_refs = [&binding.config_schema_ref, &binding.state_schema_ref];
+        for schema_ref in schema_refs.into_iter().flatten() {
+            if !seen_schemas.insert((binding.pack_ref.clone(), schema_ref.clone())) {
+                continue;
+            }
+            let failure = match resolved.pack.load_schema_json(schema_ref) {
+                Ok(Some(_)) => None,
+                Ok(None) => Some(format!(
+                    "schema {schema_ref} not found in pack {}",
+                    binding.pack_ref
+                )),
+                Err(err) => Some(format!(
+                    "schema {schema_ref} in pack {} failed to load: {err:#}",
+                    binding.pack_ref
+                )),
+            };
+            result.check(PreflightCheck::Schema, failure);
+        }
+    }
+
+    let missing_secrets = runtime.missing_secrets();
+    for requirement in runtime.required_secrets() {
+        let missing = missing_secrets
+            .iter()
+            .any(|missing| missing.key.as_str() == requirement.key.as_str())
+            .then(|| format!("secret {} is not resolvable", requirement.key.as_str()));
+        result.check(PreflightCheck::Secret, missing);
+    }
+
+    result
+}
@@ -169,7 +169,21 @@ impl ProviderRegistry {
         }
     }
 
+    /// Config of the stored instance `provider_id`, if one exists and carries any.
+    pub fn instance_config(&self, provider_id: &str) -> Result<Option<Value>> {
+        Ok(self
+            .load_instance_doc(provider_id)?
+            .map(|instance| instance.config)
+            .filter(|config| !config.is_null()))
+    }
+
     fn load_instance(&self, provider_id: &str) -> Result<Option<ProviderBinding>> {
+        Ok(self
+            .load_instance_doc(provider_id)?
+            .map(binding_from_instance))
+    }
+
+    fn load_instance_doc(&self, provider_id: &str) -> Result<Option<ProviderInstance>> {
         let store = match &self.state_store {
             Some(store) => Arc::clone(store),
             None => return Ok(None),
@@ -187,7 +201,7 @@ impl ProviderRegistry {
         if !instance.enabled {
             bail!("provider `{provider_id}` is disabled");
         }
-        Ok(Some(binding_from_instance(instance)))
+        Ok(Some(instance))
     }
 }
 
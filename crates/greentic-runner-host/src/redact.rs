@@ -0,0 +1,261 @@
+//! Redaction of secret values from what an invocation reports.
+//!
+//! Every operator invocation runs in a [`scope`] holding a [`Redactor`]. Secrets read
+//! while it runs, through operator attachments, `TenantRuntime::get_secret` or the
+//! component secrets store, are registered with it; each occurrence of a registered
+//! value is replaced by `[REDACTED:<key>]`. The tenant's `redaction.patterns` catch
+//! values the runner never resolved (e.g. `sk-[A-Za-z0-9]{20,}`) and are replaced by
+//! `[REDACTED]`.
+//!
+//! The operator scrubs its response (error message, diagnostics, returned logs) in one
+//! place before it is serialized; captured stdio read through [`crate::stdio::captured`]
+//! and audit events are scrubbed as they are produced. Values shorter than
+//! [`MIN_REDACTED_LEN`] are not registered, since replacing them would mangle
+//! unrelated text.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+use crate::stdio::CapturedStdio;
+
+/// Shortest secret value that is registered for redaction, in characters.
+pub const MIN_REDACTED_LEN: usize = 4;
+
+tokio::task_local! {
+    static CURRENT: Redactor;
+}
+
+/// `redaction` bindings block.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RedactionConfig {
+    /// Regular expressions whose matches are always redacted.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// The compiled `redaction` block of a tenant.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionPolicy {
+    patterns: Arc<[Regex]>,
+}
+
+impl RedactionPolicy {
+    pub fn from_config(config: &RedactionConfig) -> Result<Self> {
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("invalid redaction pattern `{pattern}`"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            patterns: patterns.into(),
+        })
+    }
+
+    pub fn patterns(&self) -> &[Regex] {
+        &self.patterns
+    }
+}
+
+/// Secret values resolved during one invocation, plus the tenant's patterns.
+#[derive(Clone, Default)]
+pub struct Redactor(Arc<RedactorInner>);
+
+#[derive(Default)]
+struct RedactorInner {
+    policy: RedactionPolicy,
+    /// Key name and value, longest value first.
+    values: Mutex<Vec<(String, Zeroizing<String>)>>,
+}
+
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("patterns", &self.0.policy.patterns.len())
+            .field("values", &self.0.values.lock().len())
+            .finish()
+    }
+}
+
+impl Redactor {
+    pub fn new(policy: RedactionPolicy) -> Self {
+        Self(Arc::new(RedactorInner {
+            policy,
+            values: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Redact `value` as `[REDACTED:<key>]` from now on.
+    pub fn register(&self, key: &str, value: &str) {
+        if value.chars().count() < MIN_REDACTED_LEN {
+            return;
+        }
+        let mut values = self.0.values.lock();
+        if values.iter().any(|(_, known)| known.as_str() == value) {
+            return;
+        }
+        values.push((key.to_string(), Zeroizing::new(value.to_string())));
+        // Longest first, so a value containing another is replaced whole.
+        values.sort_by(|(_, a), (_, b)| b.len().cmp(&a.len()));
+    }
+
+    /// Register a value read as bytes; values that are not UTF-8 cannot appear in text.
+    pub fn register_bytes(&self, key: &str, value: &[u8]) {
+        if let Ok(value) = std::str::from_utf8(value) {
+            self.register(key, value);
+        }
+    }
+
+    pub fn scrub<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (key, value) in self.0.values.lock().iter() {
+            if text.contains(value.as_str()) {
+                text = Cow::Owned(text.replace(value.as_str(), &format!("[REDACTED:{key}]")));
+            }
+        }
+        for pattern in self.0.policy.patterns.iter() {
+            if pattern.is_match(&text) {
+                text = Cow::Owned(pattern.replace_all(&text, "[REDACTED]").into_owned());
+            }
+        }
+        text
+    }
+
+    /// Scrub `text` in place.
+    pub fn scrub_string(&self, text: &mut String) {
+        let scrubbed = match self.scrub(text) {
+            Cow::Owned(scrubbed) => Some(scrubbed),
+            Cow::Borrowed(_) => None,
+        };
+        if let Some(scrubbed) = scrubbed {
+            *text = scrubbed;
+        }
+    }
+
+    pub fn scrub_stdio(&self, mut output: CapturedStdio) -> CapturedStdio {
+        self.scrub_string(&mut output.stdout);
+        self.scrub_string(&mut output.stderr);
+        output
+    }
+
+    /// Scrub every string in a JSON value.
+    pub fn scrub_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => self.scrub_string(text),
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.scrub_json(item))
+            }
+            serde_json::Value::Object(map) => {
+                map.values_mut().for_each(|item| self.scrub_json(item))
+            }
+            _ => {}
+        }
+    }
+
+    /// Scrub every text value in a CBOR value.
+    pub fn scrub_cbor(&self, value: &mut serde_cbor::Value) {
+        match value {
+            serde_cbor::Value::Text(text) => self.scrub_string(text),
+            serde_cbor::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.scrub_cbor(item))
+            }
+            serde_cbor::Value::Map(map) => map.values_mut().for_each(|item| self.scrub_cbor(item)),
+            serde_cbor::Value::Tag(_, inner) => self.scrub_cbor(inner),
+            _ => {}
+        }
+    }
+}
+
+/// Run `future` with `redactor` collecting the secrets it resolves.
+pub async fn scope<F: Future>(redactor: Redactor, future: F) -> F::Output {
+    CURRENT.scope(redactor, future).await
+}
+
+/// Redactor of the enclosing [`scope`], if any.
+pub fn current() -> Option<Redactor> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Register a resolved secret with the enclosing [`scope`]; a no-op outside one.
+pub fn register(key: &str, value: &str) {
+    if let Some(redactor) = current() {
+        redactor.register(key, value);
+    }
+}
+
+/// Scrub `text` with the enclosing [`scope`]; unchanged outside one.
+pub fn scrub(text: &str) -> Cow<'_, str> {
+    match current() {
+        Some(redactor) => Cow::Owned(redactor.scrub(text).into_owned()),
+        None => Cow::Borrowed(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(patterns: &[&str]) -> RedactionPolicy {
+        RedactionPolicy::from_config(&RedactionConfig {
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn registered_values_and_patterns_are_replaced() {
+        let redactor = Redactor::new(policy(&["sk-[A-Za-z0-9]{20,}"]));
+        redactor.register("API_TOKEN", "tok-12345");
+        redactor.register("PIN", "123");
+        let text = "auth tok-12345 failed; fallback sk-abcdefghijklmnopqrstuvwx, pin 123";
+        assert_eq!(
+            redactor.scrub(text),
+            "auth [REDACTED:API_TOKEN] failed; fallback [REDACTED], pin 123"
+        );
+        assert!(matches!(redactor.scrub("nothing here"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn structured_values_are_scrubbed() {
+        let redactor = Redactor::default();
+        redactor.register("API_TOKEN", "tok-12345");
+        let mut json = serde_json::json!({"error": ["bad tok-12345"], "code": 1});
+        redactor.scrub_json(&mut json);
+        assert_eq!(
+            json,
+            serde_json::json!({"error": ["bad [REDACTED:API_TOKEN]"], "code": 1})
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let config = RedactionConfig {
+            patterns: vec!["sk-[".into()],
+        };
+        assert!(RedactionPolicy::from_config(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn registration_is_scoped() {
+        register("API_TOKEN", "tok-12345");
+        assert_eq!(scrub("tok-12345"), "tok-12345");
+        let redactor = Redactor::default();
+        let scrubbed = scope(redactor.clone(), async {
+            register("API_TOKEN", "tok-12345");
+            scrub("tok-12345").into_owned()
+        })
+        .await;
+        assert_eq!(scrubbed, "[REDACTED:API_TOKEN]");
+        assert_eq!(redactor.scrub("tok-12345"), "[REDACTED:API_TOKEN]");
+    }
+}
@@ -11,57 +11,82 @@ use serde_json::Value;
 use serde_json::json;
 
 use crate::runner::ServerState;
-use crate::runtime::TenantRuntime;
+use crate::runtime::{ActivePacks, TenantRuntime};
 
-#[derive(Clone)]
+/// Header internal gateways use to name the tenant without rewriting request bodies.
+pub const TENANT_HEADER: &str = "x-greentic-tenant";
+
+/// Tenant used when no default is configured, more than one tenant is loaded and
+/// the request names none.
+const LEGACY_DEFAULT_TENANT: &str = "demo";
+
+#[derive(Clone, Default)]
 pub struct RoutingConfig {
     pub resolver: TenantResolver,
-    pub default_tenant: String,
+    /// Tenant for requests that carry no tenant of their own. When unset and exactly
+    /// one tenant is loaded, that tenant is used.
+    pub default_tenant: Option<String>,
+    /// Ignore `X-Greentic-Tenant` for deployments that do not trust upstream headers.
+    pub ignore_tenant_header: bool,
 }
 
 impl RoutingConfig {
     pub fn from_env() -> Self {
-        Self::from_env_with_default("demo".into())
+        Self::from_env_with_default(None)
     }
 
-    pub fn from_env_with_default(default_tenant: String) -> Self {
-        let default_tenant = std::env::var("DEFAULT_TENANT").unwrap_or(default_tenant);
+    pub fn from_env_with_default(default_tenant: Option<String>) -> Self {
+        let default_tenant = std::env::var("DEFAULT_TENANT")
+            .ok()
+            .filter(|value| !value.is_empty())
+            .or(default_tenant);
         let resolver = std::env::var("TENANT_RESOLVER")
-            .map(|value| TenantResolver::from_str(&value, &default_tenant))
+            .map(|value| TenantResolver::from_str(&value))
             .unwrap_or(Ok(TenantResolver::Env))
             .unwrap_or_else(|err| {
                 tracing::warn!(error = %err, "invalid TENANT_RESOLVER, falling back to env");
                 TenantResolver::Env
             });
+        let ignore_tenant_header = std::env::var("GREENTIC_TRUST_TENANT_HEADER")
+            .map(|value| matches!(value.to_ascii_lowercase().as_str(), "0" | "false" | "no"))
+            .unwrap_or(false);
         Self {
             resolver,
             default_tenant,
+            ignore_tenant_header,
         }
     }
 }
 
-impl Default for RoutingConfig {
-    fn default() -> Self {
-        Self {
-            resolver: TenantResolver::Env,
-            default_tenant: "demo".into(),
-        }
-    }
-}
-
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum TenantResolver {
     Host,
+    /// Tenant is the single label in front of `suffix` (`acme.runner.example.com`);
+    /// hosts outside the suffix are rejected with 404.
+    Subdomain {
+        suffix: String,
+    },
     Header(HeaderName),
-    Jwt { header: HeaderName, claim: String },
+    /// Tenant is `claim` of the bearer token in `header`; requests without a token
+    /// carrying the claim are rejected with 401, whatever else names a tenant.
+    Jwt {
+        header: HeaderName,
+        claim: String,
+    },
     Env,
 }
 
 impl TenantResolver {
-    fn from_str(value: &str, _default: &str) -> Result<Self> {
+    fn from_str(value: &str) -> Result<Self> {
         match value.to_ascii_lowercase().as_str() {
             "host" => Ok(Self::Host),
-            "header" => Ok(Self::Header(HeaderName::from_static("x-greentic-tenant"))),
+            "subdomain" => {
+                let suffix = std::env::var("TENANT_DOMAIN_SUFFIX").map_err(|_| {
+                    anyhow!("TENANT_RESOLVER=subdomain requires TENANT_DOMAIN_SUFFIX")
+                })?;
+                Ok(Self::subdomain(suffix))
+            }
+            "header" => Ok(Self::Header(HeaderName::from_static(TENANT_HEADER))),
             "jwt" => Ok(Self::Jwt {
                 header: AUTHORIZATION,
                 claim: "tenant".into(),
@@ -70,12 +95,33 @@ impl TenantResolver {
             other => bail!("unsupported TENANT_RESOLVER `{other}`"),
         }
     }
+
+    pub fn subdomain(suffix: impl AsRef<str>) -> Self {
+        Self::Subdomain {
+            suffix: suffix
+                .as_ref()
+                .trim()
+                .trim_start_matches('.')
+                .to_ascii_lowercase(),
+        }
+    }
 }
 
+/// The request's Host header does not map to any tenant.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown host `{0}`")]
+pub struct UnknownHost(pub String);
+
+/// The request carries no bearer token naming its tenant.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid tenant token: {0}")]
+pub struct InvalidTenantToken(pub String);
+
 #[derive(Clone)]
 pub struct TenantRouting {
     resolver: TenantResolver,
-    default_tenant: String,
+    default_tenant: Option<String>,
+    ignore_tenant_header: bool,
 }
 
 impl TenantRouting {
@@ -83,55 +129,115 @@ impl TenantRouting {
         Self {
             resolver: cfg.resolver,
             default_tenant: cfg.default_tenant,
+            ignore_tenant_header: cfg.ignore_tenant_header,
+        }
+    }
+
+    pub fn default_tenant(&self) -> Option<&str> {
+        self.default_tenant.as_deref()
+    }
+
+    pub fn resolve(&self, parts: &Parts, active: &ActivePacks) -> Result<String> {
+        if let Some(tenant) = self.requested_tenant(parts)? {
+            return Ok(tenant);
+        }
+        if let Some(tenant) = self.header_tenant(parts) {
+            return Ok(tenant);
         }
+        Ok(self.fallback_tenant(active))
     }
 
-    pub fn resolve(&self, parts: &Parts) -> Result<String> {
+    /// `X-Greentic-Tenant`, unless disabled; body `tenant_id` is still cross-checked.
+    fn header_tenant(&self, parts: &Parts) -> Option<String> {
+        if self.ignore_tenant_header {
+            return None;
+        }
+        parts
+            .headers
+            .get(TENANT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    }
+
+    /// Tenant named by the request itself, if the resolver finds one.
+    fn requested_tenant(&self, parts: &Parts) -> Result<Option<String>> {
         match &self.resolver {
-            TenantResolver::Env => Ok(self.default_tenant.clone()),
+            TenantResolver::Env => Ok(None),
             TenantResolver::Host => {
                 let host = parts
                     .headers
                     .get(HOST)
                     .and_then(|value| value.to_str().ok())
                     .unwrap_or_default();
-                if host.is_empty() {
-                    return Ok(self.default_tenant.clone());
-                }
                 Ok(host
                     .split('.')
                     .next()
-                    .map(|segment| segment.to_string())
                     .filter(|segment| !segment.is_empty())
-                    .unwrap_or_else(|| self.default_tenant.clone()))
+                    .map(|segment| segment.to_string()))
             }
-            TenantResolver::Header(name) => {
-                let tenant = parts
+            TenantResolver::Subdomain { suffix } => {
+                let host = parts
                     .headers
-                    .get(name)
+                    .get(HOST)
                     .and_then(|value| value.to_str().ok())
-                    .filter(|value| !value.is_empty())
-                    .map(|value| value.to_string())
-                    .unwrap_or_else(|| self.default_tenant.clone());
-                Ok(tenant)
+                    .unwrap_or_default();
+                subdomain_tenant(host, suffix)
+                    .map(Some)
+                    .ok_or_else(|| UnknownHost(host.to_string()).into())
             }
+            TenantResolver::Header(name) => Ok(parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())),
             TenantResolver::Jwt { header, claim } => {
                 let token = parts
                     .headers
                     .get(header)
                     .and_then(|value| value.to_str().ok())
                     .and_then(|value| value.strip_prefix("Bearer "))
-                    .ok_or_else(|| anyhow!("authorization header missing"))?;
-                let tenant = decode_jwt_claim(token, claim)
-                    .unwrap_or_else(|err| {
+                    .ok_or_else(|| InvalidTenantToken("authorization header missing".into()))?;
+                match decode_jwt_claim(token, claim) {
+                    Ok(Some(tenant)) => Ok(Some(tenant)),
+                    Ok(None) => Err(InvalidTenantToken(format!("claim `{claim}` missing")).into()),
+                    Err(err) => {
                         tracing::warn!(error = %err, "failed to decode jwt claim");
-                        None
-                    })
-                    .unwrap_or_else(|| self.default_tenant.clone());
-                Ok(tenant)
+                        Err(InvalidTenantToken(err.to_string()).into())
+                    }
+                }
             }
         }
     }
+
+    /// Configured default, else the only loaded tenant, else the legacy `demo` tenant.
+    fn fallback_tenant(&self, active: &ActivePacks) -> String {
+        let loaded = active.snapshot();
+        let loaded = loaded.keys().map(String::as_str).collect::<Vec<_>>();
+        fallback_among(self.default_tenant.as_deref(), &loaded).to_string()
+    }
+}
+
+fn fallback_among<'a>(default_tenant: Option<&'a str>, loaded: &[&'a str]) -> &'a str {
+    match (default_tenant, loaded) {
+        (Some(tenant), _) => tenant,
+        (None, [tenant]) => *tenant,
+        (None, _) => LEGACY_DEFAULT_TENANT,
+    }
+}
+
+fn subdomain_tenant(host: &str, suffix: &str) -> Option<String> {
+    let host = host.trim().to_ascii_lowercase();
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => host,
+    };
+    let label = host.strip_suffix(suffix)?.strip_suffix('.')?;
+    if label.is_empty() || label.contains('.') {
+        return None;
+    }
+    Some(label.to_string())
 }
 
 fn decode_jwt_claim(token: &str, claim: &str) -> Result<Option<String>> {
@@ -170,12 +276,19 @@ where
     ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
         let server_state = ServerState::from_ref(state);
         async move {
-            let tenant = server_state.routing.resolve(parts).map_err(|err| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    axum::Json(json!({ "error": err.to_string() })),
-                )
-            })?;
+            let tenant = server_state
+                .routing
+                .resolve(parts, &server_state.active)
+                .map_err(|err| {
+                    let status = if err.is::<UnknownHost>() {
+                        StatusCode::NOT_FOUND
+                    } else if err.is::<InvalidTenantToken>() {
+                        StatusCode::UNAUTHORIZED
+                    } else {
+                        StatusCode::BAD_REQUEST
+                    };
+                    (status, axum::Json(json!({ "error": err.to_string() })))
+                })?;
             let runtime = server_state.active.load(&tenant).ok_or_else(|| {
                 (
                     StatusCode::NOT_FOUND,
@@ -196,7 +309,8 @@ mod tests {
     fn host_resolver_picks_subdomain() {
         let routing = TenantRouting::new(RoutingConfig {
             resolver: TenantResolver::Host,
-            default_tenant: "demo".into(),
+            default_tenant: Some("demo".into()),
+            ..Default::default()
         });
         let (parts, _) = Request::builder()
             .uri("http://foo.example.com/webhook")
@@ -204,29 +318,159 @@ mod tests {
             .body(())
             .unwrap()
             .into_parts();
-        let tenant = routing.resolve(&parts).unwrap();
+        let tenant = routing.resolve(&parts, &ActivePacks::new()).unwrap();
         assert_eq!(tenant, "foo");
     }
 
+    #[test]
+    fn subdomain_resolver_requires_configured_suffix() {
+        let routing = TenantRouting::new(RoutingConfig {
+            resolver: TenantResolver::subdomain(".Runner.Example.com"),
+            default_tenant: Some("demo".into()),
+            ..Default::default()
+        });
+        let resolve = |host: &str| {
+            let (parts, _) = Request::builder()
+                .uri("http://localhost/operator/op/invoke")
+                .header(HOST, host)
+                .body(())
+                .unwrap()
+                .into_parts();
+            routing.resolve(&parts, &ActivePacks::new())
+        };
+        assert_eq!(resolve("tenant-a.runner.example.com").unwrap(), "tenant-a");
+        assert_eq!(
+            resolve("Tenant-B.runner.example.com:8443").unwrap(),
+            "tenant-b"
+        );
+        for host in [
+            "runner.example.com",
+            "a.b.runner.example.com",
+            "tenant-a.other.example.com",
+            "tenant-arunner.example.com",
+        ] {
+            let err = resolve(host).unwrap_err();
+            assert!(err.is::<UnknownHost>(), "{host} should be unknown");
+        }
+    }
+
+    #[test]
+    fn jwt_resolver_rejects_bad_tokens_despite_tenant_header() {
+        let routing = TenantRouting::new(RoutingConfig {
+            resolver: TenantResolver::Jwt {
+                header: AUTHORIZATION,
+                claim: "tenant".into(),
+            },
+            default_tenant: Some("demo".into()),
+            ..Default::default()
+        });
+        let resolve = |authorization: Option<&str>| {
+            let mut request = Request::builder()
+                .uri("http://localhost")
+                .header(TENANT_HEADER, "victim");
+            if let Some(authorization) = authorization {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+            let (parts, _) = request.body(()).unwrap().into_parts();
+            routing.resolve(&parts, &ActivePacks::new())
+        };
+        let token = |claims: Value| {
+            let payload =
+                base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(claims.to_string());
+            format!("Bearer e30.{payload}.sig")
+        };
+
+        let tenant = resolve(Some(&token(json!({ "tenant": "acme" })))).unwrap();
+        assert_eq!(tenant, "acme");
+        for authorization in [
+            None,
+            Some("Bearer not-a-jwt".to_string()),
+            Some("Bearer e30.!!!.sig".to_string()),
+            Some(token(json!({ "sub": "someone" }))),
+        ] {
+            let err = resolve(authorization.as_deref()).unwrap_err();
+            assert!(err.is::<InvalidTenantToken>(), "{authorization:?}: {err}");
+        }
+    }
+
     #[test]
     fn header_resolver_defaults() {
         let routing = TenantRouting::new(RoutingConfig {
             resolver: TenantResolver::Header(HeaderName::from_static("x-tenant")),
-            default_tenant: "demo".into(),
+            default_tenant: Some("demo".into()),
+            ..Default::default()
         });
         let (parts, _) = Request::builder()
             .uri("http://localhost")
             .body(())
             .unwrap()
             .into_parts();
-        let tenant = routing.resolve(&parts).unwrap();
+        let tenant = routing.resolve(&parts, &ActivePacks::new()).unwrap();
         assert_eq!(tenant, "demo");
     }
 
     #[test]
     fn from_env_with_default_uses_override() {
-        let expected = std::env::var("DEFAULT_TENANT").unwrap_or_else(|_| "custom".into());
-        let cfg = RoutingConfig::from_env_with_default("custom".into());
-        assert_eq!(cfg.default_tenant, expected);
+        let expected = std::env::var("DEFAULT_TENANT")
+            .ok()
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| "custom".into());
+        let cfg = RoutingConfig::from_env_with_default(Some("custom".into()));
+        assert_eq!(cfg.default_tenant, Some(expected));
+    }
+
+    #[test]
+    fn tenant_header_overrides_default_unless_ignored() {
+        let request = || {
+            Request::builder()
+                .uri("http://localhost")
+                .header(TENANT_HEADER, "gateway")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let mut config = RoutingConfig {
+            default_tenant: Some("demo".into()),
+            ..Default::default()
+        };
+        let routing = TenantRouting::new(config.clone());
+        assert_eq!(
+            routing.resolve(&request(), &ActivePacks::new()).unwrap(),
+            "gateway"
+        );
+
+        config.ignore_tenant_header = true;
+        let routing = TenantRouting::new(config);
+        assert_eq!(
+            routing.resolve(&request(), &ActivePacks::new()).unwrap(),
+            "demo"
+        );
+    }
+
+    #[test]
+    fn fallback_uses_sole_tenant_only_without_default() {
+        assert_eq!(fallback_among(Some("acme"), &["acme", "beta"]), "acme");
+        // A configured default wins even while it is not loaded.
+        assert_eq!(fallback_among(Some("demo"), &["acme"]), "demo");
+        assert_eq!(fallback_among(None, &["acme"]), "acme");
+        assert_eq!(fallback_among(Some("demo"), &["acme", "beta"]), "demo");
+        assert_eq!(fallback_among(Some("demo"), &[]), "demo");
+        assert_eq!(
+            fallback_among(None, &["acme", "beta"]),
+            LEGACY_DEFAULT_TENANT
+        );
+    }
+
+    #[test]
+    fn without_default_or_single_tenant_falls_back_to_legacy_tenant() {
+        let routing = TenantRouting::new(RoutingConfig::default());
+        let (parts, _) = Request::builder()
+            .uri("http://localhost")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let tenant = routing.resolve(&parts, &ActivePacks::new()).unwrap();
+        assert_eq!(tenant, LEGACY_DEFAULT_TENANT);
     }
 }
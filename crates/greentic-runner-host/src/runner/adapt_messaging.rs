@@ -5,6 +5,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
+use crate::engine::rate_limit::rate_limited;
 use crate::engine::runtime::IngressEnvelope;
 use crate::ingress::{
     ProviderIds, build_canonical_payload, canonical_session_key, default_metadata, empty_entities,
@@ -149,6 +150,8 @@ pub async fn telegram_webhook(
             );
             remember_status(runtime.as_ref(), update.update_id, StatusCode::OK)
         }
+        // Not remembered: the redelivery Telegram sends after a 429 should run the flow.
+        Err(err) if rate_limited(&err).is_some() => StatusCode::TOO_MANY_REQUESTS,
         Err(err) => {
             let chained = err.chain().map(|e| e.to_string()).collect::<Vec<_>>();
             tracing::error!(
@@ -171,7 +174,7 @@ async fn send_telegram_message(runtime: &TenantRuntime, chat_id: i64, text: &str
         bail!("messaging send rate exceeded");
     }
 
-    let token = runtime.get_secret("TELEGRAM_BOT_TOKEN")?;
+    let token = runtime.get_secret("TELEGRAM_BOT_TOKEN").await?;
     let url = format!("https://api.telegram.org/bot{token}/sendMessage");
     let body = json!({
         "chat_id": chat_id,
@@ -18,7 +18,7 @@ use crate::ingress::{
 };
 use crate::provider_core_only;
 use crate::routing::TenantRuntimeHandle;
-use crate::runner::ingress_util::{collect_body, mark_processed};
+use crate::runner::ingress_util::{collect_body, flow_failure_status, mark_processed};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -98,7 +98,7 @@ pub async fn events(
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "slack flow execution failed");
-            StatusCode::BAD_GATEWAY
+            flow_failure_status(&err, StatusCode::BAD_GATEWAY)
         })?;
     Ok(StatusCode::OK.into_response())
 }
@@ -174,7 +174,7 @@ pub async fn interactive(
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "slack interactive flow failed");
-            StatusCode::BAD_GATEWAY
+            flow_failure_status(&err, StatusCode::BAD_GATEWAY)
         })?;
     Ok(StatusCode::OK)
 }
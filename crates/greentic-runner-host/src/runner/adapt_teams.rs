@@ -12,7 +12,7 @@ use crate::ingress::{
 };
 use crate::provider_core_only;
 use crate::routing::TenantRuntimeHandle;
-use crate::runner::ingress_util::{collect_body, mark_processed};
+use crate::runner::ingress_util::{collect_body, flow_failure_status, mark_processed};
 
 pub async fn activities(
     TenantRuntimeHandle { tenant, runtime }: TenantRuntimeHandle,
@@ -95,7 +95,7 @@ pub async fn activities(
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "teams flow execution failed");
-            StatusCode::BAD_GATEWAY
+            flow_failure_status(&err, StatusCode::BAD_GATEWAY)
         })?;
     Ok(StatusCode::ACCEPTED)
 }
@@ -1,116 +1,365 @@
+//! Flow runs triggered by the `timers` of a tenant's bindings.
+//!
+//! Each timer fires on a cron expression or a fixed `interval_secs` and runs its flow
+//! through the state machine like an ingress event, with the synthetic activity id
+//! `{schedule_id}@{scheduled time}`. A tick that arrives while the previous run is still
+//! going is dropped (`overlap: skip`) or run once that run ends (`overlap: coalesce`,
+//! however many ticks arrived meanwhile).
+//!
+//! A [`TimerScheduler`] outlives the runtime it was started for: a reload hands it to
+//! the next runtime, where timers whose binding did not change keep their schedule,
+//! removed or changed ones stop, and new ones start. Runs already going finish. With
+//! `catch_up`, the time of each run is kept in the state store, and a timer that
+//! should have fired while no host was running fires once when it starts.
+
+use std::collections::HashMap;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use chrono::{DateTime, Utc};
 use cron::Schedule;
-use serde_json::json;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
+use crate::config::{TimerBinding, TimerOverlap};
+use crate::engine::host::SessionKey;
 use crate::engine::runtime::IngressEnvelope;
 use crate::runtime::TenantRuntime;
 
-pub fn spawn_timers(runtime: Arc<TenantRuntime>) -> Result<Vec<JoinHandle<()>>> {
-    let mut handles = Vec::new();
+const TIMER_STATE_PACK: &str = "_timers";
 
-    for timer in runtime.config().timers.clone() {
-        let cron_expr = timer.cron.clone();
-        let normalized = normalize_cron(&cron_expr);
-        let schedule = Schedule::from_str(&normalized)
-            .with_context(|| format!("invalid cron expression for {}", timer.schedule_id()))?;
-        let flow_id = timer.flow_id.clone();
-        let schedule_id = timer.schedule_id().to_string();
-        let tenant = runtime.config().tenant.clone();
-        let runtime_clone = Arc::clone(&runtime);
+/// When a timer fires.
+pub enum TimerSchedule {
+    Cron(Box<Schedule>),
+    Every(Duration),
+}
 
-        let handle = tokio::spawn(async move {
-            tracing::info!(
-                flow_id = %flow_id,
-                schedule_id = %schedule_id,
-                cron = %cron_expr,
-                normalized_cron = %normalized,
-                "registered timer schedule"
-            );
-            for next in schedule.upcoming(Utc) {
-                if let Some(wait) = duration_until(next) {
-                    sleep(wait).await;
-                } else {
+impl TimerSchedule {
+    /// The schedule of `timer`; exactly one of `cron` and `interval_secs` must be set.
+    pub fn parse(timer: &TimerBinding) -> Result<Self> {
+        let schedule = match (&timer.cron, timer.interval_secs) {
+            (Some(expr), None) => {
+                let schedule = Schedule::from_str(&normalize_cron(expr)).with_context(|| {
+                    format!("invalid cron expression for {}", timer.schedule_id())
+                })?;
+                Self::Cron(Box::new(schedule))
+            }
+            (None, Some(secs)) if secs > 0 => Self::Every(Duration::from_secs(secs)),
+            (None, Some(_)) => bail!("timer {} has a zero interval", timer.schedule_id()),
+            (Some(_), Some(_)) => bail!(
+                "timer {} sets both cron and interval_secs",
+                timer.schedule_id()
+            ),
+            (None, None) => bail!("timer {} needs cron or interval_secs", timer.schedule_id()),
+        };
+        if !matches!(timer.input, Value::Null | Value::Object(_)) {
+            bail!("input of timer {} must be an object", timer.schedule_id());
+        }
+        Ok(schedule)
+    }
+
+    /// First time the timer fires strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron(schedule) => schedule.after(&after).next(),
+            Self::Every(interval) => chrono::Duration::from_std(*interval)
+                .ok()
+                .and_then(|interval| after.checked_add_signed(interval)),
+        }
+    }
+}
+
+/// The running timers of one tenant.
+#[derive(Default)]
+pub struct TimerScheduler {
+    /// Runtime the timers run flows on; swapped when a reload hands the scheduler over.
+    target: Arc<Mutex<Weak<TenantRuntime>>>,
+    timers: HashMap<String, RunningTimer>,
+}
+
+struct RunningTimer {
+    binding: TimerBinding,
+    handle: JoinHandle<()>,
+}
+
+/// Whether a timer's flow is running, and the tick to run once it ends.
+#[derive(Default)]
+struct InFlight {
+    running: bool,
+    pending: Option<DateTime<Utc>>,
+}
+
+impl TimerScheduler {
+    /// Run flows on `runtime` and make its config's timers the running ones.
+    pub fn sync(&mut self, runtime: &Arc<TenantRuntime>) {
+        *self.target.lock() = Arc::downgrade(runtime);
+        let declared: HashMap<&str, &TimerBinding> = runtime
+            .config()
+            .timers
+            .iter()
+            .map(|timer| (timer.schedule_id(), timer))
+            .collect();
+        self.timers.retain(|schedule_id, running| {
+            let keep = declared
+                .get(schedule_id.as_str())
+                .is_some_and(|timer| **timer == running.binding);
+            if !keep {
+                tracing::info!(schedule_id = %schedule_id, "stopping timer schedule");
+                running.handle.abort();
+            }
+            keep
+        });
+        for (schedule_id, timer) in declared {
+            if self.timers.contains_key(schedule_id) {
+                continue;
+            }
+            // Bindings are checked when the config loads; a bad one is only logged here.
+            let schedule = match TimerSchedule::parse(timer) {
+                Ok(schedule) => schedule,
+                Err(err) => {
+                    tracing::error!(schedule_id = %schedule_id, error = %err, "skipping timer");
                     continue;
                 }
-                let pack_id = match runtime_clone.engine().flow_by_id(&flow_id) {
-                    Some(flow) => flow.pack_id.clone(),
-                    None => {
-                        tracing::error!(
-                            flow_id = %flow_id,
-                            schedule_id = %schedule_id,
-                            "timer flow is ambiguous; pack_id is required"
-                        );
-                        continue;
-                    }
-                };
-                let payload = json!({
-                    "now": next.to_rfc3339(),
-                    "schedule_id": schedule_id.clone(),
-                });
-                tracing::info!(
-                    flow_id = %flow_id,
-                    schedule_id = %schedule_id,
-                    scheduled_for = %next,
-                    "triggering timer flow"
-                );
-                let envelope = IngressEnvelope {
-                    tenant: tenant.clone(),
-                    env: None,
-                    pack_id: Some(pack_id),
-                    flow_id: flow_id.clone(),
-                    flow_type: Some("timer".into()),
-                    action: Some("timer".into()),
-                    session_hint: Some(schedule_id.clone()),
-                    provider: Some("timer".into()),
-                    channel: Some(schedule_id.clone()),
-                    conversation: Some(schedule_id.clone()),
-                    user: None,
-                    activity_id: Some(format!("{}@{}", schedule_id, next)),
-                    timestamp: Some(next.to_rfc3339()),
-                    payload,
-                    metadata: None,
-                    reply_scope: None,
-                }
-                .canonicalize();
-                match runtime_clone.state_machine().handle(envelope).await {
-                    Ok(output) => {
-                        tracing::info!(
-                            flow_id = %flow_id,
-                            schedule_id = %schedule_id,
-                            now = %next,
-                            response = %output,
-                            "timer flow completed"
-                        );
-                    }
-                    Err(err) => {
-                        let chain = err.chain().map(|e| e.to_string()).collect::<Vec<_>>();
-                        tracing::error!(
-                            flow_id = %flow_id,
-                            schedule_id = %schedule_id,
-                            error.cause_chain = ?chain,
-                            "timer flow execution failed"
-                        );
-                    }
+            };
+            let handle = tokio::spawn(run_timer(Arc::clone(&self.target), timer.clone(), schedule));
+            self.timers.insert(
+                schedule_id.to_string(),
+                RunningTimer {
+                    binding: timer.clone(),
+                    handle,
+                },
+            );
+        }
+    }
+
+    /// Schedule ids of the running timers, sorted.
+    pub fn schedule_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.timers.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn stop(&mut self) {
+        for (_, running) in self.timers.drain() {
+            running.handle.abort();
+        }
+    }
+}
+
+impl Drop for TimerScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn run_timer(
+    target: Arc<Mutex<Weak<TenantRuntime>>>,
+    timer: TimerBinding,
+    schedule: TimerSchedule,
+) {
+    let schedule_id = timer.schedule_id().to_string();
+    tracing::info!(
+        flow_id = %timer.flow_id,
+        schedule_id = %schedule_id,
+        cron = ?timer.cron,
+        interval_secs = ?timer.interval_secs,
+        "registered timer schedule"
+    );
+    let timer = Arc::new(timer);
+    let in_flight = Arc::new(Mutex::new(InFlight::default()));
+    let mut last = Utc::now();
+    if timer.catch_up
+        && let Some(missed) = missed_run(&target, &timer, &schedule, last).await
+    {
+        tracing::info!(schedule_id = %schedule_id, scheduled_for = %missed, "running missed timer");
+        tick(&target, &timer, &in_flight, missed);
+    }
+    loop {
+        let Some(mut next) = schedule.next_after(last) else {
+            break;
+        };
+        // Ticks missed while the host was busy are dropped, not replayed.
+        let now = Utc::now();
+        if next < now
+            && let Some(upcoming) = schedule.next_after(now)
+        {
+            next = upcoming;
+        }
+        if let Some(wait) = duration_until(next) {
+            sleep(wait).await;
+        }
+        last = next;
+        if target.lock().strong_count() == 0 {
+            break;
+        }
+        tick(&target, &timer, &in_flight, next);
+    }
+    tracing::info!(schedule_id = %schedule_id, "timer schedule completed");
+}
+
+/// The scheduled time of a run missed since the last recorded one, if any.
+async fn missed_run(
+    target: &Mutex<Weak<TenantRuntime>>,
+    timer: &TimerBinding,
+    schedule: &TimerSchedule,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let runtime = target.lock().upgrade()?;
+    let key = fired_key(&runtime, timer);
+    let record = match runtime.state_host().get_json(&key).await {
+        Ok(record) => record?,
+        Err(err) => {
+            tracing::warn!(schedule_id = %timer.schedule_id(), error = %err, "failed to read timer record");
+            return None;
+        }
+    };
+    let fired_at = record["fired_at"].as_str()?.parse::<DateTime<Utc>>().ok()?;
+    schedule.next_after(fired_at).filter(|missed| *missed < now)
+}
+
+/// Run the timer's flow for the tick scheduled at `at`, as its overlap policy allows.
+fn tick(
+    target: &Arc<Mutex<Weak<TenantRuntime>>>,
+    timer: &Arc<TimerBinding>,
+    in_flight: &Arc<Mutex<InFlight>>,
+    at: DateTime<Utc>,
+) {
+    {
+        let mut state = in_flight.lock();
+        if state.running {
+            match timer.overlap {
+                TimerOverlap::Skip => {
+                    tracing::warn!(
+                        schedule_id = %timer.schedule_id(),
+                        scheduled_for = %at,
+                        "previous timer run still going; skipping tick"
+                    );
                 }
+                TimerOverlap::Coalesce => state.pending = Some(at),
+            }
+            return;
+        }
+        state.running = true;
+    }
+    let target = Arc::clone(target);
+    let timer = Arc::clone(timer);
+    let in_flight = Arc::clone(in_flight);
+    tokio::spawn(async move {
+        let mut at = at;
+        loop {
+            let runtime = target.lock().upgrade();
+            if let Some(runtime) = runtime {
+                fire(&runtime, &timer, at).await;
+            }
+            let pending = {
+                let mut state = in_flight.lock();
+                state.running = state.pending.is_some();
+                state.pending.take()
+            };
+            match pending {
+                Some(pending) => at = pending,
+                None => break,
             }
+        }
+    });
+}
+
+async fn fire(runtime: &TenantRuntime, timer: &TimerBinding, at: DateTime<Utc>) {
+    let schedule_id = timer.schedule_id();
+    let flow_id = &timer.flow_id;
+    let pack_id = match timer.pack_id.as_deref() {
+        Some(pack_id) => runtime
+            .engine()
+            .flow_by_key(pack_id, flow_id)
+            .map(|flow| flow.pack_id.clone()),
+        None => runtime
+            .engine()
+            .flow_by_id(flow_id)
+            .map(|flow| flow.pack_id.clone()),
+    };
+    let Some(pack_id) = pack_id else {
+        tracing::error!(
+            flow_id = %flow_id,
+            schedule_id = %schedule_id,
+            "timer flow not found or ambiguous; set pack_id"
+        );
+        return;
+    };
+    let mut payload = json!({
+        "now": at.to_rfc3339(),
+        "schedule_id": schedule_id,
+    });
+    if let (Value::Object(payload), Value::Object(input)) = (&mut payload, &timer.input) {
+        payload.extend(input.clone());
+    }
+    tracing::info!(
+        flow_id = %flow_id,
+        schedule_id = %schedule_id,
+        scheduled_for = %at,
+        "triggering timer flow"
+    );
+    let envelope = IngressEnvelope {
+        tenant: runtime.tenant().to_string(),
+        env: None,
+        pack_id: Some(pack_id),
+        flow_id: flow_id.clone(),
+        flow_type: Some("timer".into()),
+        action: Some("timer".into()),
+        session_hint: Some(schedule_id.to_string()),
+        provider: Some("timer".into()),
+        channel: Some(schedule_id.to_string()),
+        conversation: Some(schedule_id.to_string()),
+        user: None,
+        activity_id: Some(format!("{schedule_id}@{}", at.to_rfc3339())),
+        timestamp: Some(at.to_rfc3339()),
+        payload,
+        metadata: None,
+        reply_scope: None,
+    }
+    .canonicalize();
+    match runtime.state_machine().handle(envelope).await {
+        Ok(output) => {
             tracing::info!(
                 flow_id = %flow_id,
                 schedule_id = %schedule_id,
-                "timer schedule completed"
+                now = %at,
+                response = %output,
+                "timer flow completed"
             );
-        });
-
-        handles.push(handle);
+        }
+        Err(err) => {
+            let chain = err.chain().map(|e| e.to_string()).collect::<Vec<_>>();
+            tracing::error!(
+                flow_id = %flow_id,
+                schedule_id = %schedule_id,
+                error.cause_chain = ?chain,
+                "timer flow execution failed"
+            );
+        }
+    }
+    if timer.catch_up {
+        let record = json!({ "fired_at": at.to_rfc3339() });
+        if let Err(err) = runtime
+            .state_host()
+            .set_json(&fired_key(runtime, timer), record)
+            .await
+        {
+            tracing::warn!(schedule_id = %schedule_id, error = %err, "failed to record timer run");
+        }
     }
+}
 
-    Ok(handles)
+fn fired_key(runtime: &TenantRuntime, timer: &TimerBinding) -> SessionKey {
+    SessionKey::new(
+        &runtime.config().tenant_ctx(),
+        TIMER_STATE_PACK,
+        timer.schedule_id(),
+        None,
+    )
 }
 
 fn duration_until(next: DateTime<Utc>) -> Option<Duration> {
@@ -134,6 +383,10 @@ fn normalize_cron(expr: &str) -> String {
 mod tests {
     use super::*;
 
+    fn timer(yaml: &str) -> TimerBinding {
+        serde_yaml_bw::from_str(yaml).expect("timer binding")
+    }
+
     #[test]
     fn normalize_cron_adds_seconds_for_five_fields() {
         assert_eq!(normalize_cron("*/5 * * * *"), "0 */5 * * * *");
@@ -145,4 +398,26 @@ mod tests {
         let past = Utc::now() - chrono::Duration::seconds(10);
         assert_eq!(duration_until(past).unwrap(), Duration::from_secs(0));
     }
+
+    #[test]
+    fn schedules_need_exactly_one_trigger() {
+        let every = TimerSchedule::parse(&timer("flow_id: tick\ninterval_secs: 5"))
+            .expect("interval timer");
+        let start = Utc::now();
+        assert_eq!(
+            every.next_after(start),
+            Some(start + chrono::Duration::seconds(5))
+        );
+        assert!(TimerSchedule::parse(&timer("flow_id: tick\ncron: '*/5 * * * *'")).is_ok());
+
+        for invalid in [
+            "flow_id: tick",
+            "flow_id: tick\ninterval_secs: 0",
+            "flow_id: tick\ninterval_secs: 5\ncron: '* * * * *'",
+            "flow_id: tick\ncron: 'not a cron'",
+            "flow_id: tick\ninterval_secs: 5\ninput: [1]",
+        ] {
+            assert!(TimerSchedule::parse(&timer(invalid)).is_err(), "{invalid}");
+        }
+    }
 }
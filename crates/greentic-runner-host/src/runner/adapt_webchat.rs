@@ -12,7 +12,7 @@ use crate::ingress::{
 };
 use crate::provider_core_only;
 use crate::routing::TenantRuntimeHandle;
-use crate::runner::ingress_util::{collect_body, mark_processed};
+use crate::runner::ingress_util::{collect_body, flow_failure_status, mark_processed};
 
 pub async fn activities(
     TenantRuntimeHandle { tenant, runtime }: TenantRuntimeHandle,
@@ -98,7 +98,7 @@ pub async fn activities(
         Ok(_) => Ok(StatusCode::ACCEPTED),
         Err(err) => {
             tracing::error!(error = %err, "webchat flow execution failed");
-            Err(StatusCode::BAD_GATEWAY)
+            Err(flow_failure_status(&err, StatusCode::BAD_GATEWAY))
         }
     }
 }
@@ -13,7 +13,7 @@ use crate::ingress::{
 };
 use crate::provider_core_only;
 use crate::routing::TenantRuntimeHandle;
-use crate::runner::ingress_util::{collect_body, mark_processed};
+use crate::runner::ingress_util::{collect_body, flow_failure_status, mark_processed};
 
 type HmacSha1 = Hmac<Sha1>;
 
@@ -114,7 +114,7 @@ pub async fn webhook(
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "webex flow execution failed");
-            StatusCode::BAD_GATEWAY
+            flow_failure_status(&err, StatusCode::BAD_GATEWAY)
         })?;
     Ok(StatusCode::ACCEPTED)
 }
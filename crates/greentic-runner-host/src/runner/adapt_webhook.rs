@@ -9,6 +9,7 @@ use serde_json::{Map, Value, json};
 
 use crate::engine::runtime::IngressEnvelope;
 use crate::routing::TenantRuntimeHandle;
+use crate::runner::ingress_util::rate_limited_response;
 use crate::runtime::TenantRuntime;
 
 pub async fn dispatch(
@@ -84,6 +85,9 @@ pub async fn dispatch(
             }))
         }
         Err(err) => {
+            if let Some(response) = rate_limited_response(&err) {
+                return Err(response);
+            }
             let chain = err.chain().map(|e| e.to_string()).collect::<Vec<_>>();
             tracing::error!(
                 flow_id = %flow.id,
@@ -15,7 +15,7 @@ use crate::ingress::{
 };
 use crate::provider_core_only;
 use crate::routing::TenantRuntimeHandle;
-use crate::runner::ingress_util::{collect_body, mark_processed};
+use crate::runner::ingress_util::{collect_body, flow_failure_status, mark_processed};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -129,7 +129,7 @@ pub async fn webhook(
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "whatsapp flow execution failed");
-            StatusCode::BAD_GATEWAY
+            flow_failure_status(&err, StatusCode::BAD_GATEWAY)
         })?;
     Ok(StatusCode::ACCEPTED)
 }
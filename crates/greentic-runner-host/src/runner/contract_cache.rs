@@ -132,6 +132,25 @@ impl ContractCache {
         self.evict_if_needed(&mut state);
     }
 
+    /// Remove every entry recorded for `digest`; returns how many were dropped.
+    pub fn invalidate_digest(&self, digest: &str) -> usize {
+        let prefix = format!("{digest}::");
+        let mut state = self.state.lock();
+        let stale = state
+            .entries
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in &stale {
+            if let Some(entry) = state.entries.remove(key) {
+                state.total_bytes = state.total_bytes.saturating_sub(entry.bytes_estimate);
+            }
+            remove_lru(&mut state.lru, key);
+        }
+        stale.len()
+    }
+
     pub fn stats(&self) -> ContractCacheStats {
         let state = self.state.lock();
         ContractCacheStats {
@@ -208,4 +227,25 @@ mod tests {
         assert!(stats.entries >= 1);
         assert!(cache.get(&key_b).is_some());
     }
+
+    #[test]
+    fn invalidate_digest_drops_only_matching_entries() {
+        let cache = ContractCache::new(u64::MAX);
+        for digest in ["sha256:a", "sha256:b"] {
+            cache.insert(
+                format!("{digest}::component.alpha::run"),
+                Arc::new(ContractSnapshot::new(
+                    digest.to_string(),
+                    "component.alpha".to_string(),
+                    "run".to_string(),
+                    true,
+                    true,
+                )),
+            );
+        }
+        assert_eq!(cache.invalidate_digest("sha256:a"), 1);
+        assert!(cache.get("sha256:a::component.alpha::run").is_none());
+        assert!(cache.get("sha256:b::component.alpha::run").is_some());
+        assert_eq!(cache.stats().entries, 1);
+    }
 }
@@ -1,12 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
 use std::error::Error as StdError;
+use std::fmt;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::component_api::node::{ExecCtx as ComponentExecCtx, TenantCtx as ComponentTenantCtx};
 use anyhow::{Context, Result, anyhow, bail};
+use futures::{StreamExt, stream};
 use indexmap::IndexMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -14,19 +16,29 @@ use serde_json::{Map as JsonMap, Value, json};
 use tokio::task;
 
 use super::mocks::MockLayer;
-use super::templating::{TemplateOptions, render_template_value};
-use crate::config::{FlowRetryConfig, HostConfig};
+use super::templating::{TemplateEngine, TemplateOptions, TemplateSource};
+use crate::cancel;
+use crate::config::{DeterministicConfig, FlowRetryConfig, HostConfig};
 use crate::pack::{FlowDescriptor, PackRuntime};
 use crate::runner::invocation::{InvocationMeta, build_invocation_envelope};
 use crate::telemetry::{FlowSpanAttributes, annotate_span, backoff_delay_ms, set_flow_context};
 #[cfg(feature = "fault-injection")]
 use crate::testing::fault_injection::{FaultContext, FaultPoint, maybe_fail};
+use crate::trace::sampling;
 use crate::validate::{
     ValidationConfig, ValidationIssue, ValidationMode, validate_component_envelope,
     validate_tool_envelope,
 };
 use greentic_types::{Flow, Node, NodeId, Routing};
 
+/// Deepest chain of subflow calls one run may make.
+pub const MAX_SUBFLOW_DEPTH: usize = 8;
+
+tokio::task_local! {
+    /// `pack:flow` of every flow the running subflow was called through, outermost first.
+    static SUBFLOW_CHAIN: Vec<String>;
+}
+
 pub struct FlowEngine {
     packs: Vec<Arc<PackRuntime>>,
     flows: Vec<FlowDescriptor>,
@@ -34,6 +46,12 @@ pub struct FlowEngine {
     flow_cache: RwLock<HashMap<FlowKey, HostFlow>>,
     default_env: String,
     validation: ValidationConfig,
+    /// Tenant attributes from the bindings file; flows carry no request attributes.
+    attributes: Vec<(String, String)>,
+    deterministic: Option<DeterministicConfig>,
+    templates: TemplateEngine,
+    /// Nodes run at once when their inputs allow; 1 runs flows strictly in order.
+    max_parallel_nodes: usize,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -85,6 +103,10 @@ pub struct HostNode {
     operation_in_mapping: Option<String>,
     payload_expr: Value,
     routing: Routing,
+    /// Overrides of the tenant retry policy from the flow metadata.
+    retry: Option<NodeRetry>,
+    /// Earlier outputs the node's templates read.
+    inputs: NodeInputs,
 }
 
 impl HostNode {
@@ -99,6 +121,11 @@ impl HostNode {
     pub fn operation_in_mapping(&self) -> Option<&str> {
         self.operation_in_mapping.as_deref()
     }
+
+    /// Whether the node is a built-in `emit.*` whose payload becomes egress.
+    pub fn is_emit(&self) -> bool {
+        matches!(self.kind, NodeKind::BuiltinEmit { .. })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -206,6 +233,7 @@ impl FlowEngine {
             }
         }
 
+        let max_parallel_nodes = config.rate_limits.max_parallel_nodes.unwrap_or(1).max(1);
         let mut flow_map = HashMap::new();
         for flow in &descriptors {
             let pack_id = flow.pack_id.clone();
@@ -218,12 +246,16 @@ impl FlowEngine {
                 let task_flow_id = flow_id.clone();
                 match task::spawn_blocking(move || pack_clone.load_flow(&task_flow_id)).await {
                     Ok(Ok(loaded_flow)) => {
+                        let host_flow = HostFlow::from(loaded_flow);
+                        host_flow
+                            .check_acyclic()
+                            .with_context(|| format!("invalid flow in pack {pack_id}"))?;
                         flow_map.insert(
                             FlowKey {
                                 pack_id: pack_id.clone(),
                                 flow_id,
                             },
-                            HostFlow::from(loaded_flow),
+                            host_flow,
                         );
                     }
                     Ok(Err(err)) => {
@@ -243,9 +275,27 @@ impl FlowEngine {
             flow_cache: RwLock::new(flow_map),
             default_env: env::var("GREENTIC_ENV").unwrap_or_else(|_| "local".to_string()),
             validation: config.validation.clone(),
+            attributes: config
+                .attributes
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            deterministic: config.wasi.deterministic,
+            templates: TemplateEngine::shared(),
+            max_parallel_nodes,
         })
     }
 
+    /// Render node templates with `templates` instead of the shared engine.
+    pub fn with_templates(mut self, templates: TemplateEngine) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn templates(&self) -> &TemplateEngine {
+        &self.templates
+    }
+
     async fn get_or_load_flow(&self, pack_id: &str, flow_id: &str) -> Result<HostFlow> {
         let key = FlowKey {
             pack_id: pack_id.to_string(),
@@ -266,6 +316,7 @@ impl FlowEngine {
             .await
             .context("failed to join flow metadata task")??;
         let host_flow = HostFlow::from(flow);
+        host_flow.check_acyclic()?;
         self.flow_cache.write().insert(
             FlowKey {
                 pack_id: pack_id.to_string(),
@@ -277,14 +328,21 @@ impl FlowEngine {
     }
 
     pub async fn execute(&self, ctx: FlowContext<'_>, input: Value) -> Result<FlowExecution> {
+        // A run outside any span (not called by an operator invoke) starts its own trace.
+        let is_root = Span::current().is_none();
         let span = tracing::info_span!(
             "flow.execute",
             tenant = tracing::field::Empty,
             flow_id = tracing::field::Empty,
             node_id = tracing::field::Empty,
             tool = tracing::field::Empty,
-            action = tracing::field::Empty
+            action = tracing::field::Empty,
+            deterministic_seed = tracing::field::Empty,
+            deterministic_start_ms = tracing::field::Empty
         );
+        if let Some(deterministic) = &self.deterministic {
+            deterministic.record(&span);
+        }
         annotate_span(
             &span,
             &FlowSpanAttributes {
@@ -295,6 +353,7 @@ impl FlowEngine {
                 action: ctx.action,
             },
         );
+        let sampling = is_root.then(|| sampling::start_root(&span, ctx.tenant, None));
         set_flow_context(
             &self.default_env,
             ctx.tenant,
@@ -303,48 +362,27 @@ impl FlowEngine {
             ctx.provider_id,
             ctx.session_id,
         );
-        let retry_config = ctx.retry_config;
-        let original_input = input;
-        let mut ctx = ctx;
-        async move {
-            let mut attempt = 0u32;
-            loop {
-                attempt += 1;
-                ctx.attempt = attempt;
-                #[cfg(feature = "fault-injection")]
-                {
-                    let fault_ctx = FaultContext {
-                        pack_id: ctx.pack_id,
-                        flow_id: ctx.flow_id,
-                        node_id: ctx.node_id,
-                        attempt: ctx.attempt,
-                    };
-                    maybe_fail(FaultPoint::Timeout, fault_ctx)
-                        .map_err(|err| anyhow!(err.to_string()))?;
-                }
-                match self.execute_once(&ctx, original_input.clone()).await {
-                    Ok(value) => return Ok(value),
-                    Err(err) => {
-                        if attempt >= retry_config.max_attempts || !should_retry(&err) {
-                            return Err(err);
-                        }
-                        let delay = backoff_delay_ms(retry_config.base_delay_ms, attempt - 1);
-                        tracing::warn!(
-                            tenant = ctx.tenant,
-                            flow_id = ctx.flow_id,
-                            attempt,
-                            max_attempts = retry_config.max_attempts,
-                            delay_ms = delay,
-                            error = %err,
-                            "transient flow execution failure, backing off"
-                        );
-                        tokio::time::sleep(Duration::from_millis(delay)).await;
-                    }
-                }
+        let result = async move {
+            #[cfg(feature = "fault-injection")]
+            {
+                let fault_ctx = FaultContext {
+                    pack_id: ctx.pack_id,
+                    flow_id: ctx.flow_id,
+                    node_id: ctx.node_id,
+                    attempt: ctx.attempt,
+                };
+                maybe_fail(FaultPoint::Timeout, fault_ctx)
+                    .map_err(|err| anyhow!(err.to_string()))?;
             }
+            self.execute_once(&ctx, input).await
         }
         .instrument(span)
-        .await
+        .await;
+        if let Some(sampling) = sampling {
+            let error = result.as_ref().err().map(|err| format!("{err:#}"));
+            sampling.finish(error.as_deref());
+        }
+        result
     }
 
     pub async fn resume(
@@ -371,8 +409,23 @@ impl FlowEngine {
         let mut state = snapshot.state;
         state.replace_input(input);
         state.ensure_entry();
-        self.drive_flow(&ctx, flow_ir, state, Some(snapshot.next_node))
-            .await
+        let next_node = match snapshot.next_node.split_once('/') {
+            // `node/child`: the subflow called by `node` paused before `child`; `node`
+            // runs again and resumes it.
+            Some((node, child)) => {
+                let pending = state.subflow.as_ref().filter(|pending| {
+                    pending.node_id == node && pending.snapshot.next_node == child
+                });
+                if pending.is_none() {
+                    bail!(
+                        "snapshot resumes subflow node {node} at {child} without its subflow state"
+                    );
+                }
+                node.to_string()
+            }
+            None => snapshot.next_node,
+        };
+        self.drive_flow(&ctx, flow_ir, state, Some(next_node)).await
     }
 
     async fn execute_once(&self, ctx: &FlowContext<'_>, input: Value) -> Result<FlowExecution> {
@@ -399,64 +452,32 @@ impl FlowEngine {
         };
 
         loop {
-            let node = flow_ir
-                .nodes
-                .get(&current)
-                .with_context(|| format!("node {} not found", current.as_str()))?;
-
-            let payload_template = node.payload_expr.clone();
-            let prev = state
-                .last_output
-                .as_ref()
-                .cloned()
-                .unwrap_or_else(|| Value::Object(JsonMap::new()));
-            let ctx_value = template_context(&state, prev);
-            #[cfg(feature = "fault-injection")]
-            {
-                let fault_ctx = FaultContext {
-                    pack_id: ctx.pack_id,
-                    flow_id: ctx.flow_id,
-                    node_id: Some(current.as_str()),
-                    attempt: ctx.attempt,
-                };
-                maybe_fail(FaultPoint::TemplateRender, fault_ctx)
-                    .map_err(|err| anyhow!(err.to_string()))?;
-            }
-            let payload =
-                render_template_value(&payload_template, &ctx_value, TemplateOptions::default())
-                    .context("failed to render node input template")?;
-            let observed_payload = payload.clone();
-            let node_id = current.clone();
-            let event = NodeEvent {
-                context: ctx,
-                node_id: node_id.as_str(),
-                node,
-                payload: &observed_payload,
+            let segment = if self.max_parallel_nodes > 1 {
+                flow_ir.segment(&current)
+            } else {
+                vec![current.clone()]
             };
-            if let Some(observer) = ctx.observer {
-                observer.on_node_start(&event);
-            }
-            let dispatch = self
-                .dispatch_node(ctx, node_id.as_str(), node, &mut state, payload, &event)
-                .await;
             let DispatchOutcome {
                 output,
                 wait_reason,
-            } = match dispatch {
-                Ok(outcome) => outcome,
-                Err(err) => {
-                    if let Some(observer) = ctx.observer {
-                        observer.on_node_error(&event, err.as_ref());
-                    }
-                    return Err(err);
-                }
+                subflow,
+            } = if segment.len() > 1 {
+                self.run_segment(ctx, &flow_ir, &segment, &mut state)
+                    .await?
+            } else {
+                let node = flow_ir
+                    .nodes
+                    .get(&current)
+                    .with_context(|| format!("node {} not found", current.as_str()))?;
+                self.run_node(ctx, &current, node, &mut state).await?
             };
-
-            state.nodes.insert(node_id.clone().into(), output.clone());
-            state.last_output = Some(output.payload.clone());
-            if let Some(observer) = ctx.observer {
-                observer.on_node_end(&event, &output.payload);
-            }
+            // A segment only continues routing from its last node, which is the only one
+            // that may wait.
+            let node_id = segment.last().cloned().unwrap_or(current);
+            let node = flow_ir
+                .nodes
+                .get(&node_id)
+                .with_context(|| format!("node {} not found", node_id.as_str()))?;
 
             let (next, should_exit) = match &node.routing {
                 Routing::Next { node_id } => (Some(node_id.clone()), false),
@@ -474,18 +495,31 @@ impl FlowEngine {
             };
 
             if let Some(wait_reason) = wait_reason {
-                let resume_target = next.clone().ok_or_else(|| {
-                    anyhow!(
-                        "session.wait node {} requires a non-empty route",
-                        current.as_str()
-                    )
-                })?;
+                let resume_target = match &subflow {
+                    Some(child) => format!("{}/{}", node_id.as_str(), child.next_node),
+                    None => next
+                        .clone()
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "session.wait node {} requires a non-empty route",
+                                node_id.as_str()
+                            )
+                        })?
+                        .as_str()
+                        .to_string(),
+                };
                 let mut snapshot_state = state.clone();
                 snapshot_state.clear_egress();
+                snapshot_state.subflow = subflow.map(|snapshot| {
+                    Box::new(PendingSubflow {
+                        node_id: node_id.as_str().to_string(),
+                        snapshot,
+                    })
+                });
                 let snapshot = FlowSnapshot {
                     pack_id: ctx.pack_id.to_string(),
                     flow_id: ctx.flow_id.to_string(),
-                    next_node: resume_target.as_str().to_string(),
+                    next_node: resume_target,
                     state: snapshot_state,
                 };
                 let output_value = state.clone().finalize_with(None);
@@ -515,6 +549,201 @@ impl FlowEngine {
         }
     }
 
+    /// Render the node's input, run it and record its output in `state`.
+    async fn run_node(
+        &self,
+        ctx: &FlowContext<'_>,
+        node_id: &NodeId,
+        node: &HostNode,
+        state: &mut ExecutionState,
+    ) -> Result<DispatchOutcome> {
+        let payload_template = node.payload_expr.clone();
+        let prev = state
+            .last_output
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| Value::Object(JsonMap::new()));
+        let ctx_value = template_context(state, prev);
+        #[cfg(feature = "fault-injection")]
+        {
+            let fault_ctx = FaultContext {
+                pack_id: ctx.pack_id,
+                flow_id: ctx.flow_id,
+                node_id: Some(node_id.as_str()),
+                attempt: ctx.attempt,
+            };
+            maybe_fail(FaultPoint::TemplateRender, fault_ctx)
+                .map_err(|err| anyhow!(err.to_string()))?;
+        }
+        let source = TemplateSource {
+            pack_id: ctx.pack_id,
+            flow_id: ctx.flow_id,
+            node_id: node_id.as_str(),
+            field: "input",
+        };
+        let payload = self.templates.render_value_with(
+            &payload_template,
+            &ctx_value,
+            TemplateOptions::default(),
+            Some(&source),
+        )?;
+        let observed_payload = payload.clone();
+        let event = NodeEvent {
+            context: ctx,
+            node_id: node_id.as_str(),
+            node,
+            payload: &observed_payload,
+        };
+        if let Some(observer) = ctx.observer {
+            observer.on_node_start(&event);
+        }
+        let dispatch = self
+            .dispatch_with_retry(ctx, node_id.as_str(), node, state, payload, &event)
+            .await;
+        let outcome = match dispatch {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                if let Some(observer) = ctx.observer {
+                    observer.on_node_error(&event, err.as_ref());
+                }
+                return Err(err);
+            }
+        };
+
+        state
+            .nodes
+            .insert(node_id.as_str().to_string(), outcome.output.clone());
+        state.last_output = Some(outcome.output.payload.clone());
+        if let Some(observer) = ctx.observer {
+            observer.on_node_end(&event, &outcome.output.payload);
+        }
+        Ok(outcome)
+    }
+
+    /// Run the nodes of `segment`, in routing order, in waves: each wave starts every
+    /// node whose inputs are ready, at most `max_parallel_nodes` at a time, and merges
+    /// the outputs into `state` by node id once all of them finished. Every node sees
+    /// the outputs a sequential run would have shown it, so the final state is the same.
+    /// Returns the outcome of the last node.
+    async fn run_segment(
+        &self,
+        ctx: &FlowContext<'_>,
+        flow_ir: &HostFlow,
+        segment: &[NodeId],
+        state: &mut ExecutionState,
+    ) -> Result<DispatchOutcome> {
+        let nodes = segment
+            .iter()
+            .map(|id| {
+                flow_ir
+                    .nodes
+                    .get(id)
+                    .with_context(|| format!("node {} not found", id.as_str()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let deps = segment_dependencies(segment, &nodes);
+        let base = state.clone();
+        let mut outcomes: Vec<Option<DispatchOutcome>> = segment.iter().map(|_| None).collect();
+        while outcomes.iter().any(Option::is_none) {
+            let wave = (0..segment.len())
+                .filter(|&index| {
+                    outcomes[index].is_none()
+                        && deps[index].iter().all(|&dep| outcomes[dep].is_some())
+                })
+                .collect::<Vec<_>>();
+            let runs = wave.iter().map(|&index| {
+                let (node_id, node) = (&segment[index], nodes[index]);
+                let mut view = base.segment_view(segment, &outcomes, index);
+                async move {
+                    let outcome = self.run_node(ctx, node_id, node, &mut view).await;
+                    (index, outcome.map(|outcome| (outcome, view.egress)))
+                }
+            });
+            let mut finished = stream::iter(runs)
+                .buffer_unordered(self.max_parallel_nodes)
+                .collect::<Vec<_>>()
+                .await;
+            finished.sort_by(|(a, _), (b, _)| segment[*a].as_str().cmp(segment[*b].as_str()));
+            for (index, outcome) in finished {
+                let (outcome, egress) = outcome?;
+                state
+                    .nodes
+                    .insert(segment[index].as_str().to_string(), outcome.output.clone());
+                state.egress.extend(egress);
+                outcomes[index] = Some(outcome);
+            }
+        }
+        let last = outcomes
+            .pop()
+            .flatten()
+            .context("flow segment ran no nodes")?;
+        state.last_output = Some(last.output.payload.clone());
+        Ok(last)
+    }
+
+    /// Run the node until it succeeds, fails with an error its retry policy does not
+    /// cover, or runs out of attempts. Each attempt sees its number in `ctx.attempt`,
+    /// counted on from the attempt `ctx` starts at (the delivery of a redelivered run).
+    async fn dispatch_with_retry(
+        &self,
+        ctx: &FlowContext<'_>,
+        node_id: &str,
+        node: &HostNode,
+        state: &mut ExecutionState,
+        payload: Value,
+        event: &NodeEvent<'_>,
+    ) -> Result<DispatchOutcome> {
+        // The nodes of a called flow retry on their own.
+        let policy = match node.kind {
+            NodeKind::FlowCall => None,
+            _ => Some(ctx.retry_config.for_node(node.retry.as_ref())),
+        };
+        let mut attempt = 1u32;
+        loop {
+            let attempt_ctx = ctx.with_attempt(ctx.attempt + attempt - 1);
+            let outcome = self
+                .dispatch_node(&attempt_ctx, node_id, node, state, payload.clone(), event)
+                .await;
+            let err = match outcome {
+                Ok(outcome) => return Ok(outcome),
+                Err(err) => err,
+            };
+            let Some(policy) = policy
+                .as_ref()
+                .filter(|policy| attempt < policy.max_attempts && policy.retries(&err))
+            else {
+                if attempt == 1 {
+                    return Err(err);
+                }
+                return Err(RetriesExhausted {
+                    node_id: node_id.to_string(),
+                    attempts: attempt,
+                    error: err,
+                }
+                .into());
+            };
+            let hint_ms = err
+                .downcast_ref::<NodeFailure>()
+                .and_then(|failure| failure.backoff_ms);
+            let delay = Duration::from_millis(policy.delay_ms(attempt - 1, hint_ms));
+            tracing::warn!(
+                tenant = ctx.tenant,
+                flow_id = ctx.flow_id,
+                node_id,
+                attempt,
+                max_attempts = policy.max_attempts,
+                delay_ms = delay.as_millis() as u64,
+                error = %err,
+                "node failed, retrying"
+            );
+            if let Some(observer) = ctx.observer {
+                observer.on_node_retry(event, attempt, err.as_ref(), delay);
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
     async fn dispatch_node(
         &self,
         ctx: &FlowContext<'_>,
@@ -543,10 +772,10 @@ impl FlowEngine {
                 .execute_component_call(ctx, node_id, node, payload, component_ref.as_str(), event)
                 .await
                 .map(DispatchOutcome::complete),
-            NodeKind::FlowCall => self
-                .execute_flow_call(ctx, payload)
-                .await
-                .map(DispatchOutcome::complete),
+            NodeKind::FlowCall => {
+                self.execute_flow_call(ctx, node_id, node, state, payload)
+                    .await
+            }
             NodeKind::ProviderInvoke => self
                 .execute_provider_invoke(ctx, node_id, state, payload, event)
                 .await
@@ -558,6 +787,9 @@ impl FlowEngine {
                         tracing::debug!(%component, "handling emit.* as builtin");
                     }
                 }
+                if let Some(observer) = ctx.observer {
+                    observer.on_egress(event, &payload);
+                }
                 state.push_egress(payload.clone());
                 Ok(DispatchOutcome::complete(NodeOutput::new(payload)))
             }
@@ -568,54 +800,98 @@ impl FlowEngine {
         }
     }
 
-    async fn execute_flow_call(&self, ctx: &FlowContext<'_>, payload: Value) -> Result<NodeOutput> {
+    /// Run the flow a `flow.call`/`subflow` node names, in the same tenant and by default
+    /// the same pack, and return its output as the node's. The called flow runs inside
+    /// the caller's span and cancel scope with the caller's attempt; calls nested deeper
+    /// than [`MAX_SUBFLOW_DEPTH`] fail. When the called flow waits, the node waits with
+    /// it and resumes it where it paused.
+    async fn execute_flow_call(
+        &self,
+        ctx: &FlowContext<'_>,
+        node_id: &str,
+        node: &HostNode,
+        state: &mut ExecutionState,
+        payload: Value,
+    ) -> Result<DispatchOutcome> {
         #[derive(Deserialize)]
         struct FlowCallPayload {
+            #[serde(default)]
+            pack_id: Option<String>,
             #[serde(alias = "flow")]
             flow_id: String,
             #[serde(default)]
             input: Value,
         }
 
-        let call: FlowCallPayload =
-            serde_json::from_value(payload).context("invalid payload for flow.call node")?;
+        let call: FlowCallPayload = serde_json::from_value(payload)
+            .with_context(|| format!("invalid payload for {} node", node.component))?;
         if call.flow_id.trim().is_empty() {
-            bail!("flow.call requires a non-empty flow_id");
+            bail!("{} requires a non-empty flow_id", node.component);
         }
 
-        let sub_input = if call.input.is_null() {
-            Value::Null
-        } else {
-            call.input
+        let pending = state.subflow.take_if(|pending| pending.node_id == node_id);
+        let (pack_id, flow_id) = match &pending {
+            Some(pending) => (
+                pending.snapshot.pack_id.clone(),
+                pending.snapshot.flow_id.clone(),
+            ),
+            None => (
+                call.pack_id
+                    .filter(|pack_id| !pack_id.trim().is_empty())
+                    .unwrap_or_else(|| ctx.pack_id.to_string()),
+                call.flow_id,
+            ),
         };
-
-        let flow_id_owned = call.flow_id;
-        let action = "flow.call";
+        let chain = subflow_chain(ctx, &pack_id, &flow_id)?;
         let sub_ctx = FlowContext {
             tenant: ctx.tenant,
-            pack_id: ctx.pack_id,
-            flow_id: flow_id_owned.as_str(),
+            pack_id: pack_id.as_str(),
+            flow_id: flow_id.as_str(),
             node_id: None,
             tool: ctx.tool,
-            action: Some(action),
+            action: Some(node.component.as_str()),
             session_id: ctx.session_id,
             provider_id: ctx.provider_id,
-            retry_config: ctx.retry_config,
+            retry_config: ctx.retry_config.clone(),
             attempt: ctx.attempt,
             observer: ctx.observer,
             mocks: ctx.mocks,
         };
 
-        let execution = Box::pin(self.execute(sub_ctx, sub_input))
-            .await
-            .with_context(|| format!("flow.call failed for {}", flow_id_owned))?;
+        let execution = match pending {
+            Some(pending) => {
+                let input = state.input.clone();
+                SUBFLOW_CHAIN
+                    .scope(
+                        chain,
+                        Box::pin(self.resume(sub_ctx, pending.snapshot, input)),
+                    )
+                    .await
+            }
+            None => {
+                SUBFLOW_CHAIN
+                    .scope(chain, Box::pin(self.execute(sub_ctx, call.input)))
+                    .await
+            }
+        }
+        .with_context(|| format!("{} {pack_id}:{flow_id} failed", node.component))?;
         match execution.status {
-            FlowStatus::Completed => Ok(NodeOutput::new(execution.output)),
-            FlowStatus::Waiting(wait) => bail!(
-                "flow.call cannot pause (flow {} waiting {:?})",
-                flow_id_owned,
-                wait.reason
-            ),
+            FlowStatus::Completed => {
+                Ok(DispatchOutcome::complete(NodeOutput::new(execution.output)))
+            }
+            FlowStatus::Waiting(wait) => {
+                // What the called flow emitted before it paused goes out with the reply.
+                match execution.output {
+                    Value::Null => {}
+                    Value::Array(items) => state.egress.extend(items),
+                    other => state.push_egress(other),
+                }
+                Ok(DispatchOutcome {
+                    output: NodeOutput::new(Value::Null),
+                    wait_reason: Some(wait.reason.unwrap_or_else(|| "subflow".to_string())),
+                    subflow: Some(wait.snapshot),
+                })
+            }
         }
     }
 
@@ -747,7 +1023,7 @@ impl FlowEngine {
             Some(serde_json::to_string(&call.config)?)
         };
 
-        let exec_ctx = component_exec_ctx(ctx, node_id);
+        let exec_ctx = component_exec_ctx(ctx, node_id, &self.attributes);
         #[cfg(feature = "fault-injection")]
         {
             let fault_ctx = FaultContext {
@@ -780,13 +1056,8 @@ impl FlowEngine {
                 .map_err(|err| anyhow!(err.to_string()))?;
         }
 
-        if let Some((code, message)) = component_error(&value) {
-            bail!(
-                "component {} failed: {}: {}",
-                call.component_ref,
-                code,
-                message
-            );
+        if let Some(failure) = component_error(&call.component_ref, &value) {
+            return Err(failure.into());
         }
         Ok(NodeOutput::new(value))
     }
@@ -839,14 +1110,20 @@ impl FlowEngine {
                 map.insert("input".into(), payload.input.clone());
                 map.insert("result".into(), payload.input.clone());
             }
-            render_template_value(
+            self.templates.render_value_with(
                 &payload.in_map,
                 &ctx_value,
                 TemplateOptions {
                     allow_pointer: true,
+                    ..TemplateOptions::default()
                 },
-            )
-            .context("failed to render provider.invoke in_map")?
+                Some(&TemplateSource {
+                    pack_id: ctx.pack_id,
+                    flow_id: ctx.flow_id,
+                    node_id,
+                    field: "input/in_map",
+                }),
+            )?
         } else if !payload.input.is_null() {
             payload.input
         } else {
@@ -875,7 +1152,7 @@ impl FlowEngine {
             payload.provider_id.as_deref(),
             payload.provider_type.as_deref(),
         )?;
-        let exec_ctx = component_exec_ctx(ctx, node_id);
+        let exec_ctx = component_exec_ctx(ctx, node_id, &self.attributes);
         #[cfg(feature = "fault-injection")]
         {
             let fault_ctx = FaultContext {
@@ -910,14 +1187,20 @@ impl FlowEngine {
                 map.insert("input".into(), result.clone());
                 map.insert("result".into(), result.clone());
             }
-            render_template_value(
+            self.templates.render_value_with(
                 &payload.out_map,
                 &ctx_value,
                 TemplateOptions {
                     allow_pointer: true,
+                    ..TemplateOptions::default()
                 },
-            )
-            .context("failed to render provider.invoke out_map")?
+                Some(&TemplateSource {
+                    pack_id: ctx.pack_id,
+                    flow_id: ctx.flow_id,
+                    node_id,
+                    field: "input/out_map",
+                }),
+            )?
         };
         let _ = payload.err_map;
         Ok(NodeOutput::new(output))
@@ -1056,6 +1339,17 @@ pub trait ExecutionObserver: Send + Sync {
     fn on_node_end(&self, event: &NodeEvent<'_>, output: &Value);
     fn on_node_error(&self, event: &NodeEvent<'_>, error: &dyn StdError);
     fn on_validation(&self, _event: &NodeEvent<'_>, _issues: &[ValidationIssue]) {}
+    /// A built-in `emit.*` node queued `payload` as egress.
+    fn on_egress(&self, _event: &NodeEvent<'_>, _payload: &Value) {}
+    /// Attempt `attempt` of the node failed with `error`; the next starts after `delay`.
+    fn on_node_retry(
+        &self,
+        _event: &NodeEvent<'_>,
+        _attempt: u32,
+        _error: &dyn StdError,
+        _delay: Duration,
+    ) {
+    }
 }
 
 pub struct NodeEvent<'a> {
@@ -1077,6 +1371,16 @@ pub struct ExecutionState {
     egress: Vec<Value>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     last_output: Option<Value>,
+    /// The paused flow a subflow node of this run is waiting on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subflow: Option<Box<PendingSubflow>>,
+}
+
+/// A flow called by `node_id` that paused; the node resumes it when the run resumes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PendingSubflow {
+    node_id: String,
+    snapshot: FlowSnapshot,
 }
 
 impl ExecutionState {
@@ -1087,6 +1391,7 @@ impl ExecutionState {
             nodes: HashMap::new(),
             egress: Vec::new(),
             last_output: None,
+            subflow: None,
         }
     }
 
@@ -1122,6 +1427,37 @@ impl ExecutionState {
         }
         outputs
     }
+
+    /// What a sequential run shows the node at `index` of `segment`: the state before the
+    /// segment plus the outputs of its earlier nodes, with the one before it as `prev`.
+    fn segment_view(
+        &self,
+        segment: &[NodeId],
+        outcomes: &[Option<DispatchOutcome>],
+        index: usize,
+    ) -> Self {
+        let mut nodes = self.nodes.clone();
+        for (id, outcome) in segment[..index].iter().zip(outcomes) {
+            if let Some(outcome) = outcome {
+                nodes.insert(id.as_str().to_string(), outcome.output.clone());
+            }
+        }
+        let last_output = match index {
+            0 => self.last_output.clone(),
+            _ => outcomes[index - 1]
+                .as_ref()
+                .map(|outcome| outcome.output.payload.clone()),
+        };
+        Self {
+            entry: self.entry.clone(),
+            input: self.input.clone(),
+            nodes,
+            egress: Vec::new(),
+            last_output,
+            subflow: None,
+        }
+    }
+
     fn push_egress(&mut self, payload: Value) {
         self.egress.push(payload);
     }
@@ -1170,6 +1506,8 @@ impl NodeOutput {
 struct DispatchOutcome {
     output: NodeOutput,
     wait_reason: Option<String>,
+    /// Where the flow a subflow node called paused.
+    subflow: Option<FlowSnapshot>,
 }
 
 impl DispatchOutcome {
@@ -1177,6 +1515,7 @@ impl DispatchOutcome {
         Self {
             output,
             wait_reason: None,
+            subflow: None,
         }
     }
 
@@ -1184,11 +1523,33 @@ impl DispatchOutcome {
         Self {
             output,
             wait_reason: reason,
+            subflow: None,
         }
     }
 }
 
-fn component_exec_ctx(ctx: &FlowContext<'_>, node_id: &str) -> ComponentExecCtx {
+/// The call chain of a subflow run from `ctx` into `pack_id:flow_id`, or an error when
+/// it would nest deeper than [`MAX_SUBFLOW_DEPTH`].
+fn subflow_chain(ctx: &FlowContext<'_>, pack_id: &str, flow_id: &str) -> Result<Vec<String>> {
+    let mut chain = SUBFLOW_CHAIN
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| vec![format!("{}:{}", ctx.pack_id, ctx.flow_id)]);
+    chain.push(format!("{pack_id}:{flow_id}"));
+    if chain.len() > MAX_SUBFLOW_DEPTH + 1 {
+        return Err(SubflowDepthExceeded {
+            limit: MAX_SUBFLOW_DEPTH,
+            chain,
+        }
+        .into());
+    }
+    Ok(chain)
+}
+
+fn component_exec_ctx(
+    ctx: &FlowContext<'_>,
+    node_id: &str,
+    attributes: &[(String, String)],
+) -> ComponentExecCtx {
     ComponentExecCtx {
         tenant: ComponentTenantCtx {
             tenant: ctx.tenant.to_string(),
@@ -1200,6 +1561,8 @@ fn component_exec_ctx(ctx: &FlowContext<'_>, node_id: &str) -> ComponentExecCtx
             deadline_unix_ms: None,
             attempt: ctx.attempt,
             idempotency_key: ctx.session_id.map(str::to_string),
+            attributes: attributes.to_vec(),
+            impersonation: None,
         },
         i18n_id: None,
         flow_id: ctx.flow_id.to_string(),
@@ -1207,7 +1570,7 @@ fn component_exec_ctx(ctx: &FlowContext<'_>, node_id: &str) -> ComponentExecCtx
     }
 }
 
-fn component_error(value: &Value) -> Option<(String, String)> {
+fn component_error(component: &str, value: &Value) -> Option<NodeFailure> {
     let obj = value.as_object()?;
     let ok = obj.get("ok").and_then(Value::as_bool)?;
     if ok {
@@ -1222,7 +1585,16 @@ fn component_error(value: &Value) -> Option<(String, String)> {
         .get("message")
         .and_then(Value::as_str)
         .unwrap_or("component reported error");
-    Some((code.to_string(), message.to_string()))
+    Some(NodeFailure {
+        component: component.to_string(),
+        code: code.to_string(),
+        message: message.to_string(),
+        retryable: err
+            .get("retryable")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        backoff_ms: err.get("backoff_ms").and_then(Value::as_u64),
+    })
 }
 
 fn extract_wait_reason(payload: &Value) -> Option<String> {
@@ -1252,9 +1624,12 @@ fn template_context(state: &ExecutionState, prev: Value) -> Value {
 
 impl From<Flow> for HostFlow {
     fn from(value: Flow) -> Self {
+        let mut retries = node_retries(&value);
         let mut nodes = IndexMap::new();
         for (id, node) in value.nodes {
-            nodes.insert(id.clone(), HostNode::from(node));
+            let mut host_node = HostNode::from(node);
+            host_node.retry = retries.remove(id.as_str());
+            nodes.insert(id.clone(), host_node);
         }
         let start = value
             .entrypoints
@@ -1270,6 +1645,162 @@ impl From<Flow> for HostFlow {
     }
 }
 
+impl HostFlow {
+    /// The nodes routing runs from `start` until the flow ends, waits or loops back,
+    /// with `start` first and the node that continues routing last.
+    fn segment(&self, start: &NodeId) -> Vec<NodeId> {
+        let mut segment = vec![start.clone()];
+        while let Some(node) = segment.last().and_then(|id| self.nodes.get(id)) {
+            // Only the last node of a segment may pause the run.
+            if matches!(node.kind, NodeKind::Wait | NodeKind::FlowCall) {
+                break;
+            }
+            let next = match &node.routing {
+                Routing::Next { node_id } => Some(node_id),
+                Routing::Branch { default, .. } => default.as_ref(),
+                Routing::End | Routing::Reply | Routing::Custom(_) => None,
+            };
+            match next {
+                Some(next) if self.nodes.contains_key(next) && !segment.contains(next) => {
+                    segment.push(next.clone());
+                }
+                _ => break,
+            }
+        }
+        segment
+    }
+
+    /// Reject flows whose nodes read each other's outputs in a cycle; their order
+    /// cannot be worked out from the references.
+    fn check_acyclic(&self) -> Result<()> {
+        fn visit<'a>(
+            flow: &'a HostFlow,
+            id: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Result<()> {
+            if done.contains(id) {
+                return Ok(());
+            }
+            if let Some(pos) = path.iter().position(|seen| *seen == id) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.push(id);
+                bail!(
+                    "flow {} has a node dependency cycle: {}",
+                    flow.id,
+                    cycle.join(" -> ")
+                );
+            }
+            let Some((_, node)) = flow
+                .nodes
+                .iter()
+                .find(|(node_id, _)| node_id.as_str() == id)
+            else {
+                return Ok(());
+            };
+            path.push(id);
+            for dep in &node.inputs.nodes {
+                if dep != id {
+                    visit(flow, dep, path, done)?;
+                }
+            }
+            path.pop();
+            done.insert(id);
+            Ok(())
+        }
+
+        let mut done = HashSet::new();
+        for id in self.nodes.keys() {
+            visit(self, id.as_str(), &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+}
+
+/// For each node of `segment`, the positions of the earlier nodes it has to wait for:
+/// those its templates read, the one before it for `prev`, all of them for a template
+/// reading every output or for a wait, and the previous emit so egress keeps its order.
+fn segment_dependencies(segment: &[NodeId], nodes: &[&HostNode]) -> Vec<Vec<usize>> {
+    let mut last_emit = None;
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let inputs = &node.inputs;
+            let waits_for_all =
+                inputs.all || matches!(node.kind, NodeKind::Wait | NodeKind::FlowCall);
+            let mut deps = (0..index)
+                .filter(|&dep| {
+                    waits_for_all
+                        || (inputs.prev && dep + 1 == index)
+                        || inputs.nodes.contains(segment[dep].as_str())
+                })
+                .collect::<Vec<_>>();
+            if node.is_emit() {
+                deps.extend(last_emit.replace(index));
+            }
+            deps
+        })
+        .collect()
+}
+
+/// Outputs of other nodes a node's templates read.
+#[derive(Clone, Debug, Default)]
+struct NodeInputs {
+    /// Nodes named as `node.<id>` or `state.nodes.<id>`.
+    nodes: BTreeSet<String>,
+    /// `prev`, the output of the node routed here.
+    prev: bool,
+    /// The whole `node` or `state` object, so every earlier output.
+    all: bool,
+}
+
+impl NodeInputs {
+    fn scan(payload: &Value) -> Self {
+        let mut inputs = Self::default();
+        inputs.scan_value(payload);
+        inputs
+    }
+
+    fn scan_value(&mut self, value: &Value) {
+        match value {
+            Value::String(raw) => self.scan_template(raw),
+            Value::Array(items) => items.iter().for_each(|item| self.scan_value(item)),
+            Value::Object(map) => map.values().for_each(|item| self.scan_value(item)),
+            _ => {}
+        }
+    }
+
+    /// Look at every path in a handlebars template or mapping pointer; reading more
+    /// than the node needs only costs parallelism, so anything unclear reads it all.
+    fn scan_template(&mut self, raw: &str) {
+        if !raw.contains("{{") && !raw.starts_with('/') {
+            return;
+        }
+        let is_path_char = |ch: char| {
+            ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.' | '/' | '[' | ']' | '@')
+        };
+        for token in raw.split(|ch: char| !is_path_char(ch)) {
+            let segments = token
+                .split(['.', '/'])
+                .map(|segment| segment.trim_start_matches('[').trim_end_matches(']'))
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<_>>();
+            for (pos, segment) in segments.iter().enumerate() {
+                let rest = &segments[pos + 1..];
+                match (*segment, rest) {
+                    ("prev", _) => self.prev = true,
+                    ("node", [id, ..]) | ("state", ["nodes", id, ..]) => {
+                        self.nodes.insert((*id).to_string());
+                    }
+                    ("node", []) | ("state", [] | ["nodes"]) => self.all = true,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
 impl From<Node> for HostNode {
     fn from(node: Node) -> Self {
         let component_ref = node.component.id.as_str().to_string();
@@ -1312,7 +1843,7 @@ impl From<Node> for HostNode {
             }
         } else {
             match component_ref.as_str() {
-                "flow.call" => NodeKind::FlowCall,
+                "flow.call" | "subflow" => NodeKind::FlowCall,
                 "provider.invoke" => NodeKind::ProviderInvoke,
                 "session.wait" => NodeKind::Wait,
                 comp if comp.starts_with("emit.") => NodeKind::BuiltinEmit {
@@ -1327,7 +1858,7 @@ impl From<Node> for HostNode {
             NodeKind::Exec { .. } => "component.exec".to_string(),
             NodeKind::PackComponent { component_ref } => component_ref.clone(),
             NodeKind::ProviderInvoke => "provider.invoke".to_string(),
-            NodeKind::FlowCall => "flow.call".to_string(),
+            NodeKind::FlowCall => component_ref.clone(),
             NodeKind::BuiltinEmit { kind } => emit_ref_from_kind(kind),
             NodeKind::Wait => "session.wait".to_string(),
         };
@@ -1340,6 +1871,7 @@ impl From<Node> for HostNode {
             NodeKind::BuiltinEmit { .. } => extract_emit_payload(&node.input.mapping),
             _ => node.input.mapping.clone(),
         };
+        let inputs = NodeInputs::scan(&payload_expr);
         Self {
             kind,
             component: component_label,
@@ -1352,10 +1884,27 @@ impl From<Node> for HostNode {
             operation_in_mapping,
             payload_expr,
             routing: node.routing,
+            retry: None,
+            inputs,
         }
     }
 }
 
+/// Per-node retry overrides under `retry.<node_id>` in the flow's `metadata.extra`.
+fn node_retries(flow: &Flow) -> HashMap<String, NodeRetry> {
+    let Some(raw) = flow.metadata.extra.get("retry") else {
+        return HashMap::new();
+    };
+    serde_json::from_value(raw.clone()).unwrap_or_else(|err| {
+        tracing::warn!(
+            flow_id = %flow.id,
+            error = %err,
+            "ignoring invalid retry overrides in flow metadata"
+        );
+        HashMap::new()
+    })
+}
+
 fn extract_target_component(payload: &Value) -> Option<String> {
     match payload {
         Value::Object(map) => map
@@ -1547,8 +2096,8 @@ mod tests {
     use super::*;
     use crate::validate::{ValidationConfig, ValidationMode};
     use greentic_types::{
-        Flow, FlowComponentRef, FlowId, FlowKind, InputMapping, Node, NodeId, OutputMapping,
-        Routing, TelemetryHints,
+        Flow, FlowComponentRef, FlowId, FlowKind, FlowMetadata, InputMapping, Node, NodeId,
+        OutputMapping, Routing, TelemetryHints,
     };
     use serde_json::json;
     use std::collections::BTreeMap;
@@ -1566,6 +2115,10 @@ mod tests {
             validation: ValidationConfig {
                 mode: ValidationMode::Off,
             },
+            attributes: Vec::new(),
+            deterministic: None,
+            templates: TemplateEngine::shared(),
+            max_parallel_nodes: 1,
         }
     }
 
@@ -1622,7 +2175,7 @@ mod tests {
         let rt = Runtime::new().unwrap();
         let retry_config = RetryConfig {
             max_attempts: 1,
-            base_delay_ms: 1,
+            ..RetryConfig::default()
         };
         let ctx = FlowContext {
             tenant: "tenant",
@@ -1648,6 +2201,7 @@ mod tests {
             operation_in_mapping: None,
             payload_expr: Value::Null,
             routing: Routing::End,
+            retry: None,
         };
         let _state = ExecutionState::new(Value::Null);
         let payload = json!({ "component": "qa.process" });
@@ -1687,7 +2241,7 @@ mod tests {
         let rt = Runtime::new().unwrap();
         let retry_config = RetryConfig {
             max_attempts: 1,
-            base_delay_ms: 1,
+            ..RetryConfig::default()
         };
         let ctx = FlowContext {
             tenant: "tenant",
@@ -1713,6 +2267,7 @@ mod tests {
             operation_in_mapping: Some("render".into()),
             payload_expr: Value::Null,
             routing: Routing::End,
+            retry: None,
         };
         let _state = ExecutionState::new(Value::Null);
         let payload = json!({ "component": "qa.process" });
@@ -1821,6 +2376,10 @@ mod tests {
             validation: ValidationConfig {
                 mode: ValidationMode::Off,
             },
+            attributes: Vec::new(),
+            deterministic: None,
+            templates: TemplateEngine::shared(),
+            max_parallel_nodes: 1,
         };
         let observer = CountingObserver::new();
         let ctx = FlowContext {
@@ -1834,7 +2393,7 @@ mod tests {
             provider_id: None,
             retry_config: RetryConfig {
                 max_attempts: 1,
-                base_delay_ms: 1,
+                ..RetryConfig::default()
             },
             attempt: 1,
             observer: Some(&observer),
@@ -1851,9 +2410,383 @@ mod tests {
         assert_eq!(ends.len(), 1);
         assert_eq!(ends[0], json!({ "message": "logged" }));
     }
+
+    fn emit_node(id: &NodeId) -> Node {
+        Node {
+            id: id.clone(),
+            component: FlowComponentRef {
+                id: "emit.log".parse().unwrap(),
+                pack_alias: None,
+                operation: None,
+            },
+            input: InputMapping {
+                mapping: json!({ "message": "logged" }),
+            },
+            output: OutputMapping {
+                mapping: Value::Null,
+            },
+            routing: Routing::End,
+            telemetry: TelemetryHints::default(),
+        }
+    }
+
+    #[test]
+    fn flow_metadata_overrides_node_retry() {
+        let flaky = NodeId::from_str("flaky").unwrap();
+        let steady = NodeId::from_str("steady").unwrap();
+        let mut nodes = indexmap::IndexMap::default();
+        nodes.insert(flaky.clone(), emit_node(&flaky));
+        nodes.insert(steady.clone(), emit_node(&steady));
+        let flow = Flow {
+            schema_version: "1.0".into(),
+            id: FlowId::from_str("retry.flow").unwrap(),
+            kind: FlowKind::Messaging,
+            entrypoints: BTreeMap::new(),
+            nodes,
+            metadata: FlowMetadata {
+                extra: json!({
+                    "retry": {
+                        "flaky": { "max_attempts": 5, "max_backoff_ms": 2000, "retry_on": ["busy"] }
+                    }
+                }),
+                ..Default::default()
+            },
+        };
+        let host_flow = HostFlow::from(flow);
+        let tenant = RetryConfig {
+            max_attempts: 2,
+            ..RetryConfig::default()
+        };
+
+        let node = &host_flow.nodes[&flaky];
+        let policy = tenant.for_node(node.retry.as_ref());
+        assert_eq!(policy.max_attempts, 5);
+        assert_eq!(policy.max_delay_ms, 2000);
+        assert_eq!(policy.base_delay_ms, tenant.base_delay_ms);
+        assert_eq!(policy.retry_on, ["busy"]);
+
+        let node = &host_flow.nodes[&steady];
+        assert!(node.retry.is_none());
+        assert_eq!(tenant.for_node(node.retry.as_ref()).max_attempts, 2);
+    }
+
+    /// A flow of `emit.log`-style nodes routed in the given order, each with `input`.
+    fn chained_flow(nodes: &[(&str, Value)]) -> HostFlow {
+        let mut mapped = indexmap::IndexMap::default();
+        for (index, (id, input)) in nodes.iter().enumerate() {
+            let node_id = NodeId::from_str(id).unwrap();
+            let mut node = emit_node(&node_id);
+            node.component.id = "qa.process".parse().unwrap();
+            node.input.mapping = input.clone();
+            if let Some((next, _)) = nodes.get(index + 1) {
+                node.routing = Routing::Next {
+                    node_id: NodeId::from_str(next).unwrap(),
+                };
+            }
+            mapped.insert(node_id, node);
+        }
+        HostFlow::from(Flow {
+            schema_version: "1.0".into(),
+            id: FlowId::from_str("fanout.flow").unwrap(),
+            kind: FlowKind::Messaging,
+            entrypoints: BTreeMap::new(),
+            nodes: mapped,
+            metadata: Default::default(),
+        })
+    }
+
+    #[test]
+    fn node_inputs_come_from_template_references() {
+        let inputs = NodeInputs::scan(&json!({
+            "a": "{{node.left.text}} and {{state.nodes.[right].payload}}",
+            "b": ["/node/pointer/x", "{{#each prev.items}}{{this}}{{/each}}"],
+            "c": "node.plain text is not a template",
+        }));
+        assert_eq!(
+            inputs.nodes.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["left", "pointer", "right"]
+        );
+        assert!(inputs.prev);
+        assert!(!inputs.all);
+        assert!(NodeInputs::scan(&json!("{{json node}}")).all);
+        assert!(NodeInputs::scan(&json!("{{lookup state.nodes \"left\"}}")).all);
+        assert!(!NodeInputs::scan(&json!("{{entry.text}} {{state.input}}")).all);
+    }
+
+    #[test]
+    fn segments_wait_only_for_the_nodes_they_read() {
+        let flow = chained_flow(&[
+            ("left", json!({ "text": "{{entry.text}}" })),
+            ("right", json!({ "text": "{{entry.text}}" })),
+            (
+                "merge",
+                json!({ "l": "{{node.left}}", "r": "{{node.right}}" }),
+            ),
+            ("after", json!({ "text": "{{prev.text}}" })),
+            ("report", json!({ "all": "{{json node}}" })),
+        ]);
+        let start = NodeId::from_str("left").unwrap();
+        let segment = flow.segment(&start);
+        assert_eq!(segment.len(), 5);
+        let nodes = segment.iter().map(|id| &flow.nodes[id]).collect::<Vec<_>>();
+        assert_eq!(
+            segment_dependencies(&segment, &nodes),
+            [vec![], vec![], vec![0, 1], vec![2], vec![0, 1, 2, 3]]
+        );
+        assert!(flow.check_acyclic().is_ok());
+    }
+
+    #[test]
+    fn reference_cycles_are_rejected() {
+        let flow = chained_flow(&[
+            ("a", json!({ "x": "{{node.c.x}}" })),
+            ("b", json!({ "x": "{{node.a.x}} {{node.b.x}}" })),
+            ("c", json!({ "x": "{{node.b.x}}" })),
+        ]);
+        let err = flow.check_acyclic().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "flow fanout.flow has a node dependency cycle: a -> c -> b -> a"
+        );
+    }
+
+    /// `flow_id` made of builtin nodes routed in the given order: `(id, component, input)`.
+    fn builtin_flow(flow_id: &str, nodes: &[(&str, &str, Value)]) -> HostFlow {
+        let mut mapped = indexmap::IndexMap::default();
+        for (index, (id, component, input)) in nodes.iter().enumerate() {
+            let node_id = NodeId::from_str(id).unwrap();
+            let mut node = emit_node(&node_id);
+            node.component.id = component.parse().unwrap();
+            node.input.mapping = input.clone();
+            if let Some((next, _, _)) = nodes.get(index + 1) {
+                node.routing = Routing::Next {
+                    node_id: NodeId::from_str(next).unwrap(),
+                };
+            }
+            mapped.insert(node_id, node);
+        }
+        HostFlow::from(Flow {
+            schema_version: "1.0".into(),
+            id: FlowId::from_str(flow_id).unwrap(),
+            kind: FlowKind::Messaging,
+            entrypoints: BTreeMap::new(),
+            nodes: mapped,
+            metadata: Default::default(),
+        })
+    }
+
+    fn engine_with_flows(flows: Vec<HostFlow>) -> FlowEngine {
+        let engine = minimal_engine();
+        for flow in flows {
+            let key = FlowKey {
+                pack_id: "test-pack".to_string(),
+                flow_id: flow.id.clone(),
+            };
+            engine.flow_cache.write().insert(key, flow);
+        }
+        engine
+    }
+
+    fn flow_ctx<'a>(
+        flow_id: &'a str,
+        observer: Option<&'a dyn ExecutionObserver>,
+    ) -> FlowContext<'a> {
+        FlowContext {
+            tenant: "demo",
+            pack_id: "test-pack",
+            flow_id,
+            node_id: None,
+            tool: None,
+            action: None,
+            session_id: None,
+            provider_id: None,
+            retry_config: RetryConfig {
+                max_attempts: 1,
+                ..RetryConfig::default()
+            },
+            attempt: 1,
+            observer,
+            mocks: None,
+        }
+    }
+
+    fn greet_flow() -> HostFlow {
+        builtin_flow(
+            "greet.flow",
+            &[
+                (
+                    "hello",
+                    "emit.log",
+                    json!({ "text": "Hello {{entry.name}}" }),
+                ),
+                ("shout", "emit.log", json!({ "text": "{{prev.text}}!" })),
+            ],
+        )
+    }
+
+    #[test]
+    fn subflow_output_becomes_the_node_output() {
+        let engine = engine_with_flows(vec![
+            greet_flow(),
+            builtin_flow(
+                "main.flow",
+                &[
+                    (
+                        "call",
+                        "subflow",
+                        json!({ "flow_id": "greet.flow", "input": { "name": "{{entry.user}}" } }),
+                    ),
+                    (
+                        "reply",
+                        "emit.log",
+                        json!({ "text": "{{node.call[2].text}}" }),
+                    ),
+                ],
+            ),
+        ]);
+        let observer = CountingObserver::new();
+        let rt = Runtime::new().unwrap();
+        let result = rt
+            .block_on(engine.execute(
+                flow_ctx("main.flow", Some(&observer)),
+                json!({ "user": "Ada" }),
+            ))
+            .unwrap();
+        assert!(matches!(result.status, FlowStatus::Completed));
+        assert_eq!(
+            result.output,
+            json!([{ "text": "Hello Ada!" }, { "text": "Hello Ada!" }])
+        );
+
+        let ends = observer.ends.lock().unwrap();
+        assert_eq!(ends.len(), 4, "{ends:?}");
+        assert_eq!(
+            ends[2],
+            json!([
+                { "text": "Hello Ada" },
+                { "text": "Hello Ada!" },
+                { "text": "Hello Ada!" }
+            ])
+        );
+    }
+
+    #[test]
+    fn self_recursive_subflows_hit_the_depth_limit() {
+        let engine = engine_with_flows(vec![builtin_flow(
+            "loop.flow",
+            &[("again", "subflow", json!({ "flow_id": "loop.flow" }))],
+        )]);
+        let rt = Runtime::new().unwrap();
+        let err = rt
+            .block_on(engine.execute(flow_ctx("loop.flow", None), json!({})))
+            .unwrap_err();
+        let depth = err
+            .downcast_ref::<SubflowDepthExceeded>()
+            .unwrap_or_else(|| panic!("unexpected error: {err:#}"));
+        assert_eq!(depth.limit, MAX_SUBFLOW_DEPTH);
+        assert_eq!(depth.chain.len(), MAX_SUBFLOW_DEPTH + 2);
+        assert!(
+            depth
+                .to_string()
+                .ends_with("(test-pack:loop.flow calls itself)"),
+            "{depth}"
+        );
+    }
+
+    #[test]
+    fn subflow_waits_pause_the_calling_run() {
+        let engine = engine_with_flows(vec![
+            builtin_flow(
+                "ask.flow",
+                &[
+                    ("ask", "session.wait", json!({ "reason": "need-name" })),
+                    (
+                        "hello",
+                        "emit.log",
+                        json!({ "text": "Hello {{state.input.name}}" }),
+                    ),
+                ],
+            ),
+            builtin_flow(
+                "main.flow",
+                &[
+                    ("call", "subflow", json!({ "flow_id": "ask.flow" })),
+                    (
+                        "done",
+                        "emit.log",
+                        json!({ "greeting": "{{node.call[0].text}}" }),
+                    ),
+                ],
+            ),
+        ]);
+        let rt = Runtime::new().unwrap();
+        let paused = rt
+            .block_on(engine.execute(flow_ctx("main.flow", None), json!({})))
+            .unwrap();
+        let FlowStatus::Waiting(wait) = paused.status else {
+            panic!("the subflow wait should pause the caller");
+        };
+        assert_eq!(wait.reason.as_deref(), Some("need-name"));
+        assert_eq!(wait.snapshot.flow_id, "main.flow");
+        assert_eq!(wait.snapshot.next_node, "call/hello");
+
+        // The snapshot survives the session store round trip.
+        let snapshot: FlowSnapshot =
+            serde_json::from_value(serde_json::to_value(&wait.snapshot).unwrap()).unwrap();
+        let resumed = rt
+            .block_on(engine.resume(
+                flow_ctx("main.flow", None),
+                snapshot,
+                json!({ "name": "Ada" }),
+            ))
+            .unwrap();
+        assert!(matches!(resumed.status, FlowStatus::Completed));
+        assert_eq!(
+            resumed.output,
+            json!([{ "greeting": "Hello Ada" }, { "greeting": "Hello Ada" }])
+        );
+    }
+
+    fn failure(code: &str, retryable: bool) -> anyhow::Error {
+        NodeFailure {
+            component: "flaky.node".into(),
+            code: code.into(),
+            message: "try again".into(),
+            retryable,
+            backoff_ms: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn retry_policy_covers_retryable_errors_and_listed_codes() {
+        let policy = RetryConfig {
+            retry_on: vec!["rate_limited".into()],
+            ..RetryConfig::default()
+        };
+        assert!(policy.retries(&failure("busy", true)));
+        assert!(policy.retries(&failure("RATE_LIMITED", false)));
+        assert!(!policy.retries(&failure("bad_input", false)));
+        assert!(policy.retries(&anyhow!("transient: connection reset")));
+        assert!(!policy.retries(&anyhow!("invalid payload for flow.call node")));
+    }
+
+    #[test]
+    fn retry_delays_double_up_to_the_cap() {
+        let policy = RetryConfig::from(FlowRetryConfig {
+            base_delay_ms: 100,
+            max_delay_ms: 250,
+            jitter: false,
+            ..FlowRetryConfig::default()
+        });
+        let delays: Vec<u64> = (0..3).map(|retry| policy.delay_ms(retry, None)).collect();
+        assert_eq!(delays, [100, 200, 250]);
+        assert_eq!(policy.delay_ms(0, Some(120)), 120);
+        assert_eq!(policy.delay_ms(0, Some(5_000)), 250);
+    }
 }
 
-use tracing::Instrument;
+use tracing::{Instrument, Span};
 
 pub struct FlowContext<'a> {
     pub tenant: &'a str,
@@ -1870,25 +2803,187 @@ pub struct FlowContext<'a> {
     pub mocks: Option<&'a MockLayer>,
 }
 
-#[derive(Copy, Clone)]
+impl FlowContext<'_> {
+    fn with_attempt(&self, attempt: u32) -> Self {
+        FlowContext {
+            retry_config: self.retry_config.clone(),
+            attempt,
+            ..*self
+        }
+    }
+}
+
+/// How the engine retries a failing node.
+#[derive(Clone, Debug)]
 pub struct RetryConfig {
     pub max_attempts: u32,
     pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: bool,
+    /// Error codes retried even when the component did not mark the error retryable.
+    pub retry_on: Vec<String>,
 }
 
-fn should_retry(err: &anyhow::Error) -> bool {
-    let lower = err.to_string().to_lowercase();
-    lower.contains("transient")
-        || lower.contains("unavailable")
-        || lower.contains("internal")
-        || lower.contains("timeout")
+impl RetryConfig {
+    /// `self` with the fields `node` sets replaced.
+    fn for_node(&self, node: Option<&NodeRetry>) -> Self {
+        let Some(node) = node else {
+            return self.clone();
+        };
+        Self::from(FlowRetryConfig {
+            max_attempts: node.max_attempts.unwrap_or(self.max_attempts),
+            base_delay_ms: node.base_delay_ms.unwrap_or(self.base_delay_ms),
+            max_delay_ms: node.max_delay_ms.unwrap_or(self.max_delay_ms),
+            jitter: node.jitter.unwrap_or(self.jitter),
+            retry_on: node
+                .retry_on
+                .clone()
+                .unwrap_or_else(|| self.retry_on.clone()),
+            redelivery: None,
+        })
+    }
+
+    /// Whether another attempt may succeed. Errors a component reported are retried when
+    /// marked retryable or when their code is in `retry_on`; other errors when their
+    /// message says they are transient. Cancelled invocations are never retried.
+    fn retries(&self, err: &anyhow::Error) -> bool {
+        if cancel::current().is_some_and(|cancel| cancel.is_cancelled()) {
+            return false;
+        }
+        if let Some(failure) = err.downcast_ref::<NodeFailure>()
+            && (failure.retryable
+                || self
+                    .retry_on
+                    .iter()
+                    .any(|code| code.eq_ignore_ascii_case(&failure.code)))
+        {
+            return true;
+        }
+        let lower = err.to_string().to_lowercase();
+        lower.contains("transient")
+            || lower.contains("unavailable")
+            || lower.contains("internal")
+            || lower.contains("timeout")
+    }
+
+    /// Whether a run that failed with `err` may succeed when run again: its failing node
+    /// ran out of attempts or failed with an error this policy retries.
+    pub fn redelivers(&self, err: &anyhow::Error) -> bool {
+        match err.downcast_ref::<RetriesExhausted>() {
+            Some(exhausted) => self.retries(&exhausted.error),
+            None => self.retries(err),
+        }
+    }
+
+    /// Delay before retry number `retry + 1`. A delay the component asked for wins over
+    /// the exponential backoff; both are capped at `max_delay_ms`.
+    fn delay_ms(&self, retry: u32, hint_ms: Option<u64>) -> u64 {
+        let delay = match hint_ms {
+            Some(hint) => hint,
+            None if self.jitter => backoff_delay_ms(self.base_delay_ms, retry),
+            None => self.base_delay_ms.saturating_mul(1 << retry.min(10)),
+        };
+        delay.min(self.max_delay_ms)
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        FlowRetryConfig::default().into()
+    }
 }
 
 impl From<FlowRetryConfig> for RetryConfig {
     fn from(value: FlowRetryConfig) -> Self {
+        let base_delay_ms = value.base_delay_ms.max(50);
         Self {
             max_attempts: value.max_attempts.max(1),
-            base_delay_ms: value.base_delay_ms.max(50),
+            base_delay_ms,
+            max_delay_ms: value.max_delay_ms.max(base_delay_ms),
+            jitter: value.jitter,
+            retry_on: value.retry_on,
         }
     }
 }
+
+/// Fields a flow overrides in the retry policy of one node.
+#[derive(Clone, Debug, Default, Deserialize)]
+struct NodeRetry {
+    max_attempts: Option<u32>,
+    #[serde(alias = "initial_backoff_ms")]
+    base_delay_ms: Option<u64>,
+    #[serde(alias = "max_backoff_ms")]
+    max_delay_ms: Option<u64>,
+    jitter: Option<bool>,
+    retry_on: Option<Vec<String>>,
+}
+
+/// A component returned an error instead of output.
+#[derive(Debug)]
+pub struct NodeFailure {
+    pub component: String,
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+    pub backoff_ms: Option<u64>,
+}
+
+impl fmt::Display for NodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "component {} failed: {}: {}",
+            self.component, self.code, self.message
+        )
+    }
+}
+
+impl StdError for NodeFailure {}
+
+/// The last error of a node that failed on every attempt its retry policy allowed.
+#[derive(Debug)]
+pub struct RetriesExhausted {
+    pub node_id: String,
+    pub attempts: u32,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for RetriesExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "node {} failed after {} attempts: {:#}",
+            self.node_id, self.attempts, self.error
+        )
+    }
+}
+
+impl StdError for RetriesExhausted {}
+
+/// A chain of subflow calls went deeper than the limit, usually because flows call
+/// each other in a cycle.
+#[derive(Debug)]
+pub struct SubflowDepthExceeded {
+    pub limit: usize,
+    /// `pack:flow` of each flow in the chain, outermost first.
+    pub chain: Vec<String>,
+}
+
+impl fmt::Display for SubflowDepthExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "subflow calls nest deeper than {}: {}",
+            self.limit,
+            self.chain.join(" -> ")
+        )?;
+        if let Some((last, earlier)) = self.chain.split_last()
+            && earlier.contains(last)
+        {
+            write!(f, " ({last} calls itself)")?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for SubflowDepthExceeded {}
@@ -0,0 +1,198 @@
+//! JSON routes over [`RunnerApi`] for the routed tenant.
+//!
+//! `GET /api/flows` lists the tenant's flows, `GET /api/flows/{pack_id}/{flow_id}/schema`
+//! returns one flow's input schema and `POST /api/flows/{pack_id}/{flow_id}/run` runs a
+//! flow with a [`RunFlowRequest`] body. The body must name the flow in the path and the
+//! tenant the request was routed to.
+//!
+//! With `?stream=true` the run answers with server-sent events instead: `flow-started`,
+//! then `node-started`/`node-finished`/`egress` as the flow progresses (see
+//! [`FlowProgressEvent`]), and exactly one terminal `flow-finished` (carrying the
+//! [`RunFlowResult`]) or `flow-error`. Keep-alive comments are sent every
+//! [`SSE_KEEP_ALIVE`]. Dropping the stream before the terminal event cancels the run.
+//!
+//! With `?trace=true` the run keeps an execution trace (see [`crate::trace::run`]) even
+//! when the tenant does not trace every run; the result's `run_id` names it, and
+//! `GET /api/runs/{run_id}/trace` returns it until it expires.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::cancel::{self, InvocationCancel};
+use crate::engine::{
+    FlowSchema, FlowSummary, GResult, RunFlowRequest, RunFlowResult, RunnerApi, RunnerError,
+};
+use crate::routing::TenantRuntimeHandle;
+use crate::runner::progress::{self, FlowProgressEvent, ProgressObserver};
+use crate::runtime::TenantRuntime;
+use crate::trace::RunTrace;
+use crate::trace::run::{self, RunTraceRequest};
+
+/// Interval of the keep-alive comments on streamed runs.
+pub const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type ApiError = (StatusCode, Json<Value>);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RunQuery {
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub trace: bool,
+}
+
+pub async fn list_flows(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+) -> Result<Json<Vec<FlowSummary>>, ApiError> {
+    let tenant = runtime.config().tenant_ctx();
+    let mut flows = runtime.list_flows(&tenant).await.map_err(api_error)?;
+    flows.sort_by(|a, b| (&a.pack_id, &a.id).cmp(&(&b.pack_id, &b.id)));
+    Ok(Json(flows))
+}
+
+pub async fn flow_schema(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    Path((pack_id, flow_id)): Path<(String, String)>,
+) -> Result<Json<FlowSchema>, ApiError> {
+    let tenant = runtime.config().tenant_ctx();
+    runtime
+        .get_flow_schema(&tenant, &pack_id, &flow_id)
+        .await
+        .map(Json)
+        .map_err(api_error)
+}
+
+pub async fn run_flow(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    Path((pack_id, flow_id)): Path<(String, String)>,
+    Query(query): Query<RunQuery>,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let request: RunFlowRequest = serde_json::from_slice(&body).map_err(|err| {
+        error(
+            StatusCode::BAD_REQUEST,
+            format!("invalid run request: {err}"),
+        )
+    })?;
+    if request.pack_id != pack_id || request.flow_id != flow_id {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "request names flow `{}/{}` but the path names `{pack_id}/{flow_id}`",
+                request.pack_id, request.flow_id
+            ),
+        ));
+    }
+    let trace = query.trace.then(RunTraceRequest::generate);
+    if query.stream {
+        return Ok(stream_run(runtime, request, trace).into_response());
+    }
+    let result = match trace {
+        Some(trace) => run::scope(trace, runtime.run_flow(request)).await,
+        None => runtime.run_flow(request).await,
+    };
+    result
+        .map(|result| Json(result).into_response())
+        .map_err(api_error)
+}
+
+pub async fn run_trace(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    Path(run_id): Path<String>,
+) -> Result<Json<RunTrace>, ApiError> {
+    let tenant = runtime.config().tenant_ctx();
+    match runtime.state_machine().run_trace(&tenant, &run_id).await {
+        Ok(Some(trace)) => Ok(Json(trace)),
+        Ok(None) => Err(error(
+            StatusCode::NOT_FOUND,
+            format!("no trace stored for run `{run_id}`"),
+        )),
+        Err(err) => Err(api_error(err)),
+    }
+}
+
+fn stream_run(
+    runtime: Arc<TenantRuntime>,
+    request: RunFlowRequest,
+    trace: Option<Arc<RunTraceRequest>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let started = FlowProgressEvent::FlowStarted {
+        pack_id: request.pack_id.clone(),
+        flow_id: request.flow_id.clone(),
+    };
+    let (observer, events) = ProgressObserver::channel();
+    let cancel = InvocationCancel::new();
+    let disconnect = cancel.disconnect_guard();
+    let run = tokio::spawn(cancel::scope(
+        cancel,
+        progress::scope(observer, async move {
+            match trace {
+                Some(trace) => run::scope(trace, runtime.run_flow(request)).await,
+                None => runtime.run_flow(request).await,
+            }
+        }),
+    ));
+    let state = Some((events, run, disconnect));
+    let progress = futures::stream::unfold(state, |state| async move {
+        let (mut events, run, disconnect) = state?;
+        if let Some(event) = events.recv().await {
+            return Some((event, Some((events, run, disconnect))));
+        }
+        let outcome = run.await.unwrap_or_else(|err| {
+            Err(RunnerError::AdapterCall {
+                reason: format!("flow run task failed: {err}"),
+            })
+        });
+        disconnect.disarm();
+        Some((terminal_event(outcome), None))
+    });
+    let frames = futures::stream::once(async move { started })
+        .chain(progress)
+        .map(|event| Ok(sse_event(&event)));
+    Sse::new(frames).keep_alive(KeepAlive::new().interval(SSE_KEEP_ALIVE))
+}
+
+fn terminal_event(outcome: GResult<RunFlowResult>) -> FlowProgressEvent {
+    match outcome {
+        Ok(result) => FlowProgressEvent::FlowFinished { result },
+        Err(err) => FlowProgressEvent::FlowError {
+            status: error_status(&err).as_u16(),
+            error: err.to_string(),
+        },
+    }
+}
+
+fn sse_event(event: &FlowProgressEvent) -> Event {
+    let data = serde_json::to_string(event).unwrap_or_else(|err| {
+        json!({ "event": "flow-error", "status": 500, "error": err.to_string() }).to_string()
+    });
+    Event::default().event(event.name()).data(data)
+}
+
+fn api_error(err: RunnerError) -> ApiError {
+    error(error_status(&err), err.to_string())
+}
+
+fn error_status(err: &RunnerError) -> StatusCode {
+    match err {
+        RunnerError::FlowNotFound { .. } => StatusCode::NOT_FOUND,
+        RunnerError::Policy { .. } => StatusCode::FORBIDDEN,
+        RunnerError::Serialization { .. } => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error(status: StatusCode, message: String) -> ApiError {
+    (status, Json(json!({ "error": message })))
+}
@@ -1,7 +1,15 @@
 use std::env;
+use std::path::{Path, PathBuf};
 
+use anyhow::{Context, Result};
 use greentic_i18n as shared;
-pub use greentic_i18n::I18nText;
+pub use greentic_i18n::{
+    Catalog, CatalogLint, I18nText, Message, PluralCategory, known_message_keys, lint_catalogs,
+    register_catalog,
+};
+
+/// Directory of `{locale}.json` message catalogs loaded at startup.
+pub const CATALOG_DIR_ENV: &str = "GREENTIC_I18N_CATALOG_DIR";
 
 pub fn select_locale(explicit: Option<&str>) -> String {
     let cli_locale = env::var("GREENTIC_LOCALE_CLI").ok();
@@ -19,10 +27,42 @@ pub fn resolve_message(key: &str, fallback: &str, locale: &str) -> String {
     shared::resolve_message(key, fallback, locale)
 }
 
+pub fn resolve_message_with_args(
+    key: &str,
+    fallback: &str,
+    args: &[(&str, &str)],
+    locale: &str,
+) -> String {
+    shared::resolve_message_with_args(key, fallback, args, locale)
+}
+
+pub fn resolve_plural(key: &str, count: u64, args: &[(&str, &str)], locale: &str) -> String {
+    shared::resolve_plural(key, count, args, locale)
+}
+
 pub fn resolve_text(text: &I18nText, locale: &str) -> String {
     shared::resolve_text(text, locale)
 }
 
+/// Register the catalogs in `GREENTIC_I18N_CATALOG_DIR`, if set.
+pub fn load_catalogs_from_env() -> Result<()> {
+    let Some(dir) = env::var_os(CATALOG_DIR_ENV)
+        .map(PathBuf::from)
+        .filter(|dir| !dir.as_os_str().is_empty())
+    else {
+        return Ok(());
+    };
+    let locales = load_catalogs(&dir)?;
+    tracing::info!(dir = %dir.display(), locales = ?locales, "loaded message catalogs");
+    Ok(())
+}
+
+/// Register the `{locale}.json` catalogs in `dir`, returning their locales.
+pub fn load_catalogs(dir: &Path) -> Result<Vec<String>> {
+    shared::load_catalog_dir(dir)
+        .with_context(|| format!("failed to load message catalogs from {}", dir.display()))
+}
+
 fn system_locale() -> Option<String> {
     for key in ["LC_ALL", "LANG", "LC_MESSAGES"] {
         if let Ok(value) = env::var(key) {
@@ -51,9 +91,9 @@ mod tests {
 
     #[test]
     fn resolve_text_uses_key_and_fallback() {
-        let text = I18nText::new("runner.operator.op_not_found", "fallback");
+        let text = I18nText::new("runner.operator.provider_not_found", "fallback");
         let message = resolve_text(&text, "en");
-        assert_eq!(message, "operation not found");
+        assert_eq!(message, "provider not found");
     }
 
     #[test]
@@ -0,0 +1,204 @@
+//! Replay of operator responses by idempotency key.
+//!
+//! Webhook-driven callers retry operator invokes they think were lost. A request that
+//! carries an `idempotency_key` claims `(tenant, provider, op, key)` before its
+//! component runs; a successful response is then kept in the tenant's state store for
+//! `operator.idempotency_ttl_secs`, together with a fingerprint of the request payload,
+//! and returned to later requests with the same key and payload without invoking the
+//! component again. A later request with the same key but another payload is a
+//! [`ReplayLookup::Conflict`]. Failed invocations are not kept, so a retry runs them
+//! again.
+//!
+//! Concurrent duplicates on one host wait for the request that claimed the key and
+//! replay its response. Hosts sharing a store only see each other's stored responses,
+//! so duplicates racing on different hosts may both run.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use greentic_types::TenantCtx;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use tokio::sync::OwnedMutexGuard;
+
+use crate::engine::error::GResult;
+use crate::engine::host::{SessionKey, StateHost};
+
+const REPLAY_PACK: &str = "_operator";
+
+/// The stored responses of one tenant and the requests currently claiming keys; shared
+/// by every runtime built for the tenant, like its audit log.
+pub struct OperatorReplay {
+    state: Arc<dyn StateHost>,
+    tenant: TenantCtx,
+    ttl: Option<Duration>,
+    flights: Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>,
+}
+
+/// Result of [`OperatorReplay::claim`].
+pub enum ReplayLookup {
+    /// An earlier request with the key succeeded; answer with its CBOR output.
+    Stored(Vec<u8>),
+    /// An earlier request used the key for another payload.
+    Conflict,
+    /// This request runs; record its output under the claim.
+    Claimed(ReplayClaim),
+}
+
+/// A key claimed by a running request. Dropping it lets waiting duplicates go ahead.
+pub struct ReplayClaim {
+    key: SessionKey,
+    fingerprint: String,
+    _flight: OwnedMutexGuard<()>,
+}
+
+impl OperatorReplay {
+    /// `ttl` of `None` turns replay off.
+    pub fn new(tenant: TenantCtx, state: Arc<dyn StateHost>, ttl: Option<Duration>) -> Self {
+        Self {
+            state,
+            tenant,
+            ttl,
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    /// Claim `key` for `provider` and `op`, after any request holding it has finished.
+    /// `fingerprint` identifies the request payload. With `bypass` the stored response
+    /// is ignored and the request always runs.
+    pub async fn claim(
+        &self,
+        provider: &str,
+        op: &str,
+        key: &str,
+        fingerprint: &str,
+        bypass: bool,
+    ) -> GResult<ReplayLookup> {
+        let key = SessionKey::new(
+            &self.tenant,
+            REPLAY_PACK,
+            &format!("{provider}/{op}"),
+            Some(key.to_string()),
+        );
+        let flight = self.flight(&key).lock_owned().await;
+        if !bypass
+            && let Some(record) = self.state.get_json(&key).await?
+            && let Some((stored_fingerprint, output)) = decode_record(&record)
+        {
+            if stored_fingerprint != fingerprint {
+                return Ok(ReplayLookup::Conflict);
+            }
+            return Ok(ReplayLookup::Stored(output));
+        }
+        Ok(ReplayLookup::Claimed(ReplayClaim {
+            key,
+            fingerprint: fingerprint.to_string(),
+            _flight: flight,
+        }))
+    }
+
+    /// Keep the CBOR `output` of a successful request for replays, then release the
+    /// claim.
+    pub async fn record(&self, claim: ReplayClaim, output: &[u8]) -> GResult<()> {
+        let Some(ttl) = self.ttl else {
+            return Ok(());
+        };
+        let record = json!({
+            "fingerprint": claim.fingerprint,
+            "output": STANDARD.encode(output),
+        });
+        self.state.set_json_ttl(&claim.key, record, ttl).await
+    }
+
+    fn flight(&self, key: &SessionKey) -> Arc<tokio::sync::Mutex<()>> {
+        let id = format!(
+            "{}/{}/{}",
+            key.tenant_key,
+            key.flow_id,
+            key.session_hint.as_deref().unwrap_or_default()
+        );
+        let mut flights = self.flights.lock();
+        if let Some(flight) = flights.get(&id).and_then(Weak::upgrade) {
+            return flight;
+        }
+        flights.retain(|_, flight| flight.strong_count() > 0);
+        let flight = Arc::new(tokio::sync::Mutex::new(()));
+        flights.insert(id, Arc::downgrade(&flight));
+        flight
+    }
+}
+
+/// Fingerprint and output of a stored record.
+fn decode_record(record: &Value) -> Option<(&str, Vec<u8>)> {
+    let fingerprint = record["fingerprint"].as_str()?;
+    let output = STANDARD.decode(record["output"].as_str()?).ok()?;
+    Some((fingerprint, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{new_state_store, state_host_from};
+    use greentic_types::{EnvId, TenantId};
+
+    fn replay() -> OperatorReplay {
+        let tenant = TenantCtx::new(EnvId::new("local").unwrap(), TenantId::new("demo").unwrap());
+        let state: Arc<dyn StateHost> = state_host_from(new_state_store());
+        OperatorReplay::new(tenant, state, Some(Duration::from_secs(60)))
+    }
+
+    #[tokio::test]
+    async fn recorded_output_is_replayed_unless_bypassed() {
+        let replay = replay();
+        let ReplayLookup::Claimed(claim) = replay.claim("p", "op", "k", "f", false).await.unwrap()
+        else {
+            panic!("first request must claim the key");
+        };
+        replay.record(claim, b"out").await.unwrap();
+
+        match replay.claim("p", "op", "k", "f", false).await.unwrap() {
+            ReplayLookup::Stored(output) => assert_eq!(output, b"out"),
+            _ => panic!("expected the stored output"),
+        }
+        assert!(matches!(
+            replay.claim("p", "other", "k", "f", false).await.unwrap(),
+            ReplayLookup::Claimed(_)
+        ));
+        assert!(matches!(
+            replay.claim("p", "op", "k", "g", false).await.unwrap(),
+            ReplayLookup::Conflict
+        ));
+        assert!(matches!(
+            replay.claim("p", "op", "k", "f", true).await.unwrap(),
+            ReplayLookup::Claimed(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn duplicates_wait_for_the_claiming_request() {
+        let replay = Arc::new(replay());
+        let ReplayLookup::Claimed(claim) = replay.claim("p", "op", "k", "f", false).await.unwrap()
+        else {
+            panic!("first request must claim the key");
+        };
+        let waiter = tokio::spawn({
+            let replay = Arc::clone(&replay);
+            async move { replay.claim("p", "op", "k", "f", false).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        replay.record(claim, b"once").await.unwrap();
+        match waiter.await.unwrap().unwrap() {
+            ReplayLookup::Stored(output) => assert_eq!(output, b"once"),
+            _ => panic!("duplicate must replay the first output"),
+        }
+    }
+}
@@ -1,11 +1,16 @@
+use axum::Json;
 use axum::body::Body;
 use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::response::{IntoResponse, Response};
 use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use lru::LruCache;
 use parking_lot::Mutex;
 use serde_json::Value;
 
+use crate::engine::rate_limit::rate_limited;
+
 pub fn mark_processed(cache: &Mutex<LruCache<String, Value>>, key: &str) -> bool {
     let mut cache = cache.lock();
     if cache.get(key).is_some() {
@@ -25,3 +30,27 @@ pub async fn collect_body(body: Body) -> Result<Bytes, StatusCode> {
     }
     Ok(data.freeze())
 }
+
+/// Status for a failed flow run: 429 when the ingress rate limit refused the envelope, so
+/// the provider redelivers it later, `fallback` otherwise.
+pub fn flow_failure_status(err: &anyhow::Error, fallback: StatusCode) -> StatusCode {
+    if rate_limited(err).is_some() {
+        StatusCode::TOO_MANY_REQUESTS
+    } else {
+        fallback
+    }
+}
+
+/// 429 with `Retry-After` and the `rate_limited` outcome, when the ingress rate limit
+/// refused the envelope.
+pub fn rate_limited_response(err: &anyhow::Error) -> Option<Response> {
+    let limited = rate_limited(err)?;
+    Some(
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, limited.retry_after_secs().to_string())],
+            Json(limited.outcome()),
+        )
+            .into_response(),
+    )
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+/// Families of HTTP routes that can be enabled per listener.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RouteGroup {
+    /// Channel adapters (messaging, webchat, slack, webhook, ...).
+    Ingress,
+    /// `/operator/op/invoke` and the flow API under `/api/flows`.
+    Operator,
+    /// `/admin/*`.
+    Admin,
+    /// `/metrics`.
+    Metrics,
+    /// `/healthz` and `/readyz`.
+    Health,
+}
+
+impl RouteGroup {
+    pub const ALL: [RouteGroup; 5] = [
+        RouteGroup::Ingress,
+        RouteGroup::Operator,
+        RouteGroup::Admin,
+        RouteGroup::Metrics,
+        RouteGroup::Health,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteGroup::Ingress => "ingress",
+            RouteGroup::Operator => "operator",
+            RouteGroup::Admin => "admin",
+            RouteGroup::Metrics => "metrics",
+            RouteGroup::Health => "health",
+        }
+    }
+}
+
+impl FromStr for RouteGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ingress" => Ok(RouteGroup::Ingress),
+            "operator" => Ok(RouteGroup::Operator),
+            "admin" => Ok(RouteGroup::Admin),
+            "metrics" => Ok(RouteGroup::Metrics),
+            "health" => Ok(RouteGroup::Health),
+            other => bail!("unknown route group `{other}`"),
+        }
+    }
+}
+
+/// Where a listener accepts connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    /// Unix domain socket path; peers are treated as local for admin auth.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{addr}"),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// One HTTP listener and the route groups it serves.
+#[derive(Clone, Debug)]
+pub struct ListenerConfig {
+    pub addr: ListenAddr,
+    pub groups: BTreeSet<RouteGroup>,
+}
+
+impl ListenerConfig {
+    pub fn new(addr: ListenAddr, groups: impl IntoIterator<Item = RouteGroup>) -> Self {
+        Self {
+            addr,
+            groups: groups.into_iter().collect(),
+        }
+    }
+
+    /// Listener on `0.0.0.0:port` serving every route group (the single-listener default).
+    pub fn all_on_port(port: u16) -> Self {
+        Self::new(
+            ListenAddr::Tcp(SocketAddr::from(([0, 0, 0, 0], port))),
+            RouteGroup::ALL,
+        )
+    }
+}
+
+impl FromStr for ListenerConfig {
+    type Err = anyhow::Error;
+
+    /// Parses `ADDR[=group,group]`, where `ADDR` is `host:port` or `unix:/path`.
+    /// Omitting the groups enables all of them.
+    fn from_str(spec: &str) -> Result<Self> {
+        let (addr, groups) = match spec.split_once('=') {
+            Some((addr, groups)) => (addr.trim(), Some(groups)),
+            None => (spec.trim(), None),
+        };
+        let addr = match addr.strip_prefix("unix:") {
+            Some(path) if !path.is_empty() => ListenAddr::Unix(PathBuf::from(path)),
+            Some(_) => return Err(anyhow!("listener `{spec}` is missing a socket path")),
+            None => ListenAddr::Tcp(
+                addr.parse()
+                    .with_context(|| format!("invalid listener address in `{spec}`"))?,
+            ),
+        };
+        let groups = match groups {
+            Some(groups) => groups
+                .split(',')
+                .filter(|group| !group.trim().is_empty())
+                .map(RouteGroup::from_str)
+                .collect::<Result<BTreeSet<_>>>()?,
+            None => RouteGroup::ALL.into_iter().collect(),
+        };
+        if groups.is_empty() {
+            bail!("listener `{spec}` enables no route groups");
+        }
+        Ok(Self { addr, groups })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tcp_listener_with_groups() {
+        let listener: ListenerConfig = "127.0.0.1:9090=admin,metrics".parse().unwrap();
+        assert_eq!(
+            listener.addr,
+            ListenAddr::Tcp("127.0.0.1:9090".parse().unwrap())
+        );
+        assert_eq!(
+            listener.groups,
+            BTreeSet::from([RouteGroup::Admin, RouteGroup::Metrics])
+        );
+    }
+
+    #[test]
+    fn parses_unix_listener_with_all_groups() {
+        let listener: ListenerConfig = "unix:/run/greentic.sock".parse().unwrap();
+        assert_eq!(
+            listener.addr,
+            ListenAddr::Unix(PathBuf::from("/run/greentic.sock"))
+        );
+        assert_eq!(listener.groups.len(), RouteGroup::ALL.len());
+    }
+
+    #[test]
+    fn rejects_unknown_groups_and_empty_sets() {
+        assert!(
+            "127.0.0.1:9090=admin,bogus"
+                .parse::<ListenerConfig>()
+                .is_err()
+        );
+        assert!("127.0.0.1:9090=".parse::<ListenerConfig>().is_err());
+    }
+}
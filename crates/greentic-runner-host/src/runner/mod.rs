@@ -10,30 +10,39 @@ pub mod contract_cache;
 pub mod contract_introspection;
 pub mod engine;
 pub mod flow_adapter;
+pub mod flow_api;
 pub mod i18n;
+pub mod idempotency;
 pub mod ingress_util;
 pub mod invocation;
+pub mod listener;
 pub mod mocks;
 pub mod operator;
+pub mod progress;
+pub mod rate_limit;
+pub mod redelivery;
 pub mod schema_validator;
 pub mod templating;
 
+use std::collections::BTreeSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use axum::routing::{any, get, post};
-use axum::{Router, serve};
+use axum::{Router, middleware, serve};
 use tokio::net::TcpListener;
+use tokio::task::JoinSet;
 
-use crate::http::{self, admin, auth::AdminAuth, health::HealthState};
+use crate::http::{self, admin, auth::AdminAuth, health::HealthState, metrics::HttpMetrics};
 use crate::routing::TenantRouting;
 use crate::runtime::ActivePacks;
 use crate::watcher::PackReloadHandle;
 
+pub use listener::{ListenAddr, ListenerConfig, RouteGroup};
+
 pub struct HostServer {
-    addr: SocketAddr,
-    router: Router,
+    listeners: Vec<(ListenerConfig, Router)>,
     _state: ServerState,
 }
 
@@ -46,15 +55,202 @@ impl HostServer {
         reload: Option<PackReloadHandle>,
         admin: AdminAuth,
     ) -> Result<Self> {
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        Self::with_listeners(
+            vec![ListenerConfig::all_on_port(port)],
+            active,
+            routing,
+            health,
+            reload,
+            admin,
+        )
+    }
+
+    /// Build one router per listener; all listeners share the same tenant runtimes.
+    pub fn with_listeners(
+        listeners: Vec<ListenerConfig>,
+        active: Arc<ActivePacks>,
+        routing: TenantRouting,
+        health: Arc<HealthState>,
+        reload: Option<PackReloadHandle>,
+        admin: AdminAuth,
+    ) -> Result<Self> {
+        if listeners.is_empty() {
+            bail!("at least one listener is required");
+        }
         let state = ServerState {
             active,
             routing,
             health,
             reload,
             admin,
+            http_metrics: Arc::new(HttpMetrics::new()),
         };
-        let router = Router::new()
+        let listeners = listeners
+            .into_iter()
+            .map(|listener| {
+                if listener.groups.is_empty() {
+                    bail!("listener {} enables no route groups", listener.addr);
+                }
+                let router = build_router(&listener.groups, &state);
+                Ok((listener, router))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            listeners,
+            _state: state,
+        })
+    }
+
+    /// Bind every listener up front; any failure aborts before serving starts.
+    pub async fn bind(self) -> Result<BoundHostServer> {
+        let mut bound = Vec::with_capacity(self.listeners.len());
+        for (config, router) in self.listeners {
+            let socket = BoundSocket::bind(&config.addr)
+                .await
+                .with_context(|| format!("failed to bind listener {}", config.addr))?;
+            bound.push(BoundListener {
+                config,
+                router,
+                socket,
+            });
+        }
+        Ok(BoundHostServer { listeners: bound })
+    }
+
+    pub async fn serve(self) -> Result<()> {
+        self.bind().await?.serve().await
+    }
+}
+
+/// Host server whose listeners are bound but not yet accepting requests.
+pub struct BoundHostServer {
+    listeners: Vec<BoundListener>,
+}
+
+impl BoundHostServer {
+    /// Actual bound addresses, in listener order (resolves port `0`).
+    pub fn local_addrs(&self) -> Result<Vec<ListenAddr>> {
+        self.listeners
+            .iter()
+            .map(|listener| listener.socket.local_addr())
+            .collect()
+    }
+
+    pub async fn serve(self) -> Result<()> {
+        let mut tasks = JoinSet::new();
+        for listener in self.listeners {
+            tasks.spawn(listener.serve());
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.context("listener task failed")??;
+        }
+        Ok(())
+    }
+}
+
+struct BoundListener {
+    config: ListenerConfig,
+    router: Router,
+    socket: BoundSocket,
+}
+
+impl BoundListener {
+    async fn serve(self) -> Result<()> {
+        tracing::info!(
+            addr = %self.config.addr,
+            groups = ?self.config.groups,
+            "starting host server"
+        );
+        match self.socket {
+            BoundSocket::Tcp(listener) => {
+                serve(
+                    listener,
+                    self.router
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await?;
+            }
+            #[cfg(unix)]
+            BoundSocket::Unix(listener, _) => {
+                // Unix socket peers are local; present them as loopback to admin auth.
+                // The socket is owner-only (see `BoundSocket::bind`), so this admits the
+                // host's own user only.
+                let loopback = SocketAddr::from(([127, 0, 0, 1], 0));
+                let router = self
+                    .router
+                    .layer(axum::Extension(axum::extract::ConnectInfo(loopback)));
+                serve(listener, router.into_make_service()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum BoundSocket {
+    Tcp(TcpListener),
+    /// The listener's filesystem path; its own address names the staging directory
+    /// it was bound in.
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, std::path::PathBuf),
+}
+
+impl BoundSocket {
+    async fn bind(addr: &ListenAddr) -> Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+                if let Ok(meta) = std::fs::symlink_metadata(path) {
+                    if !meta.file_type().is_socket() {
+                        bail!("{} exists and is not a socket", path.display());
+                    }
+                    std::fs::remove_file(path).with_context(|| {
+                        format!("failed to remove stale socket {}", path.display())
+                    })?;
+                }
+                // Peers pass admin auth as loopback; keep other users off the socket. It is
+                // bound inside an owner-only directory and restricted before it is moved
+                // into place, so it is never reachable with looser permissions.
+                let parent = path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .unwrap_or(std::path::Path::new("."));
+                let staging = tempfile::Builder::new()
+                    .prefix(".runner-sock")
+                    .permissions(std::fs::Permissions::from_mode(0o700))
+                    .tempdir_in(parent)
+                    .with_context(|| format!("failed to stage socket {}", path.display()))?;
+                let staged = staging.path().join("sock");
+                let listener = tokio::net::UnixListener::bind(&staged)?;
+                std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))
+                    .with_context(|| format!("failed to restrict socket {}", path.display()))?;
+                std::fs::rename(&staged, path)
+                    .with_context(|| format!("failed to move socket to {}", path.display()))?;
+                Ok(Self::Unix(listener, path.clone()))
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => {
+                bail!("unix socket listeners are not supported on this platform")
+            }
+        }
+    }
+
+    fn local_addr(&self) -> Result<ListenAddr> {
+        match self {
+            Self::Tcp(listener) => Ok(ListenAddr::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            Self::Unix(_, path) => Ok(ListenAddr::Unix(path.clone())),
+        }
+    }
+}
+
+fn build_router(groups: &BTreeSet<RouteGroup>, state: &ServerState) -> Router {
+    // Tenant-facing routes share the per-tenant in-flight limit.
+    let mut tenant_routes = Router::new();
+    if groups.contains(&RouteGroup::Ingress) {
+        tenant_routes = tenant_routes
             .route(
                 "/messaging/telegram/webhook",
                 post(adapt_messaging::telegram_webhook),
@@ -68,30 +264,66 @@ impl HostServer {
                 "/whatsapp/webhook",
                 get(adapt_whatsapp::verify).post(adapt_whatsapp::webhook),
             )
-            .route("/webhook/{flow_id}", any(adapt_webhook::dispatch))
+            .route("/webhook/{flow_id}", any(adapt_webhook::dispatch));
+    }
+    if groups.contains(&RouteGroup::Operator) {
+        tenant_routes = tenant_routes
             .route("/operator/op/invoke", post(operator::invoke))
+            .route("/operator/op/invoke/stream", post(operator::invoke_stream))
+            .route("/operator/op/invoke-stream", post(operator::invoke_stream))
+            .route("/operator/op/describe", post(operator::describe))
+            .route("/operator/ops", get(operator::list_ops))
+            .route("/api/flows", get(flow_api::list_flows))
+            .route(
+                "/api/flows/{pack_id}/{flow_id}/schema",
+                get(flow_api::flow_schema),
+            )
+            .route(
+                "/api/flows/{pack_id}/{flow_id}/run",
+                post(flow_api::run_flow),
+            )
+            .route("/api/runs/{run_id}/trace", get(flow_api::run_trace));
+    }
+    let mut router = Router::new();
+    if groups.contains(&RouteGroup::Ingress) || groups.contains(&RouteGroup::Operator) {
+        router = router.merge(tenant_routes.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            http::limits::limit_in_flight,
+        )));
+    }
+    if groups.contains(&RouteGroup::Health) {
+        router = router
             .route("/healthz", get(http::health::handler))
+            .route("/readyz", get(http::health::ready_handler));
+    }
+    if groups.contains(&RouteGroup::Metrics) {
+        router = router
+            .route("/metrics", get(http::metrics::handler))
+            .route("/metrics/cache", get(http::metrics::tenant_cache));
+    }
+    if groups.contains(&RouteGroup::Admin) {
+        router = router
             .route("/admin/packs/status", get(admin::status))
             .route("/admin/packs/reload", post(admin::reload))
-            .with_state(state.clone());
-        Ok(Self {
-            addr,
-            router,
-            _state: state,
-        })
-    }
-
-    pub async fn serve(self) -> Result<()> {
-        tracing::info!(addr = %self.addr, "starting host server");
-        let listener = TcpListener::bind(self.addr).await?;
-        serve(
-            listener,
-            self.router
-                .into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await?;
-        Ok(())
+            .route("/admin/packs/{tenant}/reload", post(admin::reload_pack))
+            .route(
+                "/admin/secrets/{tenant}/invalidate",
+                post(admin::invalidate_secrets),
+            )
+            .route("/admin/secrets/rotated", post(admin::secret_rotated))
+            .route(
+                "/admin/invocations/{tenant}/{invocation_id}/abort",
+                post(admin::abort_invocation),
+            )
+            .route("/admin/tenants", get(admin::tenants))
+            .route("/admin/tenants/{tenant}/stats", get(admin::tenant_stats));
     }
+    router
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            http::metrics::track_requests,
+        ))
+        .with_state(state.clone())
 }
 
 #[derive(Clone)]
@@ -101,4 +333,5 @@ pub struct ServerState {
     pub health: Arc<HealthState>,
     pub reload: Option<PackReloadHandle>,
     pub admin: AdminAuth,
+    pub http_metrics: Arc<HttpMetrics>,
 }
@@ -1,30 +1,59 @@
 use axum::{
     body::{Body, to_bytes},
-    http::{HeaderMap, Response, StatusCode},
+    http::{
+        HeaderMap, HeaderValue, Response, StatusCode,
+        header::{ACCEPT, RETRY_AFTER},
+    },
 };
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_cbor;
 use serde_json::{Map, Value, json};
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use tracing::{Level, span};
+use tracing::{Instrument, Level, Span, field, span};
 
-use crate::component_api::node::{ExecCtx as ComponentExecCtx, TenantCtx as ComponentTenantCtx};
-use crate::operator_registry::OperatorResolveError;
+use crate::audit::SecretRequester;
+use crate::cancel::{self, CancelReason, Cancelled, InvocationCancel};
+use crate::component_api::node::{
+    ExecCtx as ComponentExecCtx, Impersonation, TenantCtx as ComponentTenantCtx,
+};
+use crate::component_api::tenant_attributes;
+use crate::operator_registry::{OperatorBinding, OperatorResolveError};
+use crate::pack::PackRuntime;
 use crate::provider::ProviderBinding;
+use crate::redact::{self, Redactor};
 use crate::routing::TenantRuntimeHandle;
 use crate::runner::contract_cache::ContractSnapshot;
 use crate::runner::contract_introspection::introspect_component_contract;
-use crate::runner::i18n::{I18nText, resolve_text, select_locale};
-use crate::runner::schema_validator::validate_json_instance;
+use crate::runner::i18n::{I18nText, resolve_plural, resolve_text, select_locale};
+use crate::runner::idempotency::{ReplayClaim, ReplayLookup};
+use crate::runner::rate_limit::OperatorRateLimited;
+use crate::runner::schema_validator::{SchemaValidationIssue, validate_json_instance};
 use crate::runtime::TenantRuntime;
+use crate::stdio::{self, CapturedStdio, StdioSink};
+use crate::storage::quota::StateQuotaExceeded;
+use crate::stream::{
+    ChannelStreamObserver, DEFAULT_STREAM_CHANNEL_CAPACITY, StreamEvent, StreamObserver,
+    StreamUnsupported,
+};
+use crate::trace::sampling;
+use crate::trap::TrapDetails;
+use crate::wasm_engine::PoolExhausted;
+use crate::wasm_limits::{DeadlineExceeded, DeadlineKind, ResourceExhausted};
 
 const CONTENT_TYPE_CBOR: &str = "application/cbor";
+const CONTENT_TYPE_CBOR_SEQ: &str = "application/cbor-seq";
 const FLAG_SKIP_OUTPUT_VALIDATE: &str = "skip-output-validate";
-const FLAG_PERMISSIVE_SCHEMA: &str = "permissive-schema";
+pub(crate) const FLAG_PERMISSIVE_SCHEMA: &str = "permissive-schema";
+const FLAG_INCLUDE_LOGS: &str = "include-logs";
+const FLAG_NO_IDEMPOTENT_REPLAY: &str = "no-idempotent-replay";
 
 /// Operator-facing invocation payload (CBOR envelope).
 #[derive(Debug, Deserialize)]
@@ -42,6 +71,10 @@ pub struct OperatorRequest {
     pub trace_id: Option<String>,
     #[serde(default)]
     pub correlation_id: Option<String>,
+    /// Replays the stored response of an earlier request with the same key and payload
+    /// instead of invoking again.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
     #[serde(default)]
     pub timeout: Option<u64>,
     #[serde(default)]
@@ -52,6 +85,13 @@ pub struct OperatorRequest {
     pub schema_hash: Option<String>,
     #[serde(default)]
     pub locale: Option<String>,
+    /// Tenant attributes (region, plan tier) forwarded to v0.5 components; attributes
+    /// from the tenant bindings take precedence.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Invoke on the tenant's behalf; requires `operator.allow_impersonation`.
+    #[serde(default)]
+    pub impersonation: Option<Impersonation>,
     pub payload: OperatorPayload,
 }
 
@@ -85,6 +125,12 @@ pub struct OperatorResponse {
     pub cbor_output: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<OperatorError>,
+    /// Component stdout/stderr, returned when the request sets the `include-logs` flag.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<CapturedStdio>,
+    /// Problems with the request that did not stop it, e.g. a `timeout` cut to the host cap.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Diagnostic>,
 }
 
 impl OperatorResponse {
@@ -93,6 +139,8 @@ impl OperatorResponse {
             status: OperatorStatus::Ok,
             cbor_output: Some(output),
             error: None,
+            logs: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -104,7 +152,10 @@ impl OperatorResponse {
                 code,
                 message: message.into(),
                 details_cbor: None,
+                retry_after: None,
             }),
+            logs: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -121,13 +172,40 @@ impl OperatorResponse {
                 code,
                 message: message.into(),
                 details_cbor,
+                retry_after: None,
             }),
+            logs: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Scrub resolved secret values and configured patterns from the error message,
+    /// diagnostics and logs. The output is the component's own answer and is left as is.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        if let Some(error) = &mut self.error {
+            redactor.scrub_string(&mut error.message);
+            if let Some(details) = &mut error.details_cbor
+                && let Ok(mut value) = serde_cbor::from_slice::<serde_cbor::Value>(details)
+            {
+                redactor.scrub_cbor(&mut value);
+                if let Ok(scrubbed) = serde_cbor::to_vec(&value) {
+                    *details = scrubbed;
+                }
+            }
+        }
+        if let Some(logs) = self.logs.take() {
+            self.logs = Some(redactor.scrub_stdio(logs));
         }
     }
 
     pub fn to_cbor(&self) -> Result<Vec<u8>, serde_cbor::Error> {
         serde_cbor::ser::to_vec_packed(self)
     }
+
+    /// How long a `RATE_LIMITED` response asks the caller to wait before retrying.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.error.as_ref()?.retry_after
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -136,6 +214,9 @@ pub struct OperatorError {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details_cbor: Option<Vec<u8>>,
+    /// Wait before retrying a `RATE_LIMITED` request; sent over HTTP as `Retry-After`.
+    #[serde(skip)]
+    pub retry_after: Option<Duration>,
 }
 
 #[derive(Debug, Serialize)]
@@ -160,14 +241,39 @@ pub struct Diagnostic {
     pub message_key: String,
     pub fallback: String,
     pub message: String,
+    /// Values of the `{name}` placeholders in the message and fallback, so clients can
+    /// render the message themselves.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+    /// The hint resolved for the request locale.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hint: Option<String>,
+    /// Message key and placeholder values of `hint`, for clients rendering it themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_key: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hint_args: BTreeMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub component_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub digest: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub operation_id: Option<String>,
+    /// Structured context for tooling, e.g. the frames of a trap.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl Diagnostic {
+    /// Set the hint from `hint` resolved for `locale`; `None` leaves it unset.
+    pub fn with_hint(mut self, hint: Option<I18nText>, locale: &str) -> Self {
+        if let Some(hint) = hint {
+            self.hint = Some(resolve_text(&hint, locale));
+            self.hint_key = Some(hint.message_key);
+            self.hint_args = hint.args;
+        }
+        self
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -183,6 +289,10 @@ pub enum OperatorErrorCode {
     InvokeTrap,
     Timeout,
     PolicyDenied,
+    ResourceExhausted,
+    Cancelled,
+    RateLimited,
+    IdempotencyConflict,
     HostFailure,
 }
 
@@ -201,6 +311,12 @@ impl Default for ExecutionValidationOptions {
     }
 }
 
+fn include_logs_from_flags(flags: &[String]) -> bool {
+    flags
+        .iter()
+        .any(|flag| flag.trim().eq_ignore_ascii_case(FLAG_INCLUDE_LOGS))
+}
+
 fn validation_options_from_flags(flags: &[String]) -> ExecutionValidationOptions {
     let mut options = ExecutionValidationOptions::default();
     for flag in flags {
@@ -353,6 +469,7 @@ fn schema_issues_to_diagnostics(
         .into_iter()
         .map(|issue| {
             let text = I18nText::new(issue.message_key, issue.fallback);
+            let hint = issue.hint;
             let path = if path_prefix.is_empty() {
                 issue.path
             } else if issue.path == "/" {
@@ -367,11 +484,16 @@ fn schema_issues_to_diagnostics(
                 message_key: text.message_key.clone(),
                 fallback: text.fallback.clone(),
                 message: resolve_text(&text, locale),
+                args: text.args,
                 hint: None,
+                hint_key: None,
+                hint_args: BTreeMap::new(),
                 component_id: Some(component_ref.to_string()),
                 digest: Some(resolved_digest.to_string()),
                 operation_id: Some(op_id.to_string()),
+                details: None,
             }
+            .with_hint(hint, locale)
         })
         .collect()
 }
@@ -385,9 +507,33 @@ fn diagnostic_error(
     operation_id: Option<&str>,
     component_id: Option<&str>,
     digest: Option<&str>,
+    hint: Option<I18nText>,
+    locale: &str,
+) -> Diagnostic {
+    text_diagnostic(
+        code,
+        path,
+        I18nText::new(message_key, fallback),
+        operation_id,
+        component_id,
+        digest,
+        hint,
+        locale,
+    )
+}
+
+/// [`diagnostic_error`] for a message with placeholder args.
+#[allow(clippy::too_many_arguments)]
+fn text_diagnostic(
+    code: &str,
+    path: &str,
+    text: I18nText,
+    operation_id: Option<&str>,
+    component_id: Option<&str>,
+    digest: Option<&str>,
+    hint: Option<I18nText>,
     locale: &str,
 ) -> Diagnostic {
-    let text = I18nText::new(message_key, fallback);
     let message = resolve_text(&text, locale);
     Diagnostic {
         code: code.to_string(),
@@ -396,11 +542,27 @@ fn diagnostic_error(
         message_key: text.message_key,
         message,
         fallback: text.fallback,
+        args: text.args,
         hint: None,
+        hint_key: None,
+        hint_args: BTreeMap::new(),
         component_id: component_id.map(ToString::to_string),
         digest: digest.map(ToString::to_string),
         operation_id: operation_id.map(ToString::to_string),
+        details: None,
     }
+    .with_hint(hint, locale)
+}
+
+/// Top-level message of a schema failure, e.g. "input failed schema validation: 2 issues
+/// found", in the plural form `diagnostics.len()` calls for.
+fn schema_failure_summary(target: &str, diagnostics: &[Diagnostic], locale: &str) -> String {
+    resolve_plural(
+        "runner.operator.schema_validation_summary",
+        diagnostics.len() as u64,
+        &[("target", target)],
+        locale,
+    )
 }
 
 impl OperatorErrorCode {
@@ -416,170 +578,523 @@ impl OperatorErrorCode {
             OperatorErrorCode::InvokeTrap => "component trapped during invoke",
             OperatorErrorCode::Timeout => "invocation timed out",
             OperatorErrorCode::PolicyDenied => "policy denied the operation",
+            OperatorErrorCode::ResourceExhausted => "component exceeded a resource limit",
+            OperatorErrorCode::Cancelled => "invocation was cancelled",
+            OperatorErrorCode::RateLimited => "operator rate limit exceeded",
+            OperatorErrorCode::IdempotencyConflict => "idempotency key reused for another payload",
             OperatorErrorCode::HostFailure => "internal host failure",
         }
     }
+
+    /// The code as it goes over the wire, e.g. `TYPE_MISMATCH`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperatorErrorCode::OpNotFound => "OP_NOT_FOUND",
+            OperatorErrorCode::ProviderNotFound => "PROVIDER_NOT_FOUND",
+            OperatorErrorCode::TenantNotAllowed => "TENANT_NOT_ALLOWED",
+            OperatorErrorCode::InvalidRequest => "INVALID_REQUEST",
+            OperatorErrorCode::CborDecode => "CBOR_DECODE",
+            OperatorErrorCode::TypeMismatch => "TYPE_MISMATCH",
+            OperatorErrorCode::ComponentLoad => "COMPONENT_LOAD",
+            OperatorErrorCode::InvokeTrap => "INVOKE_TRAP",
+            OperatorErrorCode::Timeout => "TIMEOUT",
+            OperatorErrorCode::PolicyDenied => "POLICY_DENIED",
+            OperatorErrorCode::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            OperatorErrorCode::Cancelled => "CANCELLED",
+            OperatorErrorCode::RateLimited => "RATE_LIMITED",
+            OperatorErrorCode::IdempotencyConflict => "IDEMPOTENCY_CONFLICT",
+            OperatorErrorCode::HostFailure => "HOST_FAILURE",
+        }
+    }
 }
 
+/// Longest span attribute value, in bytes; longer values are cut.
+pub const MAX_SPAN_ATTRIBUTE_LEN: usize = 256;
+
+// Attributes of the `operator.invoke` span besides its `tenant`, `op_id` and provider
+// selectors. All sit under `greentic.`; values are cut to `MAX_SPAN_ATTRIBUTE_LEN`.
+//
+// Recorded once the op is resolved:
+// - `greentic.pack_ref`: `id@version` of the pack serving the op
+// - `greentic.component_ref`, `greentic.component_digest`: the component invoked
+// - `greentic.schema_hash`: hash of the op contract, as compared with `schema_hash`
+// - `greentic.validate_output`, `greentic.strict`: the validation flags of the request
+// - `greentic.request_bytes`: size of the CBOR input
+//
+// Recorded when the invoke completes, on every path:
+// - `greentic.status`: `ok` or `error`, mirrored in `otel.status_code`
+// - `greentic.error_code`: the `OperatorErrorCode`, e.g. `TYPE_MISMATCH`
+// - `greentic.response_bytes`: size of the CBOR output
+//
+// `invoke_component` carries the digest and the request and response sizes too.
+const SPAN_PACK_REF: &str = "greentic.pack_ref";
+const SPAN_COMPONENT_REF: &str = "greentic.component_ref";
+const SPAN_COMPONENT_DIGEST: &str = "greentic.component_digest";
+const SPAN_SCHEMA_HASH: &str = "greentic.schema_hash";
+const SPAN_VALIDATE_OUTPUT: &str = "greentic.validate_output";
+const SPAN_STRICT: &str = "greentic.strict";
+const SPAN_RESPONSE_BYTES: &str = "greentic.response_bytes";
+const SPAN_STATUS: &str = "greentic.status";
+const SPAN_ERROR_CODE: &str = "greentic.error_code";
+
 /// Invoke an operator request without assuming HTTP transport.
 pub async fn invoke_operator(
     runtime: &TenantRuntime,
     request: OperatorRequest,
 ) -> OperatorResponse {
+    invoke_operator_tracked(runtime, request, None).await
+}
+
+/// Like [`invoke_operator`], but node components are called through `invoke-stream`
+/// with their events handed to `observer`. With output validation on, each `data`
+/// event is checked against the output schema. Components without the export
+/// (provider-core, node@0.6) fail with `invalid_request`.
+pub async fn invoke_operator_streaming(
+    runtime: &TenantRuntime,
+    request: OperatorRequest,
+    observer: Arc<dyn StreamObserver>,
+) -> OperatorResponse {
+    invoke_operator_tracked(runtime, request, Some(observer)).await
+}
+
+/// Request of `/operator/op/describe` (CBOR): the op to describe, selected as in an
+/// [`OperatorRequest`]. `flags` pick the validation mode whose contract cache entry
+/// describing fills, so an invoke with the same flags is a cache hit.
+#[derive(Debug, Deserialize)]
+pub struct OperatorDescribeRequest {
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    #[serde(default)]
+    pub provider_type: Option<String>,
+    pub op_id: String,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// The contract of an op as invoking it would check it; `schema_hash` is the value to
+/// pin in [`OperatorRequest::schema_hash`].
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorContractDescription {
+    pub provider_id: Option<String>,
+    pub provider_type: String,
+    pub op_id: String,
+    /// Operation the component is invoked with.
+    pub selected_operation: String,
+    pub pack_ref: String,
+    pub component_ref: String,
+    pub component_digest: String,
+    pub describe_hash: String,
+    pub schema_hash: String,
+    pub input_schema: Value,
+    pub output_schema: Value,
+    pub config_schema: Value,
+}
+
+/// `/operator/op/describe` response envelope: `contract` on success, `error` (with the
+/// diagnostics an invoke would carry) on failure.
+#[derive(Debug, Serialize)]
+pub struct OperatorDescribeResponse {
+    pub status: OperatorStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract: Option<OperatorContractDescription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<OperatorError>,
+}
+
+impl OperatorDescribeResponse {
+    pub fn to_cbor(&self) -> Result<Vec<u8>, serde_cbor::Error> {
+        serde_cbor::ser::to_vec_packed(self)
+    }
+}
+
+impl From<Result<OperatorContractDescription, OperatorResponse>> for OperatorDescribeResponse {
+    fn from(result: Result<OperatorContractDescription, OperatorResponse>) -> Self {
+        match result {
+            Ok(contract) => Self {
+                status: OperatorStatus::Ok,
+                contract: Some(contract),
+                error: None,
+            },
+            Err(response) => Self {
+                status: OperatorStatus::Error,
+                contract: None,
+                error: response.error,
+            },
+        }
+    }
+}
+
+/// Resolve `op_id` and introspect its contract without invoking the component. The
+/// hashes land in the contract cache as an invoke with default flags records them, and
+/// failures carry the diagnostics that invoke would return.
+pub fn describe_operator_contract(
+    runtime: &TenantRuntime,
+    selector: ProviderSelector<'_>,
+    op_id: &str,
+) -> Result<OperatorContractDescription, OperatorResponse> {
+    describe_contract(
+        runtime,
+        selector,
+        op_id,
+        ExecutionValidationOptions::default(),
+        &select_locale(None),
+    )
+}
+
+/// [`describe_operator_contract`] for a `/operator/op/describe` request.
+pub fn describe_operator(
+    runtime: &TenantRuntime,
+    request: &OperatorDescribeRequest,
+) -> OperatorDescribeResponse {
     let op_id = normalize_operation_id(&request.op_id);
-    let validation_options = validation_options_from_flags(&request.flags);
     let locale = select_locale(request.locale.as_deref());
-    if let Some(request_tenant) = request.tenant_id.as_deref()
-        && request_tenant != runtime.tenant()
-    {
-        let message = format!(
-            "tenant mismatch: routing resolved `{}` but request wants `{request_tenant}`",
-            runtime.tenant(),
-        );
-        return OperatorResponse::error_with_diagnostics(
-            OperatorErrorCode::TenantNotAllowed,
-            message.clone(),
-            vec![diagnostic_error(
-                "tenant_mismatch",
-                "/tenant_id",
-                "runner.operator.tenant_mismatch",
-                message,
-                Some(op_id.as_str()),
-                None,
-                runtime.digest(),
+    let described = check_request_tenant(runtime, request.tenant_id.as_deref(), &op_id, &locale)
+        .and_then(|()| {
+            describe_contract(
+                runtime,
+                ProviderSelector {
+                    provider_id: request.provider_id.as_deref(),
+                    provider_type: request.provider_type.as_deref(),
+                },
+                &op_id,
+                validation_options_from_flags(&request.flags),
                 &locale,
-            )],
-        );
-    }
+            )
+        });
+    OperatorDescribeResponse::from(described)
+}
 
-    if request.provider_id.is_none() && request.provider_type.is_none() {
-        let message = "operator invoke requires provider_id or provider_type".to_string();
-        return OperatorResponse::error_with_diagnostics(
-            OperatorErrorCode::InvalidRequest,
-            message.clone(),
-            vec![diagnostic_error(
-                "missing_provider_selector",
-                "/provider_id",
-                "runner.operator.missing_provider_selector",
-                message,
-                Some(op_id.as_str()),
-                None,
-                runtime.digest(),
-                &locale,
-            )],
-        );
-    }
+fn describe_contract(
+    runtime: &TenantRuntime,
+    selector: ProviderSelector<'_>,
+    op_id: &str,
+    validation_options: ExecutionValidationOptions,
+    locale: &str,
+) -> Result<OperatorContractDescription, OperatorResponse> {
+    let op_id = normalize_operation_id(op_id);
+    selector.check("operator describe", runtime, &op_id, locale)?;
+    let binding = resolve_binding(runtime, selector, &op_id, locale)?;
+    check_operator_policy(runtime, selector.provider_id, binding, locale)?;
+    let contract = load_contract(
+        runtime,
+        binding,
+        &op_id,
+        validation_options,
+        locale,
+        &Span::none(),
+    )?;
+    Ok(OperatorContractDescription {
+        provider_id: binding.provider_id.clone(),
+        provider_type: binding.provider_type.clone(),
+        op_id,
+        selected_operation: contract.selected_operation,
+        pack_ref: binding.pack_ref.clone(),
+        component_ref: binding.runtime.component_ref.clone(),
+        component_digest: contract.resolved_digest,
+        describe_hash: contract.snapshot.describe_hash.clone().unwrap_or_default(),
+        schema_hash: contract.snapshot.schema_hash.clone().unwrap_or_default(),
+        input_schema: contract.input_schema,
+        output_schema: contract.output_schema,
+        config_schema: contract.config_schema,
+    })
+}
 
+/// Record invoke latency, capture component stdio (returned under `logs` with the
+/// `include-logs` flag) and, when the request carries a `correlation_id`, register the
+/// invocation's cancellation token so admins can abort it while it runs. Every response
+/// leaves through here, so this is where secret values are redacted from it and where the
+/// `operator.invoke` trace is sampled.
+async fn invoke_operator_tracked(
+    runtime: &TenantRuntime,
+    mut request: OperatorRequest,
+    observer: Option<Arc<dyn StreamObserver>>,
+) -> OperatorResponse {
+    let started = Instant::now();
+    let timeout_warning = clamp_timeout(runtime, &mut request);
+    let _inflight = runtime.operator_metrics().track_inflight();
+    let cancel = cancel::current().unwrap_or_default();
+    let _registration = request
+        .correlation_id
+        .as_deref()
+        .map(|id| runtime.invocations().register(id, cancel.clone()));
+    let include_logs = include_logs_from_flags(&request.flags);
+    let sink = StdioSink::new();
+    let redactor = Redactor::new(runtime.config().redaction.clone());
     let tenant = runtime.tenant();
     let root_span = span!(
         Level::INFO,
         "operator.invoke",
         tenant = %tenant,
-        op_id = %op_id,
+        op_id = %normalize_operation_id(&request.op_id),
         provider_id = ?request.provider_id,
-        provider_type = ?request.provider_type
+        provider_type = ?request.provider_type,
+        greentic.pack_ref = field::Empty,
+        greentic.component_ref = field::Empty,
+        greentic.component_digest = field::Empty,
+        greentic.schema_hash = field::Empty,
+        greentic.validate_output = field::Empty,
+        greentic.strict = field::Empty,
+        greentic.request_bytes = request.payload.cbor_input.len(),
+        greentic.response_bytes = field::Empty,
+        greentic.status = field::Empty,
+        greentic.error_code = field::Empty,
+        otel.status_code = field::Empty
     );
-    let _root_guard = root_span.enter();
-
-    let provider_id = request.provider_id.as_deref();
-    let provider_type = request.provider_type.as_deref();
+    let sampling = sampling::start_root(&root_span, tenant, request.trace_id.as_deref());
+    let mut replay = None;
+    let invocation =
+        invoke_operator_inner(runtime, request, observer, root_span.clone(), &mut replay);
+    let invocation = cancel::scope(cancel, invocation);
+    let invocation = redact::scope(redactor.clone(), stdio::scope(sink.clone(), invocation));
+    let mut response = invocation.instrument(root_span.clone()).await;
+    if let Some(claim) = replay
+        && let Some(output) = response.cbor_output.as_deref()
+        && let Err(err) = runtime.operator_replay().record(claim, output).await
+    {
+        tracing::warn!(
+            tenant = %tenant,
+            error = %err,
+            "failed to store operator response for replay"
+        );
+    }
     runtime
         .operator_metrics()
-        .resolve_attempts
-        .fetch_add(1, Ordering::Relaxed);
+        .invoke_latency
+        .observe(started.elapsed());
+    if include_logs {
+        response.logs = Some(sink.snapshot());
+    }
+    response.redact(&redactor);
+    response.warnings.extend(timeout_warning);
+    record_completion(&root_span, &response);
+    sampling.finish(response.error.as_ref().map(|error| error.message.as_str()));
+    response
+}
+
+/// Cut `request.timeout` to the host's `max_timeout_ms`, returning a warning when it
+/// had to.
+fn clamp_timeout(runtime: &TenantRuntime, request: &mut OperatorRequest) -> Option<Diagnostic> {
+    let max = runtime.config().operator_policy.max_timeout_ms();
+    let requested = request.timeout.filter(|requested| *requested > max)?;
+    request.timeout = Some(max);
+    let locale = select_locale(request.locale.as_deref());
+    let text = I18nText::new(
+        "runner.operator.timeout_clamped",
+        "requested timeout of {requested}ms was cut to the {max}ms maximum",
+    )
+    .with_arg("requested", requested.to_string())
+    .with_arg("max", max.to_string());
+    let op_id = normalize_operation_id(&request.op_id);
+    let mut diagnostic = text_diagnostic(
+        "timeout_clamped",
+        "/timeout",
+        text,
+        Some(&op_id),
+        None,
+        None,
+        None,
+        &locale,
+    );
+    diagnostic.severity = DiagnosticSeverity::Warning;
+    Some(diagnostic)
+}
+
+/// The stored output of an earlier request with idempotency `key`, flagged so callers
+/// can tell it was not invoked again.
+fn replayed_response(output: Vec<u8>, key: &str, op_id: &str, locale: &str) -> OperatorResponse {
+    let text = I18nText::new(
+        "runner.operator.idempotent_replay",
+        "replayed the response stored for idempotency key `{key}`",
+    )
+    .with_arg("key", key);
+    let mut diagnostic = text_diagnostic(
+        "idempotent_replay",
+        "/idempotency_key",
+        text,
+        Some(op_id),
+        None,
+        None,
+        None,
+        locale,
+    );
+    diagnostic.severity = DiagnosticSeverity::Warning;
+    let mut response = OperatorResponse::ok(output);
+    response.warnings.push(diagnostic);
+    response
+}
+
+/// Refusal of a request reusing idempotency `key` for another payload.
+fn replay_conflict(key: &str, op_id: &str, locale: &str) -> OperatorResponse {
+    let text = I18nText::new(
+        "runner.operator.idempotency_conflict",
+        "idempotency key `{key}` was already used for a different payload",
+    )
+    .with_arg("key", key);
+    OperatorResponse::error_with_diagnostics(
+        OperatorErrorCode::IdempotencyConflict,
+        text.fallback_message(),
+        vec![text_diagnostic(
+            "idempotency_conflict",
+            "/idempotency_key",
+            text,
+            Some(op_id),
+            None,
+            None,
+            None,
+            locale,
+        )],
+    )
+}
+
+/// `sha256:` digest of the CBOR input and attachment references of `payload`.
+fn payload_fingerprint(payload: &OperatorPayload) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((payload.cbor_input.len() as u64).to_be_bytes());
+    hasher.update(&payload.cbor_input);
+    for attachment in &payload.attachments {
+        let metadata = attachment
+            .metadata
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or_default();
+        for part in [attachment.id.as_bytes(), metadata.as_bytes()] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+    }
+    format!("sha256:{:x}", hasher.finalize())
+}
+
+/// The host gave up waiting for an invocation that outlived its request `timeout`,
+/// typically because the guest was blocked in a host call the epoch interrupt cannot
+/// reach.
+#[derive(Debug, thiserror::Error)]
+#[error("invocation exceeded its {}ms timeout", .timeout.as_millis())]
+struct InvokeTimeout {
+    timeout: Duration,
+}
+
+/// Await `invocation`, giving up [`cancel::CANCEL_GRACE`] after `timeout` so the epoch
+/// interrupt still reports guests that were running. Giving up cancels the invocation.
+async fn within_timeout<T>(
+    timeout: Option<Duration>,
+    invocation: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let Some(timeout) = timeout else {
+        return invocation.await;
+    };
+    match tokio::time::timeout(timeout + cancel::CANCEL_GRACE, invocation).await {
+        Ok(result) => result,
+        Err(_) => {
+            if let Some(cancel) = cancel::current() {
+                cancel.cancel(CancelReason::Deadline);
+            }
+            Err(InvokeTimeout { timeout }.into())
+        }
+    }
+}
+
+/// Whether `err` is the request `timeout` cutting an invocation off.
+fn is_request_timeout(err: &anyhow::Error) -> bool {
+    err.is::<InvokeTimeout>()
+        || err
+            .downcast_ref::<DeadlineExceeded>()
+            .is_some_and(|hit| hit.deadline.kind == DeadlineKind::WallClock)
+}
+
+fn record_invoke_error(runtime: &TenantRuntime, err: &anyhow::Error) {
+    let metrics = runtime.operator_metrics();
+    metrics.invoke_errors.fetch_add(1, Ordering::Relaxed);
+    if is_request_timeout(err) {
+        metrics.invoke_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Stamp the outcome of an invoke on its `operator.invoke` span.
+fn record_completion(span: &Span, response: &OperatorResponse) {
+    let output_bytes = response.cbor_output.as_ref().map_or(0, Vec::len);
+    span.record(SPAN_RESPONSE_BYTES, output_bytes);
+    match &response.error {
+        None => {
+            span.record(SPAN_STATUS, "ok");
+            span.record("otel.status_code", "OK");
+        }
+        Some(error) => {
+            span.record(SPAN_STATUS, "error");
+            span.record(SPAN_ERROR_CODE, error.code.as_str());
+            span.record("otel.status_code", "ERROR");
+        }
+    }
+}
+
+/// Record `value` as the `field` attribute of `span`, cut to [`MAX_SPAN_ATTRIBUTE_LEN`].
+fn record_bounded(span: &Span, field: &str, value: &str) {
+    span.record(field, &*bounded_attribute(value));
+}
+
+/// `value` cut to [`MAX_SPAN_ATTRIBUTE_LEN`] bytes at a char boundary, ending in `…`.
+fn bounded_attribute(value: &str) -> Cow<'_, str> {
+    if value.len() <= MAX_SPAN_ATTRIBUTE_LEN {
+        return Cow::Borrowed(value);
+    }
+    let mut end = MAX_SPAN_ATTRIBUTE_LEN - '…'.len_utf8();
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}…", &value[..end]))
+}
+
+async fn invoke_operator_inner(
+    runtime: &TenantRuntime,
+    request: OperatorRequest,
+    observer: Option<Arc<dyn StreamObserver>>,
+    root_span: Span,
+    replay: &mut Option<ReplayClaim>,
+) -> OperatorResponse {
+    let op_id = normalize_operation_id(&request.op_id);
+    let validation_options = validation_options_from_flags(&request.flags);
+    root_span.record(SPAN_VALIDATE_OUTPUT, validation_options.validate_output);
+    root_span.record(SPAN_STRICT, validation_options.strict);
+    let locale = select_locale(request.locale.as_deref());
+    if let Err(response) =
+        check_request_tenant(runtime, request.tenant_id.as_deref(), &op_id, &locale)
+    {
+        return response;
+    }
+    let selector = ProviderSelector {
+        provider_id: request.provider_id.as_deref(),
+        provider_type: request.provider_type.as_deref(),
+    };
+    if let Err(response) = selector.check("operator invoke", runtime, &op_id, &locale) {
+        return response;
+    }
+
+    let provider_id = selector.provider_id;
     let resolve_span = span!(Level::DEBUG, "resolve_op");
     let _resolve_guard = resolve_span.enter();
-    let binding = match runtime
-        .operator_registry()
-        .resolve(provider_id, provider_type, &op_id)
-    {
+    let binding = match resolve_binding(runtime, selector, &op_id, &locale) {
         Ok(binding) => binding,
-        Err(err) => {
-            let (code, message) = match err {
-                OperatorResolveError::ProviderNotFound => {
-                    let label = provider_id.or(provider_type).unwrap_or("unknown");
-                    (
-                        OperatorErrorCode::ProviderNotFound,
-                        format!("provider `{label}` not registered"),
-                    )
-                }
-                OperatorResolveError::OpNotFound => {
-                    let label = provider_id.or(provider_type).unwrap_or("unknown provider");
-                    (
-                        OperatorErrorCode::OpNotFound,
-                        format!("op `{}` not found for provider `{label}`", &op_id),
-                    )
-                }
-            };
-            runtime
-                .operator_metrics()
-                .resolve_errors
-                .fetch_add(1, Ordering::Relaxed);
-            let response = OperatorResponse::error(code, message);
-            let diagnostic = diagnostic_error(
-                match code {
-                    OperatorErrorCode::ProviderNotFound => "provider_not_found",
-                    OperatorErrorCode::OpNotFound => "op_not_found",
-                    _ => "resolve_error",
-                },
-                "/op_id",
-                match code {
-                    OperatorErrorCode::ProviderNotFound => "runner.operator.provider_not_found",
-                    OperatorErrorCode::OpNotFound => "runner.operator.op_not_found",
-                    _ => "runner.operator.resolve_error",
-                },
-                response
-                    .error
-                    .as_ref()
-                    .map(|e| e.message.clone())
-                    .unwrap_or_else(|| "operator resolve failed".to_string()),
-                Some(op_id.as_str()),
-                binding_component_ref_hint(provider_id, provider_type),
-                runtime.digest(),
-                &locale,
-            );
-            let response = OperatorResponse::error_with_diagnostics(
-                code,
-                response
-                    .error
-                    .as_ref()
-                    .map(|e| e.message.clone())
-                    .unwrap_or_else(|| "operator resolve failed".to_string()),
-                vec![diagnostic],
-            );
-            return response;
-        }
+        Err(response) => return response,
     };
     drop(_resolve_guard);
+    record_bounded(&root_span, SPAN_PACK_REF, &binding.pack_ref);
+    record_bounded(
+        &root_span,
+        SPAN_COMPONENT_REF,
+        &binding.runtime.component_ref,
+    );
 
-    let policy = &runtime.config().operator_policy;
-    if !policy.allows_provider(provider_id, binding.provider_type.as_str()) {
-        return OperatorResponse::error(
-            OperatorErrorCode::PolicyDenied,
-            format!(
-                "provider `{}` not allowed for tenant {}",
-                binding
-                    .provider_id
-                    .as_deref()
-                    .unwrap_or(&binding.provider_type),
-                runtime.config().tenant
-            ),
-        );
+    if let Err(response) = check_operator_policy(runtime, provider_id, binding, &locale) {
+        return response;
     }
-    if !policy.allows_op(provider_id, binding.provider_type.as_str(), &binding.op_id) {
-        return OperatorResponse::error(
-            OperatorErrorCode::PolicyDenied,
-            format!(
-                "op `{}` is not permitted for provider `{}` on tenant {}",
-                binding.op_id,
-                binding
-                    .provider_id
-                    .as_deref()
-                    .unwrap_or(&binding.provider_type),
-                runtime.config().tenant
-            ),
-        );
+    if let Err(response) = check_rate_limit(runtime, binding, &locale) {
+        return response;
     }
+    let policy = &runtime.config().operator_policy;
 
     if let Some(req_pack) = request.pack_id.as_deref() {
         let binding_pack = binding
@@ -598,142 +1113,125 @@ pub async fn invoke_operator(
         }
     }
 
-    let attachments = match resolve_attachments(&request.payload, runtime) {
-        Ok(map) => map,
-        Err(response) => return response,
-    };
-
-    let decode_span = span!(Level::DEBUG, "decode_cbor");
-    let _decode_guard = decode_span.enter();
-    let input_value = match decode_request_payload(&request.payload.cbor_input) {
-        Ok(value) => value,
-        Err(err) => {
-            runtime
-                .operator_metrics()
-                .cbor_decode_errors
-                .fetch_add(1, Ordering::Relaxed);
-            return OperatorResponse::error(OperatorErrorCode::CborDecode, format!("{err}"));
+    if let Some(impersonation) = &request.impersonation {
+        if impersonation.actor_id.trim().is_empty() {
+            return OperatorResponse::error(
+                OperatorErrorCode::InvalidRequest,
+                "impersonation requires a non-empty actor_id".to_string(),
+            );
         }
-    };
-    drop(_decode_guard);
-
-    let input_value = merge_input_with_attachments(input_value, attachments);
-
-    let component_ref = &binding.runtime.component_ref;
-    let resolved = match runtime.resolve_component(component_ref) {
-        Some(resolved) => resolved,
-        None => {
+        let allowed = policy.allows_impersonation();
+        runtime.audit().impersonation(
+            &runtime.config().tenant,
+            &impersonation.actor_id,
+            impersonation.reason.as_deref(),
+            allowed,
+            &operator_requester(&binding, None),
+        );
+        if !allowed {
             return OperatorResponse::error(
-                OperatorErrorCode::ComponentLoad,
-                format!("component `{}` not found in tenant packs", component_ref),
+                OperatorErrorCode::PolicyDenied,
+                format!(
+                    "impersonation by `{}` is not permitted for tenant {}",
+                    impersonation.actor_id,
+                    runtime.config().tenant
+                ),
             );
         }
-    };
-    let pack = resolved.pack;
-    let resolved_digest = if resolved.digest == "unknown" {
-        binding
-            .pack_digest
-            .clone()
-            .unwrap_or_else(|| resolved.digest.clone())
-    } else {
-        resolved.digest.clone()
-    };
-    let introspected_contract =
-        match introspect_component_contract(pack.as_ref(), component_ref.as_str(), &op_id) {
-            Ok(value) => value,
+    }
+
+    // Streamed events cannot be replayed, so streaming requests always run.
+    let replayable = observer.is_none() && runtime.operator_replay().is_enabled();
+    if let Some(key) = request.idempotency_key.as_deref().filter(|_| replayable) {
+        let provider = provider_id.unwrap_or(&binding.provider_type);
+        let bypass = request
+            .flags
+            .iter()
+            .any(|flag| flag == FLAG_NO_IDEMPOTENT_REPLAY);
+        let fingerprint = payload_fingerprint(&request.payload);
+        match runtime
+            .operator_replay()
+            .claim(provider, &op_id, key, &fingerprint, bypass)
+            .await
+        {
+            Ok(ReplayLookup::Stored(output)) => {
+                return replayed_response(output, key, &op_id, &locale);
+            }
+            Ok(ReplayLookup::Conflict) => return replay_conflict(key, &op_id, &locale),
+            Ok(ReplayLookup::Claimed(claim)) => *replay = Some(claim),
             Err(err) => {
-                let message = format!("failed to introspect component contract: {err}");
-                return OperatorResponse::error_with_diagnostics(
-                    OperatorErrorCode::TypeMismatch,
-                    message.clone(),
-                    vec![diagnostic_error(
-                        "contract_introspection_failed",
-                        "/operation",
-                        "runner.operator.contract_introspection_failed",
-                        message,
-                        Some(op_id.as_str()),
-                        Some(component_ref.as_str()),
-                        Some(resolved_digest.as_str()),
-                        &locale,
-                    )],
+                tracing::warn!(
+                    op_id = %op_id,
+                    error = %err,
+                    "operator replay lookup failed; invoking"
                 );
             }
-        };
-    let invoke_op_id = introspected_contract
-        .as_ref()
-        .map(|contract| contract.selected_operation.clone())
-        .unwrap_or_else(|| op_id.clone());
-    let loaded_config_schema = introspected_contract
-        .as_ref()
-        .map(|contract| contract.config_schema.clone())
-        .filter(|value| !value.is_null())
-        .or_else(|| {
-            binding
-                .config_schema_ref
-                .as_deref()
-                .and_then(|schema_ref| pack.load_schema_json(schema_ref).ok().flatten())
-        })
-        .unwrap_or(Value::Null);
-    let loaded_output_schema = introspected_contract
-        .as_ref()
-        .map(|contract| contract.output_schema.clone())
-        .filter(|value| !value.is_null())
-        .or_else(|| {
-            derive_output_schema_ref(binding.config_schema_ref.as_deref())
-                .and_then(|schema_ref| pack.load_schema_json(&schema_ref).ok().flatten())
-        })
-        .unwrap_or(Value::Null);
-    let loaded_input_schema = introspected_contract
+        }
+    }
+
+    let actor_id = request
+        .impersonation
         .as_ref()
-        .map(|contract| contract.input_schema.clone())
-        .filter(|value| !value.is_null())
-        .or_else(|| loaded_config_schema.is_null().then_some(Value::Null))
-        .or_else(|| Some(loaded_config_schema.clone()))
-        .unwrap_or(Value::Null);
-    let loaded_config_schema = binding
-        .config_schema_ref
-        .as_deref()
-        .and_then(|schema_ref| pack.load_schema_json(schema_ref).ok().flatten())
-        .unwrap_or_else(|| loaded_config_schema.clone());
-    let contract_key = format!(
-        "{}::{component_ref}::{op_id}::validate_output={}::strict={}",
-        resolved_digest, validation_options.validate_output, validation_options.strict
-    );
-    let _contract_snapshot = if let Some(snapshot) = runtime.contract_cache().get(&contract_key) {
-        snapshot
-    } else {
-        let (describe_hash, schema_hash) = introspected_contract
-            .as_ref()
-            .map(|contract| (contract.describe_hash.clone(), contract.schema_hash.clone()))
-            .unwrap_or_else(|| {
-                compute_contract_hashes(
-                    &resolved_digest,
-                    component_ref,
-                    &invoke_op_id,
-                    &binding.runtime.world,
-                    &binding.runtime.export,
-                    &loaded_input_schema,
-                    &loaded_output_schema,
-                    &loaded_config_schema,
-                    binding.state_schema_ref.as_deref(),
-                    &binding.pack_ref,
-                )
-            });
-        let mut snapshot = ContractSnapshot::new(
-            resolved_digest.clone(),
-            component_ref.clone(),
-            invoke_op_id.clone(),
-            validation_options.validate_output,
-            validation_options.strict,
-        );
-        snapshot.describe_hash = Some(describe_hash);
-        snapshot.schema_hash = Some(schema_hash);
-        let snapshot = Arc::new(snapshot);
-        runtime
-            .contract_cache()
-            .insert(contract_key, Arc::clone(&snapshot));
-        snapshot
+        .map(|imp| imp.actor_id.as_str());
+    let attachments = match resolve_attachments(&request.payload, runtime, &binding, actor_id).await
+    {
+        Ok(map) => map,
+        Err(response) => return response,
+    };
+
+    let decode_span = span!(Level::DEBUG, "decode_cbor");
+    let _decode_guard = decode_span.enter();
+    let input_value = match decode_request_payload(&request.payload.cbor_input) {
+        Ok(value) => value,
+        Err(err) => {
+            runtime
+                .operator_metrics()
+                .cbor_decode_errors
+                .fetch_add(1, Ordering::Relaxed);
+            return OperatorResponse::error(OperatorErrorCode::CborDecode, format!("{err}"));
+        }
+    };
+    drop(_decode_guard);
+
+    let input_value = merge_input_with_attachments(input_value, attachments);
+
+    let component_ref = &binding.runtime.component_ref;
+    let OperatorContract {
+        pack,
+        resolved_digest,
+        selected_operation: invoke_op_id,
+        input_schema: loaded_input_schema,
+        config_schema: loaded_config_schema,
+        snapshot: _contract_snapshot,
+        ..
+    } = match load_contract(
+        runtime,
+        binding,
+        &op_id,
+        validation_options,
+        &locale,
+        &root_span,
+    ) {
+        Ok(contract) => contract,
+        Err(response) => return response,
     };
+    let is_provider_core = binding.runtime.world.starts_with("greentic:provider-core");
+    if observer.is_some() && is_provider_core {
+        let unsupported = StreamUnsupported {
+            component_ref: component_ref.clone(),
+        };
+        return invoke_failure(
+            "provider",
+            unsupported.into(),
+            &op_id,
+            component_ref,
+            &resolved_digest,
+            &locale,
+        );
+    }
+    if let Some(schema_hash) = _contract_snapshot.schema_hash.as_deref() {
+        record_bounded(&root_span, SPAN_SCHEMA_HASH, schema_hash);
+    }
     if !loaded_input_schema.is_null() {
         let issues = validate_json_instance(
             &loaded_input_schema,
@@ -751,7 +1249,7 @@ pub async fn invoke_operator(
             );
             return OperatorResponse::error_with_diagnostics(
                 OperatorErrorCode::TypeMismatch,
-                "input failed schema validation".to_string(),
+                schema_failure_summary("input", &diagnostics, &locale),
                 diagnostics,
             );
         }
@@ -772,6 +1270,7 @@ pub async fn invoke_operator(
                 Some(op_id.as_str()),
                 Some(component_ref.as_str()),
                 Some(resolved_digest.as_str()),
+                None,
                 &locale,
             )],
         );
@@ -783,27 +1282,64 @@ pub async fn invoke_operator(
         let expected = normalize_sha256_hash(expected_schema_hash);
         let provided = normalize_sha256_hash(request_schema_hash);
         if expected != provided {
-            let message = format!(
-                "schema_hash mismatch for op `{}`: expected `{}`, got `{}`",
-                op_id, expected, provided
-            );
+            let text = I18nText::new(
+                "runner.operator.schema_hash_mismatch",
+                "schema_hash mismatch for op `{op}`: expected `{expected}`, got `{provided}`",
+            )
+            .with_arg("op", op_id.as_str())
+            .with_arg("expected", expected)
+            .with_arg("provided", provided);
             return OperatorResponse::error_with_diagnostics(
                 OperatorErrorCode::TypeMismatch,
-                message.clone(),
-                vec![diagnostic_error(
+                text.fallback_message(),
+                vec![text_diagnostic(
                     "schema_hash_mismatch",
                     "/schema_hash",
-                    "runner.operator.schema_hash_mismatch",
-                    message,
+                    text,
                     Some(op_id.as_str()),
                     Some(component_ref.as_str()),
                     Some(resolved_digest.as_str()),
+                    None,
                     &locale,
                 )],
             );
         }
     }
 
+    let provider_config = match provider_id {
+        Some(id) => match pack.provider_instance_config(id) {
+            Ok(config) => config,
+            Err(err) => {
+                return OperatorResponse::error(
+                    OperatorErrorCode::ProviderNotFound,
+                    err.to_string(),
+                );
+            }
+        },
+        None => None,
+    };
+    if let Some(config) = &provider_config
+        && !loaded_config_schema.is_null()
+    {
+        let issues =
+            validate_json_instance(&loaded_config_schema, config, validation_options.strict);
+        if !issues.is_empty() {
+            let diagnostics = schema_issues_to_diagnostics(
+                issues,
+                "/provider_config",
+                component_ref,
+                &resolved_digest,
+                &op_id,
+                &locale,
+            );
+            return OperatorResponse::error_with_diagnostics(
+                OperatorErrorCode::TypeMismatch,
+                schema_failure_summary("provider_config", &diagnostics, &locale),
+                diagnostics,
+            );
+        }
+    }
+
     let input_json = match serde_json::to_string(&input_value) {
         Ok(json) => json,
         Err(err) => {
@@ -814,14 +1350,56 @@ pub async fn invoke_operator(
         }
     };
 
-    let exec_ctx = build_exec_ctx(&request, runtime, &op_id);
+    let exec_ctx = match build_exec_ctx(&request, runtime, &op_id) {
+        Ok(ctx) => ctx,
+        Err(err) => {
+            return OperatorResponse::error(
+                OperatorErrorCode::InvalidRequest,
+                format!("invalid tenant attributes: {err}"),
+            );
+        }
+    };
+    let output_check = match &observer {
+        Some(observer) if validation_options.validate_output => {
+            derive_output_schema_ref(binding.config_schema_ref.as_deref())
+                .and_then(|output_ref| pack.load_schema_json(&output_ref).ok().flatten())
+                .map(|schema| {
+                    Arc::new(OutputCheckingObserver::new(
+                        Arc::clone(observer),
+                        schema,
+                        validation_options.strict,
+                    ))
+                })
+        }
+        _ => None,
+    };
+    let observer = match &output_check {
+        Some(check) => Some(Arc::clone(check) as Arc<dyn StreamObserver>),
+        None => observer,
+    };
     runtime
         .operator_metrics()
         .invoke_attempts
         .fetch_add(1, Ordering::Relaxed);
-    let invoke_span = span!(Level::INFO, "invoke_component", component = %component_ref);
+    let invoke_span = span!(
+        Level::INFO,
+        "invoke_component",
+        component = %component_ref,
+        actor_id = actor_id,
+        greentic.component_digest = %bounded_attribute(&resolved_digest),
+        greentic.request_bytes = input_json.len(),
+        greentic.response_bytes = field::Empty,
+        stdout = field::Empty,
+        stderr = field::Empty,
+        deterministic_seed = field::Empty,
+        deterministic_start_ms = field::Empty
+    );
+    if let Some(deterministic) = &runtime.config().wasi.deterministic {
+        deterministic.record(&invoke_span);
+    }
     let _invoke_guard = invoke_span.enter();
-    let result = if binding.runtime.world.starts_with("greentic:provider-core") {
+    let timeout = request.timeout.map(Duration::from_millis);
+    let result = if is_provider_core {
         let input_bytes = input_json.clone().into_bytes();
         let provider_binding = ProviderBinding {
             provider_id: binding.provider_id.clone(),
@@ -829,52 +1407,99 @@ pub async fn invoke_operator(
             component_ref: binding.runtime.component_ref.clone(),
             export: binding.runtime.export.clone(),
             world: binding.runtime.world.clone(),
-            config_json: None,
+            config_json: provider_config.as_ref().map(Value::to_string),
             pack_ref: Some(binding.pack_ref.clone()),
         };
-        match pack
-            .invoke_provider(&provider_binding, exec_ctx, &invoke_op_id, input_bytes)
-            .await
-        {
+        let invocation = within_timeout(
+            timeout,
+            pack.invoke_provider(&provider_binding, exec_ctx, &invoke_op_id, input_bytes),
+        )
+        .await;
+        record_stdio(&invoke_span);
+        match invocation {
             Ok(value) => value,
             Err(err) => {
-                runtime
-                    .operator_metrics()
-                    .invoke_errors
-                    .fetch_add(1, Ordering::Relaxed);
-                return OperatorResponse::error(
-                    OperatorErrorCode::HostFailure,
-                    format!("provider invoke failed: {err}"),
+                record_invoke_error(runtime, &err);
+                return invoke_failure(
+                    "provider",
+                    err,
+                    &op_id,
+                    component_ref,
+                    &resolved_digest,
+                    &locale,
                 );
             }
         }
     } else {
-        match pack
-            .invoke_component(
-                component_ref,
-                exec_ctx,
-                &invoke_op_id,
-                None,
-                input_json.clone(),
-            )
-            .await
-        {
+        let invocation = match observer {
+            Some(observer) => {
+                within_timeout(
+                    timeout,
+                    pack.invoke_component_stream(
+                        component_ref,
+                        exec_ctx,
+                        &invoke_op_id,
+                        input_json.clone(),
+                        observer,
+                    ),
+                )
+                .await
+            }
+            None => {
+                within_timeout(
+                    timeout,
+                    pack.invoke_component(
+                        component_ref,
+                        exec_ctx,
+                        &invoke_op_id,
+                        None,
+                        input_json.clone(),
+                    ),
+                )
+                .await
+            }
+        };
+        record_stdio(&invoke_span);
+        match invocation {
             Ok(value) => value,
             Err(err) => {
-                runtime
-                    .operator_metrics()
-                    .invoke_errors
-                    .fetch_add(1, Ordering::Relaxed);
-                return OperatorResponse::error(
-                    OperatorErrorCode::HostFailure,
-                    format!("component invoke failed: {err}"),
+                record_invoke_error(runtime, &err);
+                return invoke_failure(
+                    "component",
+                    err,
+                    &op_id,
+                    component_ref,
+                    &resolved_digest,
+                    &locale,
                 );
             }
         }
     };
     drop(_invoke_guard);
 
-    if validation_options.validate_output
+    if let Some(check) = &output_check {
+        let diagnostics: Vec<Diagnostic> = check
+            .take_failures()
+            .into_iter()
+            .flat_map(|(index, issues)| {
+                schema_issues_to_diagnostics(
+                    issues,
+                    &format!("/events/{index}"),
+                    component_ref,
+                    &resolved_digest,
+                    &op_id,
+                    &locale,
+                )
+            })
+            .collect();
+        if !diagnostics.is_empty() {
+            return OperatorResponse::error_with_diagnostics(
+                OperatorErrorCode::TypeMismatch,
+                schema_failure_summary("output", &diagnostics, &locale),
+                diagnostics,
+            );
+        }
+    } else if validation_options.validate_output
         && let Some(output_ref) = derive_output_schema_ref(binding.config_schema_ref.as_deref())
         && let Ok(Some(output_schema)) = pack.load_schema_json(&output_ref)
     {
@@ -895,7 +1520,7 @@ pub async fn invoke_operator(
             );
             return OperatorResponse::error_with_diagnostics(
                 OperatorErrorCode::TypeMismatch,
-                "output failed schema validation".to_string(),
+                schema_failure_summary("output", &diagnostics, &locale),
                 diagnostics,
             );
         }
@@ -921,6 +1546,7 @@ pub async fn invoke_operator(
                             Some(op_id.as_str()),
                             Some(component_ref.as_str()),
                             Some(resolved_digest.as_str()),
+                            None,
                             &locale,
                         )],
                     );
@@ -941,6 +1567,7 @@ pub async fn invoke_operator(
                             Some(op_id.as_str()),
                             Some(component_ref.as_str()),
                             Some(resolved_digest.as_str()),
+                            None,
                             &locale,
                         )],
                     );
@@ -954,48 +1581,468 @@ pub async fn invoke_operator(
                     "/new_state",
                     component_ref,
                     &resolved_digest,
-                    &op_id,
-                    &locale,
-                );
-                return OperatorResponse::error_with_diagnostics(
-                    OperatorErrorCode::TypeMismatch,
-                    "new_state failed schema validation".to_string(),
-                    diagnostics,
-                );
-            }
-        } else if validation_options.strict {
-            let message = "new_state returned but no config_schema_ref is available".to_string();
-            return OperatorResponse::error_with_diagnostics(
-                OperatorErrorCode::TypeMismatch,
-                message.clone(),
-                vec![diagnostic_error(
-                    "new_state_schema_unavailable",
-                    "/new_state",
-                    "runner.operator.new_state_schema_unavailable",
-                    message,
-                    Some(op_id.as_str()),
-                    Some(component_ref.as_str()),
-                    Some(resolved_digest.as_str()),
-                    &locale,
-                )],
-            );
-        }
-    }
-
-    let encode_span = span!(Level::DEBUG, "encode_cbor");
-    let _encode_guard = encode_span.enter();
-    let output_bytes = match serde_cbor::to_vec(&result) {
-        Ok(bytes) => bytes,
-        Err(err) => {
-            return OperatorResponse::error(
-                OperatorErrorCode::HostFailure,
-                format!("failed to encode CBOR output: {err}"),
-            );
-        }
+                    &op_id,
+                    &locale,
+                );
+                return OperatorResponse::error_with_diagnostics(
+                    OperatorErrorCode::TypeMismatch,
+                    schema_failure_summary("new_state", &diagnostics, &locale),
+                    diagnostics,
+                );
+            }
+        } else if validation_options.strict {
+            let message = "new_state returned but no config_schema_ref is available".to_string();
+            return OperatorResponse::error_with_diagnostics(
+                OperatorErrorCode::TypeMismatch,
+                message.clone(),
+                vec![diagnostic_error(
+                    "new_state_schema_unavailable",
+                    "/new_state",
+                    "runner.operator.new_state_schema_unavailable",
+                    message,
+                    Some(op_id.as_str()),
+                    Some(component_ref.as_str()),
+                    Some(resolved_digest.as_str()),
+                    None,
+                    &locale,
+                )],
+            );
+        }
+    }
+
+    let encode_span = span!(Level::DEBUG, "encode_cbor");
+    let _encode_guard = encode_span.enter();
+    let output_bytes = match serde_cbor::to_vec(&result) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            return OperatorResponse::error(
+                OperatorErrorCode::HostFailure,
+                format!("failed to encode CBOR output: {err}"),
+            );
+        }
+    };
+    drop(_encode_guard);
+    invoke_span.record(SPAN_RESPONSE_BYTES, output_bytes.len());
+
+    OperatorResponse::ok(output_bytes)
+}
+
+/// Record the (truncated) stdio captured so far on the invoke span.
+/// Which provider an op is looked up on; `provider_id` wins when both are set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProviderSelector<'a> {
+    pub provider_id: Option<&'a str>,
+    pub provider_type: Option<&'a str>,
+}
+
+impl ProviderSelector<'_> {
+    /// Refuse a selector naming neither a provider id nor a type; `action` names the
+    /// request in the message.
+    fn check(
+        &self,
+        action: &str,
+        runtime: &TenantRuntime,
+        op_id: &str,
+        locale: &str,
+    ) -> Result<(), OperatorResponse> {
+        if self.provider_id.is_some() || self.provider_type.is_some() {
+            return Ok(());
+        }
+        let message = format!("{action} requires provider_id or provider_type");
+        Err(OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::InvalidRequest,
+            message.clone(),
+            vec![diagnostic_error(
+                "missing_provider_selector",
+                "/provider_id",
+                "runner.operator.missing_provider_selector",
+                message,
+                Some(op_id),
+                None,
+                runtime.digest(),
+                None,
+                locale,
+            )],
+        ))
+    }
+}
+
+/// Refuse a request naming a tenant other than the one routing resolved.
+fn check_request_tenant(
+    runtime: &TenantRuntime,
+    request_tenant: Option<&str>,
+    op_id: &str,
+    locale: &str,
+) -> Result<(), OperatorResponse> {
+    let Some(request_tenant) = request_tenant else {
+        return Ok(());
+    };
+    if request_tenant == runtime.tenant() {
+        return Ok(());
+    }
+    let message = format!(
+        "tenant mismatch: routing resolved `{}` but request wants `{request_tenant}`",
+        runtime.tenant(),
+    );
+    Err(OperatorResponse::error_with_diagnostics(
+        OperatorErrorCode::TenantNotAllowed,
+        message.clone(),
+        vec![diagnostic_error(
+            "tenant_mismatch",
+            "/tenant_id",
+            "runner.operator.tenant_mismatch",
+            message,
+            Some(op_id),
+            None,
+            runtime.digest(),
+            None,
+            locale,
+        )],
+    ))
+}
+
+/// Look up the binding of `op_id`, counting the attempt and any failure in the operator
+/// metrics.
+fn resolve_binding<'a>(
+    runtime: &'a TenantRuntime,
+    selector: ProviderSelector<'_>,
+    op_id: &str,
+    locale: &str,
+) -> Result<&'a OperatorBinding, OperatorResponse> {
+    let ProviderSelector {
+        provider_id,
+        provider_type,
+    } = selector;
+    runtime
+        .operator_metrics()
+        .resolve_attempts
+        .fetch_add(1, Ordering::Relaxed);
+    let err = match runtime
+        .operator_registry()
+        .resolve(provider_id, provider_type, op_id)
+    {
+        Ok(binding) => return Ok(binding),
+        Err(err) => err,
+    };
+    let (code, diagnostic_code, text) = match err {
+        OperatorResolveError::ProviderNotFound => {
+            let label = provider_id.or(provider_type).unwrap_or("unknown");
+            (
+                OperatorErrorCode::ProviderNotFound,
+                "provider_not_found",
+                I18nText::new(
+                    "runner.operator.provider_not_found",
+                    format!("provider `{label}` not registered"),
+                ),
+            )
+        }
+        OperatorResolveError::OpNotFound => {
+            let label = provider_id.or(provider_type).unwrap_or("unknown provider");
+            (
+                OperatorErrorCode::OpNotFound,
+                "op_not_found",
+                I18nText::new(
+                    "runner.operator.op_not_found",
+                    "op `{op}` not found for provider `{provider}`",
+                )
+                .with_arg("op", op_id)
+                .with_arg("provider", label),
+            )
+        }
+    };
+    runtime
+        .operator_metrics()
+        .resolve_errors
+        .fetch_add(1, Ordering::Relaxed);
+    let message = text.fallback_message();
+    let diagnostic = text_diagnostic(
+        diagnostic_code,
+        "/op_id",
+        text,
+        Some(op_id),
+        binding_component_ref_hint(provider_id, provider_type),
+        runtime.digest(),
+        None,
+        locale,
+    );
+    Err(OperatorResponse::error_with_diagnostics(
+        code,
+        message,
+        vec![diagnostic],
+    ))
+}
+
+/// Refuse a binding the tenant's operator policy does not allow; `provider_id` is the
+/// one the request selected the provider by.
+fn check_operator_policy(
+    runtime: &TenantRuntime,
+    provider_id: Option<&str>,
+    binding: &OperatorBinding,
+    locale: &str,
+) -> Result<(), OperatorResponse> {
+    let policy = &runtime.config().operator_policy;
+    if !policy.allows_provider(provider_id, binding.provider_type.as_str()) {
+        return Err(OperatorResponse::error(
+            OperatorErrorCode::PolicyDenied,
+            format!(
+                "provider `{}` not allowed for tenant {}",
+                binding
+                    .provider_id
+                    .as_deref()
+                    .unwrap_or(&binding.provider_type),
+                runtime.config().tenant
+            ),
+        ));
+    }
+    if !policy.allows_op(provider_id, binding.provider_type.as_str(), &binding.op_id) {
+        return Err(OperatorResponse::error(
+            OperatorErrorCode::PolicyDenied,
+            format!(
+                "op `{}` is not permitted for provider `{}` on tenant {}",
+                binding.op_id,
+                binding
+                    .provider_id
+                    .as_deref()
+                    .unwrap_or(&binding.provider_type),
+                runtime.config().tenant
+            ),
+        ));
+    }
+    if let Some(capability) = policy.refused_capability(&binding.capabilities) {
+        let text = I18nText::new(
+            "runner.operator.capability_denied",
+            "op `{op}` declares capability `{capability}`, which tenant {tenant} does not permit",
+        )
+        .with_arg("op", binding.op_id.as_str())
+        .with_arg("capability", capability)
+        .with_arg("tenant", runtime.config().tenant.as_str());
+        return Err(OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::PolicyDenied,
+            text.fallback_message(),
+            vec![text_diagnostic(
+                "capability_denied",
+                "/capabilities",
+                text,
+                Some(binding.op_id.as_str()),
+                Some(binding.runtime.component_ref.as_str()),
+                None,
+                None,
+                locale,
+            )],
+        ));
+    }
+    Ok(())
+}
+
+/// Take a token from the bucket of the binding's op, see [`crate::runner::rate_limit`].
+fn check_rate_limit(
+    runtime: &TenantRuntime,
+    binding: &OperatorBinding,
+    locale: &str,
+) -> Result<(), OperatorResponse> {
+    let limits = &runtime.config().rate_limits.operators;
+    let Err(limited) =
+        runtime
+            .operator_rate_limits()
+            .admit(limits, &binding.provider_type, &binding.op_id)
+    else {
+        return Ok(());
+    };
+    runtime
+        .operator_metrics()
+        .throttled
+        .fetch_add(1, Ordering::Relaxed);
+    Err(rate_limited_response(&limited, locale))
+}
+
+fn rate_limited_response(limited: &OperatorRateLimited, locale: &str) -> OperatorResponse {
+    let retry_after_ms = limited.retry_after.as_millis().max(1);
+    let text = I18nText::new(
+        "runner.operator.rate_limited",
+        "rate limit of op `{op}` of `{provider}` exceeded; retry in {retry_after_ms} ms",
+    )
+    .with_arg("op", limited.op_id.as_str())
+    .with_arg("provider", limited.provider_type.as_str())
+    .with_arg("retry_after_ms", retry_after_ms.to_string());
+    let mut response = OperatorResponse::error_with_diagnostics(
+        OperatorErrorCode::RateLimited,
+        limited.to_string(),
+        vec![text_diagnostic(
+            "rate_limited",
+            "/op_id",
+            text,
+            Some(limited.op_id.as_str()),
+            None,
+            None,
+            None,
+            locale,
+        )],
+    );
+    if let Some(error) = &mut response.error {
+        error.retry_after = Some(limited.retry_after);
+    }
+    response
+}
+
+/// An op's component with its contract, as invoke and describe load it.
+struct OperatorContract {
+    pack: Arc<PackRuntime>,
+    resolved_digest: String,
+    /// Operation the component is invoked with; v0.6 components may map `op_id`.
+    selected_operation: String,
+    input_schema: Value,
+    output_schema: Value,
+    config_schema: Value,
+    snapshot: Arc<ContractSnapshot>,
+}
+
+/// Resolve the component of `binding`, introspect its contract and take its hashes from
+/// the contract cache, filling the cache on a miss. Records the component digest on
+/// `span`.
+fn load_contract(
+    runtime: &TenantRuntime,
+    binding: &OperatorBinding,
+    op_id: &str,
+    validation_options: ExecutionValidationOptions,
+    locale: &str,
+    span: &Span,
+) -> Result<OperatorContract, OperatorResponse> {
+    let component_ref = &binding.runtime.component_ref;
+    let resolved = match runtime.resolve_component(component_ref, binding.pack_preference()) {
+        Ok(resolved) => resolved,
+        Err(err) => {
+            return Err(OperatorResponse::error(
+                OperatorErrorCode::ComponentLoad,
+                err.to_string(),
+            ));
+        }
+    };
+    let pack = resolved.pack;
+    let resolved_digest = if resolved.digest == "unknown" && resolved.fallback_from.is_none() {
+        binding
+            .pack_digest
+            .clone()
+            .unwrap_or_else(|| resolved.digest.clone())
+    } else {
+        resolved.digest.clone()
+    };
+    record_bounded(span, SPAN_COMPONENT_DIGEST, &resolved_digest);
+    let introspected_contract =
+        match introspect_component_contract(pack.as_ref(), component_ref.as_str(), op_id) {
+            Ok(value) => value,
+            Err(err) => {
+                let message = format!("failed to introspect component contract: {err}");
+                return Err(OperatorResponse::error_with_diagnostics(
+                    OperatorErrorCode::TypeMismatch,
+                    message.clone(),
+                    vec![diagnostic_error(
+                        "contract_introspection_failed",
+                        "/operation",
+                        "runner.operator.contract_introspection_failed",
+                        message,
+                        Some(op_id),
+                        Some(component_ref.as_str()),
+                        Some(resolved_digest.as_str()),
+                        None,
+                        locale,
+                    )],
+                ));
+            }
+        };
+    let invoke_op_id = introspected_contract
+        .as_ref()
+        .map(|contract| contract.selected_operation.clone())
+        .unwrap_or_else(|| op_id.to_string());
+    let loaded_config_schema = introspected_contract
+        .as_ref()
+        .map(|contract| contract.config_schema.clone())
+        .filter(|value| !value.is_null())
+        .or_else(|| {
+            binding
+                .config_schema_ref
+                .as_deref()
+                .and_then(|schema_ref| pack.load_schema_json(schema_ref).ok().flatten())
+        })
+        .unwrap_or(Value::Null);
+    let loaded_output_schema = introspected_contract
+        .as_ref()
+        .map(|contract| contract.output_schema.clone())
+        .filter(|value| !value.is_null())
+        .or_else(|| {
+            derive_output_schema_ref(binding.config_schema_ref.as_deref())
+                .and_then(|schema_ref| pack.load_schema_json(&schema_ref).ok().flatten())
+        })
+        .unwrap_or(Value::Null);
+    let loaded_input_schema = introspected_contract
+        .as_ref()
+        .map(|contract| contract.input_schema.clone())
+        .filter(|value| !value.is_null())
+        .or_else(|| loaded_config_schema.is_null().then_some(Value::Null))
+        .or_else(|| Some(loaded_config_schema.clone()))
+        .unwrap_or(Value::Null);
+    let loaded_config_schema = binding
+        .config_schema_ref
+        .as_deref()
+        .and_then(|schema_ref| pack.load_schema_json(schema_ref).ok().flatten())
+        .unwrap_or_else(|| loaded_config_schema.clone());
+    let contract_key = format!(
+        "{}::{component_ref}::{op_id}::validate_output={}::strict={}",
+        resolved_digest, validation_options.validate_output, validation_options.strict
+    );
+    let snapshot = if let Some(snapshot) = runtime.contract_cache().get(&contract_key) {
+        snapshot
+    } else {
+        let (describe_hash, schema_hash) = introspected_contract
+            .as_ref()
+            .map(|contract| (contract.describe_hash.clone(), contract.schema_hash.clone()))
+            .unwrap_or_else(|| {
+                compute_contract_hashes(
+                    &resolved_digest,
+                    component_ref,
+                    &invoke_op_id,
+                    &binding.runtime.world,
+                    &binding.runtime.export,
+                    &loaded_input_schema,
+                    &loaded_output_schema,
+                    &loaded_config_schema,
+                    binding.state_schema_ref.as_deref(),
+                    &binding.pack_ref,
+                )
+            });
+        let mut snapshot = ContractSnapshot::new(
+            resolved_digest.clone(),
+            component_ref.clone(),
+            invoke_op_id.clone(),
+            validation_options.validate_output,
+            validation_options.strict,
+        );
+        snapshot.describe_hash = Some(describe_hash);
+        snapshot.schema_hash = Some(schema_hash);
+        let snapshot = Arc::new(snapshot);
+        runtime
+            .contract_cache()
+            .insert(contract_key, Arc::clone(&snapshot));
+        snapshot
     };
-    drop(_encode_guard);
+    Ok(OperatorContract {
+        pack,
+        resolved_digest,
+        selected_operation: invoke_op_id,
+        input_schema: loaded_input_schema,
+        output_schema: loaded_output_schema,
+        config_schema: loaded_config_schema,
+        snapshot,
+    })
+}
 
-    OperatorResponse::ok(output_bytes)
+fn record_stdio(span: &Span) {
+    let output = stdio::captured();
+    if !output.stdout.is_empty() {
+        span.record("stdout", output.stdout.as_str());
+    }
+    if !output.stderr.is_empty() {
+        span.record("stderr", output.stderr.as_str());
+    }
 }
 
 fn binding_component_ref_hint<'a>(
@@ -1016,27 +2063,217 @@ pub async fn invoke_operator_cbor(
 }
 
 /// Axum handler stub for `/operator/op/invoke`.
+///
+/// The invocation runs on its own task; if the client disconnects, dropping this
+/// handler cancels it instead of leaving the component running unobserved. A
+/// `RATE_LIMITED` response is sent with status 429 and `Retry-After`, an
+/// `IDEMPOTENCY_CONFLICT` one with 409.
 pub async fn invoke(
     TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
     _headers: HeaderMap,
     body: Body,
 ) -> Result<Response<Body>, Response<Body>> {
-    let bytes = match to_bytes(body, usize::MAX).await {
-        Ok(bytes) => bytes,
-        Err(err) => {
-            return Err(bad_request(format!("failed to read body: {err}")));
+    let request: OperatorRequest = read_request(body).await?;
+    let cancel = InvocationCancel::new();
+    let disconnect = cancel.disconnect_guard();
+    let invocation = tokio::spawn(cancel::scope(cancel, async move {
+        invoke_operator(&runtime, request).await
+    }));
+    let response = invocation.await.unwrap_or_else(invocation_task_failed);
+    disconnect.disarm();
+    let retry_after = response.retry_after();
+    let conflict = response
+        .error
+        .as_ref()
+        .is_some_and(|error| matches!(error.code, OperatorErrorCode::IdempotencyConflict));
+    let mut reply = build_cbor_response(response)?;
+    if conflict {
+        *reply.status_mut() = StatusCode::CONFLICT;
+    }
+    if let Some(retry_after) = retry_after {
+        let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        *reply.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+        reply
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(secs));
+    }
+    Ok(reply)
+}
+
+/// One item of the `/operator/op/invoke/stream` CBOR sequence: any number of events,
+/// then exactly one response.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorStreamFrame {
+    Event(StreamEvent),
+    Response(OperatorResponse),
+}
+
+/// Checks each `data` event against the op's output schema before passing events on.
+/// A failing event reaches `inner` as an `error` event, and its issues are kept for the
+/// final response.
+struct OutputCheckingObserver {
+    inner: Arc<dyn StreamObserver>,
+    schema: Value,
+    strict: bool,
+    checked: Mutex<CheckedEvents>,
+}
+
+#[derive(Default)]
+struct CheckedEvents {
+    seen: usize,
+    failures: Vec<(usize, Vec<SchemaValidationIssue>)>,
+}
+
+impl OutputCheckingObserver {
+    fn new(inner: Arc<dyn StreamObserver>, schema: Value, strict: bool) -> Self {
+        Self {
+            inner,
+            schema,
+            strict,
+            checked: Mutex::new(CheckedEvents::default()),
+        }
+    }
+
+    /// Schema issues per failing event, keyed by the event's position in the stream.
+    fn take_failures(&self) -> Vec<(usize, Vec<SchemaValidationIssue>)> {
+        std::mem::take(&mut self.checked.lock().failures)
+    }
+}
+
+impl StreamObserver for OutputCheckingObserver {
+    fn on_event(&self, event: &StreamEvent) {
+        let mut checked = self.checked.lock();
+        let index = checked.seen;
+        checked.seen += 1;
+        if let StreamEvent::Data(data) = event {
+            let output = data
+                .as_object()
+                .and_then(|obj| obj.get("output"))
+                .unwrap_or(data);
+            let issues = validate_json_instance(&self.schema, output, self.strict);
+            if !issues.is_empty() {
+                checked.failures.push((index, issues));
+                drop(checked);
+                self.inner.on_event(&StreamEvent::Error(format!(
+                    "data event {index} does not match the output schema"
+                )));
+                return;
+            }
         }
+        drop(checked);
+        self.inner.on_event(event);
+    }
+}
+
+/// Axum handler for `/operator/op/invoke/stream` (also served as
+/// `/operator/op/invoke-stream`). Events travel through a bounded
+/// channel (see [`ChannelStreamObserver`] for the drop policy); the final response
+/// frame is always sent. Dropping the response body before that frame (the client
+/// went away) cancels the invocation.
+pub async fn invoke_stream(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    _headers: HeaderMap,
+    body: Body,
+) -> Result<Response<Body>, Response<Body>> {
+    let request: OperatorRequest = read_request(body).await?;
+    let (observer, events) = ChannelStreamObserver::channel(DEFAULT_STREAM_CHANNEL_CAPACITY);
+    let cancel = InvocationCancel::new();
+    let disconnect = cancel.disconnect_guard();
+    let invocation = tokio::spawn(cancel::scope(cancel, async move {
+        invoke_operator_streaming(&runtime, request, observer).await
+    }));
+    let state = Some((events, invocation, disconnect));
+    let frames = futures::stream::unfold(state, |state| async move {
+        let (mut events, invocation, disconnect) = state?;
+        if let Some(event) = events.recv().await {
+            let frame = OperatorStreamFrame::Event(event);
+            return Some((encode_frame(&frame), Some((events, invocation, disconnect))));
+        }
+        let response = invocation.await.unwrap_or_else(invocation_task_failed);
+        disconnect.disarm();
+        Some((encode_frame(&OperatorStreamFrame::Response(response)), None))
+    });
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", CONTENT_TYPE_CBOR_SEQ)
+        .body(Body::from_stream(frames))
+        .expect("building CBOR sequence response must succeed"))
+}
+
+/// Axum handler for `/operator/op/describe`.
+#[allow(clippy::result_large_err)]
+pub async fn describe(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    _headers: HeaderMap,
+    body: Body,
+) -> Result<Response<Body>, Response<Body>> {
+    let request: OperatorDescribeRequest = read_request(body).await?;
+    let response = describe_operator(&runtime, &request);
+    match response.to_cbor() {
+        Ok(bytes) => Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", CONTENT_TYPE_CBOR)
+            .body(Body::from(bytes))
+            .expect("building CBOR response must succeed")),
+        Err(err) => Err(bad_request(format!(
+            "failed to serialize response CBOR: {err}"
+        ))),
+    }
+}
+
+/// Axum handler for `GET /operator/ops`: the ops the tenant's operator policy lets it
+/// invoke, as CBOR when `Accept` asks for `application/cbor` and as JSON otherwise.
+#[allow(clippy::result_large_err)]
+pub async fn list_ops(
+    TenantRuntimeHandle { runtime, .. }: TenantRuntimeHandle,
+    headers: HeaderMap,
+) -> Result<Response<Body>, Response<Body>> {
+    let ops = runtime
+        .operator_registry()
+        .list_operations(&runtime.config().operator_policy);
+    let wants_cbor = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains(CONTENT_TYPE_CBOR));
+    let (content_type, body) = if wants_cbor {
+        let bytes = serde_cbor::to_vec(&ops)
+            .map_err(|err| bad_request(format!("failed to serialize ops CBOR: {err}")))?;
+        (CONTENT_TYPE_CBOR, bytes)
+    } else {
+        let bytes = serde_json::to_vec(&ops)
+            .map_err(|err| bad_request(format!("failed to serialize ops JSON: {err}")))?;
+        ("application/json", bytes)
     };
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", content_type)
+        .body(Body::from(body))
+        .expect("building ops response must succeed"))
+}
+
+fn invocation_task_failed(err: tokio::task::JoinError) -> OperatorResponse {
+    OperatorResponse::error(
+        OperatorErrorCode::HostFailure,
+        format!("operator invocation task failed: {err}"),
+    )
+}
 
-    let request = match OperatorRequest::from_cbor(&bytes) {
-        Ok(request) => request,
+fn encode_frame(frame: &OperatorStreamFrame) -> Result<Vec<u8>, serde_cbor::Error> {
+    serde_cbor::ser::to_vec_packed(frame)
+}
+
+#[allow(clippy::result_large_err)]
+async fn read_request<T: DeserializeOwned>(body: Body) -> Result<T, Response<Body>> {
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
         Err(err) => {
-            return Err(bad_request(format!("failed to decode request CBOR: {err}")));
+            return Err(bad_request(format!("failed to read body: {err}")));
         }
     };
 
-    let response = invoke_operator(&runtime, request).await;
-    build_cbor_response(response)
+    serde_cbor::from_slice(&bytes)
+        .map_err(|err| bad_request(format!("failed to decode request CBOR: {err}")))
 }
 
 fn bad_request(message: String) -> Response<Body> {
@@ -1069,11 +2306,231 @@ fn decode_request_payload(bytes: &[u8]) -> Result<Value, serde_cbor::Error> {
     serde_cbor::from_slice(bytes)
 }
 
+/// Map an invoke error to a response; store limit hits get their own code and a
+/// diagnostic pointing at the exceeded `wasm_limits` field (or the `wasm_engine.pooling`
+/// block for pool limits), state writes rejected by the tenant's quota report
+/// `POLICY_DENIED`, epoch interrupts report whether the CPU budget or the
+/// request timeout cut the guest off, invocations the host stopped waiting for at the
+/// request timeout report `invoke_timeout`, components trapped after ignoring an abort or
+/// client disconnect report `CANCELLED`, and other traps report `INVOKE_TRAP` with the
+/// trap code, the export and the wasm frames. Traps and host failures also carry the
+/// component's captured stdio under `details.stdio`.
+fn invoke_failure(
+    kind: &str,
+    err: anyhow::Error,
+    op_id: &str,
+    component_ref: &str,
+    digest: &str,
+    locale: &str,
+) -> OperatorResponse {
+    if let Some(hit) = err.downcast_ref::<PoolExhausted>() {
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::ResourceExhausted,
+            format!("{kind} invoke failed: {hit}"),
+            vec![diagnostic_error(
+                "pool_exhausted",
+                "/wasm_engine/pooling",
+                "runner.operator.pool_exhausted",
+                hit.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(hit) = err.downcast_ref::<ResourceExhausted>() {
+        let message = format!("{kind} invoke failed: {hit}");
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::ResourceExhausted,
+            message,
+            vec![diagnostic_error(
+                "resource_exhausted",
+                &format!("/wasm_limits/{}", hit.limit),
+                "runner.operator.resource_exhausted",
+                hit.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(hit) = err.downcast_ref::<StreamUnsupported>() {
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::InvalidRequest,
+            format!("{kind} invoke failed: {hit}"),
+            vec![diagnostic_error(
+                "stream_unsupported",
+                "/op_id",
+                "runner.operator.stream_unsupported",
+                hit.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(hit) = err.downcast_ref::<StateQuotaExceeded>() {
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::PolicyDenied,
+            format!("{kind} invoke failed: {hit}"),
+            vec![diagnostic_error(
+                "state_quota_exceeded",
+                &format!("/state_store/quota/{}", hit.limit),
+                "runner.operator.state_quota_exceeded",
+                hit.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(cancelled) = err.downcast_ref::<Cancelled>() {
+        // Aborts are addressed by correlation id; a disconnect has no request field.
+        let path = match cancelled.reason {
+            CancelReason::AdminAbort => "/correlation_id",
+            _ => "",
+        };
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::Cancelled,
+            format!("{kind} invoke failed: {cancelled}"),
+            vec![diagnostic_error(
+                "cancelled",
+                path,
+                "runner.operator.cancelled",
+                cancelled.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(hit) = err.downcast_ref::<DeadlineExceeded>() {
+        let (code, path) = match hit.deadline.kind {
+            DeadlineKind::Cpu => ("cpu_deadline", "/wasm_limits/max_cpu_ms"),
+            DeadlineKind::WallClock => ("wall_clock_timeout", "/timeout"),
+        };
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::Timeout,
+            format!("{kind} invoke failed: {hit}"),
+            vec![diagnostic_error(
+                code,
+                path,
+                &format!("runner.operator.{code}"),
+                hit.to_string(),
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(hit) = err.downcast_ref::<InvokeTimeout>() {
+        let text = I18nText::new(
+            "runner.operator.invoke_timeout",
+            "invocation exceeded its {timeout_ms}ms timeout",
+        )
+        .with_arg("timeout_ms", hit.timeout.as_millis().to_string());
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::Timeout,
+            format!("{kind} invoke failed: {hit}"),
+            vec![text_diagnostic(
+                "invoke_timeout",
+                "/timeout",
+                text,
+                Some(op_id),
+                Some(component_ref),
+                Some(digest),
+                None,
+                locale,
+            )],
+        );
+    }
+    if let Some(trap) = err.downcast_ref::<TrapDetails>() {
+        tracing::warn!(
+            component_ref,
+            digest,
+            op_id,
+            export = %trap.export,
+            trap_code = %trap.code,
+            frames = ?trap.frames,
+            truncated_frames = trap.truncated_frames,
+            "component trapped"
+        );
+        let hint = trap.summary().map(|frames| {
+            I18nText::new("runner.operator.invoke_trap_hint", "trapped at {frames}")
+                .with_arg("frames", frames)
+        });
+        let mut diagnostic = diagnostic_error(
+            "invoke_trap",
+            "",
+            "runner.operator.invoke_trap",
+            trap.to_string(),
+            Some(op_id),
+            Some(component_ref),
+            Some(digest),
+            hint,
+            locale,
+        );
+        diagnostic.details = serde_json::to_value(trap).ok().map(|mut details| {
+            if let (Value::Object(map), Some(output)) = (&mut details, stdio_details()) {
+                map.insert("stdio".to_string(), output);
+            }
+            details
+        });
+        return OperatorResponse::error_with_diagnostics(
+            OperatorErrorCode::InvokeTrap,
+            format!("{kind} invoke failed: {trap}"),
+            vec![diagnostic],
+        );
+    }
+    let message = format!("{kind} invoke failed: {err}");
+    let Some(output) = stdio_details() else {
+        return OperatorResponse::error(OperatorErrorCode::HostFailure, message);
+    };
+    let mut diagnostic = diagnostic_error(
+        "host_failure",
+        "",
+        "runner.operator.host_failure",
+        message.clone(),
+        Some(op_id),
+        Some(component_ref),
+        Some(digest),
+        None,
+        locale,
+    );
+    diagnostic.details = Some(json!({ "stdio": output }));
+    OperatorResponse::error_with_diagnostics(
+        OperatorErrorCode::HostFailure,
+        message,
+        vec![diagnostic],
+    )
+}
+
+/// Captured component stdio for failure diagnostics, if the component wrote any.
+fn stdio_details() -> Option<Value> {
+    let output = stdio::captured();
+    if output.is_empty() {
+        return None;
+    }
+    serde_json::to_value(output).ok()
+}
+
 fn build_exec_ctx(
     request: &OperatorRequest,
     runtime: &TenantRuntime,
     operation_id: &str,
-) -> ComponentExecCtx {
+) -> anyhow::Result<ComponentExecCtx> {
     let deadline_unix_ms = request.timeout.and_then(|timeout_ms| {
         SystemTime::now()
             .checked_add(Duration::from_millis(timeout_ms))
@@ -1090,32 +2547,52 @@ fn build_exec_ctx(
         correlation_id: request.correlation_id.clone(),
         deadline_unix_ms,
         attempt: 1,
-        idempotency_key: request.correlation_id.clone(),
+        idempotency_key: request
+            .idempotency_key
+            .clone()
+            .or_else(|| request.correlation_id.clone()),
+        attributes: tenant_attributes(&runtime.config().attributes, &request.attributes)?,
+        impersonation: request.impersonation.clone(),
     };
 
-    ComponentExecCtx {
+    Ok(ComponentExecCtx {
         tenant: tenant_ctx,
         i18n_id: None,
         flow_id: format!("operator/{operation_id}"),
         node_id: None,
+    })
+}
+
+fn operator_requester(binding: &OperatorBinding, actor_id: Option<&str>) -> SecretRequester {
+    SecretRequester {
+        component_ref: Some(binding.runtime.component_ref.clone()),
+        op: Some(binding.op_id.clone()),
+        flow_id: Some(format!("operator/{}", binding.op_id)),
+        actor_id: actor_id.map(str::to_string),
     }
 }
 
-fn resolve_attachments(
+async fn resolve_attachments(
     payload: &OperatorPayload,
     runtime: &TenantRuntime,
+    binding: &OperatorBinding,
+    actor_id: Option<&str>,
 ) -> Result<Map<String, Value>, OperatorResponse> {
+    let requester = operator_requester(binding, actor_id);
     let mut attachments = Map::new();
     for attachment in &payload.attachments {
         if let Some(kind) = AttachmentKind::from_metadata(attachment.metadata.as_ref()) {
             match kind {
                 AttachmentKind::Secret { key, alias } => {
-                    let secret = runtime.get_secret(&key).map_err(|err| {
-                        OperatorResponse::error(
-                            OperatorErrorCode::PolicyDenied,
-                            format!("secret `{key}` access denied: {err}"),
-                        )
-                    })?;
+                    let secret = runtime
+                        .get_secret_for(&key, &requester)
+                        .await
+                        .map_err(|err| {
+                            OperatorResponse::error(
+                                OperatorErrorCode::PolicyDenied,
+                                format!("secret `{key}` access denied: {err}"),
+                            )
+                        })?;
                     attachments.insert(alias, Value::String(secret));
                 }
             }
@@ -1170,6 +2647,16 @@ mod tests {
     use super::*;
     use serde_json::{Map, Value, json};
 
+    #[test]
+    fn span_attributes_are_cut_at_a_char_boundary() {
+        assert_eq!(bounded_attribute("sha256:abc"), "sha256:abc");
+        let long = "é".repeat(MAX_SPAN_ATTRIBUTE_LEN);
+        let cut = bounded_attribute(&long);
+        assert!(cut.len() <= MAX_SPAN_ATTRIBUTE_LEN);
+        assert!(cut.ends_with('…'));
+        assert!(cut.trim_end_matches('…').chars().all(|ch| ch == 'é'));
+    }
+
     #[test]
     fn merge_input_with_attachments_preserves_map_fields() {
         let mut attachments = Map::new();
@@ -1235,10 +2722,14 @@ mod tests {
             message_key: "runner.operator.op_not_found".to_string(),
             fallback: "op `echo` not found".to_string(),
             message: "op `echo` not found".to_string(),
+            args: BTreeMap::from([("op".to_string(), "echo".to_string())]),
             hint: None,
+            hint_key: None,
+            hint_args: BTreeMap::new(),
             component_id: Some("provider.demo".to_string()),
             digest: Some("sha256:abc123".to_string()),
             operation_id: Some("echo".to_string()),
+            details: None,
         }];
 
         let response = OperatorResponse::error_with_diagnostics(
@@ -1256,6 +2747,167 @@ mod tests {
         assert_eq!(decoded, diagnostics);
     }
 
+    #[test]
+    fn diagnostics_resolve_through_registered_catalogs() {
+        crate::runner::i18n::register_catalog(
+            "nl",
+            crate::runner::i18n::Catalog::from([(
+                "runner.operator.op_not_found".to_string(),
+                "bewerking niet gevonden".into(),
+            )]),
+        );
+        let diagnostic = |locale| {
+            diagnostic_error(
+                "op_not_found",
+                "/op_id",
+                "runner.operator.op_not_found",
+                "op `echo` not found".to_string(),
+                Some("echo"),
+                Some("provider.demo"),
+                None,
+                None,
+                locale,
+            )
+        };
+        assert_eq!(diagnostic("nl").message, "bewerking niet gevonden");
+        assert_eq!(diagnostic("nl-BE").message, "bewerking niet gevonden");
+        assert_eq!(diagnostic("fr").message, "op `echo` not found");
+    }
+
+    #[test]
+    fn diagnostics_fill_message_placeholders() {
+        let text = I18nText::new("runner.operator.op_not_found", "op `{op}` missing")
+            .with_arg("op", "echo")
+            .with_arg("provider", "demo");
+        let diagnostic = |locale| {
+            text_diagnostic(
+                "op_not_found",
+                "/op_id",
+                text.clone(),
+                Some("echo"),
+                None,
+                None,
+                None,
+                locale,
+            )
+        };
+        let english = diagnostic("en");
+        assert_eq!(
+            english.message,
+            "operation `echo` not found for provider `demo`"
+        );
+        assert_eq!(english.fallback, "op `{op}` missing");
+        assert_eq!(english.args["provider"], "demo");
+        assert_eq!(diagnostic("fr").message, "op `echo` missing");
+        assert_eq!(text.fallback_message(), "op `echo` missing");
+    }
+
+    #[test]
+    fn hints_resolve_for_the_request_locale() {
+        crate::runner::i18n::register_catalog(
+            "sv",
+            crate::runner::i18n::Catalog::from([(
+                "runner.operator.invoke_trap_hint".to_string(),
+                "stoppade vid {frames}".into(),
+            )]),
+        );
+        let hint = I18nText::new("runner.operator.invoke_trap_hint", "at {frames}")
+            .with_arg("frames", "main <- run");
+        let diagnostic = |locale| {
+            diagnostic_error(
+                "invoke_trap",
+                "",
+                "runner.operator.invoke_trap",
+                "trap".into(),
+                None,
+                None,
+                None,
+                Some(hint.clone()),
+                locale,
+            )
+        };
+        let english = diagnostic("en");
+        assert_eq!(english.hint.as_deref(), Some("trapped at main <- run"));
+        assert_eq!(
+            english.hint_key.as_deref(),
+            Some("runner.operator.invoke_trap_hint")
+        );
+        assert_eq!(english.hint_args["frames"], "main <- run");
+        let swedish = diagnostic("sv-SE");
+        assert_eq!(swedish.hint.as_deref(), Some("stoppade vid main <- run"));
+        assert_eq!(swedish.hint_args, english.hint_args);
+
+        let json = serde_json::to_value(&swedish).unwrap();
+        assert_eq!(json["hint"], "stoppade vid main <- run");
+        assert_eq!(json["hint_args"]["frames"], "main <- run");
+        assert!(
+            serde_json::to_value(diagnostic_error(
+                "x",
+                "",
+                "k",
+                "f".into(),
+                None,
+                None,
+                None,
+                None,
+                "en"
+            ))
+            .unwrap()
+            .get("hint_key")
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn schema_failure_summary_is_pluralised_per_locale() {
+        use crate::runner::i18n::{Catalog, Message, PluralCategory, register_catalog};
+
+        register_catalog(
+            "pl",
+            Catalog::from([(
+                "runner.operator.schema_validation_summary".to_string(),
+                Message::Plural(BTreeMap::from([
+                    (PluralCategory::One, "{target}: {count} błąd".to_string()),
+                    (PluralCategory::Few, "{target}: {count} błędy".to_string()),
+                    (PluralCategory::Many, "{target}: {count} błędów".to_string()),
+                ])),
+            )]),
+        );
+        let summary = |count, locale| {
+            let diagnostics = (0..count)
+                .map(|_| {
+                    diagnostic_error(
+                        "schema_validation",
+                        "/input",
+                        "k",
+                        "f".into(),
+                        None,
+                        None,
+                        None,
+                        None,
+                        "en",
+                    )
+                })
+                .collect::<Vec<_>>();
+            schema_failure_summary("input", &diagnostics, locale)
+        };
+        assert_eq!(
+            summary(1, "en"),
+            "input failed schema validation: 1 issue found"
+        );
+        assert_eq!(
+            summary(2, "en"),
+            "input failed schema validation: 2 issues found"
+        );
+        assert_eq!(
+            summary(5, "en"),
+            "input failed schema validation: 5 issues found"
+        );
+        assert_eq!(summary(1, "pl-PL"), "input: 1 błąd");
+        assert_eq!(summary(2, "pl-PL"), "input: 2 błędy");
+        assert_eq!(summary(5, "pl-PL"), "input: 5 błędów");
+    }
+
     #[test]
     fn validation_options_default_to_strict_with_output_validation() {
         let options = validation_options_from_flags(&[]);
@@ -0,0 +1,222 @@
+//! Live progress of flow runs.
+//!
+//! Callers that want to watch a run install a [`ProgressObserver`] with [`scope`]; the
+//! pack flow adapter picks it up with [`current`] and reports every node to it next to
+//! the trace recorder. Events go through an unbounded channel so that none is lost and
+//! their order is kept; a run only produces a handful per node.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::engine::RunFlowResult;
+use crate::runner::engine::{ExecutionObserver, NodeEvent};
+use crate::validate::ValidationIssue;
+
+tokio::task_local! {
+    static CURRENT: Arc<dyn ExecutionObserver>;
+}
+
+/// Run `future` with `observer` watching every flow it runs.
+pub async fn scope<F: Future>(observer: Arc<dyn ExecutionObserver>, future: F) -> F::Output {
+    CURRENT.scope(observer, future).await
+}
+
+/// Observer of the enclosing [`scope`], if any.
+pub fn current() -> Option<Arc<dyn ExecutionObserver>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+/// One step of a flow run, as streamed to clients.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum FlowProgressEvent {
+    FlowStarted {
+        pack_id: String,
+        flow_id: String,
+    },
+    NodeStarted {
+        flow_id: String,
+        node_id: String,
+        component: String,
+    },
+    /// `error` is set when the node failed; the run then ends with `flow-error`.
+    NodeFinished {
+        flow_id: String,
+        node_id: String,
+        component: String,
+        duration_ms: u64,
+        output_bytes: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Egress {
+        flow_id: String,
+        node_id: String,
+        payload: Value,
+    },
+    FlowFinished {
+        result: RunFlowResult,
+    },
+    FlowError {
+        status: u16,
+        error: String,
+    },
+}
+
+impl FlowProgressEvent {
+    /// The SSE event name, which is also the `event` field of the payload.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FlowProgressEvent::FlowStarted { .. } => "flow-started",
+            FlowProgressEvent::NodeStarted { .. } => "node-started",
+            FlowProgressEvent::NodeFinished { .. } => "node-finished",
+            FlowProgressEvent::Egress { .. } => "egress",
+            FlowProgressEvent::FlowFinished { .. } => "flow-finished",
+            FlowProgressEvent::FlowError { .. } => "flow-error",
+        }
+    }
+}
+
+/// Turns node callbacks into [`FlowProgressEvent`]s on a channel.
+pub struct ProgressObserver {
+    tx: mpsc::UnboundedSender<FlowProgressEvent>,
+    started: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl ProgressObserver {
+    pub fn channel() -> (Arc<Self>, mpsc::UnboundedReceiver<FlowProgressEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let observer = Arc::new(Self {
+            tx,
+            started: Mutex::new(HashMap::new()),
+        });
+        (observer, rx)
+    }
+
+    fn send(&self, event: FlowProgressEvent) {
+        // The receiver is gone once the client disconnected; the run is being cancelled.
+        let _ = self.tx.send(event);
+    }
+
+    fn finished(&self, event: &NodeEvent<'_>, output_bytes: usize, error: Option<String>) {
+        let key = (event.context.flow_id.to_string(), event.node_id.to_string());
+        let duration_ms = self
+            .started
+            .lock()
+            .remove(&key)
+            .map(|started| started.elapsed().as_millis() as u64)
+            .unwrap_or(0);
+        self.send(FlowProgressEvent::NodeFinished {
+            flow_id: key.0,
+            node_id: key.1,
+            component: event.node.component.clone(),
+            duration_ms,
+            output_bytes,
+            error,
+        });
+    }
+}
+
+impl ExecutionObserver for ProgressObserver {
+    fn on_node_start(&self, event: &NodeEvent<'_>) {
+        let flow_id = event.context.flow_id.to_string();
+        let node_id = event.node_id.to_string();
+        self.started
+            .lock()
+            .insert((flow_id.clone(), node_id.clone()), Instant::now());
+        self.send(FlowProgressEvent::NodeStarted {
+            flow_id,
+            node_id,
+            component: event.node.component.clone(),
+        });
+    }
+
+    fn on_node_end(&self, event: &NodeEvent<'_>, output: &Value) {
+        let output_bytes = serde_json::to_vec(output)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0);
+        self.finished(event, output_bytes, None);
+    }
+
+    fn on_node_error(&self, event: &NodeEvent<'_>, error: &dyn StdError) {
+        self.finished(event, 0, Some(error.to_string()));
+    }
+
+    fn on_egress(&self, event: &NodeEvent<'_>, payload: &Value) {
+        self.send(FlowProgressEvent::Egress {
+            flow_id: event.context.flow_id.to_string(),
+            node_id: event.node_id.to_string(),
+            payload: payload.clone(),
+        });
+    }
+}
+
+/// Forwards every callback to each observer in turn.
+pub struct ObserverSet<'a> {
+    observers: Vec<&'a dyn ExecutionObserver>,
+}
+
+impl<'a> ObserverSet<'a> {
+    pub fn new(observers: impl IntoIterator<Item = &'a dyn ExecutionObserver>) -> Self {
+        Self {
+            observers: observers.into_iter().collect(),
+        }
+    }
+
+    /// `self` as a flow observer, or `None` when it would forward to nobody.
+    pub fn as_observer(&self) -> Option<&dyn ExecutionObserver> {
+        (!self.observers.is_empty()).then_some(self as &dyn ExecutionObserver)
+    }
+}
+
+impl ExecutionObserver for ObserverSet<'_> {
+    fn on_node_start(&self, event: &NodeEvent<'_>) {
+        for observer in &self.observers {
+            observer.on_node_start(event);
+        }
+    }
+
+    fn on_node_end(&self, event: &NodeEvent<'_>, output: &Value) {
+        for observer in &self.observers {
+            observer.on_node_end(event, output);
+        }
+    }
+
+    fn on_node_error(&self, event: &NodeEvent<'_>, error: &dyn StdError) {
+        for observer in &self.observers {
+            observer.on_node_error(event, error);
+        }
+    }
+
+    fn on_validation(&self, event: &NodeEvent<'_>, issues: &[ValidationIssue]) {
+        for observer in &self.observers {
+            observer.on_validation(event, issues);
+        }
+    }
+
+    fn on_egress(&self, event: &NodeEvent<'_>, payload: &Value) {
+        for observer in &self.observers {
+            observer.on_egress(event, payload);
+        }
+    }
+
+    fn on_node_retry(
+        &self,
+        event: &NodeEvent<'_>,
+        attempt: u32,
+        error: &dyn StdError,
+        delay: Duration,
+    ) {
+        for observer in &self.observers {
+            observer.on_node_retry(event, attempt, error, delay);
+        }
+    }
+}
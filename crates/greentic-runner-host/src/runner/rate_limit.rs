@@ -0,0 +1,158 @@
+//! Operator invoke limits from the `rate_limits.operators` bindings block.
+//!
+//! Every operator invocation takes a token from the bucket of its provider type and op
+//! once the op is resolved, before its component is loaded. An entry for
+//! `{provider_type}/{op_id}` wins over one for the provider type; ops with neither are
+//! not limited. A bucket refills at `per_second` up to `burst`. Without a token the
+//! request fails with [`OperatorRateLimited`], answered as `RATE_LIMITED` and, over
+//! HTTP, 429 with `Retry-After`.
+//!
+//! Every op has its own bucket, also when the limit is configured for its provider.
+//! Buckets belong to the tenant and carry over pack reloads; a reload that changes a
+//! limit keeps the bucket's level. Buckets that have refilled completely are dropped
+//! now and then, as a new bucket would start out the same.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+
+use crate::config::OperatorRateLimit;
+
+/// Refill rate used for limits of zero invocations per second.
+const MIN_RATE: f64 = 1e-3;
+/// How often buckets that are full again are dropped.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The operator buckets of one tenant, keyed by `{provider_type}/{op_id}`.
+pub struct OperatorRateLimits {
+    buckets: DashMap<String, Bucket>,
+    swept: Mutex<Instant>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    /// When the bucket is full again if no invocation takes a token.
+    full_at: Instant,
+}
+
+impl Default for OperatorRateLimits {
+    fn default() -> Self {
+        Self {
+            buckets: DashMap::new(),
+            swept: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl OperatorRateLimits {
+    /// Take a token for `op_id` of `provider_type` under the tenant's `limits`.
+    pub fn admit(
+        &self,
+        limits: &HashMap<String, OperatorRateLimit>,
+        provider_type: &str,
+        op_id: &str,
+    ) -> Result<(), OperatorRateLimited> {
+        let now = Instant::now();
+        self.sweep(now);
+        if limits.is_empty() {
+            return Ok(());
+        }
+        let key = format!("{provider_type}/{op_id}");
+        let Some(limit) = limits.get(&key).or_else(|| limits.get(provider_type)) else {
+            return Ok(());
+        };
+        let mut bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: capacity(limit),
+            refilled: now,
+            full_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.refilled = now;
+        bucket.tokens = (bucket.tokens + elapsed * rate(limit)).min(capacity(limit));
+        let admitted = bucket.tokens >= 1.0;
+        if admitted {
+            bucket.tokens -= 1.0;
+        }
+        let refill = (capacity(limit) - bucket.tokens) / rate(limit);
+        bucket.full_at = now + Duration::from_secs_f64(refill);
+        if admitted {
+            return Ok(());
+        }
+        Err(OperatorRateLimited {
+            provider_type: provider_type.to_string(),
+            op_id: op_id.to_string(),
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / rate(limit)),
+        })
+    }
+
+    /// Drop the buckets that are full again, at most once per [`SWEEP_INTERVAL`].
+    fn sweep(&self, now: Instant) {
+        {
+            let mut swept = self.swept.lock();
+            if now.saturating_duration_since(*swept) < SWEEP_INTERVAL {
+                return;
+            }
+            *swept = now;
+        }
+        self.buckets.retain(|_, bucket| bucket.full_at > now);
+    }
+}
+
+fn rate(limit: &OperatorRateLimit) -> f64 {
+    limit.per_second.max(MIN_RATE)
+}
+
+fn capacity(limit: &OperatorRateLimit) -> f64 {
+    limit
+        .burst
+        .map(f64::from)
+        .unwrap_or(limit.per_second.ceil())
+        .max(1.0)
+}
+
+/// An operator invocation refused by the rate limit of its op.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error(
+    "rate limit of op `{op_id}` of `{provider_type}` exceeded; retry in {} ms",
+    .retry_after.as_millis()
+)]
+pub struct OperatorRateLimited {
+    pub provider_type: String,
+    pub op_id: String,
+    /// When the bucket will have a token again.
+    pub retry_after: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refilled_buckets_are_dropped() {
+        let limits = OperatorRateLimits::default();
+        let config = HashMap::from([(
+            "messaging".to_string(),
+            OperatorRateLimit {
+                per_second: 1.0,
+                burst: Some(2),
+            },
+        )]);
+        limits.admit(&config, "messaging", "send").unwrap();
+        limits.admit(&config, "messaging", "send").unwrap();
+        assert!(limits.admit(&config, "messaging", "send").is_err());
+        limits.admit(&config, "messaging", "edit").unwrap();
+        assert_eq!(limits.buckets.len(), 2);
+
+        // `edit` is full again after a second, `send` only after two.
+        let start = *limits.swept.lock();
+        limits.sweep(start + SWEEP_INTERVAL);
+        assert_eq!(limits.buckets.len(), 0);
+
+        limits.admit(&config, "messaging", "send").unwrap();
+        limits.sweep(Instant::now() + Duration::from_secs(1));
+        assert_eq!(limits.buckets.len(), 1, "swept again before the interval");
+    }
+}
@@ -0,0 +1,501 @@
+//! Redelivery of ingress envelopes whose flow failed with a retryable error.
+//!
+//! With a `retry.redelivery` block in the bindings, `StateMachineRuntime::handle` does
+//! not fail such an envelope: it stores a [`RedeliveryRecord`] with the envelope, the
+//! deliveries made so far and when the next one is due, and answers with a
+//! `status: "redelivery_scheduled"` outcome. A worker per tenant delivers the envelope
+//! again when it is due, with exponential backoff, until a delivery succeeds, fails with
+//! an error that is not retryable, or `max_attempts` deliveries failed; the last case
+//! is recorded as a `redelivery_exhausted` audit event.
+//!
+//! Redeliveries keep the envelope and its `activity_id`: the activity's dedupe claim
+//! stays in progress meanwhile, so the provider's own redeliveries are answered as
+//! duplicates, and the response of the delivery that succeeds is the one recorded.
+//! Components see the delivery number as their `attempt`, which node retries count on
+//! from.
+//!
+//! Records live in the tenant's state store, behind its quota and encryption, so a
+//! restarted host picks up the redeliveries of the one before. Each record is a
+//! document of its own, found through an index of record ids; only scheduling and
+//! removing a record touch the index, and a host re-reads the index after adding to it,
+//! so a write from another host sharing the store does not drop the record. Hosts
+//! sharing a store may each make a due delivery.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use greentic_types::TenantCtx;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use rand::{RngExt, rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::config::RedeliveryConfig;
+use crate::engine::error::{GResult, RunnerError};
+use crate::engine::host::{SessionKey, StateHost};
+use crate::engine::runtime::IngressEnvelope;
+use crate::runtime::TenantRuntime;
+
+const REDELIVERY_PACK: &str = "_redelivery";
+const REDELIVERY_INDEX: &str = "index";
+/// Writes of the index tried before scheduling a record fails.
+const INDEX_WRITE_ATTEMPTS: usize = 5;
+/// Longest the worker goes without reading the stored records.
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+/// `status` of the value returned for an envelope stored for a redelivery.
+pub const STATUS_REDELIVERY_SCHEDULED: &str = "redelivery_scheduled";
+
+tokio::task_local! {
+    static ATTEMPT: u32;
+}
+
+static METRICS: Lazy<RedeliveryMetrics> = Lazy::new(RedeliveryMetrics::default);
+
+/// Redelivery counts of every tenant in the process.
+pub fn redelivery_metrics() -> &'static RedeliveryMetrics {
+    &METRICS
+}
+
+/// Run the flow runs of `future` as delivery `attempt`.
+pub async fn scope<F: Future>(attempt: u32, future: F) -> F::Output {
+    ATTEMPT.scope(attempt, future).await
+}
+
+/// Delivery of the enclosing [`scope`]; the first outside one.
+pub fn current_attempt() -> u32 {
+    ATTEMPT.try_with(|attempt| *attempt).unwrap_or(1)
+}
+
+/// Whether a delivery that failed with `err` may succeed when made again.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<RunnerError>()
+        .is_some_and(RunnerError::is_transient)
+}
+
+/// An envelope waiting for its next delivery.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RedeliveryRecord {
+    pub id: String,
+    pub envelope: IngressEnvelope,
+    /// Deliveries made so far.
+    pub attempt: u32,
+    pub next_attempt_unix_ms: u64,
+    /// Error of the last delivery.
+    pub error: String,
+}
+
+impl RedeliveryRecord {
+    /// The `status: "redelivery_scheduled"` outcome handed back for the envelope.
+    pub fn outcome(&self) -> Value {
+        json!({
+            "status": STATUS_REDELIVERY_SCHEDULED,
+            "redelivery_id": self.id,
+            "attempt": self.attempt,
+            "next_attempt_unix_ms": self.next_attempt_unix_ms,
+        })
+    }
+}
+
+/// The stored redeliveries of one tenant and the worker making them; shared by every
+/// runtime built for the tenant, like its audit log.
+pub struct RedeliveryQueue {
+    state: Arc<dyn StateHost>,
+    tenant: TenantCtx,
+    index: SessionKey,
+    /// Serialises updates of the index.
+    update: tokio::sync::Mutex<()>,
+    wake: Notify,
+    /// Runtime the worker delivers on; swapped when a reload hands the queue over.
+    target: Mutex<Weak<TenantRuntime>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl RedeliveryQueue {
+    pub fn new(tenant: &TenantCtx, state: Arc<dyn StateHost>) -> Self {
+        Self {
+            state,
+            tenant: tenant.clone(),
+            index: SessionKey::new(tenant, REDELIVERY_PACK, REDELIVERY_INDEX, None),
+            update: tokio::sync::Mutex::new(()),
+            wake: Notify::new(),
+            target: Mutex::new(Weak::new()),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Store `envelope`, whose delivery `attempt` failed with `error`, for the next one.
+    pub async fn schedule(
+        &self,
+        envelope: &IngressEnvelope,
+        attempt: u32,
+        error: &anyhow::Error,
+        policy: &RedeliveryConfig,
+    ) -> GResult<RedeliveryRecord> {
+        let record = RedeliveryRecord {
+            id: format!("redelivery-{:032x}", rng().random::<u128>()),
+            envelope: envelope.clone(),
+            attempt,
+            next_attempt_unix_ms: due_after(policy.delay(attempt)),
+            error: format!("{error:#}"),
+        };
+        self.store(&record).await?;
+        self.index_insert(&record.id).await?;
+        redelivery_metrics().record(&envelope.tenant, RedeliveryOutcome::Scheduled);
+        tracing::warn!(
+            flow_id = %envelope.flow_id,
+            activity_id = ?envelope.activity_id,
+            attempt,
+            next_attempt_unix_ms = record.next_attempt_unix_ms,
+            error = %record.error,
+            "flow failed; redelivery scheduled"
+        );
+        self.wake.notify_one();
+        Ok(record)
+    }
+
+    /// Stored records, the next due first.
+    pub async fn pending(&self) -> GResult<Vec<RedeliveryRecord>> {
+        let ids = self.load_index().await?.into_iter().collect::<Vec<_>>();
+        let keys = ids.iter().map(|id| self.record_key(id)).collect::<Vec<_>>();
+        let mut records = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        for (id, value) in ids.into_iter().zip(self.state.get_many(&keys).await?) {
+            match value {
+                Some(value) => records.push(decode_record(value)?),
+                None => missing.push(id),
+            }
+        }
+        if !missing.is_empty() {
+            // Removed by another host after it read the index.
+            self.index_remove(&missing).await?;
+        }
+        records.sort_by_key(|record| record.next_attempt_unix_ms);
+        Ok(records)
+    }
+
+    /// Deliver due records on `runtime` from now on, starting the worker if it is not
+    /// running.
+    pub fn serve(self: &Arc<Self>, runtime: &Arc<TenantRuntime>) {
+        *self.target.lock() = Arc::downgrade(runtime);
+        let mut worker = self.worker.lock();
+        if worker.as_ref().is_none_or(JoinHandle::is_finished) {
+            *worker = Some(tokio::spawn(run_worker(Arc::clone(self))));
+        }
+        self.wake.notify_one();
+    }
+
+    /// Stop the worker; stored records wait for the next [`Self::serve`].
+    pub fn stop(&self) {
+        if let Some(worker) = self.worker.lock().take() {
+            worker.abort();
+        }
+    }
+
+    /// Make the due deliveries; returns when the next record is due.
+    async fn redeliver_due(&self, runtime: &TenantRuntime) -> GResult<Option<u64>> {
+        let now = now_unix_ms();
+        for record in self.pending().await? {
+            if record.next_attempt_unix_ms > now {
+                break;
+            }
+            self.redeliver(runtime, record).await?;
+        }
+        Ok(self
+            .pending()
+            .await?
+            .first()
+            .map(|record| record.next_attempt_unix_ms))
+    }
+
+    async fn redeliver(
+        &self,
+        runtime: &TenantRuntime,
+        mut record: RedeliveryRecord,
+    ) -> GResult<()> {
+        let attempt = record.attempt + 1;
+        // A reload that drops `retry.redelivery` makes this delivery the last.
+        let max_attempts = runtime
+            .config()
+            .retry
+            .redelivery
+            .map_or(attempt, |policy| policy.max_attempts);
+        let tenant = runtime.tenant();
+        let flow_id = record.envelope.flow_id.clone();
+        let activity_id = record.envelope.activity_id.clone();
+        let result = runtime
+            .state_machine()
+            .redeliver(&record.envelope, attempt)
+            .await;
+        let err = match result {
+            Ok(_) => {
+                tracing::info!(
+                    flow_id = %flow_id,
+                    activity_id = ?activity_id,
+                    attempt,
+                    "redelivered flow completed"
+                );
+                self.remove(&record.id).await?;
+                redelivery_metrics().record(tenant, RedeliveryOutcome::Succeeded);
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+        let error = format!("{err:#}");
+        if is_retryable(&err) && attempt < max_attempts {
+            let policy = runtime.config().retry.redelivery.unwrap_or_default();
+            record.attempt = attempt;
+            record.next_attempt_unix_ms = due_after(policy.delay(attempt));
+            record.error = error;
+            tracing::warn!(
+                flow_id = %flow_id,
+                activity_id = ?activity_id,
+                attempt,
+                next_attempt_unix_ms = record.next_attempt_unix_ms,
+                error = %record.error,
+                "redelivered flow failed; redelivery scheduled"
+            );
+            self.store(&record).await?;
+            redelivery_metrics().record(tenant, RedeliveryOutcome::Scheduled);
+            return Ok(());
+        }
+        self.remove(&record.id).await?;
+        runtime
+            .state_machine()
+            .release_activity(&record.envelope)
+            .await;
+        if is_retryable(&err) {
+            tracing::error!(
+                flow_id = %flow_id,
+                activity_id = ?activity_id,
+                attempts = attempt,
+                error = %error,
+                "redeliveries exhausted"
+            );
+            runtime.audit().redelivery_exhausted(
+                tenant,
+                &flow_id,
+                activity_id.as_deref(),
+                attempt,
+                &error,
+            );
+            redelivery_metrics().record(tenant, RedeliveryOutcome::Exhausted);
+        } else {
+            tracing::error!(
+                flow_id = %flow_id,
+                activity_id = ?activity_id,
+                attempt,
+                error = %error,
+                "redelivered flow failed with an error that is not retryable"
+            );
+            redelivery_metrics().record(tenant, RedeliveryOutcome::Failed);
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> GResult<()> {
+        self.state.del(&self.record_key(id)).await?;
+        self.index_remove(&[id.to_string()]).await
+    }
+
+    fn record_key(&self, id: &str) -> SessionKey {
+        SessionKey::new(&self.tenant, REDELIVERY_PACK, &format!("record/{id}"), None)
+    }
+
+    async fn store(&self, record: &RedeliveryRecord) -> GResult<()> {
+        let value = serde_json::to_value(record).map_err(|err| RunnerError::Serialization {
+            reason: format!("failed to encode redelivery record: {err}"),
+        })?;
+        self.state
+            .set_json(&self.record_key(&record.id), value)
+            .await
+    }
+
+    /// Add `id` to the index, writing it again while a read-back lacks it.
+    async fn index_insert(&self, id: &str) -> GResult<()> {
+        let _guard = self.update.lock().await;
+        for _ in 0..INDEX_WRITE_ATTEMPTS {
+            let mut ids = self.load_index().await?;
+            if ids.contains(id) {
+                return Ok(());
+            }
+            ids.insert(id.to_string());
+            self.write_index(&ids).await?;
+        }
+        if self.load_index().await?.contains(id) {
+            return Ok(());
+        }
+        Err(RunnerError::State {
+            reason: format!("redelivery {id} could not be added to the index"),
+        })
+    }
+
+    async fn index_remove(&self, removed: &[String]) -> GResult<()> {
+        let _guard = self.update.lock().await;
+        let mut ids = self.load_index().await?;
+        let before = ids.len();
+        for id in removed {
+            ids.remove(id);
+        }
+        if ids.len() == before {
+            return Ok(());
+        }
+        self.write_index(&ids).await
+    }
+
+    async fn load_index(&self) -> GResult<BTreeSet<String>> {
+        match self.state.get_json(&self.index).await? {
+            Some(ids) => serde_json::from_value(ids).map_err(|err| RunnerError::Serialization {
+                reason: format!("invalid redelivery index: {err}"),
+            }),
+            None => Ok(BTreeSet::new()),
+        }
+    }
+
+    async fn write_index(&self, ids: &BTreeSet<String>) -> GResult<()> {
+        if ids.is_empty() {
+            return self.state.del(&self.index).await;
+        }
+        self.state.set_json(&self.index, json!(ids)).await
+    }
+}
+
+fn decode_record(value: Value) -> GResult<RedeliveryRecord> {
+    serde_json::from_value(value).map_err(|err| RunnerError::Serialization {
+        reason: format!("invalid redelivery record: {err}"),
+    })
+}
+
+async fn run_worker(queue: Arc<RedeliveryQueue>) {
+    loop {
+        let Some(runtime) = queue.target.lock().upgrade() else {
+            break;
+        };
+        let wait = match queue.redeliver_due(&runtime).await {
+            Ok(next) => next.map_or(IDLE_POLL, |next| {
+                Duration::from_millis(next.saturating_sub(now_unix_ms())).min(IDLE_POLL)
+            }),
+            Err(err) => {
+                tracing::warn!(tenant = %runtime.tenant(), error = %err, "redelivery failed");
+                IDLE_POLL
+            }
+        };
+        drop(runtime);
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = queue.wake.notified() => {}
+        }
+    }
+}
+
+/// What became of a delivery, as counted by [`RedeliveryMetrics`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RedeliveryOutcome {
+    /// A failed delivery was stored for another one.
+    Scheduled,
+    Succeeded,
+    /// The last delivery `max_attempts` allows failed.
+    Exhausted,
+    /// A redelivery failed with an error that is not retryable.
+    Failed,
+}
+
+impl RedeliveryOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::Succeeded => "succeeded",
+            Self::Exhausted => "exhausted",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RedeliveryMetrics {
+    counts: RwLock<BTreeMap<(String, RedeliveryOutcome), u64>>,
+}
+
+impl RedeliveryMetrics {
+    fn record(&self, tenant: &str, outcome: RedeliveryOutcome) {
+        *self
+            .counts
+            .write()
+            .entry((tenant.to_string(), outcome))
+            .or_default() += 1;
+    }
+
+    /// Counts by tenant and outcome.
+    pub fn snapshot(&self) -> Vec<(String, RedeliveryOutcome, u64)> {
+        self.counts
+            .read()
+            .iter()
+            .map(|((tenant, outcome), count)| (tenant.clone(), *outcome, *count))
+            .collect()
+    }
+
+    pub fn count(&self, tenant: &str, outcome: RedeliveryOutcome) -> u64 {
+        self.counts
+            .read()
+            .get(&(tenant.to_string(), outcome))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn due_after(delay: Duration) -> u64 {
+    now_unix_ms().saturating_add(delay.as_millis() as u64)
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::shims::InMemoryStateHost;
+    use greentic_types::{EnvId, TenantId};
+
+    fn envelope(flow_id: &str) -> IngressEnvelope {
+        serde_json::from_value(json!({ "tenant": "demo", "flow_id": flow_id })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn queues_sharing_a_store_keep_each_others_records() {
+        let tenant = TenantCtx::new(EnvId::new("local").unwrap(), TenantId::new("demo").unwrap());
+        let state: Arc<dyn StateHost> = Arc::new(InMemoryStateHost::new());
+        let first = RedeliveryQueue::new(&tenant, Arc::clone(&state));
+        let second = RedeliveryQueue::new(&tenant, state);
+        let policy = RedeliveryConfig::default();
+        let error = anyhow::anyhow!("unavailable");
+
+        let (a, b) = tokio::join!(
+            first.schedule(&envelope("a"), 1, &error, &policy),
+            second.schedule(&envelope("b"), 1, &error, &policy)
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let mut flows = first
+            .pending()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|record| record.envelope.flow_id)
+            .collect::<Vec<_>>();
+        flows.sort();
+        assert_eq!(flows, ["a", "b"]);
+
+        second.remove(&a.id).await.unwrap();
+        let pending = first.pending().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, b.id);
+    }
+}
@@ -1,12 +1,16 @@
 use jsonschema::{Draft, Validator};
 use serde_json::Value;
 
+use crate::runner::i18n::I18nText;
+use crate::runner::operator::FLAG_PERMISSIVE_SCHEMA;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SchemaValidationIssue {
     pub code: String,
     pub path: String,
     pub message_key: String,
     pub fallback: String,
+    pub hint: Option<I18nText>,
 }
 
 pub fn validate_json_instance(
@@ -24,6 +28,13 @@ pub fn validate_json_instance(
                 path,
                 message_key: "runner.schema.unsupported_constraint".to_string(),
                 fallback: "schema contains unsupported constraint".to_string(),
+                hint: Some(
+                    I18nText::new(
+                        "runner.schema.unsupported_constraint_hint",
+                        "pass the `{flag}` flag to skip unsupported constraints",
+                    )
+                    .with_arg("flag", FLAG_PERMISSIVE_SCHEMA),
+                ),
             });
         }
         return issues;
@@ -37,6 +48,7 @@ pub fn validate_json_instance(
                 path: "/".to_string(),
                 message_key: "runner.schema.invalid_schema".to_string(),
                 fallback: format!("invalid schema: {err}"),
+                hint: None,
             });
             return issues;
         }
@@ -53,6 +65,7 @@ pub fn validate_json_instance(
             },
             message_key: "runner.schema.validation_failed".to_string(),
             fallback: err.to_string(),
+            hint: None,
         });
     }
     issues
@@ -1,82 +1,841 @@
-use anyhow::{Result, anyhow};
-use handlebars::Handlebars;
+use std::borrow::Cow;
+use std::cmp::Ordering as CmpOrdering;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, anyhow, bail};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use handlebars::{
+    BlockContext, Context, Handlebars, Helper, HelperDef, RenderContext, RenderError,
+    RenderErrorReason, Renderable, ScopedJson, StringOutput, Template,
+};
+use lru::LruCache;
 use once_cell::sync::Lazy;
-use serde_json::{Map as JsonMap, Value};
+use parking_lot::{Mutex, RwLock};
+use serde_json::{Map as JsonMap, Value, json};
+
+use super::i18n::I18nText;
+use super::operator::{Diagnostic, DiagnosticSeverity};
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct TemplateOptions {
     pub allow_pointer: bool,
+    /// Render paths that do not resolve as null instead of failing; `??` fallbacks
+    /// still take precedence.
+    pub missing_as_null: bool,
+}
+
+/// Where a template lives, named in render errors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TemplateSource<'a> {
+    pub pack_id: &'a str,
+    pub flow_id: &'a str,
+    pub node_id: &'a str,
+    /// Node config field holding the template, e.g. `input`.
+    pub field: &'a str,
+}
+
+/// A template of a flow node failed to render.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    pub pack_id: String,
+    pub flow_id: String,
+    pub node_id: String,
+    /// Config field and pointer to the failing string, e.g. `input/user/id`.
+    pub field: String,
+    /// The failing template string.
+    pub template: String,
+    pub reason: String,
+    /// Top-level keys of the render context.
+    pub available_keys: Vec<String>,
+}
+
+impl TemplateError {
+    fn new(
+        source: &TemplateSource<'_>,
+        path: &str,
+        template: &str,
+        err: &anyhow::Error,
+        ctx: &Value,
+    ) -> Self {
+        Self {
+            pack_id: source.pack_id.to_string(),
+            flow_id: source.flow_id.to_string(),
+            node_id: source.node_id.to_string(),
+            field: format!("{}{path}", source.field),
+            template: template.to_string(),
+            reason: format!("{err:#}"),
+            available_keys: ctx
+                .as_object()
+                .map(|map| {
+                    map.keys()
+                        .filter(|key| *key != INDEX_KEY)
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The error as an operator diagnostic, its hint resolved for `locale`.
+    pub fn diagnostic(&self, locale: &str) -> Diagnostic {
+        let message = self.to_string();
+        let hint = (!self.available_keys.is_empty()).then(|| {
+            I18nText::new(
+                "runner.template.available_keys",
+                "available context keys: {keys}",
+            )
+            .with_arg("keys", self.available_keys.join(", "))
+        });
+        Diagnostic {
+            code: "TEMPLATE_RENDER_FAILED".to_string(),
+            path: format!("/{}", self.field),
+            severity: DiagnosticSeverity::Error,
+            message_key: "runner.template.render_failed".to_string(),
+            fallback: message.clone(),
+            message,
+            args: Default::default(),
+            hint: None,
+            hint_key: None,
+            hint_args: Default::default(),
+            component_id: None,
+            digest: None,
+            operation_id: None,
+            details: Some(json!({
+                "pack_id": self.pack_id,
+                "flow_id": self.flow_id,
+                "node_id": self.node_id,
+                "template": self.template,
+            })),
+        }
+        .with_hint(hint, locale)
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to render `{}` at `{}` of node `{}` in flow `{}` (pack `{}`): {}",
+            self.template, self.field, self.node_id, self.flow_id, self.pack_id, self.reason
+        )?;
+        if !self.available_keys.is_empty() {
+            write!(
+                f,
+                "; available context keys: {}",
+                self.available_keys.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Key of a structured iteration:
+/// `{"$map": {"over": "{{entry.items}}", "as": "item", "template": {...}}}`.
+const MAP_KEY: &str = "$map";
+
+/// Context key holding the position of the current `$map` element.
+const INDEX_KEY: &str = "@index";
+
+/// Helpers whose result keeps its JSON type when the whole template is one call.
+const TYPED_HELPERS: &[&str] = &["eq", "ne", "gt", "lt", "contains", "coalesce"];
+
+/// Internal helper serializing the value of a typed helper call; registered after the
+/// custom helpers so it cannot be replaced.
+const TYPED_VALUE_HELPER: &str = "__greentic_typed_value";
+
+/// Parsed mixed templates kept between renders.
+pub const TEMPLATE_CACHE_CAPACITY: usize = 1024;
+
+#[cfg(test)]
+thread_local! {
+    static PARSES: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+/// Engine behind [`render_template_value`], for callers without one of their own.
+static DEFAULT_ENGINE: Lazy<TemplateEngine> = Lazy::new(TemplateEngine::default);
+
+/// A custom helper, as registered on a [`TemplateEngine`].
+pub type BoxedHelper = Box<dyn HelperDef + Send + Sync + 'static>;
+
+/// Helper registry and parsed-template cache used to render flow templates.
+///
+/// Each tenant runtime owns one, so helpers registered for a tenant are not visible to
+/// the others; clones share the registry and the cache. Helpers can be added until the
+/// first render, after which the registry is frozen.
+#[derive(Clone)]
+pub struct TemplateEngine {
+    inner: Arc<EngineInner>,
+}
+
+struct EngineInner {
+    registry: RwLock<Handlebars<'static>>,
+    /// Parsed mixed templates keyed by source, so hot flows parse each template once.
+    templates: Mutex<LruCache<String, Arc<Template>>>,
+    rendered: AtomicBool,
+}
+
+/// Builds a [`TemplateEngine`] with the built-in helpers and any custom ones.
+#[derive(Default)]
+pub struct TemplateEngineBuilder {
+    helpers: Vec<(String, BoxedHelper)>,
+}
+
+impl TemplateEngineBuilder {
+    /// Register `helper` as `name`; a custom helper replaces a built-in of the same name.
+    pub fn helper(mut self, name: impl Into<String>, helper: BoxedHelper) -> Self {
+        self.helpers.push((name.into(), helper));
+        self
+    }
+
+    pub fn build(self) -> TemplateEngine {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(false);
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.register_helper("json", Box::new(ValueHelper(json_helper)));
+        registry.register_helper("default", Box::new(ValueHelper(default_helper)));
+        registry.register_helper("upper", Box::new(ValueHelper(upper_helper)));
+        registry.register_helper("lower", Box::new(ValueHelper(lower_helper)));
+        registry.register_helper("base64", Box::new(ValueHelper(base64_helper)));
+        registry.register_helper("eq", Box::new(ValueHelper(eq_helper)));
+        registry.register_helper("ne", Box::new(ValueHelper(ne_helper)));
+        registry.register_helper("gt", Box::new(ValueHelper(gt_helper)));
+        registry.register_helper("lt", Box::new(ValueHelper(lt_helper)));
+        registry.register_helper("contains", Box::new(ValueHelper(contains_helper)));
+        registry.register_helper("coalesce", Box::new(ValueHelper(coalesce_helper)));
+        for (name, helper) in self.helpers {
+            registry.register_helper(&name, helper);
+        }
+        registry.register_helper(TYPED_VALUE_HELPER, Box::new(ValueHelper(json_helper)));
+        TemplateEngine {
+            inner: Arc::new(EngineInner {
+                registry: RwLock::new(registry),
+                templates: Mutex::new(LruCache::new(
+                    NonZeroUsize::new(TEMPLATE_CACHE_CAPACITY).expect("capacity is non-zero"),
+                )),
+                rendered: AtomicBool::new(false),
+            }),
+        }
+    }
+}
+
+impl Default for TemplateEngine {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl TemplateEngine {
+    pub fn builder() -> TemplateEngineBuilder {
+        TemplateEngineBuilder::default()
+    }
+
+    /// The engine shared by callers without one of their own.
+    pub fn shared() -> Self {
+        DEFAULT_ENGINE.clone()
+    }
+
+    /// Register `helper` as `name`; fails once the engine has rendered a template, so
+    /// every render of a flow sees the same helpers.
+    pub fn register_helper(&self, name: &str, helper: BoxedHelper) -> Result<()> {
+        let mut registry = self.inner.registry.write();
+        if self.inner.rendered.load(Ordering::Acquire) {
+            bail!("cannot register template helper `{name}` after the first render");
+        }
+        registry.register_helper(name, helper);
+        Ok(())
+    }
+
+    pub fn has_helper(&self, name: &str) -> bool {
+        self.inner.registry.read().has_helper(name)
+    }
+
+    pub fn render_value(
+        &self,
+        template: &Value,
+        ctx: &Value,
+        options: TemplateOptions,
+    ) -> Result<Value> {
+        self.render_value_with(template, ctx, options, None)
+    }
+
+    /// [`Self::render_value`], failing with a [`TemplateError`] that names `source` and
+    /// the field being rendered.
+    pub fn render_value_with(
+        &self,
+        template: &Value,
+        ctx: &Value,
+        options: TemplateOptions,
+        source: Option<&TemplateSource<'_>>,
+    ) -> Result<Value> {
+        self.inner.rendered.store(true, Ordering::Release);
+        Renderer {
+            engine: &self.inner,
+            options,
+            source,
+        }
+        .value(template, ctx, &mut String::new())
+    }
+
+    /// Drop every parsed template; templates of replaced packs are not rendered again.
+    pub fn clear_cache(&self) {
+        self.inner.templates.lock().clear();
+    }
+}
+
+/// Helper computing a JSON value from its params, usable as a subexpression.
+struct ValueHelper(fn(&Helper<'_>) -> Result<Value, RenderError>);
+
+impl HelperDef for ValueHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        (self.0)(h).map(ScopedJson::Derived)
+    }
+}
+
+fn helper_error(message: String) -> RenderError {
+    RenderErrorReason::Other(message).into()
+}
+
+/// Param `index`, which must resolve; the registry is lenient, so a missing path
+/// would otherwise render as null.
+fn required_param<'a>(h: &'a Helper<'_>, index: usize) -> Result<&'a Value, RenderError> {
+    let name = h.name();
+    let param = h.param(index).ok_or_else(|| {
+        helper_error(format!(
+            "`{name}` expects at least {} argument(s)",
+            index + 1
+        ))
+    })?;
+    if param.is_value_missing() {
+        let path = param.relative_path().map(String::as_str).unwrap_or("?");
+        return Err(helper_error(format!("`{name}`: `{path}` not found")));
+    }
+    Ok(param.value())
+}
+
+fn string_param<'a>(h: &'a Helper<'_>, index: usize) -> Result<&'a str, RenderError> {
+    let name = h.name();
+    required_param(h, index)?
+        .as_str()
+        .ok_or_else(|| helper_error(format!("`{name}` expects a string")))
+}
+
+/// `{{json value}}`: the value serialized as JSON text.
+fn json_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let value = required_param(h, 0)?;
+    serde_json::to_string(value)
+        .map(Value::String)
+        .map_err(|err| helper_error(format!("`json`: {err}")))
+}
+
+/// `{{default value fallback}}`: `fallback` when `value` is null or missing.
+fn default_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let fallback = required_param(h, 1)?;
+    match h.param(0).map(|param| param.value()) {
+        Some(Value::Null) | None => Ok(fallback.clone()),
+        Some(value) => Ok(value.clone()),
+    }
+}
+
+fn upper_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    string_param(h, 0).map(|text| Value::String(text.to_uppercase()))
+}
+
+fn lower_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    string_param(h, 0).map(|text| Value::String(text.to_lowercase()))
+}
+
+/// `{{base64 value}}`: standard base64 of a string's UTF-8 or of an array of bytes.
+fn base64_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let bytes = match required_param(h, 0)? {
+        Value::String(text) => text.as_bytes().to_vec(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| helper_error("`base64` expects an array of bytes".into()))?,
+        _ => return Err(helper_error("`base64` expects a string or bytes".into())),
+    };
+    Ok(Value::String(STANDARD.encode(bytes)))
+}
+
+/// Param `index`, with a missing path read as null.
+fn nullable_param<'a>(h: &'a Helper<'_>, index: usize) -> Result<&'a Value, RenderError> {
+    let name = h.name();
+    h.param(index).map(|param| param.value()).ok_or_else(|| {
+        helper_error(format!(
+            "`{name}` expects at least {} argument(s)",
+            index + 1
+        ))
+    })
 }
 
-static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
-    let mut registry = Handlebars::new();
-    registry.set_strict_mode(false);
-    registry.register_escape_fn(handlebars::no_escape);
-    registry
-});
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        _ => left == right,
+    }
+}
+
+/// Order of two numbers or two strings; other pairs do not compare.
+fn compare_values(left: &Value, right: &Value) -> Option<CmpOrdering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+/// `{{eq a b}}`: whether the values are equal; values of different types are not.
+fn eq_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    Ok(Value::Bool(values_equal(
+        nullable_param(h, 0)?,
+        nullable_param(h, 1)?,
+    )))
+}
+
+fn ne_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    Ok(Value::Bool(!values_equal(
+        nullable_param(h, 0)?,
+        nullable_param(h, 1)?,
+    )))
+}
+
+/// `{{gt a b}}`: `a > b` for two numbers or two strings, false for anything else.
+fn gt_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let ordering = compare_values(nullable_param(h, 0)?, nullable_param(h, 1)?);
+    Ok(Value::Bool(ordering == Some(CmpOrdering::Greater)))
+}
 
+/// `{{lt a b}}`: `a < b` for two numbers or two strings, false for anything else.
+fn lt_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let ordering = compare_values(nullable_param(h, 0)?, nullable_param(h, 1)?);
+    Ok(Value::Bool(ordering == Some(CmpOrdering::Less)))
+}
+
+/// `{{contains haystack needle}}`: an array element equal to `needle`, a substring of a
+/// string, or a key of an object; false for anything else.
+fn contains_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    let needle = nullable_param(h, 1)?;
+    let found = match (nullable_param(h, 0)?, needle) {
+        (Value::Array(items), needle) => items.iter().any(|item| values_equal(item, needle)),
+        (Value::String(text), Value::String(needle)) => text.contains(needle.as_str()),
+        (Value::Object(map), Value::String(key)) => map.contains_key(key),
+        _ => false,
+    };
+    Ok(Value::Bool(found))
+}
+
+/// `{{coalesce a b ...}}`: the first argument that is neither null nor missing, or null.
+fn coalesce_helper(h: &Helper<'_>) -> Result<Value, RenderError> {
+    Ok(h.params()
+        .iter()
+        .map(|param| param.value())
+        .find(|value| !value.is_null())
+        .cloned()
+        .unwrap_or(Value::Null))
+}
+
+/// Render with the shared engine; see [`TemplateEngine::render_value`].
 pub fn render_template_value(
     template: &Value,
     ctx: &Value,
     options: TemplateOptions,
 ) -> Result<Value> {
-    match template {
-        Value::String(raw) => render_template_string(raw, ctx, options),
-        Value::Array(items) => {
-            let mut rendered = Vec::with_capacity(items.len());
-            for item in items {
-                rendered.push(render_template_value(item, ctx, options)?);
+    DEFAULT_ENGINE.render_value(template, ctx, options)
+}
+
+/// Render with the shared engine; see [`TemplateEngine::render_value_with`].
+pub fn render_template_value_with(
+    template: &Value,
+    ctx: &Value,
+    options: TemplateOptions,
+    source: Option<&TemplateSource<'_>>,
+) -> Result<Value> {
+    DEFAULT_ENGINE.render_value_with(template, ctx, options, source)
+}
+
+struct Renderer<'a> {
+    engine: &'a EngineInner,
+    options: TemplateOptions,
+    source: Option<&'a TemplateSource<'a>>,
+}
+
+impl Renderer<'_> {
+    /// Render `template`, found at the pointer `path` below the source field.
+    fn value(&self, template: &Value, ctx: &Value, path: &mut String) -> Result<Value> {
+        match template {
+            Value::String(raw) => render_template_string(self.engine, raw, ctx, self.options)
+                .map_err(|err| self.locate(err, path, raw, ctx)),
+            Value::Array(items) => {
+                let mut rendered = Vec::with_capacity(items.len());
+                for (index, item) in items.iter().enumerate() {
+                    rendered.push(nested(path, &index.to_string(), |path| {
+                        self.value(item, ctx, path)
+                    })?);
+                }
+                Ok(Value::Array(rendered))
+            }
+            Value::Object(map) if map.len() == 1 && map.contains_key(MAP_KEY) => {
+                nested(path, MAP_KEY, |path| {
+                    self.map(&map[MAP_KEY], ctx, path)
+                        .map_err(|err| self.locate(err, path, MAP_KEY, ctx))
+                })
             }
-            Ok(Value::Array(rendered))
+            Value::Object(map) => {
+                let mut rendered = JsonMap::new();
+                for (key, value) in map {
+                    let value = nested(path, key, |path| self.value(value, ctx, path))?;
+                    rendered.insert(key.clone(), value);
+                }
+                Ok(Value::Object(rendered))
+            }
+            other => Ok(other.clone()),
         }
-        Value::Object(map) => {
-            let mut rendered = JsonMap::new();
-            for (key, value) in map {
-                rendered.insert(key.clone(), render_template_value(value, ctx, options)?);
+    }
+
+    /// Render `template` once per element of `over`, with the element bound to `as`
+    /// (default `item`) and its position to `@index`, into a JSON array.
+    fn map(&self, spec: &Value, ctx: &Value, path: &mut String) -> Result<Value> {
+        let Value::Object(spec) = spec else {
+            bail!("`{MAP_KEY}` expects an object with `over` and `template`");
+        };
+        let over = spec
+            .get("over")
+            .ok_or_else(|| anyhow!("`{MAP_KEY}` is missing `over`"))?;
+        let template = spec
+            .get("template")
+            .ok_or_else(|| anyhow!("`{MAP_KEY}` is missing `template`"))?;
+        let name = match spec.get("as") {
+            None => "item",
+            Some(Value::String(name)) if !name.is_empty() && !name.starts_with('@') => {
+                name.as_str()
+            }
+            Some(other) => bail!("`{MAP_KEY}.as` must be a variable name, got {other}"),
+        };
+        let items = match nested(path, "over", |path| self.value(over, ctx, path))
+            .map_err(|err| add_context(err, || format!("failed to render `{MAP_KEY}.over`")))?
+        {
+            Value::Array(items) => items,
+            Value::Null if self.options.missing_as_null => Vec::new(),
+            other => bail!("`{MAP_KEY}.over` must render to an array, got {other}"),
+        };
+        let Value::Object(scope) = ctx else {
+            bail!("`{MAP_KEY}` needs an object render context");
+        };
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let mut scope = scope.clone();
+                scope.insert(name.to_string(), item);
+                scope.insert(INDEX_KEY.to_string(), Value::from(index));
+                let scope = Value::Object(scope);
+                nested(path, &format!("template[{index}]"), |path| {
+                    self.value(template, &scope, path)
+                })
+                .map_err(|err| {
+                    add_context(err, || {
+                        format!("failed to render `{MAP_KEY}` element {index}")
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array)
+    }
+
+    /// Turn `err` into a [`TemplateError`] when rendering for a source, unless a nested
+    /// template already did.
+    fn locate(&self, err: anyhow::Error, path: &str, template: &str, ctx: &Value) -> anyhow::Error {
+        match self.source {
+            Some(source) if !err.is::<TemplateError>() => {
+                TemplateError::new(source, path, template, &err, ctx).into()
             }
-            Ok(Value::Object(rendered))
+            _ => err,
         }
-        other => Ok(other.clone()),
     }
 }
 
-fn render_template_string(raw: &str, ctx: &Value, options: TemplateOptions) -> Result<Value> {
-    if options.allow_pointer && raw.starts_with('/') && !raw.contains("{{") {
-        return ctx
-            .pointer(raw)
-            .cloned()
-            .ok_or_else(|| anyhow!("mapping path `{raw}` not found"));
+/// Run `render` with `segment` appended to the pointer `path`.
+fn nested<T>(path: &mut String, segment: &str, render: impl FnOnce(&mut String) -> T) -> T {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    let result = render(path);
+    path.truncate(len);
+    result
+}
+
+/// Add context to `err`, unless it is a [`TemplateError`] that already says where.
+fn add_context<C>(err: anyhow::Error, context: impl FnOnce() -> C) -> anyhow::Error
+where
+    C: fmt::Display + Send + Sync + 'static,
+{
+    if err.is::<TemplateError>() {
+        err
+    } else {
+        err.context(context())
     }
+}
 
-    if let Some(expr) = extract_exact_expression(raw)
-        && let Some(path) = parse_path_expression(expr)
-    {
-        return resolve_path(ctx, &path)
-            .cloned()
-            .ok_or_else(|| anyhow!("template expression `{expr}` not found"));
+fn render_template_string(
+    engine: &EngineInner,
+    raw: &str,
+    ctx: &Value,
+    options: TemplateOptions,
+) -> Result<Value> {
+    if options.allow_pointer && !raw.contains("{{") {
+        // `\/text` keeps a leading-slash string verbatim.
+        if let Some(literal) = raw.strip_prefix('\\')
+            && literal.starts_with('/')
+        {
+            return Ok(Value::String(literal.to_string()));
+        }
+        if raw.starts_with('/') {
+            return match resolve_pointer(ctx, raw) {
+                Ok(value) => Ok(value.clone()),
+                Err(_) if options.missing_as_null => Ok(Value::Null),
+                Err(reason) => Err(anyhow!("mapping path `{raw}` not found: {reason}")),
+            };
+        }
+    }
+
+    if let Some(expr) = extract_exact_expression(raw) {
+        if let Some((path_expr, fallback)) = split_fallback(expr)? {
+            let path = (!is_helper_call(path_expr))
+                .then(|| parse_path_expression(path_expr))
+                .flatten()
+                .ok_or_else(|| anyhow!("invalid template path `{path_expr}`"))?;
+            return Ok(resolve_path(ctx, &path)
+                .filter(|value| !value.is_null())
+                .cloned()
+                .unwrap_or(fallback));
+        }
+        if !is_helper_call(expr)
+            && let Some(path) = parse_path_expression(expr)
+        {
+            return resolve_path(ctx, &path)
+                .cloned()
+                .or_else(|| options.missing_as_null.then_some(Value::Null))
+                .ok_or_else(|| anyhow!("template expression `{expr}` not found"));
+        }
+        if TYPED_HELPERS.contains(&helper_name(expr)) {
+            let typed = format!("{{{{{TYPED_VALUE_HELPER} ({})}}}}", expr.trim());
+            let json = render_handlebars(engine, &typed, ctx)
+                .map_err(|err| anyhow!("template render failed: {err}"))?;
+            return serde_json::from_str(&json)
+                .map_err(|err| anyhow!("helper `{expr}` did not produce JSON: {err}"));
+        }
     }
 
     if raw.contains("{{") {
-        let rendered = HANDLEBARS
-            .render_template(raw, ctx)
-            .map_err(|err| anyhow!("template render failed: {err}"))?;
-        return Ok(Value::String(rendered));
+        return render_handlebars(engine, &rewrite_fallbacks(raw)?, ctx)
+            .map(Value::String)
+            .map_err(|err| anyhow!("template render failed: {err}"));
     }
 
     Ok(Value::String(raw.to_string()))
 }
 
+fn compiled_template(engine: &EngineInner, raw: &str) -> Result<Arc<Template>, RenderError> {
+    if let Some(template) = engine.templates.lock().get(raw) {
+        return Ok(Arc::clone(template));
+    }
+    let template = Arc::new(
+        Template::compile(raw)
+            .map_err(|err| RenderError::from(RenderErrorReason::Other(err.to_string())))?,
+    );
+    #[cfg(test)]
+    PARSES.with(|parses| parses.set(parses.get() + 1));
+    engine
+        .templates
+        .lock()
+        .put(raw.to_string(), Arc::clone(&template));
+    Ok(template)
+}
+
+fn render_handlebars(engine: &EngineInner, raw: &str, ctx: &Value) -> Result<String, RenderError> {
+    let template = compiled_template(engine, raw)?;
+    let context = Context::wraps(ctx)?;
+    let mut render_ctx = RenderContext::new(None);
+    // Inside `$map`, `{{@index}}` is a block-local variable, as within `{{#each}}`.
+    if let Some(index) = ctx.get(INDEX_KEY) {
+        let mut block = BlockContext::new();
+        block.set_local_var("index", index.clone());
+        render_ctx.push_block(block);
+    }
+    let mut out = StringOutput::new();
+    template.render(&engine.registry.read(), &context, &mut render_ctx, &mut out)?;
+    out.into_string()
+        .map_err(|err| RenderErrorReason::Other(err.to_string()).into())
+}
+
 fn extract_exact_expression(raw: &str) -> Option<&str> {
     let trimmed = raw.trim();
     if trimmed.starts_with("{{") && trimmed.ends_with("}}") {
         let inner = trimmed.trim_start_matches('{').trim_end_matches('}').trim();
-        if !inner.is_empty() {
+        if !inner.is_empty() && !inner.contains("{{") && !inner.contains("}}") {
             return Some(inner);
         }
     }
     None
 }
 
+/// Resolve an RFC 6901 pointer (`~1` is `/`, `~0` is `~`), naming the first segment
+/// that does not resolve.
+fn resolve_pointer<'a>(root: &'a Value, pointer: &str) -> Result<&'a Value, String> {
+    let mut current = root;
+    let mut parent_end = 0;
+    for raw_segment in pointer[1..].split('/') {
+        let parent = match &pointer[..parent_end] {
+            "" => "the root",
+            parent => parent,
+        };
+        let segment = unescape_pointer_segment(raw_segment)
+            .ok_or_else(|| format!("invalid escape in segment `{raw_segment}`"))?;
+        current = match current {
+            Value::Object(map) => map
+                .get(&segment)
+                .ok_or_else(|| format!("no key `{segment}` under {parent}"))?,
+            Value::Array(items) => {
+                let index = segment
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|_| segment == "0" || !segment.starts_with('0'))
+                    .ok_or_else(|| format!("`{segment}` is not an array index under {parent}"))?;
+                items.get(index).ok_or_else(|| {
+                    format!(
+                        "index {index} is out of range under {parent} ({} items)",
+                        items.len()
+                    )
+                })?
+            }
+            _ => return Err(format!("{parent} is not an object or array")),
+        };
+        parent_end += raw_segment.len() + 1;
+    }
+    Ok(current)
+}
+
+fn unescape_pointer_segment(segment: &str) -> Option<String> {
+    let mut out = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '~' {
+            match chars.next()? {
+                '0' => out.push('~'),
+                '1' => out.push('/'),
+                _ => return None,
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+    Some(out)
+}
+
+/// Split `path ?? literal` into the path and the literal's value. The literal is a
+/// quoted string (single or double quotes), a number, `true`, `false` or `null`.
+fn split_fallback(expr: &str) -> Result<Option<(&str, Value)>> {
+    let Some(at) = find_fallback_operator(expr) else {
+        return Ok(None);
+    };
+    let path = expr[..at].trim();
+    let literal = expr[at + 2..].trim();
+    let value = match literal
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        Some(text) => Value::String(text.to_string()),
+        None => serde_json::from_str::<Value>(literal)
+            .ok()
+            .filter(|value| !value.is_array() && !value.is_object())
+            .ok_or_else(|| anyhow!("invalid fallback `{literal}` in `{expr}`"))?,
+    };
+    Ok(Some((path, value)))
+}
+
+/// Byte offset of a `??` outside brackets and quotes.
+fn find_fallback_operator(expr: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut chars = expr.char_indices().peekable();
+    while let Some((at, ch)) = chars.next() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, '?') if depth == 0 && matches!(chars.peek(), Some((_, '?'))) => {
+                return Some(at);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Turn each `{{path ?? literal}}` of a mixed template into a `default` helper call.
+fn rewrite_fallbacks(raw: &str) -> Result<Cow<'_, str>> {
+    if !raw.contains("??") {
+        return Ok(Cow::Borrowed(raw));
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + len];
+        out.push_str(&rest[..start]);
+        match split_fallback(inner)? {
+            Some((path, fallback)) => {
+                out.push_str(&format!("{{{{default {path} {fallback}}}}}"));
+            }
+            None => out.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(Cow::Owned(out))
+}
+
+/// Whether `expr` calls a helper (`json entry`, `default (x) 'y'`) rather than naming a
+/// path: whitespace or a parenthesis outside brackets.
+fn is_helper_call(expr: &str) -> bool {
+    let mut depth = 0usize;
+    for ch in expr.trim().chars() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            '(' if depth == 0 => return true,
+            ch if ch.is_whitespace() && depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Name of the helper called by `expr`.
+fn helper_name(expr: &str) -> &str {
+    let expr = expr.trim();
+    expr.split(|ch: char| ch.is_whitespace() || ch == '(')
+        .next()
+        .unwrap_or(expr)
+}
+
 #[derive(Debug)]
 enum PathSegment {
     Key(String),
@@ -271,4 +1030,448 @@ mod tests {
         .unwrap();
         assert_eq!(rendered, Value::String("https://x/42".to_string()));
     }
+
+    fn render(raw: &str, ctx: &Value) -> Result<Value> {
+        render_template_value(
+            &Value::String(raw.to_string()),
+            ctx,
+            TemplateOptions::default(),
+        )
+    }
+
+    #[test]
+    fn json_helper_stringifies_with_types() {
+        let ctx = json!({ "entry": { "count": 3, "tags": ["a"], "ok": true } });
+        assert_eq!(
+            render("{{json entry}}", &ctx).unwrap(),
+            json!(r#"{"count":3,"ok":true,"tags":["a"]}"#)
+        );
+        assert_eq!(render("{{json entry.count}}", &ctx).unwrap(), json!("3"));
+        assert_eq!(
+            render("body={{json entry.tags}}", &ctx).unwrap(),
+            json!(r#"body=["a"]"#)
+        );
+        assert!(render("{{json entry.missing}}", &ctx).is_err());
+    }
+
+    #[test]
+    fn default_helper_covers_null_and_missing() {
+        let ctx = json!({ "entry": { "name": "ada", "nick": null } });
+        assert_eq!(
+            render("{{default entry.name 'anon'}}", &ctx).unwrap(),
+            json!("ada")
+        );
+        assert_eq!(
+            render("{{default entry.nick 'anon'}}", &ctx).unwrap(),
+            json!("anon")
+        );
+        assert_eq!(
+            render("hi {{default entry.missing.deep 'anon'}}", &ctx).unwrap(),
+            json!("hi anon")
+        );
+        assert!(render("{{entry.missing}}", &ctx).is_err());
+    }
+
+    #[test]
+    fn case_helpers_fold_strings() {
+        let ctx = json!({ "entry": { "name": "Ada", "count": 3 } });
+        assert_eq!(render("{{upper entry.name}}", &ctx).unwrap(), json!("ADA"));
+        assert_eq!(render("{{lower entry.name}}", &ctx).unwrap(), json!("ada"));
+        assert_eq!(
+            render("{{upper (default entry.missing 'x')}}", &ctx).unwrap(),
+            json!("X")
+        );
+        assert!(render("{{upper entry.count}}", &ctx).is_err());
+        assert!(render("{{lower entry.missing}}", &ctx).is_err());
+    }
+
+    #[test]
+    fn base64_helper_encodes_strings_and_bytes() {
+        let ctx = json!({ "entry": { "user": "ada", "raw": [104, 105] } });
+        assert_eq!(
+            render("Basic {{base64 entry.user}}", &ctx).unwrap(),
+            json!("Basic YWRh")
+        );
+        assert_eq!(render("{{base64 entry.raw}}", &ctx).unwrap(), json!("aGk="));
+        assert!(render("{{base64 entry.missing}}", &ctx).is_err());
+        assert!(render("{{base64 entry}}", &ctx).is_err());
+    }
+
+    #[test]
+    fn fallbacks_keep_their_json_type() {
+        let ctx = json!({ "entry": { "name": "ada", "nick": null, "items": [] } });
+        assert_eq!(render("{{entry.count ?? 0}}", &ctx).unwrap(), json!(0));
+        assert_eq!(render("{{entry.ratio ?? 0.5}}", &ctx).unwrap(), json!(0.5));
+        assert_eq!(render("{{entry.on ?? false}}", &ctx).unwrap(), json!(false));
+        assert_eq!(render("{{entry.who ?? null}}", &ctx).unwrap(), Value::Null);
+        assert_eq!(
+            render("{{entry.nick ?? 'anon'}}", &ctx).unwrap(),
+            json!("anon")
+        );
+        assert_eq!(
+            render(r#"{{entry.name ?? "anon"}}"#, &ctx).unwrap(),
+            json!("ada")
+        );
+        assert_eq!(
+            render("{{entry.items[3].id ?? 'none'}}", &ctx).unwrap(),
+            json!("none")
+        );
+        assert!(render("{{entry.count ?? [1]}}", &ctx).is_err());
+    }
+
+    #[test]
+    fn fallbacks_apply_to_nested_missing_paths() {
+        let ctx = json!({ "entry": {}, "node": {} });
+        assert_eq!(
+            render("{{node.start.user.id ?? 7}}", &ctx).unwrap(),
+            json!(7)
+        );
+        assert_eq!(
+            render(
+                "user {{node.start.user.name ?? 'guest'}} ({{entry.id ?? 0}})",
+                &ctx
+            )
+            .unwrap(),
+            json!("user guest (0)")
+        );
+    }
+
+    #[test]
+    fn missing_paths_are_strict_unless_permissive() {
+        let ctx = json!({ "entry": {} });
+        let err = render("{{entry.missing.deep}}", &ctx).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err}");
+
+        let permissive = TemplateOptions {
+            allow_pointer: true,
+            missing_as_null: true,
+        };
+        let template = json!({ "a": "{{entry.missing.deep}}", "b": "/entry/missing", "c": "{{entry.x ?? 1}}" });
+        assert_eq!(
+            render_template_value(&template, &ctx, permissive).unwrap(),
+            json!({ "a": null, "b": null, "c": 1 })
+        );
+    }
+
+    fn render_mapping(raw: &str, ctx: &Value) -> Result<Value> {
+        render_template_value(
+            &Value::String(raw.to_string()),
+            ctx,
+            TemplateOptions {
+                allow_pointer: true,
+                ..TemplateOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn pointers_unescape_keys_and_index_arrays() {
+        let ctx = json!({
+            "headers": { "content/type": "text/plain", "a~b": 1 },
+            "items": [{ "id": 7 }, { "id": 8 }],
+        });
+        assert_eq!(
+            render_mapping("/headers/content~1type", &ctx).unwrap(),
+            json!("text/plain")
+        );
+        assert_eq!(render_mapping("/headers/a~0b", &ctx).unwrap(), json!(1));
+        assert_eq!(render_mapping("/items/1/id", &ctx).unwrap(), json!(8));
+        assert_eq!(render_mapping("", &ctx).unwrap(), json!(""));
+    }
+
+    #[test]
+    fn pointer_errors_name_the_failing_segment() {
+        let ctx = json!({ "headers": {}, "items": [{ "id": 7 }] });
+        let err = render_mapping("/headers/content~1type", &ctx).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("no key `content/type` under /headers"),
+            "{err}"
+        );
+        let err = render_mapping("/items/3/id", &ctx).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("index 3 is out of range under /items (1 items)"),
+            "{err}"
+        );
+        let err = render_mapping("/items/01", &ctx).unwrap_err();
+        assert!(err.to_string().contains("not an array index"), "{err}");
+        let err = render_mapping("/missing/x", &ctx).unwrap_err();
+        assert!(err.to_string().contains("under the root"), "{err}");
+        assert!(render_mapping("/headers/a~2", &ctx).is_err());
+    }
+
+    #[test]
+    fn escaped_slash_strings_stay_literal() {
+        let ctx = json!({ "v1": { "users": 1 } });
+        assert_eq!(
+            render_mapping("\\/v1/users", &ctx).unwrap(),
+            json!("/v1/users")
+        );
+        assert_eq!(render_mapping("/v1/users", &ctx).unwrap(), json!(1));
+        assert_eq!(render("\\/v1/users", &ctx).unwrap(), json!("\\/v1/users"));
+    }
+
+    #[test]
+    fn map_reshapes_arrays_and_keeps_types() {
+        let ctx = json!({
+            "entry": {
+                "items": [
+                    { "sku": "a-1", "qty": 2, "price": 9.5 },
+                    { "sku": "b-2", "qty": 1, "price": 20 }
+                ]
+            }
+        });
+        let template = json!({
+            "lines": {
+                "$map": {
+                    "over": "{{entry.items}}",
+                    "as": "line",
+                    "template": {
+                        "id": "{{line.sku}}",
+                        "quantity": "{{line.qty}}",
+                        "unit_price": "{{line.price}}",
+                        "position": "{{@index}}",
+                        "label": "#{{@index}} {{upper line.sku}}"
+                    }
+                }
+            }
+        });
+        let rendered = render_template_value(&template, &ctx, TemplateOptions::default()).unwrap();
+        assert_eq!(
+            rendered,
+            json!({
+                "lines": [
+                    { "id": "a-1", "quantity": 2, "unit_price": 9.5, "position": 0, "label": "#0 A-1" },
+                    { "id": "b-2", "quantity": 1, "unit_price": 20, "position": 1, "label": "#1 B-2" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn map_nests_and_handles_empty_arrays() {
+        let ctx = json!({
+            "entry": {
+                "groups": [
+                    { "name": "x", "members": [1, 2] },
+                    { "name": "y", "members": [] }
+                ]
+            }
+        });
+        let template = json!({
+            "$map": {
+                "over": "{{entry.groups}}",
+                "as": "group",
+                "template": {
+                    "name": "{{group.name}}",
+                    "ids": {
+                        "$map": {
+                            "over": "{{group.members}}",
+                            "template": { "group": "{{group.name}}", "id": "{{item}}" }
+                        }
+                    }
+                }
+            }
+        });
+        let rendered = render_template_value(&template, &ctx, TemplateOptions::default()).unwrap();
+        assert_eq!(
+            rendered,
+            json!([
+                { "name": "x", "ids": [{ "group": "x", "id": 1 }, { "group": "x", "id": 2 }] },
+                { "name": "y", "ids": [] }
+            ])
+        );
+    }
+
+    #[test]
+    fn map_rejects_non_array_sources() {
+        let ctx = json!({ "entry": { "name": "ada" } });
+        let template = json!({ "$map": { "over": "{{entry.name}}", "template": "{{item}}" } });
+        let err = render_template_value(&template, &ctx, TemplateOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("must render to an array"), "{err}");
+
+        let template = json!({ "$map": { "over": "{{entry.items}}", "template": "{{item}}" } });
+        assert!(render_template_value(&template, &ctx, TemplateOptions::default()).is_err());
+        let permissive = TemplateOptions {
+            missing_as_null: true,
+            ..TemplateOptions::default()
+        };
+        assert_eq!(
+            render_template_value(&template, &ctx, permissive).unwrap(),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn mixed_templates_are_parsed_once() {
+        let ctx = json!({ "entry": { "n": 1 } });
+        let raw = "parsed once: {{entry.n}} {{upper 'x'}}";
+        let before = PARSES.with(|parses| parses.get());
+        for _ in 0..1000 {
+            assert_eq!(render(raw, &ctx).unwrap(), json!("parsed once: 1 X"));
+        }
+        assert_eq!(PARSES.with(|parses| parses.get()) - before, 1);
+
+        render("{{entry.n}}", &ctx).unwrap();
+        assert_eq!(PARSES.with(|parses| parses.get()) - before, 1);
+    }
+
+    const SOURCE: TemplateSource<'static> = TemplateSource {
+        pack_id: "billing",
+        flow_id: "invoice",
+        node_id: "send_email",
+        field: "input",
+    };
+
+    #[test]
+    fn errors_name_the_node_field_and_expression() {
+        let ctx = json!({ "entry": {}, "prev": {}, "node": {}, "state": {} });
+        let template = json!({ "body": { "text": "{{prev.text}}" } });
+        let err =
+            render_template_value_with(&template, &ctx, TemplateOptions::default(), Some(&SOURCE))
+                .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("node `send_email`"), "{message}");
+        assert!(message.contains("`{{prev.text}}`"), "{message}");
+        assert!(message.contains("at `input/body/text`"), "{message}");
+        assert!(
+            message.contains("available context keys: entry, node, prev, state"),
+            "{message}"
+        );
+
+        let diagnostic = err
+            .downcast_ref::<TemplateError>()
+            .unwrap()
+            .diagnostic("en");
+        assert_eq!(diagnostic.code, "TEMPLATE_RENDER_FAILED");
+        assert_eq!(
+            diagnostic.hint.as_deref(),
+            Some("available context keys: entry, node, prev, state")
+        );
+        assert_eq!(diagnostic.hint_args["keys"], "entry, node, prev, state");
+        assert_eq!(diagnostic.path, "/input/body/text");
+        assert_eq!(diagnostic.details.unwrap()["node_id"], "send_email");
+    }
+
+    #[test]
+    fn errors_inside_map_name_the_element() {
+        let ctx = json!({ "entry": { "items": [{ "id": 1 }, {}] } });
+        let template = json!({
+            "ids": { "$map": { "over": "{{entry.items}}", "template": "{{item.id}}" } }
+        });
+        let err =
+            render_template_value_with(&template, &ctx, TemplateOptions::default(), Some(&SOURCE))
+                .unwrap_err();
+        let error = err.downcast_ref::<TemplateError>().unwrap();
+        assert_eq!(error.field, "input/ids/$map/template[1]");
+        assert_eq!(error.template, "{{item.id}}");
+        assert!(!error.available_keys.contains(&"@index".to_string()));
+    }
+
+    #[test]
+    fn bracketed_keys_with_spaces_stay_paths() {
+        let ctx = json!({ "entry": { "first name": "Ada" } });
+        assert_eq!(
+            render("{{entry.[first name]}}", &ctx).unwrap(),
+            json!("Ada")
+        );
+    }
+
+    #[test]
+    fn tenant_helpers_stay_on_their_engine() {
+        let ctx = json!({ "entry": { "plan": "pro" } });
+        let raw = json!("{{brand entry.plan}}");
+        let acme = TemplateEngine::builder()
+            .helper(
+                "brand",
+                Box::new(ValueHelper(|h| {
+                    Ok(json!(format!("acme-{}", string_param(h, 0)?)))
+                })),
+            )
+            .build();
+        let other = TemplateEngine::default();
+        assert_eq!(
+            acme.render_value(&raw, &ctx, TemplateOptions::default())
+                .unwrap(),
+            json!("acme-pro")
+        );
+        assert!(acme.has_helper("brand") && acme.has_helper("upper"));
+        assert!(!other.has_helper("brand"));
+        assert!(
+            other
+                .render_value(&raw, &ctx, TemplateOptions::default())
+                .is_err()
+        );
+
+        let late = other.register_helper("brand", Box::new(ValueHelper(|_| Ok(Value::Null))));
+        assert!(
+            late.unwrap_err()
+                .to_string()
+                .contains("after the first render")
+        );
+    }
+
+    #[test]
+    fn conditionals_select_object_branches() {
+        let template = json!({
+            "$map": {
+                "over": "{{entry.orders}}",
+                "as": "order",
+                "template": {
+                    "priority": "{{#if (gt order.total 100)}}high{{else}}normal{{/if}}",
+                    "express": "{{contains order.tags 'express'}}",
+                    "status": "{{#if (eq order.status 'paid')}}ready{{else}}{{#if (ne order.status 'void')}}pending{{else}}void{{/if}}{{/if}}",
+                    "small": "{{lt order.total 10}}"
+                }
+            }
+        });
+        let ctx = json!({ "entry": { "orders": [
+            { "total": 250, "tags": ["express"], "status": "paid" },
+            { "total": 5, "tags": [], "status": "open" },
+            { "total": "12", "tags": "express", "status": "void" }
+        ] } });
+        let rendered = render_template_value(&template, &ctx, TemplateOptions::default()).unwrap();
+        assert_eq!(
+            rendered,
+            json!([
+                { "priority": "high", "express": true, "status": "ready", "small": false },
+                { "priority": "normal", "express": false, "status": "pending", "small": true },
+                { "priority": "normal", "express": true, "status": "void", "small": false }
+            ])
+        );
+    }
+
+    #[test]
+    fn comparison_helpers_keep_json_types() {
+        let ctx = json!({
+            "entry": { "count": 3, "label": "3", "ratio": 3.0 },
+            "prev": { "a": null, "b": { "id": 7 } },
+        });
+        assert_eq!(
+            render("{{coalesce prev.a prev.b 'x'}}", &ctx).unwrap(),
+            json!({ "id": 7 })
+        );
+        assert_eq!(
+            render("{{coalesce prev.a prev.missing 'x'}}", &ctx).unwrap(),
+            json!("x")
+        );
+        assert_eq!(
+            render("{{eq entry.count entry.ratio}}", &ctx).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            render("{{eq entry.count entry.label}}", &ctx).unwrap(),
+            json!(false)
+        );
+        assert_eq!(
+            render("{{gt entry.count entry.label}}", &ctx).unwrap(),
+            json!(false)
+        );
+        assert_eq!(render("{{ne entry.missing 1}}", &ctx).unwrap(), json!(true));
+        assert_eq!(
+            render("count is 3: {{eq entry.count 3}}", &ctx).unwrap(),
+            json!("count is 3: true")
+        );
+    }
 }
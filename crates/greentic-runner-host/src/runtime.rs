@@ -2,34 +2,54 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::num::NonZeroUsize;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use arc_swap::ArcSwap;
+use async_trait::async_trait;
 use axum::http::StatusCode;
 use lru::LruCache;
 use parking_lot::Mutex;
 use reqwest::Client;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
 use tokio::runtime::{Handle, Runtime};
-use tokio::task::JoinHandle;
 
+use crate::audit::{AuditLog, SecretDecision, SecretRequester};
+use crate::cache::SecretsCache;
+use crate::cancel::InvocationRegistry;
 use crate::config::HostConfig;
 use crate::engine::host::{SessionHost, StateHost};
-use crate::engine::runtime::StateMachineRuntime;
+use crate::engine::runtime::{IngressEnvelope, StateMachineRuntime};
+use crate::engine::{
+    FlowSchema, FlowSummary, GResult, RunFlowRequest, RunFlowResult, RunnerApi, RunnerError,
+};
+use crate::lifecycle::{STOP_REASON_RELOAD, STOP_REASON_SHUTDOWN, STOP_REASON_TENANT_REMOVED};
 use crate::operator_metrics::OperatorMetrics;
 use crate::operator_registry::OperatorRegistry;
 use crate::pack::{ComponentResolution, PackRuntime};
+use crate::redact;
+use crate::runner::adapt_timer::TimerScheduler;
 use crate::runner::contract_cache::{ContractCache, ContractCacheStats};
 use crate::runner::engine::FlowEngine;
+use crate::runner::idempotency::OperatorReplay;
 use crate::runner::mocks::MockLayer;
-use crate::secrets::{DynSecretsManager, read_secret_blocking};
+use crate::runner::rate_limit::OperatorRateLimits;
+use crate::runner::redelivery::RedeliveryQueue;
+use crate::runner::templating::TemplateEngine;
+use crate::secrets::{DynSecretsManager, rotation, secret_path_for_pack};
+use crate::storage::encryption::apply_encryption;
+use crate::storage::listing::{self, Cursor};
+use crate::storage::quota::{self, StateUsage};
 use crate::storage::session::DynSessionStore;
-use crate::storage::state::DynStateStore;
+use crate::storage::state::{DynStateStore, state_host_from};
 use crate::trace::PackTraceInfo;
+use crate::trace::run::{self, RunTraceRequest};
 use crate::wasi::RunnerWasiPolicy;
-use greentic_types::SecretRequirement;
+use greentic_types::{EnvId, SecretRequirement, StateKey, TenantCtx, TenantId};
 
 const TELEGRAM_CACHE_CAPACITY: usize = 1024;
 const WEBHOOK_CACHE_CAPACITY: usize = 256;
@@ -59,6 +79,98 @@ impl ActivePacks {
         self.inner.store(Arc::new(next));
     }
 
+    /// Like [`Self::replace`], then start the timers and redeliveries of new runtimes
+    /// (taking over those of the runtime they replace) and send `on-stop` to every pack the new runtimes no
+    /// longer serve: `reload` for tenants still present, `tenant-removed` for the rest.
+    pub async fn replace_and_stop(&self, next: HashMap<String, Arc<TenantRuntime>>) {
+        let next = Arc::new(next);
+        let previous = self.inner.swap(Arc::clone(&next));
+        for (tenant, successor) in next.iter() {
+            match previous.get(tenant) {
+                Some(runtime) if Arc::ptr_eq(runtime, successor) => {}
+                runtime => {
+                    successor.start_timers(runtime.map(Arc::as_ref));
+                    if let Some(runtime) = runtime {
+                        runtime.stop_redelivery();
+                    }
+                    successor.start_redelivery();
+                }
+            }
+        }
+        for (tenant, runtime) in previous.iter() {
+            match next.get(tenant) {
+                Some(successor) => {
+                    runtime
+                        .stop_retired_packs(Some(successor.as_ref()), STOP_REASON_RELOAD)
+                        .await
+                }
+                None => {
+                    runtime.stop_timers();
+                    runtime.stop_redelivery();
+                    runtime
+                        .stop_retired_packs(None, STOP_REASON_TENANT_REMOVED)
+                        .await
+                }
+            }
+        }
+    }
+
+    /// Drop every tenant and send `on-stop` with reason `shutdown` to all their packs.
+    pub async fn shutdown(&self) {
+        let previous = self.inner.swap(Arc::new(HashMap::new()));
+        for runtime in previous.values() {
+            runtime.stop_timers();
+            runtime.stop_redelivery();
+            runtime.stop_retired_packs(None, STOP_REASON_SHUTDOWN).await;
+        }
+    }
+
+    /// Replace one pack of a live tenant with the pack at `artifact` without
+    /// rebuilding the other tenants.
+    ///
+    /// The new runtime is swapped in only if the tenant still maps to the runtime the
+    /// reload started from; on any failure the previous runtime keeps serving.
+    /// In-flight invocations hold their own `Arc<TenantRuntime>` and finish against the
+    /// previous packs.
+    pub async fn reload_pack(
+        &self,
+        tenant: &str,
+        artifact: &Path,
+        digest: Option<String>,
+    ) -> Result<ReloadedPack> {
+        let current = self
+            .load(tenant)
+            .with_context(|| format!("tenant {tenant} is not loaded"))?;
+        let reloaded = current.reload_pack(artifact, digest).await?;
+        let next = &reloaded.runtime;
+
+        let mut swapped = false;
+        self.inner.rcu(|tenants| {
+            swapped = tenants
+                .get(tenant)
+                .is_some_and(|live| Arc::ptr_eq(live, &current));
+            if !swapped {
+                return Arc::clone(tenants);
+            }
+            let mut tenants = (**tenants).clone();
+            tenants.insert(tenant.to_string(), Arc::clone(next));
+            Arc::new(tenants)
+        });
+        if !swapped {
+            next.stop_retired_packs(Some(current.as_ref()), STOP_REASON_RELOAD)
+                .await;
+            bail!("tenant {tenant} was reloaded concurrently; retry the pack reload");
+        }
+        next.start_timers(Some(&*current));
+        next.start_redelivery();
+        current.retire_digests(next);
+        current
+            .stop_retired_packs(Some(next.as_ref()), STOP_REASON_RELOAD)
+            .await;
+        next.templates().clear_cache();
+        Ok(reloaded)
+    }
+
     pub fn len(&self) -> usize {
         self.inner.load().len()
     }
@@ -81,17 +193,49 @@ pub struct TenantRuntime {
     packs: Vec<Arc<PackRuntime>>,
     digests: Vec<Option<String>>,
     engine: Arc<FlowEngine>,
+    /// Helpers and parsed templates of this tenant; kept across pack reloads.
+    templates: TemplateEngine,
     state_machine: Arc<StateMachineRuntime>,
     http_client: Client,
     telegram_cache: Mutex<LruCache<i64, StatusCode>>,
     webhook_cache: Mutex<LruCache<String, Value>>,
     messaging_rate: Mutex<RateLimiter>,
     mocks: Option<Arc<MockLayer>>,
-    timer_handles: Mutex<Vec<JoinHandle<()>>>,
+    /// Started by [`ActivePacks::replace_and_stop`] and handed on across reloads.
+    timers: Mutex<Option<TimerScheduler>>,
     secrets: DynSecretsManager,
     operator_registry: OperatorRegistry,
     operator_metrics: Arc<OperatorMetrics>,
     contract_cache: ContractCache,
+    stores: TenantStores,
+    audit: Arc<AuditLog>,
+    secrets_cache: Arc<SecretsCache>,
+    invocations: Arc<InvocationRegistry>,
+    redelivery: Arc<RedeliveryQueue>,
+    operator_replay: Arc<OperatorReplay>,
+    operator_rate_limits: Arc<OperatorRateLimits>,
+    /// When this runtime was built: at tenant load, or by the last reload.
+    loaded_at: OffsetDateTime,
+}
+
+/// Storage handles a tenant runtime was built with, kept so packs can be reloaded.
+#[derive(Clone)]
+struct TenantStores {
+    session_host: Arc<dyn SessionHost>,
+    session_store: DynSessionStore,
+    state_store: DynStateStore,
+    state_host: Arc<dyn StateHost>,
+    /// `state_host` behind the tenant's state quota and encryption, for everything the
+    /// tenant persists.
+    layered_state_host: Arc<dyn StateHost>,
+}
+
+/// Outcome of [`TenantRuntime::reload_pack`].
+pub struct ReloadedPack {
+    /// Tenant runtime serving the new pack.
+    pub runtime: Arc<TenantRuntime>,
+    pub pack: Arc<PackRuntime>,
+    pub digest: Option<String>,
 }
 
 #[derive(Clone)]
@@ -99,6 +243,35 @@ pub struct ResolvedComponent {
     pub digest: String,
     pub component_ref: String,
     pub pack: Arc<PackRuntime>,
+    /// `pack_id@version` of the pack that satisfied the lookup.
+    pub pack_ref: String,
+    /// Preferred pack that lacked the component, when resolution fell back to priority
+    /// order.
+    pub fallback_from: Option<String>,
+}
+
+/// Pack a component lookup should be satisfied by, typically the pack that declared the
+/// operator binding. A pack matches on its digest, or on `pack_ref` being its
+/// `pack_id@version` or bare `pack_id`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackPreference<'a> {
+    pub pack_ref: Option<&'a str>,
+    pub pack_digest: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ComponentResolveError {
+    #[error("component `{component_ref}` not found in tenant packs")]
+    NotFound { component_ref: String },
+    #[error(
+        "component `{component_ref}` is shipped by several packs ({}); bind it to one pack",
+        candidates.join(", ")
+    )]
+    Ambiguous {
+        component_ref: String,
+        /// `pack_id@version` of every pack shipping the component, in priority order.
+        candidates: Vec<String>,
+    },
 }
 
 /// Block on a future whether or not we're already inside a tokio runtime.
@@ -171,16 +344,80 @@ impl TenantRuntime {
         mocks: Option<Arc<MockLayer>>,
         session_host: Arc<dyn SessionHost>,
         session_store: DynSessionStore,
-        _state_store: DynStateStore,
+        state_store: DynStateStore,
         state_host: Arc<dyn StateHost>,
         secrets_manager: DynSecretsManager,
+    ) -> Result<Arc<Self>> {
+        let state_policy = &config.state_store_policy;
+        let layered_state_host =
+            if state_policy.quota.is_limited() || state_policy.encryption.is_some() {
+                state_host_from(apply_encryption(
+                    quota::apply_quota(Arc::clone(&state_store), state_policy.quota),
+                    state_policy.encryption.as_ref(),
+                    Arc::clone(&secrets_manager),
+                ))
+            } else {
+                Arc::clone(&state_host)
+            };
+        let stores = TenantStores {
+            session_host,
+            session_store,
+            state_store,
+            state_host,
+            layered_state_host,
+        };
+        let secrets_cache = Arc::new(SecretsCache::from_env());
+        rotation::listen(&secrets_cache);
+        let redelivery = Arc::new(RedeliveryQueue::new(
+            &config.tenant_ctx(),
+            Arc::clone(&stores.layered_state_host),
+        ));
+        let operator_replay = Arc::new(OperatorReplay::new(
+            config.tenant_ctx(),
+            Arc::clone(&stores.layered_state_host),
+            config.operator_policy.idempotency_ttl(),
+        ));
+        Self::build(
+            config,
+            packs,
+            mocks,
+            stores,
+            secrets_manager,
+            Arc::new(OperatorMetrics::default()),
+            ContractCache::from_env(),
+            Arc::new(AuditLog::default()),
+            secrets_cache,
+            Arc::new(InvocationRegistry::default()),
+            redelivery,
+            operator_replay,
+            Arc::new(OperatorRateLimits::default()),
+            TemplateEngine::default(),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn build(
+        config: Arc<HostConfig>,
+        packs: Vec<(Arc<PackRuntime>, Option<String>)>,
+        mocks: Option<Arc<MockLayer>>,
+        stores: TenantStores,
+        secrets_manager: DynSecretsManager,
+        operator_metrics: Arc<OperatorMetrics>,
+        contract_cache: ContractCache,
+        audit: Arc<AuditLog>,
+        secrets_cache: Arc<SecretsCache>,
+        invocations: Arc<InvocationRegistry>,
+        redelivery: Arc<RedeliveryQueue>,
+        operator_replay: Arc<OperatorReplay>,
+        operator_rate_limits: Arc<OperatorRateLimits>,
+        templates: TemplateEngine,
     ) -> Result<Arc<Self>> {
         let telegram_capacity = NonZeroUsize::new(TELEGRAM_CACHE_CAPACITY)
             .expect("telegram cache capacity must be > 0");
         let webhook_capacity =
             NonZeroUsize::new(WEBHOOK_CACHE_CAPACITY).expect("webhook cache capacity must be > 0");
         let operator_registry = OperatorRegistry::build(&packs)?;
-        let operator_metrics = Arc::new(OperatorMetrics::default());
         let pack_runtimes = packs
             .iter()
             .map(|(pack, _)| Arc::clone(pack))
@@ -209,29 +446,33 @@ impl TenantRuntime {
         let engine = Arc::new(
             FlowEngine::new(pack_runtimes.clone(), Arc::clone(&config))
                 .await
-                .context("failed to prime flow engine")?,
+                .context("failed to prime flow engine")?
+                .with_templates(templates.clone()),
         );
+        let state_host = Arc::clone(&stores.layered_state_host);
         let state_machine = Arc::new(
             StateMachineRuntime::from_flow_engine(
                 Arc::clone(&config),
                 Arc::clone(&engine),
                 pack_trace,
-                session_host,
-                session_store,
+                Arc::clone(&stores.session_host),
+                Arc::clone(&stores.session_store),
                 state_host,
                 Arc::clone(&secrets_manager),
                 mocks.clone(),
+                Arc::clone(&redelivery),
             )
             .context("failed to initialise state machine runtime")?,
         );
         let http_client = Client::builder().build()?;
         let rate_limits = config.rate_limits.clone();
-        Ok(Arc::new(Self {
+        let runtime = Arc::new(Self {
             tenant: config.tenant.clone(),
             config,
             packs: pack_runtimes,
             digests,
             engine,
+            templates,
             state_machine,
             http_client,
             telegram_cache: Mutex::new(LruCache::new(telegram_capacity)),
@@ -241,12 +482,115 @@ impl TenantRuntime {
                 rate_limits.messaging_burst,
             )),
             mocks,
-            timer_handles: Mutex::new(Vec::new()),
+            timers: Mutex::new(None),
             secrets: secrets_manager,
             operator_registry,
             operator_metrics,
-            contract_cache: ContractCache::from_env(),
-        }))
+            contract_cache,
+            stores,
+            audit,
+            secrets_cache,
+            invocations,
+            redelivery,
+            operator_replay,
+            operator_rate_limits,
+            loaded_at: OffsetDateTime::now_utc(),
+        });
+        if !runtime.config.starts_components_lazily() {
+            // Packs shared with a runtime this one replaces are already started.
+            for pack in &runtime.packs {
+                pack.start_components().await.with_context(|| {
+                    format!(
+                        "failed to start components of pack {} for tenant {}",
+                        pack.metadata().pack_id,
+                        runtime.tenant
+                    )
+                })?;
+            }
+        }
+        Ok(runtime)
+    }
+
+    /// Build a runtime that serves the pack at `artifact` in place of the loaded pack
+    /// with the same pack id, keeping the other packs, metrics, contract cache, audit
+    /// log, secrets cache, template engine and in-flight invocations.
+    ///
+    /// `self` is left untouched; see [`ActivePacks::reload_pack`] for the swap. Without
+    /// an explicit `digest` the artifact file's sha256 is used.
+    pub async fn reload_pack(
+        &self,
+        artifact: &Path,
+        digest: Option<String>,
+    ) -> Result<ReloadedPack> {
+        let pack = PackRuntime::load(
+            artifact,
+            Arc::clone(&self.config),
+            self.mocks.clone(),
+            Some(artifact),
+            Some(Arc::clone(&self.stores.session_store)),
+            Some(Arc::clone(&self.stores.state_store)),
+            self.main_pack().wasi_policy(),
+            Arc::clone(&self.secrets),
+            self.config.oauth_broker_config(),
+            true,
+            ComponentResolution::default(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load pack {} for tenant {}",
+                artifact.display(),
+                self.tenant
+            )
+        })?;
+        let pack_id = pack.metadata().pack_id.clone();
+        let index = self
+            .packs
+            .iter()
+            .position(|loaded| loaded.metadata().pack_id == pack_id)
+            .with_context(|| format!("tenant {} has no pack {pack_id} to reload", self.tenant))?;
+        let digest = match digest {
+            Some(digest) => Some(digest),
+            None => artifact_digest(artifact)?,
+        };
+
+        let pack = Arc::new(pack);
+        let mut packs = self
+            .packs
+            .iter()
+            .cloned()
+            .zip(self.digests.iter().cloned())
+            .collect::<Vec<_>>();
+        packs[index] = (Arc::clone(&pack), digest.clone());
+        let next = Self::build(
+            Arc::clone(&self.config),
+            packs,
+            self.mocks.clone(),
+            self.stores.clone(),
+            Arc::clone(&self.secrets),
+            Arc::clone(&self.operator_metrics),
+            self.contract_cache.clone(),
+            Arc::clone(&self.audit),
+            Arc::clone(&self.secrets_cache),
+            Arc::clone(&self.invocations),
+            Arc::clone(&self.redelivery),
+            Arc::clone(&self.operator_replay),
+            Arc::clone(&self.operator_rate_limits),
+            self.templates.clone(),
+        )
+        .await
+        .with_context(|| format!("failed to rebuild tenant {} runtime", self.tenant))?;
+        tracing::info!(
+            tenant = %self.tenant,
+            pack_id = %pack_id,
+            digest = digest.as_deref().unwrap_or("unknown"),
+            "pack reloaded"
+        );
+        Ok(ReloadedPack {
+            runtime: next,
+            pack,
+            digest,
+        })
     }
 
     pub fn tenant(&self) -> &str {
@@ -287,10 +631,26 @@ impl TenantRuntime {
         self.packs.iter().skip(1).cloned().collect()
     }
 
+    /// Main pack followed by the overlays, in priority order.
+    pub fn packs(&self) -> &[Arc<PackRuntime>] {
+        &self.packs
+    }
+
+    /// Digests of [`Self::packs`], in the same order.
+    pub fn pack_digests(&self) -> &[Option<String>] {
+        &self.digests
+    }
+
     pub fn engine(&self) -> &Arc<FlowEngine> {
         &self.engine
     }
 
+    /// Template engine of this tenant; custom helpers are registered here before the
+    /// first flow runs.
+    pub fn templates(&self) -> &TemplateEngine {
+        &self.templates
+    }
+
     pub fn state_machine(&self) -> &Arc<StateMachineRuntime> {
         &self.state_machine
     }
@@ -337,21 +697,185 @@ impl TenantRuntime {
         self.mocks.as_ref()
     }
 
-    pub fn register_timers(&self, handles: Vec<JoinHandle<()>>) {
-        self.timer_handles.lock().extend(handles);
+    /// Run the timers of this runtime's config, taking over those of `previous` so
+    /// that timers the reload left unchanged keep their schedule.
+    pub fn start_timers(self: &Arc<Self>, previous: Option<&TenantRuntime>) {
+        let mut scheduler = previous
+            .and_then(|previous| previous.timers.lock().take())
+            .unwrap_or_default();
+        scheduler.sync(self);
+        *self.timers.lock() = Some(scheduler);
+    }
+
+    /// Schedule ids of the timers this runtime is running.
+    pub fn timer_ids(&self) -> Vec<String> {
+        self.timers
+            .lock()
+            .as_ref()
+            .map(TimerScheduler::schedule_ids)
+            .unwrap_or_default()
+    }
+
+    pub fn stop_timers(&self) {
+        if let Some(mut scheduler) = self.timers.lock().take() {
+            scheduler.stop();
+        }
+    }
+
+    /// Make the tenant's due redeliveries on this runtime, or stop making them when its
+    /// config has no `retry.redelivery`; stored ones then wait for a config that does.
+    pub fn start_redelivery(self: &Arc<Self>) {
+        if self.config.retry.redelivery.is_some() {
+            self.redelivery.serve(self);
+        } else {
+            self.redelivery.stop();
+        }
+    }
+
+    pub fn stop_redelivery(&self) {
+        self.redelivery.stop();
+    }
+
+    /// Redeliveries shared by every runtime built for this tenant, including pack
+    /// reloads.
+    pub fn redelivery(&self) -> &Arc<RedeliveryQueue> {
+        &self.redelivery
+    }
+
+    /// Operator responses kept for replay by idempotency key, shared across pack
+    /// reloads.
+    pub fn operator_replay(&self) -> &Arc<OperatorReplay> {
+        &self.operator_replay
+    }
+
+    /// Buckets of the tenant's `rate_limits.operators`, shared across pack reloads.
+    pub fn operator_rate_limits(&self) -> &Arc<OperatorRateLimits> {
+        &self.operator_rate_limits
+    }
+
+    /// The tenant's state store, without its quota or encryption layers.
+    pub fn state_host(&self) -> &Arc<dyn StateHost> {
+        &self.stores.state_host
+    }
+
+    /// Send `on-stop` with `reason` to this runtime's packs that `next` does not serve.
+    async fn stop_retired_packs(&self, next: Option<&TenantRuntime>, reason: &str) {
+        for pack in &self.packs {
+            let retained = next.is_some_and(|next| {
+                next.packs
+                    .iter()
+                    .any(|candidate| Arc::ptr_eq(candidate, pack))
+            });
+            if !retained {
+                pack.stop_components(reason).await;
+            }
+        }
+    }
+
+    /// Drop shared contract-cache entries for digests `next` no longer serves.
+    fn retire_digests(&self, next: &TenantRuntime) {
+        let live = (0..next.packs.len())
+            .map(|index| next.resolved_digest(index))
+            .collect::<Vec<_>>();
+        for index in 0..self.packs.len() {
+            let digest = self.resolved_digest(index);
+            if !live.contains(&digest) {
+                self.contract_cache.invalidate_digest(&digest);
+            }
+        }
+    }
+
+    /// Digest reported for the pack at `index`, falling back to the main pack's, then
+    /// to the digest of the pack's components computed at load.
+    fn resolved_digest(&self, index: usize) -> String {
+        self.digests[index]
+            .clone()
+            .or_else(|| self.digest().map(ToString::to_string))
+            .unwrap_or_else(|| self.packs[index].content_digest())
+    }
+
+    /// Audit log shared by every runtime built for this tenant, including pack reloads.
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// Invocations that can be aborted by correlation id, shared like the audit log so
+    /// a reload does not orphan requests still running on the retired pack.
+    pub fn invocations(&self) -> &Arc<InvocationRegistry> {
+        &self.invocations
+    }
+
+    /// When this runtime was built: at tenant load, or by the last reload.
+    pub fn loaded_at(&self) -> OffsetDateTime {
+        self.loaded_at
+    }
+
+    /// State usage recorded in the tenant's quota ledger. The ledger is only kept up
+    /// to date while the tenant sets a `state_store.quota` limit.
+    pub fn state_usage(&self) -> Result<StateUsage> {
+        let env = std::env::var("GREENTIC_ENV").unwrap_or_else(|_| "local".to_string());
+        let ctx = TenantCtx::new(EnvId::from_str(&env)?, TenantId::from_str(&self.tenant)?);
+        quota::usage(&*self.stores.state_store, &ctx).map_err(|err| anyhow!(err.to_string()))
+    }
+
+    /// One page of the tenant's runner state keys starting with `prefix`; pass the
+    /// returned cursor back to continue the scan.
+    pub fn list_state(
+        &self,
+        prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> Result<(Vec<StateKey>, Option<Cursor>)> {
+        let env = std::env::var("GREENTIC_ENV").unwrap_or_else(|_| "local".to_string());
+        let ctx = TenantCtx::new(EnvId::from_str(&env)?, TenantId::from_str(&self.tenant)?);
+        listing::list_state(&self.stores.state_store, &ctx, prefix, cursor, limit)
+            .map_err(|err| anyhow!(err.to_string()))
+    }
+
+    /// Resolved runner secrets, shared like the audit log. Values expire after the
+    /// configured TTL and are dropped when a rotation is published; call
+    /// [`SecretsCache::invalidate_tenant`] to drop them sooner.
+    pub fn secrets_cache(&self) -> &SecretsCache {
+        &self.secrets_cache
+    }
+
+    pub async fn get_secret(&self, key: &str) -> Result<String> {
+        self.get_secret_for(key, &SecretRequester::default()).await
     }
 
-    pub fn get_secret(&self, key: &str) -> Result<String> {
+    /// Read a runner-level secret on behalf of `requester`, recording the policy
+    /// decision in the tenant audit log. Inside an invocation the value is registered
+    /// for redaction.
+    pub async fn get_secret_for(&self, key: &str, requester: &SecretRequester) -> Result<String> {
         if crate::provider_core_only::is_enabled() {
             bail!(crate::provider_core_only::blocked_message("secrets"))
         }
-        if !self.config.secrets_policy.is_allowed(key) {
-            bail!("secret {key} is not permitted by bindings policy");
+        let decision = self.config.secrets_policy.explain(key);
+        if !decision.allowed {
+            self.audit
+                .secret_access(&self.tenant, key, SecretDecision::Denied, requester);
+            bail!(
+                "secret {key} is not permitted by bindings policy (rule `{}`)",
+                decision.rule
+            );
         }
+        self.audit
+            .secret_access(&self.tenant, key, SecretDecision::Allowed, requester);
         let ctx = self.config.tenant_ctx();
-        let bytes = read_secret_blocking(&self.secrets, &ctx, RUNTIME_SECRETS_PACK_ID, key)
+        let scoped_key = secret_path_for_pack(
+            &self.config.secrets_policy,
+            &ctx,
+            RUNTIME_SECRETS_PACK_ID,
+            key,
+        )?;
+        let bytes = self
+            .secrets_cache
+            .get_or_read(&self.tenant, key, || self.secrets.read(&scoped_key))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))
             .context("failed to read secret from manager")?;
         let value = String::from_utf8(bytes).context("secret value is not valid UTF-8")?;
+        redact::register(key, &value);
         Ok(value)
     }
 
@@ -373,24 +897,181 @@ impl TenantRuntime {
             .map(|(pack, digest)| (Arc::clone(pack), digest.clone()))
     }
 
-    pub fn resolve_component(&self, component_ref: &str) -> Option<ResolvedComponent> {
-        self.pack_for_component_with_digest(component_ref)
-            .map(|(pack, digest)| ResolvedComponent {
-                digest: digest
-                    .or_else(|| self.digest().map(ToString::to_string))
-                    .unwrap_or_else(|| "unknown".to_string()),
+    /// Find the pack serving `component_ref`. The pack named by `preference` wins; if it
+    /// does not ship the component, the first pack in priority order that does is used
+    /// and a warning is logged. Without a loaded preferred pack, a component shipped by
+    /// several packs is ambiguous.
+    pub fn resolve_component(
+        &self,
+        component_ref: &str,
+        preference: PackPreference<'_>,
+    ) -> Result<ResolvedComponent, ComponentResolveError> {
+        let candidates = (0..self.packs.len())
+            .filter(|&index| self.packs[index].contains_component(component_ref))
+            .collect::<Vec<_>>();
+        let Some(&first) = candidates.first() else {
+            return Err(ComponentResolveError::NotFound {
+                component_ref: component_ref.to_string(),
+            });
+        };
+        let preferred = (0..self.packs.len()).find(|&index| self.pack_matches(index, preference));
+        match preferred {
+            Some(index) if candidates.contains(&index) => {
+                Ok(self.resolved(component_ref, index, None))
+            }
+            Some(index) => {
+                let preferred = pack_ref(&self.packs[index]);
+                tracing::warn!(
+                    tenant = %self.tenant,
+                    component_ref,
+                    preferred_pack = %preferred,
+                    resolved_pack = %pack_ref(&self.packs[first]),
+                    "preferred pack does not ship component; falling back to pack priority order"
+                );
+                Ok(self.resolved(component_ref, first, Some(preferred)))
+            }
+            None if candidates.len() > 1 => Err(ComponentResolveError::Ambiguous {
                 component_ref: component_ref.to_string(),
-                pack,
-            })
+                candidates: candidates
+                    .iter()
+                    .map(|&index| pack_ref(&self.packs[index]))
+                    .collect(),
+            }),
+            None => Ok(self.resolved(component_ref, first, None)),
+        }
+    }
+
+    fn pack_matches(&self, index: usize, preference: PackPreference<'_>) -> bool {
+        if let Some(digest) = preference.pack_digest
+            && self.digests[index].as_deref() == Some(digest)
+        {
+            return true;
+        }
+        let pack = &self.packs[index];
+        preference
+            .pack_ref
+            .is_some_and(|wanted| wanted == pack.metadata().pack_id || wanted == pack_ref(pack))
+    }
+
+    fn resolved(
+        &self,
+        component_ref: &str,
+        index: usize,
+        fallback_from: Option<String>,
+    ) -> ResolvedComponent {
+        let pack = Arc::clone(&self.packs[index]);
+        ResolvedComponent {
+            digest: self.resolved_digest(index),
+            component_ref: component_ref.to_string(),
+            pack_ref: pack_ref(&pack),
+            pack,
+            fallback_from,
+        }
+    }
+
+    fn ensure_tenant(&self, tenant: &TenantCtx) -> GResult<()> {
+        if tenant.tenant.as_str() == self.tenant {
+            return Ok(());
+        }
+        Err(RunnerError::Policy {
+            reason: format!(
+                "tenant mismatch: routing resolved `{}` but request wants `{}`",
+                self.tenant,
+                tenant.tenant.as_str()
+            ),
+        })
     }
 }
 
+/// [`RunnerApi`] scoped to this tenant: every call must name the tenant the runtime
+/// serves, and flows run through the state machine like ingress events do.
+#[async_trait]
+impl RunnerApi for TenantRuntime {
+    async fn list_flows(&self, tenant: &TenantCtx) -> GResult<Vec<FlowSummary>> {
+        self.ensure_tenant(tenant)?;
+        self.state_machine.runner().list_flows(tenant).await
+    }
+
+    async fn get_flow_schema(
+        &self,
+        tenant: &TenantCtx,
+        pack_id: &str,
+        flow_id: &str,
+    ) -> GResult<FlowSchema> {
+        self.ensure_tenant(tenant)?;
+        self.state_machine
+            .runner()
+            .get_flow_schema(tenant, pack_id, flow_id)
+            .await
+    }
+
+    async fn run_flow(&self, req: RunFlowRequest) -> GResult<RunFlowResult> {
+        self.ensure_tenant(&req.tenant)?;
+        let envelope = IngressEnvelope {
+            tenant: self.tenant.clone(),
+            env: Some(req.tenant.env.as_str().to_string()),
+            pack_id: Some(req.pack_id.clone()),
+            flow_id: req.flow_id.clone(),
+            flow_type: None,
+            action: Some("api".into()),
+            session_hint: req.session_hint.clone(),
+            provider: Some("api".into()),
+            channel: None,
+            conversation: None,
+            user: None,
+            activity_id: None,
+            timestamp: None,
+            payload: req.input,
+            metadata: None,
+            reply_scope: None,
+        }
+        .canonicalize();
+        let input = serde_json::to_value(&envelope).map_err(|err| RunnerError::Serialization {
+            reason: format!("failed to serialise ingress envelope: {err}"),
+        })?;
+        let flow_run = self.state_machine.runner().run_flow(RunFlowRequest {
+            tenant: envelope.tenant_ctx(),
+            pack_id: req.pack_id,
+            flow_id: req.flow_id,
+            input,
+            session_hint: envelope.session_hint,
+        });
+        let trace = run::current().or_else(|| {
+            self.config
+                .trace
+                .runs
+                .enabled
+                .then(RunTraceRequest::generate)
+        });
+        let Some(trace) = trace else {
+            return flow_run.await;
+        };
+        let mut result = run::scope(Arc::clone(&trace), flow_run).await?;
+        result.run_id = trace.recorded().then(|| trace.run_id().to_string());
+        Ok(result)
+    }
+}
+
+/// `pack_id@version`, the ref operator bindings use for packs without an explicit one.
+fn pack_ref(pack: &PackRuntime) -> String {
+    let metadata = pack.metadata();
+    format!("{}@{}", metadata.pack_id, metadata.version)
+}
+
 impl Drop for TenantRuntime {
     fn drop(&mut self) {
-        for handle in self.timer_handles.lock().drain(..) {
-            handle.abort();
-        }
+        self.stop_timers();
+    }
+}
+
+/// `sha256:` digest of a pack archive; directories have no single digest.
+fn artifact_digest(artifact: &Path) -> Result<Option<String>> {
+    if !artifact.is_file() {
+        return Ok(None);
     }
+    let bytes = std::fs::read(artifact)
+        .with_context(|| format!("failed to read pack {}", artifact.display()))?;
+    Ok(Some(format!("sha256:{:x}", Sha256::digest(&bytes))))
 }
 
 pub struct RateLimiter {
@@ -1,18 +1,60 @@
+pub mod aws;
+pub mod file;
+pub mod health;
+pub mod keyring;
+pub mod memory;
+pub mod policy;
+pub mod rotation;
+pub mod vault;
+
 use std::sync::Arc;
 
 use crate::runtime::block_on;
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use greentic_secrets_lib::env::EnvSecretsManager;
 use greentic_secrets_lib::{SecretScope, SecretsManager};
 use greentic_types::TenantCtx;
 
+pub use self::aws::{AwsSecretsConfig, AwsSecretsService};
+pub use self::file::{FileSecretsConfig, FileSecretsManager};
+pub use self::health::{DynSecretsHealthCheck, SecretsHealthCheck};
+pub use self::keyring::KeyringSecretsConfig;
+pub use self::memory::MemorySecretsManager;
+pub use self::policy::SecretsPolicy;
+pub use self::rotation::{RotationListener, SecretRotation};
+pub use self::vault::{VaultAuth, VaultConfig};
+
 /// Shared secrets manager handle used by the host.
 pub type DynSecretsManager = Arc<dyn SecretsManager>;
 
+/// Environment prefix of the Vault settings read by [`VaultConfig::from_env`].
+pub const VAULT_ENV_PREFIX: &str = "GREENTIC_SECRETS_VAULT";
+
+/// Environment prefix of the AWS settings read by [`AwsSecretsConfig::from_env`].
+pub const AWS_ENV_PREFIX: &str = "GREENTIC_SECRETS_AWS";
+
+/// Environment prefix of the file settings read by [`FileSecretsConfig::from_env`].
+pub const FILE_ENV_PREFIX: &str = "GREENTIC_SECRETS_FILE";
+
+/// Environment prefix of the keyring settings read by [`KeyringSecretsConfig::from_env`].
+pub const KEYRING_ENV_PREFIX: &str = "GREENTIC_SECRETS_KEYRING";
+
+/// Namespace of the secrets every tenant may read under tenant-prefix isolation, as
+/// `shared/{key}`.
+pub const SHARED_SECRETS_NAMESPACE: &str = "shared";
+
 /// Supported secrets backend kinds recognised by the runner.
 #[derive(Clone, Debug)]
 pub enum SecretsBackend {
     Env,
+    /// HashiCorp Vault KV v2; requires the `secrets-vault` feature.
+    Vault(VaultConfig),
+    /// AWS Secrets Manager or SSM Parameter Store; requires the `secrets-aws` feature.
+    Aws(AwsSecretsConfig),
+    /// One file per key under a directory, reloaded when the files change.
+    File(FileSecretsConfig),
+    /// The OS keyring; requires the `secrets-keyring` feature.
+    Keyring(KeyringSecretsConfig),
 }
 
 impl SecretsBackend {
@@ -24,6 +66,18 @@ impl SecretsBackend {
             .as_str()
         {
             "" | "env" => Ok(SecretsBackend::Env),
+            "vault" => Ok(SecretsBackend::Vault(VaultConfig::from_env(
+                VAULT_ENV_PREFIX,
+            )?)),
+            "aws" => Ok(SecretsBackend::Aws(AwsSecretsConfig::from_env(
+                AWS_ENV_PREFIX,
+            )?)),
+            "file" => Ok(SecretsBackend::File(FileSecretsConfig::from_env(
+                FILE_ENV_PREFIX,
+            )?)),
+            "keyring" => Ok(SecretsBackend::Keyring(KeyringSecretsConfig::from_env(
+                KEYRING_ENV_PREFIX,
+            )?)),
             other => Err(anyhow!("unsupported SECRETS_BACKEND `{other}`")),
         }
     }
@@ -31,20 +85,73 @@ impl SecretsBackend {
     pub fn from_config(cfg: &greentic_config_types::SecretsBackendRefConfig) -> Result<Self> {
         match cfg.kind.trim().to_ascii_lowercase().as_str() {
             "" | "none" | "env" => Ok(SecretsBackend::Env),
+            "vault" => Ok(SecretsBackend::Vault(VaultConfig::from_env(
+                VAULT_ENV_PREFIX,
+            )?)),
+            "aws" => Ok(SecretsBackend::Aws(AwsSecretsConfig::from_env(
+                AWS_ENV_PREFIX,
+            )?)),
+            "file" => Ok(SecretsBackend::File(FileSecretsConfig::from_env(
+                FILE_ENV_PREFIX,
+            )?)),
+            "keyring" => Ok(SecretsBackend::Keyring(KeyringSecretsConfig::from_env(
+                KEYRING_ENV_PREFIX,
+            )?)),
             other => Err(anyhow!("unsupported secrets backend `{other}`")),
         }
     }
 
     pub fn build_manager(&self) -> Result<DynSecretsManager> {
+        self.build_manager_with_health().map(|(manager, _)| manager)
+    }
+
+    /// Build the manager together with its health probe, both backed by one client.
+    pub fn build_manager_with_health(&self) -> Result<(DynSecretsManager, DynSecretsHealthCheck)> {
         match self {
             SecretsBackend::Env => {
                 ensure_env_secrets_allowed()?;
-                Ok(Arc::new(EnvSecretsManager) as DynSecretsManager)
+                Ok(with_health(EnvSecretsManager))
+            }
+            #[cfg(feature = "secrets-vault")]
+            SecretsBackend::Vault(config) => Ok(with_health(vault::VaultSecretsManager::new(
+                config.clone(),
+            )?)),
+            #[cfg(not(feature = "secrets-vault"))]
+            SecretsBackend::Vault(_) => Err(anyhow!(
+                "vault secrets backend requires the `secrets-vault` feature"
+            )),
+            #[cfg(feature = "secrets-aws")]
+            SecretsBackend::Aws(config) => {
+                Ok(with_health(aws::AwsSecretsManager::new(config.clone())))
+            }
+            #[cfg(not(feature = "secrets-aws"))]
+            SecretsBackend::Aws(_) => Err(anyhow!(
+                "aws secrets backend requires the `secrets-aws` feature"
+            )),
+            SecretsBackend::File(config) => {
+                Ok(with_health(FileSecretsManager::new(config.clone())?))
             }
+            #[cfg(feature = "secrets-keyring")]
+            SecretsBackend::Keyring(config) => Ok(with_health(
+                keyring::KeyringSecretsManager::new(config.clone()),
+            )),
+            #[cfg(not(feature = "secrets-keyring"))]
+            SecretsBackend::Keyring(_) => Err(anyhow!(
+                "keyring secrets backend requires the `secrets-keyring` feature"
+            )),
         }
     }
 }
 
+fn with_health<M>(manager: M) -> (DynSecretsManager, DynSecretsHealthCheck)
+where
+    M: SecretsManager + SecretsHealthCheck + 'static,
+{
+    let manager = Arc::new(manager);
+    let health: DynSecretsHealthCheck = manager.clone();
+    (manager as DynSecretsManager, health)
+}
+
 pub fn default_manager() -> Result<DynSecretsManager> {
     SecretsBackend::Env.build_manager()
 }
@@ -67,7 +174,7 @@ pub fn scoped_secret_path_for_pack(ctx: &TenantCtx, pack_id: &str, key: &str) ->
     if key.is_empty() {
         return Err(anyhow!("secret key must not be empty"));
     }
-    let safe_key = key.replace('/', ".").replace(' ', "_");
+    let safe_key = secret_name(key);
     let user = ctx.user_id.as_ref().or(ctx.user.as_ref());
     let name = if let Some(user_id) = user {
         format!("user.{}.{}", user_id.as_str(), safe_key)
@@ -92,26 +199,160 @@ pub fn scoped_secret_path_for_pack(ctx: &TenantCtx, pack_id: &str, key: &str) ->
     ))
 }
 
+/// Path of `key` with tenant-prefix isolation on.
+///
+/// The key is looked up as `{tenant}/{key}`, so the stored name is `{tenant}.{key}`
+/// whatever the backend does with the tenant segment; a flat env or file layout then
+/// holds `ACME_API_KEY` or `acme.api_key` rather than one value for every tenant. Keys
+/// under `shared/` resolve to `secrets://{env}/shared/_/{pack}/shared.{key}`, the same
+/// path for every tenant.
+pub fn isolated_secret_path_for_pack(ctx: &TenantCtx, pack_id: &str, key: &str) -> Result<String> {
+    if ctx.tenant.as_str() == SHARED_SECRETS_NAMESPACE {
+        bail!("tenant `{SHARED_SECRETS_NAMESPACE}` is reserved for shared secrets");
+    }
+    let Some(shared) = shared_secret_key(key) else {
+        let prefixed = format!("{}/{}", ctx.tenant.as_str(), key.trim());
+        return scoped_secret_path_for_pack(ctx, pack_id, &prefixed);
+    };
+    if shared.trim().is_empty() {
+        bail!("shared secret key must not be empty");
+    }
+    let pack_segment = pack_id.trim();
+    if pack_segment.is_empty() {
+        bail!("pack_id must not be empty for scoped secrets");
+    }
+    Ok(format!(
+        "secrets://{}/{SHARED_SECRETS_NAMESPACE}/_/{}/{}",
+        ctx.env.as_str(),
+        normalize_pack_segment(pack_segment),
+        secret_name(key)
+    ))
+}
+
+/// Path of `key` as `policy` namespaces it.
+pub fn secret_path_for_pack(
+    policy: &SecretsPolicy,
+    ctx: &TenantCtx,
+    pack_id: &str,
+    key: &str,
+) -> Result<String> {
+    if policy.tenant_prefix() {
+        isolated_secret_path_for_pack(ctx, pack_id, key)
+    } else {
+        scoped_secret_path_for_pack(ctx, pack_id, key)
+    }
+}
+
+/// The key within the shared namespace, e.g. `api_token` for `shared/api_token`.
+pub fn shared_secret_key(key: &str) -> Option<&str> {
+    key.trim()
+        .strip_prefix(SHARED_SECRETS_NAMESPACE)?
+        .strip_prefix('/')
+}
+
+/// The name segment a runner secret `key` is stored under.
+pub(crate) fn secret_name(key: &str) -> String {
+    key.trim().replace('/', ".").replace(' ', "_")
+}
+
+/// The segments of a `secrets://{env}/{tenant}/{team}/{pack}/{name}` path, as built by
+/// [`scoped_secret_path_for_pack`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ScopedSecretPath<'a> {
+    pub env: &'a str,
+    pub tenant: &'a str,
+    pub team: &'a str,
+    pub pack: &'a str,
+    pub name: &'a str,
+}
+
+impl<'a> ScopedSecretPath<'a> {
+    pub(crate) fn parse(uri: &'a str) -> Result<Self> {
+        let rest = uri
+            .strip_prefix("secrets://")
+            .ok_or_else(|| anyhow!("`{uri}` is not a secrets:// path"))?;
+        let segments: Vec<&str> = rest.split('/').collect();
+        let [env, tenant, team, pack, name] = segments.as_slice() else {
+            return Err(anyhow!(
+                "`{uri}` is not of the form secrets://env/tenant/team/pack/name"
+            ));
+        };
+        for segment in [env, tenant, team, pack, name] {
+            if segment.is_empty() || *segment == "." || *segment == ".." {
+                return Err(anyhow!("`{uri}` has an empty or relative segment"));
+            }
+        }
+        Ok(Self {
+            env,
+            tenant,
+            team,
+            pack,
+            name,
+        })
+    }
+
+    /// Fill `{env}`, `{tenant}`, `{team}`, `{pack}` and `{key}` (the secret name).
+    pub(crate) fn render(&self, template: &str) -> String {
+        template
+            .replace("{env}", self.env)
+            .replace("{tenant}", self.tenant)
+            .replace("{team}", self.team)
+            .replace("{pack}", self.pack)
+            .replace("{key}", self.name)
+    }
+}
+
+/// Reject placeholders in `template` other than `allowed`.
+pub(crate) fn check_template(what: &str, template: &str, allowed: &[&str]) -> Result<()> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed placeholder in {what} `{template}`"))?;
+        let name = &rest[start + 1..start + end];
+        if !allowed.contains(&name) {
+            return Err(anyhow!(
+                "unknown placeholder `{{{name}}}` in {what} `{template}`"
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+pub async fn read_secret(
+    manager: &DynSecretsManager,
+    policy: &SecretsPolicy,
+    ctx: &TenantCtx,
+    pack_id: &str,
+    key: &str,
+) -> Result<Vec<u8>> {
+    let scoped_key = secret_path_for_pack(policy, ctx, pack_id, key)?;
+    manager
+        .read(scoped_key.as_str())
+        .await
+        .map_err(|err| anyhow!(err.to_string()))
+}
+
 pub fn read_secret_blocking(
     manager: &DynSecretsManager,
+    policy: &SecretsPolicy,
     ctx: &TenantCtx,
     pack_id: &str,
     key: &str,
 ) -> Result<Vec<u8>> {
-    let scoped_key = scoped_secret_path_for_pack(ctx, pack_id, key)?;
-    let bytes =
-        block_on(manager.read(scoped_key.as_str())).map_err(|err| anyhow!(err.to_string()))?;
-    Ok(bytes)
+    block_on(read_secret(manager, policy, ctx, pack_id, key))
 }
 
 pub fn write_secret_blocking(
     manager: &DynSecretsManager,
+    policy: &SecretsPolicy,
     ctx: &TenantCtx,
     pack_id: &str,
     key: &str,
     value: &[u8],
 ) -> Result<()> {
-    let scoped_key = scoped_secret_path_for_pack(ctx, pack_id, key)?;
+    let scoped_key = secret_path_for_pack(policy, ctx, pack_id, key)?;
     block_on(manager.write(scoped_key.as_str(), value)).map_err(|err| anyhow!(err.to_string()))?;
     Ok(())
 }
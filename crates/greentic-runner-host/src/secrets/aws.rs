@@ -0,0 +1,664 @@
+//! AWS Secrets Manager and SSM Parameter Store secrets backend.
+//!
+//! Runner secret paths (`secrets://{env}/{tenant}/{team}/{pack}/{name}`) are turned
+//! into a secret or parameter name with `name_template`, which takes `{env}`,
+//! `{tenant}`, `{team}`, `{pack}` and `{key}` placeholders. With a `json_key`
+//! selector the named secret holds a JSON object: the object is flattened (nested keys
+//! joined with `.`) and the selector, rendered with the same placeholders, picks one
+//! entry. This lets one AWS secret carry every key of a pack.
+//!
+//! Credentials come from the default AWS SDK chain (environment, profile, web
+//! identity, instance metadata). Requests use the SDK's adaptive retry mode, which
+//! backs off and rate-limits the client when AWS reports throttling. Fetched secrets
+//! are cached for `cache_ttl`, keyed by AWS name, so keys sharing a JSON secret cost
+//! one call.
+//!
+//! Settings are read from `GREENTIC_SECRETS_AWS_*`; see [`AwsSecretsConfig::from_env`].
+//! The manager itself is behind the `secrets-aws` feature.
+
+use std::collections::HashMap;
+use std::env;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use parking_lot::Mutex;
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use super::{ScopedSecretPath, check_template};
+
+const DEFAULT_NAME_TEMPLATE: &str = "/greentic/{env}/{tenant}/{team}/{pack}/{key}";
+const PLACEHOLDERS: &[&str] = &["env", "tenant", "team", "pack", "key"];
+
+/// Which AWS service holds the secrets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AwsSecretsService {
+    SecretsManager,
+    /// SSM Parameter Store; values are written as `SecureString`.
+    Ssm,
+}
+
+/// AWS secrets backend settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AwsSecretsConfig {
+    pub service: AwsSecretsService,
+    /// Secret or parameter name of a runner secret.
+    pub name_template: String,
+    /// Entry of a JSON-valued secret to return; `None` returns the whole value.
+    pub json_key: Option<String>,
+    /// Overrides the region from the SDK chain.
+    pub region: Option<String>,
+    /// Custom endpoint, e.g. LocalStack.
+    pub endpoint_url: Option<String>,
+    /// How long a fetched secret is reused; zero disables caching.
+    pub cache_ttl: Duration,
+    /// Attempts per request, including the first.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl AwsSecretsConfig {
+    pub fn new(service: AwsSecretsService) -> Self {
+        Self {
+            service,
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            json_key: None,
+            region: None,
+            endpoint_url: None,
+            cache_ttl: Duration::from_secs(60),
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+        }
+    }
+
+    /// Read `{prefix}_SERVICE` (`secretsmanager` or `ssm`; default `secretsmanager`),
+    /// `{prefix}_NAME_TEMPLATE`, `{prefix}_JSON_KEY`, `{prefix}_REGION`,
+    /// `{prefix}_ENDPOINT_URL`, `{prefix}_CACHE_TTL_SECS`, `{prefix}_MAX_ATTEMPTS` and
+    /// `{prefix}_INITIAL_BACKOFF_MS`.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        Self::from_vars(prefix, |name| env::var(name).ok())
+    }
+
+    pub(crate) fn from_vars(prefix: &str, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |suffix: &str| {
+            var(&format!("{prefix}_{suffix}"))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let service = match get("SERVICE")
+            .unwrap_or_else(|| "secretsmanager".into())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "secretsmanager" | "secrets-manager" | "secrets_manager" => {
+                AwsSecretsService::SecretsManager
+            }
+            "ssm" | "parameter-store" | "parameterstore" => AwsSecretsService::Ssm,
+            other => bail!("unsupported {prefix}_SERVICE `{other}`"),
+        };
+        let mut config = AwsSecretsConfig::new(service);
+        if let Some(template) = get("NAME_TEMPLATE") {
+            config.name_template = template;
+        }
+        config.json_key = get("JSON_KEY");
+        config.region = get("REGION");
+        config.endpoint_url = get("ENDPOINT_URL");
+        if let Some(value) = get("CACHE_TTL_SECS") {
+            let secs = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_CACHE_TTL_SECS"))?;
+            config.cache_ttl = Duration::from_secs(secs);
+        }
+        if let Some(value) = get("MAX_ATTEMPTS") {
+            config.max_attempts = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_MAX_ATTEMPTS"))?;
+        }
+        if let Some(value) = get("INITIAL_BACKOFF_MS") {
+            let millis = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_INITIAL_BACKOFF_MS"))?;
+            config.initial_backoff = Duration::from_millis(millis);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_template("aws name template", &self.name_template, PLACEHOLDERS)?;
+        if let Some(selector) = &self.json_key {
+            check_template("aws json key", selector, PLACEHOLDERS)?;
+        }
+        if self.max_attempts == 0 {
+            bail!("aws max attempts must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// Where a `secrets://` URI lives: the AWS name and, with a selector, the JSON entry.
+    pub fn locate(&self, uri: &str) -> Result<AwsSecretLocation> {
+        let path = ScopedSecretPath::parse(uri)?;
+        let name = path.render(&self.name_template);
+        if name.is_empty() {
+            bail!("aws name template renders `{uri}` to an empty name");
+        }
+        Ok(AwsSecretLocation {
+            name,
+            json_key: self
+                .json_key
+                .as_deref()
+                .map(|selector| path.render(selector)),
+        })
+    }
+}
+
+/// A runner secret's place in AWS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AwsSecretLocation {
+    pub name: String,
+    pub json_key: Option<String>,
+}
+
+impl AwsSecretLocation {
+    /// The runner secret inside the fetched value: the value itself without a selector,
+    /// otherwise the selected entry of the flattened JSON object. `None` means the entry
+    /// is absent.
+    pub fn select(&self, value: &str) -> Result<Option<Vec<u8>>> {
+        let Some(key) = &self.json_key else {
+            return Ok(Some(value.as_bytes().to_vec()));
+        };
+        let json: Value = serde_json::from_str(value)
+            .with_context(|| format!("aws secret `{}` is not valid JSON", self.name))?;
+        if !json.is_object() {
+            bail!("aws secret `{}` is not a JSON object", self.name);
+        }
+        let mut flat = HashMap::new();
+        flatten("", &json, &mut flat);
+        Ok(flat.remove(key.as_str()))
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut HashMap<String, Vec<u8>>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, out);
+            }
+        }
+        Value::String(text) => {
+            out.insert(prefix.to_string(), text.as_bytes().to_vec());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string().into_bytes());
+        }
+    }
+}
+
+/// Fetched secret values by AWS name, each kept for a fixed TTL and wiped when dropped.
+pub struct AwsSecretsCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Zeroizing<String>, Instant)>>,
+}
+
+impl AwsSecretsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str, now: Instant) -> Option<Zeroizing<String>> {
+        let mut entries = self.entries.lock();
+        match entries.get(name) {
+            Some((value, expires_at)) if *expires_at > now => Some(value.clone()),
+            Some(_) => {
+                entries.remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, name: &str, value: Zeroizing<String>, now: Instant) {
+        if !self.ttl.is_zero() {
+            self.entries
+                .lock()
+                .insert(name.to_string(), (value, now + self.ttl));
+        }
+    }
+
+    pub fn invalidate(&self, name: &str) {
+        self.entries.lock().remove(name);
+    }
+}
+
+#[cfg(feature = "secrets-aws")]
+pub use self::manager::AwsSecretsManager;
+
+#[cfg(feature = "secrets-aws")]
+mod manager {
+    use std::time::Instant;
+
+    use async_trait::async_trait;
+    use aws_config::retry::RetryConfig;
+    use aws_config::{BehaviorVersion, Region};
+    use aws_sdk_secretsmanager::error::ProvideErrorMetadata;
+    use aws_sdk_ssm::types::ParameterType;
+    use greentic_secrets_lib::{SecretError, SecretsManager};
+    use serde_json::Value;
+    use tokio::sync::OnceCell;
+    use zeroize::Zeroizing;
+
+    use super::{AwsSecretLocation, AwsSecretsCache, AwsSecretsConfig, AwsSecretsService};
+    use crate::secrets::health::SecretsHealthCheck;
+
+    enum AwsClient {
+        SecretsManager(aws_sdk_secretsmanager::Client),
+        Ssm(aws_sdk_ssm::Client),
+    }
+
+    /// Reads and writes runner secrets in AWS Secrets Manager or SSM Parameter Store.
+    pub struct AwsSecretsManager {
+        config: AwsSecretsConfig,
+        client: OnceCell<AwsClient>,
+        cache: AwsSecretsCache,
+    }
+
+    impl AwsSecretsManager {
+        /// Build the manager; the SDK chain is resolved when the first secret is used.
+        pub fn new(config: AwsSecretsConfig) -> Self {
+            let cache = AwsSecretsCache::new(config.cache_ttl);
+            Self {
+                config,
+                client: OnceCell::new(),
+                cache,
+            }
+        }
+
+        pub fn config(&self) -> &AwsSecretsConfig {
+            &self.config
+        }
+
+        async fn client(&self) -> &AwsClient {
+            self.client
+                .get_or_init(|| async {
+                    let retry = RetryConfig::adaptive()
+                        .with_max_attempts(self.config.max_attempts)
+                        .with_initial_backoff(self.config.initial_backoff);
+                    let mut loader =
+                        aws_config::defaults(BehaviorVersion::latest()).retry_config(retry);
+                    if let Some(region) = &self.config.region {
+                        loader = loader.region(Region::new(region.clone()));
+                    }
+                    if let Some(endpoint) = &self.config.endpoint_url {
+                        loader = loader.endpoint_url(endpoint);
+                    }
+                    let sdk = loader.load().await;
+                    match self.config.service {
+                        AwsSecretsService::SecretsManager => {
+                            AwsClient::SecretsManager(aws_sdk_secretsmanager::Client::new(&sdk))
+                        }
+                        AwsSecretsService::Ssm => AwsClient::Ssm(aws_sdk_ssm::Client::new(&sdk)),
+                    }
+                })
+                .await
+        }
+
+        fn locate(&self, uri: &str) -> Result<AwsSecretLocation, SecretError> {
+            self.config
+                .locate(uri)
+                .map_err(|err| SecretError::Backend(err.to_string()))
+        }
+
+        /// The raw value stored under `name`, or `None` when AWS has no such secret.
+        async fn fetch(&self, name: &str) -> Result<Option<Zeroizing<String>>, SecretError> {
+            if let Some(value) = self.cache.get(name, Instant::now()) {
+                return Ok(Some(value));
+            }
+            let value = match self.client().await {
+                AwsClient::SecretsManager(client) => {
+                    match client.get_secret_value().secret_id(name).send().await {
+                        Ok(output) => {
+                            if let Some(text) = output.secret_string() {
+                                Some(text.to_string())
+                            } else if let Some(blob) = output.secret_binary() {
+                                Some(String::from_utf8(blob.as_ref().to_vec()).map_err(|_| {
+                                    SecretError::Backend(format!(
+                                        "aws secret `{name}` holds binary data that is not UTF-8"
+                                    ))
+                                })?)
+                            } else {
+                                None
+                            }
+                        }
+                        Err(err) => {
+                            let err = err.into_service_error();
+                            if err.is_resource_not_found_exception() {
+                                None
+                            } else {
+                                return Err(aws_error("GetSecretValue", name, &err));
+                            }
+                        }
+                    }
+                }
+                AwsClient::Ssm(client) => {
+                    match client
+                        .get_parameter()
+                        .name(name)
+                        .with_decryption(true)
+                        .send()
+                        .await
+                    {
+                        Ok(output) => output
+                            .parameter()
+                            .and_then(|parameter| parameter.value())
+                            .map(str::to_string),
+                        Err(err) => {
+                            let err = err.into_service_error();
+                            if err.is_parameter_not_found() {
+                                None
+                            } else {
+                                return Err(aws_error("GetParameter", name, &err));
+                            }
+                        }
+                    }
+                }
+            };
+            let value = value.map(Zeroizing::new);
+            if let Some(value) = &value {
+                self.cache.insert(name, value.clone(), Instant::now());
+            }
+            Ok(value)
+        }
+
+        async fn store(&self, name: &str, value: &str) -> Result<(), SecretError> {
+            self.cache.invalidate(name);
+            match self.client().await {
+                AwsClient::SecretsManager(client) => {
+                    match client
+                        .put_secret_value()
+                        .secret_id(name)
+                        .secret_string(value)
+                        .send()
+                        .await
+                    {
+                        Ok(_) => Ok(()),
+                        Err(err) => {
+                            let err = err.into_service_error();
+                            if !err.is_resource_not_found_exception() {
+                                return Err(aws_error("PutSecretValue", name, &err));
+                            }
+                            client
+                                .create_secret()
+                                .name(name)
+                                .secret_string(value)
+                                .send()
+                                .await
+                                .map(|_| ())
+                                .map_err(|err| {
+                                    aws_error("CreateSecret", name, &err.into_service_error())
+                                })
+                        }
+                    }
+                }
+                AwsClient::Ssm(client) => client
+                    .put_parameter()
+                    .name(name)
+                    .value(value)
+                    .r#type(ParameterType::SecureString)
+                    .overwrite(true)
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| aws_error("PutParameter", name, &err.into_service_error())),
+            }
+        }
+
+        async fn remove(&self, name: &str) -> Result<(), SecretError> {
+            self.cache.invalidate(name);
+            match self.client().await {
+                AwsClient::SecretsManager(client) => {
+                    match client
+                        .delete_secret()
+                        .secret_id(name)
+                        .force_delete_without_recovery(true)
+                        .send()
+                        .await
+                    {
+                        Ok(_) => Ok(()),
+                        Err(err) => {
+                            let err = err.into_service_error();
+                            if err.is_resource_not_found_exception() {
+                                Ok(())
+                            } else {
+                                Err(aws_error("DeleteSecret", name, &err))
+                            }
+                        }
+                    }
+                }
+                AwsClient::Ssm(client) => match client.delete_parameter().name(name).send().await {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        let err = err.into_service_error();
+                        if err.is_parameter_not_found() {
+                            Ok(())
+                        } else {
+                            Err(aws_error("DeleteParameter", name, &err))
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Name the operation, secret name and AWS error code; AWS error messages do not
+    /// carry secret values.
+    fn aws_error(op: &str, name: &str, err: &impl ProvideErrorMetadata) -> SecretError {
+        let code = err.code().unwrap_or("unknown");
+        if matches!(code, "ThrottlingException" | "TooManyRequestsException") {
+            tracing::warn!(op, name, "aws throttled secrets request after retries");
+        }
+        SecretError::Backend(format!(
+            "aws {op} `{name}` failed ({code}): {}",
+            err.message().unwrap_or("no message")
+        ))
+    }
+
+    /// Lists at most one secret or parameter, which proves credentials and reachability
+    /// without touching a value.
+    #[async_trait]
+    impl SecretsHealthCheck for AwsSecretsManager {
+        async fn healthcheck(&self) -> Result<(), SecretError> {
+            match self.client().await {
+                AwsClient::SecretsManager(client) => client
+                    .list_secrets()
+                    .max_results(1)
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| aws_error("ListSecrets", "*", &err.into_service_error())),
+                AwsClient::Ssm(client) => client
+                    .describe_parameters()
+                    .max_results(1)
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| aws_error("DescribeParameters", "*", &err.into_service_error())),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SecretsManager for AwsSecretsManager {
+        async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+            let location = self.locate(path)?;
+            let Some(value) = self.fetch(&location.name).await? else {
+                return Err(SecretError::NotFound(path.to_string()));
+            };
+            location
+                .select(&value)
+                .map_err(|err| SecretError::Backend(err.to_string()))?
+                .ok_or_else(|| SecretError::NotFound(path.to_string()))
+        }
+
+        async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+            let location = self.locate(path)?;
+            let text = std::str::from_utf8(bytes).map_err(|_| {
+                SecretError::Backend(format!("aws secret `{}` must be UTF-8", location.name))
+            })?;
+            let Some(key) = &location.json_key else {
+                return self.store(&location.name, text).await;
+            };
+            // Selected entries are updated in place; other keys of the object are kept.
+            let mut object = match self.fetch(&location.name).await? {
+                Some(value) => serde_json::from_str::<Value>(&value)
+                    .ok()
+                    .and_then(|value| value.as_object().cloned())
+                    .ok_or_else(|| {
+                        SecretError::Backend(format!(
+                            "aws secret `{}` is not a JSON object",
+                            location.name
+                        ))
+                    })?,
+                None => Default::default(),
+            };
+            object.insert(key.clone(), Value::String(text.to_string()));
+            let value = Zeroizing::new(Value::Object(object).to_string());
+            self.store(&location.name, &value).await
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), SecretError> {
+            let location = self.locate(path)?;
+            let Some(key) = &location.json_key else {
+                return self.remove(&location.name).await;
+            };
+            let Some(value) = self.fetch(&location.name).await? else {
+                return Ok(());
+            };
+            let Some(mut object) = serde_json::from_str::<Value>(&value)
+                .ok()
+                .and_then(|value| value.as_object().cloned())
+            else {
+                return Ok(());
+            };
+            if object.remove(key).is_none() {
+                return Ok(());
+            }
+            let value = Zeroizing::new(Value::Object(object).to_string());
+            self.store(&location.name, &value).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<AwsSecretsConfig> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (format!("AWS_TEST_{key}"), value.to_string()))
+            .collect();
+        AwsSecretsConfig::from_vars("AWS_TEST", |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn service_defaults_to_secrets_manager() {
+        let config = config_from(&[]).unwrap();
+        assert_eq!(config.service, AwsSecretsService::SecretsManager);
+        assert_eq!(config.name_template, DEFAULT_NAME_TEMPLATE);
+
+        let config = config_from(&[("SERVICE", "ssm"), ("REGION", "eu-west-1")]).unwrap();
+        assert_eq!(config.service, AwsSecretsService::Ssm);
+        assert_eq!(config.region.as_deref(), Some("eu-west-1"));
+
+        assert!(config_from(&[("SERVICE", "kms")]).is_err());
+        assert!(config_from(&[("NAME_TEMPLATE", "/greentic/{flow}/{key}")]).is_err());
+    }
+
+    #[test]
+    fn name_template_renders_scope_and_key() {
+        let mut config = AwsSecretsConfig::new(AwsSecretsService::SecretsManager);
+        config.name_template = "/greentic/{tenant}/{key}".into();
+        let location = config
+            .locate("secrets://prod/acme/_/billing/api_key")
+            .unwrap();
+        assert_eq!(location.name, "/greentic/acme/api_key");
+        assert_eq!(location.json_key, None);
+
+        let location = AwsSecretsConfig::new(AwsSecretsService::Ssm)
+            .locate("secrets://prod/acme/ops/billing/api_key")
+            .unwrap();
+        assert_eq!(location.name, "/greentic/prod/acme/ops/billing/api_key");
+
+        assert!(config.locate("secrets://prod/acme/api_key").is_err());
+        assert!(
+            config
+                .locate("secrets://prod/../_/billing/api_key")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn json_selector_picks_flattened_entry() {
+        let mut config = AwsSecretsConfig::new(AwsSecretsService::SecretsManager);
+        config.name_template = "/greentic/{tenant}/{pack}".into();
+        config.json_key = Some("{key}".into());
+        let location = config
+            .locate("secrets://prod/acme/_/billing/stripe.api_key")
+            .unwrap();
+        assert_eq!(location.name, "/greentic/acme/billing");
+        assert_eq!(location.json_key.as_deref(), Some("stripe.api_key"));
+
+        let value = r#"{"stripe": {"api_key": "sk_live", "retries": 3}, "other": "x"}"#;
+        assert_eq!(location.select(value).unwrap(), Some(b"sk_live".to_vec()));
+
+        let retries = AwsSecretLocation {
+            name: location.name.clone(),
+            json_key: Some("stripe.retries".into()),
+        };
+        assert_eq!(retries.select(value).unwrap(), Some(b"3".to_vec()));
+
+        let missing = AwsSecretLocation {
+            name: location.name.clone(),
+            json_key: Some("absent".into()),
+        };
+        assert_eq!(missing.select(value).unwrap(), None);
+        assert!(location.select("not json").is_err());
+    }
+
+    #[test]
+    fn cache_serves_until_ttl() {
+        let cache = AwsSecretsCache::new(Duration::from_secs(30));
+        let start = Instant::now();
+        cache.insert("/greentic/acme/api_key", Zeroizing::new("v1".into()), start);
+
+        let hit = cache.get("/greentic/acme/api_key", start + Duration::from_secs(29));
+        assert_eq!(hit.as_deref().map(String::as_str), Some("v1"));
+        assert!(
+            cache
+                .get("/greentic/acme/api_key", start + Duration::from_secs(30))
+                .is_none()
+        );
+        // Expired entries are dropped, not revived.
+        assert!(cache.get("/greentic/acme/api_key", start).is_none());
+
+        cache.insert("/greentic/acme/api_key", Zeroizing::new("v2".into()), start);
+        cache.invalidate("/greentic/acme/api_key");
+        assert!(cache.get("/greentic/acme/api_key", start).is_none());
+    }
+
+    #[test]
+    fn zero_ttl_disables_cache() {
+        let cache = AwsSecretsCache::new(Duration::ZERO);
+        let now = Instant::now();
+        cache.insert("/greentic/acme/api_key", Zeroizing::new("v1".into()), now);
+        assert!(cache.get("/greentic/acme/api_key", now).is_none());
+    }
+}
@@ -0,0 +1,324 @@
+//! Secrets read from files, as mounted by Kubernetes secret volumes.
+//!
+//! The secret `secrets://{env}/{tenant}/{team}/{pack}/{name}` is the file `{root}/{name}`,
+//! or `{root}/{tenant}/{name}` with `per_tenant`. Files are read on first use and
+//! cached. A filesystem watcher on the root drops the whole cache once changes have
+//! been quiet for `debounce`, so rotated values are served without a restart. Kubernetes
+//! rotates by pointing the `..data` symlink at a new directory; the key files are
+//! symlinks through `..data`, so reads follow the swap and the rename of `..data` is
+//! the event that clears the cache. Cached secrets whose file now holds something else
+//! are published as rotations; see [`super::rotation`].
+//!
+//! Settings are read from `GREENTIC_SECRETS_FILE_*`; see [`FileSecretsConfig::from_env`].
+
+use std::collections::HashMap;
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use greentic_secrets_lib::{SecretError, SecretsManager};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use zeroize::Zeroizing;
+
+use super::ScopedSecretPath;
+use super::health::SecretsHealthCheck;
+use super::rotation::{self, RotationListener, SecretRotation, rotated};
+
+/// File secrets backend settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSecretsConfig {
+    /// Directory holding one file per secret key.
+    pub root: PathBuf,
+    /// Look in `{root}/{tenant}` instead of `root`.
+    pub per_tenant: bool,
+    /// Quiet period after a change before cached values are dropped.
+    pub debounce: Duration,
+}
+
+impl FileSecretsConfig {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            per_tenant: false,
+            debounce: Duration::from_millis(200),
+        }
+    }
+
+    /// Read `{prefix}_ROOT` (required), `{prefix}_PER_TENANT` and `{prefix}_DEBOUNCE_MS`.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        let get = |suffix: &str| {
+            env::var(format!("{prefix}_{suffix}"))
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let root = get("ROOT").with_context(|| format!("{prefix}_ROOT is required"))?;
+        let mut config = FileSecretsConfig::new(root);
+        if let Some(value) = get("PER_TENANT") {
+            config.per_tenant = match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => true,
+                "0" | "false" | "no" => false,
+                other => bail!("invalid {prefix}_PER_TENANT `{other}`"),
+            };
+        }
+        if let Some(value) = get("DEBOUNCE_MS") {
+            let millis = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_DEBOUNCE_MS"))?;
+            config.debounce = Duration::from_millis(millis);
+        }
+        Ok(config)
+    }
+
+    /// The file holding a `secrets://` URI.
+    pub fn file_path(&self, uri: &str) -> Result<PathBuf> {
+        let path = ScopedSecretPath::parse(uri)?;
+        // Dot files are the mount's own bookkeeping (`..data`, timestamped dirs).
+        if path.name.starts_with('.') {
+            bail!("`{uri}` names a hidden file");
+        }
+        let dir = if self.per_tenant {
+            if path.tenant.starts_with('.') {
+                bail!("`{uri}` names a hidden tenant directory");
+            }
+            self.root.join(path.tenant)
+        } else {
+            self.root.clone()
+        };
+        Ok(dir.join(path.name))
+    }
+}
+
+/// Values read so far; `generation` moves on every invalidation so a read that raced
+/// with a change does not put the old value back.
+#[derive(Default)]
+struct FileCache {
+    /// Keyed by `secrets://` URI, so a change can be reported per tenant.
+    entries: HashMap<String, Zeroizing<Vec<u8>>>,
+    generation: u64,
+}
+
+impl RotationListener for Mutex<FileCache> {
+    fn secret_rotated(&self, rotation: &SecretRotation) {
+        let mut cache = self.lock();
+        cache.entries.retain(|uri, _| !rotated(uri, rotation));
+        cache.generation = cache.generation.wrapping_add(1);
+    }
+}
+
+/// Reads runner secrets from files under a root directory and follows rotations.
+pub struct FileSecretsManager {
+    config: FileSecretsConfig,
+    cache: Arc<Mutex<FileCache>>,
+    /// Held for its lifetime; the lock only makes the manager `Sync`.
+    _watcher: Mutex<RecommendedWatcher>,
+}
+
+impl FileSecretsManager {
+    /// Start watching `config.root`, which must exist.
+    pub fn new(config: FileSecretsConfig) -> Result<Self> {
+        if !config.root.is_dir() {
+            bail!("secrets root {} is not a directory", config.root.display());
+        }
+        let cache = Arc::new(Mutex::new(FileCache::default()));
+        rotation::listen(&cache);
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .context("failed to create secrets file watcher")?;
+        watcher
+            .watch(&config.root, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", config.root.display()))?;
+
+        let debounce = config.debounce;
+        let invalidate = Arc::clone(&cache);
+        let watched = config.clone();
+        std::thread::Builder::new()
+            .name("greentic-secrets-file-watch".into())
+            .spawn(move || {
+                // Ends when the watcher, and with it the sender, is dropped.
+                while let Ok(event) = rx.recv() {
+                    if let Err(err) = event {
+                        tracing::warn!(error = %err, "secrets file watcher error");
+                    }
+                    loop {
+                        match rx.recv_timeout(debounce) {
+                            Ok(_) => continue,
+                            Err(RecvTimeoutError::Timeout) => break,
+                            Err(RecvTimeoutError::Disconnected) => return,
+                        }
+                    }
+                    let stale = {
+                        let mut cache = invalidate.lock();
+                        cache.generation = cache.generation.wrapping_add(1);
+                        std::mem::take(&mut cache.entries)
+                    };
+                    tracing::debug!("secrets files changed; cache cleared");
+                    for (uri, value) in stale {
+                        let current = watched
+                            .file_path(&uri)
+                            .ok()
+                            .and_then(|file| std::fs::read(file).ok());
+                        if current.as_deref() != Some(value.as_slice()) {
+                            rotation::publish_uri(&uri);
+                        }
+                    }
+                }
+            })
+            .context("failed to spawn secrets file watcher")?;
+
+        Ok(Self {
+            config,
+            cache,
+            _watcher: Mutex::new(watcher),
+        })
+    }
+
+    pub fn config(&self) -> &FileSecretsConfig {
+        &self.config
+    }
+
+    fn file_path(&self, uri: &str) -> Result<PathBuf, SecretError> {
+        self.config
+            .file_path(uri)
+            .map_err(|err| SecretError::Backend(err.to_string()))
+    }
+
+    fn forget(&self, uri: &str) {
+        let mut cache = self.cache.lock();
+        cache.entries.remove(uri);
+        cache.generation = cache.generation.wrapping_add(1);
+    }
+}
+
+#[async_trait]
+impl SecretsManager for FileSecretsManager {
+    async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+        let file = self.file_path(path)?;
+        let generation = {
+            let cache = self.cache.lock();
+            if let Some(value) = cache.entries.get(path) {
+                return Ok(value.to_vec());
+            }
+            cache.generation
+        };
+        let value = match tokio::fs::read(&file).await {
+            Ok(value) => Zeroizing::new(value),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(SecretError::NotFound(path.to_string()));
+            }
+            Err(err) => {
+                return Err(SecretError::Backend(format!(
+                    "failed to read secret file {}: {err}",
+                    file.display()
+                )));
+            }
+        };
+        let mut cache = self.cache.lock();
+        if cache.generation == generation {
+            cache.entries.insert(path.to_string(), value.clone());
+        }
+        Ok(value.to_vec())
+    }
+
+    async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+        let file = self.file_path(path)?;
+        let dir = file.parent().unwrap_or(&self.config.root).to_path_buf();
+        let bytes = bytes.to_vec();
+        let target = file.clone();
+        // Write beside the target and rename, so readers never see a partial value.
+        tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            std::fs::create_dir_all(&dir)?;
+            let mut tmp = tempfile::NamedTempFile::new_in(&dir)?;
+            std::io::Write::write_all(&mut tmp, &bytes)?;
+            tmp.persist(&target).map_err(|err| err.error)?;
+            Ok(())
+        })
+        .await
+        .map_err(|err| SecretError::Backend(err.to_string()))?
+        .map_err(|err| {
+            SecretError::Backend(format!(
+                "failed to write secret file {}: {err}",
+                file.display()
+            ))
+        })?;
+        self.forget(path);
+        rotation::publish_uri(path);
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), SecretError> {
+        let file = self.file_path(path)?;
+        let result = tokio::fs::remove_file(&file).await;
+        self.forget(path);
+        match result {
+            Ok(()) => {
+                rotation::publish_uri(path);
+                Ok(())
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(SecretError::Backend(format!(
+                "failed to delete secret file {}: {err}",
+                file.display()
+            ))),
+        }
+    }
+}
+
+/// The root can still be listed.
+#[async_trait]
+impl SecretsHealthCheck for FileSecretsManager {
+    async fn healthcheck(&self) -> Result<(), SecretError> {
+        tokio::fs::read_dir(&self.config.root)
+            .await
+            .map(|_| ())
+            .map_err(|err| {
+                SecretError::Backend(format!(
+                    "secrets root {} is not readable: {err}",
+                    self.config.root.display()
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_path_uses_key_and_optional_tenant_dir() {
+        let mut config = FileSecretsConfig::new("/etc/greentic/secrets");
+        assert_eq!(
+            config
+                .file_path("secrets://prod/acme/_/billing/api_key")
+                .unwrap(),
+            PathBuf::from("/etc/greentic/secrets/api_key")
+        );
+
+        config.per_tenant = true;
+        assert_eq!(
+            config
+                .file_path("secrets://prod/acme/_/billing/api_key")
+                .unwrap(),
+            PathBuf::from("/etc/greentic/secrets/acme/api_key")
+        );
+
+        assert!(
+            config
+                .file_path("secrets://prod/acme/_/billing/..data")
+                .is_err()
+        );
+        assert!(
+            config
+                .file_path("secrets://prod/../_/billing/api_key")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Probes telling whether the secrets backend can serve reads.
+//!
+//! `greentic_secrets_lib::SecretsManager` has no health method, so backends implement
+//! [`SecretsHealthCheck`] beside it: Vault asks `sys/health`, AWS lists one secret or
+//! parameter, the file backend lists its root. The env backend, and anything without a
+//! natural probe, answers with a static ok ([`AlwaysHealthy`]).
+//!
+//! The host probes once when it starts and then every
+//! `GREENTIC_SECRETS_HEALTH_INTERVAL_SECS` (default 30, `0` disables the monitor). Each
+//! outcome is recorded on [`HealthState`], which feeds `/healthz`, `/readyz`, the tenant
+//! stats endpoint and the `greentic_secrets_backend_*` metrics.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use greentic_secrets_lib::SecretError;
+use greentic_secrets_lib::env::EnvSecretsManager;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+
+use crate::http::health::HealthState;
+
+/// Probe interval used when `GREENTIC_SECRETS_HEALTH_INTERVAL_SECS` is unset.
+pub const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Cheap backend-specific probe; never reads a secret value.
+#[async_trait]
+pub trait SecretsHealthCheck: Send + Sync {
+    async fn healthcheck(&self) -> Result<(), SecretError>;
+}
+
+pub type DynSecretsHealthCheck = Arc<dyn SecretsHealthCheck>;
+
+/// Static ok for backends without a natural probe.
+pub struct AlwaysHealthy;
+
+#[async_trait]
+impl SecretsHealthCheck for AlwaysHealthy {
+    async fn healthcheck(&self) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+/// Environment variables are always there to read.
+#[async_trait]
+impl SecretsHealthCheck for EnvSecretsManager {
+    async fn healthcheck(&self) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+/// Outcome of the probes run so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SecretsHealthSnapshot {
+    pub checks: u64,
+    pub last_success: Option<OffsetDateTime>,
+    pub consecutive_failures: u64,
+    pub last_error: Option<String>,
+}
+
+impl SecretsHealthSnapshot {
+    /// Healthy until a probe fails, and again after the next one succeeds.
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+
+    pub fn record(&mut self, result: Result<(), &SecretError>) {
+        self.checks += 1;
+        match result {
+            Ok(()) => {
+                self.last_success = Some(OffsetDateTime::now_utc());
+                self.consecutive_failures = 0;
+                self.last_error = None;
+            }
+            Err(err) => {
+                self.consecutive_failures += 1;
+                self.last_error = Some(err.to_string());
+            }
+        }
+    }
+}
+
+/// `GREENTIC_SECRETS_HEALTH_INTERVAL_SECS`, or `None` when set to `0`.
+pub fn health_interval_from_env() -> Option<Duration> {
+    match std::env::var("GREENTIC_SECRETS_HEALTH_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+    {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(DEFAULT_HEALTH_INTERVAL),
+    }
+}
+
+/// Run `check` once and record the outcome on `health`.
+pub async fn probe(
+    check: &dyn SecretsHealthCheck,
+    health: &HealthState,
+) -> Result<(), SecretError> {
+    let result = check.healthcheck().await;
+    let was_healthy = health.secrets_health().is_healthy();
+    health.record_secrets_check(result.as_ref().map(|_| ()));
+    match &result {
+        Ok(()) if !was_healthy => tracing::info!("secrets backend healthy again"),
+        Ok(()) => {}
+        Err(err) => tracing::warn!(error = %err, "secrets backend health check failed"),
+    }
+    result
+}
+
+/// Probe every `interval` until the handle is aborted; the first probe runs after one
+/// interval, the host having probed at start.
+pub fn spawn_monitor(
+    check: DynSecretsHealthCheck,
+    health: Arc<HealthState>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let _ = probe(check.as_ref(), &health).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_count_until_the_next_success() {
+        let mut snapshot = SecretsHealthSnapshot::default();
+        assert!(snapshot.is_healthy());
+        let err = SecretError::Backend("vault unreachable".into());
+        snapshot.record(Err(&err));
+        snapshot.record(Err(&err));
+        assert!(!snapshot.is_healthy());
+        assert_eq!(snapshot.consecutive_failures, 2);
+        assert!(snapshot.last_success.is_none());
+
+        snapshot.record(Ok(()));
+        assert!(snapshot.is_healthy());
+        assert_eq!(snapshot.checks, 3);
+        assert!(snapshot.last_success.is_some());
+        assert!(snapshot.last_error.is_none());
+    }
+}
@@ -0,0 +1,303 @@
+//! OS keyring secrets backend for developer machines.
+//!
+//! The secret `secrets://{env}/{tenant}/{team}/{pack}/{name}` is the keyring entry with
+//! service `{prefix}:{tenant}:{name}` and account `{env}`, so a token stored once is
+//! seen by every pack of the tenant, as with environment variables. The prefix defaults
+//! to `greentic`. Entries go to the macOS Keychain, the Windows Credential Manager or
+//! the Secret Service on Linux; `greentic-runner secrets set/get/delete` manages them.
+//!
+//! Headless Linux often has no Secret Service to talk to. [`probe`] reports that
+//! without failing, and [`local_backend`] then falls back to the env backend, which is
+//! how the desktop runner picks its default.
+//!
+//! Settings are read from `GREENTIC_SECRETS_KEYRING_*`; see
+//! [`KeyringSecretsConfig::from_env`]. The manager itself is behind the
+//! `secrets-keyring` feature.
+
+use std::env;
+
+use anyhow::Result;
+
+use super::{KEYRING_ENV_PREFIX, ScopedSecretPath, SecretsBackend, secret_name};
+
+/// Service prefix used when `{prefix}_SERVICE_PREFIX` is unset.
+pub const DEFAULT_SERVICE_PREFIX: &str = "greentic";
+
+/// Keyring secrets backend settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyringSecretsConfig {
+    /// First segment of every service name.
+    pub service_prefix: String,
+}
+
+impl Default for KeyringSecretsConfig {
+    fn default() -> Self {
+        Self {
+            service_prefix: DEFAULT_SERVICE_PREFIX.to_string(),
+        }
+    }
+}
+
+impl KeyringSecretsConfig {
+    /// Read `{prefix}_SERVICE_PREFIX`.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(value) = env::var(format!("{prefix}_SERVICE_PREFIX"))
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+        {
+            config.service_prefix = value;
+        }
+        Ok(config)
+    }
+
+    /// The entry holding `key` of `tenant` in `env`.
+    pub fn entry(&self, env: &str, tenant: &str, key: &str) -> KeyringEntry {
+        KeyringEntry {
+            service: format!("{}:{tenant}:{}", self.service_prefix, secret_name(key)),
+            account: env.to_string(),
+        }
+    }
+
+    /// The entry holding a `secrets://` URI.
+    pub fn entry_for_uri(&self, uri: &str) -> Result<KeyringEntry> {
+        let path = ScopedSecretPath::parse(uri)?;
+        Ok(self.entry(path.env, path.tenant, path.name))
+    }
+}
+
+/// Service and account naming one keyring entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyringEntry {
+    pub service: String,
+    pub account: String,
+}
+
+/// Backend picked for a developer machine.
+#[derive(Clone, Debug)]
+pub struct LocalBackend {
+    pub backend: SecretsBackend,
+    /// Why the keyring was passed over for the env backend.
+    pub fallback: Option<String>,
+}
+
+/// Pick the backend for a developer machine: `requested` (the `SECRETS_BACKEND` value)
+/// when set, otherwise the keyring if `probe` can reach it, otherwise env.
+pub fn local_backend<P>(requested: Option<&str>, probe: P) -> Result<LocalBackend>
+where
+    P: FnOnce(&KeyringSecretsConfig) -> Result<(), String>,
+{
+    if let Some(requested) = requested.filter(|value| !value.trim().is_empty()) {
+        return Ok(LocalBackend {
+            backend: SecretsBackend::from_env(Some(requested.to_string()))?,
+            fallback: None,
+        });
+    }
+    let config = KeyringSecretsConfig::from_env(KEYRING_ENV_PREFIX)?;
+    Ok(match probe(&config) {
+        Ok(()) => LocalBackend {
+            backend: SecretsBackend::Keyring(config),
+            fallback: None,
+        },
+        Err(reason) => LocalBackend {
+            backend: SecretsBackend::Env,
+            fallback: Some(reason),
+        },
+    })
+}
+
+/// Whether the platform keyring can be used; never fails on a missing keyring.
+#[cfg(feature = "secrets-keyring")]
+pub fn probe(config: &KeyringSecretsConfig) -> Result<(), String> {
+    manager::probe(config).map_err(|err| err.to_string())
+}
+
+/// Whether the platform keyring can be used; never fails on a missing keyring.
+#[cfg(not(feature = "secrets-keyring"))]
+pub fn probe(_config: &KeyringSecretsConfig) -> Result<(), String> {
+    Err("built without the `secrets-keyring` feature".to_string())
+}
+
+#[cfg(feature = "secrets-keyring")]
+pub use self::manager::KeyringSecretsManager;
+
+#[cfg(feature = "secrets-keyring")]
+mod manager {
+    use async_trait::async_trait;
+    use greentic_secrets_lib::{SecretError, SecretsManager};
+    use keyring::Entry;
+
+    use super::{KeyringEntry, KeyringSecretsConfig};
+    use crate::secrets::health::SecretsHealthCheck;
+    use crate::secrets::rotation;
+
+    /// Looked up by [`probe`]; it is never written.
+    const PROBE_ACCOUNT: &str = "greentic-runner";
+
+    fn backend_error(err: keyring::Error) -> SecretError {
+        SecretError::Backend(format!("keyring: {err}"))
+    }
+
+    /// A lookup of an entry that does not exist proves the keyring answers.
+    pub(super) fn probe(config: &KeyringSecretsConfig) -> Result<(), SecretError> {
+        let service = format!("{}:_probe", config.service_prefix);
+        let entry = Entry::new(&service, PROBE_ACCOUNT).map_err(backend_error)?;
+        match entry.get_secret() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(backend_error(err)),
+        }
+    }
+
+    impl KeyringEntry {
+        fn open(&self) -> Result<Entry, SecretError> {
+            Entry::new(&self.service, &self.account).map_err(backend_error)
+        }
+
+        pub fn get(&self) -> Result<Vec<u8>, SecretError> {
+            match self.open()?.get_secret() {
+                Ok(value) => Ok(value),
+                Err(keyring::Error::NoEntry) => Err(SecretError::NotFound(format!(
+                    "{} ({})",
+                    self.service, self.account
+                ))),
+                Err(err) => Err(backend_error(err)),
+            }
+        }
+
+        pub fn set(&self, value: &[u8]) -> Result<(), SecretError> {
+            self.open()?.set_secret(value).map_err(backend_error)
+        }
+
+        /// Deleting an entry that does not exist is not an error.
+        pub fn delete(&self) -> Result<(), SecretError> {
+            match self.open()?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(err) => Err(backend_error(err)),
+            }
+        }
+    }
+
+    /// Reads and writes runner secrets in the platform keyring.
+    pub struct KeyringSecretsManager {
+        config: KeyringSecretsConfig,
+    }
+
+    impl KeyringSecretsManager {
+        pub fn new(config: KeyringSecretsConfig) -> Self {
+            Self { config }
+        }
+
+        pub fn config(&self) -> &KeyringSecretsConfig {
+            &self.config
+        }
+
+        fn entry(&self, uri: &str) -> Result<KeyringEntry, SecretError> {
+            self.config
+                .entry_for_uri(uri)
+                .map_err(|err| SecretError::Backend(err.to_string()))
+        }
+    }
+
+    /// Keyring calls block on IPC with the platform service.
+    async fn blocking<T, F>(call: F) -> Result<T, SecretError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, SecretError> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(call)
+            .await
+            .map_err(|err| SecretError::Backend(err.to_string()))?
+    }
+
+    #[async_trait]
+    impl SecretsManager for KeyringSecretsManager {
+        async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+            let entry = self.entry(path)?;
+            blocking(move || entry.get()).await
+        }
+
+        async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+            let entry = self.entry(path)?;
+            let bytes = bytes.to_vec();
+            blocking(move || entry.set(&bytes)).await?;
+            rotation::publish_uri(path);
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), SecretError> {
+            let entry = self.entry(path)?;
+            blocking(move || entry.delete()).await?;
+            rotation::publish_uri(path);
+            Ok(())
+        }
+    }
+
+    /// The keyring answers a lookup.
+    #[async_trait]
+    impl SecretsHealthCheck for KeyringSecretsManager {
+        async fn healthcheck(&self) -> Result<(), SecretError> {
+            let config = self.config.clone();
+            blocking(move || probe(&config)).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_named_by_tenant_and_key() {
+        let config = KeyringSecretsConfig::default();
+        assert_eq!(
+            config.entry("local", "acme", "api/token"),
+            KeyringEntry {
+                service: "greentic:acme:api.token".into(),
+                account: "local".into(),
+            }
+        );
+        assert_eq!(
+            config
+                .entry_for_uri("secrets://local/acme/_/billing/api.token")
+                .unwrap(),
+            config.entry("local", "acme", "api/token")
+        );
+        assert!(config.entry_for_uri("secrets://local/acme").is_err());
+    }
+
+    #[test]
+    fn local_backend_falls_back_to_env_without_a_keyring() {
+        let chosen = local_backend(None, |_| Ok(())).unwrap();
+        assert!(matches!(chosen.backend, SecretsBackend::Keyring(_)));
+        assert!(chosen.fallback.is_none());
+
+        let chosen = local_backend(None, |_| Err("no secret service".into())).unwrap();
+        assert!(matches!(chosen.backend, SecretsBackend::Env));
+        assert_eq!(chosen.fallback.as_deref(), Some("no secret service"));
+
+        let chosen = local_backend(Some("env"), |_| panic!("keyring probed")).unwrap();
+        assert!(matches!(chosen.backend, SecretsBackend::Env));
+        assert!(local_backend(Some("bogus"), |_| Ok(())).is_err());
+    }
+
+    #[cfg(feature = "secrets-keyring")]
+    #[test]
+    fn secrets_round_trip_when_the_keyring_is_available() {
+        let config = KeyringSecretsConfig {
+            service_prefix: format!("greentic-test-{}", std::process::id()),
+        };
+        if probe(&config).is_err() {
+            eprintln!("skipping: no platform keyring");
+            return;
+        }
+        let entry = config.entry("test", "acme", "api_token");
+        entry.set(b"tok-12345").unwrap();
+        assert_eq!(entry.get().unwrap(), b"tok-12345");
+        entry.delete().unwrap();
+        assert!(matches!(
+            entry.get(),
+            Err(greentic_secrets_lib::SecretError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+//! In-memory secrets for tests and local runs.
+//!
+//! Secrets are held by name whatever the scope, so `api/token` is served for
+//! `secrets://{env}/{tenant}/{team}/{pack}/api.token`. Names match ignoring ASCII case
+//! and punctuation, since components ask for `API_TOKEN` and the secrets-store
+//! interface reads it as `api_token`. A name the map does not hold is read from the
+//! fallback manager when there is one; writes and deletes stay in memory and never
+//! reach it.
+//!
+//! Values are never part of the `Debug` output.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use async_trait::async_trait;
+use greentic_secrets_lib::{SecretError, SecretsManager};
+use parking_lot::RwLock;
+
+use super::health::SecretsHealthCheck;
+use super::{DynSecretsManager, ScopedSecretPath, rotation};
+
+/// Serves secrets from a map, ahead of an optional fallback backend.
+pub struct MemorySecretsManager {
+    values: RwLock<BTreeMap<String, Vec<u8>>>,
+    fallback: Option<DynSecretsManager>,
+}
+
+impl MemorySecretsManager {
+    /// Seed the manager with `secrets`, keyed as a pack requests them.
+    pub fn new<K, V>(secrets: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<[u8]>,
+    {
+        let values = secrets
+            .into_iter()
+            .map(|(key, value)| (fold_name(key.as_ref()), value.as_ref().to_vec()))
+            .collect();
+        Self {
+            values: RwLock::new(values),
+            fallback: None,
+        }
+    }
+
+    /// Read names missing from the map from `fallback`.
+    pub fn with_fallback(mut self, fallback: DynSecretsManager) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    fn name(path: &str) -> Result<String, SecretError> {
+        ScopedSecretPath::parse(path)
+            .map(|path| fold_name(path.name))
+            .map_err(|err| SecretError::Backend(err.to_string()))
+    }
+}
+
+/// `API-Token`, `api/token` and `api.token` all name `api_token`.
+fn fold_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|ch| match ch.to_ascii_lowercase() {
+            ch @ ('a'..='z' | '0'..='9') => ch,
+            _ => '_',
+        })
+        .collect()
+}
+
+impl fmt::Debug for MemorySecretsManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemorySecretsManager")
+            .field("keys", &self.values.read().keys().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl SecretsManager for MemorySecretsManager {
+    async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+        let name = Self::name(path)?;
+        if let Some(value) = self.values.read().get(&name) {
+            return Ok(value.clone());
+        }
+        match &self.fallback {
+            Some(fallback) => fallback.read(path).await,
+            None => Err(SecretError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+        let name = Self::name(path)?;
+        self.values.write().insert(name, bytes.to_vec());
+        rotation::publish_uri(path);
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), SecretError> {
+        let name = Self::name(path)?;
+        self.values.write().remove(&name);
+        rotation::publish_uri(path);
+        Ok(())
+    }
+}
+
+/// The map is always there to read.
+#[async_trait]
+impl SecretsHealthCheck for MemorySecretsManager {
+    async fn healthcheck(&self) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    const PATH: &str = "secrets://local/acme/_/billing/api.token";
+
+    #[tokio::test]
+    async fn seeded_values_shadow_the_fallback() {
+        let fallback = Arc::new(MemorySecretsManager::new([
+            ("api/token", "from-backend"),
+            ("other", "kept"),
+        ]));
+        let manager =
+            MemorySecretsManager::new([("API_TOKEN", "tok-12345")]).with_fallback(fallback);
+
+        assert_eq!(manager.read(PATH).await.unwrap(), b"tok-12345");
+        assert_eq!(
+            manager
+                .read("secrets://local/acme/_/billing/other")
+                .await
+                .unwrap(),
+            b"kept"
+        );
+
+        manager.delete(PATH).await.unwrap();
+        assert_eq!(manager.read(PATH).await.unwrap(), b"from-backend");
+        assert!(matches!(
+            MemorySecretsManager::new(Vec::<(String, String)>::new())
+                .read(PATH)
+                .await,
+            Err(SecretError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn debug_output_leaves_out_values() {
+        let manager = MemorySecretsManager::new([("api/token", "tok-12345")]);
+        let debug = format!("{manager:?}");
+        assert!(debug.contains("api_token"));
+        assert!(!debug.contains("tok-12345"));
+    }
+}
@@ -0,0 +1,405 @@
+//! Which runner secrets a tenant may read.
+//!
+//! A policy is an ordered list of allow/deny glob rules plus a default. Among the
+//! rules matching a key the most specific one wins, specificity being the number of
+//! literal characters in its pattern; on a tie deny beats allow, then the earlier rule
+//! wins. `*` matches any run of characters, `?` exactly one, and matching is
+//! case-sensitive. So with `allow: SLACK_*` and `deny: "*_PROD_*"`,
+//! `SLACK_PROD_TOKEN` is denied (six literals each) while an `allow:
+//! SLACK_PROD_TOKEN` rule would win again. [`SecretsPolicy::explain`] reports which
+//! rule decided.
+//!
+//! Rules see the key as written in bindings; tenant-prefix isolation and the `shared/`
+//! namespace are described on [`SecretsPolicy::with_tenant_prefix`].
+
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+
+use super::shared_secret_key;
+
+/// Rule id reported when no rule matches.
+pub const DEFAULT_RULE_ID: &str = "default";
+
+/// Rule id reported for `shared/` keys decided by the `shared` list.
+pub const SHARED_RULE_ID: &str = "shared";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretRuleEffect {
+    Allow,
+    #[default]
+    Deny,
+}
+
+/// One `secrets.rules` entry; exactly one of `allow` and `deny` is set.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SecretRuleConfig {
+    /// Reported by [`SecretsPolicy::explain`]; defaults to `rules[<index>]`.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub allow: Option<String>,
+    #[serde(default)]
+    pub deny: Option<String>,
+}
+
+/// `secrets` bindings block.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SecretsPolicyConfig {
+    /// Effect for keys no rule matches.
+    #[serde(default)]
+    pub default: SecretRuleEffect,
+    #[serde(default)]
+    pub rules: Vec<SecretRuleConfig>,
+    /// Namespace every secret lookup under the tenant.
+    #[serde(default)]
+    pub tenant_prefix: bool,
+    /// Keys readable as `shared/{key}` when `tenant_prefix` is on.
+    #[serde(default)]
+    pub shared: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretRule {
+    pub id: String,
+    pub effect: SecretRuleEffect,
+    pub pattern: String,
+}
+
+impl SecretRule {
+    pub fn allow(id: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            effect: SecretRuleEffect::Allow,
+            pattern: pattern.into(),
+        }
+    }
+
+    pub fn deny(id: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            effect: SecretRuleEffect::Deny,
+            pattern: pattern.into(),
+        }
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        glob_match(&self.pattern, key)
+    }
+
+    /// Literal characters in the pattern; more is more specific.
+    pub fn specificity(&self) -> usize {
+        self.pattern
+            .chars()
+            .filter(|ch| !matches!(ch, '*' | '?'))
+            .count()
+    }
+}
+
+/// Outcome of [`SecretsPolicy::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Id of the deciding rule, [`DEFAULT_RULE_ID`] or [`SHARED_RULE_ID`].
+    pub rule: String,
+    /// Pattern of the deciding rule, if a rule decided.
+    pub pattern: Option<String>,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.allowed { "allowed" } else { "denied" };
+        match &self.pattern {
+            Some(pattern) => write!(f, "{verdict} by rule `{}` ({pattern})", self.rule),
+            None => write!(f, "{verdict} by rule `{}`", self.rule),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecretsPolicy {
+    rules: Vec<SecretRule>,
+    default: SecretRuleEffect,
+    /// Look every key up as `{tenant}/{key}`; see [`SecretsPolicy::with_tenant_prefix`].
+    tenant_prefix: bool,
+    /// Keys of the `shared/` namespace the tenant may read under tenant-prefix isolation.
+    shared: HashSet<String>,
+}
+
+enum DecidedBy<'a> {
+    Rule(&'a SecretRule),
+    Shared,
+    Default,
+}
+
+impl SecretsPolicy {
+    /// A policy with no rules denying every key.
+    pub fn deny_all() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: SecretRuleEffect::Deny,
+            tenant_prefix: false,
+            shared: HashSet::new(),
+        }
+    }
+
+    pub fn allow_all() -> Self {
+        Self::deny_all().with_default(SecretRuleEffect::Allow)
+    }
+
+    pub fn from_config(config: &SecretsPolicyConfig) -> Result<Self> {
+        let mut policy = Self::deny_all()
+            .with_default(config.default)
+            .with_tenant_prefix(config.tenant_prefix)
+            .with_shared(config.shared.iter().cloned());
+        for (index, rule) in config.rules.iter().enumerate() {
+            let id = rule.id.clone().unwrap_or_else(|| format!("rules[{index}]"));
+            policy = policy.with_rule(match (&rule.allow, &rule.deny) {
+                (Some(pattern), None) => SecretRule::allow(id, pattern.clone()),
+                (None, Some(pattern)) => SecretRule::deny(id, pattern.clone()),
+                _ => bail!("secrets rule `{id}` must set exactly one of `allow` and `deny`"),
+            });
+        }
+        Ok(policy)
+    }
+
+    pub fn with_default(mut self, effect: SecretRuleEffect) -> Self {
+        self.default = effect;
+        self
+    }
+
+    /// Append a rule; earlier rules win ties of equal specificity and effect.
+    pub fn with_rule(mut self, rule: SecretRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Namespace every lookup as `{tenant}/{key}`, so no key names another tenant's
+    /// secret; cross-tenant values are only reachable through `shared/`. Off by default,
+    /// which keeps flat-key deployments working.
+    pub fn with_tenant_prefix(mut self, enabled: bool) -> Self {
+        self.tenant_prefix = enabled;
+        self
+    }
+
+    /// Allow reading `shared/{key}` for each of `keys` under tenant-prefix isolation.
+    pub fn with_shared(mut self, keys: impl IntoIterator<Item = String>) -> Self {
+        self.shared.extend(keys.into_iter().map(|key| {
+            let key = key.trim();
+            shared_secret_key(key).unwrap_or(key).trim().to_string()
+        }));
+        self
+    }
+
+    pub fn tenant_prefix(&self) -> bool {
+        self.tenant_prefix
+    }
+
+    pub fn rules(&self) -> &[SecretRule] {
+        &self.rules
+    }
+
+    /// Whether the tenant may read `key`.
+    pub fn is_allowed(&self, key: &str) -> bool {
+        self.decide(key).0 == SecretRuleEffect::Allow
+    }
+
+    /// Whether the tenant may write `key`; the shared namespace is read-only.
+    pub fn is_write_allowed(&self, key: &str) -> bool {
+        let shared = self.tenant_prefix && shared_secret_key(key).is_some();
+        !shared && self.is_allowed(key)
+    }
+
+    /// The decision for `key` and the rule that made it.
+    pub fn explain(&self, key: &str) -> Decision {
+        let (effect, by) = self.decide(key);
+        let (rule, pattern) = match by {
+            DecidedBy::Rule(rule) => (rule.id.clone(), Some(rule.pattern.clone())),
+            DecidedBy::Shared => (SHARED_RULE_ID.to_string(), None),
+            DecidedBy::Default => (DEFAULT_RULE_ID.to_string(), None),
+        };
+        Decision {
+            allowed: effect == SecretRuleEffect::Allow,
+            rule,
+            pattern,
+        }
+    }
+
+    fn decide(&self, key: &str) -> (SecretRuleEffect, DecidedBy<'_>) {
+        // Under tenant-prefix isolation a `shared/` key is only allowed when listed,
+        // even by an allow-all policy; deny rules still apply to listed keys.
+        if self.tenant_prefix
+            && let Some(shared) = shared_secret_key(key)
+        {
+            if !self.shared.contains(shared.trim()) {
+                return (SecretRuleEffect::Deny, DecidedBy::Shared);
+            }
+            return match self.winning_rule(key) {
+                Some(rule) if rule.effect == SecretRuleEffect::Deny => {
+                    (SecretRuleEffect::Deny, DecidedBy::Rule(rule))
+                }
+                _ => (SecretRuleEffect::Allow, DecidedBy::Shared),
+            };
+        }
+        match self.winning_rule(key) {
+            Some(rule) => (rule.effect, DecidedBy::Rule(rule)),
+            None => (self.default, DecidedBy::Default),
+        }
+    }
+
+    fn winning_rule(&self, key: &str) -> Option<&SecretRule> {
+        let mut best: Option<&SecretRule> = None;
+        for rule in self.rules.iter().filter(|rule| rule.matches(key)) {
+            let wins = match best {
+                None => true,
+                Some(best) => {
+                    let (specificity, current) = (rule.specificity(), best.specificity());
+                    specificity > current
+                        || (specificity == current
+                            && rule.effect == SecretRuleEffect::Deny
+                            && best.effect == SecretRuleEffect::Allow)
+                }
+            };
+            if wins {
+                best = Some(rule);
+            }
+        }
+        best
+    }
+}
+
+/// Match `text` against `pattern`, where `*` is any run of characters and `?` one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it is currently matched to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(ch) if *ch == '?' || *ch == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slack_policy() -> SecretsPolicy {
+        SecretsPolicy::deny_all()
+            .with_rule(SecretRule::allow("slack", "SLACK_*"))
+            .with_rule(SecretRule::deny("no-prod", "*_PROD_*"))
+    }
+
+    #[test]
+    fn glob_edge_cases() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "A"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**", "ANY"));
+        assert!(glob_match("SLACK_*", "SLACK_"));
+        assert!(!glob_match("SLACK_*", "slack_token"));
+        assert!(glob_match("A?C", "ABC"));
+        assert!(!glob_match("A?C", "AC"));
+        assert!(glob_match("*_PROD_*", "DB_PROD_PASSWORD"));
+        assert!(!glob_match("*_PROD_*", "PROD_PASSWORD"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxa"));
+        assert!(glob_match("k?y", "kéy"));
+    }
+
+    #[test]
+    fn specific_rules_win_and_deny_breaks_ties() {
+        let policy = slack_policy();
+        assert!(policy.is_allowed("SLACK_TOKEN"));
+        assert!(!policy.is_allowed("SLACK_PROD_TOKEN"));
+        assert!(!policy.is_allowed("TEAMS_TOKEN"));
+
+        let policy = slack_policy().with_rule(SecretRule::allow("slack-prod", "SLACK_PROD_TOKEN"));
+        assert!(policy.is_allowed("SLACK_PROD_TOKEN"));
+        assert!(!policy.is_allowed("SLACK_PROD_SECRET"));
+
+        let policy = SecretsPolicy::allow_all().with_rule(SecretRule::deny("no-prod", "*_PROD_*"));
+        assert!(policy.is_allowed("API_KEY"));
+        assert!(!policy.is_allowed("API_PROD_KEY"));
+    }
+
+    #[test]
+    fn earlier_rule_wins_equal_ties() {
+        let policy = SecretsPolicy::deny_all()
+            .with_rule(SecretRule::allow("first", "A*"))
+            .with_rule(SecretRule::allow("second", "*A"));
+        assert_eq!(policy.explain("AA").rule, "first");
+    }
+
+    #[test]
+    fn explain_reports_the_deciding_rule() {
+        let policy = slack_policy();
+        let decision = policy.explain("SLACK_PROD_TOKEN");
+        assert_eq!(
+            decision,
+            Decision {
+                allowed: false,
+                rule: "no-prod".into(),
+                pattern: Some("*_PROD_*".into()),
+            }
+        );
+        assert_eq!(decision.to_string(), "denied by rule `no-prod` (*_PROD_*)");
+        assert_eq!(
+            policy.explain("OTHER").to_string(),
+            "denied by rule `default`"
+        );
+        assert_eq!(
+            SecretsPolicy::allow_all().explain("OTHER").to_string(),
+            "allowed by rule `default`"
+        );
+    }
+
+    #[test]
+    fn shared_keys_need_listing_and_respect_denies() {
+        let policy = SecretsPolicy::allow_all()
+            .with_tenant_prefix(true)
+            .with_rule(SecretRule::deny("no-prod", "*_PROD_*"))
+            .with_shared(["BASE_URL".to_string(), "shared/API_PROD_KEY".to_string()]);
+        assert_eq!(policy.explain("shared/OTHER").rule, SHARED_RULE_ID);
+        assert!(!policy.is_allowed("shared/OTHER"));
+        assert!(policy.is_allowed("shared/BASE_URL"));
+        assert!(!policy.is_write_allowed("shared/BASE_URL"));
+        assert_eq!(policy.explain("shared/API_PROD_KEY").rule, "no-prod");
+    }
+
+    #[test]
+    fn config_rules_need_one_effect() {
+        let config: SecretsPolicyConfig = serde_yaml_bw::from_str(
+            "default: deny\nrules:\n  - id: slack\n    allow: SLACK_*\n  - deny: \"*_PROD_*\"\n",
+        )
+        .unwrap();
+        let policy = SecretsPolicy::from_config(&config).unwrap();
+        assert_eq!(policy.explain("SLACK_PROD_X").rule, "rules[1]");
+        assert!(policy.is_allowed("SLACK_X"));
+
+        let config: SecretsPolicyConfig =
+            serde_yaml_bw::from_str("rules:\n  - allow: A\n    deny: B\n").unwrap();
+        assert!(SecretsPolicy::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Notifications that a runner secret changed value.
+//!
+//! Backends that notice a rotation (the file watcher, a Vault re-read returning a new
+//! version once the cached lease lapsed, a write through the manager) and the
+//! `POST /admin/secrets/rotated` endpoint call [`publish`]. Events carry the tenant and
+//! the secret name only, never the value.
+//!
+//! Anything holding a value or material derived from one registers with [`listen`] and
+//! drops it when notified: the tenant secrets cache and the state encryption data keys.
+//! Listeners run before [`publish`] returns, so the next read sees the new value.
+//! [`subscribe`] hands out a broadcast receiver for observers that only need to know.
+
+use std::sync::{Arc, Weak};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use super::{SHARED_SECRETS_NAMESPACE, ScopedSecretPath, shared_secret_key};
+
+static LISTENERS: Lazy<Mutex<Vec<Weak<dyn RotationListener>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+static EVENTS: Lazy<broadcast::Sender<SecretRotation>> = Lazy::new(|| broadcast::channel(256).0);
+
+/// A secret of `tenant` named `key` has a new value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretRotation {
+    pub tenant: String,
+    /// The name as it appears in the secret path, e.g. `api.token` for `api/token`.
+    pub key: String,
+}
+
+impl SecretRotation {
+    pub fn new(tenant: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            tenant: tenant.into(),
+            key: key.into(),
+        }
+    }
+
+    /// Whether `key`, as passed to the secrets helpers, names the rotated secret.
+    pub fn matches(&self, key: &str) -> bool {
+        key == self.key || super::secret_name(key) == self.key
+    }
+
+    /// Whether `key`, as read by `tenant`, names the rotated secret, with or without
+    /// tenant-prefix isolation. Rotations of shared secrets apply to every tenant.
+    pub fn applies_to(&self, tenant: &str, key: &str) -> bool {
+        if self.tenant == SHARED_SECRETS_NAMESPACE {
+            return shared_secret_key(key).is_some() && self.matches(key);
+        }
+        self.tenant == tenant && (self.matches(key) || self.matches(&format!("{tenant}/{key}")))
+    }
+}
+
+/// Holder of secret values, or of material derived from them, to be told of rotations.
+pub trait RotationListener: Send + Sync {
+    fn secret_rotated(&self, rotation: &SecretRotation);
+}
+
+/// Notify `listener` of rotations for as long as it is alive.
+pub fn listen<L>(listener: &Arc<L>)
+where
+    L: RotationListener + 'static,
+{
+    let listener: Arc<dyn RotationListener> = listener.clone();
+    LISTENERS.lock().push(Arc::downgrade(&listener));
+}
+
+/// Receive every rotation published from now on.
+pub fn subscribe() -> broadcast::Receiver<SecretRotation> {
+    EVENTS.subscribe()
+}
+
+/// Tell every live listener and subscriber that a secret rotated.
+pub fn publish(rotation: SecretRotation) {
+    let listeners = {
+        let mut listeners = LISTENERS.lock();
+        listeners.retain(|listener| listener.strong_count() > 0);
+        listeners
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>()
+    };
+    tracing::info!(
+        tenant = %rotation.tenant,
+        key = %rotation.key,
+        listeners = listeners.len(),
+        "secrets.rotated"
+    );
+    for listener in listeners {
+        listener.secret_rotated(&rotation);
+    }
+    // No receivers is not an error; nobody is watching.
+    let _ = EVENTS.send(rotation);
+}
+
+/// Whether the secret at a `secrets://` URI is the one `rotation` names.
+pub(crate) fn rotated(uri: &str, rotation: &SecretRotation) -> bool {
+    ScopedSecretPath::parse(uri)
+        .is_ok_and(|path| path.tenant == rotation.tenant && path.name == rotation.key)
+}
+
+/// Publish the rotation of the secret at a `secrets://` URI.
+pub(crate) fn publish_uri(uri: &str) {
+    match ScopedSecretPath::parse(uri) {
+        Ok(path) => publish(SecretRotation::new(path.tenant, path.name)),
+        Err(err) => tracing::warn!(error = %err, "rotated secret has an unparsable path"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    impl RotationListener for Counter {
+        fn secret_rotated(&self, rotation: &SecretRotation) {
+            if rotation.tenant == "rotation-test" {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_matches_raw_and_path_names() {
+        let rotation = SecretRotation::new("acme", "api.token");
+        assert!(rotation.matches("api/token"));
+        assert!(rotation.matches("api.token"));
+        assert!(!rotation.matches("api"));
+    }
+
+    #[test]
+    fn rotation_applies_to_prefixed_and_shared_keys() {
+        let rotation = SecretRotation::new("acme", "acme.api_token");
+        assert!(rotation.applies_to("acme", "api_token"));
+        assert!(!rotation.applies_to("globex", "api_token"));
+
+        let shared = SecretRotation::new("shared", "shared.api_token");
+        assert!(shared.applies_to("acme", "shared/api_token"));
+        assert!(shared.applies_to("globex", "shared/api_token"));
+        assert!(!shared.applies_to("acme", "api_token"));
+    }
+
+    #[test]
+    fn listeners_and_subscribers_see_rotations() {
+        let counter = Arc::new(Counter::default());
+        listen(&counter);
+        let mut events = subscribe();
+        publish_uri("secrets://test/rotation-test/_/runner/API_TOKEN");
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        let event = std::iter::from_fn(|| events.try_recv().ok())
+            .find(|event| event.tenant == "rotation-test");
+        assert_eq!(
+            event,
+            Some(SecretRotation::new("rotation-test", "API_TOKEN"))
+        );
+    }
+}
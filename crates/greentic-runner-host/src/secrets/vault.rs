@@ -0,0 +1,723 @@
+//! HashiCorp Vault KV v2 secrets backend.
+//!
+//! Runner secret paths (`secrets://{env}/{tenant}/{team}/{pack}/{name}`) map to one KV
+//! entry each, at `{path_template}/{name}` under the configured mount. The template
+//! takes `{env}`, `{tenant}`, `{team}` and `{pack}` placeholders, so each tenant can be
+//! given its own subtree and policy. The value lives in the entry's `value` field as a
+//! string; bytes that are not UTF-8 are written to `value_base64` instead.
+//!
+//! The client logs in lazily with a static token, the Kubernetes auth method or
+//! AppRole. Before each request it renews the token once less than a third of its TTL
+//! is left; when renewal fails (or the token is not renewable) the login methods log in
+//! again. Read values are cached for the lease Vault reports, or `cache_ttl` when the
+//! engine has no lease (KV v2 never does). A read after the lease lapsed that finds a
+//! new version, and every write or delete, is published as a rotation; see
+//! [`super::rotation`].
+//!
+//! Settings are read from `GREENTIC_SECRETS_VAULT_*`; see [`VaultConfig::from_env`].
+//! The manager itself is behind the `secrets-vault` feature.
+
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+use super::{ScopedSecretPath, check_template};
+
+const DEFAULT_MOUNT: &str = "secret";
+const DEFAULT_PATH_TEMPLATE: &str = "greentic/{env}/{tenant}/{team}/{pack}";
+const DEFAULT_KUBERNETES_JWT_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// How the runner authenticates to Vault.
+#[derive(Clone, PartialEq, Eq)]
+pub enum VaultAuth {
+    Token {
+        token: String,
+    },
+    /// Exchange the pod's service account JWT for a token.
+    Kubernetes {
+        role: String,
+        jwt_path: PathBuf,
+        /// Auth mount, `kubernetes` unless overridden.
+        mount: String,
+    },
+    AppRole {
+        role_id: String,
+        secret_id: String,
+        /// Auth mount, `approle` unless overridden.
+        mount: String,
+    },
+}
+
+impl fmt::Debug for VaultAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultAuth::Token { .. } => f.debug_struct("Token").finish_non_exhaustive(),
+            VaultAuth::Kubernetes {
+                role,
+                jwt_path,
+                mount,
+            } => f
+                .debug_struct("Kubernetes")
+                .field("role", role)
+                .field("jwt_path", jwt_path)
+                .field("mount", mount)
+                .finish(),
+            VaultAuth::AppRole { role_id, mount, .. } => f
+                .debug_struct("AppRole")
+                .field("role_id", role_id)
+                .field("mount", mount)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Vault connection settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultConfig {
+    /// Base address, e.g. `https://vault.internal:8200`.
+    pub address: String,
+    pub auth: VaultAuth,
+    /// KV v2 mount holding runner secrets.
+    pub mount: String,
+    /// Directory of a pack's secrets under the mount.
+    pub path_template: String,
+    /// Enterprise namespace sent as `X-Vault-Namespace`.
+    pub namespace: Option<String>,
+    pub request_timeout: Duration,
+    /// How long a read value is reused when Vault reports no lease; zero disables it.
+    pub cache_ttl: Duration,
+}
+
+impl VaultConfig {
+    pub fn new(address: impl Into<String>, auth: VaultAuth) -> Self {
+        Self {
+            address: address.into(),
+            auth,
+            mount: DEFAULT_MOUNT.to_string(),
+            path_template: DEFAULT_PATH_TEMPLATE.to_string(),
+            namespace: None,
+            request_timeout: Duration::from_secs(10),
+            cache_ttl: Duration::from_secs(30),
+        }
+    }
+
+    /// Read `{prefix}_ADDR` and `{prefix}_AUTH` (`token`, `kubernetes` or `approle`;
+    /// default `token`). Token auth needs `{prefix}_TOKEN`; Kubernetes needs
+    /// `{prefix}_ROLE` and takes `{prefix}_JWT_PATH`; AppRole needs `{prefix}_ROLE_ID`
+    /// and `{prefix}_SECRET_ID`. Both login methods take `{prefix}_AUTH_MOUNT`. Shared
+    /// settings are `{prefix}_{MOUNT,PATH_TEMPLATE,NAMESPACE}`,
+    /// `{prefix}_TIMEOUT_MS` and `{prefix}_CACHE_TTL_SECS`.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        Self::from_vars(prefix, |name| env::var(name).ok())
+    }
+
+    pub(crate) fn from_vars(prefix: &str, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |suffix: &str| {
+            var(&format!("{prefix}_{suffix}"))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let require =
+            |suffix: &str| get(suffix).with_context(|| format!("{prefix}_{suffix} is required"));
+        let address = require("ADDR")?;
+        let auth = match get("AUTH")
+            .unwrap_or_else(|| "token".into())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "token" => VaultAuth::Token {
+                token: require("TOKEN")?,
+            },
+            "kubernetes" | "k8s" => VaultAuth::Kubernetes {
+                role: require("ROLE")?,
+                jwt_path: get("JWT_PATH")
+                    .unwrap_or_else(|| DEFAULT_KUBERNETES_JWT_PATH.into())
+                    .into(),
+                mount: get("AUTH_MOUNT").unwrap_or_else(|| "kubernetes".into()),
+            },
+            "approle" => VaultAuth::AppRole {
+                role_id: require("ROLE_ID")?,
+                secret_id: require("SECRET_ID")?,
+                mount: get("AUTH_MOUNT").unwrap_or_else(|| "approle".into()),
+            },
+            other => bail!("unsupported {prefix}_AUTH `{other}`"),
+        };
+        let mut config = VaultConfig::new(address, auth);
+        if let Some(mount) = get("MOUNT") {
+            config.mount = mount;
+        }
+        if let Some(template) = get("PATH_TEMPLATE") {
+            config.path_template = template;
+        }
+        config.namespace = get("NAMESPACE");
+        if let Some(value) = get("TIMEOUT_MS") {
+            let millis = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_TIMEOUT_MS"))?;
+            config.request_timeout = Duration::from_millis(millis);
+        }
+        if let Some(value) = get("CACHE_TTL_SECS") {
+            let secs = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_CACHE_TTL_SECS"))?;
+            config.cache_ttl = Duration::from_secs(secs);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.address.starts_with("http://") && !self.address.starts_with("https://") {
+            bail!("vault address `{}` must be an http(s) URL", self.address);
+        }
+        if self.mount.trim_matches('/').is_empty() {
+            bail!("vault mount is empty");
+        }
+        check_template(
+            "vault path template",
+            &self.path_template,
+            &["env", "tenant", "team", "pack"],
+        )
+    }
+
+    /// The KV path (below the mount) of a `secrets://` URI.
+    pub fn kv_path(&self, uri: &str) -> Result<String> {
+        let path = ScopedSecretPath::parse(uri)?;
+        let dir = path.render(&self.path_template);
+        let dir = dir.trim_matches('/');
+        Ok(if dir.is_empty() {
+            path.name.to_string()
+        } else {
+            format!("{dir}/{}", path.name)
+        })
+    }
+}
+
+#[cfg(feature = "secrets-vault")]
+pub use self::manager::VaultSecretsManager;
+
+#[cfg(feature = "secrets-vault")]
+mod manager {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD;
+    use greentic_secrets_lib::{SecretError, SecretsManager};
+    use reqwest::{Method, StatusCode};
+    use serde_json::{Value, json};
+    use tokio::sync::Mutex as AsyncMutex;
+    use zeroize::Zeroizing;
+
+    use super::{VaultAuth, VaultConfig};
+    use crate::secrets::health::SecretsHealthCheck;
+    use crate::secrets::rotation::{self, RotationListener, SecretRotation, rotated};
+
+    /// Reads and writes runner secrets in a Vault KV v2 mount.
+    pub struct VaultSecretsManager {
+        config: VaultConfig,
+        http: reqwest::Client,
+        token: AsyncMutex<Option<VaultToken>>,
+        cache: Arc<parking_lot::Mutex<HashMap<String, CachedValue>>>,
+    }
+
+    struct VaultToken {
+        value: Zeroizing<String>,
+        renewable: bool,
+        /// `None` for tokens that never expire (TTL 0).
+        expires_at: Option<Instant>,
+        ttl: Duration,
+    }
+
+    impl VaultToken {
+        fn from_auth(auth: &Value) -> Result<Self, SecretError> {
+            let value = auth
+                .get("client_token")
+                .and_then(Value::as_str)
+                .ok_or_else(|| SecretError::Backend("vault login returned no token".into()))?;
+            let ttl = auth
+                .get("lease_duration")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            let renewable = auth
+                .get("renewable")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            Ok(Self::new(value.to_string(), ttl, renewable))
+        }
+
+        fn new(value: String, ttl_secs: u64, renewable: bool) -> Self {
+            let ttl = Duration::from_secs(ttl_secs);
+            Self {
+                value: Zeroizing::new(value),
+                renewable,
+                expires_at: (ttl_secs > 0).then(|| Instant::now() + ttl),
+                ttl,
+            }
+        }
+
+        fn needs_renewal(&self, now: Instant) -> bool {
+            self.expires_at
+                .is_some_and(|expires_at| expires_at.saturating_duration_since(now) < self.ttl / 3)
+        }
+
+        fn is_expired(&self, now: Instant) -> bool {
+            self.expires_at.is_some_and(|expires_at| expires_at <= now)
+        }
+    }
+
+    /// A failed Vault call; `message` never includes a request body.
+    struct VaultError {
+        status: Option<StatusCode>,
+        message: String,
+    }
+
+    impl From<VaultError> for SecretError {
+        fn from(err: VaultError) -> Self {
+            if err.status == Some(StatusCode::NOT_FOUND) {
+                SecretError::NotFound(err.message)
+            } else {
+                SecretError::Backend(err.message)
+            }
+        }
+    }
+
+    /// Expired entries are kept as the last version seen, to spot rotations.
+    struct CachedValue {
+        value: Zeroizing<Vec<u8>>,
+        version: Option<u64>,
+        expires_at: Instant,
+    }
+
+    /// Rotations published elsewhere expire the entry but keep its version.
+    impl RotationListener for parking_lot::Mutex<HashMap<String, CachedValue>> {
+        fn secret_rotated(&self, rotation: &SecretRotation) {
+            let now = Instant::now();
+            for (uri, entry) in self.lock().iter_mut() {
+                if rotated(uri, rotation) {
+                    entry.expires_at = entry.expires_at.min(now);
+                }
+            }
+        }
+    }
+
+    impl VaultSecretsManager {
+        /// Build the client; nothing is sent to Vault until the first secret is used.
+        pub fn new(config: VaultConfig) -> anyhow::Result<Self> {
+            let http = reqwest::Client::builder()
+                .timeout(config.request_timeout)
+                .build()?;
+            let cache = Arc::new(parking_lot::Mutex::new(HashMap::new()));
+            rotation::listen(&cache);
+            Ok(Self {
+                config,
+                http,
+                token: AsyncMutex::new(None),
+                cache,
+            })
+        }
+
+        pub fn config(&self) -> &VaultConfig {
+            &self.config
+        }
+
+        /// A usable client token, logging in or renewing first when needed.
+        async fn token(&self) -> Result<Zeroizing<String>, SecretError> {
+            let mut slot = self.token.lock().await;
+            let now = Instant::now();
+            let current = slot.take();
+            let token = match current {
+                None => self.login().await?,
+                Some(token) if !token.needs_renewal(now) => token,
+                Some(token) if token.renewable => match self.renew(&token).await {
+                    Ok(renewed) => renewed,
+                    Err(err) => {
+                        tracing::warn!(error = %err, "vault token renewal failed");
+                        self.replace_expiring(token, now).await?
+                    }
+                },
+                Some(token) => self.replace_expiring(token, now).await?,
+            };
+            let value = token.value.clone();
+            *slot = Some(token);
+            Ok(value)
+        }
+
+        /// Log in again where the auth method allows it; a static token is kept until
+        /// it expires.
+        async fn replace_expiring(
+            &self,
+            token: VaultToken,
+            now: Instant,
+        ) -> Result<VaultToken, SecretError> {
+            match &self.config.auth {
+                VaultAuth::Token { .. } if token.is_expired(now) => Err(SecretError::Backend(
+                    "vault token expired and cannot be renewed".into(),
+                )),
+                VaultAuth::Token { .. } => Ok(token),
+                _ => self.login().await,
+            }
+        }
+
+        async fn login(&self) -> Result<VaultToken, SecretError> {
+            let (path, body) = match &self.config.auth {
+                VaultAuth::Token { token } => {
+                    // Learn the TTL so the token can be renewed before it lapses.
+                    let body = self
+                        .send(Method::GET, "auth/token/lookup-self", token, None)
+                        .await?;
+                    let data = body.get("data").cloned().unwrap_or_default();
+                    return Ok(VaultToken::new(
+                        token.clone(),
+                        data.get("ttl").and_then(Value::as_u64).unwrap_or_default(),
+                        data.get("renewable")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                    ));
+                }
+                VaultAuth::Kubernetes {
+                    role,
+                    jwt_path,
+                    mount,
+                } => {
+                    let jwt = Zeroizing::new(
+                        tokio::fs::read_to_string(jwt_path)
+                            .await
+                            .map_err(|err| {
+                                SecretError::Backend(format!(
+                                    "failed to read service account token {}: {err}",
+                                    jwt_path.display()
+                                ))
+                            })?
+                            .trim()
+                            .to_string(),
+                    );
+                    (
+                        format!("auth/{}/login", mount.trim_matches('/')),
+                        Zeroizing::new(json!({ "role": role, "jwt": jwt.as_str() }).to_string()),
+                    )
+                }
+                VaultAuth::AppRole {
+                    role_id,
+                    secret_id,
+                    mount,
+                } => (
+                    format!("auth/{}/login", mount.trim_matches('/')),
+                    Zeroizing::new(
+                        json!({ "role_id": role_id, "secret_id": secret_id }).to_string(),
+                    ),
+                ),
+            };
+            let body = self.send(Method::POST, &path, "", Some(body)).await?;
+            VaultToken::from_auth(body.get("auth").unwrap_or(&Value::Null))
+        }
+
+        async fn renew(&self, token: &VaultToken) -> Result<VaultToken, SecretError> {
+            let body = self
+                .send(Method::POST, "auth/token/renew-self", &token.value, None)
+                .await?;
+            VaultToken::from_auth(body.get("auth").unwrap_or(&Value::Null))
+        }
+
+        /// Send one request; the error names the method, API path and status, plus the
+        /// messages Vault returned, never the request body.
+        async fn send(
+            &self,
+            method: Method,
+            path: &str,
+            token: &str,
+            body: Option<Zeroizing<String>>,
+        ) -> Result<Value, VaultError> {
+            let url = format!("{}/v1/{path}", self.config.address.trim_end_matches('/'));
+            let mut request = self.http.request(method.clone(), &url);
+            if !token.is_empty() {
+                request = request.header("X-Vault-Token", token);
+            }
+            if let Some(namespace) = &self.config.namespace {
+                request = request.header("X-Vault-Namespace", namespace);
+            }
+            if let Some(body) = body {
+                request = request
+                    .header("Content-Type", "application/json")
+                    .body(body.as_str().to_string());
+            }
+            let response = request.send().await.map_err(|err| VaultError {
+                status: None,
+                message: format!("vault {method} {path} failed: {err}"),
+            })?;
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            if status == StatusCode::NOT_FOUND {
+                return Err(VaultError {
+                    status: Some(status),
+                    message: path.to_string(),
+                });
+            }
+            if !status.is_success() {
+                let messages = serde_json::from_str::<Value>(&text)
+                    .ok()
+                    .and_then(|body| body.get("errors").cloned())
+                    .and_then(|errors| serde_json::from_value::<Vec<String>>(errors).ok())
+                    .filter(|errors| !errors.is_empty())
+                    .map(|errors| format!(": {}", errors.join("; ")))
+                    .unwrap_or_default();
+                return Err(VaultError {
+                    status: Some(status),
+                    message: format!("vault {method} {path} returned {status}{messages}"),
+                });
+            }
+            if text.trim().is_empty() {
+                return Ok(Value::Null);
+            }
+            serde_json::from_str(&text).map_err(|err| VaultError {
+                status: Some(status),
+                message: format!("vault {method} {path} returned invalid JSON: {err}"),
+            })
+        }
+
+        /// Send with the current token, logging in once more when a login-based token
+        /// was revoked under us.
+        async fn send_authed(
+            &self,
+            method: Method,
+            path: &str,
+            body: Option<Zeroizing<String>>,
+        ) -> Result<Value, SecretError> {
+            let token = self.token().await?;
+            match self.send(method.clone(), path, &token, body.clone()).await {
+                Err(err)
+                    if err.status == Some(StatusCode::FORBIDDEN)
+                        && !matches!(self.config.auth, VaultAuth::Token { .. }) =>
+                {
+                    *self.token.lock().await = None;
+                    let token = self.token().await?;
+                    Ok(self.send(method, path, &token, body).await?)
+                }
+                result => Ok(result?),
+            }
+        }
+
+        fn api_path(&self, kind: &str, uri: &str) -> Result<String, SecretError> {
+            let kv_path = self
+                .config
+                .kv_path(uri)
+                .map_err(|err| SecretError::Backend(err.to_string()))?;
+            Ok(format!(
+                "{}/{kind}/{kv_path}",
+                self.config.mount.trim_matches('/')
+            ))
+        }
+
+        fn cached(&self, uri: &str) -> Option<Vec<u8>> {
+            self.cache
+                .lock()
+                .get(uri)
+                .filter(|entry| entry.expires_at > Instant::now())
+                .map(|entry| entry.value.to_vec())
+        }
+    }
+
+    /// `sys/health` needs no token; standbys count as healthy since they forward reads.
+    #[async_trait]
+    impl SecretsHealthCheck for VaultSecretsManager {
+        async fn healthcheck(&self) -> Result<(), SecretError> {
+            self.send(Method::GET, "sys/health?standbyok=true", "", None)
+                .await
+                .map(|_| ())
+                .map_err(|err| SecretError::Backend(err.message))
+        }
+    }
+
+    #[async_trait]
+    impl SecretsManager for VaultSecretsManager {
+        async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+            if let Some(value) = self.cached(path) {
+                return Ok(value);
+            }
+            let api_path = self.api_path("data", path)?;
+            let body = match self.send_authed(Method::GET, &api_path, None).await {
+                Err(SecretError::NotFound(_)) => {
+                    return Err(SecretError::NotFound(path.to_string()));
+                }
+                other => other?,
+            };
+            let data = body.pointer("/data/data").unwrap_or(&Value::Null);
+            let value = if let Some(text) = data.get("value").and_then(Value::as_str) {
+                text.as_bytes().to_vec()
+            } else if let Some(encoded) = data.get("value_base64").and_then(Value::as_str) {
+                STANDARD.decode(encoded).map_err(|err| {
+                    SecretError::Backend(format!(
+                        "vault {api_path} has invalid value_base64: {err}"
+                    ))
+                })?
+            } else {
+                // KV v2 answers a deleted version with metadata but no data.
+                return Err(SecretError::NotFound(path.to_string()));
+            };
+            let lease = body
+                .get("lease_duration")
+                .and_then(Value::as_u64)
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(self.config.cache_ttl);
+            let version = body
+                .pointer("/data/metadata/version")
+                .and_then(Value::as_u64);
+            let previous = self.cache.lock().get(path).map(|entry| entry.version);
+            if previous.is_some_and(|previous| previous != version) {
+                rotation::publish_uri(path);
+            }
+            self.cache.lock().insert(
+                path.to_string(),
+                CachedValue {
+                    value: Zeroizing::new(value.clone()),
+                    version,
+                    expires_at: Instant::now() + lease,
+                },
+            );
+            Ok(value)
+        }
+
+        async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+            let api_path = self.api_path("data", path)?;
+            let data = match std::str::from_utf8(bytes) {
+                Ok(text) => json!({ "value": text }),
+                Err(_) => json!({ "value_base64": STANDARD.encode(bytes) }),
+            };
+            let body = Zeroizing::new(json!({ "data": data }).to_string());
+            self.cache.lock().remove(path);
+            self.send_authed(Method::POST, &api_path, Some(body))
+                .await?;
+            rotation::publish_uri(path);
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), SecretError> {
+            let api_path = self.api_path("data", path)?;
+            self.cache.lock().remove(path);
+            match self.send_authed(Method::DELETE, &api_path, None).await {
+                Ok(_) => {
+                    rotation::publish_uri(path);
+                    Ok(())
+                }
+                Err(SecretError::NotFound(_)) => Ok(()),
+                Err(err) => Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<VaultConfig> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (format!("VAULT_TEST_{key}"), value.to_string()))
+            .collect();
+        VaultConfig::from_vars("VAULT_TEST", |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn token_auth_is_the_default() {
+        let config = config_from(&[("ADDR", "http://127.0.0.1:8200"), ("TOKEN", "root")]).unwrap();
+        assert_eq!(
+            config.auth,
+            VaultAuth::Token {
+                token: "root".into()
+            }
+        );
+        assert_eq!(config.mount, "secret");
+        assert_eq!(config.path_template, DEFAULT_PATH_TEMPLATE);
+    }
+
+    #[test]
+    fn login_methods_need_their_credentials() {
+        let err = config_from(&[("ADDR", "http://vault:8200"), ("AUTH", "approle")]).unwrap_err();
+        assert!(err.to_string().contains("VAULT_TEST_ROLE_ID"));
+
+        let config = config_from(&[
+            ("ADDR", "http://vault:8200"),
+            ("AUTH", "kubernetes"),
+            ("ROLE", "runner"),
+            ("AUTH_MOUNT", "k8s-prod"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config.auth,
+            VaultAuth::Kubernetes {
+                role: "runner".into(),
+                jwt_path: DEFAULT_KUBERNETES_JWT_PATH.into(),
+                mount: "k8s-prod".into(),
+            }
+        );
+
+        assert!(config_from(&[("ADDR", "http://vault:8200"), ("AUTH", "ldap")]).is_err());
+        assert!(config_from(&[("TOKEN", "root")]).is_err());
+    }
+
+    #[test]
+    fn unknown_template_placeholders_are_rejected() {
+        let err = config_from(&[
+            ("ADDR", "http://vault:8200"),
+            ("TOKEN", "root"),
+            ("PATH_TEMPLATE", "runner/{tenant}/{flow}"),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("{flow}"));
+    }
+
+    #[test]
+    fn kv_path_renders_the_template() {
+        let mut config = VaultConfig::new(
+            "http://vault:8200",
+            VaultAuth::Token {
+                token: "root".into(),
+            },
+        );
+        assert_eq!(
+            config
+                .kv_path("secrets://prod/acme/_/billing/api_key")
+                .unwrap(),
+            "greentic/prod/acme/_/billing/api_key"
+        );
+
+        config.path_template = "tenants/{tenant}/".into();
+        assert_eq!(
+            config
+                .kv_path("secrets://prod/acme/_/billing/api_key")
+                .unwrap(),
+            "tenants/acme/api_key"
+        );
+
+        assert!(config.kv_path("secrets://prod/acme/_/api_key").is_err());
+        assert!(
+            config
+                .kv_path("secrets://prod/../_/billing/api_key")
+                .is_err()
+        );
+        assert!(config.kv_path("env://API_KEY").is_err());
+    }
+
+    #[test]
+    fn debug_hides_credentials() {
+        let auth = VaultAuth::AppRole {
+            role_id: "role".into(),
+            secret_id: "s3cr3t".into(),
+            mount: "approle".into(),
+        };
+        assert!(!format!("{auth:?}").contains("s3cr3t"));
+        let auth = VaultAuth::Token {
+            token: "hvs.t0ken".into(),
+        };
+        assert!(!format!("{auth:?}").contains("hvs.t0ken"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Per-invocation capture of component stdout/stderr.
+//!
+//! Every store writes its WASI stdout/stderr into a pair of bounded in-memory pipes
+//! ([`StdioCapture`]) instead of the host's own streams. When the store is dropped the
+//! captured text is published to the [`StdioSink`] of the enclosing [`scope`], where the
+//! operator picks it up for tracing spans, the `include-logs` response field and
+//! trap/host-failure diagnostics. Stores outside a scope log their output instead.
+//! Output read through [`captured`] has secret values redacted; see [`crate::redact`].
+//!
+//! A pipe holds at most [`STDIO_BUFFER_BYTES`]; a component writing past that gets a
+//! stream error on the write instead of growing host memory. Reports keep the last
+//! [`STDIO_REPORT_BYTES`] of each stream, since the end of the output is usually what
+//! explains a failure.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
+
+use crate::redact;
+
+/// Capacity of each captured stream.
+pub const STDIO_BUFFER_BYTES: usize = 1024 * 1024;
+/// Bytes of each stream kept in reports; earlier output is counted in
+/// `truncated_bytes`.
+pub const STDIO_REPORT_BYTES: usize = 4 * 1024;
+
+tokio::task_local! {
+    static CURRENT: StdioSink;
+}
+
+/// Bounded stdout/stderr pipes for one store.
+#[derive(Clone)]
+pub struct StdioCapture {
+    stdout: MemoryOutputPipe,
+    stderr: MemoryOutputPipe,
+}
+
+impl StdioCapture {
+    pub fn new() -> Self {
+        Self {
+            stdout: MemoryOutputPipe::new(STDIO_BUFFER_BYTES),
+            stderr: MemoryOutputPipe::new(STDIO_BUFFER_BYTES),
+        }
+    }
+
+    pub fn stdout(&self) -> MemoryOutputPipe {
+        self.stdout.clone()
+    }
+
+    pub fn stderr(&self) -> MemoryOutputPipe {
+        self.stderr.clone()
+    }
+
+    /// Output written so far, trimmed for reporting.
+    pub fn output(&self) -> CapturedStdio {
+        CapturedStdio::from_bytes(&self.stdout.contents(), &self.stderr.contents())
+    }
+}
+
+impl Default for StdioCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stdout/stderr text of an invocation, keeping the tail of each stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CapturedStdio {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+    /// Bytes dropped from the front of either stream.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub truncated_bytes: usize,
+}
+
+impl CapturedStdio {
+    pub fn from_bytes(stdout: &[u8], stderr: &[u8]) -> Self {
+        let mut captured = Self::default();
+        captured.append(stdout, stderr);
+        captured
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stdout.is_empty() && self.stderr.is_empty() && self.truncated_bytes == 0
+    }
+
+    /// Add the output of another store (e.g. a lifecycle hook before the invoke).
+    pub fn append(&mut self, stdout: &[u8], stderr: &[u8]) {
+        self.truncated_bytes += push_tail(&mut self.stdout, stdout);
+        self.truncated_bytes += push_tail(&mut self.stderr, stderr);
+    }
+}
+
+/// Append `bytes` to `text`, dropping the oldest output past [`STDIO_REPORT_BYTES`];
+/// returns the number of bytes dropped.
+fn push_tail(text: &mut String, bytes: &[u8]) -> usize {
+    if bytes.is_empty() {
+        return 0;
+    }
+    text.push_str(&String::from_utf8_lossy(bytes));
+    if text.len() <= STDIO_REPORT_BYTES {
+        return 0;
+    }
+    let mut cut = text.len() - STDIO_REPORT_BYTES;
+    while !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    text.drain(..cut);
+    cut
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Collects the output of every store created for one invocation.
+#[derive(Clone, Debug, Default)]
+pub struct StdioSink(Arc<Mutex<CapturedStdio>>);
+
+impl StdioSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, capture: &StdioCapture) {
+        self.0
+            .lock()
+            .append(&capture.stdout.contents(), &capture.stderr.contents());
+    }
+
+    /// Output recorded so far.
+    pub fn snapshot(&self) -> CapturedStdio {
+        self.0.lock().clone()
+    }
+
+    /// Output recorded so far, leaving the sink empty for what follows.
+    pub fn take(&self) -> CapturedStdio {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+/// Run `future` with `sink` collecting the stdio of component invocations it makes.
+pub async fn scope<F: Future>(sink: StdioSink, future: F) -> F::Output {
+    CURRENT.scope(sink, future).await
+}
+
+/// Sink of the enclosing [`scope`], if any.
+pub fn current() -> Option<StdioSink> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Output recorded by the enclosing [`scope`], scrubbed by the enclosing
+/// [`redact::scope`]; empty outside one.
+pub fn captured() -> CapturedStdio {
+    let output = current().map(|sink| sink.snapshot()).unwrap_or_default();
+    match redact::current() {
+        Some(redactor) => redactor.scrub_stdio(output),
+        None => output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_keep_the_tail_of_each_stream() {
+        let noise = "é".repeat(STDIO_REPORT_BYTES);
+        let mut captured = CapturedStdio::from_bytes(b"ready\n", noise.as_bytes());
+        captured.append(b"", b"fatal: boom\n");
+        assert_eq!(captured.stdout, "ready\n");
+        assert!(captured.stderr.len() <= STDIO_REPORT_BYTES);
+        assert!(captured.stderr.starts_with('é'));
+        assert!(captured.stderr.ends_with("éfatal: boom\n"));
+        assert_eq!(
+            captured.truncated_bytes,
+            noise.len() + "fatal: boom\n".len() - captured.stderr.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn sink_is_visible_only_inside_its_scope() {
+        assert!(current().is_none());
+        assert!(captured().is_empty());
+        let sink = StdioSink::new();
+        let seen = scope(sink.clone(), async {
+            current().expect("sink in scope").0.lock().stderr = "oops".into();
+            captured()
+        })
+        .await;
+        assert_eq!(seen.stderr, "oops");
+        assert_eq!(sink.snapshot().stderr, "oops");
+    }
+}
@@ -0,0 +1,437 @@
+//! Envelope encryption of state store values.
+//!
+//! Tenants whose `state_store.encryption` bindings block names a data key get their
+//! store wrapped in an [`EncryptedStateStore`]. Values are serialized and sealed with
+//! AES-256-GCM before they reach the backend; the stored document is a marker object
+//! carrying the key id, nonce and ciphertext. The tenant, prefix and key are bound as
+//! associated data, so a sealed value copied to another key does not decrypt.
+//!
+//! Data keys are read from the secrets manager at
+//! `secrets://{env}/{tenant}/_/runner/{key}` and must hold 32 bytes, raw or base64.
+//! The key id is derived from the key bytes, so rotating means writing a new key under
+//! a new name, pointing `key` at it and moving the old name to `previous_keys`. Reads of
+//! values sealed with a previous key, or of plaintext written before encryption was
+//! enabled, return the value and write it back sealed with the current key, keeping the
+//! TTL the key has left. Data keys are cached for a minute; a published rotation of a
+//! key drops it at once.
+//!
+//! A write whose data key cannot be read fails; it never falls back to plaintext.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{ErrorCode, GResult, GreenticError, TenantCtx};
+use parking_lot::Mutex;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+
+use crate::runtime::block_on;
+use crate::secrets::rotation::{self, RotationListener, SecretRotation};
+use crate::secrets::{DynSecretsManager, scoped_secret_path_for_pack};
+use crate::storage::listing::Cursor;
+use crate::storage::state::{self, DynStateStore, HostStateStore};
+
+/// Field marking a stored document as sealed; its value names the algorithm.
+pub const SEALED_MARKER: &str = "$greentic_enc";
+const ALGORITHM: &str = "A256GCM";
+/// Pack segment of the secret path data keys are read from.
+const KEY_SECRET_PACK: &str = "runner";
+const KEY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// `state_store.encryption` bindings block.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StateEncryption {
+    /// Secret holding the data key new values are sealed with.
+    pub key: String,
+    /// Secrets holding retired data keys, still accepted on read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_keys: Vec<String>,
+}
+
+/// Stored form of a sealed value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sealed {
+    #[serde(rename = "$greentic_enc")]
+    algorithm: String,
+    kid: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Wrap `store` so values are encrypted with the tenant's data key; without an
+/// encryption block it is returned as is.
+pub fn apply_encryption(
+    store: DynStateStore,
+    encryption: Option<&StateEncryption>,
+    secrets: DynSecretsManager,
+) -> DynStateStore {
+    match encryption {
+        Some(encryption) => {
+            let encrypted = Arc::new(EncryptedStateStore {
+                inner: store,
+                encryption: encryption.clone(),
+                secrets,
+                keys: Mutex::new(HashMap::new()),
+            });
+            rotation::listen(&encrypted);
+            encrypted
+        }
+        None => store,
+    }
+}
+
+/// Whether `stored` is a sealed value rather than plaintext JSON.
+pub fn is_sealed(stored: &Value) -> bool {
+    stored
+        .as_object()
+        .is_some_and(|map| map.contains_key(SEALED_MARKER))
+}
+
+#[derive(Clone)]
+struct DataKey {
+    kid: String,
+    bytes: [u8; 32],
+}
+
+impl DataKey {
+    fn parse(raw: &[u8]) -> Result<Self> {
+        let bytes: [u8; 32] = match <[u8; 32]>::try_from(raw) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                let text = std::str::from_utf8(raw).context("data key is not 32 bytes")?;
+                STANDARD
+                    .decode(text.trim())
+                    .context("data key is neither 32 raw bytes nor base64")?
+                    .try_into()
+                    .map_err(|_| anyhow!("data key must decode to 32 bytes"))?
+            }
+        };
+        let kid = hex::encode(&Sha256::digest(bytes)[..8]);
+        Ok(Self { kid, bytes })
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &self.bytes).expect("AES-256-GCM key is 32 bytes"),
+        )
+    }
+
+    fn seal(&self, aad: &[u8], value: &Value) -> Result<Value> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("failed to generate nonce"))?;
+        let mut in_out = serde_json::to_vec(value)?;
+        self.aead()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("failed to encrypt state value"))?;
+        Ok(serde_json::to_value(Sealed {
+            algorithm: ALGORITHM.to_string(),
+            kid: self.kid.clone(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(in_out),
+        })?)
+    }
+
+    fn open(&self, aad: &[u8], sealed: &Sealed) -> Result<Value> {
+        let nonce: [u8; NONCE_LEN] = STANDARD
+            .decode(&sealed.nonce)?
+            .try_into()
+            .map_err(|_| anyhow!("sealed value has an invalid nonce"))?;
+        let mut in_out = STANDARD.decode(&sealed.ciphertext)?;
+        let plaintext = self
+            .aead()
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut in_out,
+            )
+            .map_err(|_| anyhow!("failed to decrypt state value with key {}", self.kid))?;
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+}
+
+pub struct EncryptedStateStore {
+    inner: DynStateStore,
+    encryption: StateEncryption,
+    secrets: DynSecretsManager,
+    /// Keyed by tenant label and key name.
+    keys: Mutex<HashMap<(String, String), (DataKey, Instant)>>,
+}
+
+impl EncryptedStateStore {
+    fn data_key(&self, tenant: &TenantCtx, name: &str) -> Result<DataKey> {
+        let cache_key = (tenant_label(tenant), name.to_string());
+        if let Some((key, fetched)) = self.keys.lock().get(&cache_key)
+            && fetched.elapsed() < KEY_CACHE_TTL
+        {
+            return Ok(key.clone());
+        }
+        let scope = TenantCtx::new(tenant.env.clone(), tenant.tenant.clone());
+        let path = scoped_secret_path_for_pack(&scope, KEY_SECRET_PACK, name)?;
+        let read = || block_on(self.secrets.read(path.as_str()));
+        // The secrets manager is async; read on a scoped thread when already inside a
+        // runtime so the caller's worker is not blocked re-entrantly.
+        let raw = if Handle::try_current().is_ok() {
+            thread::scope(|scope| scope.spawn(read).join())
+                .map_err(|_| anyhow!("data key read panicked"))?
+        } else {
+            read()
+        }
+        .map_err(|err| anyhow!("state encryption key '{name}' unavailable: {err}"))?;
+        let key = DataKey::parse(&raw).with_context(|| format!("state encryption key '{name}'"))?;
+        self.keys
+            .lock()
+            .insert(cache_key, (key.clone(), Instant::now()));
+        Ok(key)
+    }
+
+    fn current_key(&self, tenant: &TenantCtx) -> GResult<DataKey> {
+        self.data_key(tenant, &self.encryption.key)
+            .map_err(unavailable)
+    }
+
+    /// Find the configured key that sealed a value with `kid`.
+    fn key_for(&self, tenant: &TenantCtx, kid: &str) -> Result<DataKey> {
+        for name in std::iter::once(&self.encryption.key).chain(&self.encryption.previous_keys) {
+            let key = self.data_key(tenant, name)?;
+            if key.kid == kid {
+                return Ok(key);
+            }
+        }
+        bail!("no configured state encryption key has id {kid}")
+    }
+
+    fn write_back(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey, value: &Value) {
+        let result = self.current_key(tenant).and_then(|current| {
+            let sealed = current
+                .seal(&aad(tenant, prefix, key), value)
+                .map_err(internal)?;
+            state::write_back(self.inner.as_ref(), tenant, prefix, key, &sealed)
+        });
+        if let Err(err) = result {
+            tracing::warn!(
+                tenant = tenant.tenant.as_str(),
+                prefix,
+                error = %err,
+                "failed to re-encrypt state value"
+            );
+        }
+    }
+
+    /// Turn a stored document back into its value, re-encrypting it with the current
+    /// key when it is plaintext or sealed with a previous one.
+    fn reveal(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        stored: Value,
+    ) -> GResult<Value> {
+        if !is_sealed(&stored) {
+            self.write_back(tenant, prefix, key, &stored);
+            return Ok(stored);
+        }
+        let sealed: Sealed = serde_json::from_value(stored).map_err(|err| {
+            GreenticError::new(
+                ErrorCode::Internal,
+                format!("malformed sealed state value: {err}"),
+            )
+        })?;
+        if sealed.algorithm != ALGORITHM {
+            return Err(GreenticError::new(
+                ErrorCode::Internal,
+                format!("unsupported state encryption '{}'", sealed.algorithm),
+            ));
+        }
+        let data_key = self.key_for(tenant, &sealed.kid).map_err(unavailable)?;
+        let value = data_key
+            .open(&aad(tenant, prefix, key), &sealed)
+            .map_err(internal)?;
+        if data_key.kid != self.current_key(tenant)?.kid {
+            self.write_back(tenant, prefix, key, &value);
+        }
+        Ok(value)
+    }
+}
+
+impl RotationListener for EncryptedStateStore {
+    fn secret_rotated(&self, rotation: &SecretRotation) {
+        self.keys.lock().retain(|(label, name), _| {
+            let tenant = label.split_once("::").map(|(_, tenant)| tenant);
+            !(tenant == Some(rotation.tenant.as_str()) && rotation.matches(name))
+        });
+    }
+}
+
+impl StateStore for EncryptedStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        reject_path(path)?;
+        self.inner
+            .get_json(tenant, prefix, key, None)?
+            .map(|stored| self.reveal(tenant, prefix, key, stored))
+            .transpose()
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        reject_path(path)?;
+        let sealed = self
+            .current_key(tenant)?
+            .seal(&aad(tenant, prefix, key), value)
+            .map_err(internal)?;
+        self.inner
+            .set_json(tenant, prefix, key, None, &sealed, ttl_secs)
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        self.inner.del(tenant, prefix, key)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        self.inner.del_prefix(tenant, prefix)
+    }
+}
+
+impl HostStateStore for EncryptedStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        self.inner
+            .get_many(tenant, prefix, keys)?
+            .into_iter()
+            .zip(keys)
+            .map(|(stored, key)| {
+                stored
+                    .map(|stored| self.reveal(tenant, prefix, key, stored))
+                    .transpose()
+            })
+            .collect()
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let current = self.current_key(tenant)?;
+        let sealed = entries
+            .iter()
+            .map(|(key, value)| {
+                let sealed = current
+                    .seal(&aad(tenant, prefix, key), value)
+                    .map_err(internal)?;
+                Ok((key.clone(), sealed))
+            })
+            .collect::<GResult<Vec<_>>>()?;
+        self.inner.set_many(tenant, prefix, &sealed, ttl_secs)
+    }
+}
+
+fn tenant_label(tenant: &TenantCtx) -> String {
+    format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str())
+}
+
+fn aad(tenant: &TenantCtx, prefix: &str, key: &StateKey) -> Vec<u8> {
+    format!("{}/{prefix}/{}", tenant_label(tenant), key.as_str()).into_bytes()
+}
+
+fn internal(err: anyhow::Error) -> GreenticError {
+    GreenticError::new(ErrorCode::Internal, format!("{err:#}"))
+}
+
+fn unavailable(err: anyhow::Error) -> GreenticError {
+    GreenticError::new(ErrorCode::Unavailable, format!("{err:#}"))
+}
+
+fn reject_path(path: Option<&StatePath>) -> GResult<()> {
+    match path {
+        None => Ok(()),
+        Some(_) => Err(GreenticError::new(
+            ErrorCode::InvalidInput,
+            "encrypted state values do not support JSON paths",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sealed_values_are_bound_to_their_key() {
+        let key = DataKey::parse(&[7u8; 32]).unwrap();
+        let sealed = key
+            .seal(b"local::a/runner/k", &json!({"pii": "x"}))
+            .unwrap();
+        assert!(is_sealed(&sealed));
+        let sealed: Sealed = serde_json::from_value(sealed).unwrap();
+        assert_eq!(
+            key.open(b"local::a/runner/k", &sealed).unwrap(),
+            json!({"pii": "x"})
+        );
+        assert!(key.open(b"local::a/runner/other", &sealed).is_err());
+    }
+
+    #[test]
+    fn data_keys_accept_raw_or_base64_bytes() {
+        let raw = DataKey::parse(&[1u8; 32]).unwrap();
+        let encoded = DataKey::parse(STANDARD.encode([1u8; 32]).as_bytes()).unwrap();
+        assert_eq!(raw.kid, encoded.kid);
+        assert!(DataKey::parse(b"short").is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Key expiry for state stores without native TTLs.
+//!
+//! Stores that keep expiry themselves (Redis) get `ttl_secs` passed straight through.
+//! The default in-memory store is wrapped in an [`ExpiringStateStore`] that records a
+//! deadline per key, treats expired keys as absent and deletes them on read. The
+//! Postgres store keeps an `expires_at` column and does the same.
+//!
+//! Keys that are never read again are removed by a sweeper: stores registered with
+//! [`track`] are swept every interval by the task from [`spawn_sweeper`], which
+//! `RunnerHost::start` launches. Other expiring data (flow resume waits) joins the same
+//! sweep through [`watch`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{GResult, TenantCtx};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::storage::listing::Cursor;
+use crate::storage::state::{DynStateStore, HostStateStore};
+
+/// Default pause between sweeps.
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+static SWEEPERS: Lazy<Mutex<Vec<Weak<dyn ExpirySweep>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// A store that can drop its expired keys in bulk.
+pub trait ExpirySweep: Send + Sync {
+    /// Delete every expired key; returns how many were removed.
+    fn sweep_expired(&self) -> GResult<u64>;
+}
+
+/// Register `store` with the sweeper and return it as a state store.
+pub fn track<S>(store: Arc<S>) -> DynStateStore
+where
+    S: HostStateStore + ExpirySweep + 'static,
+{
+    watch(&store);
+    store
+}
+
+/// Sweep `sweeper` with the registered stores for as long as it is alive.
+pub fn watch<S>(sweeper: &Arc<S>)
+where
+    S: ExpirySweep + 'static,
+{
+    let sweeper: Arc<dyn ExpirySweep> = sweeper.clone();
+    SWEEPERS.lock().push(Arc::downgrade(&sweeper));
+}
+
+/// Sweep every live registered store once; returns the number of keys removed.
+pub fn sweep_all() -> u64 {
+    let sweepers = {
+        let mut sweepers = SWEEPERS.lock();
+        sweepers.retain(|sweeper| sweeper.strong_count() > 0);
+        sweepers
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>()
+    };
+    let mut removed = 0;
+    for sweeper in sweepers {
+        match sweeper.sweep_expired() {
+            Ok(count) => removed += count,
+            Err(err) => tracing::warn!(error = %err, "failed to sweep expired state keys"),
+        }
+    }
+    removed
+}
+
+/// Run [`sweep_all`] every `interval` until the returned task is aborted.
+pub fn spawn_sweeper(interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match tokio::task::spawn_blocking(sweep_all).await {
+                Ok(removed) if removed > 0 => {
+                    tracing::debug!(removed, "swept expired state keys");
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!(error = %err, "state sweeper task failed"),
+            }
+        }
+    })
+}
+
+type EntryKey = (String, String, String);
+
+/// Adds per-key expiry to a store that ignores `ttl_secs`.
+pub struct ExpiringStateStore {
+    inner: DynStateStore,
+    deadlines: Mutex<HashMap<EntryKey, (TenantCtx, Instant)>>,
+}
+
+impl ExpiringStateStore {
+    pub fn new(inner: DynStateStore) -> Self {
+        Self {
+            inner,
+            deadlines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_expired(&self, entry: &EntryKey) -> bool {
+        self.deadlines
+            .lock()
+            .get(entry)
+            .is_some_and(|(_, deadline)| *deadline <= Instant::now())
+    }
+
+    /// Delete `key` if its deadline has passed; returns whether it did.
+    fn drop_expired(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        let entry = entry_key(tenant, prefix, key);
+        if !self.is_expired(&entry) {
+            return Ok(false);
+        }
+        self.deadlines.lock().remove(&entry);
+        self.inner.del(tenant, prefix, key)?;
+        Ok(true)
+    }
+}
+
+impl StateStore for ExpiringStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        if self.drop_expired(tenant, prefix, key)? {
+            return Ok(None);
+        }
+        self.inner.get_json(tenant, prefix, key, path)
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        let entry = entry_key(tenant, prefix, key);
+        if path.is_some() && self.is_expired(&entry) {
+            // Do not merge a fragment into a document that has already expired.
+            self.deadlines.lock().remove(&entry);
+            self.inner.del(tenant, prefix, key)?;
+        }
+        self.inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs)?;
+        let mut deadlines = self.deadlines.lock();
+        match ttl_secs {
+            Some(ttl) => {
+                let deadline = Instant::now() + Duration::from_secs(u64::from(ttl));
+                deadlines.insert(entry, (tenant.clone(), deadline));
+            }
+            // A whole-document write without a TTL keeps the key forever; a path write
+            // leaves the document's expiry as it was.
+            None if path.is_none() => {
+                deadlines.remove(&entry);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        self.deadlines
+            .lock()
+            .remove(&entry_key(tenant, prefix, key));
+        self.inner.del(tenant, prefix, key)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let label = tenant_label(tenant);
+        self.deadlines
+            .lock()
+            .retain(|(entry_tenant, entry_prefix, _), _| {
+                *entry_tenant != label || entry_prefix != prefix
+            });
+        self.inner.del_prefix(tenant, prefix)
+    }
+}
+
+impl HostStateStore for ExpiringStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        // Skip keys that have expired but not been swept, fetching more to fill the page.
+        let mut page = Vec::new();
+        let mut cursor = cursor.cloned();
+        loop {
+            let (keys, next) =
+                self.inner
+                    .list_keys(tenant, prefix, key_prefix, cursor.as_ref(), limit)?;
+            for key in keys {
+                if page.len() == limit {
+                    return Ok((page, cursor));
+                }
+                cursor = Some(Cursor::new(key.as_str()));
+                if !self.is_expired(&entry_key(tenant, prefix, &key)) {
+                    page.push(key);
+                }
+            }
+            if next.is_none() {
+                return Ok((page, None));
+            }
+            if page.len() == limit {
+                return Ok((page, next));
+            }
+            cursor = next;
+        }
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        let deadline = self
+            .deadlines
+            .lock()
+            .get(&entry_key(tenant, prefix, key))
+            .map(|(_, deadline)| *deadline);
+        match deadline {
+            Some(deadline) => Ok(Some(deadline.saturating_duration_since(Instant::now()))),
+            None => self.inner.remaining_ttl(tenant, prefix, key),
+        }
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        // Expired keys are deleted first, so the batch read reports them as misses.
+        for key in keys {
+            self.drop_expired(tenant, prefix, key)?;
+        }
+        self.inner.get_many(tenant, prefix, keys)
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let results = self.inner.set_many(tenant, prefix, entries, ttl_secs)?;
+        let mut deadlines = self.deadlines.lock();
+        for ((key, _), result) in entries.iter().zip(&results) {
+            if result.is_err() {
+                continue;
+            }
+            let entry = entry_key(tenant, prefix, key);
+            match ttl_secs {
+                Some(ttl) => {
+                    let deadline = Instant::now() + Duration::from_secs(u64::from(ttl));
+                    deadlines.insert(entry, (tenant.clone(), deadline));
+                }
+                None => {
+                    deadlines.remove(&entry);
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl ExpirySweep for ExpiringStateStore {
+    fn sweep_expired(&self) -> GResult<u64> {
+        let now = Instant::now();
+        let expired = {
+            let mut deadlines = self.deadlines.lock();
+            let keys = deadlines
+                .iter()
+                .filter(|(_, (_, deadline))| *deadline <= now)
+                .map(|(entry, _)| entry.clone())
+                .collect::<Vec<_>>();
+            keys.into_iter()
+                .filter_map(|entry| deadlines.remove(&entry).map(|(ctx, _)| (entry, ctx)))
+                .collect::<Vec<_>>()
+        };
+        let mut removed = 0;
+        for ((_, prefix, key), tenant) in expired {
+            if self.inner.del(&tenant, &prefix, &StateKey::from(key))? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn tenant_label(tenant: &TenantCtx) -> String {
+    format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str())
+}
+
+fn entry_key(tenant: &TenantCtx, prefix: &str, key: &StateKey) -> EntryKey {
+    (
+        tenant_label(tenant),
+        prefix.to_string(),
+        key.as_str().to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::listing::IndexedStateStore;
+    use greentic_state::inmemory::InMemoryStateStore;
+    use greentic_types::{EnvId, TenantId};
+    use serde_json::json;
+
+    #[test]
+    fn writes_without_ttl_clear_an_earlier_deadline() {
+        let store = ExpiringStateStore::new(Arc::new(IndexedStateStore::new(Arc::new(
+            InMemoryStateStore::new(),
+        ))));
+        let ctx = TenantCtx::new(
+            EnvId::new("local").unwrap(),
+            TenantId::new("expiry-unit").unwrap(),
+        );
+        let key = StateKey::new("k");
+        store
+            .set_json(&ctx, "runner", &key, None, &json!(1), Some(1))
+            .unwrap();
+        store
+            .set_json(&ctx, "runner", &key, None, &json!(2), None)
+            .unwrap();
+        assert!(store.deadlines.lock().is_empty());
+        assert_eq!(store.sweep_expired().unwrap(), 0);
+        assert_eq!(
+            store.get_json(&ctx, "runner", &key, None).unwrap(),
+            Some(json!(2))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Prefix scans over a tenant's state keys.
+//!
+//! `StateStore` has no way to enumerate keys, so listing is
+//! [`HostStateStore::list_keys`]. The wrappers the host stacks on top (faults,
+//! expiry, migrations, metrics, quota, encryption) never rename keys and forward it to
+//! the store they wrap.
+//!
+//! The in-memory backend answers from a `BTreeSet` range over an index kept by
+//! [`IndexedStateStore`], the Postgres store uses `key LIKE 'prefix%'`, and the Redis
+//! store runs `SCAN ... MATCH` over the tenant's keys for each page. The order of keys
+//! is up to the backend (all currently sort by key), but a scan is stable: the
+//! [`Cursor`] returned with a page resumes right after its last key, so paging through
+//! a prefix never repeats a key. Keys written behind the cursor during a scan are not
+//! returned.
+//!
+//! Every scan is scoped to one tenant.
+
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{GResult, TenantCtx};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::storage::state::{self, DynStateStore, HostStateStore, STATE_PREFIX};
+
+/// Where the next page of a scan starts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Cursor(String);
+
+impl Cursor {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// List the tenant's runner state keys starting with `key_prefix`.
+pub fn list_state(
+    store: &DynStateStore,
+    tenant: &TenantCtx,
+    key_prefix: &str,
+    cursor: Option<&Cursor>,
+    limit: usize,
+) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+    store.list_keys(tenant, STATE_PREFIX, key_prefix, cursor, limit.max(1))
+}
+
+type IndexKey = (String, String, String);
+
+/// Keeps a sorted index of the keys written through it, for stores that cannot be
+/// enumerated themselves.
+pub struct IndexedStateStore {
+    inner: Arc<dyn StateStore>,
+    keys: RwLock<BTreeSet<IndexKey>>,
+}
+
+impl IndexedStateStore {
+    pub fn new(inner: Arc<dyn StateStore>) -> Self {
+        Self {
+            inner,
+            keys: RwLock::new(BTreeSet::new()),
+        }
+    }
+}
+
+impl StateStore for IndexedStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        self.inner.get_json(tenant, prefix, key, path)
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        self.inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs)?;
+        self.keys.write().insert(index_key(tenant, prefix, key));
+        Ok(())
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        let removed = self.inner.del(tenant, prefix, key)?;
+        self.keys.write().remove(&index_key(tenant, prefix, key));
+        Ok(removed)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let removed = self.inner.del_prefix(tenant, prefix)?;
+        let label = tenant_label(tenant);
+        self.keys.write().retain(|(entry_tenant, entry_prefix, _)| {
+            *entry_tenant != label || entry_prefix != prefix
+        });
+        Ok(removed)
+    }
+}
+
+impl HostStateStore for IndexedStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        let label = tenant_label(tenant);
+        // A cursor sorting before the prefix (or from another scan) starts at the prefix.
+        let start = match cursor {
+            Some(cursor) if cursor.as_str() >= key_prefix => Bound::Excluded((
+                label.clone(),
+                prefix.to_string(),
+                cursor.as_str().to_string(),
+            )),
+            _ => Bound::Included((label.clone(), prefix.to_string(), key_prefix.to_string())),
+        };
+        let keys = self.keys.read();
+        let mut matches = keys
+            .range((start, Bound::Unbounded))
+            .take_while(|(entry_tenant, entry_prefix, entry_key)| {
+                *entry_tenant == label
+                    && entry_prefix == prefix
+                    && entry_key.starts_with(key_prefix)
+            })
+            .map(|(_, _, key)| key.clone());
+        let page = matches.by_ref().take(limit).collect::<Vec<_>>();
+        let next = match matches.next() {
+            Some(_) => page.last().cloned().map(Cursor::new),
+            None => None,
+        };
+        Ok((page.into_iter().map(StateKey::from).collect(), next))
+    }
+
+    fn remaining_ttl(
+        &self,
+        _tenant: &TenantCtx,
+        _prefix: &str,
+        _key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        // The stores this indexes ignore TTLs.
+        Ok(None)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        state::get_each(&*self.inner, tenant, prefix, keys)
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        Ok(state::set_each(self, tenant, prefix, entries, ttl_secs))
+    }
+}
+
+fn tenant_label(tenant: &TenantCtx) -> String {
+    format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str())
+}
+
+fn index_key(tenant: &TenantCtx, prefix: &str, key: &StateKey) -> IndexKey {
+    (
+        tenant_label(tenant),
+        prefix.to_string(),
+        key.as_str().to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greentic_state::inmemory::InMemoryStateStore;
+    use greentic_types::{EnvId, TenantId};
+    use serde_json::json;
+
+    #[test]
+    fn cursor_before_the_prefix_does_not_leak_other_keys() {
+        let store = IndexedStateStore::new(Arc::new(InMemoryStateStore::new()));
+        let ctx = TenantCtx::new(
+            EnvId::new("local").unwrap(),
+            TenantId::new("listing-unit").unwrap(),
+        );
+        for key in ["a/1", "a/2", "b/1", "b/2", "c/1"] {
+            store
+                .set_json(&ctx, "runner", &StateKey::new(key), None, &json!(1), None)
+                .unwrap();
+        }
+        let (keys, next) = store
+            .list_keys(&ctx, "runner", "b/", Some(&Cursor::new("a/1")), 10)
+            .unwrap();
+        let keys = keys.iter().map(StateKey::as_str).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["b/1", "b/2"]);
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,567 @@
+//! Latency and outcome metrics for the state and session stores.
+//!
+//! [`instrument_state`] and [`instrument_session`] wrap the host-level stores so every
+//! get/set/del is counted per store, tenant and operation in the process-wide
+//! [`store_metrics`] registry, which feeds `/metrics` and the tenant stats endpoint.
+//! Operations slower than [`StoreMetricsConfig::slow_threshold`] are logged with the
+//! tenant and key prefix; keys and values are never logged.
+//!
+//! Session metrics are taken at the runner session host, so `touch` and compare-and-swap
+//! updates count as sets. Flow resume waits dropped by TTL expiry are counted per
+//! tenant alongside.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{GResult, TenantCtx};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde_json::Value;
+
+use crate::engine::error::GResult as RunnerResult;
+use crate::engine::host::{SessionHost, SessionKey, SessionSnapshot};
+use crate::operator_metrics::{LatencyHistogram, LatencySnapshot};
+use crate::storage::listing::Cursor;
+use crate::storage::state::{DynStateStore, HostStateStore};
+
+pub const STATE_STORE: &str = "state";
+pub const SESSION_STORE: &str = "session";
+
+const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_millis(250);
+
+static STORE_METRICS: Lazy<StoreMetrics> = Lazy::new(StoreMetrics::default);
+
+/// Process-wide registry shared by every instrumented store.
+pub fn store_metrics() -> &'static StoreMetrics {
+    &STORE_METRICS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StoreOp {
+    Get,
+    Set,
+    Del,
+}
+
+impl StoreOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::Set => "set",
+            Self::Del => "del",
+        }
+    }
+}
+
+/// Store instrumentation settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreMetricsConfig {
+    /// Wrap the stores at all; when off they are used as is.
+    pub enabled: bool,
+    /// Operations taking longer than this are logged at warn level.
+    pub slow_threshold: Duration,
+}
+
+impl Default for StoreMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            slow_threshold: DEFAULT_SLOW_THRESHOLD,
+        }
+    }
+}
+
+impl StoreMetricsConfig {
+    /// Read `GREENTIC_STORE_METRICS` (`0`/`false`/`off` disables) and
+    /// `GREENTIC_STORE_SLOW_MS`.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(value) = env::var("GREENTIC_STORE_METRICS") {
+            config.enabled = !matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "0" | "false" | "off" | "no"
+            );
+        }
+        if let Ok(value) = env::var("GREENTIC_STORE_SLOW_MS") {
+            let millis = value
+                .trim()
+                .parse::<u64>()
+                .with_context(|| format!("invalid GREENTIC_STORE_SLOW_MS value '{value}'"))?;
+            config.slow_threshold = Duration::from_millis(millis);
+        }
+        Ok(config)
+    }
+}
+
+/// Counters and latency for one store operation of one tenant.
+#[derive(Debug, Default)]
+pub struct StoreOpMetrics {
+    calls: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+    slow: AtomicU64,
+    latency: LatencyHistogram,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StoreOpSnapshot {
+    pub calls: u64,
+    /// Reads that found a value; only counted for [`StoreOp::Get`].
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+    pub slow: u64,
+    pub latency: LatencySnapshot,
+}
+
+impl StoreOpMetrics {
+    pub fn snapshot(&self) -> StoreOpSnapshot {
+        StoreOpSnapshot {
+            calls: self.calls.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            slow: self.slow.load(Ordering::Relaxed),
+            latency: self.latency.snapshot(),
+        }
+    }
+}
+
+type OpKey = (&'static str, String, StoreOp);
+
+#[derive(Debug, Default)]
+pub struct StoreMetrics {
+    ops: RwLock<BTreeMap<OpKey, Arc<StoreOpMetrics>>>,
+    expired_waits: RwLock<BTreeMap<String, u64>>,
+}
+
+/// One row of [`StoreMetrics::snapshot`].
+#[derive(Clone, Debug)]
+pub struct StoreOpSample {
+    pub store: &'static str,
+    pub tenant: String,
+    pub op: StoreOp,
+    pub metrics: StoreOpSnapshot,
+}
+
+impl StoreMetrics {
+    fn op(&self, store: &'static str, tenant: &str, op: StoreOp) -> Arc<StoreOpMetrics> {
+        let key = (store, tenant.to_string(), op);
+        if let Some(metrics) = self.ops.read().get(&key) {
+            return Arc::clone(metrics);
+        }
+        Arc::clone(self.ops.write().entry(key).or_default())
+    }
+
+    /// Count flow resume waits of `tenant` dropped because their TTL passed.
+    pub fn record_expired_waits(&self, tenant: &str, count: u64) {
+        if count > 0 {
+            *self
+                .expired_waits
+                .write()
+                .entry(tenant.to_string())
+                .or_default() += count;
+        }
+    }
+
+    /// Expired flow resume waits per tenant.
+    pub fn expired_waits(&self) -> Vec<(String, u64)> {
+        self.expired_waits
+            .read()
+            .iter()
+            .map(|(tenant, count)| (tenant.clone(), *count))
+            .collect()
+    }
+
+    /// All recorded operations, ordered by store, tenant and operation.
+    pub fn snapshot(&self) -> Vec<StoreOpSample> {
+        self.ops
+            .read()
+            .iter()
+            .map(|((store, tenant, op), metrics)| StoreOpSample {
+                store,
+                tenant: tenant.clone(),
+                op: *op,
+                metrics: metrics.snapshot(),
+            })
+            .collect()
+    }
+
+    pub fn tenant(&self, tenant: &str) -> Vec<StoreOpSample> {
+        self.snapshot()
+            .into_iter()
+            .filter(|sample| sample.tenant == tenant)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Outcome {
+    Hit,
+    Miss,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Copy)]
+struct Recorder {
+    store: &'static str,
+    slow_threshold: Duration,
+}
+
+impl Recorder {
+    fn record(&self, tenant: &str, prefix: &str, op: StoreOp, started: Instant, outcome: Outcome) {
+        self.record_batch(tenant, prefix, op, started, &[outcome]);
+    }
+
+    /// Record a batch as one call, counting hits, misses and errors per key.
+    fn record_batch(
+        &self,
+        tenant: &str,
+        prefix: &str,
+        op: StoreOp,
+        started: Instant,
+        outcomes: &[Outcome],
+    ) {
+        let elapsed = started.elapsed();
+        let metrics = store_metrics().op(self.store, tenant, op);
+        metrics.calls.fetch_add(1, Ordering::Relaxed);
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Hit => metrics.hits.fetch_add(1, Ordering::Relaxed),
+                Outcome::Miss => metrics.misses.fetch_add(1, Ordering::Relaxed),
+                Outcome::Failed => metrics.errors.fetch_add(1, Ordering::Relaxed),
+                Outcome::Done => 0,
+            };
+        }
+        metrics.latency.observe(elapsed);
+        if elapsed > self.slow_threshold {
+            metrics.slow.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                store = self.store,
+                tenant,
+                prefix,
+                op = op.as_str(),
+                elapsed_ms = elapsed.as_millis() as u64,
+                threshold_ms = self.slow_threshold.as_millis() as u64,
+                "slow store operation"
+            );
+        }
+    }
+}
+
+fn read_outcome<T, E>(result: &Result<Option<T>, E>) -> Outcome {
+    match result {
+        Ok(Some(_)) => Outcome::Hit,
+        Ok(None) => Outcome::Miss,
+        Err(_) => Outcome::Failed,
+    }
+}
+
+fn write_outcome<T, E>(result: &Result<T, E>) -> Outcome {
+    match result {
+        Ok(_) => Outcome::Done,
+        Err(_) => Outcome::Failed,
+    }
+}
+
+/// Wrap `store` so its operations are recorded; a disabled config returns it as is.
+pub fn instrument_state(store: DynStateStore, config: &StoreMetricsConfig) -> DynStateStore {
+    if !config.enabled {
+        return store;
+    }
+    Arc::new(InstrumentedStateStore {
+        inner: store,
+        recorder: Recorder {
+            store: STATE_STORE,
+            slow_threshold: config.slow_threshold,
+        },
+    })
+}
+
+pub struct InstrumentedStateStore {
+    inner: DynStateStore,
+    recorder: Recorder,
+}
+
+impl StateStore for InstrumentedStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        let started = Instant::now();
+        let result = self.inner.get_json(tenant, prefix, key, path);
+        self.recorder.record(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Get,
+            started,
+            read_outcome(&result),
+        );
+        result
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        let started = Instant::now();
+        let result = self
+            .inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs);
+        self.recorder.record(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Set,
+            started,
+            write_outcome(&result),
+        );
+        result
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        let started = Instant::now();
+        let result = self.inner.del(tenant, prefix, key);
+        self.recorder.record(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Del,
+            started,
+            write_outcome(&result),
+        );
+        result
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let started = Instant::now();
+        let result = self.inner.del_prefix(tenant, prefix);
+        self.recorder.record(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Del,
+            started,
+            write_outcome(&result),
+        );
+        result
+    }
+}
+
+impl HostStateStore for InstrumentedStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        let started = Instant::now();
+        let result = self.inner.get_many(tenant, prefix, keys);
+        let outcomes = match &result {
+            Ok(values) => values
+                .iter()
+                .map(|value| match value {
+                    Some(_) => Outcome::Hit,
+                    None => Outcome::Miss,
+                })
+                .collect(),
+            Err(_) => vec![Outcome::Failed],
+        };
+        self.recorder.record_batch(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Get,
+            started,
+            &outcomes,
+        );
+        result
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let started = Instant::now();
+        let result = self.inner.set_many(tenant, prefix, entries, ttl_secs);
+        let outcomes = match &result {
+            Ok(results) => results.iter().map(write_outcome).collect(),
+            Err(_) => vec![Outcome::Failed],
+        };
+        self.recorder.record_batch(
+            tenant.tenant.as_str(),
+            prefix,
+            StoreOp::Set,
+            started,
+            &outcomes,
+        );
+        result
+    }
+}
+
+/// Wrap `host` so its operations are recorded; a disabled config returns it as is.
+pub fn instrument_session(
+    host: Arc<dyn SessionHost>,
+    config: &StoreMetricsConfig,
+) -> Arc<dyn SessionHost> {
+    if !config.enabled {
+        return host;
+    }
+    Arc::new(InstrumentedSessionHost {
+        inner: host,
+        recorder: Recorder {
+            store: SESSION_STORE,
+            slow_threshold: config.slow_threshold,
+        },
+    })
+}
+
+pub struct InstrumentedSessionHost {
+    inner: Arc<dyn SessionHost>,
+    recorder: Recorder,
+}
+
+impl InstrumentedSessionHost {
+    fn record(&self, key: &SessionKey, op: StoreOp, started: Instant, outcome: Outcome) {
+        // Session keys carry `env::tenant`; label by tenant like the state store.
+        let tenant = key
+            .tenant_key
+            .split_once("::")
+            .map(|(_, tenant)| tenant)
+            .unwrap_or(key.tenant_key.as_str());
+        self.recorder
+            .record(tenant, key.pack_id.as_str(), op, started, outcome);
+    }
+}
+
+#[async_trait]
+impl SessionHost for InstrumentedSessionHost {
+    async fn get(&self, key: &SessionKey) -> RunnerResult<Option<SessionSnapshot>> {
+        let started = Instant::now();
+        let result = self.inner.get(key).await;
+        self.record(key, StoreOp::Get, started, read_outcome(&result));
+        result
+    }
+
+    async fn put(&self, snapshot: SessionSnapshot) -> RunnerResult<()> {
+        let key = snapshot.key.clone();
+        let started = Instant::now();
+        let result = self.inner.put(snapshot).await;
+        self.record(&key, StoreOp::Set, started, write_outcome(&result));
+        result
+    }
+
+    async fn update_cas(
+        &self,
+        snapshot: SessionSnapshot,
+        expected_revision: u64,
+    ) -> RunnerResult<bool> {
+        let key = snapshot.key.clone();
+        let started = Instant::now();
+        let result = self.inner.update_cas(snapshot, expected_revision).await;
+        self.record(&key, StoreOp::Set, started, write_outcome(&result));
+        result
+    }
+
+    async fn delete(&self, key: &SessionKey) -> RunnerResult<()> {
+        let started = Instant::now();
+        let result = self.inner.delete(key).await;
+        self.record(key, StoreOp::Del, started, write_outcome(&result));
+        result
+    }
+
+    async fn touch(&self, key: &SessionKey, ttl: Duration) -> RunnerResult<()> {
+        let started = Instant::now();
+        let result = self.inner.touch(key, ttl).await;
+        self.record(key, StoreOp::Set, started, write_outcome(&result));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::state::new_state_store;
+    use greentic_types::{EnvId, TenantId};
+    use serde_json::json;
+
+    #[test]
+    fn reads_are_split_into_hits_and_misses() {
+        let store = instrument_state(new_state_store(), &StoreMetricsConfig::default());
+        let ctx = TenantCtx::new(
+            EnvId::new("local").unwrap(),
+            TenantId::new("store-metrics-unit").unwrap(),
+        );
+        let key = StateKey::new("k");
+        store.get_json(&ctx, "runner", &key, None).unwrap();
+        store
+            .set_json(&ctx, "runner", &key, None, &json!(1), None)
+            .unwrap();
+        store.get_json(&ctx, "runner", &key, None).unwrap();
+
+        let samples = store_metrics().tenant("store-metrics-unit");
+        let get = samples
+            .iter()
+            .find(|sample| sample.op == StoreOp::Get)
+            .expect("get recorded");
+        assert_eq!(get.store, STATE_STORE);
+        assert_eq!(
+            (get.metrics.calls, get.metrics.hits, get.metrics.misses),
+            (2, 1, 1)
+        );
+        let set = samples
+            .iter()
+            .find(|sample| sample.op == StoreOp::Set)
+            .expect("set recorded");
+        assert_eq!((set.metrics.calls, set.metrics.hits), (1, 0));
+    }
+
+    #[test]
+    fn disabled_config_leaves_the_store_unwrapped() {
+        let store = new_state_store();
+        let config = StoreMetricsConfig {
+            enabled: false,
+            ..StoreMetricsConfig::default()
+        };
+        assert!(Arc::ptr_eq(
+            &store,
+            &instrument_state(Arc::clone(&store), &config)
+        ));
+    }
+}
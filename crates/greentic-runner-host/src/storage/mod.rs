@@ -1,11 +1,25 @@
+pub mod encryption;
+pub mod expiry;
+pub mod listing;
+pub mod metrics;
+#[cfg(feature = "state-postgres")]
+pub mod postgres;
+pub mod quota;
+pub mod redis;
+#[cfg(feature = "session-redis")]
+pub mod redis_state;
 pub mod session;
 pub mod state;
+pub mod versioned;
 
 use std::sync::Arc;
 
+use greentic_state::StateStore;
+
+pub use self::redis::{RedisConfig, RedisTopology};
 use crate::engine::host::{SessionHost, StateHost};
-pub use session::DynSessionStore;
-pub use state::DynStateStore;
+pub use session::{DynSessionStore, SessionBackend};
+pub use state::{DynStateStore, HostStateStore, PostgresStateConfig, StateBackend};
 
 pub fn new_session_store() -> DynSessionStore {
     session::new_session_store()
@@ -15,6 +29,10 @@ pub fn new_state_store() -> DynStateStore {
     state::new_state_store()
 }
 
+pub fn state_store_from(store: Arc<dyn StateStore>) -> DynStateStore {
+    state::state_store_from(store)
+}
+
 pub fn session_host_from(store: DynSessionStore) -> Arc<dyn SessionHost> {
     session::session_host_from(store)
 }
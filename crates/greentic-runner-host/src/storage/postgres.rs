@@ -0,0 +1,496 @@
+//! Postgres-backed [`StateStore`].
+//!
+//! Rows live in `runner_state`, keyed by `(tenant, prefix, key)` where `tenant` is the
+//! `env::tenant` pair of the [`TenantCtx`]. The table is created by the migration
+//! embedded from `migrations/state` the first time a store connects. Every write bumps
+//! the row's `version`, which [`PostgresStateStore::set_json_if_version`] uses for
+//! optimistic concurrency; TTLs are stored as `expires_at`. Expired rows read as
+//! missing, are deleted when read, and are purged in bulk by the expiry sweeper.
+//!
+//! [`StateStore`] is synchronous, so the store owns a small tokio runtime for its pool
+//! and callers block on a channel until the query finishes. That keeps it usable from
+//! both async handlers and the WASI threads without nesting runtimes.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{ErrorCode, GResult, GreenticError, TenantCtx};
+use serde_json::Value;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::types::Json;
+use tokio::runtime::Runtime;
+
+use crate::storage::expiry::ExpirySweep;
+use crate::storage::listing::Cursor;
+use crate::storage::state::{HostStateStore, PostgresStateConfig};
+
+pub struct PostgresStateStore {
+    pool: PgPool,
+    runtime: Option<Runtime>,
+}
+
+impl PostgresStateStore {
+    /// Connect with default pool settings.
+    pub fn from_url(url: &str) -> Result<Self> {
+        Self::connect(&PostgresStateConfig::new(url))
+    }
+
+    /// Open the pool and apply the embedded schema migration.
+    pub fn connect(config: &PostgresStateConfig) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(config.runtime_threads.max(1))
+            .thread_name("greentic-state-pg")
+            .enable_all()
+            .build()
+            .context("failed to start postgres state runtime")?;
+        let mut options = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_millis(config.acquire_timeout_ms));
+        if let Some(idle) = config.idle_timeout_ms {
+            options = options.idle_timeout(Duration::from_millis(idle));
+        }
+        let url = config.url.clone();
+        let pool = wait_on(&runtime, async move {
+            let pool = options
+                .connect(&url)
+                .await
+                .context("failed to connect to postgres state store")?;
+            let mut migrator = sqlx::migrate!("./migrations/state");
+            // The database may hold other applications' migrations.
+            migrator.set_ignore_missing(true);
+            migrator
+                .run(&pool)
+                .await
+                .context("failed to migrate postgres state store")?;
+            Ok::<_, anyhow::Error>(pool)
+        })
+        .context("postgres state runtime stopped")??;
+        Ok(Self {
+            pool,
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Read a value together with its current version.
+    pub fn get_versioned(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<(Value, i64)>> {
+        let pool = self.pool.clone();
+        let (tenant, prefix, key) = row_key(tenant, prefix, key);
+        self.run(async move {
+            // Expired rows read as absent; drop this one now instead of waiting for the
+            // sweeper.
+            sqlx::query(
+                "DELETE FROM runner_state \
+                 WHERE tenant = $1 AND prefix = $2 AND key = $3 AND expires_at <= now()",
+            )
+            .bind(&tenant)
+            .bind(&prefix)
+            .bind(&key)
+            .execute(&pool)
+            .await
+            .map_err(db_error)?;
+            let row: Option<(Json<Value>, i64)> = sqlx::query_as(
+                "SELECT value, version FROM runner_state \
+                 WHERE tenant = $1 AND prefix = $2 AND key = $3 \
+                 AND (expires_at IS NULL OR expires_at > now())",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(key)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(row.map(|(Json(value), version)| (value, version)))
+        })
+    }
+
+    /// Write `value` only if the row is still at `expected` (`None` = the key must not
+    /// exist). Returns the new version, or `None` when another writer got there first.
+    pub fn set_json_if_version(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        value: &Value,
+        expected: Option<i64>,
+        ttl_secs: Option<u32>,
+    ) -> GResult<Option<i64>> {
+        let pool = self.pool.clone();
+        let (tenant, prefix, key) = row_key(tenant, prefix, key);
+        let value = Json(value.clone());
+        let ttl = ttl_secs.map(f64::from);
+        self.run(async move {
+            let query = match expected {
+                Some(version) => sqlx::query_scalar(
+                    "UPDATE runner_state SET value = $4, version = version + 1, \
+                     expires_at = now() + $5 * interval '1 second', updated_at = now() \
+                     WHERE tenant = $1 AND prefix = $2 AND key = $3 AND version = $6 \
+                     AND (expires_at IS NULL OR expires_at > now()) \
+                     RETURNING version",
+                )
+                .bind(tenant)
+                .bind(prefix)
+                .bind(key)
+                .bind(value)
+                .bind(ttl)
+                .bind(version),
+                // An expired row counts as absent.
+                None => sqlx::query_scalar(
+                    "INSERT INTO runner_state (tenant, prefix, key, value, expires_at) \
+                     VALUES ($1, $2, $3, $4, now() + $5 * interval '1 second') \
+                     ON CONFLICT (tenant, prefix, key) DO UPDATE \
+                     SET value = EXCLUDED.value, version = runner_state.version + 1, \
+                     expires_at = EXCLUDED.expires_at, updated_at = now() \
+                     WHERE runner_state.expires_at IS NOT NULL \
+                     AND runner_state.expires_at <= now() \
+                     RETURNING version",
+                )
+                .bind(tenant)
+                .bind(prefix)
+                .bind(key)
+                .bind(value)
+                .bind(ttl),
+            };
+            query.fetch_optional(&pool).await.map_err(db_error)
+        })
+    }
+
+    /// Delete every expired row; returns how many were removed.
+    pub fn purge_expired(&self) -> GResult<u64> {
+        let pool = self.pool.clone();
+        self.run(async move {
+            let result = sqlx::query("DELETE FROM runner_state WHERE expires_at <= now()")
+                .execute(&pool)
+                .await
+                .map_err(db_error)?;
+            Ok(result.rows_affected())
+        })
+    }
+
+    /// Run `future` on the store's runtime and wait for it.
+    fn run<T, F>(&self, future: F) -> GResult<T>
+    where
+        T: Send + 'static,
+        F: Future<Output = GResult<T>> + Send + 'static,
+    {
+        let runtime = self.runtime.as_ref().expect("runtime present until drop");
+        wait_on(runtime, future).unwrap_or_else(|| {
+            Err(GreenticError::new(
+                ErrorCode::Unavailable,
+                "postgres state runtime stopped",
+            ))
+        })
+    }
+}
+
+impl Drop for PostgresStateStore {
+    fn drop(&mut self) {
+        // A plain drop blocks, which panics when the last handle goes away inside an
+        // async context.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl StateStore for PostgresStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        reject_path(path)?;
+        Ok(self
+            .get_versioned(tenant, prefix, key)?
+            .map(|(value, _)| value))
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        reject_path(path)?;
+        let pool = self.pool.clone();
+        let (tenant, prefix, key) = row_key(tenant, prefix, key);
+        let value = Json(value.clone());
+        let ttl = ttl_secs.map(f64::from);
+        self.run(async move {
+            sqlx::query(
+                "INSERT INTO runner_state (tenant, prefix, key, value, expires_at) \
+                 VALUES ($1, $2, $3, $4, now() + $5 * interval '1 second') \
+                 ON CONFLICT (tenant, prefix, key) DO UPDATE \
+                 SET value = EXCLUDED.value, version = runner_state.version + 1, \
+                 expires_at = EXCLUDED.expires_at, updated_at = now()",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(key)
+            .bind(value)
+            .bind(ttl)
+            .execute(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(())
+        })
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        let pool = self.pool.clone();
+        let (tenant, prefix, key) = row_key(tenant, prefix, key);
+        self.run(async move {
+            let result = sqlx::query(
+                "DELETE FROM runner_state WHERE tenant = $1 AND prefix = $2 AND key = $3",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(key)
+            .execute(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let pool = self.pool.clone();
+        let tenant = tenant_column(tenant);
+        let prefix = prefix.to_string();
+        self.run(async move {
+            let result = sqlx::query("DELETE FROM runner_state WHERE tenant = $1 AND prefix = $2")
+                .bind(tenant)
+                .bind(prefix)
+                .execute(&pool)
+                .await
+                .map_err(db_error)?;
+            Ok(result.rows_affected())
+        })
+    }
+}
+
+impl HostStateStore for PostgresStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        let pool = self.pool.clone();
+        let tenant = tenant_column(tenant);
+        let prefix = prefix.to_string();
+        let pattern = format!("{}%", escape_like(key_prefix));
+        let after = cursor.map(|cursor| cursor.as_str().to_string());
+        // One extra row tells whether another page follows.
+        let fetch = i64::try_from(limit).unwrap_or(i64::MAX - 1) + 1;
+        let mut keys = self.run(async move {
+            let keys: Vec<String> = sqlx::query_scalar(
+                "SELECT key FROM runner_state \
+                 WHERE tenant = $1 AND prefix = $2 AND key LIKE $3 \
+                 AND ($4::text IS NULL OR key > $4) \
+                 AND (expires_at IS NULL OR expires_at > now()) \
+                 ORDER BY key LIMIT $5",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(pattern)
+            .bind(after)
+            .bind(fetch)
+            .fetch_all(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(keys)
+        })?;
+        let next = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned().map(Cursor::new)
+        } else {
+            None
+        };
+        Ok((keys.into_iter().map(StateKey::from).collect(), next))
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        let pool = self.pool.clone();
+        let (tenant, prefix, key) = row_key(tenant, prefix, key);
+        let left = self.run(async move {
+            let left: Option<Option<f64>> = sqlx::query_scalar(
+                "SELECT EXTRACT(EPOCH FROM expires_at - now())::float8 FROM runner_state \
+                 WHERE tenant = $1 AND prefix = $2 AND key = $3",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(key)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(left.flatten())
+        })?;
+        Ok(left.map(|secs| Duration::from_secs_f64(secs.max(0.0))))
+    }
+
+    /// Read `keys` in one statement; results follow `keys`, with `None` for misses.
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        let pool = self.pool.clone();
+        let tenant = tenant_column(tenant);
+        let prefix = prefix.to_string();
+        let names = keys
+            .iter()
+            .map(|key| key.as_str().to_string())
+            .collect::<Vec<_>>();
+        let rows = self.run(async move {
+            let rows: Vec<(String, Json<Value>)> = sqlx::query_as(
+                "SELECT key, value FROM runner_state \
+                 WHERE tenant = $1 AND prefix = $2 AND key = ANY($3) \
+                 AND (expires_at IS NULL OR expires_at > now())",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(names)
+            .fetch_all(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(rows)
+        })?;
+        let found = rows
+            .into_iter()
+            .map(|(key, Json(value))| (key, value))
+            .collect::<HashMap<_, _>>();
+        Ok(keys
+            .iter()
+            .map(|key| found.get(key.as_str()).cloned())
+            .collect())
+    }
+
+    /// Upsert every entry in one statement; it succeeds or fails as a whole.
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let pool = self.pool.clone();
+        let tenant = tenant_column(tenant);
+        let prefix = prefix.to_string();
+        // One statement may touch each row once, so the last entry for a key wins.
+        let mut seen = HashSet::new();
+        let mut rows = entries
+            .iter()
+            .rev()
+            .filter(|(key, _)| seen.insert(key.as_str()))
+            .map(|(key, value)| (key.as_str().to_string(), Json(value.clone())))
+            .collect::<Vec<_>>();
+        rows.reverse();
+        let (keys, values): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        let ttl = ttl_secs.map(f64::from);
+        self.run(async move {
+            sqlx::query(
+                "INSERT INTO runner_state (tenant, prefix, key, value, expires_at) \
+                 SELECT $1, $2, entry.key, entry.value, now() + $5 * interval '1 second' \
+                 FROM UNNEST($3::text[], $4::jsonb[]) AS entry(key, value) \
+                 ON CONFLICT (tenant, prefix, key) DO UPDATE \
+                 SET value = EXCLUDED.value, version = runner_state.version + 1, \
+                 expires_at = EXCLUDED.expires_at, updated_at = now()",
+            )
+            .bind(tenant)
+            .bind(prefix)
+            .bind(keys)
+            .bind(values)
+            .bind(ttl)
+            .execute(&pool)
+            .await
+            .map_err(db_error)?;
+            Ok(())
+        })?;
+        Ok(entries.iter().map(|_| Ok(())).collect())
+    }
+}
+
+impl ExpirySweep for PostgresStateStore {
+    fn sweep_expired(&self) -> GResult<u64> {
+        self.purge_expired()
+    }
+}
+
+/// Spawn `future` on `runtime` and block until it completes; `None` if the runtime
+/// dropped the task.
+fn wait_on<T, F>(runtime: &Runtime, future: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: Future<Output = T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::sync_channel(1);
+    runtime.spawn(async move {
+        let _ = tx.send(future.await);
+    });
+    rx.recv().ok()
+}
+
+fn tenant_column(tenant: &TenantCtx) -> String {
+    format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str())
+}
+
+fn row_key(tenant: &TenantCtx, prefix: &str, key: &StateKey) -> (String, String, String) {
+    (
+        tenant_column(tenant),
+        prefix.to_string(),
+        key.as_str().to_string(),
+    )
+}
+
+/// Escape `LIKE` wildcards so `key_prefix` matches literally.
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+fn reject_path(path: Option<&StatePath>) -> GResult<()> {
+    match path {
+        None => Ok(()),
+        Some(_) => Err(GreenticError::new(
+            ErrorCode::InvalidInput,
+            "postgres state store does not support JSON paths",
+        )),
+    }
+}
+
+fn db_error(err: sqlx::Error) -> GreenticError {
+    GreenticError::new(
+        ErrorCode::Unavailable,
+        format!("postgres state store: {err}"),
+    )
+}
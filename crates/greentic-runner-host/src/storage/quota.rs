@@ -0,0 +1,411 @@
+//! Per-tenant quotas on the state store.
+//!
+//! Tenants whose `state_store.quota` bindings block sets a limit get their store wrapped
+//! in a [`QuotaStateStore`]. It keeps a usage ledger (keys and serialized bytes per
+//! prefix) in the store itself under [`USAGE_PREFIX`], so every wrapper built for the
+//! tenant, and the tenant stats endpoint, see the same totals. Ledger updates are
+//! serialized per tenant within the process; writes from other hosts sharing the store
+//! can race, so totals are best-effort. Keys dropped by TTL expiry are not subtracted.
+//!
+//! A write that would take the tenant past a limit is rejected before it reaches the
+//! store. The wrapper returns a `GreenticError` whose source is the typed
+//! [`StateQuotaExceeded`]; [`breach`] recovers it to report a policy denial.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{ErrorCode, GResult, GreenticError, TenantCtx};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::storage::listing::Cursor;
+use crate::storage::state::{DynStateStore, HostStateStore};
+
+/// Prefix holding the usage ledger; writes under it are not counted.
+pub const USAGE_PREFIX: &str = "runner.quota";
+const USAGE_KEY: &str = "usage";
+
+static TENANT_LOCKS: Lazy<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// `state_store.quota` bindings block; unset limits are unbounded.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StateQuota {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_keys: Option<u64>,
+    /// Total size of the tenant's values, serialized as JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+impl StateQuota {
+    pub fn is_limited(&self) -> bool {
+        self.max_keys.is_some() || self.max_bytes.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StateUsage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+/// A state write was rejected because it would exceed the tenant's [`StateQuota`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("state quota exceeded for tenant {tenant}: {limit} would reach {requested}, limit {max}")]
+pub struct StateQuotaExceeded {
+    pub tenant: String,
+    /// Name of the exceeded `state_store.quota` field.
+    pub limit: &'static str,
+    pub requested: u64,
+    pub max: u64,
+}
+
+/// Wrap `store` so writes are checked against `quota`; unlimited quotas return it as is.
+pub fn apply_quota(store: DynStateStore, quota: StateQuota) -> DynStateStore {
+    if !quota.is_limited() {
+        return store;
+    }
+    Arc::new(QuotaStateStore {
+        inner: store,
+        quota,
+    })
+}
+
+/// Usage recorded in `tenant`'s ledger.
+pub fn usage(store: &dyn StateStore, tenant: &TenantCtx) -> GResult<StateUsage> {
+    Ok(Ledger::load(store, tenant)?.total())
+}
+
+/// Quota breach `err` was returned for, if a write was rejected by [`QuotaStateStore`].
+pub fn breach(err: &GreenticError) -> Option<&StateQuotaExceeded> {
+    std::error::Error::source(err)?.downcast_ref()
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Ledger {
+    #[serde(default)]
+    prefixes: BTreeMap<String, StateUsage>,
+}
+
+impl Ledger {
+    fn load(store: &dyn StateStore, tenant: &TenantCtx) -> GResult<Self> {
+        Ok(store
+            .get_json(tenant, USAGE_PREFIX, &StateKey::new(USAGE_KEY), None)?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default())
+    }
+
+    fn save(&self, store: &dyn StateStore, tenant: &TenantCtx) -> GResult<()> {
+        let value = serde_json::to_value(self).unwrap_or(Value::Null);
+        store.set_json(
+            tenant,
+            USAGE_PREFIX,
+            &StateKey::new(USAGE_KEY),
+            None,
+            &value,
+            None,
+        )
+    }
+
+    fn total(&self) -> StateUsage {
+        self.prefixes
+            .values()
+            .fold(StateUsage::default(), |total, usage| StateUsage {
+                keys: total.keys + usage.keys,
+                bytes: total.bytes + usage.bytes,
+            })
+    }
+}
+
+pub struct QuotaStateStore {
+    inner: DynStateStore,
+    quota: StateQuota,
+}
+
+impl QuotaStateStore {
+    fn check(
+        &self,
+        tenant: &TenantCtx,
+        limit: &'static str,
+        requested: u64,
+        max: Option<u64>,
+    ) -> GResult<()> {
+        match max {
+            Some(max) if requested > max => {
+                let hit = StateQuotaExceeded {
+                    tenant: tenant.tenant.as_str().to_string(),
+                    limit,
+                    requested,
+                    max,
+                };
+                Err(
+                    GreenticError::new(ErrorCode::PermissionDenied, hit.to_string())
+                        .with_source(hit),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a write taking a key from `previous` bytes (`None` for a new key) to
+    /// `new_bytes`, on top of the tenant's `total` usage.
+    fn admit(
+        &self,
+        tenant: &TenantCtx,
+        total: StateUsage,
+        previous: Option<u64>,
+        new_bytes: u64,
+    ) -> GResult<()> {
+        if previous.is_none() {
+            self.check(tenant, "max_keys", total.keys + 1, self.quota.max_keys)?;
+        }
+        let old_bytes = previous.unwrap_or(0);
+        if new_bytes > old_bytes {
+            let requested = total.bytes + new_bytes - old_bytes;
+            self.check(tenant, "max_bytes", requested, self.quota.max_bytes)?;
+        }
+        Ok(())
+    }
+
+    fn size_of(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<Option<u64>> {
+        Ok(self
+            .inner
+            .get_json(tenant, prefix, key, None)?
+            .map(|value| encoded_len(&value)))
+    }
+}
+
+impl StateStore for QuotaStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        self.inner.get_json(tenant, prefix, key, path)
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        if prefix == USAGE_PREFIX {
+            return self
+                .inner
+                .set_json(tenant, prefix, key, path, value, ttl_secs);
+        }
+        let lock = tenant_lock(tenant);
+        let _guard = lock.lock();
+        let mut ledger = Ledger::load(&*self.inner, tenant)?;
+        let previous = self.size_of(tenant, prefix, key)?;
+        let old_bytes = previous.unwrap_or(0);
+        // A path write merges into the document; count the fragment as growth until the
+        // stored document can be measured.
+        let new_bytes = match path {
+            None => encoded_len(value),
+            Some(_) => old_bytes + encoded_len(value),
+        };
+        self.admit(tenant, ledger.total(), previous, new_bytes)?;
+        self.inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs)?;
+        let new_bytes = match path {
+            None => new_bytes,
+            Some(_) => self.size_of(tenant, prefix, key)?.unwrap_or(0),
+        };
+        let entry = ledger.prefixes.entry(prefix.to_string()).or_default();
+        if previous.is_none() {
+            entry.keys += 1;
+        }
+        entry.bytes = (entry.bytes + new_bytes).saturating_sub(old_bytes);
+        ledger.save(&*self.inner, tenant)
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        if prefix == USAGE_PREFIX {
+            return self.inner.del(tenant, prefix, key);
+        }
+        let lock = tenant_lock(tenant);
+        let _guard = lock.lock();
+        let previous = self.size_of(tenant, prefix, key)?;
+        let removed = self.inner.del(tenant, prefix, key)?;
+        if let Some(bytes) = previous {
+            let mut ledger = Ledger::load(&*self.inner, tenant)?;
+            if let Some(entry) = ledger.prefixes.get_mut(prefix) {
+                entry.keys = entry.keys.saturating_sub(1);
+                entry.bytes = entry.bytes.saturating_sub(bytes);
+            }
+            ledger.save(&*self.inner, tenant)?;
+        }
+        Ok(removed)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        if prefix == USAGE_PREFIX {
+            return self.inner.del_prefix(tenant, prefix);
+        }
+        let lock = tenant_lock(tenant);
+        let _guard = lock.lock();
+        let removed = self.inner.del_prefix(tenant, prefix)?;
+        let mut ledger = Ledger::load(&*self.inner, tenant)?;
+        if ledger.prefixes.remove(prefix).is_some() {
+            ledger.save(&*self.inner, tenant)?;
+        }
+        Ok(removed)
+    }
+}
+
+impl HostStateStore for QuotaStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        self.inner.get_many(tenant, prefix, keys)
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        if prefix == USAGE_PREFIX {
+            return self.inner.set_many(tenant, prefix, entries, ttl_secs);
+        }
+        let lock = tenant_lock(tenant);
+        let _guard = lock.lock();
+        let mut ledger = Ledger::load(&*self.inner, tenant)?;
+        let keys = entries
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let stored = self.inner.get_many(tenant, prefix, &keys)?;
+        let mut sizes = HashMap::new();
+        for (key, value) in keys.iter().zip(&stored) {
+            sizes
+                .entry(key.as_str())
+                .or_insert_with(|| value.as_ref().map(encoded_len));
+        }
+        // Each entry is checked against the usage the entries admitted before it leave.
+        let mut total = ledger.total();
+        let mut results = Vec::with_capacity(entries.len());
+        let mut admitted = Vec::new();
+        for (index, (key, value)) in entries.iter().enumerate() {
+            let previous = sizes[key.as_str()];
+            let new_bytes = encoded_len(value);
+            match self.admit(tenant, total, previous, new_bytes) {
+                Ok(()) => {
+                    total.keys += u64::from(previous.is_none());
+                    total.bytes = (total.bytes + new_bytes).saturating_sub(previous.unwrap_or(0));
+                    sizes.insert(key.as_str(), Some(new_bytes));
+                    admitted.push((index, previous, new_bytes));
+                    results.push(Ok(()));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+        let batch = admitted
+            .iter()
+            .map(|&(index, _, _)| entries[index].clone())
+            .collect::<Vec<_>>();
+        let written = self.inner.set_many(tenant, prefix, &batch, ttl_secs)?;
+        let usage = ledger.prefixes.entry(prefix.to_string()).or_default();
+        for ((index, previous, new_bytes), outcome) in admitted.into_iter().zip(written) {
+            match outcome {
+                Ok(()) => {
+                    usage.keys += u64::from(previous.is_none());
+                    usage.bytes = (usage.bytes + new_bytes).saturating_sub(previous.unwrap_or(0));
+                }
+                Err(err) => results[index] = Err(err),
+            }
+        }
+        ledger.save(&*self.inner, tenant)?;
+        Ok(results)
+    }
+}
+
+fn tenant_lock(tenant: &TenantCtx) -> Arc<Mutex<()>> {
+    let name = format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str());
+    Arc::clone(TENANT_LOCKS.lock().entry(name).or_default())
+}
+
+fn encoded_len(value: &Value) -> u64 {
+    serde_json::to_vec(value)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::state::new_state_store;
+    use greentic_types::{EnvId, TenantId};
+    use serde_json::json;
+
+    fn tenant(name: &str) -> TenantCtx {
+        TenantCtx::new(EnvId::new("local").unwrap(), TenantId::new(name).unwrap())
+    }
+
+    #[test]
+    fn overwrites_are_charged_by_their_growth() {
+        let store = apply_quota(
+            new_state_store(),
+            StateQuota {
+                max_keys: None,
+                max_bytes: Some(8),
+            },
+        );
+        let ctx = tenant("quota-overwrite");
+        let key = StateKey::new("k");
+        store
+            .set_json(&ctx, "runner", &key, None, &json!("abcd"), None)
+            .unwrap();
+        store
+            .set_json(&ctx, "runner", &key, None, &json!("abcdef"), None)
+            .unwrap();
+        let err = store
+            .set_json(&ctx, "runner", &key, None, &json!("abcdefgh"), None)
+            .unwrap_err();
+        let hit = breach(&err).expect("breach attached");
+        assert_eq!(hit.limit, "max_bytes");
+        assert_eq!(hit.requested, 10);
+        assert_eq!(
+            usage(&*store, &ctx).unwrap(),
+            StateUsage { keys: 1, bytes: 8 }
+        );
+    }
+}
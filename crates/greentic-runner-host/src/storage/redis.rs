@@ -0,0 +1,363 @@
+//! Connection settings shared by the Redis session and state backends.
+//!
+//! The state backend is the host's own [`RedisStateStore`], which connects per
+//! topology and follows cluster redirects and Sentinel failovers itself. The session
+//! store comes from `greentic-session` and takes a single node URL, so for sessions
+//! this module decides which URL to hand it:
+//!
+//! - `Single` uses the configured URL as is.
+//! - `Sentinel` asks each sentinel in turn for the current primary of the service,
+//!   retrying up to `max_retries` rounds while a failover is in progress. The URL is
+//!   resolved when the session store is built.
+//! - `Cluster` is rejected for sessions: the upstream store does not follow `MOVED`
+//!   redirects, and a client pinned to one node would fail on most keys.
+//!
+//! [`RedisStateStore`]: crate::storage::redis_state::RedisStateStore
+//!
+//! Settings are read from `GREENTIC_{SESSION,STATE}_REDIS_*`; see
+//! [`RedisConfig::from_env`].
+
+use std::env;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+/// Which Redis deployment to talk to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedisTopology {
+    Single {
+        url: String,
+    },
+    /// Seed nodes of a Redis Cluster, as `host:port` or `redis://` URLs.
+    Cluster {
+        nodes: Vec<String>,
+    },
+    /// Sentinels watching `service`, as `host:port` or `redis://` URLs.
+    Sentinel {
+        service: String,
+        sentinels: Vec<String>,
+    },
+}
+
+/// Redis connection settings for one backend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedisConfig {
+    pub topology: RedisTopology,
+    /// Talk TLS (`rediss://`) to the data nodes.
+    pub tls: bool,
+    /// Logical database on the data nodes.
+    pub db: Option<i64>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Per-sentinel timeout while resolving the primary.
+    pub connect_timeout: Duration,
+    /// Rounds over the sentinel list before giving up.
+    pub max_retries: u32,
+    /// Pause between rounds.
+    pub retry_backoff: Duration,
+}
+
+impl RedisConfig {
+    pub fn new(topology: RedisTopology) -> Self {
+        Self {
+            topology,
+            tls: false,
+            db: None,
+            username: None,
+            password: None,
+            connect_timeout: Duration::from_millis(2_000),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// Read `{prefix}_URL`, `{prefix}_CLUSTER_NODES` or `{prefix}_SENTINELS` (with
+    /// `{prefix}_SENTINEL_SERVICE`); node lists are comma separated and at most one
+    /// mode may be set. Shared settings are `{prefix}_{TLS,DB,USERNAME,PASSWORD}`,
+    /// `{prefix}_CONNECT_TIMEOUT_MS`, `{prefix}_MAX_RETRIES` and
+    /// `{prefix}_RETRY_BACKOFF_MS`. Returns `None` when no mode is set.
+    pub fn from_env(prefix: &str) -> Result<Option<Self>> {
+        Self::from_vars(prefix, |name| env::var(name).ok())
+    }
+
+    pub(crate) fn from_vars(
+        prefix: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>> {
+        let get = |suffix: &str| {
+            var(&format!("{prefix}_{suffix}"))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let url = get("URL");
+        let nodes = get("CLUSTER_NODES").map(|value| split_list(&value));
+        let sentinels = get("SENTINELS").map(|value| split_list(&value));
+        let topology = match (url, nodes, sentinels) {
+            (None, None, None) => return Ok(None),
+            (Some(url), None, None) => RedisTopology::Single { url },
+            (None, Some(nodes), None) => RedisTopology::Cluster { nodes },
+            (None, None, Some(sentinels)) => RedisTopology::Sentinel {
+                service: get("SENTINEL_SERVICE").with_context(|| {
+                    format!("{prefix}_SENTINELS requires {prefix}_SENTINEL_SERVICE")
+                })?,
+                sentinels,
+            },
+            _ => {
+                bail!("set only one of {prefix}_URL, {prefix}_CLUSTER_NODES and {prefix}_SENTINELS")
+            }
+        };
+        let mut config = RedisConfig::new(topology);
+        if let Some(value) = get("TLS") {
+            config.tls = parse_flag(&value).with_context(|| format!("invalid {prefix}_TLS"))?;
+        }
+        if let Some(value) = get("DB") {
+            config.db = Some(
+                value
+                    .parse()
+                    .with_context(|| format!("invalid {prefix}_DB"))?,
+            );
+        }
+        config.username = get("USERNAME");
+        config.password = get("PASSWORD");
+        if let Some(value) = get("CONNECT_TIMEOUT_MS") {
+            let millis = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_CONNECT_TIMEOUT_MS"))?;
+            config.connect_timeout = Duration::from_millis(millis);
+        }
+        if let Some(value) = get("MAX_RETRIES") {
+            config.max_retries = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_MAX_RETRIES"))?;
+        }
+        if let Some(value) = get("RETRY_BACKOFF_MS") {
+            let millis = value
+                .parse()
+                .with_context(|| format!("invalid {prefix}_RETRY_BACKOFF_MS"))?;
+            config.retry_backoff = Duration::from_millis(millis);
+        }
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    fn validate(&self) -> Result<()> {
+        match &self.topology {
+            RedisTopology::Single { url } if url.is_empty() => bail!("redis url is empty"),
+            RedisTopology::Cluster { nodes } if nodes.is_empty() => {
+                bail!("redis cluster needs at least one node")
+            }
+            RedisTopology::Cluster { .. } if self.db.is_some_and(|db| db != 0) => {
+                bail!("redis cluster only has database 0")
+            }
+            RedisTopology::Sentinel { sentinels, .. } if sentinels.is_empty() => {
+                bail!("redis sentinel needs at least one sentinel")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The URL of the node to write to: the configured one, or the current primary.
+    #[cfg(feature = "session-redis")]
+    pub fn resolve_url(&self) -> Result<String> {
+        match &self.topology {
+            RedisTopology::Single { url } => Ok(url.clone()),
+            RedisTopology::Cluster { .. } => bail!(
+                "redis cluster is not supported by the greentic session store, \
+                 which connects to a single node; use a standalone or sentinel deployment"
+            ),
+            RedisTopology::Sentinel { service, sentinels } => {
+                let (host, port) = self.resolve_primary(service, sentinels)?;
+                Ok(self.node_url(&format!("{host}:{port}")))
+            }
+        }
+    }
+
+    /// URLs of the cluster seed `nodes`; bare `host:port` entries get the shared TLS
+    /// and credential settings.
+    #[cfg(feature = "session-redis")]
+    pub(crate) fn cluster_urls(&self, nodes: &[String]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| {
+                if node.contains("://") {
+                    node.clone()
+                } else {
+                    self.node_url(node)
+                }
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "session-redis")]
+    fn resolve_primary(&self, service: &str, sentinels: &[String]) -> Result<(String, u16)> {
+        let mut last_error = None;
+        for round in 0..=self.max_retries {
+            if round > 0 {
+                std::thread::sleep(self.retry_backoff);
+            }
+            for sentinel in sentinels {
+                match self.ask_sentinel(sentinel, service) {
+                    Ok(Some(primary)) => return Ok(primary),
+                    Ok(None) => {
+                        last_error = Some(anyhow::anyhow!(
+                            "sentinel {sentinel} does not know service `{service}`"
+                        ));
+                    }
+                    Err(err) => {
+                        tracing::debug!(
+                            sentinel = %sentinel,
+                            error = %err,
+                            "sentinel lookup failed"
+                        );
+                        last_error = Some(err);
+                    }
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("no sentinels configured"))
+            .context(format!("failed to resolve redis primary for `{service}`")))
+    }
+
+    #[cfg(feature = "session-redis")]
+    fn ask_sentinel(&self, sentinel: &str, service: &str) -> Result<Option<(String, u16)>> {
+        let client = ::redis::Client::open(with_scheme(sentinel))
+            .with_context(|| format!("invalid sentinel address {sentinel}"))?;
+        let mut conn = client
+            .get_connection_with_timeout(self.connect_timeout)
+            .with_context(|| format!("failed to connect to sentinel {sentinel}"))?;
+        let primary: Option<(String, u16)> = ::redis::cmd("SENTINEL")
+            .arg("get-master-addr-by-name")
+            .arg(service)
+            .query(&mut conn)
+            .with_context(|| format!("sentinel {sentinel} lookup failed"))?;
+        Ok(primary)
+    }
+
+    #[cfg(feature = "session-redis")]
+    fn node_url(&self, address: &str) -> String {
+        let scheme = if self.tls { "rediss" } else { "redis" };
+        let auth = match (&self.username, &self.password) {
+            (Some(user), Some(password)) => format!("{user}:{password}@"),
+            (None, Some(password)) => format!(":{password}@"),
+            (Some(user), None) => format!("{user}@"),
+            (None, None) => String::new(),
+        };
+        let db = self.db.map(|db| format!("/{db}")).unwrap_or_default();
+        format!("{scheme}://{auth}{address}{db}")
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_flag(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Ok(true),
+        "0" | "false" | "no" => Ok(false),
+        other => bail!("expected a boolean, got `{other}`"),
+    }
+}
+
+#[cfg(feature = "session-redis")]
+fn with_scheme(address: &str) -> String {
+    if address.contains("://") {
+        address.to_string()
+    } else {
+        format!("redis://{address}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<RedisConfig>> {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (format!("TEST_REDIS_{name}"), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        RedisConfig::from_vars("TEST_REDIS", |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn parses_single_node() {
+        let config = parse(&[("URL", "redis://cache:6379/0"), ("MAX_RETRIES", "5")])
+            .unwrap()
+            .expect("configured");
+        assert_eq!(
+            config.topology,
+            RedisTopology::Single {
+                url: "redis://cache:6379/0".into()
+            }
+        );
+        assert_eq!(config.max_retries, 5);
+        assert!(parse(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn parses_cluster_nodes() {
+        let config = parse(&[
+            (
+                "CLUSTER_NODES",
+                "10.0.0.1:6379, 10.0.0.2:6379,,10.0.0.3:6379",
+            ),
+            ("TLS", "true"),
+            ("CONNECT_TIMEOUT_MS", "750"),
+        ])
+        .unwrap()
+        .expect("configured");
+        assert_eq!(
+            config.topology,
+            RedisTopology::Cluster {
+                nodes: vec![
+                    "10.0.0.1:6379".into(),
+                    "10.0.0.2:6379".into(),
+                    "10.0.0.3:6379".into()
+                ]
+            }
+        );
+        assert!(config.tls);
+        assert_eq!(config.connect_timeout, Duration::from_millis(750));
+    }
+
+    #[test]
+    fn parses_sentinel_service() {
+        let config = parse(&[
+            ("SENTINELS", "sentinel-a:26379,sentinel-b:26379"),
+            ("SENTINEL_SERVICE", "sessions"),
+            ("DB", "2"),
+            ("PASSWORD", "hunter2"),
+        ])
+        .unwrap()
+        .expect("configured");
+        assert_eq!(
+            config.topology,
+            RedisTopology::Sentinel {
+                service: "sessions".into(),
+                sentinels: vec!["sentinel-a:26379".into(), "sentinel-b:26379".into()],
+            }
+        );
+        assert_eq!(config.db, Some(2));
+        assert_eq!(config.password.as_deref(), Some("hunter2"));
+
+        let err = parse(&[("SENTINELS", "sentinel-a:26379")]).unwrap_err();
+        assert!(err.to_string().contains("SENTINEL_SERVICE"), "{err}");
+    }
+
+    #[test]
+    fn rejects_conflicting_modes() {
+        let err = parse(&[("URL", "redis://a"), ("CLUSTER_NODES", "b:6379")]).unwrap_err();
+        assert!(err.to_string().contains("only one"), "{err}");
+        assert!(parse(&[("CLUSTER_NODES", " , ")]).is_err());
+        let err = parse(&[("CLUSTER_NODES", "b:6379"), ("DB", "3")]).unwrap_err();
+        assert!(err.to_string().contains("database 0"), "{err}");
+    }
+}
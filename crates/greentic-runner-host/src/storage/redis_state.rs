@@ -0,0 +1,570 @@
+//! Redis-backed [`StateStore`] that follows cluster redirects and Sentinel failovers.
+//!
+//! Values are stored as JSON strings under `greentic:state:{env::tenant}:{prefix}:{key}`.
+//! The tenant is the key's hash tag, so all of a tenant's keys live in one cluster
+//! slot. TTLs map to native expiry (`SET ... EX`), and listing and prefix deletes
+//! `SCAN ... MATCH` the node holding that slot. Batch reads are one `MGET` and batch
+//! writes one pipeline of `SET`s, which the shared slot keeps on a single node.
+//!
+//! The connection depends on the configured [`RedisTopology`]:
+//!
+//! - `Single` connects to the URL as is.
+//! - `Cluster` uses a cluster connection seeded with the configured nodes, which
+//!   follows `MOVED`/`ASK` redirects and refreshes its slot map itself.
+//! - `Sentinel` asks the sentinels for the current primary on every new connection.
+//!
+//! Idle connections are kept for reuse. An operation failing with a connection error,
+//! or with `READONLY`/`MASTERDOWN`/`TRYAGAIN`/`CLUSTERDOWN` while a failover is in
+//! progress, drops them and is retried on a fresh connection up to `max_retries`
+//! times, `retry_backoff` apart; for Sentinel that re-resolves the primary.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{ErrorCode, GResult, GreenticError, TenantCtx};
+use parking_lot::Mutex;
+use redis::cluster::{ClusterClient, ClusterConnection};
+use redis::cluster_routing::{Route, RoutingInfo, SingleNodeRoutingInfo, SlotAddr};
+use redis::{Cmd, ConnectionLike, Pipeline, RedisError, RedisResult};
+use serde_json::Value;
+
+use crate::storage::listing::Cursor;
+use crate::storage::redis::{RedisConfig, RedisTopology};
+use crate::storage::state::HostStateStore;
+
+const KEY_NAMESPACE: &str = "greentic:state";
+/// Keys requested per `SCAN` call.
+const SCAN_COUNT: usize = 256;
+const CLUSTER_SLOTS: u16 = 16384;
+
+pub struct RedisStateStore {
+    config: RedisConfig,
+    idle: Mutex<Vec<Connection>>,
+    round_trips: Arc<AtomicU64>,
+}
+
+impl RedisStateStore {
+    /// Check the configuration and open a first connection.
+    pub fn connect(config: &RedisConfig) -> anyhow::Result<Self> {
+        let store = Self {
+            config: config.clone(),
+            idle: Mutex::new(Vec::new()),
+            round_trips: Arc::new(AtomicU64::new(0)),
+        };
+        let conn = store.open()?;
+        store.idle.lock().push(conn);
+        Ok(store)
+    }
+
+    /// Requests sent to Redis so far: one per command, `SCAN` page or pipeline.
+    pub fn round_trips(&self) -> u64 {
+        self.round_trips.load(Ordering::Relaxed)
+    }
+
+    fn open(&self) -> anyhow::Result<Connection> {
+        let link = match &self.config.topology {
+            RedisTopology::Cluster { nodes } => {
+                let client = ClusterClient::builder(self.config.cluster_urls(nodes))
+                    .connection_timeout(self.config.connect_timeout)
+                    .retries(self.config.max_retries)
+                    .build()?;
+                Link::Cluster(client.get_connection()?)
+            }
+            RedisTopology::Single { .. } | RedisTopology::Sentinel { .. } => {
+                let client = redis::Client::open(self.config.resolve_url()?)?;
+                Link::Node(client.get_connection_with_timeout(self.config.connect_timeout)?)
+            }
+        };
+        Ok(Connection {
+            link,
+            round_trips: Arc::clone(&self.round_trips),
+        })
+    }
+
+    /// Run `op` on an idle or new connection, reconnecting after failover errors.
+    fn run<T>(&self, mut op: impl FnMut(&mut Connection) -> RedisResult<T>) -> GResult<T> {
+        with_retries(
+            self.config.max_retries,
+            || thread::sleep(self.config.retry_backoff),
+            || {
+                let mut conn = match self.idle.lock().pop() {
+                    Some(conn) => conn,
+                    None => self.open().map_err(Failure::Reconnect)?,
+                };
+                let value = op(&mut conn).map_err(|err| {
+                    if is_failover(&err) {
+                        // Pooled connections point at the same, now unusable, node.
+                        self.idle.lock().clear();
+                        Failure::Reconnect(err.into())
+                    } else {
+                        Failure::Fatal(err)
+                    }
+                })?;
+                self.idle.lock().push(conn);
+                Ok(value)
+            },
+        )
+    }
+
+    /// Every key of `tenant` under `prefix`, without the namespace.
+    fn scan(&self, tenant: &TenantCtx, prefix: &str) -> GResult<Vec<String>> {
+        let base = prefix_base(tenant, prefix);
+        let pattern = format!("{}*", escape_glob(&base));
+        let tag = hash_tag(tenant);
+        self.run(|conn| {
+            let mut keys = Vec::new();
+            let mut cursor = 0u64;
+            loop {
+                let mut cmd = redis::cmd("SCAN");
+                cmd.arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(SCAN_COUNT);
+                let (next, batch) = conn.scan_slot(&tag, &cmd)?;
+                keys.extend(
+                    batch
+                        .into_iter()
+                        .filter_map(|key| key.strip_prefix(&base).map(str::to_string)),
+                );
+                if next == 0 {
+                    return Ok(keys);
+                }
+                cursor = next;
+            }
+        })
+    }
+}
+
+impl StateStore for RedisStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        reject_path(path)?;
+        let name = redis_key(tenant, prefix, key);
+        let stored: Option<String> = self.run(|conn| conn.query(redis::cmd("GET").arg(&name)))?;
+        stored.map(|text| decode(&text)).transpose()
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        reject_path(path)?;
+        let cmd = set_cmd(&redis_key(tenant, prefix, key), value, ttl_secs)?;
+        self.run(|conn| conn.query::<()>(&cmd))
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        let name = redis_key(tenant, prefix, key);
+        let removed: u64 = self.run(|conn| conn.query(redis::cmd("DEL").arg(&name)))?;
+        Ok(removed > 0)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let base = prefix_base(tenant, prefix);
+        let mut removed = 0;
+        for chunk in self.scan(tenant, prefix)?.chunks(SCAN_COUNT) {
+            let mut cmd = redis::cmd("DEL");
+            for key in chunk {
+                cmd.arg(format!("{base}{key}"));
+            }
+            removed += self.run(|conn| conn.query::<u64>(&cmd))?;
+        }
+        Ok(removed)
+    }
+}
+
+impl HostStateStore for RedisStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        // SCAN may return a key more than once and in any order, so every page scans
+        // the tenant's keys and keeps the first `limit + 1` after the cursor in order.
+        let after = cursor.map(Cursor::as_str);
+        let mut page = BTreeSet::new();
+        for key in self.scan(tenant, prefix)? {
+            if !key.starts_with(key_prefix) || after.is_some_and(|after| key.as_str() <= after) {
+                continue;
+            }
+            page.insert(key);
+            if page.len() > limit.saturating_add(1) {
+                page.pop_last();
+            }
+        }
+        let mut keys = page.into_iter().collect::<Vec<_>>();
+        let next = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned().map(Cursor::new)
+        } else {
+            None
+        };
+        Ok((keys.into_iter().map(StateKey::from).collect(), next))
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        let name = redis_key(tenant, prefix, key);
+        // Negative replies mean the key is missing (-2) or has no expiry (-1).
+        let millis: i64 = self.run(|conn| conn.query(redis::cmd("PTTL").arg(&name)))?;
+        Ok(u64::try_from(millis).ok().map(Duration::from_millis))
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut cmd = redis::cmd("MGET");
+        for key in keys {
+            cmd.arg(redis_key(tenant, prefix, key));
+        }
+        let stored: Vec<Option<String>> = self.run(|conn| conn.query(&cmd))?;
+        stored
+            .into_iter()
+            .map(|text| text.map(|text| decode(&text)).transpose())
+            .collect()
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let mut results = Vec::with_capacity(entries.len());
+        let mut pipe = redis::pipe();
+        let mut sent = Vec::new();
+        for (index, (key, value)) in entries.iter().enumerate() {
+            match set_cmd(&redis_key(tenant, prefix, key), value, ttl_secs) {
+                Ok(cmd) => {
+                    pipe.add_command(cmd);
+                    sent.push(index);
+                    results.push(Ok(()));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+        if sent.is_empty() {
+            return Ok(results);
+        }
+        let replies = self.run(|conn| {
+            let replies = conn.pipeline(&pipe, sent.len())?;
+            // A failover rejects every command alike; retry the batch on a new
+            // connection instead of reporting it per key.
+            match replies.iter().find_map(failover_reply) {
+                Some(err) => Err(err),
+                None => Ok(replies),
+            }
+        })?;
+        for (index, reply) in sent.into_iter().zip(replies) {
+            if let redis::Value::ServerError(err) = reply {
+                results[index] = Err(redis_error(err.into()));
+            }
+        }
+        Ok(results)
+    }
+}
+
+struct Connection {
+    link: Link,
+    round_trips: Arc<AtomicU64>,
+}
+
+enum Link {
+    Node(redis::Connection),
+    Cluster(ClusterConnection),
+}
+
+impl Connection {
+    fn query<T: redis::FromRedisValue>(&mut self, cmd: &Cmd) -> RedisResult<T> {
+        self.round_trips.fetch_add(1, Ordering::Relaxed);
+        match &mut self.link {
+            Link::Node(conn) => cmd.query(conn),
+            Link::Cluster(conn) => cmd.query(conn),
+        }
+    }
+
+    /// Run a `SCAN` on the node serving `tag`'s slot; returns the next cursor and keys.
+    fn scan_slot(&mut self, tag: &str, cmd: &Cmd) -> RedisResult<(u64, Vec<String>)> {
+        self.round_trips.fetch_add(1, Ordering::Relaxed);
+        match &mut self.link {
+            Link::Node(conn) => cmd.query(conn),
+            Link::Cluster(conn) => {
+                let route = Route::new(key_slot(tag), SlotAddr::Master);
+                let routing = RoutingInfo::SingleNode(SingleNodeRoutingInfo::SpecificNode(route));
+                parse_scan_reply(conn.route_command(cmd, routing)?)
+            }
+        }
+    }
+
+    /// Send the `count` commands of `pipe` in one round trip. Replies follow the
+    /// commands, with per-command server errors left in place as values. On a cluster
+    /// the pipeline goes to the node owning its first key, so its keys must share a
+    /// slot.
+    fn pipeline(&mut self, pipe: &Pipeline, count: usize) -> RedisResult<Vec<redis::Value>> {
+        self.round_trips.fetch_add(1, Ordering::Relaxed);
+        let packed = pipe.get_packed_pipeline();
+        match &mut self.link {
+            Link::Node(conn) => conn.req_packed_commands(&packed, 0, count),
+            Link::Cluster(conn) => conn.req_packed_commands(&packed, 0, count),
+        }
+    }
+}
+
+/// Why an attempt failed: worth another try on a new connection, or not.
+enum Failure {
+    Reconnect(anyhow::Error),
+    Fatal(RedisError),
+}
+
+/// Run `attempt` until it succeeds, fails fatally, or has been retried `max_retries`
+/// times; `pause` runs before every retry.
+fn with_retries<T>(
+    max_retries: u32,
+    mut pause: impl FnMut(),
+    mut attempt: impl FnMut() -> Result<T, Failure>,
+) -> GResult<T> {
+    let mut retries = 0;
+    loop {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(Failure::Fatal(err)) => return Err(redis_error(err)),
+            Err(Failure::Reconnect(err)) if retries >= max_retries => {
+                return Err(GreenticError::new(
+                    ErrorCode::Unavailable,
+                    format!("redis state store: {err:#}"),
+                ));
+            }
+            Err(Failure::Reconnect(err)) => {
+                retries += 1;
+                tracing::debug!(retries, error = %err, "redis state store reconnecting");
+                pause();
+            }
+        }
+    }
+}
+
+/// Whether `err` means the connection, or the node behind it, cannot serve us anymore.
+fn is_failover(err: &RedisError) -> bool {
+    err.is_io_error()
+        || err.is_connection_dropped()
+        || err.is_connection_refusal()
+        || err.is_timeout()
+        || matches!(
+            err.code(),
+            Some("READONLY" | "MASTERDOWN" | "TRYAGAIN" | "CLUSTERDOWN" | "LOADING")
+        )
+}
+
+/// The error in `reply` if it is a server error worth a reconnect.
+fn failover_reply(reply: &redis::Value) -> Option<RedisError> {
+    match reply {
+        redis::Value::ServerError(err) => Some(RedisError::from(err.clone())).filter(is_failover),
+        _ => None,
+    }
+}
+
+fn parse_scan_reply(reply: redis::Value) -> RedisResult<(u64, Vec<String>)> {
+    let malformed = || RedisError::from((redis::ErrorKind::TypeError, "malformed SCAN reply"));
+    let redis::Value::Array(parts) = reply else {
+        return Err(malformed());
+    };
+    let [redis::Value::BulkString(cursor), redis::Value::Array(keys)] = parts.as_slice() else {
+        return Err(malformed());
+    };
+    let cursor = std::str::from_utf8(cursor)
+        .ok()
+        .and_then(|cursor| cursor.parse().ok())
+        .ok_or_else(malformed)?;
+    let keys = keys
+        .iter()
+        .map(|key| match key {
+            redis::Value::BulkString(bytes) => String::from_utf8(bytes.clone()).ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(malformed)?;
+    Ok((cursor, keys))
+}
+
+fn hash_tag(tenant: &TenantCtx) -> String {
+    format!("{}::{}", tenant.env.as_str(), tenant.tenant.as_str())
+}
+
+fn prefix_base(tenant: &TenantCtx, prefix: &str) -> String {
+    format!("{KEY_NAMESPACE}:{{{}}}:{prefix}:", hash_tag(tenant))
+}
+
+fn redis_key(tenant: &TenantCtx, prefix: &str, key: &StateKey) -> String {
+    format!("{}{}", prefix_base(tenant, prefix), key.as_str())
+}
+
+fn set_cmd(name: &str, value: &Value, ttl_secs: Option<u32>) -> GResult<Cmd> {
+    let text = serde_json::to_string(value).map_err(|err| {
+        GreenticError::new(
+            ErrorCode::InvalidInput,
+            format!("state value is not serializable: {err}"),
+        )
+    })?;
+    let mut cmd = redis::cmd("SET");
+    cmd.arg(name).arg(text);
+    if let Some(ttl) = ttl_secs {
+        cmd.arg("EX").arg(ttl.max(1));
+    }
+    Ok(cmd)
+}
+
+fn decode(text: &str) -> GResult<Value> {
+    serde_json::from_str(text).map_err(|err| {
+        GreenticError::new(
+            ErrorCode::Internal,
+            format!("malformed state value in redis: {err}"),
+        )
+    })
+}
+
+/// Escape `SCAN MATCH` wildcards so `value` matches literally.
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Cluster slot of `tag` (CRC16/XMODEM modulo the slot count).
+fn key_slot(tag: &str) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in tag.bytes() {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc % CLUSTER_SLOTS
+}
+
+fn reject_path(path: Option<&StatePath>) -> GResult<()> {
+    match path {
+        None => Ok(()),
+        Some(_) => Err(GreenticError::new(
+            ErrorCode::InvalidInput,
+            "redis state store does not support JSON paths",
+        )),
+    }
+}
+
+fn redis_error(err: RedisError) -> GreenticError {
+    GreenticError::new(ErrorCode::Unavailable, format!("redis state store: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use greentic_types::{EnvId, TenantId};
+    use std::cell::Cell;
+
+    fn server_error(code: &str) -> RedisError {
+        let reply = format!("-{code} simulated\r\n");
+        match redis::parse_redis_value(reply.as_bytes()) {
+            Ok(redis::Value::ServerError(err)) => err.into(),
+            other => panic!("not a server error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn tenant_keys_share_a_cluster_slot() {
+        assert_eq!(key_slot("123456789"), 12739);
+        assert_eq!(key_slot("foo"), 12182);
+        let tenant = TenantCtx::new(EnvId::new("local").unwrap(), TenantId::new("acme").unwrap());
+        let key = redis_key(&tenant, "runner", &StateKey::new("a"));
+        assert_eq!(key, "greentic:state:{local::acme}:runner:a");
+        assert_eq!(escape_glob("a*b?[c]"), "a\\*b\\?\\[c\\]");
+    }
+
+    #[test]
+    fn failover_errors_are_retried_on_a_new_connection() {
+        assert!(is_failover(&server_error("READONLY")));
+        assert!(!is_failover(&server_error("WRONGTYPE")));
+
+        let attempts = Cell::new(0);
+        let pauses = Cell::new(0);
+        let value = with_retries(
+            3,
+            || pauses.set(pauses.get() + 1),
+            || {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err(Failure::Reconnect(server_error("READONLY").into()))
+                } else {
+                    Ok("primary")
+                }
+            },
+        )
+        .unwrap();
+        assert_eq!((value, attempts.get(), pauses.get()), ("primary", 3, 2));
+    }
+
+    #[test]
+    fn retries_are_bounded_and_other_errors_are_not_retried() {
+        let attempts = Cell::new(0);
+        let err = with_retries(
+            2,
+            || {},
+            || -> Result<(), _> {
+                attempts.set(attempts.get() + 1);
+                Err(Failure::Reconnect(anyhow::anyhow!("connection refused")))
+            },
+        )
+        .unwrap_err();
+        assert_eq!(attempts.get(), 3);
+        assert!(err.to_string().contains("connection refused"), "{err}");
+
+        attempts.set(0);
+        with_retries(
+            2,
+            || {},
+            || -> Result<(), _> {
+                attempts.set(attempts.get() + 1);
+                Err(Failure::Fatal(server_error("WRONGTYPE")))
+            },
+        )
+        .unwrap_err();
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -18,6 +18,7 @@ use crate::engine::host::{
     OutboxKey, SessionCursor, SessionHost, SessionKey, SessionOutboxEntry, SessionSnapshot,
     WaitState,
 };
+use crate::storage::redis::RedisConfig;
 
 pub type DynSessionStore = Arc<dyn SessionStore>;
 
@@ -98,6 +99,42 @@ pub fn new_session_store() -> DynSessionStore {
     Arc::new(InMemorySessionStore::new())
 }
 
+/// Where the host keeps sessions and flow resume records.
+#[derive(Clone, Debug, Default)]
+pub enum SessionBackend {
+    #[default]
+    Memory,
+    /// Requires the `session-redis` feature.
+    Redis(RedisConfig),
+}
+
+impl SessionBackend {
+    /// `GREENTIC_SESSION_REDIS_*` selects Redis; see [`RedisConfig::from_env`].
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(match RedisConfig::from_env("GREENTIC_SESSION_REDIS")? {
+            Some(config) => SessionBackend::Redis(config),
+            None => SessionBackend::Memory,
+        })
+    }
+
+    pub fn build_store(&self) -> anyhow::Result<DynSessionStore> {
+        match self {
+            SessionBackend::Memory => Ok(new_session_store()),
+            #[cfg(feature = "session-redis")]
+            SessionBackend::Redis(config) => {
+                use greentic_session::{SessionBackendConfig, create_session_store};
+                let url = config.resolve_url()?;
+                let store = create_session_store(SessionBackendConfig::RedisUrl(url))?;
+                Ok(Arc::from(store))
+            }
+            #[cfg(not(feature = "session-redis"))]
+            SessionBackend::Redis(_) => {
+                anyhow::bail!("redis session store requires the `session-redis` feature")
+            }
+        }
+    }
+}
+
 pub fn session_host_from(store: DynSessionStore) -> Arc<dyn SessionHost> {
     Arc::new(SessionStoreHost::new(store))
 }
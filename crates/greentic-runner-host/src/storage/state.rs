@@ -1,6 +1,11 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::env;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use greentic_state::inmemory::InMemoryStateStore;
 use greentic_state::{StateKey as StoreStateKey, StateStore};
@@ -10,8 +15,100 @@ use serde_json::Value;
 use crate::engine::error::{GResult, RunnerError};
 use crate::engine::host::{SessionKey, StateHost};
 use crate::fault::wrap_state_store;
+use crate::storage::expiry::{self, ExpiringStateStore};
+use crate::storage::listing::{self, Cursor, IndexedStateStore};
+use crate::storage::quota;
+use crate::storage::redis::RedisConfig;
 
-pub type DynStateStore = Arc<dyn StateStore>;
+pub type DynStateStore = Arc<dyn HostStateStore>;
+
+/// [`StateStore`] plus the operations the host needs that it lacks. Every wrapper
+/// forwards them to the store it wraps, so they reach the backend through any stack.
+pub trait HostStateStore: StateStore {
+    /// Up to `limit` keys of `tenant` under `prefix` that start with `key_prefix`,
+    /// resuming after `cursor`. The returned cursor is `None` once the scan is done;
+    /// see [`listing`] for ordering.
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> greentic_types::GResult<(Vec<StoreStateKey>, Option<Cursor>)>;
+
+    /// Time left before `key` expires; `None` when it has no TTL or does not exist.
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StoreStateKey,
+    ) -> greentic_types::GResult<Option<Duration>>;
+
+    /// Read `keys` of `tenant` under `prefix`; results follow `keys`, with `None` for
+    /// misses. Backends with a batch read answer in one round trip.
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StoreStateKey],
+    ) -> greentic_types::GResult<Vec<Option<Value>>>;
+
+    /// Write every entry with `ttl_secs`. An outer error fails the whole batch;
+    /// otherwise there is one result per entry, in order, and a rejected entry does
+    /// not stop the ones after it.
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StoreStateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> greentic_types::GResult<Vec<greentic_types::GResult<()>>>;
+}
+
+/// [`HostStateStore::get_many`] for stores without a batch read: one read per key.
+pub(crate) fn get_each(
+    store: &dyn StateStore,
+    tenant: &TenantCtx,
+    prefix: &str,
+    keys: &[StoreStateKey],
+) -> greentic_types::GResult<Vec<Option<Value>>> {
+    keys.iter()
+        .map(|key| store.get_json(tenant, prefix, key, None))
+        .collect()
+}
+
+/// [`HostStateStore::set_many`] for stores without a batch write: one write per entry.
+pub(crate) fn set_each(
+    store: &dyn StateStore,
+    tenant: &TenantCtx,
+    prefix: &str,
+    entries: &[(StoreStateKey, Value)],
+    ttl_secs: Option<u32>,
+) -> Vec<greentic_types::GResult<()>> {
+    entries
+        .iter()
+        .map(|(key, value)| store.set_json(tenant, prefix, key, None, value, ttl_secs))
+        .collect()
+}
+
+/// Rewrite a whole document in `store`, keeping the TTL `key` has left. Wrappers that
+/// upgrade documents on read (re-encryption, migrations) write them back through this;
+/// a key whose deadline has already passed is left to expire.
+pub(crate) fn write_back(
+    store: &dyn HostStateStore,
+    tenant: &TenantCtx,
+    prefix: &str,
+    key: &StoreStateKey,
+    value: &Value,
+) -> greentic_types::GResult<()> {
+    let ttl = match store.remaining_ttl(tenant, prefix, key)? {
+        Some(left) if left.is_zero() => return Ok(()),
+        Some(left) => Some(ttl_secs(left)),
+        None => None,
+    };
+    store.set_json(tenant, prefix, key, None, value, ttl)
+}
 
 pub(crate) const STATE_PREFIX: &str = "runner";
 
@@ -23,11 +120,178 @@ impl StateStoreHost {
     pub fn new(store: DynStateStore) -> Self {
         Self { store }
     }
+
+    fn write(&self, key: &SessionKey, value: &Value, ttl_secs: Option<u32>) -> GResult<()> {
+        let tenant = tenant_ctx_from_key(key)?;
+        let state_key = derive_state_key(key);
+        self.store
+            .set_json(&tenant, STATE_PREFIX, &state_key, None, value, ttl_secs)
+            .map_err(map_state_error)
+    }
 }
 
 pub fn new_state_store() -> DynStateStore {
-    let store: DynStateStore = Arc::new(InMemoryStateStore::new());
-    wrap_state_store(store)
+    state_store_from(Arc::new(InMemoryStateStore::new()))
+}
+
+/// Adopt a plain `store` that ignores TTLs, adding the key index and expiry the
+/// in-memory store gets.
+pub fn state_store_from(store: Arc<dyn StateStore>) -> DynStateStore {
+    let indexed = Arc::new(IndexedStateStore::new(store));
+    wrap_state_store(expiry::track(Arc::new(ExpiringStateStore::new(indexed))))
+}
+
+/// Where the host keeps runner state.
+#[derive(Clone, Debug, Default)]
+pub enum StateBackend {
+    #[default]
+    Memory,
+    /// Requires the `state-postgres` feature.
+    Postgres(PostgresStateConfig),
+    /// Requires the `session-redis` feature, which brings in the Redis client.
+    Redis(RedisConfig),
+}
+
+impl StateBackend {
+    /// `GREENTIC_STATE_POSTGRES_URL` selects Postgres; the pool is tuned with
+    /// `GREENTIC_STATE_POSTGRES_{MAX_CONNECTIONS,MIN_CONNECTIONS,ACQUIRE_TIMEOUT_MS,IDLE_TIMEOUT_MS}`.
+    /// Otherwise `GREENTIC_STATE_REDIS_*` selects Redis; see [`RedisConfig::from_env`].
+    pub fn from_env() -> Result<Self> {
+        let Some(url) = env::var("GREENTIC_STATE_POSTGRES_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        else {
+            return Ok(match RedisConfig::from_env("GREENTIC_STATE_REDIS")? {
+                Some(config) => StateBackend::Redis(config),
+                None => StateBackend::Memory,
+            });
+        };
+        let mut config = PostgresStateConfig::new(url.trim());
+        if let Some(value) = env_number("GREENTIC_STATE_POSTGRES_MAX_CONNECTIONS")? {
+            config.max_connections = value;
+        }
+        if let Some(value) = env_number("GREENTIC_STATE_POSTGRES_MIN_CONNECTIONS")? {
+            config.min_connections = value;
+        }
+        if let Some(value) = env_number("GREENTIC_STATE_POSTGRES_ACQUIRE_TIMEOUT_MS")? {
+            config.acquire_timeout_ms = value;
+        }
+        config.idle_timeout_ms = env_number("GREENTIC_STATE_POSTGRES_IDLE_TIMEOUT_MS")?;
+        Ok(StateBackend::Postgres(config))
+    }
+
+    pub fn build_store(&self) -> Result<DynStateStore> {
+        match self {
+            StateBackend::Memory => Ok(new_state_store()),
+            #[cfg(feature = "state-postgres")]
+            StateBackend::Postgres(config) => {
+                let store = Arc::new(crate::storage::postgres::PostgresStateStore::connect(
+                    config,
+                )?);
+                Ok(wrap_state_store(expiry::track(store)))
+            }
+            #[cfg(not(feature = "state-postgres"))]
+            StateBackend::Postgres(_) => {
+                anyhow::bail!("postgres state store requires the `state-postgres` feature")
+            }
+            #[cfg(feature = "session-redis")]
+            StateBackend::Redis(config) => {
+                let store = crate::storage::redis_state::RedisStateStore::connect(config)?;
+                Ok(wrap_state_store(Arc::new(store)))
+            }
+            #[cfg(not(feature = "session-redis"))]
+            StateBackend::Redis(_) => {
+                anyhow::bail!("redis state store requires the `session-redis` feature")
+            }
+        }
+    }
+}
+
+/// Connection settings for the Postgres state store.
+#[derive(Clone, Debug)]
+pub struct PostgresStateConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_ms: u64,
+    /// Close pooled connections idle for longer than this; `None` keeps sqlx's default.
+    pub idle_timeout_ms: Option<u64>,
+    /// Worker threads of the runtime driving the pool.
+    pub runtime_threads: usize,
+}
+
+impl PostgresStateConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_ms: 5_000,
+            idle_timeout_ms: None,
+            runtime_threads: 2,
+        }
+    }
+}
+
+fn env_number<T: FromStr>(name: &str) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    env::var(name)
+        .ok()
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid {name}"))
+        })
+        .transpose()
+}
+
+/// Write `documents` as runner state of `tenant` in the `GREENTIC_ENV` environment,
+/// under the keys a component reads them with.
+pub fn seed_state(
+    store: &DynStateStore,
+    tenant: &str,
+    documents: &BTreeMap<String, Value>,
+) -> GResult<()> {
+    let tenant = tenant_ctx(tenant)?;
+    for (key, value) in documents {
+        store
+            .set_json(
+                &tenant,
+                STATE_PREFIX,
+                &StoreStateKey::from(key.clone()),
+                None,
+                value,
+                None,
+            )
+            .map_err(map_state_error)?;
+    }
+    Ok(())
+}
+
+/// Every runner state document of `tenant`, by key; the store must support listing.
+pub fn snapshot_state(store: &DynStateStore, tenant: &str) -> GResult<BTreeMap<String, Value>> {
+    let tenant = tenant_ctx(tenant)?;
+    let mut documents = BTreeMap::new();
+    let mut cursor = None;
+    loop {
+        let (keys, next) = listing::list_state(store, &tenant, "", cursor.as_ref(), 256)
+            .map_err(map_state_error)?;
+        for key in keys {
+            if let Some(value) = store
+                .get_json(&tenant, STATE_PREFIX, &key, None)
+                .map_err(map_state_error)?
+            {
+                documents.insert(key.as_str().to_string(), value);
+            }
+        }
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(documents),
+        }
+    }
 }
 
 pub fn state_host_from(store: DynStateStore) -> Arc<dyn StateHost> {
@@ -45,11 +309,11 @@ impl StateHost for StateStoreHost {
     }
 
     async fn set_json(&self, key: &SessionKey, value: Value) -> GResult<()> {
-        let tenant = tenant_ctx_from_key(key)?;
-        let state_key = derive_state_key(key);
-        self.store
-            .set_json(&tenant, STATE_PREFIX, &state_key, None, &value, None)
-            .map_err(map_state_error)
+        self.write(key, &value, None)
+    }
+
+    async fn set_json_ttl(&self, key: &SessionKey, value: Value, ttl: Duration) -> GResult<()> {
+        self.write(key, &value, Some(ttl_secs(ttl)))
     }
 
     async fn del(&self, key: &SessionKey) -> GResult<()> {
@@ -65,6 +329,84 @@ impl StateHost for StateStoreHost {
         // Prefix deletions are not currently used; provide a no-op implementation.
         Ok(())
     }
+
+    async fn get_many(&self, keys: &[SessionKey]) -> GResult<Vec<Option<Value>>> {
+        let mut values = vec![None; keys.len()];
+        for (tenant, indexes) in by_tenant(keys)? {
+            let batch = indexes
+                .iter()
+                .map(|&index| derive_state_key(&keys[index]))
+                .collect::<Vec<_>>();
+            let found = self
+                .store
+                .get_many(&tenant, STATE_PREFIX, &batch)
+                .map_err(map_state_error)?;
+            for (index, value) in indexes.into_iter().zip(found) {
+                values[index] = value;
+            }
+        }
+        Ok(values)
+    }
+
+    async fn set_many(&self, entries: Vec<(SessionKey, Value)>) -> GResult<Vec<GResult<()>>> {
+        let mut results = entries.iter().map(|_| Ok(())).collect::<Vec<GResult<()>>>();
+        let mut batches: BTreeMap<&str, (TenantCtx, Vec<usize>)> = BTreeMap::new();
+        for (index, (key, _)) in entries.iter().enumerate() {
+            match tenant_ctx_from_key(key) {
+                Ok(tenant) => batches
+                    .entry(key.tenant_key.as_str())
+                    .or_insert_with(|| (tenant, Vec::new()))
+                    .1
+                    .push(index),
+                Err(err) => results[index] = Err(err),
+            }
+        }
+        for (tenant, indexes) in batches.into_values() {
+            let batch = indexes
+                .iter()
+                .map(|&index| {
+                    let (key, value) = &entries[index];
+                    (derive_state_key(key), value.clone())
+                })
+                .collect::<Vec<_>>();
+            match self.store.set_many(&tenant, STATE_PREFIX, &batch, None) {
+                Ok(outcomes) => {
+                    for (index, outcome) in indexes.into_iter().zip(outcomes) {
+                        results[index] = outcome.map_err(map_state_error);
+                    }
+                }
+                Err(err) => {
+                    let reason = err.to_string();
+                    for index in indexes {
+                        results[index] = Err(RunnerError::State {
+                            reason: reason.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Positions of `keys` grouped by tenant, so each tenant's keys go to the store as one
+/// batch.
+fn by_tenant(keys: &[SessionKey]) -> GResult<Vec<(TenantCtx, Vec<usize>)>> {
+    let mut batches: BTreeMap<&str, (TenantCtx, Vec<usize>)> = BTreeMap::new();
+    for (index, key) in keys.iter().enumerate() {
+        let (_, indexes) = match batches.entry(key.tenant_key.as_str()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert((tenant_ctx_from_key(key)?, Vec::new())),
+        };
+        indexes.push(index);
+    }
+    Ok(batches.into_values().collect())
+}
+
+/// `tenant` in the `GREENTIC_ENV` environment, as components address their state.
+fn tenant_ctx(tenant: &str) -> GResult<TenantCtx> {
+    let env = env::var("GREENTIC_ENV").unwrap_or_else(|_| "local".to_string());
+    parse_tenant_ctx(&env, tenant)
 }
 
 fn tenant_ctx_from_key(key: &SessionKey) -> GResult<TenantCtx> {
@@ -74,6 +416,10 @@ fn tenant_ctx_from_key(key: &SessionKey) -> GResult<TenantCtx> {
         .ok_or_else(|| RunnerError::State {
             reason: format!("invalid tenant descriptor '{}'", key.tenant_key),
         })?;
+    parse_tenant_ctx(env, tenant)
+}
+
+fn parse_tenant_ctx(env: &str, tenant: &str) -> GResult<TenantCtx> {
     let env_id = EnvId::from_str(env).map_err(|err| RunnerError::State {
         reason: format!("invalid env id {env}: {err}"),
     })?;
@@ -91,8 +437,20 @@ fn derive_state_key(key: &SessionKey) -> StoreStateKey {
     ))
 }
 
+/// Whole seconds for the store, rounding up so a short TTL never means "no expiry".
+fn ttl_secs(ttl: Duration) -> u32 {
+    u32::try_from(ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0))
+        .unwrap_or(u32::MAX)
+        .max(1)
+}
+
 fn map_state_error(err: greentic_types::GreenticError) -> RunnerError {
-    RunnerError::State {
-        reason: err.to_string(),
+    match quota::breach(&err) {
+        Some(hit) => RunnerError::Policy {
+            reason: hit.to_string(),
+        },
+        None => RunnerError::State {
+            reason: err.to_string(),
+        },
     }
 }
@@ -0,0 +1,299 @@
+//! Versioned state documents.
+//!
+//! When the host registers [`StateMigrations`], every document written to the state
+//! store is wrapped in a [`StateEnvelope`] (`{"schema_version": N, "data": ...}`) at the
+//! registry's current version. Reads unwrap the envelope and, for documents written at
+//! an older version, run the registered transforms in order, write the migrated
+//! document back and return it. Documents without an envelope (written before
+//! versioning was enabled, or by other tools) are treated as version 0.
+//!
+//! Documents newer than the registry are returned unchanged so a rolled-back host can
+//! still read what it understands. The write-back after a migration keeps the TTL the
+//! document had left.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{ErrorCode, GResult, GreenticError, TenantCtx};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::storage::listing::Cursor;
+use crate::storage::state::{self, DynStateStore, HostStateStore};
+
+/// Upgrades a document from the given version to the next one.
+pub type StateMigration = Arc<dyn Fn(u32, Value) -> Result<Value> + Send + Sync>;
+
+/// Transforms keyed by the version they upgrade from.
+#[derive(Clone, Default)]
+pub struct StateMigrations {
+    steps: BTreeMap<u32, StateMigration>,
+}
+
+impl StateMigrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the transform upgrading documents at version `from` to `from + 1`.
+    pub fn register<F>(mut self, from: u32, migration: F) -> Self
+    where
+        F: Fn(u32, Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.steps.insert(from, Arc::new(migration));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Version new documents are written at: one past the last registered transform.
+    pub fn current_version(&self) -> u32 {
+        self.steps
+            .last_key_value()
+            .map(|(from, _)| from + 1)
+            .unwrap_or(0)
+    }
+
+    /// Run the transforms from `version` up to [`Self::current_version`].
+    pub fn migrate(&self, version: u32, mut data: Value) -> Result<Value> {
+        for to in version..self.current_version() {
+            let step = self.steps.get(&to).ok_or_else(|| {
+                anyhow::anyhow!("no state migration registered from version {to}")
+            })?;
+            data = step(to, data)
+                .map_err(|err| err.context(format!("state migration from version {to} failed")))?;
+        }
+        Ok(data)
+    }
+}
+
+impl fmt::Debug for StateMigrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateMigrations")
+            .field("from_versions", &self.steps.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Stored form of a versioned document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateEnvelope {
+    pub schema_version: u32,
+    pub data: Value,
+}
+
+impl StateEnvelope {
+    /// Split a stored document into its version and data; anything that is not an
+    /// envelope is a version 0 document.
+    pub fn open(stored: Value) -> Self {
+        let is_envelope = stored
+            .as_object()
+            .is_some_and(|map| map.len() == 2 && map.contains_key("schema_version"));
+        if is_envelope && let Ok(envelope) = serde_json::from_value::<Self>(stored.clone()) {
+            return envelope;
+        }
+        Self {
+            schema_version: 0,
+            data: stored,
+        }
+    }
+}
+
+/// Wrap `store` so documents are versioned with `migrations`; an empty registry
+/// returns it as is.
+pub fn apply_migrations(store: DynStateStore, migrations: StateMigrations) -> DynStateStore {
+    if migrations.is_empty() {
+        return store;
+    }
+    Arc::new(VersionedStateStore {
+        inner: store,
+        migrations,
+    })
+}
+
+pub struct VersionedStateStore {
+    inner: DynStateStore,
+    migrations: StateMigrations,
+}
+
+impl VersionedStateStore {
+    fn seal(&self, data: &Value) -> Value {
+        serde_json::to_value(StateEnvelope {
+            schema_version: self.migrations.current_version(),
+            data: data.clone(),
+        })
+        .unwrap_or(Value::Null)
+    }
+
+    /// Unwrap a stored document, migrating and writing it back if it is outdated.
+    fn upgrade(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        stored: Value,
+    ) -> GResult<Value> {
+        let envelope = StateEnvelope::open(stored);
+        if envelope.schema_version >= self.migrations.current_version() {
+            return Ok(envelope.data);
+        }
+        let data = self
+            .migrations
+            .migrate(envelope.schema_version, envelope.data)
+            .map_err(|err| GreenticError::new(ErrorCode::Internal, format!("{err:#}")))?;
+        if let Err(err) =
+            state::write_back(self.inner.as_ref(), tenant, prefix, key, &self.seal(&data))
+        {
+            tracing::warn!(
+                prefix,
+                from_version = envelope.schema_version,
+                error = %err,
+                "failed to write back migrated state document"
+            );
+        }
+        Ok(data)
+    }
+}
+
+impl StateStore for VersionedStateStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        reject_path(path)?;
+        self.inner
+            .get_json(tenant, prefix, key, None)?
+            .map(|stored| self.upgrade(tenant, prefix, key, stored))
+            .transpose()
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        reject_path(path)?;
+        self.inner
+            .set_json(tenant, prefix, key, None, &self.seal(value), ttl_secs)
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        self.inner.del(tenant, prefix, key)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        self.inner.del_prefix(tenant, prefix)
+    }
+}
+
+impl HostStateStore for VersionedStateStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        self.inner
+            .get_many(tenant, prefix, keys)?
+            .into_iter()
+            .zip(keys)
+            .map(|(stored, key)| {
+                stored
+                    .map(|stored| self.upgrade(tenant, prefix, key, stored))
+                    .transpose()
+            })
+            .collect()
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        let sealed = entries
+            .iter()
+            .map(|(key, value)| (key.clone(), self.seal(value)))
+            .collect::<Vec<_>>();
+        self.inner.set_many(tenant, prefix, &sealed, ttl_secs)
+    }
+}
+
+fn reject_path(path: Option<&StatePath>) -> GResult<()> {
+    match path {
+        None => Ok(()),
+        Some(_) => Err(GreenticError::new(
+            ErrorCode::InvalidInput,
+            "versioned state documents do not support JSON paths",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_exact_envelopes_are_unwrapped() {
+        let envelope = StateEnvelope::open(json!({"schema_version": 2, "data": {"a": 1}}));
+        assert_eq!(envelope.schema_version, 2);
+        assert_eq!(envelope.data, json!({"a": 1}));
+
+        let legacy = json!({"schema_version": 2, "data": 1, "extra": true});
+        assert_eq!(StateEnvelope::open(legacy.clone()).data, legacy);
+        assert_eq!(StateEnvelope::open(json!("text")).schema_version, 0);
+    }
+
+    #[test]
+    fn migrations_run_in_order_up_to_the_current_version() {
+        let migrations = StateMigrations::new()
+            .register(1, |_, mut data| {
+                data["steps"] = json!(format!("{}+1", data["steps"].as_str().unwrap()));
+                Ok(data)
+            })
+            .register(0, |_, data| Ok(json!({"steps": "0", "old": data})));
+        assert_eq!(migrations.current_version(), 2);
+        assert_eq!(
+            migrations.migrate(0, json!(7)).unwrap(),
+            json!({"steps": "0+1", "old": 7})
+        );
+        assert_eq!(migrations.migrate(2, json!(7)).unwrap(), json!(7));
+    }
+}
@@ -0,0 +1,122 @@
+//! Events from a component's `invoke-stream` export.
+//!
+//! [`PackRuntime::invoke_component_stream`](crate::pack::PackRuntime::invoke_component_stream)
+//! hands each event to a [`StreamObserver`] in the order the component produced it.
+//! Callers that use the plain invoke paths keep the buffered single-result behaviour.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::component_api::node;
+
+/// Channel size used by the streaming operator endpoint.
+pub const DEFAULT_STREAM_CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Partial output; JSON payloads are parsed, anything else is kept as a string.
+    Data(Value),
+    /// Percent complete as reported by the component.
+    Progress(u8),
+    Done,
+    Error(String),
+}
+
+impl From<node::StreamEvent> for StreamEvent {
+    fn from(event: node::StreamEvent) -> Self {
+        match event {
+            node::StreamEvent::Data(body) => {
+                Self::Data(serde_json::from_str(&body).unwrap_or_else(|_| Value::String(body)))
+            }
+            node::StreamEvent::Progress(percent) => Self::Progress(percent),
+            node::StreamEvent::Done => Self::Done,
+            node::StreamEvent::Error(message) => Self::Error(message),
+        }
+    }
+}
+
+/// A streaming invoke reached a component without an `invoke-stream` export, such as a
+/// provider-core or node@0.6 component.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("component `{component_ref}` has no node@0.5 or node@0.4 invoke-stream export")]
+pub struct StreamUnsupported {
+    pub component_ref: String,
+}
+
+/// Receives stream events on the Wasmtime thread running the component, so
+/// implementations must not block for long.
+pub trait StreamObserver: Send + Sync {
+    fn on_event(&self, event: &StreamEvent);
+}
+
+/// Forwards events into a bounded channel without ever blocking the component.
+///
+/// Drop policy: an event that finds the channel full, or the receiver gone, is
+/// discarded and counted in [`Self::dropped`]. Consumers that need the outcome must
+/// use the invoke's return value, which is never dropped.
+pub struct ChannelStreamObserver {
+    sender: mpsc::Sender<StreamEvent>,
+    dropped: AtomicU64,
+}
+
+impl ChannelStreamObserver {
+    pub fn channel(capacity: usize) -> (Arc<Self>, mpsc::Receiver<StreamEvent>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let observer = Arc::new(Self {
+            sender,
+            dropped: AtomicU64::new(0),
+        });
+        (observer, receiver)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl StreamObserver for ChannelStreamObserver {
+    fn on_event(&self, event: &StreamEvent) {
+        if self.sender.try_send(event.clone()).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_payloads_are_parsed_as_json_when_possible() {
+        assert_eq!(
+            StreamEvent::from(node::StreamEvent::Data(r#"{"step":1}"#.into())),
+            StreamEvent::Data(serde_json::json!({"step": 1}))
+        );
+        assert_eq!(
+            StreamEvent::from(node::StreamEvent::Data("plain".into())),
+            StreamEvent::Data(Value::String("plain".into()))
+        );
+    }
+
+    #[test]
+    fn full_channel_drops_and_counts_new_events() {
+        let (observer, mut receiver) = ChannelStreamObserver::channel(2);
+        for percent in [10, 20, 30] {
+            observer.on_event(&StreamEvent::Progress(percent));
+        }
+        observer.on_event(&StreamEvent::Done);
+        assert_eq!(observer.dropped(), 2);
+        assert_eq!(receiver.try_recv().ok(), Some(StreamEvent::Progress(10)));
+        assert_eq!(receiver.try_recv().ok(), Some(StreamEvent::Progress(20)));
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        observer.on_event(&StreamEvent::Done);
+        assert_eq!(observer.dropped(), 3);
+    }
+}
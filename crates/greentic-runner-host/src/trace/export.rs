@@ -0,0 +1,326 @@
+//! Where the host exports its spans: the `[trace]` table of the runner config file.
+//!
+//! The standard `OTEL_*` variables win over the file so that a container can repoint a
+//! deployment without editing it. Settings the exporter reads from the environment
+//! (headers, resource attributes, batching) are handed to it through [`otel_env`].
+//! Which traces are exported is the [`TraceSamplingConfig`] under `[trace.sampling]`.
+//!
+//! [`otel_env`]: TraceExportConfig::otel_env
+//! [`TraceSamplingConfig`]: super::sampling::TraceSamplingConfig
+
+use std::collections::BTreeMap;
+use std::env;
+
+use serde::Deserialize;
+
+use super::sampling::TraceSamplingConfig;
+
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Span exporter named by `exporter`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TraceExporter {
+    /// Spans stay in-process.
+    #[default]
+    None,
+    /// JSON lines on stdout.
+    Stdout,
+    OtlpGrpc,
+    OtlpHttp,
+}
+
+/// `[trace]` section of the runner config file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TraceExportConfig {
+    pub exporter: TraceExporter,
+    /// Collector URL; for `otlp-http` the base URL, `/v1/traces` is appended.
+    pub endpoint: Option<String>,
+    /// Sent with every export request, e.g. `authorization`.
+    pub headers: BTreeMap<String, String>,
+    /// `service.name` of the exported spans; `greentic-runner` when unset.
+    pub service_name: Option<String>,
+    pub service_namespace: Option<String>,
+    /// Extra resource attributes, e.g. `deployment.environment`.
+    pub resource_attributes: BTreeMap<String, String>,
+    pub batch: TraceBatchConfig,
+    pub sampling: TraceSamplingConfig,
+}
+
+/// How spans are batched before export.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TraceBatchConfig {
+    /// Spans kept while the collector is slow; newer ones are dropped.
+    pub max_queue_size: usize,
+    pub max_export_batch_size: usize,
+    pub scheduled_delay_ms: u64,
+    pub export_timeout_ms: u64,
+}
+
+impl Default for TraceBatchConfig {
+    fn default() -> Self {
+        Self {
+            max_queue_size: 2048,
+            max_export_batch_size: 512,
+            scheduled_delay_ms: 5000,
+            export_timeout_ms: 30_000,
+        }
+    }
+}
+
+impl TraceExportConfig {
+    /// `self` with the `OTEL_*` variables of the process applied on top.
+    pub fn with_env(self) -> Self {
+        self.with_env_from(|key| env::var(key).ok())
+    }
+
+    /// `self` with the `OTEL_*` variables `lookup` knows applied on top.
+    pub fn with_env_from(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let lookup = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+        if let Some(exporter) = lookup("OTEL_TRACES_EXPORTER") {
+            self.exporter = match exporter.trim() {
+                "none" => TraceExporter::None,
+                "console" | "stdout" => TraceExporter::Stdout,
+                _ => otlp_protocol(lookup("OTEL_EXPORTER_OTLP_PROTOCOL").as_deref()),
+            };
+        } else if let Some(protocol) = lookup("OTEL_EXPORTER_OTLP_PROTOCOL")
+            && matches!(
+                self.exporter,
+                TraceExporter::OtlpGrpc | TraceExporter::OtlpHttp
+            )
+        {
+            self.exporter = otlp_protocol(Some(&protocol));
+        }
+        if let Some(endpoint) = lookup("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+            .or_else(|| lookup("OTEL_EXPORTER_OTLP_ENDPOINT"))
+        {
+            self.endpoint = Some(endpoint);
+        }
+        if let Some(headers) = lookup("OTEL_EXPORTER_OTLP_HEADERS") {
+            self.headers.extend(parse_pairs(&headers));
+        }
+        if let Some(name) = lookup("OTEL_SERVICE_NAME") {
+            self.service_name = Some(name);
+        }
+        if let Some(attributes) = lookup("OTEL_RESOURCE_ATTRIBUTES") {
+            self.resource_attributes.extend(parse_pairs(&attributes));
+        }
+        if let Some(sampler) = lookup("OTEL_TRACES_SAMPLER") {
+            match sampler.trim().trim_start_matches("parentbased_") {
+                "always_on" => self.sampling.ratio = 1.0,
+                "always_off" => self.sampling.ratio = 0.0,
+                "traceidratio" => {
+                    if let Some(ratio) = lookup("OTEL_TRACES_SAMPLER_ARG")
+                        .and_then(|value| value.trim().parse::<f64>().ok())
+                    {
+                        self.sampling.ratio = ratio.clamp(0.0, 1.0);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let number = |key: &str| lookup(key).and_then(|value| value.trim().parse::<u64>().ok());
+        if let Some(size) = number("OTEL_BSP_MAX_QUEUE_SIZE") {
+            self.batch.max_queue_size = size as usize;
+        }
+        if let Some(size) = number("OTEL_BSP_MAX_EXPORT_BATCH_SIZE") {
+            self.batch.max_export_batch_size = size as usize;
+        }
+        if let Some(delay) = number("OTEL_BSP_SCHEDULE_DELAY") {
+            self.batch.scheduled_delay_ms = delay;
+        }
+        if let Some(timeout) = number("OTEL_BSP_EXPORT_TIMEOUT") {
+            self.batch.export_timeout_ms = timeout;
+        }
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.exporter != TraceExporter::None
+    }
+
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or("greentic-runner")
+    }
+
+    /// URL spans are sent to, when the exporter sends them anywhere.
+    pub fn traces_endpoint(&self) -> Option<String> {
+        let endpoint = self.endpoint.as_deref()?.trim_end_matches('/');
+        match self.exporter {
+            TraceExporter::OtlpHttp if !endpoint.ends_with(TRACES_PATH) => {
+                Some(format!("{endpoint}{TRACES_PATH}"))
+            }
+            TraceExporter::OtlpGrpc | TraceExporter::OtlpHttp => Some(endpoint.to_string()),
+            TraceExporter::None | TraceExporter::Stdout => None,
+        }
+    }
+
+    /// Resource attributes of the exported spans, `service.namespace` included.
+    pub fn resource(&self) -> BTreeMap<String, String> {
+        let mut resource = self.resource_attributes.clone();
+        if let Some(namespace) = &self.service_namespace {
+            resource.insert("service.namespace".into(), namespace.clone());
+        }
+        resource
+    }
+
+    /// `OTEL_*` variables carrying the settings the exporter reads from the environment.
+    pub fn otel_env(&self) -> Vec<(String, String)> {
+        let mut vars = vec![
+            // The host samples each trace at its root; the exporter follows that decision.
+            (
+                "OTEL_TRACES_SAMPLER".to_string(),
+                "parentbased_always_on".to_string(),
+            ),
+            (
+                "OTEL_BSP_MAX_QUEUE_SIZE".to_string(),
+                self.batch.max_queue_size.to_string(),
+            ),
+            (
+                "OTEL_BSP_MAX_EXPORT_BATCH_SIZE".to_string(),
+                self.batch.max_export_batch_size.to_string(),
+            ),
+            (
+                "OTEL_BSP_SCHEDULE_DELAY".to_string(),
+                self.batch.scheduled_delay_ms.to_string(),
+            ),
+            (
+                "OTEL_BSP_EXPORT_TIMEOUT".to_string(),
+                self.batch.export_timeout_ms.to_string(),
+            ),
+        ];
+        if !self.headers.is_empty() {
+            vars.push((
+                "OTEL_EXPORTER_OTLP_HEADERS".to_string(),
+                join_pairs(&self.headers),
+            ));
+        }
+        let resource = self.resource();
+        if !resource.is_empty() {
+            vars.push((
+                "OTEL_RESOURCE_ATTRIBUTES".to_string(),
+                join_pairs(&resource),
+            ));
+        }
+        vars
+    }
+}
+
+fn otlp_protocol(protocol: Option<&str>) -> TraceExporter {
+    match protocol.map(str::trim) {
+        Some(protocol) if protocol.starts_with("http") => TraceExporter::OtlpHttp,
+        _ => TraceExporter::OtlpGrpc,
+    }
+}
+
+/// `key=value` pairs separated by commas, as in `OTEL_RESOURCE_ATTRIBUTES`.
+fn parse_pairs(raw: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    raw.split(',').filter_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        let key = key.trim();
+        (!key.is_empty()).then(|| (key.to_string(), value.trim().to_string()))
+    })
+}
+
+fn join_pairs(pairs: &BTreeMap<String, String>) -> String {
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION: &str = r#"
+exporter = "otlp-http"
+endpoint = "https://collector.example.com:4318/"
+service_name = "runner-eu"
+service_namespace = "bots"
+
+[headers]
+authorization = "Bearer abc"
+
+[resource_attributes]
+"deployment.environment" = "prod"
+
+[batch]
+scheduled_delay_ms = 250
+
+[sampling]
+ratio = 0.1
+tenants = { acme = 1.0 }
+"#;
+
+    #[test]
+    fn trace_section_parses_with_defaults() {
+        let config: TraceExportConfig = toml::from_str(SECTION).unwrap();
+        assert_eq!(config.exporter, TraceExporter::OtlpHttp);
+        assert_eq!(
+            config.traces_endpoint().as_deref(),
+            Some("https://collector.example.com:4318/v1/traces")
+        );
+        assert_eq!(config.service_name(), "runner-eu");
+        assert_eq!(config.headers["authorization"], "Bearer abc");
+        assert_eq!(config.batch.scheduled_delay_ms, 250);
+        assert_eq!(config.batch.max_queue_size, 2048);
+        assert_eq!(config.sampling.ratio_for("globex"), 0.1);
+        assert_eq!(config.sampling.ratio_for("acme"), 1.0);
+        assert!(config.sampling.always_sample_errors);
+        assert_eq!(
+            config.resource(),
+            BTreeMap::from([
+                ("deployment.environment".to_string(), "prod".to_string()),
+                ("service.namespace".to_string(), "bots".to_string()),
+            ])
+        );
+
+        let empty: TraceExportConfig = toml::from_str("").unwrap();
+        assert!(!empty.is_enabled());
+        assert_eq!(empty.service_name(), "greentic-runner");
+    }
+
+    #[test]
+    fn otel_env_overrides_the_file() {
+        let config: TraceExportConfig = toml::from_str(SECTION).unwrap();
+        let env = BTreeMap::from([
+            ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://otel:4317"),
+            (
+                "OTEL_EXPORTER_OTLP_HEADERS",
+                "authorization=Bearer xyz,x-team=ops",
+            ),
+            ("OTEL_SERVICE_NAME", "runner-canary"),
+            ("OTEL_BSP_SCHEDULE_DELAY", "100"),
+            ("OTEL_TRACES_SAMPLER", "parentbased_traceidratio"),
+            ("OTEL_TRACES_SAMPLER_ARG", "0.5"),
+        ]);
+        let config = config.with_env_from(|key| env.get(key).map(|value| value.to_string()));
+        assert_eq!(config.exporter, TraceExporter::OtlpGrpc);
+        assert_eq!(
+            config.traces_endpoint().as_deref(),
+            Some("http://otel:4317")
+        );
+        assert_eq!(config.headers["authorization"], "Bearer xyz");
+        assert_eq!(config.headers["x-team"], "ops");
+        assert_eq!(config.service_name(), "runner-canary");
+        assert_eq!(config.batch.scheduled_delay_ms, 100);
+        assert_eq!(config.sampling.ratio_for("globex"), 0.5);
+        assert_eq!(config.sampling.ratio_for("acme"), 1.0);
+
+        let vars = config.otel_env();
+        assert!(vars.contains(&(
+            "OTEL_EXPORTER_OTLP_HEADERS".to_string(),
+            "authorization=Bearer xyz,x-team=ops".to_string()
+        )));
+        assert!(vars.contains(&("OTEL_BSP_SCHEDULE_DELAY".to_string(), "100".to_string())));
+
+        let disabled = TraceExportConfig::default()
+            .with_env_from(|key| (key == "OTEL_TRACES_EXPORTER").then(|| "none".to_string()));
+        assert!(!disabled.is_enabled());
+    }
+}
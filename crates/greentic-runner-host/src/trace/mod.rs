@@ -1,5 +1,13 @@
+mod export;
 mod model;
 mod recorder;
+pub mod run;
+pub mod sampling;
 
-pub use model::{TraceEnvelope, TraceError, TraceFlow, TraceHash, TracePack, TraceStep};
+pub use export::{TraceBatchConfig, TraceExportConfig, TraceExporter};
+pub use model::{
+    TraceEnvelope, TraceError, TraceFlow, TraceHash, TracePack, TraceRetry, TraceStep,
+};
 pub use recorder::{PackTraceInfo, TraceConfig, TraceContext, TraceMode, TraceRecorder};
+pub use run::{RunStatus, RunTrace, RunTraceConfig, RunTraceNode, RunTraceRecorder, RunTraceStore};
+pub use sampling::TraceSamplingConfig;
@@ -44,10 +44,20 @@ pub struct TraceStep {
     pub duration_ms: u64,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub validation_issues: Vec<ValidationIssue>,
+    /// Failed attempts that were retried before the step's outcome.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retries: Vec<TraceRetry>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<TraceError>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceRetry {
+    pub attempt: u32,
+    pub error: String,
+    pub delay_ms: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TraceError {
     pub code: String,
@@ -94,6 +104,7 @@ mod tests {
                 state_delta_hash: None,
                 duration_ms: 5,
                 validation_issues: Vec::new(),
+                retries: Vec::new(),
                 error: Some(TraceError {
                     code: "node_error".to_string(),
                     message: "boom".to_string(),
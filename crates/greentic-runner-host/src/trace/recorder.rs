@@ -2,7 +2,7 @@ use std::collections::VecDeque;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use parking_lot::Mutex;
@@ -11,7 +11,11 @@ use serde_json::Value;
 use crate::runner::engine::{ExecutionObserver, NodeEvent};
 use crate::validate::ValidationIssue;
 
-use super::model::{TraceEnvelope, TraceError, TraceFlow, TraceHash, TracePack, TraceStep};
+use super::export::TraceExportConfig;
+use super::model::{
+    TraceEnvelope, TraceError, TraceFlow, TraceHash, TracePack, TraceRetry, TraceStep,
+};
+use super::run::RunTraceConfig;
 
 const DEFAULT_TRACE_FILE: &str = "trace.json";
 const DEFAULT_BUFFER_SIZE: usize = 20;
@@ -23,6 +27,10 @@ pub struct TraceConfig {
     pub out_path: PathBuf,
     pub buffer_size: usize,
     pub capture_inputs: bool,
+    /// Per-run traces kept in the state store.
+    pub runs: RunTraceConfig,
+    /// Where spans are exported.
+    pub export: TraceExportConfig,
 }
 
 impl TraceConfig {
@@ -35,6 +43,8 @@ impl TraceConfig {
             out_path,
             buffer_size: DEFAULT_BUFFER_SIZE,
             capture_inputs: env::var("GREENTIC_TRACE_CAPTURE_INPUTS").ok().as_deref() == Some("1"),
+            runs: RunTraceConfig::default(),
+            export: TraceExportConfig::default().with_env(),
         }
     }
 
@@ -50,6 +60,17 @@ impl TraceConfig {
         self.capture_inputs = capture;
         self
     }
+
+    pub fn with_runs(mut self, runs: RunTraceConfig) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// Export spans as the `[trace]` section says, unless `OTEL_*` variables say otherwise.
+    pub fn with_export(mut self, export: TraceExportConfig) -> Self {
+        self.export = export.with_env();
+        self
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -92,6 +113,7 @@ struct InFlightStep {
     input_hash: TraceHash,
     started_at: Instant,
     validation_issues: Vec<ValidationIssue>,
+    retries: Vec<TraceRetry>,
     invocation_json: Option<Value>,
 }
 
@@ -151,6 +173,7 @@ impl TraceRecorder {
                     state_delta_hash: None,
                     duration_ms: in_flight.started_at.elapsed().as_millis() as u64,
                     validation_issues: in_flight.validation_issues,
+                    retries: in_flight.retries,
                     error: Some(TraceError {
                         code: "node_error".to_string(),
                         message: err.to_string(),
@@ -169,6 +192,7 @@ impl TraceRecorder {
                     state_delta_hash: None,
                     duration_ms: 0,
                     validation_issues: Vec::new(),
+                    retries: Vec::new(),
                     error: Some(TraceError {
                         code: "flow_error".to_string(),
                         message: err.to_string(),
@@ -228,6 +252,7 @@ impl ExecutionObserver for TraceRecorder {
             input_hash,
             started_at: Instant::now(),
             validation_issues: Vec::new(),
+            retries: Vec::new(),
             invocation_json: if self.config.capture_inputs {
                 Some(build_invocation(event, &component_id))
             } else {
@@ -254,6 +279,7 @@ impl ExecutionObserver for TraceRecorder {
                 state_delta_hash: None,
                 duration_ms: in_flight.started_at.elapsed().as_millis() as u64,
                 validation_issues: in_flight.validation_issues,
+                retries: in_flight.retries,
                 error: None,
             }
         } else {
@@ -272,6 +298,7 @@ impl ExecutionObserver for TraceRecorder {
                 state_delta_hash: None,
                 duration_ms: 0,
                 validation_issues: Vec::new(),
+                retries: Vec::new(),
                 error: None,
             }
         };
@@ -298,6 +325,7 @@ impl ExecutionObserver for TraceRecorder {
                 state_delta_hash: None,
                 duration_ms: in_flight.started_at.elapsed().as_millis() as u64,
                 validation_issues: in_flight.validation_issues,
+                retries: in_flight.retries,
                 error: Some(TraceError {
                     code: "node_error".to_string(),
                     message: error.to_string(),
@@ -320,6 +348,7 @@ impl ExecutionObserver for TraceRecorder {
                 state_delta_hash: None,
                 duration_ms: 0,
                 validation_issues: Vec::new(),
+                retries: Vec::new(),
                 error: Some(TraceError {
                     code: "node_error".to_string(),
                     message: error.to_string(),
@@ -337,6 +366,26 @@ impl ExecutionObserver for TraceRecorder {
         }
     }
 
+    fn on_node_retry(
+        &self,
+        _event: &NodeEvent<'_>,
+        attempt: u32,
+        error: &dyn std::error::Error,
+        delay: Duration,
+    ) {
+        if self.config.mode == TraceMode::Off {
+            return;
+        }
+        let mut state = self.state.lock();
+        if let Some(in_flight) = state.in_flight.as_mut() {
+            in_flight.retries.push(TraceRetry {
+                attempt,
+                error: error.to_string(),
+                delay_ms: delay.as_millis() as u64,
+            });
+        }
+    }
+
     fn on_validation(&self, _event: &NodeEvent<'_>, issues: &[ValidationIssue]) {
         if self.config.mode == TraceMode::Off || issues.is_empty() {
             return;
@@ -0,0 +1,366 @@
+//! Execution traces of single flow runs, kept in the state store for debugging.
+//!
+//! A run is traced when its tenant sets `run_traces.enabled`, or when its caller runs it
+//! inside [`scope`] (the flow API does for `?trace=true`). [`RunTraceRecorder`] notes,
+//! for each node, when it started and ended, its rendered config and input, its output
+//! and its error; [`RunTraceStore`] keeps the resulting [`RunTrace`] under the run id
+//! until `run_traces.ttl_secs` passes.
+//!
+//! Every string is scrubbed by the run's [`Redactor`] before it is kept: values under
+//! the input's `_attachments`, secrets the run resolves and the tenant's
+//! `redaction.patterns`. A JSON value longer than `max_node_bytes` is kept as a preview,
+//! and nodes that would grow the trace past `max_trace_bytes` are only counted in
+//! `dropped_nodes`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use greentic_types::TenantCtx;
+use parking_lot::Mutex;
+use rand::{RngExt, rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::engine::error::{GResult, RunnerError};
+use crate::engine::host::{SessionKey, StateHost};
+use crate::redact::Redactor;
+use crate::runner::engine::{ExecutionObserver, NodeEvent};
+
+const RUN_TRACE_PACK: &str = "_runs";
+const RUN_TRACE_FLOW: &str = "trace";
+
+tokio::task_local! {
+    static CURRENT: Arc<RunTraceRequest>;
+}
+
+/// `run_traces` bindings block.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct RunTraceConfig {
+    /// Trace every run of the tenant, not only those that ask for it.
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long a stored trace can be read.
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Longest JSON value kept whole, in bytes.
+    #[serde(default = "default_max_node_bytes")]
+    pub max_node_bytes: usize,
+    /// Largest trace document, in bytes of its nodes.
+    #[serde(default = "default_max_trace_bytes")]
+    pub max_trace_bytes: usize,
+}
+
+impl Default for RunTraceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_ttl_secs(),
+            max_node_bytes: default_max_node_bytes(),
+            max_trace_bytes: default_max_trace_bytes(),
+        }
+    }
+}
+
+impl RunTraceConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs.max(1))
+    }
+}
+
+fn default_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_max_node_bytes() -> usize {
+    4 * 1024
+}
+
+fn default_max_trace_bytes() -> usize {
+    256 * 1024
+}
+
+/// A run asked to be traced; says whether its trace was stored.
+#[derive(Debug)]
+pub struct RunTraceRequest {
+    run_id: String,
+    recorded: AtomicBool,
+}
+
+impl RunTraceRequest {
+    /// A request under a fresh run id.
+    pub fn generate() -> Arc<Self> {
+        let value: u128 = rng().random();
+        Arc::new(Self {
+            run_id: format!("run-{value:032x}"),
+            recorded: AtomicBool::new(false),
+        })
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Whether a flow run stored its trace under [`Self::run_id`].
+    pub fn recorded(&self) -> bool {
+        self.recorded.load(Ordering::SeqCst)
+    }
+
+    pub fn mark_recorded(&self) {
+        self.recorded.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Trace the flow runs of `future` under `request`.
+pub async fn scope<F: Future>(request: Arc<RunTraceRequest>, future: F) -> F::Output {
+    CURRENT.scope(request, future).await
+}
+
+/// Request of the enclosing [`scope`], if any.
+pub fn current() -> Option<Arc<RunTraceRequest>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Completed,
+    Waiting,
+    Failed,
+}
+
+/// The stored trace of one flow run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub run_id: String,
+    pub tenant: String,
+    pub pack_id: String,
+    pub flow_id: String,
+    pub status: RunStatus,
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    /// The flow input.
+    pub input: Value,
+    /// Nodes in the order they finished.
+    pub nodes: Vec<RunTraceNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Nodes left out to keep the trace under `max_trace_bytes`.
+    #[serde(default)]
+    pub dropped_nodes: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunTraceNode {
+    pub node_id: String,
+    pub component: String,
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    /// The `config` of the rendered node payload.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub config: Value,
+    /// The rendered node payload without its `config`.
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub output: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Collects the [`RunTrace`] of one run as an [`ExecutionObserver`].
+pub struct RunTraceRecorder {
+    config: RunTraceConfig,
+    redactor: Redactor,
+    state: Mutex<RecorderState>,
+}
+
+struct RecorderState {
+    trace: RunTrace,
+    bytes: usize,
+    /// Nodes that started and have not finished, with their start time, config and input.
+    started: HashMap<String, (u64, Value, Value)>,
+}
+
+impl RunTraceRecorder {
+    pub fn new(
+        request: &RunTraceRequest,
+        tenant: &str,
+        pack_id: &str,
+        flow_id: &str,
+        input: &Value,
+        config: RunTraceConfig,
+        redactor: Redactor,
+    ) -> Self {
+        if let Some(Value::Object(attachments)) = input.get("_attachments") {
+            for (alias, value) in attachments {
+                if let Value::String(value) = value {
+                    redactor.register(alias, value);
+                }
+            }
+        }
+        let recorder = Self {
+            config,
+            redactor,
+            state: Mutex::new(RecorderState {
+                trace: RunTrace {
+                    run_id: request.run_id().to_string(),
+                    tenant: tenant.to_string(),
+                    pack_id: pack_id.to_string(),
+                    flow_id: flow_id.to_string(),
+                    status: RunStatus::Completed,
+                    started_at_ms: unix_millis(),
+                    ended_at_ms: 0,
+                    input: Value::Null,
+                    nodes: Vec::new(),
+                    error: None,
+                    dropped_nodes: 0,
+                },
+                bytes: 0,
+                started: HashMap::new(),
+            }),
+        };
+        recorder.state.lock().trace.input = recorder.snippet(input);
+        recorder
+    }
+
+    /// Redactor the run's resolved secrets should be registered with.
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// The trace of the run, which ended with `status` (and `error` when it failed).
+    pub fn finish(&self, status: RunStatus, error: Option<&str>) -> RunTrace {
+        let mut state = self.state.lock();
+        state.trace.status = status;
+        state.trace.ended_at_ms = unix_millis();
+        state.trace.error = error.map(|error| self.redactor.scrub(error).into_owned());
+        state.trace.clone()
+    }
+
+    /// `value` scrubbed, or a preview of it when it is longer than `max_node_bytes`.
+    fn snippet(&self, value: &Value) -> Value {
+        let mut value = value.clone();
+        self.redactor.scrub_json(&mut value);
+        let text = value.to_string();
+        if text.len() <= self.config.max_node_bytes {
+            return value;
+        }
+        let mut end = self.config.max_node_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        json!({
+            "truncated": true,
+            "bytes": text.len(),
+            "preview": &text[..end],
+        })
+    }
+
+    fn end_node(&self, event: &NodeEvent<'_>, output: Value, error: Option<String>) {
+        let mut state = self.state.lock();
+        let (started_at_ms, config, input) = state
+            .started
+            .remove(event.node_id)
+            .unwrap_or_else(|| (unix_millis(), Value::Null, Value::Null));
+        let node = RunTraceNode {
+            node_id: event.node_id.to_string(),
+            component: event.node.component.clone(),
+            started_at_ms,
+            ended_at_ms: unix_millis(),
+            config,
+            input,
+            output,
+            error,
+        };
+        let bytes = serde_json::to_vec(&node)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0);
+        if state.bytes + bytes > self.config.max_trace_bytes {
+            state.trace.dropped_nodes += 1;
+            return;
+        }
+        state.bytes += bytes;
+        state.trace.nodes.push(node);
+    }
+}
+
+impl ExecutionObserver for RunTraceRecorder {
+    fn on_node_start(&self, event: &NodeEvent<'_>) {
+        let (config, input) = match event.payload {
+            Value::Object(map) if map.contains_key("config") => {
+                let mut input = map.clone();
+                let config = input.remove("config").unwrap_or_default();
+                (self.snippet(&config), self.snippet(&Value::Object(input)))
+            }
+            payload => (Value::Null, self.snippet(payload)),
+        };
+        self.state
+            .lock()
+            .started
+            .insert(event.node_id.to_string(), (unix_millis(), config, input));
+    }
+
+    fn on_node_end(&self, event: &NodeEvent<'_>, output: &Value) {
+        self.end_node(event, self.snippet(output), None);
+    }
+
+    fn on_node_error(&self, event: &NodeEvent<'_>, error: &dyn std::error::Error) {
+        let error = self.redactor.scrub(&error.to_string()).into_owned();
+        self.end_node(event, Value::Null, Some(error));
+    }
+}
+
+/// Run traces of one tenant in its state store.
+#[derive(Clone)]
+pub struct RunTraceStore {
+    state: Arc<dyn StateHost>,
+    ttl: Duration,
+}
+
+impl RunTraceStore {
+    pub fn new(state: Arc<dyn StateHost>, config: &RunTraceConfig) -> Self {
+        Self {
+            state,
+            ttl: config.ttl(),
+        }
+    }
+
+    pub async fn save(&self, tenant: &TenantCtx, trace: &RunTrace) -> GResult<()> {
+        let value = serde_json::to_value(trace).map_err(|err| RunnerError::Serialization {
+            reason: format!("failed to serialise run trace: {err}"),
+        })?;
+        self.state
+            .set_json_ttl(&trace_key(tenant, &trace.run_id), value, self.ttl)
+            .await
+    }
+
+    /// The trace stored under `run_id`, unless it expired or was never recorded.
+    pub async fn load(&self, tenant: &TenantCtx, run_id: &str) -> GResult<Option<RunTrace>> {
+        let Some(value) = self.state.get_json(&trace_key(tenant, run_id)).await? else {
+            return Ok(None);
+        };
+        serde_json::from_value(value)
+            .map(Some)
+            .map_err(|err| RunnerError::Serialization {
+                reason: format!("invalid run trace {run_id}: {err}"),
+            })
+    }
+}
+
+fn trace_key(tenant: &TenantCtx, run_id: &str) -> SessionKey {
+    SessionKey::new(
+        tenant,
+        RUN_TRACE_PACK,
+        RUN_TRACE_FLOW,
+        Some(run_id.to_string()),
+    )
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,285 @@
+//! Which traces the host exports: the `[trace.sampling]` table of the runner config file.
+//!
+//! Operator invokes and flow runs decide when their root span starts: a caller that
+//! sends a `traceparent` keeps its sampled flag, other traces are kept for `ratio` of
+//! their trace ids, or the tenant's ratio under `tenants`. The decision is handed to the
+//! exporter as the remote parent of the root span, so the parent-based exporter keeps or
+//! drops the whole trace. A dropped trace whose root fails is still reported as a
+//! `trace.error` span when `always_sample_errors` is set.
+//!
+//! The settings are process-wide and [`configure`]d on start and on every config reload.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use once_cell::sync::Lazy;
+use rand::{RngExt, rng};
+use serde::Deserialize;
+use tracing::Span;
+
+static CONFIG: Lazy<ArcSwap<TraceSamplingConfig>> =
+    Lazy::new(|| ArcSwap::from_pointee(TraceSamplingConfig::default()));
+
+/// `[trace.sampling]` section of the runner config file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TraceSamplingConfig {
+    /// Share of traces exported, from 0.0 to 1.0.
+    pub ratio: f64,
+    /// Ratio of a tenant's traces, instead of `ratio`.
+    pub tenants: BTreeMap<String, f64>,
+    /// Report failed traces that were not sampled.
+    pub always_sample_errors: bool,
+}
+
+impl Default for TraceSamplingConfig {
+    fn default() -> Self {
+        Self {
+            ratio: 1.0,
+            tenants: BTreeMap::new(),
+            always_sample_errors: true,
+        }
+    }
+}
+
+impl TraceSamplingConfig {
+    pub fn ratio_for(&self, tenant: &str) -> f64 {
+        self.tenants.get(tenant).copied().unwrap_or(self.ratio)
+    }
+
+    /// Whether the trace `trace_id` of `tenant` is kept; an id always gets the same answer.
+    pub fn samples(&self, tenant: &str, trace_id: u128) -> bool {
+        let ratio = self.ratio_for(tenant);
+        if ratio >= 1.0 {
+            return true;
+        }
+        if ratio.is_nan() || ratio <= 0.0 {
+            return false;
+        }
+        // The low half of the id against the ratio, as OpenTelemetry's `TraceIdRatioBased`.
+        let bound = (ratio * (1_u64 << 63) as f64) as u64;
+        ((trace_id as u64) >> 1) < bound
+    }
+}
+
+/// Sample the traces started from now on as `config` says.
+pub fn configure(config: TraceSamplingConfig) {
+    CONFIG.store(Arc::new(config));
+}
+
+pub fn current() -> Arc<TraceSamplingConfig> {
+    CONFIG.load_full()
+}
+
+/// Sampling decision of the trace rooted at a span passed to [`start_root`].
+#[derive(Debug)]
+#[must_use = "call `finish` so failed traces that were dropped are reported"]
+pub struct RootSampling {
+    root: &'static str,
+    tenant: String,
+    sampled: bool,
+    always_sample_errors: bool,
+}
+
+/// Decide whether the trace rooted at `span` is exported. `traceparent` is the trace
+/// context of the caller, a W3C `traceparent` or a bare trace id.
+pub fn start_root(span: &Span, tenant: &str, traceparent: Option<&str>) -> RootSampling {
+    let config = current();
+    let (trace_id, parent_sampled) = traceparent
+        .and_then(parse_traceparent)
+        .unwrap_or_else(|| (random_trace_id(), None));
+    let sampled = parent_sampled.unwrap_or_else(|| config.samples(tenant, trace_id));
+    attach(span, trace_id, sampled);
+    RootSampling {
+        root: span.metadata().map(|meta| meta.name()).unwrap_or("unknown"),
+        tenant: tenant.to_string(),
+        sampled,
+        always_sample_errors: config.always_sample_errors,
+    }
+}
+
+impl RootSampling {
+    pub fn sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// The trace ended; `error` is why its root failed.
+    pub fn finish(self, error: Option<&str>) {
+        let Some(error) = error else {
+            return;
+        };
+        if self.sampled || !self.always_sample_errors {
+            return;
+        }
+        let span = tracing::error_span!(
+            "trace.error",
+            tenant = %self.tenant,
+            root = self.root,
+            error
+        );
+        attach(&span, random_trace_id(), true);
+        drop(span.enter());
+    }
+}
+
+/// Trace id and sampled flag of `00-<trace id>-<span id>-<flags>`, or the id alone.
+fn parse_traceparent(value: &str) -> Option<(u128, Option<bool>)> {
+    let value = value.trim();
+    let (trace_id, sampled) = match value.split('-').collect::<Vec<_>>().as_slice() {
+        [_version, trace_id, span_id, flags] if span_id.len() == 16 && flags.len() == 2 => {
+            let flags = u8::from_str_radix(flags, 16).ok()?;
+            (*trace_id, Some(flags & 1 == 1))
+        }
+        [trace_id] => (*trace_id, None),
+        _ => return None,
+    };
+    if trace_id.len() != 32 {
+        return None;
+    }
+    let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+    (trace_id != 0).then_some((trace_id, sampled))
+}
+
+fn random_trace_id() -> u128 {
+    rng().random::<u128>().max(1)
+}
+
+/// Make the decision the remote parent of `span`, which the exporter's parent-based
+/// sampler follows.
+#[cfg(feature = "telemetry")]
+fn attach(span: &Span, trace_id: u128, sampled: bool) {
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let flags = if sampled {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+    let span_id = rng().random::<u64>().max(1);
+    let parent = SpanContext::new(
+        TraceId::from_bytes(trace_id.to_be_bytes()),
+        SpanId::from_bytes(span_id.to_be_bytes()),
+        flags,
+        true,
+        TraceState::default(),
+    );
+    let _ = span.set_parent(opentelemetry::Context::new().with_remote_span_context(parent));
+}
+
+#[cfg(not(feature = "telemetry"))]
+fn attach(_span: &Span, _trace_id: u128, _sampled: bool) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION: &str = r#"
+ratio = 0.25
+
+[tenants]
+vip = 1.0
+muted = 0.0
+"#;
+
+    #[test]
+    fn tenant_overrides_replace_the_global_ratio() {
+        let config: TraceSamplingConfig = toml::from_str(SECTION).unwrap();
+        assert!(config.always_sample_errors);
+        let kept = |tenant: &str| {
+            (0..10_000)
+                .filter(|_| config.samples(tenant, random_trace_id()))
+                .count()
+        };
+        let acme = kept("acme");
+        assert!((2_000..3_000).contains(&acme), "kept {acme} of 10000");
+        assert_eq!(kept("vip"), 10_000);
+        assert_eq!(kept("muted"), 0);
+    }
+
+    #[test]
+    fn traceparent_sampled_flag_wins() {
+        let id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        assert_eq!(
+            parse_traceparent(&format!("00-{id}-00f067aa0ba902b7-01")),
+            Some((0x4bf92f3577b34da6a3ce929d0e0e4736, Some(true)))
+        );
+        assert_eq!(
+            parse_traceparent(&format!("00-{id}-00f067aa0ba902b7-00")),
+            Some((0x4bf92f3577b34da6a3ce929d0e0e4736, Some(false)))
+        );
+        assert_eq!(
+            parse_traceparent(id),
+            Some((0x4bf92f3577b34da6a3ce929d0e0e4736, None))
+        );
+        assert_eq!(parse_traceparent("req-42"), None);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn recording_exporter_sees_the_configured_share() {
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_sdk::trace::{InMemorySpanExporter, Sampler, SdkTracerProvider};
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::AlwaysOn)))
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("sampling-test")));
+        configure(toml::from_str(SECTION).unwrap());
+
+        const RUNS: usize = 2_000;
+        let failed = |run: usize| run % 10 == 0;
+        tracing::subscriber::with_default(subscriber, || {
+            for tenant in ["acme", "vip"] {
+                for run in 0..RUNS {
+                    let span = tracing::info_span!("test.root", tenant, run);
+                    let sampling = start_root(&span, tenant, None);
+                    drop(span.enter());
+                    drop(span);
+                    let error = format!("{tenant} run {run} failed");
+                    sampling.finish(failed(run).then_some(error.as_str()));
+                }
+            }
+        });
+        configure(TraceSamplingConfig::default());
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let attribute = |span: &opentelemetry_sdk::trace::SpanData, key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.to_string())
+        };
+        let roots = |tenant: &str| {
+            spans
+                .iter()
+                .filter(|span| span.name == "test.root")
+                .filter(|span| attribute(span, "tenant").as_deref() == Some(tenant))
+                .filter_map(|span| attribute(span, "run")?.parse::<usize>().ok())
+                .collect::<Vec<_>>()
+        };
+        let acme = roots("acme");
+        assert!((400..600).contains(&acme.len()), "exported {}", acme.len());
+        assert_eq!(roots("vip").len(), RUNS);
+
+        let reported = spans
+            .iter()
+            .filter(|span| span.name == "trace.error")
+            .filter_map(|span| attribute(span, "error"))
+            .collect::<Vec<_>>();
+        for run in (0..RUNS).filter(|run| failed(*run)) {
+            let error = format!("acme run {run} failed");
+            assert!(
+                acme.contains(&run) || reported.contains(&error),
+                "failed run {run} missing from the export"
+            );
+        }
+    }
+}
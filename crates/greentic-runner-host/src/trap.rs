@@ -0,0 +1,164 @@
+//! Structured details for component traps.
+//!
+//! Wasmtime reports a trap as an error whose chain carries the [`Trap`] code and,
+//! when backtraces are enabled (the default), a [`WasmBacktrace`]. [`explain_trap`]
+//! folds both into a [`TrapDetails`] context together with the export being called,
+//! so the operator can report them as diagnostics instead of a flattened message.
+
+use std::fmt;
+
+use anyhow::Error;
+use serde::Serialize;
+use wasmtime::{Trap, WasmBacktrace};
+
+/// Frames kept per trap; deeper frames are counted in `truncated_frames`.
+pub const MAX_TRAP_FRAMES: usize = 32;
+/// Frames shown in the one-line summary used for diagnostic hints.
+pub const SUMMARY_FRAMES: usize = 3;
+
+/// One wasm frame, innermost first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrapFrame {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    pub func_index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub func_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_offset: Option<usize>,
+}
+
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(module) = &self.module {
+            write!(f, "{module}!")?;
+        }
+        match &self.func_name {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "<func {}>", self.func_index)?,
+        }
+        if let Some(offset) = self.module_offset {
+            write!(f, "@{offset:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[error("component trapped in `{export}`: {message}")]
+pub struct TrapDetails {
+    /// Export that was running, e.g. `invoke` or `on-start`.
+    pub export: String,
+    /// Wasmtime trap code, e.g. `UnreachableCodeReached`.
+    pub code: String,
+    pub message: String,
+    pub frames: Vec<TrapFrame>,
+    /// Frames dropped past [`MAX_TRAP_FRAMES`].
+    #[serde(skip_serializing_if = "is_zero")]
+    pub truncated_frames: usize,
+}
+
+impl TrapDetails {
+    /// Details for `err` when it is a wasm trap; `None` for any other failure.
+    pub fn from_error(err: &Error, export: &str) -> Option<Self> {
+        let trap = err.downcast_ref::<Trap>()?;
+        let frames = err
+            .downcast_ref::<WasmBacktrace>()
+            .map(|backtrace| {
+                backtrace
+                    .frames()
+                    .iter()
+                    .map(|frame| TrapFrame {
+                        module: frame.module().name().map(ToString::to_string),
+                        func_index: frame.func_index(),
+                        func_name: frame.func_name().map(ToString::to_string),
+                        module_offset: frame.module_offset(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        Some(Self::new(export, trap, frames))
+    }
+
+    fn new(export: &str, trap: &Trap, mut frames: Vec<TrapFrame>) -> Self {
+        let truncated_frames = frames.len().saturating_sub(MAX_TRAP_FRAMES);
+        frames.truncate(MAX_TRAP_FRAMES);
+        Self {
+            export: export.to_string(),
+            code: format!("{trap:?}"),
+            message: trap.to_string(),
+            frames,
+            truncated_frames,
+        }
+    }
+
+    /// The innermost [`SUMMARY_FRAMES`] frames on one line, or `None` without a
+    /// backtrace.
+    pub fn summary(&self) -> Option<String> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let top = self
+            .frames
+            .iter()
+            .take(SUMMARY_FRAMES)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" <- ");
+        let more = self.frames.len() + self.truncated_frames;
+        if more > SUMMARY_FRAMES {
+            Some(format!("{top} (+{} more)", more - SUMMARY_FRAMES))
+        } else {
+            Some(top)
+        }
+    }
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Attach [`TrapDetails`] to an error raised while calling `export`, if it is a trap.
+pub fn explain_trap(err: Error, export: &str) -> Error {
+    match TrapDetails::from_error(&err, export) {
+        Some(details) => err.context(details),
+        None => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(index: u32, name: Option<&str>) -> TrapFrame {
+        TrapFrame {
+            module: None,
+            func_index: index,
+            func_name: name.map(ToString::to_string),
+            module_offset: Some(0x10 + index as usize),
+        }
+    }
+
+    #[test]
+    fn frames_are_truncated_and_summarised() {
+        let frames = (0..40).map(|index| frame(index, None)).collect::<Vec<_>>();
+        let details = TrapDetails::new("invoke", &Trap::UnreachableCodeReached, frames);
+        assert_eq!(details.code, "UnreachableCodeReached");
+        assert_eq!(details.frames.len(), MAX_TRAP_FRAMES);
+        assert_eq!(details.truncated_frames, 8);
+        assert_eq!(
+            details.summary().as_deref(),
+            Some("<func 0>@0x10 <- <func 1>@0x11 <- <func 2>@0x12 (+37 more)")
+        );
+    }
+
+    #[test]
+    fn non_trap_errors_have_no_details() {
+        assert!(TrapDetails::from_error(&anyhow::anyhow!("boom"), "invoke").is_none());
+        let trap = explain_trap(Error::new(Trap::StackOverflow), "on-start");
+        let details = trap.downcast_ref::<TrapDetails>().expect("trap details");
+        assert_eq!(details.export, "on-start");
+        assert_eq!(details.summary(), None);
+        assert_eq!(frame(3, Some("run")).to_string(), "run@0x13");
+    }
+}
@@ -1,18 +1,36 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
 use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder};
 
+use crate::deterministic::DeterministicConfig;
+use crate::stdio::StdioCapture;
+
+/// Directory under `<tenant_preopen_dir>/<tenant>` a tenant's preopens must live in.
+const TENANT_FS_DIR: &str = "fs";
+
 /// Specification for exposing a host directory to the guest.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct PreopenSpec {
     pub host_path: PathBuf,
     pub guest_path: String,
+    #[serde(default)]
     pub read_only: bool,
 }
 
+/// `wasi` bindings block: filesystem access granted to one tenant's components, and
+/// whether they run with deterministic clocks and randomness.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TenantWasiConfig {
+    #[serde(default)]
+    pub preopens: Vec<PreopenSpec>,
+    #[serde(default)]
+    pub deterministic: Option<DeterministicConfig>,
+}
+
 impl PreopenSpec {
     pub fn new(host_path: impl Into<PathBuf>, guest_path: impl Into<String>) -> Self {
         Self {
@@ -43,10 +61,21 @@ impl PreopenSpec {
 /// Policy describing which WASI capabilities are surfaced into packs.
 #[derive(Clone, Debug)]
 pub struct RunnerWasiPolicy {
+    /// Inherit the host's stdin; stdout/stderr are always captured per invocation (see
+    /// [`crate::stdio`]).
     pub inherit_stdio: bool,
     pub env_allow: Vec<String>,
     pub env_set: HashMap<String, String>,
     pub preopens: Vec<PreopenSpec>,
+    /// Host directories any tenant's `wasi.preopens` may live under.
+    pub tenant_preopen_roots: Vec<PathBuf>,
+    /// Directories holding a `<tenant>/fs` directory per tenant; a tenant's
+    /// `wasi.preopens` may live under its own. Tenants cannot preopen anything while
+    /// both lists are empty.
+    pub tenant_preopen_dirs: Vec<PathBuf>,
+    /// Fixed clocks and seeded randomness instead of the host's; see
+    /// [`crate::deterministic`].
+    pub deterministic: Option<DeterministicConfig>,
 }
 
 impl Default for RunnerWasiPolicy {
@@ -56,6 +85,9 @@ impl Default for RunnerWasiPolicy {
             env_allow: Vec::new(),
             env_set: HashMap::new(),
             preopens: Vec::new(),
+            tenant_preopen_roots: Vec::new(),
+            tenant_preopen_dirs: Vec::new(),
+            deterministic: None,
         }
     }
 }
@@ -85,10 +117,101 @@ impl RunnerWasiPolicy {
         self
     }
 
-    pub(crate) fn instantiate(&self) -> Result<WasiCtx> {
+    pub fn with_deterministic(mut self, config: DeterministicConfig) -> Self {
+        self.deterministic = Some(config);
+        self
+    }
+
+    pub fn allow_tenant_preopen_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.tenant_preopen_roots.push(root.into());
+        self
+    }
+
+    /// Let each tenant preopen directories under `base/<tenant>/fs`.
+    pub fn allow_tenant_preopen_dirs(mut self, base: impl Into<PathBuf>) -> Self {
+        self.tenant_preopen_dirs.push(base.into());
+        self
+    }
+
+    /// Add a tenant's preopens after checking each host path is an existing directory
+    /// inside one of [`Self::tenant_preopen_roots`] or the tenant's own directory under
+    /// [`Self::tenant_preopen_dirs`], and each guest path is absolute and not already
+    /// preopened. Host paths are stored canonicalized, so symlinks swapped in later
+    /// cannot redirect the mapping.
+    pub fn with_tenant_preopens(mut self, tenant: &str, specs: &[PreopenSpec]) -> Result<Self> {
+        if specs.is_empty() {
+            return Ok(self);
+        }
+        // A tenant id that is not a single plain path segment gets no directory.
+        let mut segments = Path::new(tenant).components();
+        let own_dirs = match (segments.next(), segments.next()) {
+            (Some(Component::Normal(_)), None) => self
+                .tenant_preopen_dirs
+                .iter()
+                .map(|base| base.join(tenant).join(TENANT_FS_DIR))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let roots = self
+            .tenant_preopen_roots
+            .iter()
+            .chain(&own_dirs)
+            .filter_map(|root| root.canonicalize().ok())
+            .collect::<Vec<_>>();
+        for spec in specs {
+            let spec = self.check_tenant_preopen(spec, &roots).with_context(|| {
+                format!(
+                    "tenant {tenant}: invalid wasi preopen {} -> {}",
+                    spec.host_path.display(),
+                    spec.guest_path
+                )
+            })?;
+            self.preopens.push(spec);
+        }
+        Ok(self)
+    }
+
+    fn check_tenant_preopen(&self, spec: &PreopenSpec, roots: &[PathBuf]) -> Result<PreopenSpec> {
+        let guest = Path::new(&spec.guest_path);
+        if !guest.is_absolute()
+            || guest
+                .components()
+                .any(|part| matches!(part, Component::ParentDir | Component::CurDir))
+        {
+            bail!("guest path must be absolute without `.` or `..` segments");
+        }
+        if self
+            .preopens
+            .iter()
+            .any(|existing| Path::new(&existing.guest_path) == guest)
+        {
+            bail!("guest path is already preopened");
+        }
+        spec.validate()?;
+        let host_path = spec
+            .host_path
+            .canonicalize()
+            .context("failed to resolve host path")?;
+        if !roots.iter().any(|root| host_path.starts_with(root)) {
+            bail!("host path is outside the allowed preopen roots");
+        }
+        Ok(PreopenSpec {
+            host_path,
+            guest_path: spec.guest_path.clone(),
+            read_only: spec.read_only,
+        })
+    }
+
+    /// WASI context whose stdout/stderr go to `stdio`; only stdin follows
+    /// `inherit_stdio`.
+    pub(crate) fn instantiate(&self, stdio: &StdioCapture) -> Result<WasiCtx> {
         let mut builder = WasiCtxBuilder::new();
         if self.inherit_stdio {
-            builder.inherit_stdio();
+            builder.inherit_stdin();
+        }
+        builder.stdout(stdio.stdout()).stderr(stdio.stderr());
+        if let Some(deterministic) = &self.deterministic {
+            deterministic.install(&mut builder);
         }
         let env_pairs = self.collect_env();
         if !env_pairs.is_empty() {
@@ -140,3 +263,111 @@ impl RunnerWasiPolicy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn policy_with_root(root: &Path) -> RunnerWasiPolicy {
+        RunnerWasiPolicy::new().allow_tenant_preopen_root(root)
+    }
+
+    #[test]
+    fn tenant_preopens_must_stay_inside_roots() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        let scratch = root.join("scratch");
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(&scratch).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+
+        let policy = policy_with_root(&root)
+            .with_tenant_preopens("acme", &[PreopenSpec::new(&scratch, "/scratch")])
+            .unwrap();
+        assert_eq!(
+            policy.preopens[0].host_path,
+            scratch.canonicalize().unwrap()
+        );
+
+        for host in [outside.clone(), root.join("../outside")] {
+            let err = policy_with_root(&root)
+                .with_tenant_preopens("acme", &[PreopenSpec::new(&host, "/data")])
+                .unwrap_err();
+            assert!(format!("{err:#}").contains("outside the allowed preopen roots"));
+        }
+        #[cfg(unix)]
+        {
+            let link = root.join("escape");
+            std::os::unix::fs::symlink(&outside, &link).unwrap();
+            assert!(
+                policy_with_root(&root)
+                    .with_tenant_preopens("acme", &[PreopenSpec::new(&link, "/data")])
+                    .is_err()
+            );
+        }
+        assert!(
+            RunnerWasiPolicy::new()
+                .with_tenant_preopens("acme", &[PreopenSpec::new(&scratch, "/scratch")])
+                .is_err(),
+            "no roots configured"
+        );
+    }
+
+    #[test]
+    fn tenant_preopen_dirs_only_cover_the_tenants_fs() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let scratch = root.join("tenants/acme/fs/scratch");
+        for dir in [
+            root.join("state"),
+            root.join("cache"),
+            scratch.clone(),
+            root.join("tenants/other/fs"),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        let policy = RunnerWasiPolicy::new().allow_tenant_preopen_dirs(root.join("tenants"));
+
+        let allowed = policy
+            .with_tenant_preopens("acme", &[PreopenSpec::new(&scratch, "/scratch")])
+            .unwrap();
+        assert_eq!(allowed.preopens.len(), 1);
+        for host in [
+            root.join("state"),
+            root.join("cache"),
+            root.to_path_buf(),
+            root.join("tenants/acme"),
+            root.join("tenants/other/fs"),
+        ] {
+            let err = policy
+                .with_tenant_preopens("acme", &[PreopenSpec::new(&host, "/data")])
+                .unwrap_err();
+            assert!(
+                format!("{err:#}").contains("outside the allowed preopen roots"),
+                "{}",
+                host.display()
+            );
+        }
+        assert!(
+            policy
+                .with_tenant_preopens("..", &[PreopenSpec::new(&scratch, "/scratch")])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn tenant_guest_paths_are_checked() {
+        let temp = TempDir::new().unwrap();
+        for guest in ["scratch", "/scratch/../etc", "/assets"] {
+            let policy = policy_with_root(temp.path())
+                .with_preopen(PreopenSpec::new(temp.path(), "/assets").read_only(true));
+            assert!(
+                policy
+                    .with_tenant_preopens("acme", &[PreopenSpec::new(temp.path(), guest)])
+                    .is_err(),
+                "{guest}"
+            );
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Shared wasmtime engine configuration.
+//!
+//! Every engine runs with epoch interruption enabled and is registered with a single
+//! background ticker that advances its epoch every [`EPOCH_TICK`]. Stores check their
+//! CPU deadline and cancellation state on every tick (see [`crate::cancel`]), so a guest
+//! stuck in a loop traps instead of pinning a core after the host has given up on it.
+//!
+//! Instances are allocated on demand unless the tenant's `wasm_engine` bindings block
+//! selects the pooling allocator, which reserves slots up front and avoids mmap/munmap
+//! churn under high invoke rates.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, Error, Result, anyhow};
+use serde::Deserialize;
+use wasmtime::{
+    Config, Engine, EngineWeak, InstanceAllocationStrategy, PoolConcurrencyLimitError,
+    PoolingAllocationConfig,
+};
+
+use crate::cache::{CpuPolicy, EngineProfile};
+
+/// Interval between epoch increments; deadlines are rounded up to whole ticks.
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Fingerprint of the settings [`engine_config`] applies to every engine. Compiled
+/// artifacts depend on these settings, so any change here must change the fingerprint
+/// to keep cached artifacts apart; see [`config_fingerprint`] for the pooling suffix.
+pub const ENGINE_CONFIG_FINGERPRINT: &str = "default+epoch-interruption";
+
+/// Core instances reserved per component instance slot; a component instantiates
+/// its main module plus adapters and shims.
+const CORE_INSTANCES_PER_COMPONENT: u32 = 16;
+
+const WASM_PAGE_BYTES: u64 = 64 * 1024;
+
+/// `wasm_engine` bindings block.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct WasmEngineConfig {
+    #[serde(default)]
+    pub allocation: AllocationStrategy,
+    /// Pool sizes; only read when `allocation` is `pooling`.
+    #[serde(default)]
+    pub pooling: PoolingLimits,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationStrategy {
+    #[default]
+    OnDemand,
+    Pooling,
+}
+
+/// Pool sizes for one engine. Each pack runtime owns an engine, so the limits apply
+/// per pack rather than per tenant.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct PoolingLimits {
+    /// Component instances alive at once.
+    #[serde(default = "default_total_instances")]
+    pub total_instances: u32,
+    /// Largest linear memory a pooled instance may have, in 64 KiB wasm pages.
+    #[serde(default = "default_max_memory_pages")]
+    pub max_memory_pages: u64,
+    /// Linear memories across all pooled instances.
+    #[serde(default = "default_total_memories")]
+    pub total_memories: u32,
+    /// Tables across all pooled instances.
+    #[serde(default = "default_total_tables")]
+    pub total_tables: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            total_instances: default_total_instances(),
+            max_memory_pages: default_max_memory_pages(),
+            total_memories: default_total_memories(),
+            total_tables: default_total_tables(),
+        }
+    }
+}
+
+fn default_total_instances() -> u32 {
+    100
+}
+
+fn default_max_memory_pages() -> u64 {
+    // Matches the default `wasm_limits.max_memory_bytes` of 512 MiB.
+    8192
+}
+
+fn default_total_memories() -> u32 {
+    200
+}
+
+fn default_total_tables() -> u32 {
+    200
+}
+
+impl PoolingLimits {
+    fn allocation_config(&self) -> Result<PoolingAllocationConfig> {
+        let max_memory_size = self
+            .max_memory_pages
+            .checked_mul(WASM_PAGE_BYTES)
+            .and_then(|bytes| usize::try_from(bytes).ok())
+            .with_context(|| {
+                format!(
+                    "wasm_engine.pooling.max_memory_pages {} is too large",
+                    self.max_memory_pages
+                )
+            })?;
+        let mut pooling = PoolingAllocationConfig::default();
+        pooling
+            .total_component_instances(self.total_instances)
+            .total_core_instances(
+                self.total_instances
+                    .saturating_mul(CORE_INSTANCES_PER_COMPONENT),
+            )
+            .total_memories(self.total_memories)
+            .total_tables(self.total_tables)
+            .max_memory_size(max_memory_size);
+        Ok(pooling)
+    }
+}
+
+pub fn engine_config(settings: &WasmEngineConfig) -> Result<Config> {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    if settings.allocation == AllocationStrategy::Pooling {
+        config.allocation_strategy(InstanceAllocationStrategy::Pooling(
+            settings.pooling.allocation_config()?,
+        ));
+    }
+    Ok(config)
+}
+
+/// Fingerprint for engines built from `settings`. On-demand engines keep
+/// [`ENGINE_CONFIG_FINGERPRINT`] so existing caches stay valid.
+pub fn config_fingerprint(settings: &WasmEngineConfig) -> String {
+    match settings.allocation {
+        AllocationStrategy::OnDemand => ENGINE_CONFIG_FINGERPRINT.to_string(),
+        AllocationStrategy::Pooling => {
+            let pooling = &settings.pooling;
+            format!(
+                "{ENGINE_CONFIG_FINGERPRINT}+pooling(instances={},memory_pages={},memories={},tables={})",
+                pooling.total_instances,
+                pooling.max_memory_pages,
+                pooling.total_memories,
+                pooling.total_tables
+            )
+        }
+    }
+}
+
+/// Build an engine with [`engine_config`] and start ticking its epoch.
+pub fn new_engine(settings: &WasmEngineConfig) -> Result<Engine> {
+    let engine =
+        Engine::new(&engine_config(settings)?).context("failed to create wasmtime engine")?;
+    register_ticker(&engine)?;
+    Ok(engine)
+}
+
+/// Cache profile matching engines built by [`new_engine`] from `settings`.
+pub fn engine_profile(engine: &Engine, settings: &WasmEngineConfig) -> EngineProfile {
+    EngineProfile::from_engine(engine, CpuPolicy::Native, config_fingerprint(settings))
+}
+
+/// A pooled engine ran out of slots, or a component needs more than a slot holds.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("instance pool limit reached: {detail}")]
+pub struct PoolExhausted {
+    pub detail: String,
+}
+
+/// Messages wasmtime uses when a module does not fit a pooling slot.
+const POOL_SLOT_MARKERS: &[&str] = &[
+    "exceeds the limit of",
+    "exceeds the configured maximum",
+    "exceeds the per-instance limit",
+];
+
+/// Attach [`PoolExhausted`] to an instantiation error caused by pooling limits, so
+/// callers can report it instead of a bare wasmtime message.
+pub fn explain_pool_error(err: Error) -> Error {
+    let detail = err.chain().find_map(|cause| {
+        let message = cause.to_string();
+        (cause.is::<PoolConcurrencyLimitError>()
+            || POOL_SLOT_MARKERS
+                .iter()
+                .any(|marker| message.contains(marker)))
+        .then_some(message)
+    });
+    match detail {
+        Some(detail) => err.context(PoolExhausted { detail }),
+        None => err,
+    }
+}
+
+/// Epoch ticks covering `budget`, never less than one.
+pub fn deadline_ticks(budget: Duration) -> u64 {
+    let tick = EPOCH_TICK.as_millis();
+    (budget.as_millis().div_ceil(tick) as u64).max(1)
+}
+
+static ENGINES: Mutex<Vec<EngineWeak>> = Mutex::new(Vec::new());
+static TICKER: OnceLock<Result<(), String>> = OnceLock::new();
+
+fn register_ticker(engine: &Engine) -> Result<()> {
+    TICKER
+        .get_or_init(|| {
+            std::thread::Builder::new()
+                .name("greentic-epoch-ticker".into())
+                .spawn(tick_forever)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        })
+        .clone()
+        .map_err(|err| anyhow!("failed to spawn epoch ticker: {err}"))?;
+    ENGINES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(engine.weak());
+    Ok(())
+}
+
+fn tick_forever() {
+    loop {
+        std::thread::sleep(EPOCH_TICK);
+        let mut engines = ENGINES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Dropped engines fall out of the registry on the next tick.
+        engines.retain(|weak| match weak.upgrade() {
+            Some(engine) => {
+                engine.increment_epoch();
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_ticks_round_up() {
+        assert_eq!(deadline_ticks(Duration::ZERO), 1);
+        assert_eq!(deadline_ticks(EPOCH_TICK), 1);
+        assert_eq!(deadline_ticks(EPOCH_TICK + Duration::from_millis(1)), 2);
+        assert_eq!(deadline_ticks(Duration::from_secs(1)), 100);
+    }
+
+    #[test]
+    fn profile_differs_from_default_engine_profile() {
+        let settings = WasmEngineConfig::default();
+        let engine = new_engine(&settings).unwrap();
+        let legacy = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".into());
+        assert_ne!(engine_profile(&engine, &settings).id(), legacy.id());
+    }
+
+    #[test]
+    fn pooling_settings_change_the_profile() {
+        let on_demand = WasmEngineConfig::default();
+        let pooling: WasmEngineConfig = serde_yaml_bw::from_str(
+            "allocation: pooling\npooling:\n  total_instances: 8\n  max_memory_pages: 256",
+        )
+        .unwrap();
+        assert_eq!(pooling.pooling.total_tables, 200);
+        let larger = WasmEngineConfig {
+            pooling: PoolingLimits {
+                total_instances: 16,
+                ..pooling.pooling
+            },
+            ..pooling
+        };
+
+        let engine = new_engine(&pooling).unwrap();
+        let profiles = [&on_demand, &pooling, &larger]
+            .map(|settings| engine_profile(&engine, settings).id().to_string());
+        assert_ne!(profiles[0], profiles[1]);
+        assert_ne!(profiles[1], profiles[2]);
+        assert_eq!(config_fingerprint(&on_demand), ENGINE_CONFIG_FINGERPRINT);
+    }
+
+    #[test]
+    fn pool_slot_errors_are_explained() {
+        let err = anyhow!("memory index 0 has a minimum byte size of 1114112 which exceeds the limit of 65536 bytes")
+            .context("failed to instantiate component");
+        let err = explain_pool_error(err);
+        assert!(err.downcast_ref::<PoolExhausted>().is_some());
+
+        let err = explain_pool_error(anyhow!("wasm trap: unreachable"));
+        assert!(err.downcast_ref::<PoolExhausted>().is_none());
+    }
+}
@@ -0,0 +1,282 @@
+//! Per-store resource limits for component and provider invocations.
+//!
+//! Every store created for a component gets a [`WasmLimiter`] built from the tenant's
+//! `wasm_limits` bindings block. Hitting a memory or table limit traps the guest; the
+//! host reports it as [`ResourceExhausted`] so callers can surface which limit was
+//! reached. Running past the store's epoch deadline is reported as [`DeadlineExceeded`].
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+use serde::Deserialize;
+use wasmtime::ResourceLimiter;
+
+/// Default linear memory cap per store (512 MiB).
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 512 * 1024 * 1024;
+/// Default table element cap per table.
+pub const DEFAULT_MAX_TABLE_ELEMENTS: usize = 1_000_000;
+/// Default CPU deadline per invocation when the request carries no shorter timeout.
+pub const DEFAULT_MAX_CPU_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct WasmLimits {
+    #[serde(default = "default_max_memory_bytes")]
+    pub max_memory_bytes: usize,
+    #[serde(default = "default_max_table_elements")]
+    pub max_table_elements: usize,
+    #[serde(default = "default_max_cpu_ms")]
+    pub max_cpu_ms: u64,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: default_max_memory_bytes(),
+            max_table_elements: default_max_table_elements(),
+            max_cpu_ms: default_max_cpu_ms(),
+        }
+    }
+}
+
+fn default_max_memory_bytes() -> usize {
+    DEFAULT_MAX_MEMORY_BYTES
+}
+
+fn default_max_table_elements() -> usize {
+    DEFAULT_MAX_TABLE_ELEMENTS
+}
+
+fn default_max_cpu_ms() -> u64 {
+    DEFAULT_MAX_CPU_MS
+}
+
+impl WasmLimits {
+    /// Deadline for one invocation: the tenant's CPU budget, or the time left until
+    /// the request deadline when that is sooner.
+    pub fn deadline(&self, deadline_unix_ms: Option<u64>) -> InvokeDeadline {
+        let cpu = InvokeDeadline {
+            kind: DeadlineKind::Cpu,
+            budget: Duration::from_millis(self.max_cpu_ms),
+        };
+        let Some(deadline_unix_ms) = deadline_unix_ms else {
+            return cpu;
+        };
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_millis() as u64)
+            .unwrap_or_default();
+        let remaining = Duration::from_millis(deadline_unix_ms.saturating_sub(now_ms));
+        if remaining < cpu.budget {
+            InvokeDeadline {
+                kind: DeadlineKind::WallClock,
+                budget: remaining,
+            }
+        } else {
+            cpu
+        }
+    }
+}
+
+/// Which bound sets an invocation's epoch deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineKind {
+    /// The tenant's `wasm_limits.max_cpu_ms` budget.
+    Cpu,
+    /// The caller's request timeout.
+    WallClock,
+}
+
+impl DeadlineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlineKind::Cpu => "cpu deadline",
+            DeadlineKind::WallClock => "wall-clock timeout",
+        }
+    }
+}
+
+impl fmt::Display for DeadlineKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvokeDeadline {
+    pub kind: DeadlineKind,
+    pub budget: Duration,
+}
+
+/// A component was interrupted at its epoch deadline.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("component interrupted at its {} of {}ms", .deadline.kind, .deadline.budget.as_millis())]
+pub struct DeadlineExceeded {
+    pub deadline: InvokeDeadline,
+}
+
+/// A component tried to grow past one of its [`WasmLimits`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("component exceeded {limit}: requested {requested}, limit {max}")]
+pub struct ResourceExhausted {
+    /// Name of the exceeded `wasm_limits` field.
+    pub limit: &'static str,
+    pub requested: usize,
+    pub max: usize,
+}
+
+/// Store limiter enforcing [`WasmLimits`] and remembering the first limit hit.
+#[derive(Debug)]
+pub struct WasmLimiter {
+    limits: WasmLimits,
+    deadline: InvokeDeadline,
+    exceeded: Option<ResourceExhausted>,
+    interrupted: bool,
+}
+
+impl Default for WasmLimiter {
+    fn default() -> Self {
+        Self::new(WasmLimits::default(), None)
+    }
+}
+
+impl WasmLimiter {
+    pub fn new(limits: WasmLimits, deadline_unix_ms: Option<u64>) -> Self {
+        Self {
+            limits,
+            deadline: limits.deadline(deadline_unix_ms),
+            exceeded: None,
+            interrupted: false,
+        }
+    }
+
+    pub fn exceeded(&self) -> Option<&ResourceExhausted> {
+        self.exceeded.as_ref()
+    }
+
+    pub fn deadline(&self) -> InvokeDeadline {
+        self.deadline
+    }
+
+    /// Record that the store reached its epoch deadline; returns the trap to raise.
+    pub fn interrupt(&mut self) -> DeadlineExceeded {
+        self.interrupted = true;
+        DeadlineExceeded {
+            deadline: self.deadline,
+        }
+    }
+
+    /// Attach the recorded limit hit to an invocation error, so callers can
+    /// `downcast_ref::<ResourceExhausted>()` (or [`DeadlineExceeded`]) regardless of
+    /// how the trap was wrapped.
+    pub fn explain(&self, err: Error) -> Error {
+        if let Some(hit) = &self.exceeded {
+            return err.context(hit.clone());
+        }
+        if self.interrupted {
+            return err.context(DeadlineExceeded {
+                deadline: self.deadline,
+            });
+        }
+        err
+    }
+
+    fn check(&mut self, limit: &'static str, requested: usize, max: usize) -> wasmtime::Result<()> {
+        if requested <= max {
+            return Ok(());
+        }
+        let hit = ResourceExhausted {
+            limit,
+            requested,
+            max,
+        };
+        self.exceeded.get_or_insert_with(|| hit.clone());
+        Err(hit.into())
+    }
+}
+
+impl ResourceLimiter for WasmLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        self.check("max_memory_bytes", desired, self.limits.max_memory_bytes)?;
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        self.check(
+            "max_table_elements",
+            desired,
+            self.limits.max_table_elements,
+        )?;
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_growth_past_limit_is_recorded() {
+        let mut limiter = WasmLimiter::new(
+            WasmLimits {
+                max_memory_bytes: 1024,
+                ..WasmLimits::default()
+            },
+            None,
+        );
+        assert!(limiter.memory_growing(0, 512, None).unwrap());
+        assert!(limiter.memory_growing(512, 2048, None).is_err());
+        let err = limiter.explain(anyhow::anyhow!("wasm trap"));
+        let hit = err
+            .downcast_ref::<ResourceExhausted>()
+            .expect("limit hit attached");
+        assert_eq!(hit.limit, "max_memory_bytes");
+        assert_eq!(hit.max, 1024);
+    }
+
+    #[test]
+    fn bindings_default_missing_fields() {
+        let limits: WasmLimits = serde_yaml_bw::from_str("max_memory_bytes: 4096").unwrap();
+        assert_eq!(limits.max_memory_bytes, 4096);
+        assert_eq!(limits.max_table_elements, DEFAULT_MAX_TABLE_ELEMENTS);
+        assert_eq!(limits.max_cpu_ms, DEFAULT_MAX_CPU_MS);
+    }
+
+    #[test]
+    fn request_deadline_wins_when_sooner_than_cpu_budget() {
+        let limits = WasmLimits {
+            max_cpu_ms: 5_000,
+            ..WasmLimits::default()
+        };
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let deadline = limits.deadline(Some(now_ms + 1_000));
+        assert_eq!(deadline.kind, DeadlineKind::WallClock);
+        assert!(deadline.budget <= Duration::from_millis(1_000));
+
+        let deadline = limits.deadline(Some(now_ms + 60_000));
+        assert_eq!(deadline.kind, DeadlineKind::Cpu);
+        assert_eq!(deadline.budget, Duration::from_millis(5_000));
+
+        let mut limiter = WasmLimiter::new(limits, None);
+        limiter.interrupt();
+        let err = limiter.explain(anyhow::anyhow!("wasm trap"));
+        let hit = err
+            .downcast_ref::<DeadlineExceeded>()
+            .expect("deadline attached");
+        assert_eq!(hit.deadline.kind, DeadlineKind::Cpu);
+    }
+}
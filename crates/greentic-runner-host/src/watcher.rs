@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow};
 use runner_core::{Index, PackConfig, PackManager};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::task;
 
 use crate::HostConfig;
@@ -12,7 +12,7 @@ use crate::engine::host::{SessionHost, StateHost};
 use crate::host::RunnerHost;
 use crate::http::health::HealthState;
 use crate::pack::{ComponentResolution, PackRuntime};
-use crate::runner::adapt_timer;
+use crate::preflight;
 use crate::runtime::{ActivePacks, TenantRuntime};
 use crate::secrets::DynSecretsManager;
 use crate::storage::session::DynSessionStore;
@@ -29,18 +29,31 @@ impl Drop for PackWatcher {
     }
 }
 
+type ReloadResponder = oneshot::Sender<Result<()>>;
+
 #[derive(Clone)]
 pub struct PackReloadHandle {
-    trigger: mpsc::Sender<()>,
+    trigger: mpsc::Sender<Option<ReloadResponder>>,
 }
 
 impl PackReloadHandle {
     pub async fn trigger(&self) -> Result<()> {
         self.trigger
-            .send(())
+            .send(None)
             .await
             .map_err(|_| anyhow!("pack watcher task stopped"))
     }
+
+    /// Queue a reload and wait for its outcome.
+    pub async fn reload(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.trigger
+            .send(Some(tx))
+            .await
+            .map_err(|_| anyhow!("pack watcher task stopped"))?;
+        rx.await
+            .map_err(|_| anyhow!("pack watcher task stopped before reporting reload"))?
+    }
 }
 
 pub async fn start_pack_watcher(
@@ -53,7 +66,6 @@ pub async fn start_pack_watcher(
         .await
         .context("pack manager init task failed")??;
     let manager = Arc::new(manager);
-    let configs = Arc::new(host.tenant_configs());
     let active = host.active_packs();
     let health = host.health_state();
     let session_host = host.session_host();
@@ -64,7 +76,7 @@ pub async fn start_pack_watcher(
     let secrets_manager = host.secrets_manager();
 
     reload_once(
-        configs.as_ref(),
+        &host.tenant_configs(),
         &manager,
         &cfg,
         &active,
@@ -78,28 +90,29 @@ pub async fn start_pack_watcher(
     )
     .await?;
 
-    let (tx, mut rx) = mpsc::channel::<()>(4);
+    let (tx, mut rx) = mpsc::channel::<Option<ReloadResponder>>(4);
     let index_cfg = cfg.clone();
     let manager_clone = Arc::clone(&manager);
     let health_clone = Arc::clone(&health);
     let active_clone = Arc::clone(&active);
-    let configs_clone = Arc::clone(&configs);
+    let host_clone = Arc::clone(&host);
     let state_store_clone = Arc::clone(&state_store);
     let wasi_policy_clone = Arc::clone(&wasi_policy);
     let secrets_manager_clone = secrets_manager.clone();
     let handle = tokio::spawn(async move {
         let mut ticker = tokio::time::interval(refresh);
         loop {
-            tokio::select! {
-                _ = ticker.tick() => {},
-                recv = rx.recv() => {
-                    if recv.is_none() {
-                        break;
-                    }
-                }
-            }
-            if let Err(err) = reload_once(
-                configs_clone.as_ref(),
+            let responder = tokio::select! {
+                _ = ticker.tick() => None,
+                recv = rx.recv() => match recv {
+                    Some(responder) => responder,
+                    None => break,
+                },
+            };
+            // Configs are re-read on every pass so a swapped set (e.g. after SIGHUP)
+            // takes effect on the next reload.
+            let result = reload_once(
+                &host_clone.tenant_configs(),
                 &manager_clone,
                 &index_cfg,
                 &active_clone,
@@ -111,10 +124,13 @@ pub async fn start_pack_watcher(
                 Arc::clone(&wasi_policy_clone),
                 secrets_manager_clone.clone(),
             )
-            .await
-            {
+            .await;
+            if let Err(err) = &result {
                 tracing::error!(error = %err, "pack reload failed");
-                health_clone.record_reload_error(&err);
+                health_clone.record_reload_error(err);
+            }
+            if let Some(responder) = responder {
+                let _ = responder.send(result);
             }
         }
     });
@@ -204,12 +220,16 @@ async fn reload_once(
             Arc::clone(&secrets_manager),
         )
         .await?;
-        let timers = adapt_timer::spawn_timers(Arc::clone(&runtime))?;
-        runtime.register_timers(timers);
 
         next.insert(tenant.clone(), runtime);
     }
-    active.replace(next);
+    active.replace_and_stop(next).await;
+    let preflight_active = Arc::clone(active);
+    let report = task::spawn_blocking(move || preflight::check_all(&preflight_active))
+        .await
+        .context("preflight task failed")?;
+    report.log();
+    health.record_preflight(report);
     health.record_reload_success();
     tracing::info!("pack reload completed successfully");
     Ok(())
@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::engine::host::SessionKey;
 use greentic_runner_host::pack::PackRuntime;
@@ -28,6 +29,8 @@ fn host_config(tenant: &str) -> HostConfig {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -37,6 +40,13 @@ fn host_config(tenant: &str) -> HostConfig {
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -3,20 +3,41 @@ use std::str::FromStr;
 
 use anyhow::{Context, Result, anyhow};
 use greentic_flow::flow_bundle::load_and_validate_bundle_with_flow;
+use greentic_runner_host::audit::{AuditEvent, MemoryAuditSink};
+use greentic_runner_host::cancel::{self, CancelReason, InvocationCancel, InvocationRegistry};
+use greentic_runner_host::component_api::node::{ExecCtx, Impersonation, TenantCtx};
+use greentic_runner_host::component_api::tenant_attributes;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, RedeliveryConfig, SecretsPolicy, SessionPolicy, StateStorePolicy,
+    TenantWasiConfig, TimerBinding, TimerOverlap, WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
+use greentic_runner_host::engine::dedupe::is_duplicate;
+use greentic_runner_host::engine::runtime::IngressEnvelope;
 use greentic_runner_host::pack::{ComponentResolution, PackRuntime};
-use greentic_runner_host::runner::engine::{FlowContext, FlowEngine, FlowStatus};
+use greentic_runner_host::runner::engine::{
+    FlowContext, FlowEngine, FlowExecution, FlowStatus, RetriesExhausted,
+};
 use greentic_runner_host::runner::flow_adapter::{FlowIR, NodeIR, RouteIR};
-use greentic_runner_host::trace::TraceConfig;
+use greentic_runner_host::runner::redelivery::{
+    RedeliveryOutcome, STATUS_REDELIVERY_SCHEDULED, redelivery_metrics,
+};
+use greentic_runner_host::runtime::{ActivePacks, TenantRuntime};
+use greentic_runner_host::secrets::default_manager;
+use greentic_runner_host::storage::{
+    new_session_store, new_state_store, session_host_from, state_host_from,
+};
+use greentic_runner_host::stream::{StreamEvent, StreamObserver};
+use greentic_runner_host::trace::{
+    TraceConfig, TraceContext, TraceEnvelope, TraceMode, TraceRecorder, TraceStep,
+};
 use greentic_runner_host::validate::ValidationConfig;
+use greentic_runner_host::wasi::RunnerWasiPolicy;
 use greentic_types::{
     ComponentCapabilities, ComponentManifest, ComponentProfiles, ExtensionInline, ExtensionRef,
-    Flow, FlowComponentRef, FlowId, FlowKind, FlowMetadata, InputMapping, Node, NodeId,
-    OutputMapping, PackFlowEntry, PackKind, PackManifest, ResourceHints, Routing, TelemetryHints,
-    encode_pack_manifest,
+    Flow, FlowComponentRef, FlowId, FlowKind, FlowMetadata, HostCapabilities, InputMapping, Node,
+    NodeId, OutputMapping, PackFlowEntry, PackKind, PackManifest, ResourceHints, Routing,
+    StateCapabilities, TelemetryHints, encode_pack_manifest,
 };
 use once_cell::sync::Lazy;
 use semver::Version;
@@ -24,7 +45,8 @@ use serde_json::{Value, json};
 use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tempfile::TempDir;
 use zip::ZipArchive;
 use zip::write::FileOptions;
@@ -38,44 +60,46 @@ fn workspace_root() -> PathBuf {
 }
 
 fn build_components() -> Result<Vec<(String, PathBuf)>> {
-    let workspace = workspace_root().join("tests/fixtures/runner-components");
-    let mut results = Vec::new();
     let crates = vec![
         ("qa.process", "qa_process"),
         ("templating.handlebars", "templating_handlebars"),
         ("state.store", "state_store_component"),
     ];
+    crates
+        .into_iter()
+        .map(|(name, krate)| Ok((name.to_string(), build_component(krate)?)))
+        .collect()
+}
+
+fn build_component(krate: &str) -> Result<PathBuf> {
+    let workspace = workspace_root().join("tests/fixtures/runner-components");
     let offline = std::env::var("CARGO_NET_OFFLINE").ok();
-    for (name, krate) in &crates {
-        let manifest = workspace.join(krate).join("Cargo.toml");
-        let mut cmd = std::process::Command::new("cargo");
-        if let Some(val) = &offline {
-            cmd.env("CARGO_NET_OFFLINE", val);
-        }
-        let mut args: Vec<String> = vec![
-            "build".into(),
-            "--manifest-path".into(),
-            manifest.to_str().unwrap().into(),
-            "--target".into(),
-            "wasm32-wasip2".into(),
-            "--release".into(),
-        ];
-        if matches!(offline.as_deref(), Some("true")) {
-            args.insert(1, "--offline".into());
-        }
+    let manifest = workspace.join(krate).join("Cargo.toml");
+    let mut cmd = std::process::Command::new("cargo");
+    if let Some(val) = &offline {
+        cmd.env("CARGO_NET_OFFLINE", val);
+    }
+    let mut args: Vec<String> = vec![
+        "build".into(),
+        "--manifest-path".into(),
+        manifest.to_str().unwrap().into(),
+        "--target".into(),
+        "wasm32-wasip2".into(),
+        "--release".into(),
+    ];
+    if matches!(offline.as_deref(), Some("true")) {
+        args.insert(1, "--offline".into());
+    }
 
-        let status = cmd
-            .current_dir(&workspace)
-            .args(args)
-            .status()
-            .with_context(|| format!("failed to build {krate} component"))?;
-        if !status.success() {
-            anyhow::bail!("component build failed for {krate}");
-        }
-        let artifact = workspace.join(format!("target/wasm32-wasip2/release/{}.wasm", krate));
-        results.push((name.to_string(), artifact));
+    let status = cmd
+        .current_dir(&workspace)
+        .args(args)
+        .status()
+        .with_context(|| format!("failed to build {krate} component"))?;
+    if !status.success() {
+        anyhow::bail!("component build failed for {krate}");
     }
-    Ok(results)
+    Ok(workspace.join(format!("target/wasm32-wasip2/release/{}.wasm", krate)))
 }
 
 fn demo_flow_ir() -> FlowIR {
@@ -129,6 +153,8 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -138,6 +164,13 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -414,6 +447,165 @@ fn build_pack_with_runtime_extension(
     Ok(())
 }
 
+fn build_lifecycle_pack(component_path: &Path, pack_path: &Path) -> Result<()> {
+    let capabilities = ComponentCapabilities {
+        host: HostCapabilities {
+            state: Some(StateCapabilities {
+                read: true,
+                write: true,
+            }),
+            ..HostCapabilities::default()
+        },
+        ..ComponentCapabilities::default()
+    };
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "component.lifecycle".parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: "lifecycle.recorder".parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities,
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: Vec::new(),
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(pack_path).context("create pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let manifest_bytes = encode_pack_manifest(&manifest)?;
+    zip.start_file("manifest.cbor", options)?;
+    zip.write_all(&manifest_bytes)?;
+
+    zip.start_file("components/lifecycle.recorder.wasm", options)?;
+    let mut comp_file = File::open(component_path)?;
+    std::io::copy(&mut comp_file, &mut zip)?;
+    zip.finish().context("finalise pack archive")?;
+    Ok(())
+}
+
+/// A pack whose `ticks.flow` runs `tick.counter`'s `tick` once.
+fn build_tick_pack(component_path: &Path, pack_path: &Path) -> Result<()> {
+    build_single_node_pack(
+        "component.ticks",
+        "ticks.flow",
+        ("tick.counter", "tick"),
+        component_path,
+        pack_path,
+    )
+}
+
+/// A pack whose flow `flow_id` runs `op` of `component` in its only node; the component
+/// may use the state store.
+fn build_single_node_pack(
+    pack_id: &str,
+    flow_id: &str,
+    (component, op): (&str, &str),
+    component_path: &Path,
+    pack_path: &Path,
+) -> Result<()> {
+    let node_id = NodeId::from_str(op)?;
+    let flow = Flow {
+        schema_version: "1.0".into(),
+        id: FlowId::from_str(flow_id)?,
+        kind: FlowKind::Messaging,
+        entrypoints: BTreeMap::from([("default".into(), Value::String(node_id.to_string()))]),
+        nodes: [(
+            node_id.clone(),
+            Node {
+                id: node_id,
+                component: FlowComponentRef {
+                    id: component.parse()?,
+                    pack_alias: None,
+                    operation: Some(op.into()),
+                },
+                input: InputMapping { mapping: json!({}) },
+                output: OutputMapping {
+                    mapping: Value::Null,
+                },
+                routing: Routing::End,
+                telemetry: TelemetryHints::default(),
+            },
+        )]
+        .into_iter()
+        .collect(),
+        metadata: FlowMetadata::default(),
+    };
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: pack_id.parse()?,
+        name: None,
+        version: Version::parse("0.0.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: component.parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: vec![FlowKind::Messaging],
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities {
+                host: HostCapabilities {
+                    state: Some(StateCapabilities {
+                        read: true,
+                        write: true,
+                    }),
+                    ..HostCapabilities::default()
+                },
+                ..ComponentCapabilities::default()
+            },
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: vec![PackFlowEntry {
+            id: flow.id.clone(),
+            kind: flow.kind,
+            flow,
+            tags: Vec::new(),
+            entrypoints: vec!["default".into()],
+        }],
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: None,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(pack_path).context("create pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let manifest_bytes = encode_pack_manifest(&manifest)?;
+    zip.start_file("manifest.cbor", options)?;
+    zip.write_all(&manifest_bytes)?;
+
+    zip.start_file(format!("components/{component}.wasm"), options)?;
+    let mut comp_file = File::open(component_path)?;
+    std::io::copy(&mut comp_file, &mut zip)?;
+    zip.finish().context("finalise pack archive")?;
+    Ok(())
+}
+
 static RUNTIME: Lazy<&'static tokio::runtime::Runtime> = Lazy::new(|| {
     Box::leak(Box::new(
         tokio::runtime::Builder::new_current_thread()
@@ -449,6 +641,302 @@ fn component_exec_invokes_pack_component() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn v0_5_component_receives_tenant_attributes_and_impersonation() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let mut config = host_config(&bindings_path);
+    config
+        .attributes
+        .insert("plan".to_string(), "enterprise".to_string());
+    let config = Arc::new(config);
+
+    let component = build_component("attributes_echo")?;
+    let pack = PackRuntime::for_component_test(
+        vec![("attributes.echo".to_string(), component)],
+        HashMap::new(),
+        "attributes-pack",
+        Arc::clone(&config),
+    )?;
+    let request = BTreeMap::from([
+        ("plan".to_string(), "free".to_string()),
+        ("region".to_string(), "eu-west".to_string()),
+    ]);
+    let ctx = ExecCtx {
+        tenant: TenantCtx {
+            tenant: "demo".into(),
+            team: None,
+            user: None,
+            trace_id: None,
+            i18n_id: None,
+            correlation_id: None,
+            deadline_unix_ms: None,
+            attempt: 1,
+            idempotency_key: None,
+            attributes: tenant_attributes(&config.attributes, &request)?,
+            impersonation: Some(Impersonation {
+                actor_id: "support-7".into(),
+                reason: Some("ticket 42".into()),
+            }),
+        },
+        i18n_id: None,
+        flow_id: "attributes.flow".into(),
+        node_id: Some("echo".into()),
+    };
+    let output =
+        rt.block_on(pack.invoke_component("attributes.echo", ctx, "echo", None, "{}".to_string()))?;
+    assert_eq!(
+        output,
+        json!({
+            "attributes": { "plan": "enterprise", "region": "eu-west" },
+            "impersonation": { "actor_id": "support-7", "reason": "ticket 42" },
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn stream_observer_sees_component_events_in_order() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let config = Arc::new(host_config(&bindings_path));
+
+    let component = build_component("stream_progress")?;
+    let pack = PackRuntime::for_component_test(
+        vec![("stream.progress".to_string(), component)],
+        HashMap::new(),
+        "stream-pack",
+        config,
+    )?;
+    let ctx = ExecCtx {
+        tenant: TenantCtx {
+            tenant: "demo".into(),
+            team: None,
+            user: None,
+            trace_id: None,
+            i18n_id: None,
+            correlation_id: None,
+            deadline_unix_ms: None,
+            attempt: 1,
+            idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
+        },
+        i18n_id: None,
+        flow_id: "stream.flow".into(),
+        node_id: Some("progress".into()),
+    };
+    let observer = Arc::new(RecordingObserver::default());
+    let output = rt.block_on(pack.invoke_component_stream(
+        "stream.progress",
+        ctx,
+        "progress",
+        "{}".to_string(),
+        observer.clone(),
+    ))?;
+    assert_eq!(output, json!({"step": 1}));
+    assert_eq!(
+        *observer.events.lock().unwrap(),
+        vec![
+            StreamEvent::Progress(10),
+            StreamEvent::Progress(50),
+            StreamEvent::Data(json!({"step": 1})),
+            StreamEvent::Progress(100),
+            StreamEvent::Done,
+        ]
+    );
+    Ok(())
+}
+
+fn cancel_aware_pack(temp: &TempDir) -> Result<PackRuntime> {
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let config = Arc::new(host_config(&bindings_path));
+    let component = build_component("cancel_aware")?;
+    PackRuntime::for_component_test(
+        vec![("cancel.aware".to_string(), component)],
+        HashMap::new(),
+        "cancel-pack",
+        config,
+    )
+}
+
+fn count_ctx(deadline_unix_ms: Option<u64>) -> ExecCtx {
+    ExecCtx {
+        tenant: TenantCtx {
+            tenant: "demo".into(),
+            team: None,
+            user: None,
+            trace_id: None,
+            i18n_id: None,
+            correlation_id: None,
+            deadline_unix_ms,
+            attempt: 1,
+            idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
+        },
+        i18n_id: None,
+        flow_id: "cancel.flow".into(),
+        node_id: Some("count".into()),
+    }
+}
+
+fn assert_partial_result(output: &Value) {
+    assert_eq!(output["ok"], json!(false), "unexpected output {output}");
+    assert_eq!(output["error"]["code"], json!("cancelled"));
+    let iterations = output["error"]["details"]["iterations"]
+        .as_u64()
+        .expect("iterations reported");
+    assert!(iterations > 0, "component never ran: {output}");
+}
+
+#[test]
+fn component_polling_should_cancel_stops_at_the_deadline() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let pack = cancel_aware_pack(&temp)?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+
+    let started = Instant::now();
+    let output = rt.block_on(pack.invoke_component(
+        "cancel.aware",
+        count_ctx(Some(now_ms + 200)),
+        "count",
+        None,
+        "{}".to_string(),
+    ))?;
+    let elapsed = started.elapsed();
+    // The component returns its partial result instead of being trapped.
+    assert_partial_result(&output);
+    assert!(
+        elapsed < Duration::from_millis(200 + 750),
+        "count ran for {elapsed:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn component_polling_should_cancel_sees_an_abort() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let pack = cancel_aware_pack(&temp)?;
+    let registry = Arc::new(InvocationRegistry::default());
+    let cancel = InvocationCancel::new();
+    let _registration = registry.register("corr-1", cancel.clone());
+
+    let aborter = std::thread::spawn({
+        let registry = Arc::clone(&registry);
+        move || {
+            std::thread::sleep(Duration::from_millis(100));
+            registry.abort("corr-1")
+        }
+    });
+    let output = rt.block_on(cancel::scope(
+        cancel.clone(),
+        pack.invoke_component(
+            "cancel.aware",
+            count_ctx(None),
+            "count",
+            None,
+            "{}".to_string(),
+        ),
+    ))?;
+    assert!(aborter.join().expect("aborter thread"));
+    assert_partial_result(&output);
+    assert_eq!(cancel.reason(), Some(CancelReason::AdminAbort));
+    Ok(())
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<StreamEvent>>,
+}
+
+impl StreamObserver for RecordingObserver {
+    fn on_event(&self, event: &StreamEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+#[test]
+fn lifecycle_exports_run_start_then_stop_across_reload() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let config = Arc::new(host_config(&bindings_path));
+    let pack_path = temp.path().join("lifecycle.gtpack");
+    build_lifecycle_pack(&build_component("lifecycle_recorder")?, &pack_path)?;
+
+    rt.block_on(async {
+        let session_store = new_session_store();
+        let state_store = new_state_store();
+        let runtime = TenantRuntime::load(
+            &pack_path,
+            config,
+            None,
+            Some(&pack_path),
+            None,
+            Arc::new(RunnerWasiPolicy::new()),
+            session_host_from(Arc::clone(&session_store)),
+            session_store,
+            Arc::clone(&state_store),
+            state_host_from(state_store),
+            default_manager()?,
+        )
+        .await?;
+        let active = ActivePacks::new();
+        active.replace(HashMap::from([("demo".to_string(), runtime)]));
+        active.reload_pack("demo", &pack_path, None).await?;
+        let reloaded = active.load("demo").context("demo")?;
+        assert_eq!(
+            lifecycle_history(&reloaded).await?,
+            json!(["start", "stop:reload", "start"])
+        );
+
+        active.shutdown().await;
+        assert!(active.is_empty());
+        assert_eq!(
+            lifecycle_history(&reloaded).await?,
+            json!(["start", "stop:reload", "start", "stop:shutdown"])
+        );
+        Ok(())
+    })
+}
+
+/// Calls recorded by the fixture, read with the same flow-less context the host uses
+/// for lifecycle exports.
+async fn lifecycle_history(runtime: &TenantRuntime) -> Result<Value> {
+    let ctx = ExecCtx {
+        tenant: TenantCtx {
+            tenant: "demo".into(),
+            team: None,
+            user: None,
+            trace_id: None,
+            i18n_id: None,
+            correlation_id: None,
+            deadline_unix_ms: None,
+            attempt: 1,
+            idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
+        },
+        i18n_id: None,
+        flow_id: String::new(),
+        node_id: None,
+    };
+    let output = runtime
+        .pack()
+        .invoke_component("lifecycle.recorder", ctx, "history", None, "{}".into())
+        .await?;
+    Ok(output["calls"].clone())
+}
+
 #[test]
 fn exec_node_uses_inner_component_artifact() -> Result<()> {
     // Regression: component.exec is a meta-component and must call the referenced pack artifact.
@@ -530,6 +1018,300 @@ nodes:
     Ok(())
 }
 
+fn flaky_flow_ir() -> FlowIR {
+    let mut nodes = indexmap::IndexMap::new();
+    nodes.insert(
+        "call".into(),
+        NodeIR {
+            component: "component.exec".into(),
+            payload_expr: json!({
+                "component": "flaky.node",
+                "operation": "call",
+                "input": {}
+            }),
+            routes: vec![RouteIR {
+                to: None,
+                out: true,
+            }],
+        },
+    );
+    FlowIR {
+        id: "flaky.flow".into(),
+        flow_type: "messaging".into(),
+        start: Some("call".into()),
+        parameters: Value::Object(Default::default()),
+        nodes,
+    }
+}
+
+/// Runs `flaky.flow`, whose node fails twice before it succeeds, allowing `max_attempts`.
+/// Returns the outcome and the steps of the run's trace.
+fn run_flaky_flow(max_attempts: u32) -> Result<(Result<FlowExecution>, Vec<TraceStep>)> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let config = Arc::new(host_config(&bindings_path));
+    let flow = flaky_flow_ir();
+    let pack = Arc::new(PackRuntime::for_component_test(
+        vec![("flaky.node".to_string(), build_component("flaky_node")?)],
+        HashMap::from([(flow.id.clone(), flow)]),
+        "retry-pack",
+        Arc::clone(&config),
+    )?);
+    let engine = rt.block_on(FlowEngine::new(vec![pack], Arc::clone(&config)))?;
+
+    let trace_path = temp.path().join("trace.json");
+    let recorder = TraceRecorder::new(
+        TraceConfig::from_env().with_overrides(TraceMode::Always, Some(trace_path.clone())),
+        TraceContext {
+            pack_ref: "retry-pack@0.0.0".into(),
+            resolved_digest: None,
+            flow_id: "flaky.flow".into(),
+            flow_version: "0.0.0".into(),
+        },
+    );
+    let ctx = FlowContext {
+        tenant: "demo",
+        pack_id: "retry-pack",
+        flow_id: "flaky.flow",
+        node_id: None,
+        tool: None,
+        action: None,
+        session_id: None,
+        provider_id: None,
+        retry_config: FlowRetryConfig {
+            max_attempts,
+            base_delay_ms: 1,
+            jitter: false,
+            ..FlowRetryConfig::default()
+        }
+        .into(),
+        attempt: 1,
+        observer: Some(&recorder),
+        mocks: None,
+    };
+    let outcome = rt.block_on(engine.execute(ctx, Value::Null));
+    recorder.flush_success()?;
+    let trace: TraceEnvelope = serde_json::from_slice(&std::fs::read(&trace_path)?)?;
+    Ok((outcome, trace.steps))
+}
+
+#[test]
+fn failing_node_succeeds_within_its_retry_policy() -> Result<()> {
+    let (outcome, steps) = run_flaky_flow(3)?;
+    let execution = outcome.context("flaky flow under a 3-attempt policy")?;
+    assert!(matches!(execution.status, FlowStatus::Completed));
+    // The component saw the attempt number in its exec ctx.
+    assert_eq!(execution.output["attempt"], json!(3));
+
+    assert_eq!(steps.len(), 1);
+    let step = &steps[0];
+    assert!(step.error.is_none());
+    let attempts: Vec<u32> = step.retries.iter().map(|retry| retry.attempt).collect();
+    assert_eq!(attempts, [1, 2]);
+    assert!(step.retries[1].error.contains("busy: attempt 2 rejected"));
+    assert_eq!(step.retries[1].delay_ms, 100);
+    Ok(())
+}
+
+#[test]
+fn failing_node_fails_once_its_attempts_run_out() -> Result<()> {
+    let (outcome, steps) = run_flaky_flow(1)?;
+    let err = outcome.expect_err("a single attempt cannot get past the failures");
+    assert!(err.downcast_ref::<RetriesExhausted>().is_none());
+    assert!(
+        err.to_string().contains("busy: attempt 1 rejected"),
+        "unexpected error: {err}"
+    );
+    assert!(steps[0].retries.is_empty());
+
+    let (outcome, steps) = run_flaky_flow(2)?;
+    let err = outcome.expect_err("two attempts cannot get past the failures");
+    let exhausted = err
+        .downcast_ref::<RetriesExhausted>()
+        .context("error should carry the attempts")?;
+    assert_eq!(exhausted.attempts, 2);
+    assert!(
+        err.to_string()
+            .contains("failed after 2 attempts: component flaky.node failed: busy: attempt 2"),
+        "unexpected error: {err}"
+    );
+    assert_eq!(steps[0].retries.len(), 1);
+    assert!(steps[0].error.is_some());
+    Ok(())
+}
+
+/// Time each of the two slow nodes of `fanout.flow` takes.
+const SLOW_NODE_MS: u64 = 400;
+
+/// `left` and `right` sleep and read nothing, `merge` reads both and `pause` waits
+/// before `done`.
+fn fanout_flow_ir() -> FlowIR {
+    let slow = |tag: &str, to: &str| NodeIR {
+        component: "component.exec".into(),
+        payload_expr: json!({
+            "component": "slow.node",
+            "operation": "sleep",
+            "input": { "ms": SLOW_NODE_MS, "tag": tag }
+        }),
+        routes: vec![RouteIR {
+            to: Some(to.into()),
+            out: false,
+        }],
+    };
+    let mut nodes = indexmap::IndexMap::new();
+    nodes.insert("left".into(), slow("left", "right"));
+    nodes.insert("right".into(), slow("right", "merge"));
+    nodes.insert(
+        "merge".into(),
+        NodeIR {
+            component: "component.exec".into(),
+            payload_expr: json!({
+                "component": "qa.process",
+                "operation": "process",
+                "input": { "left": "{{node.left.tag}}", "right": "{{node.right.tag}}" }
+            }),
+            routes: vec![RouteIR {
+                to: Some("pause".into()),
+                out: false,
+            }],
+        },
+    );
+    nodes.insert(
+        "pause".into(),
+        NodeIR {
+            component: "session.wait".into(),
+            payload_expr: json!({ "reason": "merged" }),
+            routes: vec![RouteIR {
+                to: Some("done".into()),
+                out: false,
+            }],
+        },
+    );
+    nodes.insert(
+        "done".into(),
+        NodeIR {
+            component: "emit.response".into(),
+            payload_expr: json!({ "merged": "{{node.merge.left}}" }),
+            routes: vec![RouteIR {
+                to: None,
+                out: true,
+            }],
+        },
+    );
+    FlowIR {
+        id: "fanout.flow".into(),
+        flow_type: "messaging".into(),
+        start: Some("left".into()),
+        parameters: Value::Object(Default::default()),
+        nodes,
+    }
+}
+
+/// Runs `fanout.flow` up to its wait; returns the snapshot state and how long it took.
+fn run_fanout_flow(max_parallel_nodes: Option<usize>) -> Result<(Value, Duration)> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let mut config = host_config(&bindings_path);
+    config.rate_limits.max_parallel_nodes = max_parallel_nodes;
+    let config = Arc::new(config);
+    let flow = fanout_flow_ir();
+    let pack = Arc::new(PackRuntime::for_component_test(
+        vec![
+            ("slow.node".to_string(), build_component("slow_node")?),
+            ("qa.process".to_string(), build_component("qa_process")?),
+        ],
+        HashMap::from([(flow.id.clone(), flow)]),
+        "fanout-pack",
+        Arc::clone(&config),
+    )?);
+    let engine = rt.block_on(FlowEngine::new(vec![pack], Arc::clone(&config)))?;
+    let ctx = FlowContext {
+        tenant: "demo",
+        pack_id: "fanout-pack",
+        flow_id: "fanout.flow",
+        node_id: None,
+        tool: None,
+        action: None,
+        session_id: None,
+        provider_id: None,
+        retry_config: config.retry_config().into(),
+        attempt: 1,
+        observer: None,
+        mocks: None,
+    };
+    let started = Instant::now();
+    let execution = rt.block_on(engine.execute(ctx, json!({ "text": "go" })))?;
+    let elapsed = started.elapsed();
+    let FlowStatus::Waiting(wait) = execution.status else {
+        anyhow::bail!("fanout flow should wait at `pause`");
+    };
+    assert_eq!(wait.snapshot.next_node, "done");
+    Ok((serde_json::to_value(&wait.snapshot.state)?, elapsed))
+}
+
+#[test]
+fn independent_nodes_run_in_parallel() -> Result<()> {
+    let (sequential, sequential_elapsed) = run_fanout_flow(None)?;
+    let (parallel, parallel_elapsed) = run_fanout_flow(Some(4))?;
+    assert_eq!(parallel, sequential);
+    assert_eq!(parallel["nodes"]["merge"]["payload"]["right"], "right");
+
+    let slow = Duration::from_millis(SLOW_NODE_MS);
+    assert!(sequential_elapsed >= 2 * slow, "{sequential_elapsed:?}");
+    assert!(parallel_elapsed >= slow, "{parallel_elapsed:?}");
+    assert!(
+        parallel_elapsed + slow / 2 < sequential_elapsed,
+        "parallel run took {parallel_elapsed:?}, sequential {sequential_elapsed:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn reference_cycle_fails_the_load_without_parallel_nodes() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let config = Arc::new(host_config(&bindings_path));
+    let emit = |payload: Value, to: Option<&str>| NodeIR {
+        component: "emit.response".into(),
+        payload_expr: payload,
+        routes: vec![RouteIR {
+            to: to.map(str::to_string),
+            out: to.is_none(),
+        }],
+    };
+    let mut nodes = indexmap::IndexMap::new();
+    nodes.insert("a".into(), emit(json!({ "x": "{{node.b.x}}" }), Some("b")));
+    nodes.insert("b".into(), emit(json!({ "x": "{{node.a.x}}" }), None));
+    let flow = FlowIR {
+        id: "cycle.flow".into(),
+        flow_type: "messaging".into(),
+        start: Some("a".into()),
+        parameters: Value::Object(Default::default()),
+        nodes,
+    };
+    let pack = Arc::new(PackRuntime::for_component_test(
+        Vec::new(),
+        HashMap::from([(flow.id.clone(), flow)]),
+        "cycle-pack",
+        Arc::clone(&config),
+    )?);
+    let Err(err) = rt.block_on(FlowEngine::new(vec![pack], config)) else {
+        anyhow::bail!("a flow with a reference cycle must not load");
+    };
+    let message = format!("{err:#}");
+    assert!(
+        message.contains("flow cycle.flow has a node dependency cycle: a -> b -> a"),
+        "unexpected error: {message}"
+    );
+    Ok(())
+}
+
 #[test]
 fn component_exec_always_serializes_invocation_envelope() -> Result<()> {
     let rt = *RUNTIME;
@@ -895,3 +1677,287 @@ fn envelope_payload(value: &Value) -> Result<Value> {
         .context("envelope payload missing")?;
     decode_binary_value(payload_value)
 }
+
+/// Host config of `demo` running `ticks.flow` every second for each of `schedule_ids`.
+fn tick_config(bindings_path: &Path, schedule_ids: &[&str]) -> Arc<HostConfig> {
+    let mut config = host_config(bindings_path);
+    config.timers = schedule_ids
+        .iter()
+        .map(|schedule_id| TimerBinding {
+            flow_id: "ticks.flow".into(),
+            pack_id: None,
+            cron: None,
+            interval_secs: Some(1),
+            schedule_id: Some((*schedule_id).into()),
+            input: Value::Null,
+            overlap: TimerOverlap::Skip,
+            catch_up: false,
+        })
+        .collect();
+    Arc::new(config)
+}
+
+/// How many times `ticks.flow` ran, as counted by the fixture in the state store.
+async fn tick_count(runtime: &TenantRuntime) -> Result<u64> {
+    let ctx = ExecCtx {
+        tenant: TenantCtx {
+            tenant: "demo".into(),
+            team: None,
+            user: None,
+            trace_id: None,
+            i18n_id: None,
+            correlation_id: None,
+            deadline_unix_ms: None,
+            attempt: 1,
+            idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
+        },
+        i18n_id: None,
+        flow_id: String::new(),
+        node_id: None,
+    };
+    let output = runtime
+        .pack()
+        .invoke_component("tick.counter", ctx, "count", None, "{}".into())
+        .await?;
+    output["ticks"].as_u64().context("tick count")
+}
+
+#[test]
+fn interval_timers_run_until_a_reload_removes_them() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+    let pack_path = temp.path().join("ticks.gtpack");
+    build_tick_pack(&build_component("tick_counter")?, &pack_path)?;
+
+    rt.block_on(async {
+        let session_store = new_session_store();
+        let state_store = new_state_store();
+        let secrets = default_manager()?;
+        let load = |config: Arc<HostConfig>| {
+            TenantRuntime::load(
+                &pack_path,
+                config,
+                None,
+                Some(&pack_path),
+                None,
+                Arc::new(RunnerWasiPolicy::new()),
+                session_host_from(Arc::clone(&session_store)),
+                Arc::clone(&session_store),
+                Arc::clone(&state_store),
+                state_host_from(Arc::clone(&state_store)),
+                Arc::clone(&secrets),
+            )
+        };
+        let active = ActivePacks::new();
+        let runtime = load(tick_config(&bindings_path, &["every-second"])).await?;
+        active
+            .replace_and_stop(HashMap::from([("demo".to_string(), Arc::clone(&runtime))]))
+            .await;
+        assert_eq!(runtime.timer_ids(), ["every-second"]);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while tick_count(&runtime).await? < 2 {
+            assert!(Instant::now() < deadline, "timer did not run twice");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let reloaded = load(tick_config(&bindings_path, &[])).await?;
+        active
+            .replace_and_stop(HashMap::from([("demo".to_string(), Arc::clone(&reloaded))]))
+            .await;
+        assert!(reloaded.timer_ids().is_empty());
+        assert!(runtime.timer_ids().is_empty());
+
+        // A run that was already going may still finish.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let stopped_at = tick_count(&reloaded).await?;
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(tick_count(&reloaded).await?, stopped_at);
+        Ok(())
+    })
+}
+
+/// `tenant` delivering each envelope up to `max_attempts` times; nodes do not retry, so
+/// every failure of `flaky.flow` reaches redelivery.
+fn redelivery_config(bindings_path: &Path, tenant: &str, max_attempts: u32) -> Arc<HostConfig> {
+    let mut config = host_config(bindings_path);
+    config.tenant = tenant.into();
+    config.retry = FlowRetryConfig {
+        max_attempts: 1,
+        redelivery: Some(RedeliveryConfig {
+            max_attempts,
+            base_delay_ms: 50,
+            max_delay_ms: 200,
+        }),
+        ..FlowRetryConfig::default()
+    };
+    Arc::new(config)
+}
+
+fn flaky_envelope(tenant: &str, activity_id: &str) -> IngressEnvelope {
+    IngressEnvelope {
+        tenant: tenant.into(),
+        env: None,
+        pack_id: Some("component.flaky".into()),
+        flow_id: "flaky.flow".into(),
+        flow_type: Some("messaging".into()),
+        action: Some("messaging".into()),
+        session_hint: Some(activity_id.into()),
+        provider: Some("telegram".into()),
+        channel: Some("chat-1".into()),
+        conversation: Some("chat-1".into()),
+        user: Some("user-1".into()),
+        activity_id: Some(activity_id.into()),
+        timestamp: None,
+        payload: json!({ "text": "hi" }),
+        metadata: None,
+        reply_scope: None,
+    }
+    .canonicalize()
+}
+
+/// Serve `flaky.flow` for `config`'s tenant, with its redelivery worker running.
+async fn serve_flaky_tenant(
+    pack_path: &Path,
+    config: Arc<HostConfig>,
+) -> Result<(ActivePacks, Arc<TenantRuntime>)> {
+    let session_store = new_session_store();
+    let state_store = new_state_store();
+    let runtime = TenantRuntime::load(
+        pack_path,
+        Arc::clone(&config),
+        None,
+        Some(pack_path),
+        None,
+        Arc::new(RunnerWasiPolicy::new()),
+        session_host_from(Arc::clone(&session_store)),
+        session_store,
+        Arc::clone(&state_store),
+        state_host_from(state_store),
+        default_manager()?,
+    )
+    .await?;
+    let active = ActivePacks::new();
+    active
+        .replace_and_stop(HashMap::from([(
+            config.tenant.clone(),
+            Arc::clone(&runtime),
+        )]))
+        .await;
+    Ok((active, runtime))
+}
+
+async fn wait_for_redeliveries(runtime: &TenantRuntime) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !runtime.redelivery().pending().await?.is_empty() {
+        assert!(Instant::now() < deadline, "redeliveries did not settle");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    Ok(())
+}
+
+fn build_flaky_pack(temp: &TempDir) -> Result<PathBuf> {
+    let pack_path = temp.path().join("flaky.gtpack");
+    build_single_node_pack(
+        "component.flaky",
+        "flaky.flow",
+        ("flaky.node", "call"),
+        &build_component("flaky_node")?,
+        &pack_path,
+    )?;
+    Ok(pack_path)
+}
+
+#[test]
+fn failed_runs_are_redelivered_until_they_succeed() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: redelivered")?;
+    let pack_path = build_flaky_pack(&temp)?;
+    let tenant = "redelivered";
+
+    rt.block_on(async {
+        let config = redelivery_config(&bindings_path, tenant, 3);
+        let (_active, runtime) = serve_flaky_tenant(&pack_path, config).await?;
+
+        let first = runtime
+            .state_machine()
+            .handle(flaky_envelope(tenant, "update-1"))
+            .await?;
+        assert_eq!(first["status"], STATUS_REDELIVERY_SCHEDULED);
+        assert_eq!(first["attempt"], json!(1));
+        wait_for_redeliveries(&runtime).await?;
+
+        // Deliveries 1 and 2 failed and were scheduled again; the third got through.
+        let metrics = redelivery_metrics();
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Scheduled), 2);
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Succeeded), 1);
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Exhausted), 0);
+
+        // The original activity now answers with the response of the third delivery,
+        // whose component saw it as attempt 3.
+        let replay = runtime
+            .state_machine()
+            .handle(flaky_envelope(tenant, "update-1"))
+            .await?;
+        assert!(is_duplicate(&replay), "{replay}");
+        assert_eq!(replay["response"]["attempt"], json!(3));
+        Ok(())
+    })
+}
+
+#[test]
+fn redeliveries_stop_after_max_attempts() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: exhausted")?;
+    let pack_path = build_flaky_pack(&temp)?;
+    let tenant = "exhausted";
+
+    rt.block_on(async {
+        let config = redelivery_config(&bindings_path, tenant, 2);
+        let (_active, runtime) = serve_flaky_tenant(&pack_path, config).await?;
+        let sink = Arc::new(MemoryAuditSink::default());
+        runtime.audit().set_sink(sink.clone());
+
+        let first = runtime
+            .state_machine()
+            .handle(flaky_envelope(tenant, "update-2"))
+            .await?;
+        assert_eq!(first["status"], STATUS_REDELIVERY_SCHEDULED);
+        wait_for_redeliveries(&runtime).await?;
+
+        let metrics = redelivery_metrics();
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Scheduled), 1);
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Exhausted), 1);
+        assert_eq!(metrics.count(tenant, RedeliveryOutcome::Succeeded), 0);
+
+        let events = sink.events();
+        let [AuditEvent::RedeliveryExhausted(event)] = events.as_slice() else {
+            panic!("expected one redelivery_exhausted event, got {events:?}");
+        };
+        assert_eq!(event.tenant, tenant);
+        assert_eq!(event.flow_id, "flaky.flow");
+        assert_eq!(event.activity_id.as_deref(), Some("update-2"));
+        assert_eq!(event.attempts, 2);
+        assert!(
+            event.error.contains("attempt 2 rejected"),
+            "unexpected error: {}",
+            event.error
+        );
+
+        // The activity was released, so the provider's next delivery runs the flow.
+        let retried = runtime
+            .state_machine()
+            .handle(flaky_envelope(tenant, "update-2"))
+            .await?;
+        assert!(!is_duplicate(&retried), "{retried}");
+        Ok(())
+    })
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use greentic_runner_host::{
+    HostServer, ListenAddr, ListenerConfig, RouteGroup, RunnerWasiPolicy,
+    config::{HostConfig, OperatorPolicy, SecretsPolicy},
+    engine::{FlowSchema, FlowSummary, RunFlowRequest, RunFlowResult},
+    http::auth::AdminAuth,
+    http::health::HealthState,
+    routing::{RoutingConfig, TenantRouting},
+    runtime::{ActivePacks, TenantRuntime},
+    secrets::default_manager,
+    storage::{new_session_store, new_state_store, session_host_from, state_host_from},
+};
+use greentic_types::{EnvId, TenantCtx, TenantId};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+const PACK_ID: &str = "runner.components";
+const FLOW_ID: &str = "demo.flow";
+
+fn fixture_pack() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../tests/fixtures/packs/runner-components/runner-components.gtpack")
+}
+
+fn tenant_config(workspace: &Path, tenant: &str) -> Result<Arc<HostConfig>> {
+    let bindings_path = workspace.join(format!("{tenant}.bindings.yaml"));
+    std::fs::write(
+        &bindings_path,
+        format!(
+            r#"
+tenant: {tenant}
+flow_type_bindings: {{}}
+rate_limits: {{}}
+retry: {{}}
+timers: []
+"#
+        ),
+    )?;
+    let mut config =
+        HostConfig::load_from_path(&bindings_path).context("load minimal host bindings")?;
+    config.secrets_policy = SecretsPolicy::allow_all();
+    config.operator_policy = OperatorPolicy::allow_all();
+    Ok(Arc::new(config))
+}
+
+async fn serve_demo(workspace: &Path) -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()>>)> {
+    let pack_path = fixture_pack();
+    let session_store = new_session_store();
+    let state_store = new_state_store();
+    let runtime = TenantRuntime::load(
+        &pack_path,
+        tenant_config(workspace, "demo")?,
+        None,
+        Some(&pack_path),
+        None,
+        Arc::new(RunnerWasiPolicy::new()),
+        session_host_from(Arc::clone(&session_store)),
+        session_store,
+        Arc::clone(&state_store),
+        state_host_from(state_store),
+        default_manager()?,
+    )
+    .await?;
+    let active = Arc::new(ActivePacks::new());
+    active.replace(HashMap::from([("demo".to_string(), runtime)]));
+
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Operator],
+        )],
+        active,
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    Ok((addr, tokio::spawn(server.serve())))
+}
+
+fn run_request(tenant: &str, input: Value) -> Result<RunFlowRequest> {
+    Ok(RunFlowRequest {
+        tenant: TenantCtx::new(EnvId::from_str("local")?, TenantId::from_str(tenant)?),
+        pack_id: PACK_ID.into(),
+        flow_id: FLOW_ID.into(),
+        input,
+        session_hint: Some("flow-api-test".into()),
+    })
+}
+
+#[tokio::test]
+async fn lists_describes_and_runs_flows() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let (addr, serving) = serve_demo(workspace.path()).await?;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("http://{addr}/api/flows"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let flows: Vec<FlowSummary> = response.json().await?;
+    let ids: Vec<(&str, &str)> = flows
+        .iter()
+        .map(|flow| (flow.pack_id.as_str(), flow.id.as_str()))
+        .collect();
+    assert_eq!(ids, [(PACK_ID, FLOW_ID)]);
+
+    let response = client
+        .get(format!(
+            "http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/schema"
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let schema: FlowSchema = response.json().await?;
+    assert_eq!(schema.id, FLOW_ID);
+    assert_eq!(schema.schema_json["type"], "object");
+
+    let response = client
+        .get(format!(
+            "http://{addr}/api/flows/{PACK_ID}/missing.flow/schema"
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+    let body: Value = response.json().await?;
+    assert!(
+        body["error"]
+            .as_str()
+            .is_some_and(|e| e.contains("missing.flow"))
+    );
+
+    let response = client
+        .post(format!("http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/run"))
+        .json(&run_request("demo", json!({ "text": "hi" }))?)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let result: RunFlowResult = response.json().await?;
+    assert!(
+        result.outcome.to_string().contains("Echo: hello"),
+        "unexpected outcome: {}",
+        result.outcome
+    );
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_requests_are_checked_against_the_route() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let (addr, serving) = serve_demo(workspace.path()).await?;
+    let client = reqwest::Client::new();
+    let run_url = format!("http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/run");
+
+    let response = client
+        .post(&run_url)
+        .json(&run_request("other", json!({}))?)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 403);
+    let body: Value = response.json().await?;
+    assert!(
+        body["error"]
+            .as_str()
+            .is_some_and(|e| e.contains("tenant mismatch")),
+        "{body}"
+    );
+
+    let response = client
+        .post(format!("http://{addr}/api/flows/{PACK_ID}/other.flow/run"))
+        .json(&run_request("demo", json!({}))?)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 400);
+
+    let mut unknown = run_request("demo", json!({}))?;
+    unknown.flow_id = "missing.flow".into();
+    let response = client
+        .post(format!(
+            "http://{addr}/api/flows/{PACK_ID}/missing.flow/run"
+        ))
+        .json(&unknown)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    let response = client.post(&run_url).body("not json").send().await?;
+    assert_eq!(response.status(), 400);
+
+    serving.abort();
+    Ok(())
+}
+
+/// `(event, data)` of every SSE frame in `body`, keep-alive comments skipped.
+fn sse_frames(body: &str) -> Result<Vec<(String, Value)>> {
+    let mut frames = Vec::new();
+    for block in body.split("\n\n").filter(|block| !block.trim().is_empty()) {
+        let mut event = None;
+        let mut data = None;
+        for line in block.lines() {
+            if let Some(name) = line.strip_prefix("event:") {
+                event = Some(name.trim().to_string());
+            } else if let Some(payload) = line.strip_prefix("data:") {
+                data = Some(serde_json::from_str(payload.trim())?);
+            }
+        }
+        if let (Some(event), Some(data)) = (event, data) {
+            frames.push((event, data));
+        }
+    }
+    Ok(frames)
+}
+
+#[tokio::test]
+async fn streamed_runs_report_each_node() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let (addr, serving) = serve_demo(workspace.path()).await?;
+    let mut request = run_request("demo", json!({ "text": "hi" }))?;
+    request.session_hint = Some("flow-api-stream".into());
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/run?stream=true"
+        ))
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"))
+    );
+    let frames = sse_frames(&response.text().await?)?;
+
+    let steps: Vec<(&str, &str)> = frames
+        .iter()
+        .map(|(event, data)| {
+            assert_eq!(data["event"], event.as_str());
+            (event.as_str(), data["node_id"].as_str().unwrap_or(""))
+        })
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("flow-started", ""),
+            ("node-started", "qa"),
+            ("node-finished", "qa"),
+            ("node-started", "emit"),
+            ("egress", "emit"),
+            ("node-finished", "emit"),
+            ("flow-finished", ""),
+        ]
+    );
+    let qa_finished = &frames[2].1;
+    assert!(qa_finished["output_bytes"].as_u64().unwrap_or(0) > 0);
+    assert!(qa_finished["duration_ms"].is_u64());
+    assert!(qa_finished.get("error").is_none());
+
+    let (_, terminal) = frames.last().context("terminal event")?;
+    let result: RunFlowResult = serde_json::from_value(terminal["result"].clone())?;
+    assert!(
+        result.outcome.to_string().contains("Echo: hello"),
+        "unexpected outcome: {}",
+        result.outcome
+    );
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn streamed_runs_end_with_flow_error() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let (addr, serving) = serve_demo(workspace.path()).await?;
+    let mut request = run_request("demo", json!({}))?;
+    request.flow_id = "missing.flow".into();
+
+    let body = reqwest::Client::new()
+        .post(format!(
+            "http://{addr}/api/flows/{PACK_ID}/missing.flow/run?stream=true"
+        ))
+        .json(&request)
+        .send()
+        .await?
+        .text()
+        .await?;
+    let frames = sse_frames(&body)?;
+    let events: Vec<&str> = frames.iter().map(|(event, _)| event.as_str()).collect();
+    assert_eq!(events, ["flow-started", "flow-error"]);
+    assert_eq!(frames[1].1["status"], 404);
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn traced_runs_can_be_read_back() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let (addr, serving) = serve_demo(workspace.path()).await?;
+    let client = reqwest::Client::new();
+    let secret = "sk-live-4f9a1c2e7b";
+    let mut request = run_request(
+        "demo",
+        json!({ "text": "hi", "_attachments": { "api_token": secret } }),
+    )?;
+    request.session_hint = Some("flow-api-trace".into());
+
+    let response = client
+        .post(format!(
+            "http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/run?trace=true"
+        ))
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let result: RunFlowResult = response.json().await?;
+    let run_id = result
+        .run_id
+        .context("traced run should report its run id")?;
+
+    let response = client
+        .get(format!("http://{addr}/api/runs/{run_id}/trace"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let body = response.text().await?;
+    assert!(!body.contains(secret), "secret leaked into trace: {body}");
+    assert!(body.contains("[REDACTED:api_token]"), "{body}");
+
+    let trace: Value = serde_json::from_str(&body)?;
+    assert_eq!(trace["run_id"], run_id.as_str());
+    assert_eq!(trace["status"], "completed");
+    let nodes = trace["nodes"].as_array().context("trace nodes")?;
+    let ids: Vec<&str> = nodes
+        .iter()
+        .map(|node| node["node_id"].as_str().unwrap_or(""))
+        .collect();
+    assert_eq!(ids, ["qa", "emit"]);
+    for node in nodes {
+        assert!(
+            !node["output"].is_null() && node["output"] != json!({}),
+            "node without output: {node}"
+        );
+        assert!(node["ended_at_ms"].as_u64() >= node["started_at_ms"].as_u64());
+    }
+
+    let response = client
+        .get(format!("http://{addr}/api/runs/run-missing/trace"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    // Untraced runs store nothing.
+    let response = client
+        .post(format!("http://{addr}/api/flows/{PACK_ID}/{FLOW_ID}/run"))
+        .json(&run_request("demo", json!({ "text": "hi" }))?)
+        .send()
+        .await?;
+    let result: RunFlowResult = response.json().await?;
+    assert!(result.run_id.is_none());
+
+    serving.abort();
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+use greentic_runner_host::{
+    RunnerWasiPolicy,
+    config::HostConfig,
+    engine::dedupe::is_duplicate,
+    engine::runtime::IngressEnvelope,
+    runner::engine::{ExecutionObserver, NodeEvent},
+    runner::progress,
+    runtime::TenantRuntime,
+    secrets::default_manager,
+    storage::{new_session_store, new_state_store, session_host_from, state_host_from},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+/// Counts flow runs by the nodes they start.
+#[derive(Default)]
+struct NodeStarts(AtomicUsize);
+
+impl ExecutionObserver for NodeStarts {
+    fn on_node_start(&self, _event: &NodeEvent<'_>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_node_end(&self, _event: &NodeEvent<'_>, _output: &Value) {}
+
+    fn on_node_error(&self, _event: &NodeEvent<'_>, _error: &dyn StdError) {}
+}
+
+fn fixture_pack() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../tests/fixtures/packs/runner-components/runner-components.gtpack")
+}
+
+async fn load_demo(workspace: &Path, sessions: &str) -> Result<Arc<TenantRuntime>> {
+    let bindings_path = workspace.join("demo.bindings.yaml");
+    std::fs::write(&bindings_path, format!("tenant: demo\n{sessions}"))?;
+    let config = HostConfig::load_from_path(&bindings_path).context("load host bindings")?;
+    let pack_path = fixture_pack();
+    let session_store = new_session_store();
+    let state_store = new_state_store();
+    TenantRuntime::load(
+        &pack_path,
+        Arc::new(config),
+        None,
+        Some(&pack_path),
+        None,
+        Arc::new(RunnerWasiPolicy::new()),
+        session_host_from(Arc::clone(&session_store)),
+        session_store,
+        Arc::clone(&state_store),
+        state_host_from(state_store),
+        default_manager()?,
+    )
+    .await
+}
+
+/// A Telegram message; each `session` gets its own conversation state.
+fn envelope(activity_id: Option<&str>, session: &str) -> IngressEnvelope {
+    IngressEnvelope {
+        tenant: "demo".into(),
+        env: None,
+        pack_id: Some("runner.components".into()),
+        flow_id: "demo.flow".into(),
+        flow_type: Some("messaging".into()),
+        action: Some("messaging".into()),
+        session_hint: Some(session.into()),
+        provider: Some("telegram".into()),
+        channel: Some("chat-1".into()),
+        conversation: Some("chat-1".into()),
+        user: Some(format!("user-{session}")),
+        activity_id: activity_id.map(str::to_string),
+        timestamp: None,
+        payload: json!({ "text": "hi" }),
+        metadata: None,
+        reply_scope: None,
+    }
+    .canonicalize()
+}
+
+/// Handle each envelope in turn; returns the responses and how many nodes ran.
+async fn dispatch(
+    runtime: &TenantRuntime,
+    envelopes: Vec<IngressEnvelope>,
+) -> Result<(Vec<Value>, usize)> {
+    let starts = Arc::new(NodeStarts::default());
+    let responses = progress::scope(starts.clone(), async {
+        let mut responses = Vec::new();
+        for envelope in envelopes {
+            responses.push(runtime.state_machine().handle(envelope).await?);
+        }
+        anyhow::Ok(responses)
+    })
+    .await?;
+    Ok((responses, starts.0.load(Ordering::SeqCst)))
+}
+
+#[tokio::test]
+async fn redelivered_activity_runs_the_flow_once() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = load_demo(workspace.path(), "").await?;
+    let (_, per_run) = dispatch(&runtime, vec![envelope(None, "warm-up")]).await?;
+    assert!(per_run > 0, "a delivery should run the flow");
+
+    let redelivery = envelope(Some("update-42"), "chat-1");
+    let (responses, nodes) = dispatch(&runtime, vec![redelivery.clone(), redelivery]).await?;
+    assert_eq!(nodes, per_run, "the redelivery must not run the flow");
+    assert!(!is_duplicate(&responses[0]), "{}", responses[0]);
+    assert!(is_duplicate(&responses[1]), "{}", responses[1]);
+    assert_eq!(responses[1]["activity_id"], "update-42");
+    assert_eq!(responses[1]["provider"], "telegram");
+    assert_eq!(responses[1]["response"], responses[0]);
+
+    // The key is the activity, not the conversation: a redelivery is caught even when
+    // the provider's session details differ, and a new activity still runs.
+    let (responses, nodes) = dispatch(
+        &runtime,
+        vec![
+            envelope(Some("update-42"), "chat-2"),
+            envelope(Some("update-43"), "chat-3"),
+        ],
+    )
+    .await?;
+    assert_eq!(nodes, per_run);
+    assert!(is_duplicate(&responses[0]));
+    assert!(!is_duplicate(&responses[1]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn envelopes_without_activity_id_always_run() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = load_demo(workspace.path(), "").await?;
+
+    let (_, single) = dispatch(&runtime, vec![envelope(None, "chat-1")]).await?;
+    let (responses, double) = dispatch(
+        &runtime,
+        vec![envelope(None, "chat-2"), envelope(None, "chat-3")],
+    )
+    .await?;
+    assert_eq!(double, 2 * single);
+    assert!(responses.iter().all(|value| !is_duplicate(value)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenants_can_turn_deduplication_off() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = load_demo(workspace.path(), "sessions:\n  dedupe_activities: false\n").await?;
+
+    let (_, single) = dispatch(&runtime, vec![envelope(None, "chat-1")]).await?;
+    let (responses, double) = dispatch(
+        &runtime,
+        vec![
+            envelope(Some("update-42"), "chat-2"),
+            envelope(Some("update-42"), "chat-3"),
+        ],
+    )
+    .await?;
+    assert_eq!(double, 2 * single);
+    assert!(responses.iter().all(|value| !is_duplicate(value)));
+    Ok(())
+}
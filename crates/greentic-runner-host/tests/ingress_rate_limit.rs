@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use greentic_runner_host::{
+    RunnerWasiPolicy,
+    config::HostConfig,
+    engine::rate_limit::{RateLimited, STATUS_RATE_LIMITED, ingress_limits, rate_limited},
+    engine::runtime::IngressEnvelope,
+    runtime::TenantRuntime,
+    secrets::default_manager,
+    storage::{new_session_store, new_state_store, session_host_from, state_host_from},
+};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+fn fixture_pack() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../tests/fixtures/packs/runner-components/runner-components.gtpack")
+}
+
+/// `tenant` with `flows` as its `rate_limits.flows` block.
+async fn load_tenant(workspace: &Path, tenant: &str, flows: &str) -> Result<Arc<TenantRuntime>> {
+    let bindings_path = workspace.join(format!("{tenant}.bindings.yaml"));
+    std::fs::write(
+        &bindings_path,
+        format!("tenant: {tenant}\nrate_limits:\n  flows:\n{flows}"),
+    )?;
+    let config = HostConfig::load_from_path(&bindings_path).context("load host bindings")?;
+    let pack_path = fixture_pack();
+    let session_store = new_session_store();
+    let state_store = new_state_store();
+    let runtime = TenantRuntime::load(
+        &pack_path,
+        Arc::new(config),
+        None,
+        Some(&pack_path),
+        None,
+        Arc::new(RunnerWasiPolicy::new()),
+        session_host_from(Arc::clone(&session_store)),
+        session_store,
+        Arc::clone(&state_store),
+        state_host_from(state_store),
+        default_manager()?,
+    )
+    .await?;
+    // Compile the flow's components under a flow type no limit applies to.
+    runtime
+        .state_machine()
+        .handle(envelope(tenant, "warm-up", None))
+        .await?;
+    Ok(runtime)
+}
+
+fn envelope(tenant: &str, session: &str, flow_type: Option<&str>) -> IngressEnvelope {
+    IngressEnvelope {
+        tenant: tenant.into(),
+        env: None,
+        pack_id: Some("runner.components".into()),
+        flow_id: "demo.flow".into(),
+        flow_type: flow_type.map(str::to_string),
+        action: Some("messaging".into()),
+        session_hint: Some(session.into()),
+        provider: Some("telegram".into()),
+        channel: Some(session.into()),
+        conversation: Some(session.into()),
+        user: Some(format!("user-{session}")),
+        activity_id: None,
+        timestamp: None,
+        payload: json!({ "text": "hi" }),
+        metadata: None,
+        reply_scope: None,
+    }
+    .canonicalize()
+}
+
+/// Handle `count` `messaging` envelopes at once; returns the runs and the refusals.
+async fn burst(
+    runtime: &TenantRuntime,
+    tenant: &str,
+    count: usize,
+) -> (Vec<Value>, Vec<RateLimited>) {
+    let outcomes = join_all((0..count).map(|n| {
+        runtime
+            .state_machine()
+            .handle(envelope(tenant, &format!("chat-{n}"), Some("messaging")))
+    }))
+    .await;
+    let mut executed = Vec::new();
+    let mut refused = Vec::new();
+    for outcome in outcomes {
+        match outcome {
+            Ok(value) => executed.push(value),
+            Err(err) => refused.push(
+                rate_limited(&err)
+                    .unwrap_or_else(|| panic!("unexpected failure: {err:#}"))
+                    .clone(),
+            ),
+        }
+    }
+    (executed, refused)
+}
+
+#[tokio::test]
+async fn envelopes_over_the_flow_rate_are_rejected() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = load_tenant(
+        workspace.path(),
+        "limited",
+        "    messaging:\n      per_second: 2\n",
+    )
+    .await?;
+
+    let (executed, refused) = burst(&runtime, "limited", 5).await;
+    assert_eq!(executed.len(), 2);
+    assert_eq!(refused.len(), 3);
+    for limited in &refused {
+        assert_eq!(limited.flow, "messaging");
+        assert!(limited.retry_after > Duration::ZERO);
+        assert!(limited.retry_after_secs() >= 1);
+        let outcome = limited.outcome();
+        assert_eq!(outcome["status"], STATUS_RATE_LIMITED);
+        assert_eq!(outcome["tenant"], "limited");
+    }
+
+    let levels = ingress_limits().tenant("limited");
+    assert_eq!(levels.len(), 1, "{levels:?}");
+    assert_eq!(levels[0].flow, "messaging");
+    assert_eq!(levels[0].burst, 2.0);
+    assert_eq!((levels[0].admitted, levels[0].rejected), (2, 3));
+    assert!(levels[0].tokens < 1.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_envelopes_wait_for_a_token() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = load_tenant(
+        workspace.path(),
+        "queued",
+        "    messaging:\n      per_second: 2\n      queue: 2\n",
+    )
+    .await?;
+
+    let started = Instant::now();
+    let (executed, refused) = burst(&runtime, "queued", 5).await;
+    assert_eq!(executed.len(), 4);
+    assert_eq!(refused.len(), 1);
+    // The second queued envelope waits for the token the bucket refills after a second.
+    assert!(started.elapsed() >= Duration::from_millis(900));
+
+    let levels = ingress_limits().tenant("queued");
+    assert_eq!(levels[0].admitted, 4);
+    assert_eq!(levels[0].queued, 2);
+    assert_eq!(levels[0].rejected, 1);
+    Ok(())
+}
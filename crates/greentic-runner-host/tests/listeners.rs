@@ -0,0 +1,171 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use greentic_runner_host::http::auth::AdminAuth;
+use greentic_runner_host::http::health::HealthState;
+use greentic_runner_host::routing::{RoutingConfig, TENANT_HEADER, TenantRouting};
+use greentic_runner_host::runtime::ActivePacks;
+use greentic_runner_host::{HostServer, ListenAddr, ListenerConfig, RouteGroup};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn listeners_only_serve_their_route_groups() -> Result<()> {
+    let loopback = ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0)));
+    let server = HostServer::with_listeners(
+        vec![
+            ListenerConfig::new(loopback.clone(), [RouteGroup::Operator]),
+            ListenerConfig::new(loopback, [RouteGroup::Admin, RouteGroup::Health]),
+        ],
+        Arc::new(ActivePacks::new()),
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let [operator, admin] = tcp_addrs(server.local_addrs()?)?;
+    let serving = tokio::spawn(server.serve());
+
+    let client = reqwest::Client::new();
+    let status = |addr: SocketAddr, path: &'static str| {
+        let client = client.clone();
+        async move {
+            client
+                .get(format!("http://{addr}{path}"))
+                .send()
+                .await
+                .map(|response| response.status())
+        }
+    };
+
+    // GET on the POST-only invoke route proves the route exists on this listener.
+    assert_eq!(
+        status(operator, "/operator/op/invoke").await?,
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+    assert_eq!(
+        status(operator, "/admin/packs/status").await?,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(status(operator, "/healthz").await?, StatusCode::NOT_FOUND);
+
+    assert_eq!(status(admin, "/admin/packs/status").await?, StatusCode::OK);
+    assert_eq!(status(admin, "/healthz").await?, StatusCode::OK);
+    assert_eq!(
+        status(admin, "/operator/op/invoke").await?,
+        StatusCode::NOT_FOUND
+    );
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn bind_failure_on_any_listener_aborts() -> Result<()> {
+    let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let result = HostServer::with_listeners(
+        vec![
+            ListenerConfig::new(
+                ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+                [RouteGroup::Health],
+            ),
+            ListenerConfig::new(ListenAddr::Tcp(taken.local_addr()?), [RouteGroup::Admin]),
+        ],
+        Arc::new(ActivePacks::new()),
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await;
+    assert!(result.is_err(), "second listener address is already in use");
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_tenants_leave_no_in_flight_entries() -> Result<()> {
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Operator, RouteGroup::Metrics],
+        )],
+        Arc::new(ActivePacks::new()),
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let [addr] = tcp_addrs(server.local_addrs()?)?;
+    let serving = tokio::spawn(server.serve());
+
+    let client = reqwest::Client::new();
+    for index in 0..50 {
+        let response = client
+            .post(format!("http://{addr}/operator/op/invoke"))
+            .header(TENANT_HEADER, format!("bogus-{index}"))
+            .header("content-type", "application/cbor")
+            .body(Vec::new())
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    let metrics = client
+        .get(format!("http://{addr}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(
+        !metrics.contains("greentic_http_in_flight_requests{"),
+        "{metrics}"
+    );
+
+    serving.abort();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_is_owner_only() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::TempDir::new()?;
+    let path = dir.path().join("runner.sock");
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Unix(path.clone()),
+            [RouteGroup::Admin],
+        )],
+        Arc::new(ActivePacks::new()),
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let mode = std::fs::metadata(&path)?.permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(server.local_addrs()?, vec![ListenAddr::Unix(path.clone())]);
+    // The staging directory the socket was bound in is gone.
+    assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+    Ok(())
+}
+
+fn tcp_addrs<const N: usize>(addrs: Vec<ListenAddr>) -> Result<[SocketAddr; N]> {
+    let addrs = addrs
+        .into_iter()
+        .map(|addr| match addr {
+            ListenAddr::Tcp(addr) => Ok(addr),
+            other => bail!("expected tcp listener, got {other}"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    addrs
+        .try_into()
+        .map_err(|addrs: Vec<_>| anyhow::anyhow!("expected {N} listeners, got {}", addrs.len()))
+}
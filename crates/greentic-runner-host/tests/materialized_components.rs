@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -7,8 +7,9 @@ use std::sync::Arc;
 use anyhow::{Context, Result, anyhow};
 use greentic_pack::builder as legacy_pack;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::{ComponentResolution, PackRuntime};
 use greentic_runner_host::secrets::default_manager;
@@ -43,6 +44,8 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -52,6 +55,13 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
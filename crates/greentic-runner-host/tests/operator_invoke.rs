@@ -1,36 +1,87 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{Write, copy};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::http::StatusCode;
 use greentic_runner_host::{
-    RunnerWasiPolicy,
-    config::{HostConfig, OperatorPolicy, SecretsPolicy},
+    HostServer, ListenAddr, ListenerConfig, PreopenSpec, RouteGroup, RunnerWasiPolicy,
+    audit::{AuditEvent, MemoryAuditSink, SecretDecision},
+    component_api::node::Impersonation,
+    config::{
+        ComponentLoading, DeterministicConfig, HostConfig, OperatorPolicy, OperatorPolicyConfig,
+        RedactionConfig, RedactionPolicy, SecretRule, SecretsPolicy,
+    },
+    http::auth::AdminAuth,
+    http::health::HealthState,
+    http::metrics::{HttpMetrics, render as render_metrics},
+    operator_registry::OperatorOpDescriptor,
+    pack::{ComponentResolution, PackRuntime},
+    preflight::{self, PreflightCheck},
+    routing::{RoutingConfig, TENANT_HEADER, TenantResolver, TenantRouting},
     runner::operator::{
-        OperatorErrorCode, OperatorPayload, OperatorRequest, OperatorStatus, invoke_operator,
+        AttachmentRef, Diagnostic, MAX_SPAN_ATTRIBUTE_LEN, OperatorErrorCode, OperatorPayload,
+        OperatorRequest, OperatorStatus, ProviderSelector, describe_operator_contract,
+        invoke_operator, invoke_operator_streaming,
+    },
+    runtime::{ActivePacks, ComponentResolveError, PackPreference, TenantRuntime},
+    secrets::{
+        DynSecretsManager, FileSecretsConfig, FileSecretsManager, SecretRotation,
+        SecretsHealthCheck, default_manager, health, rotation,
     },
-    runtime::TenantRuntime,
-    secrets::default_manager,
-    storage::{new_session_store, new_state_store, session_host_from, state_host_from},
+    storage::{
+        DynStateStore, new_session_store, new_state_store, session_host_from, state_host_from,
+    },
+    stream::{StreamEvent, StreamObserver},
     trace::TraceConfig,
     validate::ValidationConfig,
+    wasm_engine::{AllocationStrategy, PoolingLimits, WasmEngineConfig},
 };
+use greentic_secrets_lib::{SecretError, SecretsManager};
+use greentic_state::{StateKey, StateStore};
 use greentic_types::{
-    ComponentCapabilities, ComponentManifest, ComponentProfiles, ExtensionInline, ExtensionRef,
-    PROVIDER_EXTENSION_ID, PackKind, PackManifest, ProviderDecl, ProviderExtensionInline,
-    ProviderRuntimeRef, ResourceHints, encode_pack_manifest,
+    ComponentCapabilities, ComponentManifest, ComponentProfiles, EnvId, ExtensionInline,
+    ExtensionRef, PROVIDER_EXTENSION_ID, PackKind, PackManifest, ProviderDecl,
+    ProviderExtensionInline, ProviderRuntimeRef, ResourceHints, TenantCtx, TenantId,
+    encode_pack_manifest,
 };
 use semver::Version;
 use serde_json::{Value, json};
 use tempfile::TempDir;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context as LayerContext;
+use tracing_subscriber::registry::LookupSpan;
 use zip::ZipWriter;
 use zip::write::FileOptions;
 
 const PROVIDER_TYPE: &str = "example.dummy";
+const PROVIDER_PACK_ID: &str = "operator.provider";
+const PROVIDER_COMPONENT_REF: &str = "provider.dummy";
 const PROVIDER_OP: &str = "echo";
+const SLOW_PROVIDER_OP: &str = "slow_echo";
+const GROW_MEMORY_OP: &str = "grow_memory";
+const SPIN_OP: &str = "spin";
+const VERSION_OP: &str = "version";
+const WRITE_FILE_OP: &str = "write_file";
+const UNREACHABLE_OP: &str = "unreachable";
+const LOG_ECHO_OP: &str = "log_echo";
+const LOG_AND_TRAP_OP: &str = "log_and_trap";
+const LEAK_AND_TRAP_OP: &str = "leak_and_trap";
+const NOW_OP: &str = "now";
+const STREAM_PROVIDER_TYPE: &str = "example.stream";
+const STREAM_COMPONENT_REF: &str = "stream.progress";
+const STREAM_OP: &str = "count";
+const BUILD_MARKER_V1: &[u8] = b"fixture-build:v1";
+const BUILD_MARKER_V2: &[u8] = b"fixture-build:v2";
 
 #[tokio::test]
 async fn invoke_operator_api_returns_provider_output() -> Result<()> {
@@ -50,11 +101,14 @@ async fn invoke_operator_api_returns_provider_output() -> Result<()> {
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -87,11 +141,14 @@ async fn invoke_operator_api_returns_provider_output() -> Result<()> {
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -106,6 +163,300 @@ async fn invoke_operator_api_returns_provider_output() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn metrics_scrape_reports_operator_invoke() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    let component_path = build_provider_component()?;
+    build_provider_pack(&component_path, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, Arc::clone(&config)).await?;
+
+    let payload = serde_cbor::to_vec(&json!({"message": "ping"}))?;
+    let request = OperatorRequest {
+        tenant_id: Some("demo".into()),
+        provider_id: None,
+        provider_type: Some(PROVIDER_TYPE.to_string()),
+        pack_id: None,
+        op_id: PROVIDER_OP.to_string(),
+        trace_id: None,
+        correlation_id: None,
+        idempotency_key: None,
+        timeout: None,
+        flags: Vec::new(),
+        op_version: None,
+        schema_hash: None,
+        locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
+        payload: OperatorPayload {
+            cbor_input: payload,
+            attachments: Vec::new(),
+        },
+    };
+    let response = invoke_operator(&runtime, request).await;
+    assert!(matches!(response.status, OperatorStatus::Ok));
+
+    let active = ActivePacks::new();
+    active.replace(HashMap::from([("demo".to_string(), Arc::clone(&runtime))]));
+    let http = HttpMetrics::new();
+    http.record("/operator/op/invoke", StatusCode::OK);
+    let body = render_metrics(&active, &http, &HealthState::new());
+
+    for series in [
+        "greentic_operator_resolve_attempts_total{tenant=\"demo\"}",
+        "greentic_operator_invoke_attempts_total{tenant=\"demo\"}",
+        "greentic_operator_invoke_duration_seconds_count{tenant=\"demo\"}",
+        "greentic_operator_invoke_duration_seconds_bucket{tenant=\"demo\",le=\"+Inf\"}",
+        "greentic_contract_cache_misses_total{tenant=\"demo\"}",
+        "greentic_contract_cache_entries{tenant=\"demo\"}",
+        "greentic_http_requests_total{route=\"/operator/op/invoke\",status=\"200\"}",
+    ] {
+        let value = series_value(&body, series)
+            .with_context(|| format!("series {series} missing from scrape:\n{body}"))?;
+        assert!(value >= 1.0, "series {series} expected >= 1, got {value}");
+    }
+    assert!(
+        series_value(
+            &body,
+            "greentic_cache_memory_bytes{tenant=\"demo\",pack=\"operator.provider\"}"
+        )
+        .is_some(),
+        "cache gauges missing from scrape:\n{body}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn cache_stats_route_reports_routed_tenant() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["solo"]).await?;
+    let (addr, serving) = serve_groups(
+        active,
+        RoutingConfig::default(),
+        &[RouteGroup::Operator, RouteGroup::Metrics],
+    )
+    .await?;
+    let (status, _) = invoke_http(addr, &[], None).await?;
+    assert_eq!(status, 200);
+    let client = reqwest::Client::new();
+
+    let stats: Value = client
+        .get(format!("http://{addr}/metrics/cache"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(stats["tenant"], "solo");
+    assert_eq!(stats["operator"]["invoke_attempts"], 1);
+    assert_eq!(stats["operator"]["resolve_attempts"], 1);
+    assert_eq!(stats["contract_cache"]["entries"], 1);
+    let component = &stats["component_cache"][0];
+    assert_eq!(component["pack_id"], "operator.provider");
+    assert!(component["memory"]["entries"].is_u64(), "{stats}");
+    assert!(component["disk"]["artifact_count"].is_u64(), "{stats}");
+
+    let response = client
+        .get(format!("http://{addr}/metrics/cache?format=prometheus"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await?;
+    assert_eq!(
+        series_value(
+            &body,
+            "greentic_operator_invoke_attempts_total{tenant=\"solo\"}"
+        ),
+        Some(1.0),
+        "{body}"
+    );
+    assert!(
+        series_value(
+            &body,
+            "greentic_cache_memory_entries{tenant=\"solo\",pack=\"operator.provider\"}"
+        )
+        .is_some(),
+        "{body}"
+    );
+    assert!(!body.contains("greentic_http_requests_total"));
+
+    let response = client
+        .get(format!("http://{addr}/metrics/cache?format=xml"))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 400);
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoke_operator_passes_provider_instance_config() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    let component_path = build_provider_component()?;
+    build_provider_pack(&component_path, &pack_path)?;
+    let state_store = new_state_store();
+    store_provider_instance(&state_store, json!({"message": "from-instance"}))?;
+    let runtime = load_runtime(
+        &pack_path,
+        Arc::clone(&config),
+        RunnerWasiPolicy::new(),
+        default_manager()?,
+        state_store,
+    )
+    .await?;
+
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.provider_id = Some(PROVIDER_TYPE.to_string());
+    let response = invoke_operator(&runtime, request).await;
+    assert!(
+        matches!(response.status, OperatorStatus::Ok),
+        "unexpected response: {response:?}"
+    );
+    let output = response
+        .cbor_output
+        .as_deref()
+        .context("expected CBOR output for success")?;
+    let value: Value = serde_cbor::from_slice(output)?;
+    assert_eq!(value["message"], "ping");
+    assert_eq!(value["_config"], json!({"message": "from-instance"}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoke_operator_rejects_provider_config_failing_schema() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    let component_path = build_provider_component()?;
+    build_provider_pack(&component_path, &pack_path)?;
+    let state_store = new_state_store();
+    store_provider_instance(&state_store, json!({"message": 7}))?;
+    let runtime = load_runtime(
+        &pack_path,
+        Arc::clone(&config),
+        RunnerWasiPolicy::new(),
+        default_manager()?,
+        state_store,
+    )
+    .await?;
+
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.provider_id = Some(PROVIDER_TYPE.to_string());
+    let response = invoke_operator(&runtime, request).await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected provider config error")?;
+    assert!(matches!(error.code, OperatorErrorCode::TypeMismatch));
+    let details = error
+        .details_cbor
+        .as_deref()
+        .context("expected deterministic diagnostics details")?;
+    let diagnostics: Vec<Diagnostic> = serde_cbor::from_slice(details)?;
+    assert!(!diagnostics.is_empty());
+    assert!(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.path.starts_with("/provider_config")),
+        "{diagnostics:?}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn streaming_invoke_forwards_component_events() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-stream.gtpack");
+    write_stream_pack(&build_stream_component()?, &pack_path, None)?;
+    let runtime = setup_runtime(&pack_path, Arc::clone(&config)).await?;
+
+    let mut request = operator_request(STREAM_OP)?;
+    request.provider_type = Some(STREAM_PROVIDER_TYPE.to_string());
+    let observer = Arc::new(RecordingObserver::default());
+    let response = invoke_operator_streaming(&runtime, request, observer.clone()).await;
+    assert!(
+        matches!(response.status, OperatorStatus::Ok),
+        "unexpected response: {response:?}"
+    );
+    let output = response
+        .cbor_output
+        .as_deref()
+        .context("expected CBOR output for success")?;
+    let value: Value = serde_cbor::from_slice(output)?;
+    assert_eq!(value, json!({"step": 3}));
+    assert_eq!(
+        *observer.events.lock().unwrap(),
+        vec![
+            StreamEvent::Progress(0),
+            StreamEvent::Data(json!({"step": 1})),
+            StreamEvent::Data(json!({"step": 2})),
+            StreamEvent::Data(json!({"step": 3})),
+            StreamEvent::Progress(100),
+            StreamEvent::Done,
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn streaming_invoke_checks_each_data_event_against_output_schema() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-stream.gtpack");
+    let output_schema = r#"{
+  "type": "object",
+  "properties": { "step": { "type": "integer", "maximum": 2 } }
+}"#;
+    write_stream_pack(&build_stream_component()?, &pack_path, Some(output_schema))?;
+    let runtime = setup_runtime(&pack_path, Arc::clone(&config)).await?;
+
+    let mut request = operator_request(STREAM_OP)?;
+    request.provider_type = Some(STREAM_PROVIDER_TYPE.to_string());
+    let observer = Arc::new(RecordingObserver::default());
+    let response = invoke_operator_streaming(&runtime, request, observer.clone()).await;
+    let error = response.error.context("expected output schema error")?;
+    assert!(matches!(error.code, OperatorErrorCode::TypeMismatch));
+    let details = error
+        .details_cbor
+        .as_deref()
+        .context("expected deterministic diagnostics details")?;
+    let diagnostics: Vec<Diagnostic> = serde_cbor::from_slice(details)?;
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert_eq!(diagnostics[0].path, "/events/3/step");
+
+    let events = observer.events.lock().unwrap();
+    assert_eq!(events[2], StreamEvent::Data(json!({"step": 2})));
+    assert!(matches!(&events[3], StreamEvent::Error(message) if message.contains("event 3")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn streaming_invoke_of_provider_core_is_invalid_request() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let config = minimal_config(workspace.path())?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, Arc::clone(&config)).await?;
+
+    let observer = Arc::new(RecordingObserver::default());
+    let response =
+        invoke_operator_streaming(&runtime, operator_request(PROVIDER_OP)?, observer.clone()).await;
+    let error = response
+        .error
+        .context("expected unsupported stream error")?;
+    assert!(matches!(error.code, OperatorErrorCode::InvalidRequest));
+    assert!(error.message.contains("invoke-stream"), "{}", error.message);
+    let details = error
+        .details_cbor
+        .as_deref()
+        .context("expected deterministic diagnostics details")?;
+    let diagnostics: Vec<Diagnostic> = serde_cbor::from_slice(details)?;
+    assert_eq!(diagnostics[0].code, "stream_unsupported");
+    assert!(observer.events.lock().unwrap().is_empty());
+    Ok(())
+}
+
 #[tokio::test]
 async fn invoke_operator_api_missing_operation_errors() -> Result<()> {
     let workspace = TempDir::new()?;
@@ -124,11 +475,14 @@ async fn invoke_operator_api_missing_operation_errors() -> Result<()> {
         op_id: "unknown".to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -150,6 +504,16 @@ async fn invoke_operator_api_missing_operation_errors() -> Result<()> {
     assert_eq!(diagnostics[0].path, "/op_id");
     assert_eq!(diagnostics[0].message_key, "runner.operator.op_not_found");
     assert_eq!(diagnostics[0].operation_id.as_deref(), Some("unknown"));
+    assert_eq!(diagnostics[0].args["op"], "unknown");
+    assert!(
+        diagnostics[0].message.contains("`unknown` not found"),
+        "{}",
+        diagnostics[0].message
+    );
+    assert_eq!(
+        error.message,
+        format!("op `unknown` not found for provider `{PROVIDER_TYPE}`")
+    );
     assert!(response.cbor_output.is_none());
     Ok(())
 }
@@ -172,11 +536,14 @@ async fn invoke_operator_api_rejects_schema_hash_mismatch() -> Result<()> {
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: Some("sha256:deadbeef".to_string()),
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -201,6 +568,216 @@ async fn invoke_operator_api_rejects_schema_hash_mismatch() -> Result<()> {
         "runner.operator.schema_hash_mismatch"
     );
     assert_eq!(diagnostics[0].operation_id.as_deref(), Some(PROVIDER_OP));
+    assert_eq!(diagnostics[0].args["provided"], "sha256:deadbeef");
+    assert_eq!(diagnostics[0].args["op"], PROVIDER_OP);
+    assert!(
+        diagnostics[0].message.contains("got `sha256:deadbeef`"),
+        "{}",
+        diagnostics[0].message
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn described_schema_hash_pins_the_next_invoke() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let selector = ProviderSelector {
+        provider_type: Some(PROVIDER_TYPE),
+        ..Default::default()
+    };
+
+    let contract = describe_operator_contract(&runtime, selector, PROVIDER_OP)
+        .map_err(|response| anyhow::anyhow!("describe failed: {:?}", response.error))?;
+    assert_eq!(contract.provider_type, PROVIDER_TYPE);
+    assert_eq!(contract.op_id, PROVIDER_OP);
+    assert_eq!(contract.selected_operation, PROVIDER_OP);
+    assert_eq!(contract.pack_ref, "operator.provider@0.1.0");
+    assert_eq!(contract.component_ref, PROVIDER_COMPONENT_REF);
+    assert!(contract.schema_hash.starts_with("sha256:"));
+    assert!(contract.describe_hash.starts_with("sha256:"));
+    let config_schema: Value = serde_json::from_str(PROVIDER_CONFIG_SCHEMA)?;
+    assert_eq!(contract.config_schema, config_schema);
+    assert_eq!(contract.input_schema, config_schema);
+    let stats = runtime.contract_cache_stats();
+    assert_eq!((stats.entries, stats.misses, stats.hits), (1, 1, 0));
+
+    // Describing never invokes the component, and the invoke reuses the cached contract.
+    assert_eq!(
+        runtime.operator_metrics().snapshot().invoke_attempts,
+        0,
+        "describe must not invoke"
+    );
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.schema_hash = Some(contract.schema_hash.clone());
+    let response = invoke_operator(&runtime, request).await;
+    assert!(
+        matches!(response.status, OperatorStatus::Ok),
+        "unexpected response: {response:?}"
+    );
+    let stats = runtime.contract_cache_stats();
+    assert_eq!((stats.entries, stats.misses, stats.hits), (1, 1, 1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn describe_reports_resolution_diagnostics() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    for (selector, op_id, code, diagnostic_code) in [
+        (
+            ProviderSelector {
+                provider_type: Some(PROVIDER_TYPE),
+                ..Default::default()
+            },
+            "unknown",
+            "OP_NOT_FOUND",
+            "op_not_found",
+        ),
+        (
+            ProviderSelector {
+                provider_id: Some("missing"),
+                ..Default::default()
+            },
+            PROVIDER_OP,
+            "PROVIDER_NOT_FOUND",
+            "provider_not_found",
+        ),
+        (
+            ProviderSelector::default(),
+            PROVIDER_OP,
+            "INVALID_REQUEST",
+            "missing_provider_selector",
+        ),
+    ] {
+        let response = describe_operator_contract(&runtime, selector, op_id)
+            .err()
+            .context("expected describe to fail")?;
+        let error = response.error.context("expected error")?;
+        assert_eq!(error.code.as_str(), code);
+        let diagnostics: Vec<Diagnostic> = serde_cbor::from_slice(
+            error
+                .details_cbor
+                .as_deref()
+                .context("expected diagnostics")?,
+        )?;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostic_code);
+        assert_eq!(diagnostics[0].operation_id.as_deref(), Some(op_id));
+    }
+    assert_eq!(runtime.contract_cache_stats().entries, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn describe_endpoint_answers_in_cbor() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["solo"]).await?;
+    let (addr, serving) = serve_operator(active, RoutingConfig::default()).await?;
+
+    let body = serde_cbor::to_vec(&json!({
+        "provider_type": PROVIDER_TYPE,
+        "op_id": PROVIDER_OP,
+    }))?;
+    let response = reqwest::Client::new()
+        .post(format!("http://{addr}/operator/op/describe"))
+        .header("content-type", "application/cbor")
+        .body(body)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let described: Value = serde_cbor::from_slice(&response.bytes().await?)?;
+    assert_eq!(described["status"], "Ok");
+    assert_eq!(described["contract"]["op_id"], PROVIDER_OP);
+    assert!(
+        described["contract"]["schema_hash"]
+            .as_str()
+            .is_some_and(|hash| hash.starts_with("sha256:")),
+        "{described}"
+    );
+
+    let body = serde_cbor::to_vec(&json!({
+        "tenant_id": "other",
+        "provider_type": PROVIDER_TYPE,
+        "op_id": PROVIDER_OP,
+    }))?;
+    let response = reqwest::Client::new()
+        .post(format!("http://{addr}/operator/op/describe"))
+        .header("content-type", "application/cbor")
+        .body(body)
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(is_tenant_mismatch(
+        &response.bytes().await?,
+        "solo",
+        "other"
+    )?);
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoke_spans_carry_contract_and_outcome_attributes() -> Result<()> {
+    use tracing::instrument::WithSubscriber;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let capture = SpanCapture::default();
+    let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(capture.clone()));
+
+    let request = operator_request(PROVIDER_OP)?;
+    let request_bytes = request.payload.cbor_input.len().to_string();
+    let response = invoke_operator(&runtime, request)
+        .with_subscriber(dispatch.clone())
+        .await;
+    assert!(
+        matches!(response.status, OperatorStatus::Ok),
+        "unexpected response: {response:?}"
+    );
+    let root = capture.fields("operator.invoke");
+    assert!(root["greentic.pack_ref"].starts_with(PROVIDER_PACK_ID));
+    assert_eq!(root["greentic.component_ref"], PROVIDER_COMPONENT_REF);
+    let digest = root["greentic.component_digest"].clone();
+    assert!(!digest.is_empty());
+    assert!(root["greentic.schema_hash"].starts_with("sha256:"));
+    assert_eq!(root["greentic.validate_output"], "true");
+    assert_eq!(root["greentic.strict"], "true");
+    assert_eq!(root["greentic.request_bytes"], request_bytes);
+    assert_ne!(root["greentic.response_bytes"], "0");
+    assert_eq!(root["greentic.status"], "ok");
+    assert!(!root.contains_key("greentic.error_code"));
+    let invoke = capture.fields("invoke_component");
+    assert_eq!(invoke["greentic.component_digest"], digest);
+    assert_eq!(
+        invoke["greentic.response_bytes"],
+        root["greentic.response_bytes"]
+    );
+
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.schema_hash = Some("sha256:deadbeef".to_string());
+    let response = invoke_operator(&runtime, request)
+        .with_subscriber(dispatch)
+        .await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let root = capture.fields("operator.invoke");
+    assert_eq!(root["greentic.status"], "error");
+    assert_eq!(root["greentic.error_code"], "TYPE_MISMATCH");
+    assert_eq!(root["otel.status_code"], "ERROR");
+    assert_eq!(root["greentic.component_digest"], digest);
+    assert_eq!(root["greentic.response_bytes"], "0");
+    assert!(
+        root.values()
+            .all(|value| value.len() <= MAX_SPAN_ATTRIBUTE_LEN)
+    );
     Ok(())
 }
 
@@ -222,11 +799,14 @@ async fn invoke_operator_api_rejects_invalid_input_against_schema() -> Result<()
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -286,11 +866,14 @@ async fn invoke_operator_api_strict_rejects_unsupported_schema_constraints() ->
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -361,11 +944,14 @@ async fn invoke_operator_api_rejects_invalid_output_against_output_schema() -> R
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: Vec::new(),
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -433,11 +1019,14 @@ async fn invoke_operator_api_skip_output_validate_bypasses_output_schema_errors(
         op_id: PROVIDER_OP.to_string(),
         trace_id: None,
         correlation_id: None,
+        idempotency_key: None,
         timeout: None,
         flags: vec!["skip-output-validate".to_string()],
         op_version: None,
         schema_hash: None,
         locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
         payload: OperatorPayload {
             cbor_input: payload,
             attachments: Vec::new(),
@@ -450,54 +1039,2246 @@ async fn invoke_operator_api_skip_output_validate_bypasses_output_schema_errors(
     Ok(())
 }
 
-fn minimal_config(workspace: &Path) -> Result<Arc<HostConfig>> {
-    let bindings_path = workspace.join("bindings.yaml");
-    std::fs::write(
-        &bindings_path,
-        r#"
-tenant: demo
-flow_type_bindings: {}
-rate_limits: {}
-retry: {}
-timers: []
-"#,
-    )?;
-    let mut config =
-        HostConfig::load_from_path(&bindings_path).context("load minimal host bindings")?;
-    config.secrets_policy = SecretsPolicy::allow_all();
-    config.operator_policy = OperatorPolicy::allow_all();
-    config.trace = TraceConfig::from_env();
-    config.validation = ValidationConfig::from_env();
-    Ok(Arc::new(config))
-}
-
-async fn setup_runtime(pack_path: &Path, config: Arc<HostConfig>) -> Result<Arc<TenantRuntime>> {
-    let session_store = new_session_store();
-    let session_host = session_host_from(Arc::clone(&session_store));
-    let state_store = new_state_store();
-    let state_host = state_host_from(Arc::clone(&state_store));
-    let secrets = default_manager()?;
-    TenantRuntime::load(
-        pack_path,
-        config,
-        None,
-        Some(pack_path),
-        None,
-        Arc::new(RunnerWasiPolicy::new()),
-        session_host,
-        Arc::clone(&session_store),
-        Arc::clone(&state_store),
-        state_host,
-        secrets,
+#[tokio::test]
+async fn host_header_selects_tenant_runtime() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["alpha", "beta"]).await?;
+    let (addr, serving) = serve_operator(
+        active,
+        RoutingConfig {
+            resolver: TenantResolver::subdomain("runner.example.com"),
+            default_tenant: None,
+            ..Default::default()
+        },
     )
-    .await
-}
+    .await?;
 
-fn build_provider_pack(component_path: &Path, pack_path: &Path) -> Result<()> {
-    build_provider_pack_with_schemas(
-        component_path,
-        pack_path,
-        r#"{
+    for (host, tenant) in [
+        ("alpha.runner.example.com", "alpha"),
+        ("beta.runner.example.com:8080", "beta"),
+    ] {
+        let (status, body) = invoke_http(addr, &[("host", host)], Some(tenant)).await?;
+        assert_eq!(status, 200);
+        assert_eq!(
+            cbor_output(&body)?,
+            Some(json!({"message": "ping"})),
+            "{host} should route to {tenant}"
+        );
+    }
+
+    // Body tenant is checked against the host-derived tenant.
+    let (status, body) =
+        invoke_http(addr, &[("host", "beta.runner.example.com")], Some("alpha")).await?;
+    assert_eq!(status, 200);
+    assert!(is_tenant_mismatch(&body, "beta", "alpha")?);
+
+    for host in ["gamma.runner.example.com", "alpha.elsewhere.example.com"] {
+        let (status, _) = invoke_http(addr, &[("host", host)], None).await?;
+        assert_eq!(status, 404, "{host} should be rejected");
+    }
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_without_tenant_use_default_tenant() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["alpha", "beta"]).await?;
+    let (addr, serving) = serve_operator(
+        active,
+        RoutingConfig {
+            resolver: TenantResolver::Env,
+            default_tenant: Some("beta".into()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let (status, body) = invoke_http(addr, &[], None).await?;
+    assert_eq!(status, 200);
+    assert_eq!(cbor_output(&body)?, Some(json!({"message": "ping"})));
+
+    let (status, body) = invoke_http(addr, &[], Some("alpha")).await?;
+    assert_eq!(status, 200);
+    assert!(is_tenant_mismatch(&body, "beta", "alpha")?);
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn single_loaded_tenant_is_used_without_default() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["solo"]).await?;
+    let (addr, serving) = serve_operator(active, RoutingConfig::default()).await?;
+
+    let (status, body) = invoke_http(addr, &[], None).await?;
+    assert_eq!(status, 200);
+    assert_eq!(cbor_output(&body)?, Some(json!({"message": "ping"})));
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_header_routes_and_is_cross_checked() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["alpha", "beta"]).await?;
+    let routing = RoutingConfig {
+        default_tenant: Some("alpha".into()),
+        ..Default::default()
+    };
+    let (addr, serving) = serve_operator(Arc::clone(&active), routing.clone()).await?;
+    let header = [(TENANT_HEADER, "beta")];
+
+    // Header only: overrides the default tenant.
+    let (status, body) = invoke_http(addr, &header, None).await?;
+    assert_eq!(status, 200);
+    assert_eq!(cbor_output(&body)?, Some(json!({"message": "ping"})));
+
+    // Header and body agree.
+    let (status, body) = invoke_http(addr, &header, Some("beta")).await?;
+    assert_eq!(status, 200);
+    assert_eq!(cbor_output(&body)?, Some(json!({"message": "ping"})));
+
+    // Header and body disagree.
+    let (status, body) = invoke_http(addr, &header, Some("alpha")).await?;
+    assert_eq!(status, 200);
+    assert!(is_tenant_mismatch(&body, "beta", "alpha")?);
+    serving.abort();
+
+    // Untrusted header is ignored; routing falls back to the default tenant.
+    let (addr, serving) = serve_operator(
+        active,
+        RoutingConfig {
+            ignore_tenant_header: true,
+            ..routing
+        },
+    )
+    .await?;
+    let (status, body) = invoke_http(addr, &header, Some("beta")).await?;
+    assert_eq!(status, 200);
+    assert!(is_tenant_mismatch(&body, "alpha", "beta")?);
+
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn preflight_flags_missing_schema_in_both_modes() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("missing-schema.gtpack");
+    let component_path = build_provider_component()?;
+    write_provider_pack(&component_path, &pack_path, None, None)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(temp.path())?).await?;
+    let active = Arc::new(ActivePacks::new());
+    active.replace(HashMap::from([("demo".to_string(), runtime)]));
+
+    let report = {
+        let active = Arc::clone(&active);
+        tokio::task::spawn_blocking(move || preflight::check_all(&active)).await?
+    };
+    assert_eq!(report.failed_tenants(), vec!["demo"]);
+    let failures = &report.tenants["demo"].failures;
+    assert_eq!(failures.len(), 1, "unexpected failures: {failures:?}");
+    assert_eq!(failures[0].check, PreflightCheck::Schema);
+    assert!(failures[0].detail.contains("schemas/config.schema.json"));
+
+    // Strict mode aborts startup.
+    let err = report
+        .enforce(true)
+        .expect_err("strict preflight must fail");
+    assert!(err.to_string().contains("demo"));
+
+    // Lenient mode starts, but the tenant is reported not-ready.
+    report.enforce(false)?;
+    let health = Arc::new(HealthState::new());
+    health.record_preflight(report);
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Health],
+        )],
+        active,
+        TenantRouting::new(RoutingConfig::default()),
+        health,
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    let serving = tokio::spawn(server.serve());
+    let response = reqwest::get(format!("http://{addr}/readyz")).await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = response.json().await?;
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["tenants"]["demo"]["ready"], false);
+    assert_eq!(body["tenants"]["demo"]["failures"][0]["check"], "schema");
+    serving.abort();
+    Ok(())
+}
+
+/// A secrets backend whose reachability the test controls.
+#[derive(Default)]
+struct ToggleHealth {
+    down: AtomicBool,
+}
+
+#[async_trait]
+impl SecretsHealthCheck for ToggleHealth {
+    async fn healthcheck(&self) -> Result<(), SecretError> {
+        if self.down.load(Ordering::SeqCst) {
+            Err(SecretError::Backend("backend unreachable".into()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[tokio::test]
+async fn readiness_follows_secrets_backend_health() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(temp.path())?).await?;
+    let active = Arc::new(ActivePacks::new());
+    active.replace(HashMap::from([("demo".to_string(), runtime)]));
+    let health_state = Arc::new(HealthState::new());
+    health_state.set_ready();
+    let backend = Arc::new(ToggleHealth::default());
+    let monitor = health::spawn_monitor(
+        backend.clone(),
+        Arc::clone(&health_state),
+        Duration::from_millis(20),
+    );
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Health],
+        )],
+        active,
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::clone(&health_state),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    let serving = tokio::spawn(server.serve());
+    let readyz = |expected: StatusCode| async move {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let response = reqwest::get(format!("http://{addr}/readyz")).await?;
+            if response.status() == expected {
+                return response.json::<Value>().await.map_err(anyhow::Error::from);
+            }
+            if Instant::now() > deadline {
+                anyhow::bail!("readyz never answered {expected}");
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+
+    health::probe(backend.as_ref(), &health_state).await?;
+    let body = readyz(StatusCode::OK).await?;
+    assert_eq!(body["secrets"]["healthy"], true);
+    assert!(body["secrets"]["last_success"].is_string());
+
+    backend.down.store(true, Ordering::SeqCst);
+    let body = readyz(StatusCode::SERVICE_UNAVAILABLE).await?;
+    assert_eq!(body["secrets"]["healthy"], false);
+    assert!(body["secrets"]["consecutive_failures"].as_u64() >= Some(1));
+    assert_eq!(body["tenants"]["demo"]["ready"], true);
+    assert!(!health_state.snapshot().secrets_ready);
+    let metrics = render_metrics(&ActivePacks::new(), &HttpMetrics::new(), &health_state);
+    assert!(!metrics.contains("greentic_secrets_backend_consecutive_failures 0"));
+
+    backend.down.store(false, Ordering::SeqCst);
+    readyz(StatusCode::OK).await?;
+    assert!(health_state.snapshot().secrets_ready);
+
+    monitor.abort();
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn tenant_in_flight_limit_rejects_excess_requests() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut limited = (*tenant_config(temp.path(), "alpha")?).clone();
+    limited.rate_limits.max_in_flight = Some(1);
+    let mut runtimes = HashMap::new();
+    runtimes.insert(
+        "alpha".to_string(),
+        setup_runtime(&pack_path, Arc::new(limited)).await?,
+    );
+    runtimes.insert(
+        "beta".to_string(),
+        setup_runtime(&pack_path, tenant_config(temp.path(), "beta")?).await?,
+    );
+    let active = Arc::new(ActivePacks::new());
+    active.replace(runtimes);
+
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Operator, RouteGroup::Metrics],
+        )],
+        active,
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    let serving = tokio::spawn(server.serve());
+
+    let slow = tokio::spawn(async move {
+        invoke_op_http(addr, &[(TENANT_HEADER, "alpha")], None, SLOW_PROVIDER_OP).await
+    });
+    let metrics_url = format!("http://{addr}/metrics");
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(10);
+    loop {
+        let body = reqwest::get(&metrics_url).await?.text().await?;
+        if series_value(&body, "greentic_http_in_flight_requests{tenant=\"alpha\"}") == Some(1.0) {
+            break;
+        }
+        anyhow::ensure!(
+            tokio::time::Instant::now() < deadline,
+            "slow request never went in flight"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{addr}/operator/op/invoke"))
+        .header(TENANT_HEADER, "alpha")
+        .header("content-type", "application/cbor")
+        .body(Vec::new())
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+    let body: Value = response.json().await?;
+    assert_eq!(body["tenant"], "alpha");
+    assert_eq!(body["max_in_flight"], 1);
+
+    let (status, _) = invoke_http(addr, &[(TENANT_HEADER, "beta")], None).await?;
+    assert_eq!(status, 200, "other tenants are not limited");
+
+    let (status, _) = slow.await??;
+    assert_eq!(status, 200);
+    let (status, _) = invoke_http(addr, &[(TENANT_HEADER, "alpha")], None).await?;
+    assert_eq!(
+        status, 200,
+        "slot is released once the slow request finishes"
+    );
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn operator_rate_limit_answers_excess_invokes_with_429() -> Result<()> {
+    let temp = TempDir::new()?;
+    let pack_path = temp.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut limited = (*tenant_config(temp.path(), "alpha")?).clone();
+    limited.rate_limits.operators = serde_json::from_value(json!({
+        format!("{PROVIDER_TYPE}/{PROVIDER_OP}"): { "per_second": 0.01, "burst": 3 },
+        PROVIDER_TYPE: { "per_second": 1000.0 },
+    }))?;
+    let runtime = setup_runtime(&pack_path, Arc::new(limited)).await?;
+    let active = Arc::new(ActivePacks::new());
+    active.replace(HashMap::from([("alpha".to_string(), Arc::clone(&runtime))]));
+    let (addr, serving) = serve_operator(active, RoutingConfig::default()).await?;
+
+    let body = serde_cbor::to_vec(&json!({
+        "provider_type": PROVIDER_TYPE,
+        "op_id": PROVIDER_OP,
+        "payload": { "cbor_input": serde_cbor::to_vec(&json!({"message": "ping"}))? },
+    }))?;
+    let client = reqwest::Client::new();
+    let requests = (0..10).map(|_| {
+        client
+            .post(format!("http://{addr}/operator/op/invoke"))
+            .header(TENANT_HEADER, "alpha")
+            .header("content-type", "application/cbor")
+            .body(body.clone())
+            .send()
+    });
+    let mut ok = 0;
+    let mut throttled = Vec::new();
+    for response in futures::future::join_all(requests).await {
+        let response = response?;
+        match response.status() {
+            StatusCode::OK => ok += 1,
+            StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = response
+                    .headers()
+                    .get("retry-after")
+                    .context("429 without Retry-After")?
+                    .to_str()?
+                    .parse::<u64>()?;
+                throttled.push((retry_after, response.bytes().await?));
+            }
+            other => anyhow::bail!("unexpected status {other}"),
+        }
+    }
+    assert_eq!(ok, 3, "the burst is admitted");
+    assert_eq!(throttled.len(), 7);
+    let (retry_after, body) = &throttled[0];
+    assert!(*retry_after >= 1);
+    let texts = cbor_texts(&serde_cbor::from_slice(body)?);
+    assert!(texts.iter().any(|text| text == "RATE_LIMITED"), "{texts:?}");
+    assert_eq!(runtime.operator_metrics().snapshot().throttled, 7);
+    assert_eq!(runtime.operator_metrics().snapshot().invoke_attempts, 3);
+
+    // The op entry wins; other ops of the provider draw from their own buckets.
+    let (status, _) = invoke_op_http(addr, &[(TENANT_HEADER, "alpha")], None, VERSION_OP).await?;
+    assert_eq!(status, 200);
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn rate_limited_invoke_reports_retry_after() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.rate_limits.operators =
+        serde_json::from_value(json!({ PROVIDER_TYPE: { "per_second": 0.5 } }))?;
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let first = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(matches!(first.status, OperatorStatus::Ok), "{first:?}");
+    let second = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    let retry_after = second.retry_after().context("expected a retry-after")?;
+    assert!(retry_after > Duration::from_secs(1) && retry_after <= Duration::from_secs(2));
+    let error = second.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::RateLimited),
+        "unexpected error: {error:?}"
+    );
+    let diagnostics: Vec<Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    assert_eq!(diagnostics[0].code, "rate_limited");
+    assert_eq!(diagnostics[0].path, "/op_id");
+    assert_eq!(diagnostics[0].args["provider"], PROVIDER_TYPE);
+
+    // Ops share the provider's limit but not its bucket.
+    let other = invoke_operator(&runtime, operator_request(VERSION_OP)?).await;
+    assert!(matches!(other.status, OperatorStatus::Ok), "{other:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_stats_track_invocations() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["alpha", "beta"]).await?;
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            [RouteGroup::Operator, RouteGroup::Admin],
+        )],
+        active,
+        TenantRouting::new(RoutingConfig::default()),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    let serving = tokio::spawn(server.serve());
+    let stats_url = format!("http://{addr}/admin/tenants/alpha/stats");
+
+    let before: Value = reqwest::get(&stats_url).await?.json().await?;
+    assert_eq!(before["tenant"], "alpha");
+    assert_eq!(before["packs"][0]["pack_id"], PROVIDER_PACK_ID);
+    assert_eq!(before["operator"]["invoke_attempts"], 0);
+    assert!(before["components"].as_u64() >= Some(1));
+    assert!(before["loaded_at"].is_string());
+
+    for _ in 0..2 {
+        let (status, _) = invoke_http(addr, &[(TENANT_HEADER, "alpha")], None).await?;
+        assert_eq!(status, 200);
+    }
+
+    let after: Value = reqwest::get(&stats_url).await?.json().await?;
+    assert_eq!(after["operator"]["invoke_attempts"], 2);
+    assert_eq!(after["operator"]["invoke_latency"]["count"], 2);
+    assert_eq!(after["inflight"]["invocations"], 0);
+    assert!(after["compiled_components"].as_u64() >= Some(1));
+    assert!(after["memory_cache"]["total_bytes"].as_u64() > Some(0));
+    let contract_lookups = |stats: &Value| {
+        stats["contract_cache"]["hits"].as_u64().unwrap_or_default()
+            + stats["contract_cache"]["misses"]
+                .as_u64()
+                .unwrap_or_default()
+    };
+    assert!(contract_lookups(&after) > contract_lookups(&before));
+
+    let beta: Value = reqwest::get(format!("http://{addr}/admin/tenants/beta/stats"))
+        .await?
+        .json()
+        .await?;
+    assert_eq!(
+        beta["operator"]["invoke_attempts"], 0,
+        "stats are per tenant"
+    );
+
+    let list: Value = reqwest::get(format!("http://{addr}/admin/tenants"))
+        .await?
+        .json()
+        .await?;
+    let tenants = list["tenants"].as_array().context("tenant list")?;
+    assert_eq!(tenants.len(), 2);
+    assert_eq!(tenants[0]["tenant"], "alpha");
+    assert_eq!(tenants[0]["invocations"], 2);
+    assert_eq!(tenants[1]["tenant"], "beta");
+
+    let missing = reqwest::get(format!("http://{addr}/admin/tenants/gamma/stats")).await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn memory_limit_stops_unbounded_growth() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.wasm_limits.max_memory_bytes = 64 * 1024 * 1024;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let response = invoke_operator(&runtime, operator_request(GROW_MEMORY_OP)?).await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::ResourceExhausted),
+        "unexpected error: {error:?}"
+    );
+    assert!(error.message.contains("max_memory_bytes"));
+    let diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    assert_eq!(diagnostics[0].code, "resource_exhausted");
+    assert_eq!(diagnostics[0].path, "/wasm_limits/max_memory_bytes");
+
+    // The runtime stays usable for well-behaved invocations.
+    let response = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(matches!(response.status, OperatorStatus::Ok));
+    Ok(())
+}
+
+#[tokio::test]
+async fn cpu_deadline_interrupts_spinning_component() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.wasm_limits.max_cpu_ms = 200;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let started = Instant::now();
+    let response = invoke_operator(&runtime, operator_request(SPIN_OP)?).await;
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(200 + DEADLINE_EPSILON_MS),
+        "spin ran for {elapsed:?}"
+    );
+    let diagnostic = timeout_diagnostic(response)?;
+    assert_eq!(diagnostic.code, "cpu_deadline");
+    assert_eq!(diagnostic.path, "/wasm_limits/max_cpu_ms");
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_timeout_interrupts_spinning_component() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let mut request = operator_request(SPIN_OP)?;
+    request.timeout = Some(300);
+    let started = Instant::now();
+    let response = invoke_operator(&runtime, request).await;
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(300 + DEADLINE_EPSILON_MS),
+        "spin ran for {elapsed:?}"
+    );
+    let diagnostic = timeout_diagnostic(response)?;
+    assert_eq!(diagnostic.code, "wall_clock_timeout");
+    assert_eq!(diagnostic.path, "/timeout");
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_timeout_stops_waiting_on_blocked_provider() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let mut request = operator_request(SLOW_PROVIDER_OP)?;
+    request.timeout = Some(200);
+    let started = Instant::now();
+    let response = invoke_operator(&runtime, request).await;
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(700),
+        "slow_echo ran for {elapsed:?}"
+    );
+    assert!(response.warnings.is_empty());
+    let diagnostic = timeout_diagnostic(response)?;
+    assert_eq!(diagnostic.code, "invoke_timeout");
+    assert_eq!(diagnostic.path, "/timeout");
+    assert_eq!(diagnostic.args["timeout_ms"], "200");
+    let metrics = runtime.operator_metrics().snapshot();
+    assert_eq!(metrics.invoke_timeouts, 1);
+    assert_eq!(metrics.invoke_errors, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_timeout_above_the_cap_is_clamped() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.operator_policy = OperatorPolicy::allow_all().with_max_timeout_ms(200);
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.timeout = Some(60_000);
+    let response = invoke_operator(&runtime, request).await;
+    assert!(matches!(response.status, OperatorStatus::Ok));
+    let warning = &response.warnings[0];
+    assert_eq!(warning.code, "timeout_clamped");
+    assert_eq!(warning.path, "/timeout");
+    assert_eq!(
+        warning.severity,
+        greentic_runner_host::runner::operator::DiagnosticSeverity::Warning
+    );
+    assert_eq!(warning.args["requested"], "60000");
+    assert_eq!(warning.args["max"], "200");
+
+    let mut request = operator_request(SLOW_PROVIDER_OP)?;
+    request.timeout = Some(60_000);
+    let response = invoke_operator(&runtime, request).await;
+    assert_eq!(response.warnings.len(), 1);
+    let diagnostic = timeout_diagnostic(response)?;
+    assert_eq!(diagnostic.code, "invoke_timeout");
+    assert_eq!(diagnostic.args["timeout_ms"], "200");
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeated_idempotency_key_replays_without_invoking() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let attempts = || runtime.operator_metrics().snapshot().invoke_attempts;
+    let keyed = |key: &str| -> Result<OperatorRequest> {
+        let mut request = operator_request(NOW_OP)?;
+        request.idempotency_key = Some(key.into());
+        Ok(request)
+    };
+
+    let first = invoke_operator(&runtime, keyed("order-1")?).await;
+    assert!(matches!(first.status, OperatorStatus::Ok), "{first:?}");
+    assert!(first.warnings.is_empty());
+    assert_eq!(attempts(), 1);
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let replayed = invoke_operator(&runtime, keyed("order-1")?).await;
+    assert!(matches!(replayed.status, OperatorStatus::Ok));
+    assert_eq!(replayed.cbor_output, first.cbor_output);
+    assert_eq!(replayed.warnings[0].code, "idempotent_replay");
+    assert_eq!(replayed.warnings[0].path, "/idempotency_key");
+    assert_eq!(attempts(), 1, "replay must not invoke the provider");
+
+    let other = invoke_operator(&runtime, keyed("order-2")?).await;
+    assert!(other.warnings.is_empty());
+    assert_ne!(other.cbor_output, first.cbor_output);
+    assert_eq!(attempts(), 2);
+
+    let mut request = keyed("order-1")?;
+    request.flags = vec!["no-idempotent-replay".into()];
+    let bypassed = invoke_operator(&runtime, request).await;
+    assert!(matches!(bypassed.status, OperatorStatus::Ok));
+    assert!(bypassed.warnings.is_empty());
+    assert_ne!(bypassed.cbor_output, first.cbor_output);
+    assert_eq!(attempts(), 3);
+
+    // The bypassing request stored its own response for later replays.
+    let replayed = invoke_operator(&runtime, keyed("order-1")?).await;
+    assert_eq!(replayed.cbor_output, bypassed.cbor_output);
+    assert_eq!(attempts(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reused_idempotency_key_with_another_payload_conflicts() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.idempotency_key = Some("order-1".into());
+    let first = invoke_operator(&runtime, request).await;
+    assert!(matches!(first.status, OperatorStatus::Ok), "{first:?}");
+
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.idempotency_key = Some("order-1".into());
+    request.payload.cbor_input = serde_cbor::to_vec(&json!({"message": "pong"}))?;
+    let response = invoke_operator(&runtime, request).await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected error response")?;
+    assert!(matches!(error.code, OperatorErrorCode::IdempotencyConflict));
+    let diagnostics: Vec<Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    assert_eq!(diagnostics[0].code, "idempotency_conflict");
+    assert_eq!(diagnostics[0].path, "/idempotency_key");
+    assert_eq!(runtime.operator_metrics().snapshot().invoke_attempts, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn correlation_id_alone_does_not_replay() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    for _ in 0..2 {
+        let mut request = operator_request(NOW_OP)?;
+        request.correlation_id = Some("conversation-1".into());
+        let response = invoke_operator(&runtime, request).await;
+        assert!(response.warnings.is_empty());
+    }
+    assert_eq!(runtime.operator_metrics().snapshot().invoke_attempts, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_duplicates_invoke_once() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let keyed = || -> Result<OperatorRequest> {
+        let mut request = operator_request(SLOW_PROVIDER_OP)?;
+        request.idempotency_key = Some("delivery-7".into());
+        Ok(request)
+    };
+
+    let (first, second) = tokio::join!(
+        invoke_operator(&runtime, keyed()?),
+        invoke_operator(&runtime, keyed()?)
+    );
+    assert!(matches!(first.status, OperatorStatus::Ok), "{first:?}");
+    assert!(matches!(second.status, OperatorStatus::Ok), "{second:?}");
+    assert_eq!(first.cbor_output, second.cbor_output);
+    let replays = [&first, &second]
+        .iter()
+        .filter(|response| {
+            response
+                .warnings
+                .iter()
+                .any(|warning| warning.code == "idempotent_replay")
+        })
+        .count();
+    assert_eq!(replays, 1);
+    assert_eq!(runtime.operator_metrics().snapshot().invoke_attempts, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_invocations_are_not_replayed() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    for _ in 0..2 {
+        let mut request = operator_request(UNREACHABLE_OP)?;
+        request.idempotency_key = Some("trap-1".into());
+        let response = invoke_operator(&runtime, request).await;
+        assert!(matches!(response.status, OperatorStatus::Error));
+    }
+    assert_eq!(runtime.operator_metrics().snapshot().invoke_attempts, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn trap_reports_code_and_frames() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let response = invoke_operator(&runtime, operator_request(UNREACHABLE_OP)?).await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::InvokeTrap),
+        "unexpected error: {error:?}"
+    );
+    let diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.code, "invoke_trap");
+    assert_eq!(diagnostic.component_id.as_deref(), Some("provider.dummy"));
+    let details = diagnostic.details.as_ref().context("trap details")?;
+    assert_eq!(details["export"], json!("invoke"));
+    assert_eq!(details["code"], json!("UnreachableCodeReached"));
+    let frames = details["frames"].as_array().context("frames")?;
+    assert!(!frames.is_empty(), "no frames in {details}");
+    assert!(frames.len() <= greentic_runner_host::trap::MAX_TRAP_FRAMES);
+    assert!(diagnostic.hint.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn trap_diagnostics_carry_component_stderr() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let response = invoke_operator(&runtime, operator_request(LOG_AND_TRAP_OP)?).await;
+    assert!(response.logs.is_none());
+    let error = response.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::InvokeTrap),
+        "unexpected error: {error:?}"
+    );
+    let diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    let details = diagnostics[0].details.as_ref().context("trap details")?;
+    assert_eq!(details["code"], json!("UnreachableCodeReached"));
+    assert_eq!(
+        details["stdio"]["stderr"],
+        json!("log_and_trap: giving up on purpose\n")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn secrets_leaked_by_a_component_are_redacted() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.redaction = RedactionPolicy::from_config(&RedactionConfig {
+        patterns: vec!["sk-[A-Za-z0-9]{20,}".into()],
+    })?;
+    let manager = Arc::new(FixedSecretsManager("tok-1234567890"));
+    let runtime = setup_runtime_with_secrets(
+        &pack_path,
+        Arc::new(config),
+        RunnerWasiPolicy::new(),
+        manager,
+    )
+    .await?;
+
+    let mut request = secret_request("API_TOKEN")?;
+    request.op_id = LEAK_AND_TRAP_OP.into();
+    request.flags.push("include-logs".into());
+    request.payload.cbor_input =
+        serde_cbor::to_vec(&json!({"message": "sk-abcdefghijklmnopqrstuvwx"}))?;
+    let response = invoke_operator(&runtime, request).await;
+
+    let logs = response.logs.as_ref().context("expected captured logs")?;
+    let error = response.error.as_ref().context("expected error response")?;
+    let diagnostics: Value =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    let reported = format!(
+        "{} {} {} {diagnostics}",
+        error.message, logs.stdout, logs.stderr
+    );
+    assert!(reported.contains("[REDACTED:API_TOKEN]"), "{reported}");
+    assert!(reported.contains("[REDACTED]"), "{reported}");
+    assert!(!reported.contains("tok-1234567890"), "{reported}");
+    assert!(
+        !reported.contains("sk-abcdefghijklmnopqrstuvwx"),
+        "{reported}"
+    );
+    assert!(
+        logs.stderr.contains("leak_and_trap rejected"),
+        "{:?}",
+        logs.stderr
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn include_logs_flag_returns_component_stdio() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let response = invoke_operator(&runtime, operator_request(LOG_ECHO_OP)?).await;
+    assert!(matches!(response.status, OperatorStatus::Ok));
+    assert!(response.logs.is_none());
+
+    let mut request = operator_request(LOG_ECHO_OP)?;
+    request.flags.push("include-logs".into());
+    let response = invoke_operator(&runtime, request).await;
+    assert!(matches!(response.status, OperatorStatus::Ok));
+    let logs = response.logs.context("expected captured logs")?;
+    assert!(logs.stdout.starts_with("log_echo stdout: "), "{logs:?}");
+    assert_eq!(logs.stderr, "log_echo stderr: done\n");
+    assert_eq!(logs.truncated_bytes, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn deterministic_mode_freezes_the_component_clock() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.wasi.deterministic = Some(DeterministicConfig {
+        seed: 7,
+        start_unix_ms: 1_000_000,
+        step_ms: 0,
+    });
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let mut seen = Vec::new();
+    for _ in 0..2 {
+        let response = invoke_operator(&runtime, operator_request(NOW_OP)?).await;
+        let output: Value = serde_cbor::from_slice(
+            response
+                .cbor_output
+                .as_deref()
+                .context("expected cbor output")?,
+        )?;
+        seen.push(output["unix_ms"].clone());
+    }
+    assert_eq!(seen, vec![json!(1_000_000), json!(1_000_000)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn binding_pack_wins_when_packs_share_a_component_ref() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let component = build_provider_component()?;
+    let v2_component = workspace.path().join("provider_v2.wasm");
+    write_v2_component(&component, &v2_component)?;
+    let base = workspace.path().join("base.gtpack");
+    build_provider_pack(&component, &base)?;
+    let upgrade = workspace.path().join("upgrade.gtpack");
+    write_provider_pack_as(
+        &v2_component,
+        &upgrade,
+        "operator.provider.v2",
+        PROVIDER_COMPONENT_REF,
+        Some(PROVIDER_CONFIG_SCHEMA),
+        None,
+    )?;
+    let runtime =
+        setup_multi_pack_runtime(&[&base, &upgrade], minimal_config(workspace.path())?).await?;
+
+    // The later pack re-declares the provider, so its binding names the v2 pack.
+    assert_eq!(version_output(&runtime).await?, "fixture-build:v2");
+    let binding = runtime
+        .operator_registry()
+        .resolve(None, Some(PROVIDER_TYPE), VERSION_OP)
+        .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+    let resolved = runtime.resolve_component(PROVIDER_COMPONENT_REF, binding.pack_preference())?;
+    assert_eq!(resolved.pack_ref, "operator.provider.v2@0.1.0");
+    assert_eq!(resolved.fallback_from, None);
+
+    let pinned = PackPreference {
+        pack_ref: Some(PROVIDER_PACK_ID),
+        pack_digest: None,
+    };
+    let resolved = runtime.resolve_component(PROVIDER_COMPONENT_REF, pinned)?;
+    assert_eq!(resolved.pack_ref, "operator.provider@0.1.0");
+
+    let ambiguous = runtime
+        .resolve_component(PROVIDER_COMPONENT_REF, PackPreference::default())
+        .err()
+        .context("expected ambiguity")?;
+    assert_eq!(
+        ambiguous,
+        ComponentResolveError::Ambiguous {
+            component_ref: PROVIDER_COMPONENT_REF.to_string(),
+            candidates: vec![
+                "operator.provider@0.1.0".to_string(),
+                "operator.provider.v2@0.1.0".to_string(),
+            ],
+        }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn resolution_falls_back_when_binding_pack_lacks_the_component() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let component = build_provider_component()?;
+    let base = workspace.path().join("base.gtpack");
+    build_provider_pack(&component, &base)?;
+    // Declares the provider but ships the component under another ref.
+    let shadow = workspace.path().join("shadow.gtpack");
+    write_provider_pack_as(
+        &component,
+        &shadow,
+        "operator.provider.shadow",
+        "provider.other",
+        Some(PROVIDER_CONFIG_SCHEMA),
+        None,
+    )?;
+    let runtime =
+        setup_multi_pack_runtime(&[&base, &shadow], minimal_config(workspace.path())?).await?;
+
+    let binding = runtime
+        .operator_registry()
+        .resolve(None, Some(PROVIDER_TYPE), VERSION_OP)
+        .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+    assert_eq!(binding.pack_ref, "operator.provider.shadow@0.1.0");
+    let resolved = runtime.resolve_component(PROVIDER_COMPONENT_REF, binding.pack_preference())?;
+    assert_eq!(resolved.pack_ref, "operator.provider@0.1.0");
+    assert_eq!(
+        resolved.fallback_from.as_deref(),
+        Some("operator.provider.shadow@0.1.0")
+    );
+    assert_eq!(version_output(&runtime).await?, "fixture-build:v1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn ops_listing_follows_operator_policy() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let registry = runtime.operator_registry();
+
+    let all = registry.list_operations(&OperatorPolicy::allow_all());
+    let ops = all.iter().map(|op| op.op_id.as_str()).collect::<Vec<_>>();
+    let mut sorted = ops.clone();
+    sorted.sort();
+    assert_eq!(ops, sorted);
+    assert_eq!(ops.len(), 11);
+    assert_eq!(
+        all.iter().find(|op| op.op_id == PROVIDER_OP),
+        Some(&OperatorOpDescriptor {
+            provider_id: None,
+            provider_type: PROVIDER_TYPE.into(),
+            op_id: PROVIDER_OP.into(),
+            pack_ref: "operator.provider@0.1.0".into(),
+            capabilities: Vec::new(),
+            config_schema_ref: Some("schemas/config.schema.json".into()),
+            docs_ref: None,
+        })
+    );
+
+    let restricted = OperatorPolicy::from_config(OperatorPolicyConfig {
+        allowed_providers: vec![PROVIDER_TYPE.into()],
+        allowed_ops: HashMap::from([(
+            PROVIDER_TYPE.to_string(),
+            vec![VERSION_OP.to_string(), PROVIDER_OP.to_string()],
+        )]),
+        ..Default::default()
+    });
+    let ops = registry
+        .list_operations(&restricted)
+        .into_iter()
+        .map(|op| op.op_id)
+        .collect::<Vec<_>>();
+    assert_eq!(ops, vec![PROVIDER_OP, VERSION_OP]);
+
+    let other_provider = OperatorPolicy::from_config(OperatorPolicyConfig {
+        allowed_providers: vec!["example.other".into()],
+        ..Default::default()
+    });
+    assert!(registry.list_operations(&other_provider).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn operator_policy_enforces_declared_capabilities() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    write_provider_pack_declaring(
+        &build_provider_component()?,
+        &pack_path,
+        PROVIDER_PACK_ID,
+        PROVIDER_COMPONENT_REF,
+        Some(PROVIDER_CONFIG_SCHEMA),
+        None,
+        &["network-egress", "filesystem"],
+    )?;
+    let cases = [
+        ("  denied_capabilities: [secrets]\n", None),
+        (
+            "  denied_capabilities: [network-egress]\n",
+            Some("network-egress"),
+        ),
+        (
+            "  default_deny_capabilities: true\n  allowed_capabilities: [filesystem]\n",
+            Some("network-egress"),
+        ),
+        (
+            "  default_deny_capabilities: true\n  allowed_capabilities: [filesystem, network-egress]\n",
+            None,
+        ),
+    ];
+    for (operator, refused) in cases {
+        let config = operator_block_config(workspace.path(), operator)?;
+        let runtime = setup_runtime(&pack_path, config).await?;
+        let listed = runtime
+            .operator_registry()
+            .list_operations(&runtime.config().operator_policy);
+        let response = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+        let Some(capability) = refused else {
+            assert!(
+                matches!(response.status, OperatorStatus::Ok),
+                "{operator}: {response:?}"
+            );
+            assert_eq!(listed.len(), 11, "{operator}");
+            continue;
+        };
+        assert!(listed.is_empty(), "{operator}: refused ops are not listed");
+        let error = response.error.context("expected error response")?;
+        assert!(
+            matches!(error.code, OperatorErrorCode::PolicyDenied),
+            "{operator}: {error:?}"
+        );
+        let diagnostics: Vec<Diagnostic> =
+            serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+        assert_eq!(diagnostics[0].code, "capability_denied");
+        assert_eq!(diagnostics[0].path, "/capabilities");
+        assert_eq!(diagnostics[0].args["capability"], capability);
+        assert_eq!(
+            runtime.operator_metrics().snapshot().invoke_attempts,
+            0,
+            "{operator}: refused ops never reach the component"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn ops_listing_names_the_pack_that_wins() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let component = build_provider_component()?;
+    let base = workspace.path().join("base.gtpack");
+    build_provider_pack(&component, &base)?;
+    let upgrade = workspace.path().join("upgrade.gtpack");
+    write_provider_pack_as(
+        &component,
+        &upgrade,
+        "operator.provider.v2",
+        PROVIDER_COMPONENT_REF,
+        Some(PROVIDER_CONFIG_SCHEMA),
+        None,
+    )?;
+    let runtime =
+        setup_multi_pack_runtime(&[&base, &upgrade], minimal_config(workspace.path())?).await?;
+
+    // Both packs declare every op; each is listed once, from the later pack.
+    let ops = runtime
+        .operator_registry()
+        .list_operations(&OperatorPolicy::allow_all());
+    assert_eq!(ops.len(), 11);
+    assert!(
+        ops.iter()
+            .all(|op| op.pack_ref == "operator.provider.v2@0.1.0"),
+        "{ops:?}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn ops_endpoint_answers_in_the_accepted_encoding() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let active = load_tenants(workspace.path(), &["solo"]).await?;
+    let (addr, serving) = serve_operator(active, RoutingConfig::default()).await?;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("http://{addr}/operator/ops"))
+        .header("accept", "application/json")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let json_ops: Vec<Value> = response.json().await?;
+
+    let response = client
+        .get(format!("http://{addr}/operator/ops"))
+        .header("accept", "application/cbor")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/cbor");
+    let cbor_ops: Vec<Value> = serde_cbor::from_slice(&response.bytes().await?)?;
+
+    assert_eq!(json_ops, cbor_ops);
+    assert_eq!(json_ops.len(), 11);
+    assert_eq!(json_ops[0]["provider_type"], PROVIDER_TYPE);
+    assert_eq!(json_ops[0]["op_id"], PROVIDER_OP);
+    serving.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeated_invokes_reuse_linked_component() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+
+    let first = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(matches!(first.status, OperatorStatus::Ok));
+    let linked = runtime.pack().instance_pre_stats();
+    assert!(linked.links >= 1);
+
+    let second = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(matches!(second.status, OperatorStatus::Ok));
+    assert_eq!(first.cbor_output, second.cbor_output);
+    let stats = runtime.pack().instance_pre_stats();
+    assert_eq!(stats.links, linked.links, "second invoke must not relink");
+    assert!(stats.hits > linked.hits);
+    Ok(())
+}
+
+#[tokio::test]
+async fn lazy_loading_compiles_component_on_first_invoke() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = setup_lazy_runtime(workspace.path()).await?;
+    let registry = runtime.operator_registry();
+    assert!(
+        registry
+            .resolve(None, Some(PROVIDER_TYPE), PROVIDER_OP)
+            .is_ok()
+    );
+    assert_eq!(runtime.pack().cache().metrics().compiles, 0);
+
+    for _ in 0..2 {
+        let response = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+        assert!(matches!(response.status, OperatorStatus::Ok));
+        assert_eq!(runtime.pack().cache().metrics().compiles, 1);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn strict_preflight_compiles_lazy_components() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let runtime = setup_lazy_runtime(workspace.path()).await?;
+    let active = ActivePacks::new();
+    active.replace(HashMap::from([("demo".to_string(), Arc::clone(&runtime))]));
+
+    preflight::compile_lazy_components(&active).await?;
+    assert_eq!(runtime.pack().cache().metrics().compiles, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pooling_allocator_matches_on_demand_results() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut on_demand = (*minimal_config(workspace.path())?).clone();
+    on_demand.wasm_limits.max_memory_bytes = 64 * 1024 * 1024;
+    let mut pooled = on_demand.clone();
+    pooled.wasm_engine = pooling_engine(PoolingLimits::default());
+
+    let on_demand = setup_runtime(&pack_path, Arc::new(on_demand)).await?;
+    let pooled = setup_runtime(&pack_path, Arc::new(pooled)).await?;
+
+    for op in [PROVIDER_OP, VERSION_OP, GROW_MEMORY_OP, "missing_op"] {
+        let expected = invoke_operator(&on_demand, operator_request(op)?).await;
+        // Run twice so the second invoke reuses a pooled slot.
+        for _ in 0..2 {
+            let actual = invoke_operator(&pooled, operator_request(op)?).await;
+            assert_eq!(
+                format!("{:?}", actual.status),
+                format!("{:?}", expected.status),
+                "{op}"
+            );
+            assert_eq!(actual.cbor_output, expected.cbor_output, "{op}");
+            assert_eq!(
+                actual.error.map(|error| format!("{:?}", error.code)),
+                expected
+                    .error
+                    .as_ref()
+                    .map(|error| format!("{:?}", error.code)),
+                "{op}"
+            );
+        }
+    }
+    assert_eq!(
+        input_diagnostic_digest(&pooled).await?,
+        input_diagnostic_digest(&on_demand).await?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_slot_too_small_reports_pool_exhausted() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.wasm_engine = pooling_engine(PoolingLimits {
+        max_memory_pages: 1,
+        ..PoolingLimits::default()
+    });
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let response = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::ResourceExhausted),
+        "unexpected error: {error:?}"
+    );
+    let diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    assert_eq!(diagnostics[0].code, "pool_exhausted");
+    assert_eq!(diagnostics[0].path, "/wasm_engine/pooling");
+    Ok(())
+}
+
+#[tokio::test]
+async fn secret_attachments_are_audited() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let allowed = minimal_config(workspace.path())?;
+    // The bindings file lists no secrets, so its own policy denies every key.
+    let mut denied = (*allowed).clone();
+    denied.secrets_policy = HostConfig::load_from_path(&allowed.bindings_path)?.secrets_policy;
+
+    let runtime = setup_runtime(&pack_path, allowed).await?;
+    let sink = Arc::new(MemoryAuditSink::default());
+    runtime.audit().set_sink(sink.clone());
+    for _ in 0..2 {
+        invoke_operator(&runtime, secret_request("API_TOKEN")?).await;
+    }
+    let events = sink.events();
+    assert_eq!(events.len(), 1, "hot keys are rate limited: {events:?}");
+    let AuditEvent::SecretAccess(event) = &events[0] else {
+        panic!("expected secret access event, got {:?}", events[0]);
+    };
+    assert_eq!(event.tenant, "demo");
+    assert_eq!(event.key, "API_TOKEN");
+    assert_eq!(event.decision, SecretDecision::Allowed);
+    assert_eq!(event.op.as_deref(), Some(PROVIDER_OP));
+    assert!(event.component_ref.is_some());
+
+    let runtime = setup_runtime(&pack_path, Arc::new(denied)).await?;
+    let sink = Arc::new(MemoryAuditSink::default());
+    runtime.audit().set_sink(sink.clone());
+    for _ in 0..2 {
+        let response = invoke_operator(&runtime, secret_request("API_TOKEN")?).await;
+        let error = response.error.context("expected policy denial")?;
+        assert!(matches!(error.code, OperatorErrorCode::PolicyDenied));
+    }
+    let events = sink.events();
+    assert_eq!(events.len(), 2, "denials are never rate limited");
+    assert!(events.iter().all(|event| matches!(
+        event,
+        AuditEvent::SecretAccess(event)
+            if event.decision == SecretDecision::Denied && event.key == "API_TOKEN"
+    )));
+    Ok(())
+}
+
+#[tokio::test]
+async fn secret_denial_names_the_matching_rule() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.secrets_policy =
+        SecretsPolicy::allow_all().with_rule(SecretRule::deny("no-prod", "*_PROD_*"));
+    let runtime = setup_runtime(&pack_path, Arc::new(config)).await?;
+
+    let response = invoke_operator(&runtime, secret_request("API_PROD_TOKEN")?).await;
+    let error = response.error.context("expected policy denial")?;
+    assert!(matches!(error.code, OperatorErrorCode::PolicyDenied));
+    assert!(error.message.contains("`no-prod`"), "{}", error.message);
+    Ok(())
+}
+
+#[tokio::test]
+async fn impersonation_is_denied_unless_tenant_allows_it() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let sink = Arc::new(MemoryAuditSink::default());
+    runtime.audit().set_sink(sink.clone());
+
+    let response = invoke_operator(&runtime, impersonated_request(PROVIDER_OP)?).await;
+    let error = response.error.context("expected impersonation denial")?;
+    assert!(matches!(error.code, OperatorErrorCode::PolicyDenied));
+    assert!(error.message.contains("support-7"), "{}", error.message);
+    let events = sink.events();
+    assert_eq!(events.len(), 1);
+    let AuditEvent::Impersonation(event) = &events[0] else {
+        panic!("expected impersonation event, got {:?}", events[0]);
+    };
+    assert!(!event.allowed);
+    assert_eq!(event.actor_id, "support-7");
+    assert_eq!(event.reason.as_deref(), Some("ticket 42"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn allowed_impersonation_is_audited_with_secret_reads() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config.operator_policy = OperatorPolicy::allow_all().with_impersonation(true);
+    let manager = Arc::new(CountingSecretsManager::default());
+    let runtime = setup_runtime_with_secrets(
+        &pack_path,
+        Arc::new(config),
+        RunnerWasiPolicy::new(),
+        manager,
+    )
+    .await?;
+    let sink = Arc::new(MemoryAuditSink::default());
+    runtime.audit().set_sink(sink.clone());
+
+    let mut request = impersonated_request(PROVIDER_OP)?;
+    request.payload.attachments = secret_request("API_TOKEN")?.payload.attachments;
+    let response = invoke_operator(&runtime, request).await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+
+    let events = sink.events();
+    assert_eq!(events.len(), 2, "{events:?}");
+    let AuditEvent::Impersonation(impersonation) = &events[0] else {
+        panic!("expected impersonation event, got {:?}", events[0]);
+    };
+    assert!(impersonation.allowed);
+    assert_eq!(impersonation.op.as_deref(), Some(PROVIDER_OP));
+    let AuditEvent::SecretAccess(access) = &events[1] else {
+        panic!("expected secret access event, got {:?}", events[1]);
+    };
+    assert_eq!(access.actor_id.as_deref(), Some("support-7"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn secret_reads_are_cached_until_invalidated() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let config = minimal_config(workspace.path())?;
+    let manager = Arc::new(CountingSecretsManager::default());
+    let runtime =
+        setup_runtime_with_secrets(&pack_path, config, RunnerWasiPolicy::new(), manager.clone())
+            .await?;
+
+    for _ in 0..3 {
+        let response = invoke_operator(&runtime, secret_request("API_TOKEN")?).await;
+        assert!(response.error.is_none(), "{:?}", response.error);
+    }
+    assert_eq!(
+        manager.reads(),
+        1,
+        "repeated reads are served from the cache"
+    );
+
+    assert_eq!(runtime.secrets_cache().invalidate_tenant("demo"), 1);
+    let response = invoke_operator(&runtime, secret_request("API_TOKEN")?).await;
+    assert!(response.error.is_none(), "{:?}", response.error);
+    assert_eq!(manager.reads(), 2, "invalidation forces a backend read");
+    Ok(())
+}
+
+#[tokio::test]
+async fn rotated_file_secret_is_served_on_next_attachment() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let secrets_dir = workspace.path().join("secrets");
+    std::fs::create_dir_all(&secrets_dir)?;
+    std::fs::write(secrets_dir.join("API_TOKEN"), "v1")?;
+    let mut file_config = FileSecretsConfig::new(&secrets_dir);
+    // Long enough that only the published rotation can refresh the value.
+    file_config.debounce = Duration::from_secs(3600);
+    let manager: DynSecretsManager = Arc::new(FileSecretsManager::new(file_config)?);
+    // A tenant of its own, so the rotation does not reach other tests' caches.
+    let config = tenant_config(workspace.path(), "rotating")?;
+    let runtime =
+        setup_runtime_with_secrets(&pack_path, config, RunnerWasiPolicy::new(), manager).await?;
+
+    assert_eq!(
+        secret_attachment(&runtime, "rotating", "API_TOKEN").await?,
+        "v1"
+    );
+    std::fs::write(secrets_dir.join("API_TOKEN"), "v2")?;
+    assert_eq!(
+        secret_attachment(&runtime, "rotating", "API_TOKEN").await?,
+        "v1",
+        "served from cache until the rotation is published"
+    );
+    rotation::publish(SecretRotation::new("rotating", "API_TOKEN"));
+    assert_eq!(
+        secret_attachment(&runtime, "rotating", "API_TOKEN").await?,
+        "v2"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_preopen_grants_scratch_directory() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let scratch = workspace.path().join("preopens").join("scratch");
+    std::fs::create_dir_all(&scratch)?;
+    let policy =
+        RunnerWasiPolicy::new().allow_tenant_preopen_root(workspace.path().join("preopens"));
+
+    let unconfigured = minimal_config(workspace.path())?;
+    let mut granted = (*unconfigured).clone();
+    granted
+        .wasi
+        .preopens
+        .push(PreopenSpec::new(&scratch, "/scratch"));
+
+    let runtime = setup_runtime_with_policy(&pack_path, unconfigured, policy.clone()).await?;
+    let output = write_file_output(&runtime, "/scratch/denied.txt").await?;
+    assert!(output.get("error").is_some(), "unexpected output: {output}");
+    assert!(!scratch.join("denied.txt").exists());
+
+    let runtime = setup_runtime_with_policy(&pack_path, Arc::new(granted), policy).await?;
+    let output = write_file_output(&runtime, "/scratch/granted.txt").await?;
+    assert_eq!(output["message"], "/scratch/granted.txt", "{output}");
+    let written: Value = serde_json::from_slice(&std::fs::read(scratch.join("granted.txt"))?)?;
+    assert_eq!(written["message"], "/scratch/granted.txt");
+
+    // Guest paths cannot climb out of the preopened directory.
+    let output = write_file_output(&runtime, "/scratch/../escaped.txt").await?;
+    assert!(output.get("error").is_some(), "unexpected output: {output}");
+    assert!(
+        !workspace
+            .path()
+            .join("preopens")
+            .join("escaped.txt")
+            .exists()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_preopen_outside_root_fails_tenant_load() -> Result<()> {
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let root = workspace.path().join("preopens");
+    std::fs::create_dir_all(&root)?;
+    let mut config = (*minimal_config(workspace.path())?).clone();
+    config
+        .wasi
+        .preopens
+        .push(PreopenSpec::new(workspace.path(), "/workspace"));
+
+    let policy = RunnerWasiPolicy::new().allow_tenant_preopen_root(&root);
+    let err = match setup_runtime_with_policy(&pack_path, Arc::new(config), policy).await {
+        Ok(_) => anyhow::bail!("tenant load should reject the preopen"),
+        Err(err) => err,
+    };
+    assert!(
+        format!("{err:#}").contains("outside the allowed preopen roots"),
+        "{err:#}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn reload_pack_serves_new_pack_on_next_invoke() -> Result<()> {
+    const V2_DIGEST: &str = "sha256:feedface";
+    let workspace = TempDir::new()?;
+    let component_path = build_provider_component()?;
+    let v1_pack = workspace.path().join("operator-provider-v1.gtpack");
+    build_provider_pack(&component_path, &v1_pack)?;
+    let v2_component = workspace.path().join("provider-v2.wasm");
+    write_v2_component(&component_path, &v2_component)?;
+    let v2_pack = workspace.path().join("operator-provider-v2.gtpack");
+    build_provider_pack(&v2_component, &v2_pack)?;
+
+    let v1 = setup_runtime(&v1_pack, minimal_config(workspace.path())?).await?;
+    let active = ActivePacks::new();
+    active.replace(HashMap::from([("demo".to_string(), Arc::clone(&v1))]));
+    assert_eq!(version_output(&v1).await?, "fixture-build:v1");
+    let digest_before = input_diagnostic_digest(&v1).await?;
+
+    // A pack that fails to load leaves the current runtime serving.
+    let missing = workspace.path().join("missing.gtpack");
+    assert!(active.reload_pack("demo", &missing, None).await.is_err());
+    assert!(Arc::ptr_eq(&active.load("demo").context("demo")?, &v1));
+
+    let reloaded = active
+        .reload_pack("demo", &v2_pack, Some(V2_DIGEST.to_string()))
+        .await?;
+    assert_eq!(reloaded.digest.as_deref(), Some(V2_DIGEST));
+    let v2 = active.load("demo").context("demo")?;
+    assert!(Arc::ptr_eq(&v2, &reloaded.runtime));
+    assert_eq!(version_output(&v2).await?, "fixture-build:v2");
+    let digest_after = input_diagnostic_digest(&v2).await?;
+    assert_ne!(digest_before, digest_after);
+    assert_eq!(digest_after.as_deref(), Some(V2_DIGEST));
+
+    // Callers still holding the previous runtime finish against the old pack.
+    assert_eq!(version_output(&v1).await?, "fixture-build:v1");
+    Ok(())
+}
+
+#[cfg(feature = "telemetry")]
+#[tokio::test(flavor = "multi_thread")]
+async fn configured_otlp_exporter_receives_operator_spans() -> Result<()> {
+    use greentic_runner_host::{TelemetryCfg, boot, trace::TraceExportConfig};
+
+    let (collector, exports) = spawn_otlp_collector().await?;
+    let export: TraceExportConfig = toml::from_str(&format!(
+        r#"
+exporter = "otlp-http"
+endpoint = "http://{collector}"
+service_name = "operator-invoke-test"
+
+[batch]
+scheduled_delay_ms = 100
+"#
+    ))?;
+    let telemetry =
+        TelemetryCfg::from_trace_export(&export).context("otlp-http should export spans")?;
+    boot::init(&HealthState::new(), Some(&telemetry))?;
+
+    let workspace = TempDir::new()?;
+    let pack_path = workspace.path().join("operator-provider.gtpack");
+    build_provider_pack(&build_provider_component()?, &pack_path)?;
+    let runtime = setup_runtime(&pack_path, minimal_config(workspace.path())?).await?;
+    let response = invoke_operator(&runtime, operator_request(PROVIDER_OP)?).await;
+    assert!(
+        matches!(response.status, OperatorStatus::Ok),
+        "unexpected response: {response:?}"
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while exports.load(Ordering::SeqCst) == 0 {
+        assert!(
+            Instant::now() < deadline,
+            "no spans reached the OTLP collector"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+/// A stand-in OTLP collector on a loopback port; counts the export requests it gets.
+#[cfg(feature = "telemetry")]
+async fn spawn_otlp_collector() -> Result<(SocketAddr, Arc<AtomicUsize>)> {
+    use axum::{Router, body::Bytes, extract::State};
+
+    let exports = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .fallback(
+            |State(exports): State<Arc<AtomicUsize>>, body: Bytes| async move {
+                if !body.is_empty() {
+                    exports.fetch_add(1, Ordering::SeqCst);
+                }
+                StatusCode::OK
+            },
+        )
+        .with_state(Arc::clone(&exports));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    Ok((addr, exports))
+}
+
+/// Fields of the latest span of each name, as a capturing subscriber layer sees them.
+#[derive(Clone, Default)]
+struct SpanCapture(Arc<Mutex<HashMap<&'static str, BTreeMap<String, String>>>>);
+
+impl SpanCapture {
+    fn fields(&self, span: &str) -> BTreeMap<String, String> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(span)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl<S> Layer<S> for SpanCapture
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: LayerContext<'_, S>) {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldCapture(&mut fields));
+        self.0
+            .lock()
+            .unwrap()
+            .insert(attrs.metadata().name(), fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut spans = self.0.lock().unwrap();
+        values.record(&mut FieldCapture(spans.entry(span.name()).or_default()));
+    }
+}
+
+struct FieldCapture<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldCapture<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+async fn load_tenants(workspace: &Path, tenants: &[&str]) -> Result<Arc<ActivePacks>> {
+    let pack_path = workspace.join("operator-provider.gtpack");
+    let component_path = build_provider_component()?;
+    build_provider_pack(&component_path, &pack_path)?;
+    let mut runtimes = HashMap::new();
+    for tenant in tenants {
+        let config = tenant_config(workspace, tenant)?;
+        runtimes.insert(tenant.to_string(), setup_runtime(&pack_path, config).await?);
+    }
+    let active = Arc::new(ActivePacks::new());
+    active.replace(runtimes);
+    Ok(active)
+}
+
+/// Serve the operator route group on an ephemeral loopback port.
+async fn serve_operator(
+    active: Arc<ActivePacks>,
+    routing: RoutingConfig,
+) -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()>>)> {
+    serve_groups(active, routing, &[RouteGroup::Operator]).await
+}
+
+async fn serve_groups(
+    active: Arc<ActivePacks>,
+    routing: RoutingConfig,
+    groups: &[RouteGroup],
+) -> Result<(SocketAddr, tokio::task::JoinHandle<Result<()>>)> {
+    let server = HostServer::with_listeners(
+        vec![ListenerConfig::new(
+            ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+            groups.iter().copied(),
+        )],
+        active,
+        TenantRouting::new(routing),
+        Arc::new(HealthState::new()),
+        None,
+        AdminAuth::default(),
+    )?
+    .bind()
+    .await?;
+    let addr = match server.local_addrs()?.pop() {
+        Some(ListenAddr::Tcp(addr)) => addr,
+        other => anyhow::bail!("unexpected listener address {other:?}"),
+    };
+    Ok((addr, tokio::spawn(server.serve())))
+}
+
+/// POST an echo invoke as CBOR; returns the HTTP status and raw response body.
+async fn invoke_http(
+    addr: SocketAddr,
+    headers: &[(&str, &str)],
+    tenant_id: Option<&str>,
+) -> Result<(u16, Vec<u8>)> {
+    invoke_op_http(addr, headers, tenant_id, PROVIDER_OP).await
+}
+
+async fn invoke_op_http(
+    addr: SocketAddr,
+    headers: &[(&str, &str)],
+    tenant_id: Option<&str>,
+    op_id: &str,
+) -> Result<(u16, Vec<u8>)> {
+    let body = serde_cbor::to_vec(&json!({
+        "tenant_id": tenant_id,
+        "provider_type": PROVIDER_TYPE,
+        "op_id": op_id,
+        "payload": {
+            "cbor_input": serde_cbor::to_vec(&json!({"message": "ping"}))?,
+        },
+    }))?;
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{addr}/operator/op/invoke"))
+        .header("content-type", "application/cbor")
+        .body(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = request.send().await?;
+    let status = response.status().as_u16();
+    Ok((status, response.bytes().await?.to_vec()))
+}
+
+fn is_tenant_mismatch(body: &[u8], routed: &str, requested: &str) -> Result<bool> {
+    let expected = format!("routing resolved `{routed}` but request wants `{requested}`");
+    Ok(cbor_output(body)?.is_none()
+        && cbor_texts(&serde_cbor::from_slice(body)?)
+            .iter()
+            .any(|text| text.contains(&expected)))
+}
+
+/// Decoded provider output of a successful CBOR response (the only top-level byte array).
+fn cbor_output(body: &[u8]) -> Result<Option<Value>> {
+    let response: serde_cbor::Value = serde_cbor::from_slice(body)?;
+    let serde_cbor::Value::Map(fields) = response else {
+        anyhow::bail!("operator response is not a CBOR map");
+    };
+    let Some(items) = fields.values().find_map(|value| match value {
+        serde_cbor::Value::Array(items) => Some(items),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let bytes = items
+        .iter()
+        .map(|item| match item {
+            serde_cbor::Value::Integer(byte) => u8::try_from(*byte).context("output byte"),
+            _ => anyhow::bail!("cbor_output must be a byte array"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(serde_cbor::from_slice(&bytes)?))
+}
+
+fn cbor_texts(value: &serde_cbor::Value) -> Vec<String> {
+    match value {
+        serde_cbor::Value::Text(text) => vec![text.clone()],
+        serde_cbor::Value::Array(items) => items.iter().flat_map(cbor_texts).collect(),
+        serde_cbor::Value::Map(fields) => fields.values().flat_map(cbor_texts).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Copy of the fixture component whose `version` op reports the v2 build marker.
+fn write_v2_component(component_path: &Path, target: &Path) -> Result<()> {
+    let mut bytes = std::fs::read(component_path)?;
+    let offsets = bytes
+        .windows(BUILD_MARKER_V1.len())
+        .enumerate()
+        .filter(|(_, window)| *window == BUILD_MARKER_V1)
+        .map(|(offset, _)| offset)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets.len(), 1, "build marker must appear exactly once");
+    bytes[offsets[0]..offsets[0] + BUILD_MARKER_V2.len()].copy_from_slice(BUILD_MARKER_V2);
+    std::fs::write(target, bytes)?;
+    Ok(())
+}
+
+/// Copy of the fixture component with a custom section unique to this call, so its
+/// artifact cache key cannot hit the on-disk cache left by earlier runs.
+fn write_uncached_component(component_path: &Path, target: &Path) -> Result<()> {
+    let name = b"greentic-test-nonce";
+    let nonce = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let mut section = vec![name.len() as u8];
+    section.extend_from_slice(name);
+    section.extend_from_slice(format!("{nonce}-{}", std::process::id()).as_bytes());
+    assert!(
+        section.len() < 0x80,
+        "section size must fit one LEB128 byte"
+    );
+
+    let mut bytes = std::fs::read(component_path)?;
+    bytes.push(0);
+    bytes.push(section.len() as u8);
+    bytes.extend_from_slice(&section);
+    std::fs::write(target, bytes)?;
+    Ok(())
+}
+
+/// Runtime for the provider pack with `component_loading: lazy`.
+async fn setup_lazy_runtime(workspace: &Path) -> Result<Arc<TenantRuntime>> {
+    let component_path = workspace.join("provider-uncached.wasm");
+    write_uncached_component(&build_provider_component()?, &component_path)?;
+    let pack_path = workspace.join("operator-provider.gtpack");
+    build_provider_pack(&component_path, &pack_path)?;
+    let mut config = (*minimal_config(workspace)?).clone();
+    config.component_loading = ComponentLoading::Lazy;
+    setup_runtime(&pack_path, Arc::new(config)).await
+}
+
+async fn version_output(runtime: &TenantRuntime) -> Result<String> {
+    let response = invoke_operator(runtime, operator_request(VERSION_OP)?).await;
+    let output = response
+        .cbor_output
+        .as_deref()
+        .with_context(|| format!("version op failed: {:?}", response.error))?;
+    let value: Value = serde_cbor::from_slice(output)?;
+    value["message"]
+        .as_str()
+        .map(ToString::to_string)
+        .context("version output missing message")
+}
+
+/// Digest reported by the input-validation diagnostic for a malformed payload.
+async fn input_diagnostic_digest(runtime: &TenantRuntime) -> Result<Option<String>> {
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.payload.cbor_input = serde_cbor::to_vec(&json!({"unexpected": true}))?;
+    let response = invoke_operator(runtime, request).await;
+    let error = response.error.context("expected input validation error")?;
+    let diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    Ok(diagnostics
+        .first()
+        .context("expected a diagnostic")?
+        .digest
+        .clone())
+}
+
+/// Slack allowed past an epoch deadline for tick granularity and instantiation.
+const DEADLINE_EPSILON_MS: u64 = 750;
+
+fn timeout_diagnostic(
+    response: greentic_runner_host::runner::operator::OperatorResponse,
+) -> Result<greentic_runner_host::runner::operator::Diagnostic> {
+    assert!(matches!(response.status, OperatorStatus::Error));
+    let error = response.error.context("expected error response")?;
+    assert!(
+        matches!(error.code, OperatorErrorCode::Timeout),
+        "unexpected error: {error:?}"
+    );
+    let mut diagnostics: Vec<greentic_runner_host::runner::operator::Diagnostic> =
+        serde_cbor::from_slice(error.details_cbor.as_deref().context("diagnostics")?)?;
+    Ok(diagnostics.remove(0))
+}
+
+/// Run the fixture's `write_file` op against `guest_path` and decode its JSON output.
+async fn write_file_output(runtime: &TenantRuntime, guest_path: &str) -> Result<Value> {
+    let mut request = operator_request(WRITE_FILE_OP)?;
+    request.payload.cbor_input = serde_cbor::to_vec(&json!({ "message": guest_path }))?;
+    let response = invoke_operator(runtime, request).await;
+    let output = response
+        .cbor_output
+        .as_deref()
+        .with_context(|| format!("write_file failed: {:?}", response.error))?;
+    Ok(serde_cbor::from_slice(output)?)
+}
+
+/// Echo request carrying a secret attachment for `key`.
+/// Secrets backend that answers every path and counts the reads.
+#[derive(Default)]
+struct CountingSecretsManager {
+    reads: AtomicUsize,
+}
+
+impl CountingSecretsManager {
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl SecretsManager for CountingSecretsManager {
+    async fn read(&self, _path: &str) -> Result<Vec<u8>, SecretError> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Ok(b"token".to_vec())
+    }
+
+    async fn write(&self, _path: &str, _bytes: &[u8]) -> Result<(), SecretError> {
+        Ok(())
+    }
+
+    async fn delete(&self, _path: &str) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+/// Secrets backend that answers every path with the same value.
+struct FixedSecretsManager(&'static str);
+
+#[async_trait]
+impl SecretsManager for FixedSecretsManager {
+    async fn read(&self, _path: &str) -> Result<Vec<u8>, SecretError> {
+        Ok(self.0.as_bytes().to_vec())
+    }
+
+    async fn write(&self, _path: &str, _bytes: &[u8]) -> Result<(), SecretError> {
+        Ok(())
+    }
+
+    async fn delete(&self, _path: &str) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+fn impersonated_request(op_id: &str) -> Result<OperatorRequest> {
+    let mut request = operator_request(op_id)?;
+    request.impersonation = Some(Impersonation {
+        actor_id: "support-7".into(),
+        reason: Some("ticket 42".into()),
+    });
+    Ok(request)
+}
+
+fn secret_request(key: &str) -> Result<OperatorRequest> {
+    let mut request = operator_request(PROVIDER_OP)?;
+    request.payload.attachments.push(AttachmentRef {
+        id: "secret-1".into(),
+        metadata: Some(json!({"type": "secret", "key": key})),
+    });
+    Ok(request)
+}
+
+/// Invoke the echo op with a secret attachment for `key` and return the value it saw.
+async fn secret_attachment(runtime: &TenantRuntime, tenant: &str, key: &str) -> Result<String> {
+    let mut request = secret_request(key)?;
+    request.tenant_id = Some(tenant.into());
+    let response = invoke_operator(runtime, request).await;
+    anyhow::ensure!(response.error.is_none(), "{:?}", response.error);
+    let output: Value = serde_cbor::from_slice(
+        response
+            .cbor_output
+            .as_deref()
+            .context("expected CBOR output")?,
+    )?;
+    output["_attachments"][key]
+        .as_str()
+        .map(str::to_string)
+        .context("echo output carries no secret attachment")
+}
+
+/// Pooling engine sized for tests, so pool reservations stay small.
+fn pooling_engine(limits: PoolingLimits) -> WasmEngineConfig {
+    WasmEngineConfig {
+        allocation: AllocationStrategy::Pooling,
+        pooling: PoolingLimits {
+            total_instances: 8,
+            total_memories: 16,
+            total_tables: 16,
+            ..limits
+        },
+    }
+}
+
+/// Demo-tenant request for `op_id` with the default `{"message": "ping"}` payload.
+fn operator_request(op_id: &str) -> Result<OperatorRequest> {
+    Ok(OperatorRequest {
+        tenant_id: Some("demo".into()),
+        provider_id: None,
+        provider_type: Some(PROVIDER_TYPE.to_string()),
+        pack_id: None,
+        op_id: op_id.to_string(),
+        trace_id: None,
+        correlation_id: None,
+        idempotency_key: None,
+        timeout: None,
+        flags: Vec::new(),
+        op_version: None,
+        schema_hash: None,
+        locale: None,
+        attributes: BTreeMap::new(),
+        impersonation: None,
+        payload: OperatorPayload {
+            cbor_input: serde_cbor::to_vec(&json!({"message": "ping"}))?,
+            attachments: Vec::new(),
+        },
+    })
+}
+
+/// Demo-tenant config whose bindings carry `operator` (YAML lines indented by two) as
+/// their operator block.
+fn operator_block_config(workspace: &Path, operator: &str) -> Result<Arc<HostConfig>> {
+    let bindings_path = workspace.join("demo.bindings.yaml");
+    std::fs::write(
+        &bindings_path,
+        format!("tenant: demo\nflow_type_bindings: {{}}\noperator:\n{operator}"),
+    )?;
+    let mut config =
+        HostConfig::load_from_path(&bindings_path).context("load operator bindings")?;
+    config.secrets_policy = SecretsPolicy::allow_all();
+    config.trace = TraceConfig::from_env();
+    config.validation = ValidationConfig::from_env();
+    Ok(Arc::new(config))
+}
+
+fn minimal_config(workspace: &Path) -> Result<Arc<HostConfig>> {
+    tenant_config(workspace, "demo")
+}
+
+fn tenant_config(workspace: &Path, tenant: &str) -> Result<Arc<HostConfig>> {
+    let bindings_path = workspace.join(format!("{tenant}.bindings.yaml"));
+    std::fs::write(
+        &bindings_path,
+        format!(
+            r#"
+tenant: {tenant}
+flow_type_bindings: {{}}
+rate_limits: {{}}
+retry: {{}}
+timers: []
+"#
+        ),
+    )?;
+    let mut config =
+        HostConfig::load_from_path(&bindings_path).context("load minimal host bindings")?;
+    config.secrets_policy = SecretsPolicy::allow_all();
+    config.operator_policy = OperatorPolicy::allow_all();
+    config.trace = TraceConfig::from_env();
+    config.validation = ValidationConfig::from_env();
+    Ok(Arc::new(config))
+}
+
+async fn setup_runtime(pack_path: &Path, config: Arc<HostConfig>) -> Result<Arc<TenantRuntime>> {
+    setup_runtime_with_policy(pack_path, config, RunnerWasiPolicy::new()).await
+}
+
+async fn setup_runtime_with_policy(
+    pack_path: &Path,
+    config: Arc<HostConfig>,
+    wasi_policy: RunnerWasiPolicy,
+) -> Result<Arc<TenantRuntime>> {
+    setup_runtime_with_secrets(pack_path, config, wasi_policy, default_manager()?).await
+}
+
+async fn setup_runtime_with_secrets(
+    pack_path: &Path,
+    config: Arc<HostConfig>,
+    wasi_policy: RunnerWasiPolicy,
+    secrets: DynSecretsManager,
+) -> Result<Arc<TenantRuntime>> {
+    load_runtime(pack_path, config, wasi_policy, secrets, new_state_store()).await
+}
+
+/// Tenant runtime over `pack_path` whose state lives in `state_store`.
+async fn load_runtime(
+    pack_path: &Path,
+    config: Arc<HostConfig>,
+    wasi_policy: RunnerWasiPolicy,
+    secrets: DynSecretsManager,
+    state_store: DynStateStore,
+) -> Result<Arc<TenantRuntime>> {
+    let session_store = new_session_store();
+    let session_host = session_host_from(Arc::clone(&session_store));
+    let state_host = state_host_from(Arc::clone(&state_store));
+    TenantRuntime::load(
+        pack_path,
+        config,
+        None,
+        Some(pack_path),
+        None,
+        Arc::new(wasi_policy),
+        session_host,
+        Arc::clone(&session_store),
+        Arc::clone(&state_store),
+        state_host,
+        secrets,
+    )
+    .await
+}
+
+/// Store an enabled instance of the fixture provider for the demo tenant.
+fn store_provider_instance(state_store: &DynStateStore, config: Value) -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("demo")?);
+    let instance = json!({
+        "provider_id": PROVIDER_TYPE,
+        "provider_type": PROVIDER_TYPE,
+        "pack_ref": format!("{PROVIDER_PACK_ID}@0.1.0"),
+        "component_ref": PROVIDER_COMPONENT_REF,
+        "export": "provider-core",
+        "world": "greentic:provider-core@1.0.0",
+        "enabled": true,
+        "config": config,
+    });
+    let key = format!("providers/instances/{PROVIDER_TYPE}.json");
+    state_store.set_json(
+        &ctx,
+        "runner",
+        &StateKey::new(key.as_str()),
+        None,
+        &instance,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Tenant runtime serving `pack_paths` in priority order.
+async fn setup_multi_pack_runtime(
+    pack_paths: &[&Path],
+    config: Arc<HostConfig>,
+) -> Result<Arc<TenantRuntime>> {
+    let session_store = new_session_store();
+    let state_store = new_state_store();
+    let secrets = default_manager()?;
+    let mut packs = Vec::new();
+    for &pack_path in pack_paths {
+        let pack = PackRuntime::load(
+            pack_path,
+            Arc::clone(&config),
+            None,
+            Some(pack_path),
+            Some(Arc::clone(&session_store)),
+            Some(Arc::clone(&state_store)),
+            Arc::new(RunnerWasiPolicy::new()),
+            Arc::clone(&secrets),
+            None,
+            true,
+            ComponentResolution::default(),
+        )
+        .await?;
+        packs.push((Arc::new(pack), None));
+    }
+    TenantRuntime::from_packs(
+        config,
+        packs,
+        None,
+        session_host_from(Arc::clone(&session_store)),
+        Arc::clone(&session_store),
+        Arc::clone(&state_store),
+        state_host_from(Arc::clone(&state_store)),
+        secrets,
+    )
+    .await
+}
+
+const PROVIDER_CONFIG_SCHEMA: &str = r#"{
   "$schema": "http://json-schema.org/draft-07/schema#",
   "type": "object",
   "required": ["message"],
@@ -505,9 +3286,10 @@ fn build_provider_pack(component_path: &Path, pack_path: &Path) -> Result<()> {
     "message": { "type": "string" }
   },
   "additionalProperties": false
-}"#,
-        None,
-    )
+}"#;
+
+fn build_provider_pack(component_path: &Path, pack_path: &Path) -> Result<()> {
+    build_provider_pack_with_schemas(component_path, pack_path, PROVIDER_CONFIG_SCHEMA, None)
 }
 
 fn build_provider_pack_with_schemas(
@@ -515,17 +3297,85 @@ fn build_provider_pack_with_schemas(
     pack_path: &Path,
     config_schema_json: &str,
     output_schema_json: Option<&str>,
+) -> Result<()> {
+    write_provider_pack(
+        component_path,
+        pack_path,
+        Some(config_schema_json),
+        output_schema_json,
+    )
+}
+
+/// Writes the provider pack; `None` omits the config schema the manifest references.
+fn write_provider_pack(
+    component_path: &Path,
+    pack_path: &Path,
+    config_schema_json: Option<&str>,
+    output_schema_json: Option<&str>,
+) -> Result<()> {
+    write_provider_pack_as(
+        component_path,
+        pack_path,
+        PROVIDER_PACK_ID,
+        PROVIDER_COMPONENT_REF,
+        config_schema_json,
+        output_schema_json,
+    )
+}
+
+/// Like [`write_provider_pack`], but for pack `pack_id` shipping the fixture component as
+/// `component_id`. The provider always points at [`PROVIDER_COMPONENT_REF`].
+fn write_provider_pack_as(
+    component_path: &Path,
+    pack_path: &Path,
+    pack_id: &str,
+    component_id: &str,
+    config_schema_json: Option<&str>,
+    output_schema_json: Option<&str>,
+) -> Result<()> {
+    write_provider_pack_declaring(
+        component_path,
+        pack_path,
+        pack_id,
+        component_id,
+        config_schema_json,
+        output_schema_json,
+        &[],
+    )
+}
+
+/// Like [`write_provider_pack_as`], with the provider declaring `capabilities`.
+fn write_provider_pack_declaring(
+    component_path: &Path,
+    pack_path: &Path,
+    pack_id: &str,
+    component_id: &str,
+    config_schema_json: Option<&str>,
+    output_schema_json: Option<&str>,
+    capabilities: &[&str],
 ) -> Result<()> {
     let mut extensions = BTreeMap::new();
     let inline = ProviderExtensionInline {
         providers: vec![ProviderDecl {
             provider_type: PROVIDER_TYPE.to_string(),
-            capabilities: Vec::new(),
-            ops: vec![PROVIDER_OP.to_string()],
+            capabilities: capabilities.iter().map(ToString::to_string).collect(),
+            ops: vec![
+                PROVIDER_OP.to_string(),
+                SLOW_PROVIDER_OP.to_string(),
+                GROW_MEMORY_OP.to_string(),
+                SPIN_OP.to_string(),
+                VERSION_OP.to_string(),
+                WRITE_FILE_OP.to_string(),
+                UNREACHABLE_OP.to_string(),
+                LOG_ECHO_OP.to_string(),
+                LOG_AND_TRAP_OP.to_string(),
+                LEAK_AND_TRAP_OP.to_string(),
+                NOW_OP.to_string(),
+            ],
             config_schema_ref: "schemas/config.schema.json".into(),
             state_schema_ref: Some("schemas/state.schema.json".into()),
             runtime: ProviderRuntimeRef {
-                component_ref: "provider.dummy".into(),
+                component_ref: PROVIDER_COMPONENT_REF.into(),
                 export: "provider-core".into(),
                 world: "greentic:provider-core@1.0.0".into(),
             },
@@ -546,13 +3396,13 @@ fn build_provider_pack_with_schemas(
 
     let manifest = PackManifest {
         schema_version: "1.0".into(),
-        pack_id: "operator.provider".parse()?,
-        name: Some("operator.provider".into()),
+        pack_id: pack_id.parse()?,
+        name: Some(pack_id.into()),
         version: Version::parse("0.1.0")?,
         kind: PackKind::Application,
         publisher: "test".into(),
         components: vec![ComponentManifest {
-            id: "provider.dummy".parse()?,
+            id: component_id.parse()?,
             version: Version::parse("0.1.0")?,
             supports: Vec::new(),
             world: "greentic:provider-core@1.0.0".into(),
@@ -581,13 +3431,15 @@ fn build_provider_pack_with_schemas(
     writer.start_file("manifest.cbor", options)?;
     writer.write_all(&manifest_bytes)?;
 
-    writer.start_file("components/provider.dummy.wasm", options)?;
+    writer.start_file(format!("components/{component_id}.wasm"), options)?;
     let mut component_file =
         File::open(component_path).with_context(|| format!("Open {:?}", component_path))?;
     copy(&mut component_file, &mut writer)?;
 
-    writer.start_file("schemas/config.schema.json", options)?;
-    writer.write_all(config_schema_json.as_bytes())?;
+    if let Some(config_schema) = config_schema_json {
+        writer.start_file("schemas/config.schema.json", options)?;
+        writer.write_all(config_schema.as_bytes())?;
+    }
     if let Some(output_schema) = output_schema_json {
         writer.start_file("schemas/output.schema.json", options)?;
         writer.write_all(output_schema.as_bytes())?;
@@ -607,10 +3459,135 @@ fn build_provider_pack_with_schemas(
     Ok(())
 }
 
+/// Pack serving the `stream_progress` fixture as a node@0.4 provider. `output_schema_json`
+/// becomes the schema derived from the provider's config schema.
+fn write_stream_pack(
+    component_path: &Path,
+    pack_path: &Path,
+    output_schema_json: Option<&str>,
+) -> Result<()> {
+    let mut extensions = BTreeMap::new();
+    let inline = ProviderExtensionInline {
+        providers: vec![ProviderDecl {
+            provider_type: STREAM_PROVIDER_TYPE.to_string(),
+            capabilities: Vec::new(),
+            ops: vec!["progress".to_string(), STREAM_OP.to_string()],
+            config_schema_ref: "schemas/config.schema.json".into(),
+            state_schema_ref: None,
+            runtime: ProviderRuntimeRef {
+                component_ref: STREAM_COMPONENT_REF.into(),
+                export: "node".into(),
+                world: "greentic:component@0.4.0".into(),
+            },
+            docs_ref: None,
+        }],
+        ..Default::default()
+    };
+    extensions.insert(
+        PROVIDER_EXTENSION_ID.to_string(),
+        ExtensionRef {
+            kind: PROVIDER_EXTENSION_ID.to_string(),
+            version: "1.0.0".into(),
+            digest: None,
+            location: None,
+            inline: Some(ExtensionInline::Provider(inline)),
+        },
+    );
+    let manifest = PackManifest {
+        schema_version: "1.0".into(),
+        pack_id: "operator.stream".parse()?,
+        name: Some("operator.stream".into()),
+        version: Version::parse("0.1.0")?,
+        kind: PackKind::Application,
+        publisher: "test".into(),
+        components: vec![ComponentManifest {
+            id: STREAM_COMPONENT_REF.parse()?,
+            version: Version::parse("0.1.0")?,
+            supports: Vec::new(),
+            world: "greentic:component@0.4.0".into(),
+            profiles: ComponentProfiles::default(),
+            capabilities: ComponentCapabilities::default(),
+            configurators: None,
+            operations: Vec::new(),
+            config_schema: None,
+            resources: ResourceHints::default(),
+            dev_flows: BTreeMap::new(),
+        }],
+        flows: Vec::new(),
+        dependencies: Vec::new(),
+        capabilities: Vec::new(),
+        signatures: Default::default(),
+        secret_requirements: Vec::new(),
+        bootstrap: None,
+        extensions: Some(extensions),
+    };
+
+    let mut writer = ZipWriter::new(File::create(pack_path).context("create stream pack archive")?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("manifest.cbor", options)?;
+    writer.write_all(&encode_pack_manifest(&manifest)?)?;
+    writer.start_file(format!("components/{STREAM_COMPONENT_REF}.wasm"), options)?;
+    let mut component_file =
+        File::open(component_path).with_context(|| format!("Open {:?}", component_path))?;
+    copy(&mut component_file, &mut writer)?;
+    writer.start_file("schemas/config.schema.json", options)?;
+    writer.write_all(br#"{"type": "object"}"#)?;
+    if let Some(output_schema) = output_schema_json {
+        writer.start_file("schemas/output.schema.json", options)?;
+        writer.write_all(output_schema.as_bytes())?;
+    }
+    writer.finish().context("finalise stream pack")?;
+    Ok(())
+}
+
+/// Builds the `stream_progress` node component from the shared runner-components fixtures.
+fn build_stream_component() -> Result<PathBuf> {
+    let root = fixture_path("tests/fixtures/runner-components");
+    let wasm = root.join("target/wasm32-wasip2/release/stream_progress.wasm");
+    if is_stale(&wasm, &root.join("stream_progress/src/lib.rs")) {
+        let offline = std::env::var("CARGO_NET_OFFLINE").ok();
+        let mut cmd = Command::new("cargo");
+        let mut args: Vec<String> = vec![
+            "build".into(),
+            "--release".into(),
+            "--target".into(),
+            "wasm32-wasip2".into(),
+            "--manifest-path".into(),
+            root.join("stream_progress/Cargo.toml")
+                .to_str()
+                .expect("manifest path")
+                .into(),
+        ];
+        if matches!(offline.as_deref(), Some("true")) {
+            args.insert(1, "--offline".into());
+        }
+        if let Some(val) = &offline {
+            cmd.env("CARGO_NET_OFFLINE", val);
+        }
+        cmd.current_dir(&root)
+            .args(&args)
+            .status()
+            .context("build stream component")?;
+    }
+    Ok(wasm)
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<StreamEvent>>,
+}
+
+impl StreamObserver for RecordingObserver {
+    fn on_event(&self, event: &StreamEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
 fn build_provider_component() -> Result<PathBuf> {
     let root = fixture_path("tests/assets/provider-core-dummy");
     let wasm = root.join("target/wasm32-wasip2/release/provider_core_dummy.wasm");
-    if !wasm.exists() {
+    if is_stale(&wasm, &root.join("src/lib.rs")) {
         let offline = std::env::var("CARGO_NET_OFFLINE").ok();
         let mut cmd = Command::new("cargo");
         let mut args: Vec<String> = vec![
@@ -637,6 +3614,27 @@ fn build_provider_component() -> Result<PathBuf> {
     Ok(wasm)
 }
 
+/// Missing artifacts, or artifacts older than their fixture source, need a rebuild.
+fn is_stale(artifact: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    };
+    match (modified(artifact), modified(source)) {
+        (Some(artifact), Some(source)) => artifact < source,
+        (None, _) => true,
+        (Some(_), None) => false,
+    }
+}
+
+fn series_value(body: &str, series: &str) -> Option<f64> {
+    body.lines().find_map(|line| {
+        line.strip_prefix(series)
+            .and_then(|rest| rest.trim().parse::<f64>().ok())
+    })
+}
+
 fn fixture_path(relative: &str) -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .parent()
@@ -6,8 +6,9 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use greentic_flow::flow_bundle::load_and_validate_bundle_with_flow;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::{ComponentResolution, PackRuntime};
 use greentic_runner_host::storage::new_state_store;
@@ -48,6 +49,8 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -57,6 +60,13 @@ fn host_config(bindings_path: &Path) -> HostConfig {
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -476,6 +486,31 @@ fn write_component_cache(cache_root: &Path, digest: &str, bytes: &[u8]) -> Resul
     Ok(path)
 }
 
+/// Rewrite the archive at `pack_path` with the middle byte of `entry` inverted.
+fn flip_entry_byte(pack_path: &Path, entry: &str) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(pack_path)?)?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut data)?;
+        if file.name() == entry {
+            let middle = data.len() / 2;
+            data[middle] ^= 0xff;
+        }
+        entries.push((file.name().to_string(), data));
+    }
+    let mut writer = ZipWriter::new(std::fs::File::create(pack_path)?);
+    let options: FileOptions<'_, ()> =
+        FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, data) in entries {
+        writer.start_file(name, options)?;
+        writer.write_all(&data)?;
+    }
+    writer.finish().context("rewrite test pack")?;
+    Ok(())
+}
+
 fn demo_exec_ctx(node_id: &str) -> greentic_runner_host::component_api::node::ExecCtx {
     greentic_runner_host::component_api::node::ExecCtx {
         tenant: greentic_runner_host::component_api::node::TenantCtx {
@@ -488,6 +523,8 @@ fn demo_exec_ctx(node_id: &str) -> greentic_runner_host::component_api::node::Ex
             deadline_unix_ms: None,
             attempt: 0,
             idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
         },
         i18n_id: None,
         flow_id: "demo.flow".into(),
@@ -762,6 +799,81 @@ fn gtpack_manifest_component_sources_only_loads_components() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn gtpack_component_digests_are_verified_at_load() -> Result<()> {
+    let rt = *RUNTIME;
+    let temp = TempDir::new()?;
+    let gtpack = temp.path().join("runner-components-digests.gtpack");
+    let bindings_path = temp.path().join("bindings.yaml");
+    std::fs::write(&bindings_path, b"tenant: demo")?;
+
+    let fixtures = workspace_root().join("tests/fixtures/packs/runner-components");
+    let mut entries = Vec::new();
+    let mut expected = HashMap::new();
+    for (id, artifact_path) in component_sources(&fixtures)? {
+        let bytes = std::fs::read(&artifact_path)?;
+        let digest = digest_for_bytes(&bytes);
+        expected.insert(id.clone(), digest.clone());
+        entries.push(ComponentSourceEntryV1 {
+            name: id.clone(),
+            component_id: Some(id.clone().parse()?),
+            source: ComponentSourceRef::Oci(format!("registry.test/{id}@{}", digest)),
+            resolved: ResolvedComponentV1 {
+                digest,
+                signature: None,
+                signed_by: None,
+            },
+            artifact: ArtifactLocationV1::Inline {
+                wasm_path: format!("components/{id}.wasm"),
+                manifest_path: None,
+            },
+            licensing_hint: None,
+            metering_hint: None,
+        });
+    }
+    build_pack_with_component_sources_only(&gtpack, ComponentSourcesV1::new(entries), true)?;
+
+    let config = Arc::new(host_config(&bindings_path));
+    let load = || {
+        rt.block_on(PackRuntime::load(
+            &gtpack,
+            Arc::clone(&config),
+            None,
+            None,
+            None,
+            None,
+            Arc::new(RunnerWasiPolicy::new()),
+            greentic_runner_host::secrets::default_manager()?,
+            None,
+            false,
+            ComponentResolution::default(),
+        ))
+    };
+
+    let runtime = load()?;
+    assert_eq!(
+        runtime.component_digest("qa.process"),
+        expected.get("qa.process").map(String::as_str)
+    );
+    assert!(runtime.content_digest().starts_with("sha256:"));
+    drop(runtime);
+
+    flip_entry_byte(&gtpack, "components/qa.process.wasm")?;
+    let err = match load() {
+        Ok(_) => panic!("pack with a corrupted component should not load"),
+        Err(err) => format!("{err:#}"),
+    };
+    assert!(
+        err.contains("component qa.process digest mismatch"),
+        "unexpected error: {err}"
+    );
+    assert!(
+        err.contains(&expected["qa.process"]),
+        "unexpected error: {err}"
+    );
+    Ok(())
+}
+
 #[test]
 fn state_store_roundtrip_requires_capability() -> Result<()> {
     let rt = *RUNTIME;
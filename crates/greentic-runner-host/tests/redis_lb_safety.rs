@@ -7,9 +7,11 @@ mod redis_lb {
     use greentic_runner_host::engine::host::SessionKey;
     use greentic_runner_host::engine::runtime::{FlowResumeStore, IngressEnvelope};
     use greentic_runner_host::runner::engine::{ExecutionState, FlowSnapshot, FlowWait};
-    use greentic_runner_host::storage::{DynStateStore, state_host_from};
+    use greentic_runner_host::storage::redis_state::RedisStateStore;
+    use greentic_runner_host::storage::{
+        DynStateStore, RedisConfig, RedisTopology, state_host_from,
+    };
     use greentic_session::{SessionBackendConfig, create_session_store};
-    use greentic_state::redis_store::RedisStateStore;
     use greentic_types::{EnvId, ReplyScope, TenantCtx, TenantId};
     use serde_json::json;
 
@@ -93,11 +95,12 @@ mod redis_lb {
         let ctx = TenantCtx::new(env, tenant);
         let key = SessionKey::new(&ctx, "pack.redis", "flow.main", Some("session".into()));
 
-        let state_store: DynStateStore = Arc::new(RedisStateStore::from_url(&redis_url)?);
+        let config = RedisConfig::new(RedisTopology::Single { url: redis_url });
+        let state_store: DynStateStore = Arc::new(RedisStateStore::connect(&config)?);
         let state_host = state_host_from(state_store);
         state_host.set_json(&key, json!({"value": 1})).await?;
 
-        let state_store_restart: DynStateStore = Arc::new(RedisStateStore::from_url(&redis_url)?);
+        let state_store_restart: DynStateStore = Arc::new(RedisStateStore::connect(&config)?);
         let state_host_restart = state_host_from(state_store_restart);
         let value = state_host_restart.get_json(&key).await?;
         assert_eq!(value, Some(json!({"value": 1})));
@@ -0,0 +1,177 @@
+#[cfg(feature = "session-redis")]
+mod redis_topology {
+    use anyhow::Result;
+    use greentic_runner_host::engine::host::SessionKey;
+    use greentic_runner_host::engine::runtime::{FlowResumeStore, IngressEnvelope};
+    use greentic_runner_host::runner::engine::{ExecutionState, FlowSnapshot, FlowWait};
+    use greentic_runner_host::storage::redis_state::RedisStateStore;
+    use greentic_runner_host::storage::{
+        HostStateStore, RedisConfig, RedisTopology, SessionBackend, StateBackend, state_host_from,
+    };
+    use greentic_state::{StateKey, StateStore};
+    use greentic_types::{EnvId, ReplyScope, TenantCtx, TenantId};
+    use serde_json::json;
+
+    fn envelope() -> IngressEnvelope {
+        IngressEnvelope {
+            tenant: "demo".into(),
+            env: Some("local".into()),
+            pack_id: Some("pack.redis".into()),
+            flow_id: "flow.main".into(),
+            flow_type: Some("messaging".into()),
+            action: Some("messaging".into()),
+            session_hint: Some("demo:provider:chan:conv:topology".into()),
+            provider: Some("provider".into()),
+            channel: Some("conv".into()),
+            conversation: Some("conv".into()),
+            user: Some("topology".into()),
+            activity_id: Some("activity-topology".into()),
+            timestamp: None,
+            payload: json!({ "text": "hi" }),
+            metadata: None,
+            reply_scope: Some(ReplyScope {
+                conversation: "conv".into(),
+                thread: None,
+                reply_to: None,
+                correlation: None,
+            }),
+        }
+        .canonicalize()
+    }
+
+    fn wait_snapshot() -> FlowWait {
+        let state: ExecutionState = serde_json::from_value(json!({
+            "input": { "text": "hi" },
+            "nodes": {},
+            "egress": []
+        }))
+        .expect("state");
+        FlowWait {
+            reason: Some("await-user".into()),
+            snapshot: FlowSnapshot {
+                pack_id: "pack.redis".into(),
+                flow_id: "flow.main".into(),
+                next_node: "node-a".into(),
+                state,
+            },
+        }
+    }
+
+    /// Settings under `GREENTIC_TEST_REDIS_*`, e.g. `GREENTIC_TEST_REDIS_SENTINELS`
+    /// and `GREENTIC_TEST_REDIS_SENTINEL_SERVICE` for a sentinel test deployment, or
+    /// `GREENTIC_TEST_REDIS_CLUSTER_NODES` for a cluster.
+    fn test_config() -> Result<Option<RedisConfig>> {
+        RedisConfig::from_env("GREENTIC_TEST_REDIS")
+    }
+
+    #[tokio::test]
+    async fn state_round_trips_through_configured_topology() -> Result<()> {
+        let Some(config) = test_config()? else {
+            eprintln!("GREENTIC_TEST_REDIS_* not set; skipping redis topology test");
+            return Ok(());
+        };
+        let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("redis-topology")?);
+        let writer = StateBackend::Redis(config.clone()).build_store()?;
+        let host = state_host_from(writer.clone());
+        // Enough tenants that a cluster spreads them over several slots and nodes.
+        let tenants = (0..16)
+            .map(|idx| TenantId::new(format!("redis-topology-{idx}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        for tenant in &tenants {
+            let ctx = TenantCtx::new(EnvId::new("local")?, tenant.clone());
+            let key = SessionKey::new(&ctx, "pack.redis", "flow.main", Some("s".into()));
+            host.set_json(&key, json!({"tenant": tenant.as_str()}))
+                .await?;
+        }
+
+        let reader = state_host_from(StateBackend::Redis(config).build_store()?);
+        for tenant in &tenants {
+            let ctx = TenantCtx::new(EnvId::new("local")?, tenant.clone());
+            let key = SessionKey::new(&ctx, "pack.redis", "flow.main", Some("s".into()));
+            assert_eq!(
+                reader.get_json(&key).await?,
+                Some(json!({"tenant": tenant.as_str()}))
+            );
+            reader.del(&key).await?;
+        }
+        // Prefix deletes scan the node holding the tenant's slot.
+        for key in ["a", "b"] {
+            writer.set_json(
+                &ctx,
+                "scratch",
+                &StateKey::new(key),
+                None,
+                &json!(1),
+                Some(60),
+            )?;
+        }
+        assert_eq!(writer.del_prefix(&ctx, "scratch")?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn batches_take_one_round_trip() -> Result<()> {
+        let Some(config) = test_config()? else {
+            eprintln!("GREENTIC_TEST_REDIS_* not set; skipping redis topology test");
+            return Ok(());
+        };
+        let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("redis-batch")?);
+        let store = RedisStateStore::connect(&config)?;
+        let entries = (0..8)
+            .map(|idx| (StateKey::from(format!("k{idx}")), json!(idx)))
+            .collect::<Vec<_>>();
+
+        let before = store.round_trips();
+        let results = store.set_many(&ctx, "scratch", &entries, Some(60))?;
+        assert_eq!(store.round_trips() - before, 1, "SETs are pipelined");
+        assert_eq!(results.len(), entries.len());
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        let mut keys = entries
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.push(StateKey::new("missing"));
+        let before = store.round_trips();
+        let values = store.get_many(&ctx, "scratch", &keys)?;
+        assert_eq!(store.round_trips() - before, 1, "reads are one MGET");
+        assert_eq!(values.len(), keys.len());
+        assert_eq!(values[3], Some(json!(3)));
+        assert_eq!(values[8], None);
+        assert_eq!(store.del_prefix(&ctx, "scratch")?, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn resume_round_trips_through_configured_topology() -> Result<()> {
+        let Some(config) = test_config()? else {
+            eprintln!("GREENTIC_TEST_REDIS_* not set; skipping redis topology test");
+            return Ok(());
+        };
+        if matches!(config.topology, RedisTopology::Cluster { .. }) {
+            // The upstream session store only talks to a single node.
+            let err = SessionBackend::Redis(config)
+                .build_store()
+                .err()
+                .expect("cluster is rejected");
+            assert!(err.to_string().contains("cluster"), "unexpected: {err}");
+            return Ok(());
+        }
+
+        let envelope = envelope();
+        let writer = FlowResumeStore::new(SessionBackend::Redis(config.clone()).build_store()?);
+        writer.save(&envelope, &wait_snapshot())?;
+
+        let reader = FlowResumeStore::new(SessionBackend::Redis(config).build_store()?);
+        let snapshot = reader.fetch(&envelope)?.expect("snapshot missing");
+        assert_eq!(snapshot.next_node, "node-a");
+        reader.clear(&envelope)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "session-redis"))]
+#[test]
+fn redis_topology_disabled() {
+    eprintln!("session-redis feature disabled; skipping redis topology test");
+}
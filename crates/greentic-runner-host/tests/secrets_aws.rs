@@ -0,0 +1,84 @@
+#[cfg(feature = "secrets-aws")]
+mod secrets_aws {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use greentic_runner_host::secrets::aws::AwsSecretsManager;
+    use greentic_runner_host::secrets::{AwsSecretsConfig, AwsSecretsService};
+    use greentic_secrets_lib::{SecretError, SecretsManager};
+
+    /// A LocalStack endpoint at `GREENTIC_TEST_AWS_ENDPOINT`. Credentials and region come
+    /// from the usual SDK variables (`AWS_ACCESS_KEY_ID=test`, ...); the region
+    /// defaults to `us-east-1`.
+    fn config(service: AwsSecretsService) -> Option<AwsSecretsConfig> {
+        let endpoint = std::env::var("GREENTIC_TEST_AWS_ENDPOINT").ok()?;
+        let mut config = AwsSecretsConfig::new(service);
+        config.endpoint_url = Some(endpoint);
+        config.region = Some(std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".into()));
+        config.cache_ttl = Duration::ZERO;
+        Some(config)
+    }
+
+    async fn round_trip(service: AwsSecretsService, tenant: &str) -> Result<()> {
+        let Some(config) = config(service) else {
+            eprintln!("GREENTIC_TEST_AWS_ENDPOINT not set; skipping aws secrets test");
+            return Ok(());
+        };
+        let manager = AwsSecretsManager::new(config);
+        let path = format!("secrets://test/{tenant}/_/pack_demo/api_key");
+        manager.write(&path, b"s3cr3t").await?;
+        assert_eq!(manager.read(&path).await?, b"s3cr3t");
+        manager.write(&path, b"rotated").await?;
+        assert_eq!(manager.read(&path).await?, b"rotated");
+
+        manager.delete(&path).await?;
+        assert!(matches!(
+            manager.read(&path).await,
+            Err(SecretError::NotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn secrets_manager_round_trip() -> Result<()> {
+        round_trip(AwsSecretsService::SecretsManager, "aws-sm").await
+    }
+
+    #[tokio::test]
+    async fn ssm_round_trip() -> Result<()> {
+        round_trip(AwsSecretsService::Ssm, "aws-ssm").await
+    }
+
+    #[tokio::test]
+    async fn json_secret_is_selected_by_key() -> Result<()> {
+        let Some(mut config) = config(AwsSecretsService::SecretsManager) else {
+            eprintln!("GREENTIC_TEST_AWS_ENDPOINT not set; skipping aws secrets test");
+            return Ok(());
+        };
+        config.name_template = "/greentic/{tenant}/{pack}".into();
+        config.json_key = Some("{key}".into());
+        let manager = AwsSecretsManager::new(config);
+
+        let token = "secrets://test/aws-json/_/pack_demo/token";
+        let url = "secrets://test/aws-json/_/pack_demo/url";
+        manager.write(token, b"t-1").await?;
+        manager.write(url, b"https://example.test").await?;
+        assert_eq!(manager.read(token).await?, b"t-1");
+        assert_eq!(manager.read(url).await?, b"https://example.test");
+
+        manager.delete(token).await?;
+        assert!(matches!(
+            manager.read(token).await,
+            Err(SecretError::NotFound(_))
+        ));
+        assert_eq!(manager.read(url).await?, b"https://example.test");
+        manager.delete(url).await?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "secrets-aws"))]
+#[test]
+fn secrets_aws_disabled() {
+    eprintln!("secrets-aws feature disabled; skipping aws secrets test");
+}
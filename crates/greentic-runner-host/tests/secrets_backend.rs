@@ -44,3 +44,33 @@ fn env_backend_allowed_in_local() {
         },
     }
 }
+
+#[cfg(feature = "secrets-vault")]
+#[test]
+#[serial]
+fn vault_backend_allowed_in_prod() {
+    use greentic_runner_host::secrets::{VaultAuth, VaultConfig};
+
+    let prev = env::var("GREENTIC_ENV").ok();
+    unsafe {
+        env::set_var("GREENTIC_ENV", "prod");
+    }
+
+    let backend = SecretsBackend::Vault(VaultConfig::new(
+        "http://127.0.0.1:8200",
+        VaultAuth::Token {
+            token: "unused".into(),
+        },
+    ));
+    let result = backend.build_manager();
+    assert!(result.is_ok());
+
+    match prev {
+        Some(value) => unsafe {
+            env::set_var("GREENTIC_ENV", value);
+        },
+        None => unsafe {
+            env::remove_var("GREENTIC_ENV");
+        },
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use greentic_runner_host::secrets::{FileSecretsConfig, FileSecretsManager};
+use greentic_secrets_lib::{SecretError, SecretsManager};
+
+const DEBOUNCE: Duration = Duration::from_millis(100);
+const API_KEY: &str = "secrets://prod/acme/_/billing/api_key";
+
+fn manager(root: &Path, per_tenant: bool) -> Result<FileSecretsManager> {
+    let mut config = FileSecretsConfig::new(root);
+    config.per_tenant = per_tenant;
+    config.debounce = DEBOUNCE;
+    FileSecretsManager::new(config)
+}
+
+/// Read until `expected` is served; rotations must land within the debounce window
+/// plus the watcher's delivery latency.
+async fn eventually_reads(manager: &FileSecretsManager, path: &str, expected: &[u8]) -> Result<()> {
+    let deadline = Instant::now() + DEBOUNCE + Duration::from_secs(2);
+    loop {
+        let value = manager.read(path).await?;
+        if value == expected {
+            return Ok(());
+        }
+        if Instant::now() > deadline {
+            anyhow::bail!(
+                "still reading {:?}, expected {:?}",
+                String::from_utf8_lossy(&value),
+                String::from_utf8_lossy(expected)
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn reads_written_files_and_missing_keys() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    let manager = manager(root.path(), false)?;
+    assert!(matches!(
+        manager.read(API_KEY).await,
+        Err(SecretError::NotFound(_))
+    ));
+
+    manager.write(API_KEY, b"v1").await?;
+    assert_eq!(fs::read(root.path().join("api_key"))?, b"v1");
+    assert_eq!(manager.read(API_KEY).await?, b"v1");
+
+    manager.delete(API_KEY).await?;
+    assert!(matches!(
+        manager.read(API_KEY).await,
+        Err(SecretError::NotFound(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn per_tenant_directories_are_separate() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    fs::create_dir(root.path().join("acme"))?;
+    fs::write(root.path().join("acme").join("api_key"), b"acme-key")?;
+    fs::write(root.path().join("api_key"), b"shared-key")?;
+
+    let manager = manager(root.path(), true)?;
+    assert_eq!(manager.read(API_KEY).await?, b"acme-key");
+    assert!(matches!(
+        manager
+            .read("secrets://prod/globex/_/billing/api_key")
+            .await,
+        Err(SecretError::NotFound(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn in_place_overwrite_is_picked_up() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    fs::write(root.path().join("api_key"), b"v1")?;
+    let manager = manager(root.path(), false)?;
+    assert_eq!(manager.read(API_KEY).await?, b"v1");
+
+    fs::write(root.path().join("api_key"), b"v2")?;
+    eventually_reads(&manager, API_KEY, b"v2").await
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn kubernetes_symlink_swap_is_picked_up() -> Result<()> {
+    use std::os::unix::fs::symlink;
+
+    // The layout a Kubernetes secret volume uses: keys link through `..data`, which
+    // points at a timestamped directory and is swapped by rename.
+    let root = tempfile::tempdir()?;
+    let first = root.path().join("..2026_10_16_00_00_00.1");
+    fs::create_dir(&first)?;
+    fs::write(first.join("api_key"), b"v1")?;
+    symlink("..2026_10_16_00_00_00.1", root.path().join("..data"))?;
+    symlink("..data/api_key", root.path().join("api_key"))?;
+
+    let manager = manager(root.path(), false)?;
+    assert_eq!(manager.read(API_KEY).await?, b"v1");
+
+    let second = root.path().join("..2026_10_16_00_05_00.2");
+    fs::create_dir(&second)?;
+    fs::write(second.join("api_key"), b"v2")?;
+    symlink("..2026_10_16_00_05_00.2", root.path().join("..data_tmp"))?;
+    fs::rename(root.path().join("..data_tmp"), root.path().join("..data"))?;
+    fs::remove_dir_all(&first)?;
+
+    eventually_reads(&manager, API_KEY, b"v2").await
+}
@@ -3,8 +3,9 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use greentic_runner_host::config::SecretsPolicy;
 use greentic_runner_host::secrets::{
-    DynSecretsManager, read_secret_blocking, scoped_secret_path_for_pack,
+    DynSecretsManager, read_secret_blocking, scoped_secret_path_for_pack, write_secret_blocking,
 };
 use greentic_secrets_lib::{SecretError, SecretsManager};
 use greentic_types::{EnvId, TenantCtx, TenantId, UserId};
@@ -38,6 +39,38 @@ impl SecretsManager for MemorySecretsManager {
     }
 }
 
+/// Keys values by the name segment only, like the env backend or a flat file root: the
+/// tenant in the path does not separate anything.
+#[derive(Default)]
+struct FlatSecretsManager {
+    values: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+fn flat_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+#[async_trait]
+impl SecretsManager for FlatSecretsManager {
+    async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+        self.values
+            .read()
+            .get(&flat_name(path))
+            .cloned()
+            .ok_or_else(|| SecretError::NotFound(path.to_string()))
+    }
+
+    async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+        self.values.write().insert(flat_name(path), bytes.to_vec());
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), SecretError> {
+        self.values.write().remove(&flat_name(path));
+        Ok(())
+    }
+}
+
 fn tenant_ctx(env: &str, tenant: &str, user: Option<&str>) -> TenantCtx {
     let env_id = EnvId::new(env).expect("env");
     let tenant_id = TenantId::new(tenant).expect("tenant");
@@ -53,6 +86,7 @@ fn tenant_ctx(env: &str, tenant: &str, user: Option<&str>) -> TenantCtx {
 fn tenant_scoped_secret_reads_do_not_cross() -> Result<()> {
     let manager_impl: Arc<MemorySecretsManager> = Arc::new(MemorySecretsManager::default());
     let manager: DynSecretsManager = manager_impl.clone();
+    let allow_all = SecretsPolicy::allow_all();
     let key = "API_KEY";
     let ctx_a = tenant_ctx("local", "tenant-a", None);
     let ctx_b = tenant_ctx("local", "tenant-b", None);
@@ -63,9 +97,9 @@ fn tenant_scoped_secret_reads_do_not_cross() -> Result<()> {
         .write()
         .insert(scoped_a.clone(), b"alpha".to_vec());
 
-    let value = read_secret_blocking(&manager, &ctx_a, TEST_PACK_ID, key)?;
+    let value = read_secret_blocking(&manager, &allow_all, &ctx_a, TEST_PACK_ID, key)?;
     assert_eq!(value, b"alpha".to_vec());
-    assert!(read_secret_blocking(&manager, &ctx_b, TEST_PACK_ID, key).is_err());
+    assert!(read_secret_blocking(&manager, &allow_all, &ctx_b, TEST_PACK_ID, key).is_err());
     Ok(())
 }
 
@@ -73,6 +107,7 @@ fn tenant_scoped_secret_reads_do_not_cross() -> Result<()> {
 fn user_scoped_secret_reads_include_user_prefix() -> Result<()> {
     let manager_impl: Arc<MemorySecretsManager> = Arc::new(MemorySecretsManager::default());
     let manager: DynSecretsManager = manager_impl.clone();
+    let allow_all = SecretsPolicy::allow_all();
     let key = "SESSION_TOKEN";
     let ctx = tenant_ctx("local", "tenant-a", Some("user-1"));
 
@@ -82,7 +117,77 @@ fn user_scoped_secret_reads_include_user_prefix() -> Result<()> {
         .write()
         .insert(scoped.clone(), b"token".to_vec());
 
-    let value = read_secret_blocking(&manager, &ctx, TEST_PACK_ID, key)?;
+    let value = read_secret_blocking(&manager, &allow_all, &ctx, TEST_PACK_ID, key)?;
     assert_eq!(value, b"token".to_vec());
     Ok(())
 }
+
+#[test]
+fn tenant_prefix_keeps_flat_backends_apart() -> Result<()> {
+    let manager_impl = Arc::new(FlatSecretsManager::default());
+    let manager: DynSecretsManager = manager_impl.clone();
+    let ctx_a = tenant_ctx("local", "tenant-a", None);
+    let ctx_b = tenant_ctx("local", "tenant-b", None);
+
+    // Without the prefix a flat backend hands B's value to A.
+    let flat = SecretsPolicy::allow_all();
+    write_secret_blocking(&manager, &flat, &ctx_b, TEST_PACK_ID, "API_KEY", b"bravo")?;
+    assert_eq!(
+        read_secret_blocking(&manager, &flat, &ctx_a, TEST_PACK_ID, "API_KEY")?,
+        b"bravo".to_vec()
+    );
+
+    let isolated = SecretsPolicy::allow_all().with_tenant_prefix(true);
+    write_secret_blocking(
+        &manager,
+        &isolated,
+        &ctx_b,
+        TEST_PACK_ID,
+        "API_KEY",
+        b"beta",
+    )?;
+    assert!(manager_impl.values.read().contains_key("tenant-b.API_KEY"));
+    assert_eq!(
+        read_secret_blocking(&manager, &isolated, &ctx_b, TEST_PACK_ID, "API_KEY")?,
+        b"beta".to_vec()
+    );
+    assert!(read_secret_blocking(&manager, &isolated, &ctx_a, TEST_PACK_ID, "API_KEY").is_err());
+    assert!(
+        read_secret_blocking(
+            &manager,
+            &isolated,
+            &ctx_a,
+            TEST_PACK_ID,
+            "tenant-b/API_KEY"
+        )
+        .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn shared_namespace_needs_its_own_rule() -> Result<()> {
+    let manager_impl = Arc::new(FlatSecretsManager::default());
+    let manager: DynSecretsManager = manager_impl.clone();
+    manager_impl
+        .values
+        .write()
+        .insert("shared.BASE_URL".into(), b"https://example.test".to_vec());
+    let ctx_a = tenant_ctx("local", "tenant-a", None);
+    let ctx_b = tenant_ctx("local", "tenant-b", None);
+
+    let isolated = SecretsPolicy::allow_all().with_tenant_prefix(true);
+    assert!(isolated.is_allowed("API_KEY"));
+    assert!(!isolated.is_allowed("shared/BASE_URL"));
+
+    let shared = isolated.with_shared(["BASE_URL".to_string()]);
+    assert!(shared.is_allowed("shared/BASE_URL"));
+    assert!(!shared.is_write_allowed("shared/BASE_URL"));
+    for ctx in [&ctx_a, &ctx_b] {
+        assert_eq!(
+            read_secret_blocking(&manager, &shared, ctx, TEST_PACK_ID, "shared/BASE_URL")?,
+            b"https://example.test".to_vec()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+#[cfg(feature = "secrets-vault")]
+mod secrets_vault {
+    use std::time::Duration;
+
+    use anyhow::{Context, Result};
+    use greentic_runner_host::secrets::vault::VaultSecretsManager;
+    use greentic_runner_host::secrets::{VaultAuth, VaultConfig};
+    use greentic_secrets_lib::{SecretError, SecretsManager};
+    use serde_json::{Value, json};
+
+    /// A dev-mode Vault (`vault server -dev`) at `GREENTIC_TEST_VAULT_ADDR`, with its
+    /// root token in `GREENTIC_TEST_VAULT_TOKEN`.
+    fn test_vault() -> Option<(String, String)> {
+        let addr = std::env::var("GREENTIC_TEST_VAULT_ADDR").ok()?;
+        let token = std::env::var("GREENTIC_TEST_VAULT_TOKEN").ok()?;
+        Some((addr, token))
+    }
+
+    fn manager(addr: &str, token: &str) -> Result<VaultSecretsManager> {
+        let mut config = VaultConfig::new(
+            addr,
+            VaultAuth::Token {
+                token: token.to_string(),
+            },
+        );
+        config.cache_ttl = Duration::ZERO;
+        VaultSecretsManager::new(config)
+    }
+
+    /// A renewable child token that lapses after `ttl` unless renewed.
+    async fn short_lived_token(addr: &str, root: &str, ttl: &str) -> Result<String> {
+        let body: Value = reqwest::Client::new()
+            .post(format!("{addr}/v1/auth/token/create"))
+            .header("X-Vault-Token", root)
+            .json(&json!({ "ttl": ttl, "explicit_max_ttl": "5m", "renewable": true }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        body.pointer("/auth/client_token")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .context("token create returned no token")
+    }
+
+    #[tokio::test]
+    async fn reads_written_secret() -> Result<()> {
+        let Some((addr, token)) = test_vault() else {
+            eprintln!("GREENTIC_TEST_VAULT_* not set; skipping vault test");
+            return Ok(());
+        };
+        let manager = manager(&addr, &token)?;
+        let path = "secrets://test/vault-read/_/pack_demo/api_key";
+        manager.write(path, b"s3cr3t").await?;
+        assert_eq!(manager.read(path).await?, b"s3cr3t");
+
+        let binary = "secrets://test/vault-read/_/pack_demo/binary";
+        manager.write(binary, &[0xff, 0x00, 0x10]).await?;
+        assert_eq!(manager.read(binary).await?, vec![0xff, 0x00, 0x10]);
+
+        manager.delete(path).await?;
+        manager.delete(binary).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_key_is_not_found() -> Result<()> {
+        let Some((addr, token)) = test_vault() else {
+            eprintln!("GREENTIC_TEST_VAULT_* not set; skipping vault test");
+            return Ok(());
+        };
+        let manager = manager(&addr, &token)?;
+        let path = "secrets://test/vault-missing/_/pack_demo/absent";
+        match manager.read(path).await {
+            Err(SecretError::NotFound(missing)) => assert_eq!(missing, path),
+            other => panic!("expected NotFound, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renews_token_before_it_expires() -> Result<()> {
+        let Some((addr, root)) = test_vault() else {
+            eprintln!("GREENTIC_TEST_VAULT_* not set; skipping vault test");
+            return Ok(());
+        };
+        let path = "secrets://test/vault-renew/_/pack_demo/api_key";
+        manager(&addr, &root)?.write(path, b"renewed").await?;
+
+        let token = short_lived_token(&addr, &root, "6s").await?;
+        let manager = manager(&addr, &token)?;
+        assert_eq!(manager.read(path).await?, b"renewed");
+        // Past two thirds of the TTL the next call renews; without that the token
+        // would be gone by the final read.
+        tokio::time::sleep(Duration::from_millis(4_500)).await;
+        assert_eq!(manager.read(path).await?, b"renewed");
+        tokio::time::sleep(Duration::from_millis(4_500)).await;
+        assert_eq!(manager.read(path).await?, b"renewed");
+
+        manager.delete(path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "secrets-vault"))]
+#[test]
+fn secrets_vault_disabled() {
+    eprintln!("secrets-vault feature disabled; skipping vault test");
+}
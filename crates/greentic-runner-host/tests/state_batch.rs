@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use greentic_runner_host::engine::host::SessionKey;
+use greentic_runner_host::storage::listing::Cursor;
+use greentic_runner_host::storage::quota::{StateQuota, apply_quota};
+use greentic_runner_host::storage::{
+    DynStateStore, HostStateStore, new_state_store, state_host_from,
+};
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{EnvId, GResult, TenantCtx, TenantId};
+use serde_json::{Value, json};
+
+fn session_key(ctx: &TenantCtx, session: &str) -> SessionKey {
+    SessionKey::new(ctx, "pack.batch", "flow.main", Some(session.into()))
+}
+
+/// Forwards to another store, counting single-key and batch calls.
+struct CountingStore {
+    inner: DynStateStore,
+    single: AtomicUsize,
+    batches: AtomicUsize,
+}
+
+impl StateStore for CountingStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        self.single.fetch_add(1, Ordering::Relaxed);
+        self.inner.get_json(tenant, prefix, key, path)
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        self.single.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs)
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        self.inner.del(tenant, prefix, key)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        self.inner.del_prefix(tenant, prefix)
+    }
+}
+
+impl HostStateStore for CountingStore {
+    fn list_keys(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key_prefix: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> GResult<(Vec<StateKey>, Option<Cursor>)> {
+        self.inner
+            .list_keys(tenant, prefix, key_prefix, cursor, limit)
+    }
+
+    fn remaining_ttl(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+    ) -> GResult<Option<Duration>> {
+        self.inner.remaining_ttl(tenant, prefix, key)
+    }
+
+    fn get_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        keys: &[StateKey],
+    ) -> GResult<Vec<Option<Value>>> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.inner.get_many(tenant, prefix, keys)
+    }
+
+    fn set_many(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        entries: &[(StateKey, Value)],
+        ttl_secs: Option<u32>,
+    ) -> GResult<Vec<GResult<()>>> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.inner.set_many(tenant, prefix, entries, ttl_secs)
+    }
+}
+
+#[tokio::test]
+async fn batch_reads_follow_key_order_and_report_misses() -> Result<()> {
+    let alpha = TenantCtx::new(EnvId::new("local")?, TenantId::new("batch-alpha")?);
+    let beta = TenantCtx::new(EnvId::new("local")?, TenantId::new("batch-beta")?);
+    let host = state_host_from(new_state_store());
+
+    let results = host
+        .set_many(vec![
+            (session_key(&alpha, "a"), json!(1)),
+            (session_key(&alpha, "b"), json!(2)),
+            (session_key(&beta, "a"), json!(10)),
+        ])
+        .await?;
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let values = host
+        .get_many(&[
+            session_key(&alpha, "b"),
+            session_key(&alpha, "missing"),
+            session_key(&beta, "a"),
+            session_key(&alpha, "a"),
+            session_key(&beta, "b"),
+        ])
+        .await?;
+    assert_eq!(
+        values,
+        vec![Some(json!(2)), None, Some(json!(10)), Some(json!(1)), None]
+    );
+    assert!(host.get_many(&[]).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn batches_reach_the_store_once_per_tenant() -> Result<()> {
+    let alpha = TenantCtx::new(EnvId::new("local")?, TenantId::new("batch-count-a")?);
+    let beta = TenantCtx::new(EnvId::new("local")?, TenantId::new("batch-count-b")?);
+    let counting = Arc::new(CountingStore {
+        inner: new_state_store(),
+        single: AtomicUsize::new(0),
+        batches: AtomicUsize::new(0),
+    });
+    let host = state_host_from(counting.clone());
+
+    host.set_many(vec![
+        (session_key(&alpha, "a"), json!(1)),
+        (session_key(&beta, "a"), json!(2)),
+        (session_key(&alpha, "b"), json!(3)),
+    ])
+    .await?;
+    let values = host
+        .get_many(&[
+            session_key(&alpha, "a"),
+            session_key(&alpha, "b"),
+            session_key(&beta, "a"),
+        ])
+        .await?;
+    assert_eq!(values, vec![Some(json!(1)), Some(json!(3)), Some(json!(2))]);
+    assert_eq!(counting.batches.load(Ordering::Relaxed), 4);
+    assert_eq!(counting.single.load(Ordering::Relaxed), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejected_entries_do_not_stop_the_batch() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("batch-quota")?);
+    let store = apply_quota(
+        new_state_store(),
+        StateQuota {
+            max_keys: Some(2),
+            max_bytes: None,
+        },
+    );
+    let host = state_host_from(store);
+
+    let results = host
+        .set_many(vec![
+            (session_key(&ctx, "a"), json!(1)),
+            (session_key(&ctx, "b"), json!(2)),
+            (session_key(&ctx, "c"), json!(3)),
+            (session_key(&ctx, "a"), json!(4)),
+        ])
+        .await?;
+    let accepted = results
+        .iter()
+        .map(|result| result.is_ok())
+        .collect::<Vec<_>>();
+    assert_eq!(accepted, vec![true, true, false, true]);
+    assert_eq!(
+        host.get_many(&[
+            session_key(&ctx, "a"),
+            session_key(&ctx, "b"),
+            session_key(&ctx, "c"),
+        ])
+        .await?,
+        vec![Some(json!(4)), Some(json!(2)), None]
+    );
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use greentic_runner_host::engine::host::SessionKey;
+use greentic_runner_host::secrets::{DynSecretsManager, scoped_secret_path_for_pack};
+use greentic_runner_host::storage::encryption::{StateEncryption, apply_encryption, is_sealed};
+use greentic_runner_host::storage::{
+    DynStateStore, HostStateStore, new_state_store, state_host_from,
+};
+use greentic_secrets_lib::{SecretError, SecretsManager};
+use greentic_state::{StateKey, StateStore};
+use greentic_types::{EnvId, TenantCtx, TenantId};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+#[derive(Default)]
+struct MemorySecrets {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+#[async_trait]
+impl SecretsManager for MemorySecrets {
+    async fn read(&self, path: &str) -> Result<Vec<u8>, SecretError> {
+        self.values
+            .lock()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| SecretError::NotFound(path.to_string()))
+    }
+
+    async fn write(&self, path: &str, bytes: &[u8]) -> Result<(), SecretError> {
+        self.values.lock().insert(path.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), SecretError> {
+        self.values.lock().remove(path);
+        Ok(())
+    }
+}
+
+fn put_key(secrets: &MemorySecrets, ctx: &TenantCtx, name: &str, byte: u8) -> Result<()> {
+    let path = scoped_secret_path_for_pack(ctx, "runner", name)?;
+    secrets.values.lock().insert(path, vec![byte; 32]);
+    Ok(())
+}
+
+fn encryption(key: &str, previous: &[&str]) -> StateEncryption {
+    StateEncryption {
+        key: key.to_string(),
+        previous_keys: previous.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+fn raw_session(raw: &DynStateStore, ctx: &TenantCtx) -> Result<Option<Value>> {
+    Ok(raw.get_json(
+        ctx,
+        "runner",
+        &StateKey::new("pack/pack.enc/flow/flow.main/session/s"),
+        None,
+    )?)
+}
+
+#[tokio::test]
+async fn documents_are_encrypted_at_rest_and_rotated_on_read() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-enc")?);
+    let session = SessionKey::new(&ctx, "pack.enc", "flow.main", Some("s".into()));
+    let manager = Arc::new(MemorySecrets::default());
+    put_key(&manager, &ctx, "state-key-v1", 1)?;
+    let secrets: DynSecretsManager = manager.clone();
+    let raw = new_state_store();
+
+    let document = json!({"email": "someone@example.com", "step": 3});
+    let host = state_host_from(apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key-v1", &[])),
+        Arc::clone(&secrets),
+    ));
+    host.set_json(&session, document.clone()).await?;
+    assert_eq!(host.get_json(&session).await?, Some(document.clone()));
+
+    let stored = raw_session(&raw, &ctx)?.expect("stored");
+    assert!(is_sealed(&stored));
+    assert!(!stored.to_string().contains("someone@example.com"));
+    let first_kid = stored["kid"].clone();
+
+    // Rotate: seal with v2 and keep v1 readable.
+    put_key(&manager, &ctx, "state-key-v2", 2)?;
+    let rotated = state_host_from(apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key-v2", &["state-key-v1"])),
+        Arc::clone(&secrets),
+    ));
+    assert_eq!(rotated.get_json(&session).await?, Some(document.clone()));
+    let restored = raw_session(&raw, &ctx)?.expect("stored");
+    assert!(is_sealed(&restored));
+    assert_ne!(restored["kid"], first_kid, "value re-encrypted with v2");
+
+    // Once re-encrypted, v1 can be retired.
+    let retired = state_host_from(apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key-v2", &[])),
+        secrets,
+    ));
+    assert_eq!(retired.get_json(&session).await?, Some(document));
+    Ok(())
+}
+
+#[tokio::test]
+async fn re_encrypted_values_keep_their_ttl() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-enc-ttl")?);
+    let key = StateKey::new("pack/pack.enc/flow/flow.main/session/s");
+    let manager = Arc::new(MemorySecrets::default());
+    put_key(&manager, &ctx, "state-key-v1", 1)?;
+    put_key(&manager, &ctx, "state-key-v2", 2)?;
+    let secrets: DynSecretsManager = manager;
+    let raw = new_state_store();
+
+    apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key-v1", &[])),
+        Arc::clone(&secrets),
+    )
+    .set_json(&ctx, "runner", &key, None, &json!({"step": 1}), Some(2))?;
+    let first_kid = raw_session(&raw, &ctx)?.expect("stored")["kid"].clone();
+
+    // Reading through the rotated key rewrites the value sealed with v2.
+    let rotated = apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key-v2", &["state-key-v1"])),
+        secrets,
+    );
+    assert_eq!(
+        rotated.get_json(&ctx, "runner", &key, None)?,
+        Some(json!({"step": 1}))
+    );
+    assert_ne!(raw_session(&raw, &ctx)?.expect("stored")["kid"], first_kid);
+    let left = raw.remaining_ttl(&ctx, "runner", &key)?;
+    assert!(
+        left.is_some_and(|left| left <= Duration::from_secs(2)),
+        "write-back kept the deadline: {left:?}"
+    );
+
+    tokio::time::sleep(Duration::from_millis(2_200)).await;
+    assert_eq!(rotated.get_json(&ctx, "runner", &key, None)?, None);
+    assert_eq!(raw_session(&raw, &ctx)?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn legacy_plaintext_is_read_and_sealed() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-enc-legacy")?);
+    let session = SessionKey::new(&ctx, "pack.enc", "flow.main", Some("s".into()));
+    let manager = Arc::new(MemorySecrets::default());
+    put_key(&manager, &ctx, "state-key", 9)?;
+    let raw = new_state_store();
+    state_host_from(Arc::clone(&raw))
+        .set_json(&session, json!({"legacy": true}))
+        .await?;
+
+    let host = state_host_from(apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("state-key", &[])),
+        manager,
+    ));
+    assert_eq!(
+        host.get_json(&session).await?,
+        Some(json!({"legacy": true}))
+    );
+    assert!(is_sealed(&raw_session(&raw, &ctx)?.expect("stored")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_data_key_fails_writes() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-enc-missing")?);
+    let session = SessionKey::new(&ctx, "pack.enc", "flow.main", Some("s".into()));
+    let raw = new_state_store();
+    let host = state_host_from(apply_encryption(
+        Arc::clone(&raw),
+        Some(&encryption("absent-key", &[])),
+        Arc::new(MemorySecrets::default()),
+    ));
+
+    let err = host
+        .set_json(&session, json!({"email": "someone@example.com"}))
+        .await
+        .expect_err("write without a data key must fail");
+    assert!(err.to_string().contains("absent-key"), "unexpected: {err}");
+    assert_eq!(raw_session(&raw, &ctx)?, None);
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+use greentic_runner_host::storage::listing::{self, Cursor};
+use greentic_runner_host::storage::quota::{self, StateQuota};
+use greentic_runner_host::storage::{DynStateStore, new_state_store, state_store_from};
+use greentic_state::inmemory::InMemoryStateStore;
+use greentic_state::{StateKey, StateStore};
+use greentic_types::{EnvId, TenantCtx, TenantId};
+use serde_json::json;
+
+fn put(store: &DynStateStore, ctx: &TenantCtx, key: &str) -> Result<()> {
+    store.set_json(ctx, "runner", &StateKey::new(key), None, &json!({}), None)?;
+    Ok(())
+}
+
+#[test]
+fn pages_through_one_prefix_of_one_tenant() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-list")?);
+    let other = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-list-other")?);
+    // Wrappers forward listing to the backend.
+    let store = quota::apply_quota(
+        new_state_store(),
+        StateQuota {
+            max_keys: Some(1_000),
+            max_bytes: None,
+        },
+    );
+    for idx in 0..25 {
+        put(&store, &ctx, &format!("providers/instances/p{idx:02}.json"))?;
+        put(&store, &ctx, &format!("providers/types/t{idx:02}.json"))?;
+    }
+    put(&store, &other, "providers/instances/foreign.json")?;
+
+    let mut pages = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut cursor: Option<Cursor> = None;
+    loop {
+        let (keys, next) =
+            listing::list_state(&store, &ctx, "providers/instances/", cursor.as_ref(), 10)?;
+        pages.push(keys.len());
+        for key in keys {
+            assert!(key.as_str().starts_with("providers/instances/p"));
+            assert!(seen.insert(key.as_str().to_string()), "repeated {key:?}");
+        }
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(pages, vec![10, 10, 5]);
+    assert_eq!(seen.len(), 25);
+
+    // Deleted keys drop out of later scans.
+    store.del(
+        &ctx,
+        "runner",
+        &StateKey::new("providers/instances/p00.json"),
+    )?;
+    let (keys, next) = listing::list_state(&store, &ctx, "providers/instances/", None, 100)?;
+    assert_eq!(keys.len(), 24);
+    assert_eq!(next, None);
+    Ok(())
+}
+
+#[test]
+fn adopted_plain_stores_list_through_the_index() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-list-plain")?);
+    let store = state_store_from(Arc::new(InMemoryStateStore::new()));
+    put(&store, &ctx, "providers/instances/p.json")?;
+    put(&store, &ctx, "providers/types/t.json")?;
+    let (keys, next) = listing::list_state(&store, &ctx, "providers/instances/", None, 10)?;
+    let keys = keys.iter().map(StateKey::as_str).collect::<Vec<_>>();
+    assert_eq!(keys, vec!["providers/instances/p.json"]);
+    assert_eq!(next, None);
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use greentic_runner_host::engine::error::RunnerError;
+use greentic_runner_host::engine::host::SessionKey;
+use greentic_runner_host::storage::quota::{self, StateQuota, StateUsage};
+use greentic_runner_host::storage::{new_state_store, state_host_from};
+use greentic_types::{EnvId, TenantCtx, TenantId};
+use serde_json::json;
+
+fn session_key(ctx: &TenantCtx, session: &str) -> SessionKey {
+    SessionKey::new(ctx, "pack.quota", "flow.main", Some(session.into()))
+}
+
+#[tokio::test]
+async fn tiny_quota_rejects_writes_until_state_is_deleted() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("quota-tiny")?);
+    let other = TenantCtx::new(EnvId::new("local")?, TenantId::new("quota-other")?);
+    let store = quota::apply_quota(
+        new_state_store(),
+        StateQuota {
+            max_keys: Some(2),
+            max_bytes: Some(64),
+        },
+    );
+    let host = state_host_from(store.clone());
+
+    host.set_json(&session_key(&ctx, "a"), json!({"n": 1}))
+        .await?;
+    host.set_json(&session_key(&ctx, "b"), json!({"n": 2}))
+        .await?;
+    // Overwriting an existing key does not need a new key slot.
+    host.set_json(&session_key(&ctx, "a"), json!({"n": 3}))
+        .await?;
+    assert_eq!(
+        quota::usage(&*store, &ctx)?,
+        StateUsage { keys: 2, bytes: 14 }
+    );
+
+    let err = host
+        .set_json(&session_key(&ctx, "c"), json!({"n": 4}))
+        .await
+        .expect_err("third key exceeds max_keys");
+    assert!(
+        matches!(&err, RunnerError::Policy { reason } if reason.contains("max_keys")),
+        "unexpected error: {err}"
+    );
+    assert_eq!(host.get_json(&session_key(&ctx, "c")).await?, None);
+
+    let err = host
+        .set_json(&session_key(&ctx, "b"), json!({"blob": "x".repeat(64)}))
+        .await
+        .expect_err("large value exceeds max_bytes");
+    assert!(
+        matches!(&err, RunnerError::Policy { reason } if reason.contains("max_bytes")),
+        "unexpected error: {err}"
+    );
+    assert_eq!(
+        host.get_json(&session_key(&ctx, "b")).await?,
+        Some(json!({"n": 2}))
+    );
+
+    // Other tenants keep their own budget.
+    host.set_json(&session_key(&other, "a"), json!({"n": 1}))
+        .await?;
+
+    host.del(&session_key(&ctx, "a")).await?;
+    assert_eq!(
+        quota::usage(&*store, &ctx)?,
+        StateUsage { keys: 1, bytes: 7 }
+    );
+    host.set_json(&session_key(&ctx, "c"), json!({"n": 4}))
+        .await?;
+    assert_eq!(
+        host.get_json(&session_key(&ctx, "c")).await?,
+        Some(json!({"n": 4}))
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use greentic_runner_host::engine::host::SessionKey;
+use greentic_runner_host::storage::expiry::{self, ExpiringStateStore, ExpirySweep};
+use greentic_runner_host::storage::listing::IndexedStateStore;
+use greentic_runner_host::storage::{new_state_store, state_host_from};
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{EnvId, GResult, TenantCtx, TenantId};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+/// Store without any notion of expiry, so only the wrapper can drop keys.
+#[derive(Default)]
+struct PlainStore {
+    values: Mutex<HashMap<String, Value>>,
+}
+
+fn plain_key(prefix: &str, key: &StateKey) -> String {
+    format!("{prefix}/{}", key.as_str())
+}
+
+impl StateStore for PlainStore {
+    fn get_json(
+        &self,
+        _tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        _path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        Ok(self.values.lock().get(&plain_key(prefix, key)).cloned())
+    }
+
+    fn set_json(
+        &self,
+        _tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        _path: Option<&StatePath>,
+        value: &Value,
+        _ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        self.values
+            .lock()
+            .insert(plain_key(prefix, key), value.clone());
+        Ok(())
+    }
+
+    fn del(&self, _tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        Ok(self.values.lock().remove(&plain_key(prefix, key)).is_some())
+    }
+
+    fn del_prefix(&self, _tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        let mut values = self.values.lock();
+        let before = values.len();
+        values.retain(|key, _| !key.starts_with(&format!("{prefix}/")));
+        Ok((before - values.len()) as u64)
+    }
+}
+
+fn session_key(ctx: &TenantCtx, session: &str) -> SessionKey {
+    SessionKey::new(ctx, "pack.ttl", "flow.main", Some(session.into()))
+}
+
+#[tokio::test]
+async fn short_ttl_values_expire() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-ttl")?);
+    let host = state_host_from(new_state_store());
+
+    host.set_json_ttl(
+        &session_key(&ctx, "window"),
+        json!({"hits": 1}),
+        Duration::from_millis(300),
+    )
+    .await?;
+    host.set_json(&session_key(&ctx, "kept"), json!({"hits": 2}))
+        .await?;
+    assert_eq!(
+        host.get_json(&session_key(&ctx, "window")).await?,
+        Some(json!({"hits": 1}))
+    );
+
+    // TTLs are kept in whole seconds, rounded up.
+    tokio::time::sleep(Duration::from_millis(1_200)).await;
+    assert_eq!(host.get_json(&session_key(&ctx, "window")).await?, None);
+    assert_eq!(
+        host.get_json(&session_key(&ctx, "kept")).await?,
+        Some(json!({"hits": 2}))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn sweeper_removes_expired_keys_that_are_never_read() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("state-ttl-sweep")?);
+    let raw = Arc::new(PlainStore::default());
+    let expiring = Arc::new(ExpiringStateStore::new(Arc::new(IndexedStateStore::new(
+        raw.clone(),
+    ))));
+    let store = expiry::track(Arc::clone(&expiring));
+    let host = state_host_from(store);
+
+    host.set_json_ttl(&session_key(&ctx, "a"), json!(1), Duration::from_secs(1))
+        .await?;
+    host.set_json_ttl(&session_key(&ctx, "b"), json!(2), Duration::from_secs(1))
+        .await?;
+    host.set_json(&session_key(&ctx, "c"), json!(3)).await?;
+    assert_eq!(expiring.sweep_expired()?, 0);
+    assert_eq!(raw.values.lock().len(), 3);
+
+    tokio::time::sleep(Duration::from_millis(1_100)).await;
+    // The registry sweep covers this store along with any others in the process.
+    assert!(expiry::sweep_all() >= 2);
+    assert_eq!(raw.values.lock().len(), 1);
+    assert_eq!(
+        host.get_json(&session_key(&ctx, "c")).await?,
+        Some(json!(3))
+    );
+    Ok(())
+}
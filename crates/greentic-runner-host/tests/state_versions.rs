@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use greentic_runner_host::engine::host::SessionKey;
+use greentic_runner_host::storage::versioned::{StateMigrations, apply_migrations};
+use greentic_runner_host::storage::{new_state_store, state_host_from};
+use greentic_state::{StateKey, StateStore};
+use greentic_types::{EnvId, TenantCtx, TenantId};
+use serde_json::json;
+
+#[tokio::test]
+async fn legacy_documents_are_migrated_on_read_and_written_back() -> Result<()> {
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("versions")?);
+    let raw = new_state_store();
+    let key = StateKey::new("providers/instances/messaging.json");
+    raw.set_json(&ctx, "runner", &key, None, &json!({"name": "old"}), None)?;
+
+    let migrations = StateMigrations::new().register(0, |from, data| {
+        assert_eq!(from, 0);
+        Ok(json!({"display_name": data["name"], "enabled": true}))
+    });
+    let store = apply_migrations(Arc::clone(&raw), migrations);
+
+    let migrated = json!({"display_name": "old", "enabled": true});
+    assert_eq!(
+        store.get_json(&ctx, "runner", &key, None)?,
+        Some(migrated.clone())
+    );
+    assert_eq!(
+        raw.get_json(&ctx, "runner", &key, None)?,
+        Some(json!({"schema_version": 1, "data": migrated}))
+    );
+
+    // The state host writes new documents at the current version.
+    let session = SessionKey::new(&ctx, "pack.versions", "flow.main", Some("s".into()));
+    let host = state_host_from(Arc::clone(&store));
+    host.set_json(&session, json!({"step": 2})).await?;
+    assert_eq!(host.get_json(&session).await?, Some(json!({"step": 2})));
+    let stored = raw.get_json(
+        &ctx,
+        "runner",
+        &StateKey::new("pack/pack.versions/flow/flow.main/session/s"),
+        None,
+    )?;
+    assert_eq!(
+        stored,
+        Some(json!({"schema_version": 1, "data": {"step": 2}}))
+    );
+    Ok(())
+}
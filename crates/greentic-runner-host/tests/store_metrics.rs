@@ -0,0 +1,194 @@
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use greentic_runner_host::http::health::HealthState;
+use greentic_runner_host::http::metrics::{HttpMetrics, render};
+use greentic_runner_host::runtime::ActivePacks;
+use greentic_runner_host::storage::metrics::{
+    STATE_STORE, StoreMetricsConfig, StoreOp, instrument_state, store_metrics,
+};
+use greentic_runner_host::storage::state_store_from;
+use greentic_state::inmemory::InMemoryStateStore;
+use greentic_state::{StateKey, StatePath, StateStore};
+use greentic_types::{EnvId, GResult, TenantCtx, TenantId};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// In-memory store whose reads take `delay`.
+struct SlowStore {
+    inner: InMemoryStateStore,
+    delay: Duration,
+}
+
+impl StateStore for SlowStore {
+    fn get_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+    ) -> GResult<Option<Value>> {
+        thread::sleep(self.delay);
+        self.inner.get_json(tenant, prefix, key, path)
+    }
+
+    fn set_json(
+        &self,
+        tenant: &TenantCtx,
+        prefix: &str,
+        key: &StateKey,
+        path: Option<&StatePath>,
+        value: &Value,
+        ttl_secs: Option<u32>,
+    ) -> GResult<()> {
+        self.inner
+            .set_json(tenant, prefix, key, path, value, ttl_secs)
+    }
+
+    fn del(&self, tenant: &TenantCtx, prefix: &str, key: &StateKey) -> GResult<bool> {
+        self.inner.del(tenant, prefix, key)
+    }
+
+    fn del_prefix(&self, tenant: &TenantCtx, prefix: &str) -> GResult<u64> {
+        self.inner.del_prefix(tenant, prefix)
+    }
+}
+
+/// Collects the fields of every event as `name=value` strings.
+#[derive(Default)]
+struct CapturedEvents(Mutex<Vec<String>>);
+
+struct FieldsVisitor(Vec<String>);
+
+impl Visit for FieldsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push(format!("{}={value:?}", field.name()));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push(format!("{}={value}", field.name()));
+    }
+}
+
+impl Subscriber for &'static CapturedEvents {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = FieldsVisitor(Vec::new());
+        event.record(&mut visitor);
+        self.0.lock().push(visitor.0.join(" "));
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn slow_reads_are_logged_and_counted() -> Result<()> {
+    let events: &'static CapturedEvents = Box::leak(Box::default());
+    let ctx = TenantCtx::new(EnvId::new("local")?, TenantId::new("store-metrics-slow")?);
+    let raw = state_store_from(Arc::new(SlowStore {
+        inner: InMemoryStateStore::new(),
+        delay: Duration::from_millis(30),
+    }));
+    let store = instrument_state(
+        raw,
+        &StoreMetricsConfig {
+            enabled: true,
+            slow_threshold: Duration::from_millis(10),
+        },
+    );
+    let key = StateKey::new("secret-key");
+
+    tracing::subscriber::with_default(events, || -> Result<()> {
+        store.set_json(
+            &ctx,
+            "runner",
+            &key,
+            None,
+            &json!({"token": "hunter2"}),
+            None,
+        )?;
+        assert!(store.get_json(&ctx, "runner", &key, None)?.is_some());
+        assert!(
+            store
+                .get_json(&ctx, "runner", &StateKey::new("absent"), None)?
+                .is_none()
+        );
+        Ok(())
+    })?;
+
+    let warnings = events
+        .0
+        .lock()
+        .iter()
+        .filter(|line| line.contains("slow store operation"))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 2, "unexpected events: {warnings:?}");
+    for line in &warnings {
+        assert!(line.contains("tenant=store-metrics-slow"), "{line}");
+        assert!(line.contains("prefix=runner"), "{line}");
+        assert!(line.contains("op=get"), "{line}");
+        assert!(!line.contains("secret-key"), "key leaked: {line}");
+        assert!(!line.contains("hunter2"), "value leaked: {line}");
+    }
+
+    let samples = store_metrics().tenant("store-metrics-slow");
+    let get = samples
+        .iter()
+        .find(|sample| sample.op == StoreOp::Get)
+        .expect("get recorded");
+    assert_eq!(get.store, STATE_STORE);
+    assert_eq!(get.metrics.calls, 2);
+    assert_eq!((get.metrics.hits, get.metrics.misses), (1, 1));
+    assert_eq!(get.metrics.slow, 2);
+    assert_eq!(get.metrics.latency.count, 2);
+    // 30ms reads land above the 25ms bucket.
+    let bucket = |bound: f64| {
+        get.metrics
+            .latency
+            .buckets
+            .iter()
+            .find(|(le, _)| *le == bound)
+            .map(|(_, count)| *count)
+    };
+    assert_eq!(bucket(0.025), Some(0));
+    assert_eq!(bucket(5.0), Some(2));
+
+    let set = samples
+        .iter()
+        .find(|sample| sample.op == StoreOp::Set)
+        .expect("set recorded");
+    assert_eq!((set.metrics.calls, set.metrics.slow), (1, 0));
+
+    let body = render(
+        &ActivePacks::new(),
+        &HttpMetrics::new(),
+        &HealthState::new(),
+    );
+    assert!(body.contains(
+        "greentic_store_operation_duration_seconds_count{store=\"state\",tenant=\"store-metrics-slow\",op=\"get\"} 2"
+    ));
+    assert!(
+        body.contains("greentic_store_hits_total{store=\"state\",tenant=\"store-metrics-slow\"} 1")
+    );
+    Ok(())
+}
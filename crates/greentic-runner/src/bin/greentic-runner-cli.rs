@@ -8,8 +8,8 @@ use base64::Engine;
 use base64::engine::general_purpose::STANDARD as B64_STANDARD;
 use clap::{Parser, ValueEnum};
 use greentic_runner::desktop::{
-    DevProfile, HttpMock, HttpMockMode, MocksConfig, OtlpHook, Profile, RunOptions, Runner,
-    SigningPolicy, TenantContext, ToolsMock,
+    DevProfile, HttpMock, HttpMockMode, MocksConfig, OtlpHook, Profile, RunOptions, RunResult,
+    Runner, SigningPolicy, TenantContext, ToolsMock,
 };
 use greentic_types::flow::{Flow, Node, Routing};
 use greentic_types::{PackManifest, decode_pack_manifest};
@@ -46,6 +46,10 @@ struct Cli {
     #[arg(long)]
     json: bool,
 
+    /// Run again whenever the pack or its components change, until interrupted
+    #[arg(long, conflicts_with = "mock_exec")]
+    watch: bool,
+
     /// JSON input payload (string form)
     #[arg(long, default_value = "{}")]
     input: String,
@@ -321,20 +325,57 @@ fn main() -> Result<()> {
         }
     });
 
+    if cli.watch {
+        return watch_pack(&runner, &cli.pack, cli.json);
+    }
+
     let result = runner
         .run_pack_with(&cli.pack, |_opts| {})
         .with_context(|| format!("failed to run pack {}", cli.pack.display()))?;
 
-    let mut value = serde_json::to_value(&result)
+    let value = render_result(&result, cli.json)?;
+    if let Some(err) = failure_message(&value) {
+        bail!("pack run failed: {err}");
+    }
+
+    Ok(())
+}
+
+/// Print every run until Ctrl-C; failed runs are reported and the watch carries on.
+fn watch_pack(runner: &Runner, pack: &Path, json: bool) -> Result<()> {
+    let label = pack.display().to_string();
+    let watch = runner.watch_pack(pack, move |result| {
+        let outcome = result
+            .with_context(|| format!("failed to run pack {label}"))
+            .and_then(|result| render_result(&result, json));
+        match outcome {
+            Ok(value) => {
+                if let Some(err) = failure_message(&value) {
+                    eprintln!("pack run failed: {err}");
+                }
+            }
+            Err(err) => eprintln!("{err:#}"),
+        }
+        eprintln!("Watching {label} for changes (Ctrl-C to stop)");
+    })?;
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to create tokio runtime")?
+        .block_on(tokio::signal::ctrl_c())
+        .context("failed to wait for Ctrl-C")?;
+    watch.stop();
+    Ok(())
+}
+
+/// Print the run result and return it as JSON.
+fn render_result(result: &RunResult, json: bool) -> Result<Value> {
+    let mut value = serde_json::to_value(result)
         .map_err(|err| anyhow!("failed to serialize run result: {err}"))?;
     if let Some(map) = value.as_object_mut() {
         map.insert("exec_mode".to_string(), serde_json::json!("runtime"));
     }
-    let status = value
-        .get("status")
-        .and_then(Value::as_str)
-        .unwrap_or_default();
-    let rendered = if cli.json {
+    let rendered = if json {
         serde_json::to_string(&value)
             .map_err(|err| anyhow!("failed to serialize run result: {err}"))?
     } else {
@@ -342,16 +383,20 @@ fn main() -> Result<()> {
             .map_err(|err| anyhow!("failed to serialize run result: {err}"))?
     };
     println!("{rendered}");
+    Ok(value)
+}
 
-    if status == "Failure" || status == "PartialFailure" {
-        let err = value
+fn failure_message(value: &Value) -> Option<&str> {
+    let status = value
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    (status == "Failure" || status == "PartialFailure").then(|| {
+        value
             .get("error")
             .and_then(Value::as_str)
-            .unwrap_or("pack run returned failure status");
-        bail!("pack run failed: {err}");
-    }
-
-    Ok(())
+            .unwrap_or("pack run returned failure status")
+    })
 }
 
 fn init_run_logging() -> Result<PathBuf> {
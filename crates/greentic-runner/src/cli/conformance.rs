@@ -17,8 +17,9 @@ use walkdir::WalkDir;
 
 use greentic_runner_host::RunnerWasiPolicy;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::PackRuntime;
 use greentic_runner_host::secrets::default_manager;
@@ -864,6 +865,8 @@ fn build_host(enable_trace: bool) -> Result<RunnerHost> {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -873,6 +876,13 @@ fn build_host(enable_trace: bool) -> Result<RunnerHost> {
         trace,
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: std::collections::BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     };
 
     let wasi_policy = RunnerWasiPolicy::default().inherit_stdio(false);
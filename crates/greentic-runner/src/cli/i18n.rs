@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use clap::{Args, Subcommand};
+
+use greentic_runner_host::runner::i18n::{
+    CATALOG_DIR_ENV, CatalogLint, known_message_keys, lint_catalogs, load_catalogs,
+};
+
+/// Inspect the message catalogs used for localized diagnostics.
+#[derive(Debug, Subcommand)]
+pub enum I18nCommand {
+    /// Report keys each catalog is missing or has that no runner message uses
+    Lint(LintArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct LintArgs {
+    /// Directory of {locale}.json catalogs (defaults to GREENTIC_I18N_CATALOG_DIR)
+    #[arg(long, value_name = "DIR")]
+    pub catalog_dir: Option<PathBuf>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Exit with an error when any catalog is incomplete
+    #[arg(long)]
+    pub strict: bool,
+}
+
+pub async fn run(cmd: I18nCommand) -> Result<()> {
+    match cmd {
+        I18nCommand::Lint(args) => lint(args),
+    }
+}
+
+fn lint(args: LintArgs) -> Result<()> {
+    let dir = args
+        .catalog_dir
+        .or_else(|| std::env::var_os(CATALOG_DIR_ENV).map(PathBuf::from))
+        .filter(|dir| !dir.as_os_str().is_empty());
+    let Some(dir) = dir else {
+        bail!("no catalogs to lint: pass --catalog-dir or set {CATALOG_DIR_ENV}");
+    };
+    let locales = load_catalogs(&dir)?;
+    let report = lint_catalogs(&known_message_keys())
+        .into_iter()
+        .filter(|lint| locales.contains(&lint.locale))
+        .collect::<Vec<_>>();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    let incomplete = report.iter().filter(|lint| !lint.is_complete()).count();
+    if args.strict && incomplete > 0 {
+        bail!("{incomplete} of {} catalogs are incomplete", report.len());
+    }
+    Ok(())
+}
+
+fn print_report(report: &[CatalogLint]) {
+    for lint in report {
+        if lint.is_complete() {
+            println!("{}: complete", lint.locale);
+            continue;
+        }
+        println!(
+            "{}: {} missing, {} extra",
+            lint.locale,
+            lint.missing.len(),
+            lint.extra.len()
+        );
+        for key in &lint.missing {
+            println!("  - {key}");
+        }
+        for key in &lint.extra {
+            println!("  + {key}");
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod conformance;
+pub mod i18n;
 pub mod replay;
+pub mod secrets;
@@ -10,8 +10,9 @@ use greentic_runner_host::component_api::node::{
     ExecCtx as ComponentExecCtx, TenantCtx as ComponentTenantCtx,
 };
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::{ComponentResolution, PackRuntime};
 use greentic_runner_host::secrets::default_manager;
@@ -152,6 +153,8 @@ fn replay_host_config() -> HostConfig {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -161,6 +164,13 @@ fn replay_host_config() -> HostConfig {
         trace: greentic_runner_host::trace::TraceConfig::from_env(),
         validation: greentic_runner_host::validate::ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: std::collections::BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -178,6 +188,7 @@ async fn replay_step(
     }
     if component_id.starts_with("emit.")
         || component_id == "flow.call"
+        || component_id == "subflow"
         || component_id == "session.wait"
     {
         return Ok(Value::Null);
@@ -256,6 +267,8 @@ fn component_exec_ctx(trace: &TraceEnvelope, node_id: &str) -> ComponentExecCtx
             deadline_unix_ms: None,
             attempt: 1,
             idempotency_key: None,
+            attributes: Vec::new(),
+            impersonation: None,
         },
         i18n_id: None,
         flow_id: trace.flow.id.clone(),
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+
+use greentic_runner_host::secrets::{KEYRING_ENV_PREFIX, KeyringSecretsConfig};
+
+/// Manage secrets in the OS keyring, as read by the desktop runner.
+#[derive(Debug, Subcommand)]
+pub enum SecretsCommand {
+    /// Store a secret; the value is read from stdin unless --value is given
+    Set(SecretsSetArgs),
+    /// Print a secret to stdout
+    Get(SecretKeyArgs),
+    /// Remove a secret
+    Delete(SecretKeyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SecretKeyArgs {
+    /// Secret key, as requested by the pack
+    #[arg(value_name = "KEY")]
+    pub key: String,
+
+    /// Tenant owning the secret
+    #[arg(long, default_value = "local-dev")]
+    pub tenant: String,
+
+    /// Environment the secret belongs to (GREENTIC_ENV of the runner)
+    #[arg(long, default_value = "local")]
+    pub env: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SecretsSetArgs {
+    #[command(flatten)]
+    pub target: SecretKeyArgs,
+
+    /// Secret value; prefer stdin so it stays out of shell history
+    #[arg(long, value_name = "VALUE")]
+    pub value: Option<String>,
+}
+
+pub async fn run(cmd: SecretsCommand) -> Result<()> {
+    let config = KeyringSecretsConfig::from_env(KEYRING_ENV_PREFIX)?;
+    tokio::task::spawn_blocking(move || execute(&config, cmd))
+        .await
+        .context("keyring task failed")?
+}
+
+#[cfg(feature = "secrets-keyring")]
+fn execute(config: &KeyringSecretsConfig, cmd: SecretsCommand) -> Result<()> {
+    use std::io::Write;
+
+    use anyhow::anyhow;
+    use greentic_runner_host::secrets::keyring;
+
+    keyring::probe(config).map_err(|reason| anyhow!("OS keyring unavailable: {reason}"))?;
+    match cmd {
+        SecretsCommand::Set(args) => {
+            let value = match args.value {
+                Some(value) => value,
+                None => read_stdin_value()?,
+            };
+            if value.is_empty() {
+                bail!("refusing to store an empty secret");
+            }
+            let target = &args.target;
+            config
+                .entry(&target.env, &target.tenant, &target.key)
+                .set(value.as_bytes())
+                .map_err(|err| anyhow!(err.to_string()))?;
+            eprintln!("stored {} for tenant {}", target.key, target.tenant);
+        }
+        SecretsCommand::Get(args) => {
+            let value = config
+                .entry(&args.env, &args.tenant, &args.key)
+                .get()
+                .map_err(|err| anyhow!(err.to_string()))?;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&value)?;
+            stdout.write_all(b"\n")?;
+        }
+        SecretsCommand::Delete(args) => {
+            config
+                .entry(&args.env, &args.tenant, &args.key)
+                .delete()
+                .map_err(|err| anyhow!(err.to_string()))?;
+            eprintln!("deleted {} for tenant {}", args.key, args.tenant);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "secrets-keyring"))]
+fn execute(_config: &KeyringSecretsConfig, _cmd: SecretsCommand) -> Result<()> {
+    bail!("greentic-runner was built without the `secrets-keyring` feature")
+}
+
+/// Read the value from stdin, dropping the trailing newline of `echo` or a terminal.
+#[cfg(feature = "secrets-keyring")]
+fn read_stdin_value() -> Result<String> {
+    use std::io::Read;
+
+    let mut value = String::new();
+    std::io::stdin()
+        .read_to_string(&mut value)
+        .context("failed to read secret value from stdin")?;
+    let trimmed = value.strip_suffix('\n').unwrap_or(&value);
+    Ok(trimmed.strip_suffix('\r').unwrap_or(trimmed).to_string())
+}
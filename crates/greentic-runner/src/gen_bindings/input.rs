@@ -1,11 +1,8 @@
 use anyhow::{Context, Result, bail};
-use std::{
-    fs, io,
-    path::{Path, PathBuf},
-};
+use greentic_runner_host::gtpack::{self, ArchiveLimits, ExtractionReport};
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use walkdir::WalkDir;
-use zip::ZipArchive;
 
 pub fn resolve_pack_root(input: &Path) -> Result<(PathBuf, Option<TempDir>)> {
     if input.is_dir() {
@@ -15,6 +12,7 @@ pub fn resolve_pack_root(input: &Path) -> Result<(PathBuf, Option<TempDir>)> {
     let temp_dir = tempfile::tempdir()
         .with_context(|| format!("failed to create temp dir for {}", input.display()))?;
     unzip_gtpack_to_dir(input, temp_dir.path())
+        .and_then(|report| report.ensure_clean())
         .with_context(|| format!("failed to extract {}", input.display()))?;
     let pack_root = find_pack_root(temp_dir.path()).with_context(|| {
         format!(
@@ -25,36 +23,10 @@ pub fn resolve_pack_root(input: &Path) -> Result<(PathBuf, Option<TempDir>)> {
     Ok((pack_root, Some(temp_dir)))
 }
 
-pub fn unzip_gtpack_to_dir(gtpack_path: &Path, out_dir: &Path) -> Result<()> {
-    let file = fs::File::open(gtpack_path)
-        .with_context(|| format!("failed to open {}", gtpack_path.display()))?;
-    let mut archive = ZipArchive::new(file)
-        .with_context(|| format!("{} is not a valid gtpack", gtpack_path.display()))?;
-
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .with_context(|| format!("failed to read {}", gtpack_path.display()))?;
-        let Some(enclosed) = entry.enclosed_name() else {
-            continue;
-        };
-        let out_path = out_dir.join(enclosed);
-        if entry.is_dir() {
-            fs::create_dir_all(&out_path)
-                .with_context(|| format!("failed to create {}", out_path.display()))?;
-            continue;
-        }
-        if let Some(parent) = out_path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create {}", parent.display()))?;
-        }
-        let mut outfile = fs::File::create(&out_path)
-            .with_context(|| format!("failed to create {}", out_path.display()))?;
-        io::copy(&mut entry, &mut outfile)
-            .with_context(|| format!("failed to write {}", out_path.display()))?;
-    }
-
-    Ok(())
+/// Extract `gtpack_path` into `out_dir` within the limits from the environment. Unsafe
+/// entries are skipped and listed in the report rather than failing the extraction.
+pub fn unzip_gtpack_to_dir(gtpack_path: &Path, out_dir: &Path) -> Result<ExtractionReport> {
+    gtpack::extract_to_dir(gtpack_path, out_dir, &ArchiveLimits::from_env())
 }
 
 pub fn find_pack_root(extracted_root: &Path) -> Result<PathBuf> {
@@ -121,6 +93,7 @@ pub fn find_pack_root(extracted_root: &Path) -> Result<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use std::io::Write;
     use zip::write::FileOptions;
 
@@ -185,6 +158,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn resolves_zstd_compressed_gtpack() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("zstd.gtpack");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&gtpack)?);
+        let options = FileOptions::<()>::default().compression_method(zip::CompressionMethod::Zstd);
+        zip.start_file("pack.yaml", options)?;
+        zip.write_all(b"name: demo")?;
+        zip.finish()?;
+
+        let (pack_root, _temp_dir) = resolve_pack_root(&gtpack)?;
+        assert_eq!(
+            fs::read_to_string(pack_root.join("pack.yaml"))?,
+            "name: demo"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gtpack_with_escaping_entry_errors() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let gtpack = temp.path().join("slip.gtpack");
+        write_zip(
+            &gtpack,
+            &[("pack.yaml", "name: demo"), ("../escape.txt", "boo")],
+        )?;
+        let err = resolve_pack_root(&gtpack).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("refused 1 unsafe entry: ../escape.txt"));
+        assert!(!temp.path().join("escape.txt").exists());
+        Ok(())
+    }
+
     #[test]
     fn gtpack_missing_pack_yaml_errors() -> Result<()> {
         let temp = tempfile::tempdir()?;
@@ -3,7 +3,8 @@
 //!
 //! This crate provides two supported integration paths:
 //! - [`run_http_host`] mirrors the CLI and starts the HTTP server that exposes
-//!   ingress adapters, admin endpoints, and the pack watcher.
+//!   ingress adapters, admin endpoints, and the pack watcher. [`start_http_host`]
+//!   does the same in the background and returns a [`RunnerHandle`].
 //! - [`start_embedded_host`] constructs a [`RunnerHost`] without spinning up the
 //!   HTTP server so callers can drive `handle_activity` manually (desktop/dev
 //!   harnesses, tests, etc.).
@@ -11,9 +12,9 @@
 use anyhow::Result;
 
 pub use greentic_runner_host::{
-    self as host, Activity, ActivityKind, HostBuilder, HostServer, RunnerConfig, RunnerHost,
-    TenantHandle, config, http, pack, routing, runner, runtime, runtime_wasmtime, telemetry,
-    verify, watcher,
+    self as host, Activity, ActivityKind, HostBuilder, HostServer, RunnerConfig, RunnerHandle,
+    RunnerHost, TenantHandle, config, http, pack, routing, runner, runtime, runtime_wasmtime,
+    telemetry, verify, watcher,
 };
 
 pub mod desktop {
@@ -28,6 +29,12 @@ pub async fn run_http_host(cfg: RunnerConfig) -> Result<()> {
     greentic_runner_host::run(cfg).await
 }
 
+/// Start the HTTP host in the background; the returned handle exposes the bound
+/// addresses, readiness and shutdown.
+pub async fn start_http_host(cfg: RunnerConfig) -> Result<RunnerHandle> {
+    greentic_runner_host::start(cfg).await
+}
+
 /// Build and start a [`RunnerHost`] without wiring the HTTP ingress server.
 /// Callers are responsible for loading packs via [`RunnerHost::load_pack`] and
 /// invoking [`RunnerHost::handle_activity`] directly.
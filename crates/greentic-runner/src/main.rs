@@ -1,22 +1,23 @@
 use clap::{Parser, Subcommand, ValueEnum};
 mod cli;
-use greentic_config::{ConfigFileFormat, ConfigLayer, ConfigResolver};
-use greentic_runner_host::cache::{
-    ArtifactKey, CacheConfig, CacheManager, CpuPolicy, EngineProfile,
-};
+use greentic_config::{ConfigLayer, ConfigResolver};
+use greentic_runner_host::cache::{ArtifactKey, CacheConfig, CacheManager};
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::PackRuntime;
 use greentic_runner_host::secrets::default_manager;
 use greentic_runner_host::storage::{new_session_store, new_state_store};
-use greentic_runner_host::trace::{TraceConfig, TraceMode};
+use greentic_runner_host::trace::{TraceConfig, TraceExportConfig, TraceMode};
 use greentic_runner_host::validate::{ValidationConfig, ValidationMode};
-use greentic_runner_host::{RunnerConfig, RunnerWasiPolicy, run as run_host};
+use greentic_runner_host::wasm_engine;
+use greentic_runner_host::{ListenerConfig, RunnerConfig, RunnerWasiPolicy, run as run_host};
 use greentic_types::ComponentSourceRef;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs as async_fs;
 
 use anyhow::{Context, Result, bail};
@@ -42,6 +43,10 @@ enum Command {
     Replay(cli::replay::ReplayArgs),
     Conformance(cli::conformance::ConformanceArgs),
     Contract(ContractArgs),
+    #[command(subcommand)]
+    Secrets(cli::secrets::SecretsCommand),
+    #[command(subcommand)]
+    I18n(cli::i18n::I18nCommand),
 }
 
 #[derive(Debug, Parser)]
@@ -82,7 +87,7 @@ struct ContractArgs {
 #[derive(Debug, Subcommand)]
 enum CacheCommand {
     Warmup(CacheWarmupArgs),
-    Doctor,
+    Doctor(CacheDoctorArgs),
     Prune(CachePruneArgs),
 }
 
@@ -97,11 +102,22 @@ struct CacheWarmupArgs {
     mode: CacheWarmupMode,
 }
 
+#[derive(Debug, Parser)]
+struct CacheDoctorArgs {
+    /// Delete entries the cache would never read
+    #[arg(long)]
+    repair: bool,
+}
+
 #[derive(Debug, Parser)]
 struct CachePruneArgs {
     /// Report prune result without deleting artifacts
     #[arg(long)]
     dry_run: bool,
+
+    /// Also remove entries not read for this many seconds (overrides GREENTIC_CACHE_MAX_AGE_SECS)
+    #[arg(long, value_name = "SECS")]
+    max_age_secs: Option<u64>,
 }
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
@@ -136,6 +152,16 @@ struct RunArgs {
     #[arg(long, default_value = "8080")]
     port: u16,
 
+    /// Listener as ADDR[=groups], e.g. 127.0.0.1:9090=admin,metrics or
+    /// unix:/run/greentic.sock (repeatable; replaces --port when given).
+    /// Groups: ingress, operator, admin, metrics, health.
+    #[arg(long = "listen", value_name = "SPEC")]
+    listen: Vec<ListenerConfig>,
+
+    /// Abort startup if any tenant fails preflight (also GREENTIC_STRICT_PREFLIGHT=1)
+    #[arg(long = "strict-preflight")]
+    strict_preflight: bool,
+
     /// Disable the component compilation cache
     #[arg(long)]
     no_cache: bool,
@@ -266,6 +292,8 @@ async fn run_with_cli(cli: Cli) -> anyhow::Result<()> {
             Command::Replay(args) => cli::replay::run(args).await,
             Command::Conformance(args) => cli::conformance::run(args).await,
             Command::Contract(args) => run_contract(args).await,
+            Command::Secrets(cmd) => cli::secrets::run(cmd).await,
+            Command::I18n(cmd) => cli::i18n::run(cmd).await,
         };
     }
     let run = cli.run;
@@ -298,7 +326,8 @@ async fn run_with_cli(cli: Cli) -> anyhow::Result<()> {
             },
             trace_out.or(run.trace_out.clone()),
         )
-        .with_capture_inputs(matches!(run.trace_capture_inputs, TraceCaptureArg::On));
+        .with_capture_inputs(matches!(run.trace_capture_inputs, TraceCaptureArg::On))
+        .with_export(trace_section(run.config.as_deref())?);
     let validation_mode = run.validation.map(|value| match value {
         ValidationArg::Off => ValidationMode::Off,
         ValidationArg::Warn => ValidationMode::Warn,
@@ -307,9 +336,26 @@ async fn run_with_cli(cli: Cli) -> anyhow::Result<()> {
     let validation_config = validation_mode
         .map(|mode| ValidationConfig::from_env().with_mode(mode))
         .unwrap_or_else(ValidationConfig::from_env);
-    let mut cfg = RunnerConfig::from_config(resolved, bindings)?.with_port(run.port);
+    let config_path = run.config.clone();
+    let allow_dev = run.allow_dev;
+    let binding_files = run.bindings.clone();
+    let binding_dirs = run.bindings_dir.clone();
+    let mut cfg = RunnerConfig::from_config(resolved, bindings)?
+        .with_port(run.port)
+        .with_listeners(run.listen.clone())
+        .with_reload_source(Arc::new(move || {
+            let (resolver, _) = build_resolver(config_path.as_deref(), allow_dev)?;
+            let bindings =
+                greentic_runner_host::gtbind::collect_gtbind_paths(&binding_files, &binding_dirs)?;
+            let mut next = RunnerConfig::from_config(resolver.load()?, bindings)?;
+            next.trace = next
+                .trace
+                .with_export(trace_section(config_path.as_deref())?);
+            Ok(next)
+        }));
     cfg.trace = trace_config;
     cfg.validation = validation_config;
+    cfg.strict_preflight |= run.strict_preflight;
     run_host(cfg).await
 }
 
@@ -337,15 +383,12 @@ fn build_resolver(
         resolver = resolver.allow_dev(true);
     }
     let layer = if let Some(path) = config_path {
-        let format = match path.extension().and_then(|ext| ext.to_str()) {
-            Some("json") => ConfigFileFormat::Json,
-            _ => ConfigFileFormat::Toml,
-        };
-        let contents = std::fs::read_to_string(path)?;
-        let layer = match format {
-            ConfigFileFormat::Toml => toml::from_str(&contents)?,
-            ConfigFileFormat::Json => serde_json::from_str(&contents)?,
-        };
+        let mut contents = read_config_file(path)?;
+        // `[trace]` belongs to the runner, not to greentic-config.
+        if let Some(table) = contents.as_object_mut() {
+            table.remove("trace");
+        }
+        let layer: ConfigLayer = serde_json::from_value(contents)?;
         if let Some(parent) = path.parent() {
             resolver = resolver.with_project_root_opt(Some(parent.to_path_buf()));
         }
@@ -357,6 +400,27 @@ fn build_resolver(
     Ok((resolver, layer))
 }
 
+/// The config file at `path` (toml, or json by extension) as JSON.
+fn read_config_file(path: &Path) -> anyhow::Result<Value> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&contents)?,
+        _ => toml::from_str(&contents)?,
+    })
+}
+
+/// Span export settings from the `[trace]` section of the config file, if any.
+fn trace_section(config_path: Option<&Path>) -> anyhow::Result<TraceExportConfig> {
+    let Some(path) = config_path else {
+        return Ok(TraceExportConfig::default());
+    };
+    match read_config_file(path)?.get("trace") {
+        Some(section) => serde_json::from_value(section.clone())
+            .with_context(|| format!("invalid [trace] section in {}", path.display())),
+        None => Ok(TraceExportConfig::default()),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PackLockV1 {
     schema_version: u32,
@@ -426,15 +490,16 @@ impl PackLockComponent {
 async fn run_cache(cmd: CacheCommand) -> Result<()> {
     match cmd {
         CacheCommand::Warmup(args) => warmup_cache(args).await,
-        CacheCommand::Doctor => doctor_cache().await,
+        CacheCommand::Doctor(args) => doctor_cache(args).await,
         CacheCommand::Prune(args) => prune_cache(args).await,
     }
 }
 
 async fn warmup_cache(args: CacheWarmupArgs) -> Result<()> {
     let (root, lock) = read_pack_lock(&args.pack).await?;
-    let engine = wasmtime::Engine::default();
-    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+    let settings = WasmEngineConfig::default();
+    let engine = wasm_engine::new_engine(&settings)?;
+    let profile = wasm_engine::engine_profile(&engine, &settings);
     let config = CacheConfig {
         memory_enabled: matches!(args.mode, CacheWarmupMode::Memory),
         ..CacheConfig::default()
@@ -464,9 +529,10 @@ async fn warmup_cache(args: CacheWarmupArgs) -> Result<()> {
     Ok(())
 }
 
-async fn doctor_cache() -> Result<()> {
-    let engine = wasmtime::Engine::default();
-    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
+async fn doctor_cache(args: CacheDoctorArgs) -> Result<()> {
+    let settings = WasmEngineConfig::default();
+    let engine = wasm_engine::new_engine(&settings)?;
+    let profile = wasm_engine::engine_profile(&engine, &settings);
     let cache = CacheManager::new(CacheConfig::default(), profile);
     let metrics = cache.metrics();
     let memory = cache.memory_stats();
@@ -481,23 +547,50 @@ async fn doctor_cache() -> Result<()> {
         "disk: artifacts={} bytes={} reads={} hits={}",
         disk.artifact_count, disk.artifact_bytes, metrics.disk_reads, metrics.disk_hits
     );
+    let report = cache.doctor(args.repair)?;
+    println!(
+        "entries: checked={} valid={} orphaned_artifacts={} orphaned_metadata={} invalid_metadata={} profile_mismatches={} size_mismatches={}",
+        report.entries_checked,
+        report.valid_entries,
+        report.orphaned_artifacts,
+        report.orphaned_metadata,
+        report.invalid_metadata,
+        report.profile_mismatches,
+        report.size_mismatches
+    );
+    if args.repair {
+        println!("repair: removed {} entries", report.repaired_entries);
+    } else if report.broken_entries() > 0 {
+        println!("run with --repair to remove broken entries");
+    }
     Ok(())
 }
 
 async fn prune_cache(args: CachePruneArgs) -> Result<()> {
-    let engine = wasmtime::Engine::default();
-    let profile = EngineProfile::from_engine(&engine, CpuPolicy::Native, "default".to_string());
-    let cache = CacheManager::new(CacheConfig::default(), profile);
+    let settings = WasmEngineConfig::default();
+    let engine = wasm_engine::new_engine(&settings)?;
+    let profile = wasm_engine::engine_profile(&engine, &settings);
+    let mut config = CacheConfig::default();
+    if let Some(secs) = args.max_age_secs {
+        config.disk_max_age = Some(Duration::from_secs(secs));
+    }
+    let cache = CacheManager::new(config, profile);
     let report = cache.prune_disk(args.dry_run).await?;
     if args.dry_run {
         println!(
-            "prune dry-run: would remove {} entries ({} bytes)",
-            report.removed_entries, report.removed_bytes
+            "prune dry-run: would remove {} entries ({} bytes; {} by age, {} by size)",
+            report.removed_entries,
+            report.removed_bytes,
+            report.removed_by_age,
+            report.removed_by_size
         );
     } else {
         println!(
-            "prune: removed {} entries ({} bytes)",
-            report.removed_entries, report.removed_bytes
+            "prune: removed {} entries ({} bytes; {} by age, {} by size)",
+            report.removed_entries,
+            report.removed_bytes,
+            report.removed_by_age,
+            report.removed_by_size
         );
     }
     Ok(())
@@ -695,6 +788,8 @@ async fn load_pack_runtime_for_contract(path: &Path) -> Result<PackRuntime> {
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
         state_store_policy: StateStorePolicy::default(),
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -704,6 +799,13 @@ async fn load_pack_runtime_for_contract(path: &Path) -> Result<PackRuntime> {
         trace: TraceConfig::from_env(),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: std::collections::BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     });
     PackRuntime::load(
         path,
@@ -13,15 +13,16 @@ use std::sync::{
 use anyhow::{Context, Result};
 use greentic_flow::flow_bundle::load_and_validate_bundle_with_flow;
 use greentic_runner_host::config::{
-    FlowRetryConfig, HostConfig, OperatorPolicy, RateLimits, SecretsPolicy, StateStorePolicy,
-    WebhookPolicy,
+    ComponentLoading, FlowRetryConfig, HostConfig, LifecycleConfig, OperatorPolicy, RateLimits,
+    RedactionPolicy, SecretsPolicy, SessionPolicy, StateStorePolicy, TenantWasiConfig,
+    WasmEngineConfig, WasmLimits, WebhookPolicy,
 };
 use greentic_runner_host::pack::{ComponentResolution, PackRuntime};
 use greentic_runner_host::runner::engine::{
     ExecutionObserver, FlowContext, FlowEngine, FlowStatus, NodeEvent, RetryConfig,
 };
 use greentic_runner_host::secrets::default_manager;
-use greentic_runner_host::storage::DynStateStore;
+use greentic_runner_host::storage::{DynStateStore, state_store_from};
 use greentic_runner_host::trace::{TraceConfig, TraceMode};
 use greentic_runner_host::validate::ValidationConfig;
 use greentic_runner_host::wasi::RunnerWasiPolicy;
@@ -83,7 +84,12 @@ fn host_config(tenant: &str, retry: FlowRetryConfig) -> HostConfig {
         retry,
         http_enabled: false,
         secrets_policy: SecretsPolicy::allow_all(),
-        state_store_policy: StateStorePolicy { allow: true },
+        state_store_policy: StateStorePolicy {
+            allow: true,
+            ..StateStorePolicy::default()
+        },
+        session_policy: SessionPolicy::default(),
+        redaction: RedactionPolicy::default(),
         webhook_policy: WebhookPolicy::default(),
         timers: Vec::new(),
         oauth: None,
@@ -93,6 +99,13 @@ fn host_config(tenant: &str, retry: FlowRetryConfig) -> HostConfig {
         trace: TraceConfig::from_env().with_overrides(TraceMode::Off, None),
         validation: ValidationConfig::from_env(),
         operator_policy: OperatorPolicy::allow_all(),
+        wasm_limits: WasmLimits::default(),
+        wasm_engine: WasmEngineConfig::default(),
+        wasi: TenantWasiConfig::default(),
+        attributes: BTreeMap::new(),
+        lifecycle: LifecycleConfig::default(),
+        component_loading: ComponentLoading::default(),
+        compile_concurrency: None,
     }
 }
 
@@ -581,6 +594,7 @@ nodes:
     let retry = FlowRetryConfig {
         max_attempts: 2,
         base_delay_ms: 1,
+        ..FlowRetryConfig::default()
     };
     let config = Arc::new(host_config("snapshot", retry));
     let flaky = Arc::new(FlakyStateStore::new());
@@ -589,7 +603,7 @@ nodes:
         "snapshot.flow",
         config,
         Some(&observer),
-        Some(flaky.clone()),
+        Some(state_store_from(flaky.clone())),
     )?;
 
     let state_value = flaky
@@ -5,6 +5,9 @@ use std::str::FromStr;
 use anyhow::{Context, Result, anyhow, bail};
 use url::Url;
 
+/// Pack fetches [`PackConfig::max_concurrent_fetches`] allows unless set otherwise.
+pub const DEFAULT_MAX_CONCURRENT_FETCHES: usize = 4;
+
 /// Environment-driven configuration for pack management.
 #[derive(Debug, Clone)]
 pub struct PackConfig {
@@ -13,6 +16,8 @@ pub struct PackConfig {
     pub cache_dir: PathBuf,
     pub public_key: Option<String>,
     pub network: Option<greentic_config_types::NetworkConfig>,
+    /// Distinct pack artifacts fetched at once while resolving an index.
+    pub max_concurrent_fetches: usize,
 }
 
 impl PackConfig {
@@ -33,6 +38,7 @@ impl PackConfig {
             cache_dir,
             public_key: None,
             network: None,
+            max_concurrent_fetches: DEFAULT_MAX_CONCURRENT_FETCHES,
         })
     }
 
@@ -59,6 +65,7 @@ impl PackConfig {
             cache_dir: cfg.cache_dir.clone(),
             public_key,
             network: None,
+            max_concurrent_fetches: DEFAULT_MAX_CONCURRENT_FETCHES,
         })
     }
 }
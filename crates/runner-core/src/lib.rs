@@ -12,6 +12,7 @@ pub mod path_safety;
 
 pub use env::{IndexLocation, PackConfig, PackSource};
 pub use packs::{
-    Index, PackDigest, PackManager, PackRef, PackVersion, ResolvedPack, ResolvedSet, TenantPacks,
+    Index, PackDigest, PackManager, PackRef, PackVersion, RefreshReport, ResolvedPack, ResolvedSet,
+    TenantPacks,
 };
 pub use path_safety::normalize_under_root;
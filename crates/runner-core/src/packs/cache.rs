@@ -19,6 +19,55 @@ impl PackCache {
         &self.root
     }
 
+    /// Directory resolvers download into, on the same filesystem as the cached packs.
+    pub fn download_dir(&self) -> PathBuf {
+        self.root.join(".downloads")
+    }
+
+    /// Where the OCI resolver keeps pulled manifests.
+    pub fn oci_dir(&self) -> PathBuf {
+        self.root.join(".oci")
+    }
+
+    /// The cached artifact for `entry` and the ETag it was served with, if present.
+    pub fn lookup(&self, entry: &PackEntry) -> Option<(PathBuf, Option<String>)> {
+        let path = self.dir_for(&entry.reference).join("pack.gtpack");
+        if !path.is_file() {
+            return None;
+        }
+        let etag = fs::read_to_string(etag_path(&path))
+            .ok()
+            .map(|etag| etag.trim().to_string())
+            .filter(|etag| !etag.is_empty());
+        Some((path, etag))
+    }
+
+    /// Remember the ETag of the artifact cached at `cached`, or forget a stale one.
+    pub fn record_etag(&self, cached: &Path, etag: Option<&str>) -> Result<()> {
+        let path = etag_path(cached);
+        match etag {
+            Some(etag) => fs::write(&path, etag)
+                .with_context(|| format!("failed to write {}", path.display())),
+            None => match fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(err).with_context(|| format!("failed to remove {}", path.display()))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// Delete everything cached for `reference`.
+    pub fn remove(&self, reference: &PackRef) -> Result<()> {
+        let dir = self.dir_for(reference);
+        match fs::remove_dir_all(&dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("failed to remove cached pack {}", dir.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn store(&self, entry: &PackEntry, source: &Path, digest: &PackDigest) -> Result<PathBuf> {
         let dest_dir = self.dir_for(&entry.reference);
         fs::create_dir_all(&dest_dir)
@@ -54,6 +103,10 @@ impl PackCache {
     }
 }
 
+fn etag_path(cached: &Path) -> PathBuf {
+    cached.with_extension("gtpack.etag")
+}
+
 fn copy_atomic(source: &Path, dest: &Path) -> Result<()> {
     let tmp = dest.with_extension(format!(
         "tmp-{}",
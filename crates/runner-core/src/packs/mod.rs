@@ -1,8 +1,11 @@
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow, bail};
 use greentic_pack::builder::PackManifest;
@@ -13,11 +16,13 @@ use crate::env::PackConfig;
 
 pub use cache::PackCache;
 pub use index::{Index, PackEntry, TenantRecord};
-pub use resolver::{FetchResponse, FsResolver, ResolverRegistry};
+pub use refresh::{DEFAULT_RETIRE_GRACE, RefreshLoop, RefreshReport};
+pub use resolver::{FetchRequest, FetchResponse, FsResolver, PackResolver, ResolverRegistry};
 pub use verify::PackVerifier;
 
 mod cache;
 mod index;
+mod refresh;
 pub mod resolver;
 mod verify;
 
@@ -161,6 +166,8 @@ pub struct PackManager {
     cache: PackCache,
     registry: ResolverRegistry,
     verifier: Option<PackVerifier>,
+    refresh: Mutex<refresh::RefreshState>,
+    retire_grace: Duration,
 }
 
 impl PackManager {
@@ -175,65 +182,175 @@ impl PackManager {
             .context("failed to resolve current directory")?
             .canonicalize()
             .context("failed to canonicalize current directory")?;
-        registry.register_builtin(fs_root, cfg.network.as_ref())?;
+        let cache = PackCache::new(cfg.cache_dir.clone());
+        registry.register_builtin(fs_root, &cache, cfg.network.as_ref())?;
         Ok(Self {
-            cache: PackCache::new(cfg.cache_dir.clone()),
+            cache,
             cfg,
             registry,
             verifier,
+            refresh: Mutex::default(),
+            retire_grace: DEFAULT_RETIRE_GRACE,
         })
     }
 
+    /// Register `resolver` for its scheme, replacing the built-in one if any.
+    pub fn with_resolver(mut self, resolver: impl PackResolver + 'static) -> Self {
+        self.registry.register(resolver);
+        self
+    }
+
     /// Resolve all packs referenced in the provided index.
     pub fn resolve_all_for_index(&self, index: &Index) -> Result<ResolvedSet> {
+        let resolved = self.resolve_index(index, |_| None)?;
+        self.refresh_state().current = Some(resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Resolve every entry of `index` that `reuse` has no pack for.
+    ///
+    /// Entries sharing a digest (or, unpinned, a locator) are fetched once; up to
+    /// `max_concurrent_fetches` fetches run at a time. Every failing entry is
+    /// reported, not just the first.
+    fn resolve_index<R>(&self, index: &Index, reuse: R) -> Result<ResolvedSet>
+    where
+        R: Fn(&PackEntry) -> Option<ResolvedPack>,
+    {
+        let entries: Vec<(&str, &PackEntry)> = index
+            .tenants()
+            .iter()
+            .flat_map(|(tenant, record)| {
+                std::iter::once(&record.main_pack)
+                    .chain(&record.overlays)
+                    .map(move |entry| (tenant.as_str(), entry))
+            })
+            .collect();
+        let mut results: Vec<Option<Result<ResolvedPack>>> = entries
+            .iter()
+            .map(|&(_, entry)| reuse(entry).map(Ok))
+            .collect();
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut by_key: HashMap<String, usize> = HashMap::new();
+        for (slot, (_, entry)) in entries.iter().enumerate() {
+            if results[slot].is_some() {
+                continue;
+            }
+            match self.fetch_key(entry) {
+                Some(key) => match by_key.get(&key) {
+                    Some(&group) => groups[group].push(slot),
+                    None => {
+                        by_key.insert(key, groups.len());
+                        groups.push(vec![slot]);
+                    }
+                },
+                None => groups.push(vec![slot]),
+            }
+        }
+
+        let outcomes = run_bounded(groups, self.cfg.max_concurrent_fetches, |group| {
+            let members: Vec<&PackEntry> = group.iter().map(|&slot| entries[slot].1).collect();
+            group
+                .into_iter()
+                .zip(self.resolve_group(&members))
+                .collect::<Vec<_>>()
+        });
+        for (slot, outcome) in outcomes.into_iter().flatten() {
+            results[slot] = Some(outcome);
+        }
+
+        let mut failures = Vec::new();
+        let mut packs = Vec::with_capacity(entries.len());
+        for ((tenant, entry), result) in entries.iter().zip(results) {
+            match result.expect("every index entry is resolved or reused") {
+                Ok(pack) => packs.push(pack),
+                Err(err) => {
+                    failures.push(format!("  - {tenant}/{}: {err:#}", entry.reference.name))
+                }
+            }
+        }
+        if !failures.is_empty() {
+            bail!(
+                "failed to resolve {} pack(s):\n{}",
+                failures.len(),
+                failures.join("\n")
+            );
+        }
+
+        let mut packs = packs.into_iter();
         let mut tenants = BTreeMap::new();
         for (tenant, record) in index.tenants() {
-            let main = self.resolve_entry(&record.main_pack)?;
-            let mut overlays = Vec::new();
-            for overlay in &record.overlays {
-                overlays.push(self.resolve_entry(overlay)?);
-            }
+            let main = packs.next().expect("main pack resolved");
+            let overlays = packs.by_ref().take(record.overlays.len()).collect();
             tenants.insert(tenant.clone(), TenantPacks { main, overlays });
         }
         Ok(ResolvedSet { tenants })
     }
 
-    fn resolve_entry(&self, entry: &PackEntry) -> Result<ResolvedPack> {
+    /// Entries with equal keys resolve to the same artifact.
+    fn fetch_key(&self, entry: &PackEntry) -> Option<String> {
+        match expected_digest(entry) {
+            Some(digest) => Some(digest.as_str().to_ascii_lowercase()),
+            None => entry.locator.with_fallback(self.cfg.source).ok(),
+        }
+    }
+
+    /// Fetch the artifact `entries` share once and resolve each of them from it.
+    fn resolve_group(&self, entries: &[&PackEntry]) -> Vec<Result<ResolvedPack>> {
+        match self.fetch_entry(entries[0]) {
+            Ok((response, digest)) => entries
+                .iter()
+                .map(|entry| self.finish_entry(entry, &response, &digest))
+                .collect(),
+            Err(err) => {
+                let message = format!("{err:#}");
+                entries.iter().map(|_| Err(anyhow!("{message}"))).collect()
+            }
+        }
+    }
+
+    fn fetch_entry(&self, entry: &PackEntry) -> Result<(FetchResponse, PackDigest)> {
         let locator = entry
             .locator
             .with_fallback(self.cfg.source)
             .context("pack locator missing scheme")?;
+        let expected_digest = expected_digest(entry);
+        let cached = self.cache.lookup(entry);
+        let mut request = FetchRequest::new(&locator).with_expected_digest(expected_digest);
+        if let Some((path, etag)) = &cached {
+            request = request.with_cached(path, etag.as_deref());
+        }
         let response = self
             .registry
-            .fetch(&locator)
+            .fetch_entry(&request)
             .with_context(|| format!("resolver failed for {}", locator))?;
 
         let fetched_digest = compute_digest(response.path())?;
-        if entry
-            .content_digest
-            .as_ref()
-            .or_else(|| entry.reference.version.as_digest())
-            .map(|expected| {
-                expected
-                    .as_str()
-                    .eq_ignore_ascii_case(fetched_digest.as_str())
-            })
-            == Some(false)
+        if let Some(expected) = expected_digest
+            && !expected
+                .as_str()
+                .eq_ignore_ascii_case(fetched_digest.as_str())
         {
-            let expected = entry
-                .content_digest
-                .as_ref()
-                .or_else(|| entry.reference.version.as_digest())
-                .map(|value| value.as_str())
-                .unwrap_or("<unknown>");
             bail!(
                 "digest mismatch for {}: expected {}, found {}",
                 entry.reference.name,
-                expected,
+                expected.as_str(),
                 fetched_digest.as_str()
             );
         }
+        Ok((response, fetched_digest))
+    }
 
+    fn finish_entry(
+        &self,
+        entry: &PackEntry,
+        response: &FetchResponse,
+        fetched_digest: &PackDigest,
+    ) -> Result<ResolvedPack> {
+        let locator = entry
+            .locator
+            .with_fallback(self.cfg.source)
+            .context("pack locator missing scheme")?;
         if let Some(verifier) = &self.verifier {
             let signature = entry.signature.as_deref().ok_or_else(|| {
                 anyhow!(
@@ -244,7 +361,8 @@ impl PackManager {
             verifier.verify(fetched_digest.as_str().as_bytes(), signature)?;
         }
 
-        let cached = self.cache.store(entry, response.path(), &fetched_digest)?;
+        let cached = self.cache.store(entry, response.path(), fetched_digest)?;
+        self.cache.record_etag(&cached, response.etag())?;
         let PackLoad {
             manifest, report, ..
         } = open_pack(&cached, SigningPolicy::DevOk).map_err(|err| {
@@ -260,12 +378,53 @@ impl PackManager {
             locator,
             path: cached,
             manifest,
-            digest: fetched_digest,
+            digest: fetched_digest.clone(),
             report,
         })
     }
 }
 
+fn expected_digest(entry: &PackEntry) -> Option<&PackDigest> {
+    entry
+        .content_digest
+        .as_ref()
+        .or_else(|| entry.reference.version.as_digest())
+}
+
+/// Run `job` over `jobs` on at most `limit` threads, returning outputs in job order.
+fn run_bounded<T, O, F>(jobs: Vec<T>, limit: usize, job: F) -> Vec<O>
+where
+    T: Send,
+    O: Send,
+    F: Fn(T) -> O + Sync,
+{
+    let workers = limit.max(1).min(jobs.len());
+    if workers <= 1 {
+        return jobs.into_iter().map(job).collect();
+    }
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let done = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some((position, item)) = next else {
+                        break;
+                    };
+                    let output = job(item);
+                    done.lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((position, output));
+                }
+            });
+        }
+    });
+    let mut done = done.into_inner().unwrap_or_else(PoisonError::into_inner);
+    done.sort_by_key(|(position, _)| *position);
+    done.into_iter().map(|(_, output)| output).collect()
+}
+
 fn compute_digest(path: &Path) -> Result<PackDigest> {
     use sha2::{Digest, Sha256};
     const BUF_SIZE: usize = 64 * 1024;
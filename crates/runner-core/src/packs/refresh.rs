@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+
+use crate::env::IndexLocation;
+
+use super::{Index, PackEntry, PackManager, PackRef, ResolvedPack, ResolvedSet};
+
+/// How long a pack dropped from the index stays cached, so runtimes still holding its
+/// path can finish with it.
+pub const DEFAULT_RETIRE_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// What changed between the previously resolved set and the index just read.
+#[derive(Debug, Clone)]
+pub struct RefreshReport {
+    pub added: Vec<PackRef>,
+    /// Packs whose version or content changed; these are the new references.
+    pub updated: Vec<PackRef>,
+    pub removed: Vec<PackRef>,
+    pub resolved: ResolvedSet,
+}
+
+impl RefreshReport {
+    pub fn has_changes(&self) -> bool {
+        !(self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty())
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RefreshState {
+    pub(super) current: Option<ResolvedSet>,
+    index_stamp: Option<IndexStamp>,
+    retired: Vec<Retired>,
+}
+
+struct Retired {
+    reference: PackRef,
+    since: Instant,
+}
+
+/// Modification time and size of a file index; remote indexes have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndexStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl IndexStamp {
+    fn read(location: &IndexLocation) -> Option<Self> {
+        let IndexLocation::File(path) = location else {
+            return None;
+        };
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Background poller started by [`PackManager::spawn_refresh_loop`]; dropping it stops
+/// the loop after the refresh in flight, if any.
+pub struct RefreshLoop {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RefreshLoop {
+    /// Stop polling and wait for the loop to exit.
+    pub fn stop(mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for RefreshLoop {
+    fn drop(&mut self) {
+        self.stop.take();
+    }
+}
+
+impl PackManager {
+    /// Keep packs dropped from the index cached for `grace` before pruning them.
+    pub fn with_retire_grace(mut self, grace: Duration) -> Self {
+        self.retire_grace = grace;
+        self
+    }
+
+    /// Re-read the configured index and resolve it against the current set.
+    ///
+    /// Entries pinned by digest that are unchanged since the last resolution are
+    /// reused without fetching. Packs the index no longer references are pruned from
+    /// the cache once the retire grace has passed.
+    pub fn refresh(&self) -> Result<RefreshReport> {
+        let stamp = IndexStamp::read(&self.cfg.index_location);
+        let index = Index::load(&self.cfg.index_location).with_context(|| {
+            format!(
+                "failed to reload index {}",
+                self.cfg.index_location.display()
+            )
+        })?;
+        let previous = self.refresh_state().current.clone();
+        let resolved = self.resolve_reusing(&index, previous.as_ref())?;
+
+        let previous_packs = packs_by_name(previous.as_ref());
+        let next_packs = packs_by_name(Some(&resolved));
+        let mut report = RefreshReport {
+            added: Vec::new(),
+            updated: Vec::new(),
+            removed: Vec::new(),
+            resolved: resolved.clone(),
+        };
+        let mut retiring = Vec::new();
+        for (name, next) in &next_packs {
+            match previous_packs.get(name) {
+                None => report.added.push(next.reference.clone()),
+                Some(prev) if prev.reference != next.reference => {
+                    report.updated.push(next.reference.clone());
+                    retiring.push(prev.reference.clone());
+                }
+                Some(prev) if prev.digest != next.digest => {
+                    report.updated.push(next.reference.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, prev) in &previous_packs {
+            if !next_packs.contains_key(name) {
+                report.removed.push(prev.reference.clone());
+                retiring.push(prev.reference.clone());
+            }
+        }
+
+        let mut state = self.refresh_state();
+        let now = Instant::now();
+        state
+            .retired
+            .extend(retiring.into_iter().map(|reference| Retired {
+                reference,
+                since: now,
+            }));
+        state.current = Some(resolved);
+        state.index_stamp = stamp;
+        self.prune_retired(&mut state, now);
+        Ok(report)
+    }
+
+    /// Poll the index every `interval` and refresh when it may have changed: file
+    /// indexes when their mtime or size moves, remote ones on every tick.
+    /// `on_refresh` sees failed refreshes and those that changed something.
+    pub fn spawn_refresh_loop<F>(
+        self: &Arc<Self>,
+        interval: Duration,
+        mut on_refresh: F,
+    ) -> RefreshLoop
+    where
+        F: FnMut(Result<RefreshReport>) + Send + 'static,
+    {
+        let manager = Arc::clone(self);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if !manager.index_may_have_changed() {
+                    continue;
+                }
+                match manager.refresh() {
+                    Ok(report) if !report.has_changes() => {}
+                    outcome => on_refresh(outcome),
+                }
+            }
+        });
+        RefreshLoop {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    pub(super) fn refresh_state(&self) -> MutexGuard<'_, RefreshState> {
+        self.refresh
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn index_may_have_changed(&self) -> bool {
+        let stamp = IndexStamp::read(&self.cfg.index_location);
+        stamp.is_none() || stamp != self.refresh_state().index_stamp
+    }
+
+    fn resolve_reusing(
+        &self,
+        index: &Index,
+        previous: Option<&ResolvedSet>,
+    ) -> Result<ResolvedSet> {
+        let previous: HashMap<&PackRef, &ResolvedPack> = all_packs(previous)
+            .map(|pack| (&pack.reference, pack))
+            .collect();
+        self.resolve_index(index, |entry| {
+            previous
+                .get(&entry.reference)
+                .filter(|pack| self.still_pinned(entry, pack))
+                .map(|pack| (*pack).clone())
+        })
+    }
+
+    /// Whether `entry` pins the exact content `pack` was resolved to, from the same
+    /// locator, and that content is still cached.
+    fn still_pinned(&self, entry: &PackEntry, pack: &ResolvedPack) -> bool {
+        let Some(expected) = entry
+            .content_digest
+            .as_ref()
+            .or_else(|| entry.reference.version.as_digest())
+        else {
+            return false;
+        };
+        let same_locator = entry
+            .locator
+            .with_fallback(self.cfg.source)
+            .is_ok_and(|locator| locator == pack.locator);
+        same_locator
+            && expected.as_str().eq_ignore_ascii_case(pack.digest.as_str())
+            && pack.path.is_file()
+    }
+
+    /// Delete retired packs whose grace has passed, unless the index took them back.
+    fn prune_retired(&self, state: &mut RefreshState, now: Instant) {
+        let referenced: HashSet<PackRef> = all_packs(state.current.as_ref())
+            .map(|pack| pack.reference.clone())
+            .collect();
+        let grace = self.retire_grace;
+        let cache = &self.cache;
+        state.retired.retain(|retired| {
+            if referenced.contains(&retired.reference) {
+                return false;
+            }
+            if now.duration_since(retired.since) < grace {
+                return true;
+            }
+            // Keep failed removals around to retry on the next refresh.
+            cache.remove(&retired.reference).is_err()
+        });
+    }
+}
+
+fn all_packs(set: Option<&ResolvedSet>) -> impl Iterator<Item = &ResolvedPack> {
+    set.into_iter()
+        .flat_map(|set| set.tenants.values())
+        .flat_map(|packs| std::iter::once(&packs.main).chain(&packs.overlays))
+}
+
+fn packs_by_name(set: Option<&ResolvedSet>) -> BTreeMap<&str, &ResolvedPack> {
+    all_packs(set)
+        .map(|pack| (pack.reference.name.as_str(), pack))
+        .collect()
+}
@@ -1,40 +1,148 @@
-use std::io::copy;
+use std::fs;
+use std::io::{self, Write, copy};
+use std::path::PathBuf;
+use std::thread;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use greentic_config_types::{NetworkConfig, TlsMode};
+use reqwest::StatusCode;
 use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use sha2::{Digest, Sha256};
 use std::time::Duration;
 use tempfile::NamedTempFile;
 
-use super::{FetchResponse, PackResolver};
+use super::{FetchRequest, FetchResponse, PackResolver};
+
+/// How [`HttpResolver`] retries transient failures: 5xx and 429 answers, connection
+/// errors, timeouts and bodies cut off mid-stream. Retry `n` waits
+/// `initial_backoff * 2^(n-1)`, capped at `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first included.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff)
+    }
+}
 
 pub struct HttpResolver {
     scheme: &'static str,
     client: Client,
+    retry: RetryPolicy,
+    temp_dir: Option<PathBuf>,
 }
 
 impl HttpResolver {
     pub fn new(scheme: &'static str, network: Option<&NetworkConfig>) -> Result<Self> {
-        let mut builder = Client::builder();
-        if let Some(cfg) = network {
-            if let Some(proxy) = &cfg.proxy_url {
-                builder = builder.proxy(reqwest::Proxy::all(proxy)?);
-            }
-            if let Some(timeout) = cfg.connect_timeout_ms {
-                builder = builder.connect_timeout(Duration::from_millis(timeout));
-            }
-            if let Some(timeout) = cfg.read_timeout_ms {
-                builder = builder.timeout(Duration::from_millis(timeout));
-            }
-            if matches!(cfg.tls_mode, TlsMode::Disabled) {
-                bail!("TLS certificate validation cannot be disabled");
-            }
-        }
         Ok(Self {
             scheme,
-            client: builder.build()?,
+            client: build_client(network)?,
+            retry: RetryPolicy::default(),
+            temp_dir: None,
         })
     }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Download into `dir` (created on demand) instead of the system temp dir, so the
+    /// pack cache can move the file into place without crossing filesystems.
+    pub fn with_temp_dir(mut self, dir: PathBuf) -> Self {
+        self.temp_dir = Some(dir);
+        self
+    }
+
+    fn attempt(&self, request: &FetchRequest<'_>) -> Result<FetchResponse, Failure> {
+        let locator = request.locator;
+        let mut get = self.client.get(locator);
+        if request.cached_path.is_some()
+            && let Some(etag) = request.cached_etag
+        {
+            get = get.header(IF_NONE_MATCH, etag);
+        }
+        let mut response = get.send().map_err(|err| {
+            let fatal = err.is_builder() || err.is_redirect();
+            let err = anyhow!(err).context(format!("failed to download {locator}"));
+            if fatal {
+                Failure::Fatal(err)
+            } else {
+                Failure::Transient(err)
+            }
+        })?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            let cached = request.cached_path.ok_or_else(|| {
+                Failure::Fatal(anyhow!("{locator} answered 304 without a cached copy"))
+            })?;
+            return Ok(FetchResponse::from_path(cached.to_path_buf())
+                .with_etag(request.cached_etag.map(str::to_string)));
+        }
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Failure::Transient(anyhow!(
+                "download failed {locator}: HTTP {status}"
+            )));
+        }
+        if !status.is_success() {
+            return Err(Failure::Fatal(anyhow!(
+                "download failed {locator}: HTTP {status}"
+            )));
+        }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let mut temp = self.temp_file().map_err(Failure::Fatal)?;
+        let found = {
+            let mut writer = HashingWriter::new(temp.as_file_mut());
+            copy(&mut response, &mut writer).map_err(|err| {
+                Failure::Transient(anyhow!(err).context("failed to stream HTTP content"))
+            })?;
+            writer.finish()
+        };
+        if let Some(expected) = request
+            .expected_digest
+            .filter(|expected| expected.algorithm() == "sha256")
+            && !expected.value().eq_ignore_ascii_case(&found)
+        {
+            return Err(Failure::Fatal(anyhow!(
+                "digest mismatch for {locator}: expected {}, found sha256:{found}",
+                expected.as_str()
+            )));
+        }
+        Ok(FetchResponse::from_temp(temp.into_temp_path()).with_etag(etag))
+    }
+
+    fn temp_file(&self) -> Result<NamedTempFile> {
+        let Some(dir) = &self.temp_dir else {
+            return NamedTempFile::new().context("failed to allocate temp file for download");
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create download dir {}", dir.display()))?;
+        NamedTempFile::new_in(dir).context("failed to allocate temp file for download")
+    }
 }
 
 impl PackResolver for HttpResolver {
@@ -43,19 +151,81 @@ impl PackResolver for HttpResolver {
     }
 
     fn fetch(&self, locator: &str) -> Result<FetchResponse> {
-        let mut response = self
-            .client
-            .get(locator)
-            .send()
-            .with_context(|| format!("failed to download {}", locator))?
-            .error_for_status()
-            .with_context(|| format!("download failed {}", locator))?;
-
-        let mut temp = NamedTempFile::new().context("failed to allocate temp file for download")?;
-        {
-            let mut writer = temp.as_file_mut();
-            copy(&mut response, &mut writer).context("failed to stream HTTP content")?;
+        self.fetch_entry(&FetchRequest::new(locator))
+    }
+
+    fn fetch_entry(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        let mut attempt = 1;
+        loop {
+            match self.attempt(request) {
+                Ok(response) => return Ok(response),
+                Err(Failure::Transient(_)) if attempt < self.retry.max_attempts => {
+                    thread::sleep(self.retry.backoff(attempt));
+                    attempt += 1;
+                }
+                Err(Failure::Transient(err)) => {
+                    return Err(err.context(format!("gave up after {attempt} attempts")));
+                }
+                Err(Failure::Fatal(err)) => return Err(err),
+            }
         }
-        Ok(FetchResponse::from_temp(temp.into_temp_path()))
+    }
+}
+
+/// Blocking client honouring the proxy and timeouts in `network`.
+pub(super) fn build_client(network: Option<&NetworkConfig>) -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(cfg) = network {
+        if let Some(proxy) = &cfg.proxy_url {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(timeout) = cfg.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(timeout) = cfg.read_timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout));
+        }
+        if matches!(cfg.tls_mode, TlsMode::Disabled) {
+            bail!("TLS certificate validation cannot be disabled");
+        }
+    }
+    Ok(builder.build()?)
+}
+
+enum Failure {
+    /// Worth another attempt.
+    Transient(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+/// Hashes what it writes, so the digest is known once the body has been streamed.
+pub(super) struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W> HashingWriter<W> {
+    pub(super) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Hex sha256 of everything written.
+    pub(super) fn finish(self) -> String {
+        format!("{:0x}", self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
@@ -5,38 +5,55 @@ use std::sync::Arc;
 use anyhow::{Result, anyhow, bail};
 use tempfile::TempPath;
 
+use super::{PackCache, PackDigest};
+
 mod azblob;
 mod fs;
 mod gcs;
 mod http;
 mod oci;
+#[cfg(feature = "s3")]
 mod s3;
 
 pub use azblob::AzBlobResolver;
 pub use fs::FsResolver;
 pub use gcs::GcsResolver;
-pub use http::HttpResolver;
-pub use oci::OciResolver;
-pub use s3::S3Resolver;
+pub use http::{HttpResolver, RetryPolicy};
+pub use oci::{GTPACK_MEDIA_TYPE, OciReference, OciResolver, OciTarget};
+#[cfg(feature = "s3")]
+pub use s3::{S3Credentials, S3Error, S3Location, S3Resolver, S3Settings};
 
 /// Response from a resolver indicating where the artifact was stored.
 pub struct FetchResponse {
     location: FetchLocation,
+    etag: Option<String>,
 }
 
 impl FetchResponse {
     pub fn from_path(path: PathBuf) -> Self {
         Self {
             location: FetchLocation::Permanent(path),
+            etag: None,
         }
     }
 
     pub fn from_temp(path: TempPath) -> Self {
         Self {
             location: FetchLocation::Temporary(path),
+            etag: None,
         }
     }
 
+    pub fn with_etag(mut self, etag: Option<String>) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    /// Validator the origin attached to the artifact, if any.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
     pub fn path(&self) -> &Path {
         match &self.location {
             FetchLocation::Permanent(path) => path,
@@ -50,9 +67,48 @@ enum FetchLocation {
     Temporary(TempPath),
 }
 
+/// What the manager knows about an entry before fetching it.
+#[derive(Debug, Clone, Copy)]
+pub struct FetchRequest<'a> {
+    pub locator: &'a str,
+    /// Digest from the index the artifact must match.
+    pub expected_digest: Option<&'a PackDigest>,
+    /// Copy already in the pack cache, reusable when the origin reports it unchanged.
+    pub cached_path: Option<&'a Path>,
+    pub cached_etag: Option<&'a str>,
+}
+
+impl<'a> FetchRequest<'a> {
+    pub fn new(locator: &'a str) -> Self {
+        Self {
+            locator,
+            expected_digest: None,
+            cached_path: None,
+            cached_etag: None,
+        }
+    }
+
+    pub fn with_expected_digest(mut self, digest: Option<&'a PackDigest>) -> Self {
+        self.expected_digest = digest;
+        self
+    }
+
+    pub fn with_cached(mut self, path: &'a Path, etag: Option<&'a str>) -> Self {
+        self.cached_path = Some(path);
+        self.cached_etag = etag;
+        self
+    }
+}
+
 pub trait PackResolver: Send + Sync {
     fn scheme(&self) -> &'static str;
     fn fetch(&self, locator: &str) -> Result<FetchResponse>;
+
+    /// Fetch with the entry's digest and cached copy at hand; resolvers that cannot use
+    /// them fall back to [`PackResolver::fetch`].
+    fn fetch_entry(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        self.fetch(request.locator)
+    }
 }
 
 #[derive(Default)]
@@ -69,25 +125,36 @@ impl ResolverRegistry {
     pub fn register_builtin(
         &mut self,
         fs_root: PathBuf,
+        cache: &PackCache,
         network: Option<&greentic_config_types::NetworkConfig>,
     ) -> Result<()> {
+        let download_dir = cache.download_dir();
         self.register(FsResolver::new(fs_root));
-        self.register(HttpResolver::new("http", network)?);
-        self.register(HttpResolver::new("https", network)?);
-        self.register(OciResolver::new(network)?);
-        self.register(S3Resolver::new(network)?);
+        self.register(HttpResolver::new("http", network)?.with_temp_dir(download_dir.clone()));
+        self.register(HttpResolver::new("https", network)?.with_temp_dir(download_dir));
+        self.register(OciResolver::new(network)?.with_store(cache.oci_dir()));
+        #[cfg(feature = "s3")]
+        self.register(S3Resolver::new(network)?.with_download_dir(cache.download_dir()));
         self.register(GcsResolver::new(network)?);
         self.register(AzBlobResolver::new(network)?);
         Ok(())
     }
 
     pub fn fetch(&self, reference: &str) -> Result<FetchResponse> {
-        let parsed = ParsedReference::parse(reference)?;
+        self.fetch_entry(&FetchRequest::new(reference))
+    }
+
+    /// Like [`ResolverRegistry::fetch`], with `request.locator` as the full reference.
+    pub fn fetch_entry(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        let parsed = ParsedReference::parse(request.locator)?;
         let resolver = self
             .resolvers
             .get(&parsed.scheme)
             .ok_or_else(|| anyhow!("no resolver registered for scheme `{}`", parsed.scheme))?;
-        resolver.fetch(&parsed.locator)
+        resolver.fetch_entry(&FetchRequest {
+            locator: &parsed.locator,
+            ..*request
+        })
     }
 }
 
@@ -1,18 +1,299 @@
-use anyhow::Result;
+use std::env;
+use std::fs;
+use std::io::copy;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use greentic_config_types::NetworkConfig;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use url::Url;
+
+use super::http::{HashingWriter, build_client};
+use super::{FetchRequest, FetchResponse, PackResolver};
+use crate::packs::PackDigest;
 
-use super::{FetchResponse, HttpResolver, PackResolver};
+/// Media type of the manifest layer holding the `.gtpack`, unless `PACK_OCI_MEDIA_TYPE`
+/// names another.
+pub const GTPACK_MEDIA_TYPE: &str = "application/vnd.greentic.gtpack.v1+zip";
 
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.manifest.v1+json, \
+                               application/vnd.docker.distribution.manifest.v2+json";
+
+/// Pulls packs from OCI registries: `oci://registry/repo:tag` or
+/// `oci://registry/repo@sha256:...`. Registries on `localhost` are spoken to over plain
+/// HTTP, as are `oci+http://` references.
+///
+/// Pulls are anonymous unless `PACK_OCI_TOKEN` holds a bearer token or the docker
+/// config (`$DOCKER_CONFIG/config.json`, else `~/.docker/config.json`) has credentials
+/// for the registry; either way a registry asking for a token is sent to its realm.
 pub struct OciResolver {
-    inner: HttpResolver,
+    client: Client,
+    media_type: String,
+    token: Option<String>,
+    docker_config: Option<PathBuf>,
+    store: Option<PathBuf>,
 }
 
 impl OciResolver {
     pub fn new(network: Option<&NetworkConfig>) -> Result<Self> {
         Ok(Self {
-            inner: HttpResolver::new("oci", network)?,
+            client: build_client(network)?,
+            media_type: non_empty_var("PACK_OCI_MEDIA_TYPE")
+                .unwrap_or_else(|| GTPACK_MEDIA_TYPE.to_string()),
+            token: non_empty_var("PACK_OCI_TOKEN"),
+            docker_config: non_empty_var("DOCKER_CONFIG")
+                .map(PathBuf::from)
+                .or_else(|| non_empty_var("HOME").map(|home| Path::new(&home).join(".docker")))
+                .map(|dir| dir.join("config.json")),
+            store: None,
         })
     }
+
+    pub fn with_media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.media_type = media_type.into();
+        self
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_docker_config(mut self, path: Option<PathBuf>) -> Self {
+        self.docker_config = path;
+        self
+    }
+
+    /// Keep pulled manifests and in-flight downloads under `dir`. Without a store every
+    /// pull goes to the registry.
+    pub fn with_store(mut self, dir: PathBuf) -> Self {
+        self.store = Some(dir);
+        self
+    }
+
+    fn pull(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        let reference = OciReference::parse(request.locator)?;
+        if let Some(cached) = self.cached_pin(&reference, request) {
+            return Ok(FetchResponse::from_path(cached.to_path_buf()));
+        }
+
+        let mut session = Session {
+            auth: self.token.clone().map(Auth::Bearer),
+            negotiated: false,
+        };
+        let manifest_bytes = self.manifest(&reference, &mut session)?;
+        let manifest: Manifest = serde_json::from_slice(&manifest_bytes)
+            .with_context(|| format!("invalid OCI manifest for {reference}"))?;
+        let layer = select_layer(&manifest, &self.media_type)
+            .with_context(|| format!("no pack layer in {reference}"))?;
+        let response = self.download_blob(&reference, layer, &mut session)?;
+        if let OciTarget::Digest(digest) = &reference.target {
+            self.save_manifest(digest, &manifest_bytes);
+        }
+        Ok(response)
+    }
+
+    /// For a digest-pinned reference whose manifest was stored, the cached pack when it
+    /// still is the manifest's layer.
+    fn cached_pin<'a>(
+        &self,
+        reference: &OciReference,
+        request: &FetchRequest<'a>,
+    ) -> Option<&'a Path> {
+        let OciTarget::Digest(digest) = &reference.target else {
+            return None;
+        };
+        let cached = request.cached_path?;
+        let bytes = fs::read(self.manifest_path(digest)?).ok()?;
+        if !PackDigest::sha256_from_bytes(&bytes)
+            .value()
+            .eq_ignore_ascii_case(digest.value())
+        {
+            return None;
+        }
+        let manifest: Manifest = serde_json::from_slice(&bytes).ok()?;
+        let layer = select_layer(&manifest, &self.media_type).ok()?;
+        let layer_digest = PackDigest::parse(&layer.digest).ok()?;
+        layer_digest
+            .matches_file(cached)
+            .unwrap_or(false)
+            .then_some(cached)
+    }
+
+    fn manifest(&self, reference: &OciReference, session: &mut Session) -> Result<Vec<u8>> {
+        let url = format!(
+            "{}/manifests/{}",
+            reference.repository_url(),
+            reference.target
+        );
+        let response = self.get(&url, Some(MANIFEST_ACCEPT), reference, session)?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("failed to pull manifest {reference}: HTTP {status}");
+        }
+        let bytes = response
+            .bytes()
+            .with_context(|| format!("failed to read manifest {reference}"))?
+            .to_vec();
+        if let OciTarget::Digest(digest) = &reference.target {
+            let found = PackDigest::sha256_from_bytes(&bytes);
+            if !found.value().eq_ignore_ascii_case(digest.value()) {
+                bail!(
+                    "manifest digest mismatch for {reference}: found {}",
+                    found.as_str()
+                );
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn download_blob(
+        &self,
+        reference: &OciReference,
+        layer: &Descriptor,
+        session: &mut Session,
+    ) -> Result<FetchResponse> {
+        let expected = PackDigest::parse(&layer.digest)?;
+        if expected.algorithm() != "sha256" {
+            bail!("unsupported layer digest {} in {reference}", layer.digest);
+        }
+        let url = format!("{}/blobs/{}", reference.repository_url(), layer.digest);
+        let mut response = self.get(&url, None, reference, session)?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "failed to pull layer {} of {reference}: HTTP {status}",
+                layer.digest
+            );
+        }
+
+        let mut temp = self.temp_file()?;
+        let found = {
+            let mut writer = HashingWriter::new(temp.as_file_mut());
+            copy(&mut response, &mut writer)
+                .with_context(|| format!("failed to stream layer {}", layer.digest))?;
+            writer.finish()
+        };
+        if !expected.value().eq_ignore_ascii_case(&found) {
+            bail!(
+                "layer digest mismatch for {reference}: expected {}, found sha256:{found}",
+                layer.digest
+            );
+        }
+        Ok(FetchResponse::from_temp(temp.into_temp_path()))
+    }
+
+    /// GET `url`, trading a `401` bearer challenge for a token once per pull.
+    fn get(
+        &self,
+        url: &str,
+        accept: Option<&str>,
+        reference: &OciReference,
+        session: &mut Session,
+    ) -> Result<Response> {
+        let response = self.send(url, accept, session.auth.as_ref())?;
+        if response.status() != StatusCode::UNAUTHORIZED || session.negotiated {
+            return Ok(response);
+        }
+        session.negotiated = true;
+        let Some(challenge) = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(BearerChallenge::parse)
+        else {
+            return Ok(response);
+        };
+        let token = self.exchange_token(&challenge, reference)?;
+        session.auth = Some(Auth::Bearer(token));
+        self.send(url, accept, session.auth.as_ref())
+    }
+
+    fn send(&self, url: &str, accept: Option<&str>, auth: Option<&Auth>) -> Result<Response> {
+        let mut request = self.client.get(url);
+        if let Some(accept) = accept {
+            request = request.header(ACCEPT, accept);
+        }
+        if let Some(auth) = auth {
+            request = request.header(AUTHORIZATION, auth.header());
+        }
+        request
+            .send()
+            .with_context(|| format!("failed to reach {url}"))
+    }
+
+    fn exchange_token(
+        &self,
+        challenge: &BearerChallenge,
+        reference: &OciReference,
+    ) -> Result<String> {
+        let mut url = Url::parse(&challenge.realm)
+            .with_context(|| format!("invalid token realm `{}`", challenge.realm))?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(service) = &challenge.service {
+                query.append_pair("service", service);
+            }
+            let scope = challenge
+                .scope
+                .clone()
+                .unwrap_or_else(|| format!("repository:{}:pull", reference.repository));
+            query.append_pair("scope", &scope);
+        }
+        let basic = self.docker_credentials(&reference.registry);
+        let response = self.send(url.as_str(), None, basic.as_ref())?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "registry {} refused a pull token: HTTP {status}",
+                reference.registry
+            );
+        }
+        let body: TokenResponse = response.json().context("invalid registry token response")?;
+        body.token
+            .or(body.access_token)
+            .ok_or_else(|| anyhow!("registry {} returned no token", reference.registry))
+    }
+
+    fn docker_credentials(&self, registry: &str) -> Option<Auth> {
+        let raw = fs::read_to_string(self.docker_config.as_ref()?).ok()?;
+        docker_auth(&raw, registry)
+    }
+
+    fn manifest_path(&self, digest: &PackDigest) -> Option<PathBuf> {
+        Some(
+            self.store
+                .as_ref()?
+                .join("manifests")
+                .join(format!("{}.json", digest.cache_label())),
+        )
+    }
+
+    /// Best effort: a missing manifest only costs the next pull a round trip.
+    fn save_manifest(&self, digest: &PackDigest, bytes: &[u8]) {
+        if let Some(path) = self.manifest_path(digest)
+            && let Some(dir) = path.parent()
+            && fs::create_dir_all(dir).is_ok()
+        {
+            let _ = fs::write(path, bytes);
+        }
+    }
+
+    fn temp_file(&self) -> Result<NamedTempFile> {
+        let Some(store) = &self.store else {
+            return NamedTempFile::new().context("failed to allocate temp file for layer");
+        };
+        let dir = store.join("downloads");
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create download dir {}", dir.display()))?;
+        NamedTempFile::new_in(dir).context("failed to allocate temp file for layer")
+    }
 }
 
 impl PackResolver for OciResolver {
@@ -21,6 +302,365 @@ impl PackResolver for OciResolver {
     }
 
     fn fetch(&self, locator: &str) -> Result<FetchResponse> {
-        self.inner.fetch(locator)
+        self.pull(&FetchRequest::new(locator))
+    }
+
+    fn fetch_entry(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        self.pull(request)
+    }
+}
+
+/// `registry/repository` plus the tag or manifest digest to pull.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciReference {
+    pub registry: String,
+    pub repository: String,
+    pub target: OciTarget,
+    pub plain_http: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OciTarget {
+    Tag(String),
+    Digest(PackDigest),
+}
+
+impl std::fmt::Display for OciTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tag(tag) => f.write_str(tag),
+            Self::Digest(digest) => f.write_str(digest.as_str()),
+        }
+    }
+}
+
+impl std::fmt::Display for OciReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let separator = match self.target {
+            OciTarget::Tag(_) => ':',
+            OciTarget::Digest(_) => '@',
+        };
+        write!(
+            f,
+            "{}/{}{separator}{}",
+            self.registry, self.repository, self.target
+        )
+    }
+}
+
+impl OciReference {
+    /// Parse `oci://`, `http://` or `https://` locators (the latter two come from
+    /// `oci+http://` style references); a missing tag means `latest`.
+    pub fn parse(locator: &str) -> Result<Self> {
+        let (rest, scheme_http) = if let Some(rest) = locator.strip_prefix("oci://") {
+            (rest, None)
+        } else if let Some(rest) = locator.strip_prefix("http://") {
+            (rest, Some(true))
+        } else if let Some(rest) = locator.strip_prefix("https://") {
+            (rest, Some(false))
+        } else {
+            (locator, None)
+        };
+        let (registry, path) = rest
+            .split_once('/')
+            .filter(|(registry, path)| !registry.is_empty() && !path.is_empty())
+            .ok_or_else(|| anyhow!("OCI reference `{locator}` needs registry/repository"))?;
+
+        let (repository, target) = if let Some((repository, digest)) = path.split_once('@') {
+            let digest = PackDigest::parse(digest)
+                .with_context(|| format!("OCI reference `{locator}` has an invalid digest"))?;
+            if digest.algorithm() != "sha256" {
+                bail!("OCI reference `{locator}` must be pinned with a sha256 digest");
+            }
+            (repository, OciTarget::Digest(digest))
+        } else {
+            match path.rsplit_once(':') {
+                Some((repository, tag)) if !tag.is_empty() && !tag.contains('/') => {
+                    (repository, OciTarget::Tag(tag.to_string()))
+                }
+                _ => (path, OciTarget::Tag("latest".into())),
+            }
+        };
+        if repository.is_empty() {
+            bail!("OCI reference `{locator}` is missing a repository");
+        }
+
+        let host = registry.split(':').next().unwrap_or(registry);
+        Ok(Self {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            target,
+            plain_http: scheme_http.unwrap_or(matches!(host, "localhost" | "127.0.0.1")),
+        })
+    }
+
+    fn repository_url(&self) -> String {
+        let scheme = if self.plain_http { "http" } else { "https" };
+        format!("{scheme}://{}/v2/{}", self.registry, self.repository)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    /// Present on image indexes, which point at further manifests.
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Descriptor {
+    #[serde(rename = "mediaType")]
+    media_type: String,
+    digest: String,
+}
+
+/// The single layer of `manifest` with `media_type`.
+fn select_layer<'a>(manifest: &'a Manifest, media_type: &str) -> Result<&'a Descriptor> {
+    if manifest.layers.is_empty() && !manifest.manifests.is_empty() {
+        bail!("reference points at an image index; pin a single manifest instead");
+    }
+    let mut matching = manifest
+        .layers
+        .iter()
+        .filter(|layer| layer.media_type == media_type);
+    match (matching.next(), matching.next()) {
+        (Some(layer), None) => Ok(layer),
+        (Some(_), Some(_)) => bail!("several layers have media type {media_type}"),
+        (None, _) => {
+            let found: Vec<&str> = manifest
+                .layers
+                .iter()
+                .map(|layer| layer.media_type.as_str())
+                .collect();
+            bail!(
+                "no layer has media type {media_type} (found: {})",
+                found.join(", ")
+            )
+        }
+    }
+}
+
+enum Auth {
+    Bearer(String),
+    /// base64 of `user:password`.
+    Basic(String),
+}
+
+impl Auth {
+    fn header(&self) -> String {
+        match self {
+            Self::Bearer(token) => format!("Bearer {token}"),
+            Self::Basic(encoded) => format!("Basic {encoded}"),
+        }
+    }
+}
+
+struct Session {
+    auth: Option<Auth>,
+    negotiated: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BearerChallenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+impl BearerChallenge {
+    /// Parse `Bearer realm="...",service="...",scope="..."`.
+    fn parse(header: &str) -> Option<Self> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return None;
+        }
+        let mut realm = None;
+        let mut service = None;
+        let mut scope = None;
+        let mut rest = params.trim();
+        while !rest.is_empty() {
+            let (key, after) = rest.split_once('=')?;
+            let (value, remainder) = match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let (value, remainder) = quoted.split_once('"')?;
+                    (value, remainder)
+                }
+                None => after.split_once(',').unwrap_or((after, "")),
+            };
+            match key.trim() {
+                "realm" => realm = Some(value.to_string()),
+                "service" => service = Some(value.to_string()),
+                "scope" => scope = Some(value.to_string()),
+                _ => {}
+            }
+            rest = remainder.trim_start_matches([',', ' ']);
+        }
+        Some(Self {
+            realm: realm?,
+            service,
+            scope,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: std::collections::BTreeMap<String, DockerAuth>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+/// Credentials for `registry` in a docker `config.json`, keyed by host or by URL.
+fn docker_auth(raw: &str, registry: &str) -> Option<Auth> {
+    let config: DockerConfig = serde_json::from_str(raw).ok()?;
+    let entry = config.auths.iter().find_map(|(key, entry)| {
+        let host = key
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        (host == registry).then_some(entry)
+    })?;
+    if let Some(auth) = entry.auth.as_ref().filter(|auth| !auth.is_empty()) {
+        return Some(Auth::Basic(auth.clone()));
+    }
+    let (user, password) = (entry.username.as_ref()?, entry.password.as_ref()?);
+    Some(Auth::Basic(STANDARD.encode(format!("{user}:{password}"))))
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(media_type: &str, digest: &str) -> Descriptor {
+        Descriptor {
+            media_type: media_type.into(),
+            digest: digest.into(),
+        }
+    }
+
+    #[test]
+    fn parses_tagged_and_pinned_references() {
+        let tagged = OciReference::parse("oci://ghcr.io/greentic/packs/demo:1.2.0").unwrap();
+        assert_eq!(tagged.registry, "ghcr.io");
+        assert_eq!(tagged.repository, "greentic/packs/demo");
+        assert_eq!(tagged.target, OciTarget::Tag("1.2.0".into()));
+        assert!(!tagged.plain_http);
+
+        let pinned = OciReference::parse("oci://ghcr.io/greentic/demo@sha256:abc123").unwrap();
+        assert_eq!(pinned.repository, "greentic/demo");
+        assert_eq!(
+            pinned.target,
+            OciTarget::Digest(PackDigest::parse("sha256:abc123").unwrap())
+        );
+        assert_eq!(pinned.to_string(), "ghcr.io/greentic/demo@sha256:abc123");
+
+        let local = OciReference::parse("oci://localhost:5000/demo").unwrap();
+        assert_eq!(local.registry, "localhost:5000");
+        assert_eq!(local.target, OciTarget::Tag("latest".into()));
+        assert!(local.plain_http);
+
+        let forced = OciReference::parse("http://registry.internal:5000/demo:dev").unwrap();
+        assert!(forced.plain_http);
+        assert_eq!(forced.target, OciTarget::Tag("dev".into()));
+    }
+
+    #[test]
+    fn rejects_malformed_references() {
+        assert!(OciReference::parse("oci://ghcr.io").is_err());
+        assert!(OciReference::parse("oci:///demo:1").is_err());
+        assert!(OciReference::parse("oci://ghcr.io/demo@md5:abc").is_err());
+        assert!(OciReference::parse("oci://ghcr.io/@sha256:abc").is_err());
+    }
+
+    #[test]
+    fn selects_the_gtpack_layer() {
+        let manifest = Manifest {
+            layers: vec![
+                layer("application/vnd.oci.image.layer.v1.tar", "sha256:aa"),
+                layer(GTPACK_MEDIA_TYPE, "sha256:bb"),
+            ],
+            manifests: Vec::new(),
+        };
+        assert_eq!(
+            select_layer(&manifest, GTPACK_MEDIA_TYPE).unwrap().digest,
+            "sha256:bb"
+        );
+        let err = select_layer(&manifest, "application/x-other").unwrap_err();
+        assert!(err.to_string().contains("vnd.oci.image.layer"), "{err}");
+    }
+
+    #[test]
+    fn layer_selection_refuses_indexes_and_ambiguity() {
+        let index = Manifest {
+            layers: Vec::new(),
+            manifests: vec![layer(
+                "application/vnd.oci.image.manifest.v1+json",
+                "sha256:cc",
+            )],
+        };
+        assert!(select_layer(&index, GTPACK_MEDIA_TYPE).is_err());
+
+        let doubled = Manifest {
+            layers: vec![
+                layer(GTPACK_MEDIA_TYPE, "sha256:aa"),
+                layer(GTPACK_MEDIA_TYPE, "sha256:bb"),
+            ],
+            manifests: Vec::new(),
+        };
+        assert!(select_layer(&doubled, GTPACK_MEDIA_TYPE).is_err());
+    }
+
+    #[test]
+    fn parses_bearer_challenges() {
+        let challenge = BearerChallenge::parse(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:greentic/demo:pull""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.realm, "https://ghcr.io/token");
+        assert_eq!(challenge.service.as_deref(), Some("ghcr.io"));
+        assert_eq!(
+            challenge.scope.as_deref(),
+            Some("repository:greentic/demo:pull")
+        );
+        assert!(BearerChallenge::parse(r#"Basic realm="registry""#).is_none());
+    }
+
+    #[test]
+    fn reads_docker_config_credentials() {
+        let config = r#"{"auths":{
+            "https://ghcr.io": {"auth": "dXNlcjpwYXNz"},
+            "registry.internal:5000": {"username": "bot", "password": "secret"}
+        }}"#;
+        assert_eq!(
+            docker_auth(config, "ghcr.io").unwrap().header(),
+            "Basic dXNlcjpwYXNz"
+        );
+        assert_eq!(
+            docker_auth(config, "registry.internal:5000")
+                .unwrap()
+                .header(),
+            format!("Basic {}", STANDARD.encode("bot:secret"))
+        );
+        assert!(docker_auth(config, "docker.io").is_none());
     }
 }
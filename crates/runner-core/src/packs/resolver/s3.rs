@@ -1,18 +1,239 @@
-use anyhow::Result;
+use std::env;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::copy;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
 use greentic_config_types::NetworkConfig;
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use url::Url;
+
+use super::http::{HashingWriter, build_client};
+use super::{FetchRequest, FetchResponse, PackResolver};
+
+/// S3 reports an unsigned body hash this way; GETs have no body to sign.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Where and as whom [`S3Resolver`] talks to S3. [`NetworkConfig`] carries no region or
+/// endpoint, so [`S3Settings::from_env`] reads the standard AWS variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Settings {
+    pub region: String,
+    /// Custom endpoint such as a MinIO server; implies path-style URLs.
+    pub endpoint: Option<String>,
+    /// `None` pulls anonymously, which public buckets allow.
+    pub credentials: Option<S3Credentials>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for S3Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Settings {
+    /// `AWS_REGION` (or `AWS_DEFAULT_REGION`, else `us-east-1`), `AWS_ENDPOINT_URL_S3` (or
+    /// `AWS_ENDPOINT_URL`) and `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY`/`AWS_SESSION_TOKEN`.
+    pub fn from_env() -> Self {
+        let credentials = match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => Some(S3Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            _ => None,
+        };
+        Self {
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| "us-east-1".into()),
+            endpoint: var("AWS_ENDPOINT_URL_S3").or_else(|| var("AWS_ENDPOINT_URL")),
+            credentials,
+        }
+    }
+
+    fn object_url(&self, location: &S3Location) -> Result<Url> {
+        let key = location
+            .key
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<_>>()
+            .join("/");
+        let raw = match &self.endpoint {
+            Some(endpoint) => format!(
+                "{}/{}/{key}",
+                endpoint.trim_end_matches('/'),
+                uri_encode(&location.bucket)
+            ),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{key}",
+                location.bucket, self.region
+            ),
+        };
+        Url::parse(&raw).with_context(|| format!("invalid S3 object URL `{raw}`"))
+    }
+}
+
+/// `s3://bucket/key` split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Location {
+    pub fn parse(locator: &str) -> Result<Self> {
+        let rest = locator
+            .strip_prefix("s3://")
+            .ok_or_else(|| anyhow!("S3 locator `{locator}` must start with s3://"))?;
+        let (bucket, key) = rest
+            .split_once('/')
+            .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+            .ok_or_else(|| anyhow!("S3 locator `{locator}` needs a bucket and a key"))?;
+        Ok(Self {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for S3Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// Failures worth telling apart from transport errors; recover with
+/// `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum S3Error {
+    NotFound { location: String },
+    AccessDenied { location: String, anonymous: bool },
+}
 
-use super::{FetchResponse, HttpResolver, PackResolver};
+impl fmt::Display for S3Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound { location } => write!(f, "S3 object {location} does not exist"),
+            Self::AccessDenied {
+                location,
+                anonymous: true,
+            } => write!(
+                f,
+                "access denied to S3 object {location} for anonymous requests; \
+                 set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY"
+            ),
+            Self::AccessDenied { location, .. } => {
+                write!(f, "access denied to S3 object {location}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for S3Error {}
 
 pub struct S3Resolver {
-    inner: HttpResolver,
+    client: Client,
+    settings: S3Settings,
+    download_dir: Option<PathBuf>,
 }
 
 impl S3Resolver {
     pub fn new(network: Option<&NetworkConfig>) -> Result<Self> {
         Ok(Self {
-            inner: HttpResolver::new("s3", network)?,
+            client: build_client(network)?,
+            settings: S3Settings::from_env(),
+            download_dir: None,
         })
     }
+
+    pub fn with_settings(mut self, settings: S3Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Stream objects into `dir`, next to the pack cache, instead of the system temp dir.
+    pub fn with_download_dir(mut self, dir: PathBuf) -> Self {
+        self.download_dir = Some(dir);
+        self
+    }
+
+    fn download(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        let location = S3Location::parse(request.locator)?;
+        let url = self.settings.object_url(&location)?;
+        let mut get = self.client.get(url.clone());
+        if let Some(credentials) = &self.settings.credentials {
+            for (name, value) in sign_get(&url, &self.settings.region, credentials, Utc::now()) {
+                get = get.header(name, value);
+            }
+        }
+        let mut response = get
+            .send()
+            .with_context(|| format!("failed to download {location}"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let code = response.text().ok().and_then(|body| error_code(&body));
+            let location = location.to_string();
+            return Err(match (status, code.as_deref()) {
+                (_, Some("NoSuchKey" | "NoSuchBucket")) | (StatusCode::NOT_FOUND, _) => {
+                    S3Error::NotFound { location }.into()
+                }
+                (_, Some("AccessDenied")) | (StatusCode::FORBIDDEN, _) => S3Error::AccessDenied {
+                    location,
+                    anonymous: self.settings.credentials.is_none(),
+                }
+                .into(),
+                _ => anyhow!(
+                    "download failed {location}: HTTP {status}{}",
+                    code.map(|code| format!(" ({code})")).unwrap_or_default()
+                ),
+            });
+        }
+
+        let mut temp = self.temp_file()?;
+        let found = {
+            let mut writer = HashingWriter::new(temp.as_file_mut());
+            copy(&mut response, &mut writer)
+                .with_context(|| format!("failed to stream {location}"))?;
+            writer.finish()
+        };
+        if let Some(expected) = request
+            .expected_digest
+            .filter(|expected| expected.algorithm() == "sha256")
+            && !expected.value().eq_ignore_ascii_case(&found)
+        {
+            bail!(
+                "digest mismatch for {location}: expected {}, found sha256:{found}",
+                expected.as_str()
+            );
+        }
+        Ok(FetchResponse::from_temp(temp.into_temp_path()))
+    }
+
+    fn temp_file(&self) -> Result<NamedTempFile> {
+        let Some(dir) = &self.download_dir else {
+            return NamedTempFile::new().context("failed to allocate temp file for download");
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create download dir {}", dir.display()))?;
+        NamedTempFile::new_in(dir).context("failed to allocate temp file for download")
+    }
 }
 
 impl PackResolver for S3Resolver {
@@ -21,6 +242,186 @@ impl PackResolver for S3Resolver {
     }
 
     fn fetch(&self, locator: &str) -> Result<FetchResponse> {
-        self.inner.fetch(locator)
+        self.download(&FetchRequest::new(locator))
+    }
+
+    fn fetch_entry(&self, request: &FetchRequest<'_>) -> Result<FetchResponse> {
+        self.download(request)
+    }
+}
+
+/// SigV4 headers for an unsigned-payload GET of `url`.
+fn sign_get(
+    url: &Url,
+    region: &str,
+    credentials: &S3Credentials,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "GET\n{}\n{}\n{canonical_headers}\n{signed_headers}\n{UNSIGNED_PAYLOAD}",
+        url.path(),
+        url.query().unwrap_or_default()
+    );
+
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{:0x}",
+        Sha256::digest(canonical_request.as_bytes())
+    );
+    let mut key = hmac(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        &date,
+    );
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    let signature = format!("{:0x}", hmac(&key, &string_to_sign));
+
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            credentials.access_key_id
+        ),
+    ));
+    headers
+}
+
+fn hmac(key: &[u8], data: &str) -> hmac::digest::Output<Hmac<Sha256>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes()
+}
+
+/// Percent-encode everything but the SigV4 unreserved characters.
+fn uri_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            other => {
+                let _ = write!(encoded, "%{other:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// The `<Code>` of an S3 XML error body.
+fn error_code(body: &str) -> Option<String> {
+    let start = body.find("<Code>")? + "<Code>".len();
+    let end = body[start..].find("</Code>")? + start;
+    Some(body[start..end].trim().to_string())
+}
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn settings(endpoint: Option<&str>) -> S3Settings {
+        S3Settings {
+            region: "eu-west-1".into(),
+            endpoint: endpoint.map(str::to_string),
+            credentials: None,
+        }
+    }
+
+    #[test]
+    fn parses_bucket_and_key() {
+        let location = S3Location::parse("s3://packs/tenants/demo/pack v1.gtpack").unwrap();
+        assert_eq!(location.bucket, "packs");
+        assert_eq!(location.key, "tenants/demo/pack v1.gtpack");
+        assert_eq!(
+            location.to_string(),
+            "s3://packs/tenants/demo/pack v1.gtpack"
+        );
+
+        assert!(S3Location::parse("s3://packs").is_err());
+        assert!(S3Location::parse("s3://packs/").is_err());
+        assert!(S3Location::parse("s3:///key").is_err());
+        assert!(S3Location::parse("https://packs/key").is_err());
+    }
+
+    #[test]
+    fn builds_virtual_hosted_and_endpoint_urls() {
+        let location = S3Location::parse("s3://packs/demo/pack v1.gtpack").unwrap();
+        assert_eq!(
+            settings(None).object_url(&location).unwrap().as_str(),
+            "https://packs.s3.eu-west-1.amazonaws.com/demo/pack%20v1.gtpack"
+        );
+        assert_eq!(
+            settings(Some("http://127.0.0.1:9000/"))
+                .object_url(&location)
+                .unwrap()
+                .as_str(),
+            "http://127.0.0.1:9000/packs/demo/pack%20v1.gtpack"
+        );
+    }
+
+    #[test]
+    fn signs_with_the_request_scope() {
+        let url = Url::parse("http://127.0.0.1:9000/packs/demo.gtpack").unwrap();
+        let credentials = S3Credentials {
+            access_key_id: "AKIDEXAMPLE".into(),
+            secret_access_key: "secret".into(),
+            session_token: Some("session".into()),
+        };
+        let now = Utc.with_ymd_and_hms(2025, 3, 4, 5, 6, 7).unwrap();
+        let headers = sign_get(&url, "eu-west-1", &credentials, now);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(header("x-amz-date"), Some("20250304T050607Z"));
+        assert_eq!(header("x-amz-security-token"), Some("session"));
+        let authorization = header("authorization").unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20250304/eu-west-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token, Signature="
+        ));
+        // Signing is deterministic for a fixed clock.
+        assert_eq!(headers, sign_get(&url, "eu-west-1", &credentials, now));
+    }
+
+    #[test]
+    fn reads_error_codes() {
+        let body =
+            "<?xml version=\"1.0\"?><Error><Code>NoSuchKey</Code><Message>x</Message></Error>";
+        assert_eq!(error_code(body).as_deref(), Some("NoSuchKey"));
+        assert_eq!(error_code("not xml"), None);
     }
 }
@@ -0,0 +1,120 @@
+//! Runs against a throwaway registry:
+//! `docker run -d -p 5000:5000 registry:2`, then
+//! `cargo test -p runner-core --features oci-registry-tests --test oci_registry`.
+#![cfg(feature = "oci-registry-tests")]
+
+use std::fs;
+
+use anyhow::{Result, anyhow, ensure};
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use runner_core::packs::resolver::{GTPACK_MEDIA_TYPE, OciResolver, PackResolver};
+use runner_core::packs::{FetchRequest, PackDigest};
+use serde_json::json;
+
+fn registry() -> String {
+    std::env::var("PACK_OCI_TEST_REGISTRY").unwrap_or_else(|_| "localhost:5000".into())
+}
+
+fn upload(client: &Client, base: &str, blob: &[u8]) -> Result<PackDigest> {
+    let digest = PackDigest::sha256_from_bytes(blob);
+    let started = client.post(format!("{base}/blobs/uploads/")).send()?;
+    ensure!(
+        started.status().is_success(),
+        "upload refused: {}",
+        started.status()
+    );
+    let location = started
+        .headers()
+        .get(LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| anyhow!("upload without a location"))?;
+    let location = if location.starts_with('/') {
+        format!("http://{}{location}", registry())
+    } else {
+        location.to_string()
+    };
+    let separator = if location.contains('?') { '&' } else { '?' };
+    let put = client
+        .put(format!("{location}{separator}digest={}", digest.as_str()))
+        .body(blob.to_vec())
+        .send()?;
+    ensure!(
+        put.status().is_success(),
+        "blob upload failed: {}",
+        put.status()
+    );
+    Ok(digest)
+}
+
+/// Push `layer` as the pack layer of `repo:tag`; returns the manifest digest.
+fn push(repo: &str, tag: &str, layer: &[u8]) -> Result<PackDigest> {
+    let client = Client::new();
+    let base = format!("http://{}/v2/{repo}", registry());
+    let config = b"{}";
+    let config_digest = upload(&client, &base, config)?;
+    let layer_digest = upload(&client, &base, layer)?;
+    let manifest = serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.empty.v1+json",
+            "digest": config_digest.as_str(),
+            "size": config.len(),
+        },
+        "layers": [{
+            "mediaType": GTPACK_MEDIA_TYPE,
+            "digest": layer_digest.as_str(),
+            "size": layer.len(),
+        }],
+    }))?;
+    let put = client
+        .put(format!("{base}/manifests/{tag}"))
+        .header(CONTENT_TYPE, "application/vnd.oci.image.manifest.v1+json")
+        .body(manifest.clone())
+        .send()?;
+    ensure!(
+        put.status().is_success(),
+        "manifest push failed: {}",
+        put.status()
+    );
+    Ok(PackDigest::sha256_from_bytes(&manifest))
+}
+
+fn resolver() -> Result<OciResolver> {
+    Ok(OciResolver::new(None)?
+        .with_token(None)
+        .with_docker_config(None)
+        .with_media_type(GTPACK_MEDIA_TYPE))
+}
+
+#[test]
+fn pulls_tagged_pack_layer() -> Result<()> {
+    let layer = b"tagged pack".to_vec();
+    push("greentic/tagged", "1.0.0", &layer)?;
+
+    let locator = format!("oci://{}/greentic/tagged:1.0.0", registry());
+    let response = resolver()?.fetch(&locator)?;
+    assert_eq!(fs::read(response.path())?, layer);
+    Ok(())
+}
+
+#[test]
+fn pinned_pull_reuses_the_cached_pack() -> Result<()> {
+    let layer = b"pinned pack".to_vec();
+    let manifest = push("greentic/pinned", "1.0.0", &layer)?;
+    let store = tempfile::tempdir()?;
+    let resolver = resolver()?.with_store(store.path().to_path_buf());
+
+    let locator = format!("oci://{}/greentic/pinned@{}", registry(), manifest.as_str());
+    let first = resolver.fetch_entry(&FetchRequest::new(&locator))?;
+    assert_eq!(fs::read(first.path())?, layer);
+    let cached = store.path().join("pack.gtpack");
+    fs::copy(first.path(), &cached)?;
+
+    // Nothing listens on this registry; only the stored manifest can answer.
+    let offline = format!("oci://127.0.0.1:9/greentic/pinned@{}", manifest.as_str());
+    let second = resolver.fetch_entry(&FetchRequest::new(&offline).with_cached(&cached, None))?;
+    assert_eq!(second.path(), cached.as_path());
+    Ok(())
+}
@@ -1,16 +1,21 @@
 use std::fs;
-use std::io::ErrorKind;
-use std::net::TcpListener;
+use std::io::{Cursor, ErrorKind};
+use std::net::{SocketAddr, TcpListener};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use greentic_pack::builder::{FlowBundle, PACK_VERSION, PackBuilder, PackMeta};
-use runner_core::packs::PackDigest;
+use runner_core::packs::resolver::{HttpResolver, PackResolver, RetryPolicy};
+use runner_core::packs::{FetchRequest, FetchResponse, PackDigest};
 use runner_core::{Index, IndexLocation, PackConfig, PackManager, PackSource};
 use semver::Version;
 use serde_json::json;
-use tiny_http::{Response, Server};
+use tiny_http::{Header, Request, Response, Server};
 
 fn sample_meta() -> PackMeta {
     PackMeta {
@@ -102,6 +107,7 @@ fn build_config(index: &Path, cache_dir: &Path, source: PackSource) -> PackConfi
         cache_dir: cache_dir.to_path_buf(),
         public_key: None,
         network: None,
+        max_concurrent_fetches: runner_core::env::DEFAULT_MAX_CONCURRENT_FETCHES,
     }
 }
 
@@ -163,3 +169,415 @@ fn resolves_http_pack() -> Result<()> {
     assert_eq!(tenant.main.digest.as_str(), digest.as_str());
     Ok(())
 }
+
+/// Answer requests with `respond`, given each request and its 0-based position, on a
+/// local port; `None` when the sandbox does not allow binding one.
+fn serve<F>(mut respond: F) -> Result<Option<SocketAddr>>
+where
+    F: FnMut(&Request, usize) -> Response<Cursor<Vec<u8>>> + Send + 'static,
+{
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping http resolver test: {err}");
+            return Ok(None);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let addr = listener.local_addr()?;
+    let server =
+        Server::from_listener(listener, None).map_err(|err| anyhow!("server error: {err}"))?;
+    thread::spawn(move || {
+        for (position, request) in server.incoming_requests().enumerate() {
+            let response = respond(&request, position);
+            let _ = request.respond(response);
+        }
+    });
+    Ok(Some(addr))
+}
+
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    }
+}
+
+#[test]
+fn http_resolver_retries_server_errors() -> Result<()> {
+    let body = b"pack bytes".to_vec();
+    let digest = PackDigest::sha256_from_bytes(&body);
+    let hits = Arc::new(AtomicUsize::new(0));
+    let served = body.clone();
+    let counter = Arc::clone(&hits);
+    let Some(addr) = serve(move |_, position| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if position < 2 {
+            Response::from_data(b"busy".to_vec()).with_status_code(500)
+        } else {
+            Response::from_data(served.clone())
+        }
+    })?
+    else {
+        return Ok(());
+    };
+
+    let temp = tempfile::tempdir()?;
+    let resolver = HttpResolver::new("http", None)?
+        .with_retry(fast_retry())
+        .with_temp_dir(temp.path().join("downloads"));
+    let locator = format!("http://{addr}/pack.gtpack");
+    let response =
+        resolver.fetch_entry(&FetchRequest::new(&locator).with_expected_digest(Some(&digest)))?;
+    assert_eq!(fs::read(response.path())?, body);
+    assert!(response.path().starts_with(temp.path().join("downloads")));
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[test]
+fn http_resolver_rejects_corrupted_body() -> Result<()> {
+    let digest = PackDigest::sha256_from_bytes(b"pack bytes");
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&hits);
+    let Some(addr) = serve(move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Response::from_data(b"pack bytez".to_vec())
+    })?
+    else {
+        return Ok(());
+    };
+
+    let resolver = HttpResolver::new("http", None)?.with_retry(fast_retry());
+    let locator = format!("http://{addr}/pack.gtpack");
+    let err = match resolver
+        .fetch_entry(&FetchRequest::new(&locator).with_expected_digest(Some(&digest)))
+    {
+        Ok(_) => panic!("corrupted body was accepted"),
+        Err(err) => err,
+    };
+    assert!(format!("{err:#}").contains("digest mismatch"), "{err:#}");
+    // A wrong body is not transient; it is not fetched again.
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn http_pack_is_revalidated_with_etag() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let pack_path = build_test_pack(temp.path())?;
+    let digest = compute_digest(&pack_path)?;
+    let bytes = fs::read(&pack_path)?;
+    let not_modified = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&not_modified);
+    let Some(addr) = serve(move |request, _| {
+        let revalidating = request
+            .headers()
+            .iter()
+            .any(|header| header.field.equiv("If-None-Match") && header.value.as_str() == "\"v1\"");
+        if revalidating {
+            counter.fetch_add(1, Ordering::SeqCst);
+            return Response::from_data(Vec::new()).with_status_code(304);
+        }
+        Response::from_data(bytes.clone())
+            .with_header(Header::from_bytes(&b"ETag"[..], &b"\"v1\""[..]).unwrap())
+    })?
+    else {
+        return Ok(());
+    };
+
+    let locator = format!("http://{addr}/pack.gtpack");
+    let index_path = temp.path().join("index.json");
+    write_index(&index_path, &locator, &digest)?;
+    let config = build_config(&index_path, &temp.path().join("cache"), PackSource::Http);
+    let index = Index::load(&config.index_location)?;
+    let manager = PackManager::new(config)?;
+
+    let first = manager.resolve_all_for_index(&index)?;
+    assert_eq!(not_modified.load(Ordering::SeqCst), 0);
+    let second = manager.resolve_all_for_index(&index)?;
+    assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+
+    let first = &first.tenants()["demo"].main;
+    let second = &second.tenants()["demo"].main;
+    assert_eq!(first.path, second.path);
+    assert_eq!(second.digest.as_str(), digest.as_str());
+    Ok(())
+}
+
+/// `runner.demo` at `demo_version`, plus `runner.extra` as an overlay when given.
+fn write_refresh_index(
+    path: &Path,
+    locator: &str,
+    digest: &PackDigest,
+    demo_version: &str,
+    extra: Option<&str>,
+) -> Result<()> {
+    let entry = |name: &str, version: &str| {
+        json!({
+            "name": name,
+            "version": version,
+            "locator": locator,
+            "digest": digest.as_str(),
+        })
+    };
+    let overlays: Vec<_> = extra
+        .map(|version| entry("runner.extra", version))
+        .into_iter()
+        .collect();
+    let index = json!({
+        "demo": {
+            "main_pack": entry("runner.demo", demo_version),
+            "overlays": overlays,
+        }
+    });
+    fs::write(path, serde_json::to_vec_pretty(&index)?)?;
+    Ok(())
+}
+
+fn names(refs: &[runner_core::PackRef]) -> Vec<String> {
+    refs.iter()
+        .map(|reference| format!("{}@{}", reference.name, reference.version.cache_label()))
+        .collect()
+}
+
+#[test]
+fn refresh_reports_index_changes() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let pack_path = build_test_pack(temp.path())?;
+    let digest = compute_digest(&pack_path)?;
+    let locator = pack_path.to_str().unwrap();
+    let index_path = temp.path().join("index.json");
+    let cache_dir = temp.path().join("cache");
+    write_refresh_index(&index_path, locator, &digest, "0.1.0", None)?;
+
+    let config = build_config(&index_path, &cache_dir, PackSource::Fs);
+    let manager = PackManager::new(config)?.with_retire_grace(Duration::ZERO);
+    let report = manager.refresh()?;
+    assert_eq!(names(&report.added), ["runner.demo@0.1.0"]);
+    assert!(report.updated.is_empty() && report.removed.is_empty());
+
+    assert!(!manager.refresh()?.has_changes());
+
+    write_refresh_index(&index_path, locator, &digest, "0.2.0", Some("1.0.0"))?;
+    let report = manager.refresh()?;
+    assert_eq!(names(&report.added), ["runner.extra@1.0.0"]);
+    assert_eq!(names(&report.updated), ["runner.demo@0.2.0"]);
+    assert!(report.removed.is_empty());
+    // The replaced version is past its (zero) grace and pruned.
+    assert!(!cache_dir.join("runner.demo/0.1.0").exists());
+    assert!(cache_dir.join("runner.demo/0.2.0/pack.gtpack").exists());
+
+    write_refresh_index(&index_path, locator, &digest, "0.2.0", None)?;
+    let report = manager.refresh()?;
+    assert_eq!(names(&report.removed), ["runner.extra@1.0.0"]);
+    assert!(report.added.is_empty() && report.updated.is_empty());
+    assert!(!cache_dir.join("runner.extra").join("1.0.0").exists());
+    assert_eq!(report.resolved.tenants()["demo"].overlays.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn removed_packs_stay_cached_through_the_grace_period() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let pack_path = build_test_pack(temp.path())?;
+    let digest = compute_digest(&pack_path)?;
+    let locator = pack_path.to_str().unwrap();
+    let index_path = temp.path().join("index.json");
+    let cache_dir = temp.path().join("cache");
+    write_refresh_index(&index_path, locator, &digest, "0.1.0", Some("1.0.0"))?;
+
+    let manager = PackManager::new(build_config(&index_path, &cache_dir, PackSource::Fs))?;
+    manager.refresh()?;
+    write_refresh_index(&index_path, locator, &digest, "0.1.0", None)?;
+    let report = manager.refresh()?;
+    assert_eq!(names(&report.removed), ["runner.extra@1.0.0"]);
+    assert!(cache_dir.join("runner.extra/1.0.0/pack.gtpack").exists());
+    Ok(())
+}
+
+#[test]
+fn refresh_does_not_refetch_pinned_packs() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let pack_path = build_test_pack(temp.path())?;
+    let digest = compute_digest(&pack_path)?;
+    let bytes = fs::read(&pack_path)?;
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&hits);
+    let Some(addr) = serve(move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Response::from_data(bytes.clone())
+    })?
+    else {
+        return Ok(());
+    };
+
+    let locator = format!("http://{addr}/pack.gtpack");
+    let index_path = temp.path().join("index.json");
+    write_refresh_index(&index_path, &locator, &digest, "0.1.0", None)?;
+    let config = build_config(&index_path, &temp.path().join("cache"), PackSource::Http);
+    let manager = PackManager::new(config)?;
+
+    assert!(manager.refresh()?.has_changes());
+    assert!(!manager.refresh()?.has_changes());
+    assert!(!manager.refresh()?.has_changes());
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn refresh_loop_notices_index_edits() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let pack_path = build_test_pack(temp.path())?;
+    let digest = compute_digest(&pack_path)?;
+    let locator = pack_path.to_str().unwrap();
+    let index_path = temp.path().join("index.json");
+    write_refresh_index(&index_path, locator, &digest, "0.1.0", None)?;
+
+    let config = build_config(&index_path, &temp.path().join("cache"), PackSource::Fs);
+    let manager = Arc::new(PackManager::new(config)?);
+    manager.refresh()?;
+    let (tx, rx) = mpsc::channel();
+    let refresh_loop = manager.spawn_refresh_loop(Duration::from_millis(20), move |outcome| {
+        let _ = tx.send(outcome.map(|report| names(&report.added)));
+    });
+
+    write_refresh_index(&index_path, locator, &digest, "0.1.0", Some("1.0.0"))?;
+    let added = rx
+        .recv_timeout(Duration::from_secs(5))
+        .map_err(|_| anyhow!("refresh loop did not report the edit"))??;
+    assert_eq!(added, ["runner.extra@1.0.0"]);
+    refresh_loop.stop();
+    Ok(())
+}
+
+/// Serves `source` for `slow://` locators after `delay`; `slow://fail-*` locators fail.
+struct SlowResolver {
+    source: PathBuf,
+    delay: Duration,
+    fetches: Arc<AtomicUsize>,
+}
+
+impl PackResolver for SlowResolver {
+    fn scheme(&self) -> &'static str {
+        "slow"
+    }
+
+    fn fetch(&self, locator: &str) -> Result<FetchResponse> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(self.delay);
+        if locator.starts_with("slow://fail-") {
+            bail!("{locator} is unavailable");
+        }
+        Ok(FetchResponse::from_path(self.source.clone()))
+    }
+}
+
+/// One tenant per `(tenant, entry)`, each entry as its main pack.
+fn write_tenant_index(path: &Path, tenants: &[(&str, serde_json::Value)]) -> Result<()> {
+    let index: serde_json::Map<_, _> = tenants
+        .iter()
+        .map(|(tenant, entry)| {
+            (
+                tenant.to_string(),
+                json!({ "main_pack": entry, "overlays": [] }),
+            )
+        })
+        .collect();
+    fs::write(path, serde_json::to_vec_pretty(&index)?)?;
+    Ok(())
+}
+
+fn slow_manager(
+    temp: &Path,
+    tenants: &[(&str, serde_json::Value)],
+    delay: Duration,
+) -> Result<(PackManager, Index, Arc<AtomicUsize>)> {
+    let source = build_test_pack(temp)?;
+    let index_path = temp.join("index.json");
+    write_tenant_index(&index_path, tenants)?;
+    let config = build_config(&index_path, &temp.join("cache"), PackSource::Fs);
+    let index = Index::load(&config.index_location)?;
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let manager = PackManager::new(config)?.with_resolver(SlowResolver {
+        source,
+        delay,
+        fetches: Arc::clone(&fetches),
+    });
+    Ok((manager, index, fetches))
+}
+
+#[test]
+fn distinct_packs_are_fetched_concurrently() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let delay = Duration::from_millis(300);
+    let tenants: Vec<(String, serde_json::Value)> = (0..4)
+        .map(|n| {
+            (
+                format!("tenant-{n}"),
+                json!({ "name": format!("pack-{n}"), "version": "0.1.0", "locator": format!("slow://pack-{n}") }),
+            )
+        })
+        .collect();
+    let tenants: Vec<(&str, serde_json::Value)> = tenants
+        .iter()
+        .map(|(tenant, entry)| (tenant.as_str(), entry.clone()))
+        .collect();
+    let (manager, index, fetches) = slow_manager(temp.path(), &tenants, delay)?;
+
+    let started = Instant::now();
+    let resolved = manager.resolve_all_for_index(&index)?;
+    let elapsed = started.elapsed();
+    assert_eq!(resolved.tenants().len(), 4);
+    assert_eq!(fetches.load(Ordering::SeqCst), 4);
+    // Sequential fetches would take four delays.
+    assert!(elapsed < delay * 3, "took {elapsed:?}");
+    Ok(())
+}
+
+#[test]
+fn shared_digest_is_fetched_once() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let digest = compute_digest(&build_test_pack(temp.path())?)?;
+    let entry = |name: &str, locator: &str| json!({ "name": name, "version": "0.1.0", "locator": locator, "digest": digest.as_str() });
+    let tenants = [
+        ("alpha", entry("runner.alpha", "slow://a")),
+        ("beta", entry("runner.beta", "slow://b")),
+        ("gamma", entry("runner.alpha", "slow://a")),
+    ];
+    let (manager, index, fetches) = slow_manager(temp.path(), &tenants, Duration::ZERO)?;
+
+    let resolved = manager.resolve_all_for_index(&index)?;
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    let beta = &resolved.tenants()["beta"].main;
+    assert_eq!(beta.reference.name, "runner.beta");
+    assert_eq!(beta.locator, "slow://b");
+    assert!(beta.path.ends_with("runner.beta/0.1.0/pack.gtpack"));
+    assert_eq!(beta.digest.as_str(), digest.as_str());
+    Ok(())
+}
+
+#[test]
+fn fetch_failures_are_reported_together() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    let entry =
+        |name: &str, locator: &str| json!({ "name": name, "version": "0.1.0", "locator": locator });
+    let tenants = [
+        ("alpha", entry("runner.alpha", "slow://fail-alpha")),
+        ("beta", entry("runner.beta", "slow://beta")),
+        ("gamma", entry("runner.gamma", "slow://fail-gamma")),
+    ];
+    let (manager, index, fetches) = slow_manager(temp.path(), &tenants, Duration::ZERO)?;
+
+    let err = match manager.resolve_all_for_index(&index) {
+        Ok(_) => panic!("failing packs resolved"),
+        Err(err) => err.to_string(),
+    };
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    assert!(err.contains("failed to resolve 2 pack(s)"), "{err}");
+    assert!(err.contains("alpha/runner.alpha"), "{err}");
+    assert!(err.contains("gamma/runner.gamma"), "{err}");
+    assert!(!err.contains("beta"), "{err}");
+    Ok(())
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "s3")]
+
+use std::fs;
+use std::io::{Cursor, ErrorKind};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{Result, anyhow};
+use runner_core::packs::resolver::{PackResolver, S3Credentials, S3Error, S3Resolver, S3Settings};
+use runner_core::packs::{FetchRequest, PackDigest};
+use tiny_http::{Request, Response, Server};
+
+/// Answer requests with `respond` on a local port standing in for an S3 endpoint;
+/// `None` when the sandbox does not allow binding one.
+fn serve<F>(mut respond: F) -> Result<Option<SocketAddr>>
+where
+    F: FnMut(&Request) -> Response<Cursor<Vec<u8>>> + Send + 'static,
+{
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping s3 resolver test: {err}");
+            return Ok(None);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let addr = listener.local_addr()?;
+    let server =
+        Server::from_listener(listener, None).map_err(|err| anyhow!("server error: {err}"))?;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = respond(&request);
+            let _ = request.respond(response);
+        }
+    });
+    Ok(Some(addr))
+}
+
+fn resolver(addr: SocketAddr, credentials: Option<S3Credentials>) -> Result<S3Resolver> {
+    Ok(S3Resolver::new(None)?.with_settings(S3Settings {
+        region: "us-east-1".into(),
+        endpoint: Some(format!("http://{addr}")),
+        credentials,
+    }))
+}
+
+fn s3_error(code: &str, status: u16) -> Response<Cursor<Vec<u8>>> {
+    Response::from_data(format!("<Error><Code>{code}</Code></Error>").into_bytes())
+        .with_status_code(status)
+}
+
+#[test]
+fn streams_object_and_signs_the_request() -> Result<()> {
+    let body = b"pack bytes".to_vec();
+    let digest = PackDigest::sha256_from_bytes(&body);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = Arc::clone(&seen);
+    let served = body.clone();
+    let Some(addr) = serve(move |request| {
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.to_string());
+        record
+            .lock()
+            .unwrap()
+            .push((request.url().to_string(), authorization));
+        Response::from_data(served.clone())
+    })?
+    else {
+        return Ok(());
+    };
+
+    let downloads = tempfile::tempdir()?;
+    let resolver = resolver(
+        addr,
+        Some(S3Credentials {
+            access_key_id: "minio".into(),
+            secret_access_key: "minio-secret".into(),
+            session_token: None,
+        }),
+    )?
+    .with_download_dir(downloads.path().to_path_buf());
+    let response = resolver.fetch_entry(
+        &FetchRequest::new("s3://packs/demo/pack.gtpack").with_expected_digest(Some(&digest)),
+    )?;
+    assert_eq!(fs::read(response.path())?, body);
+    assert!(response.path().starts_with(downloads.path()));
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0].0, "/packs/demo/pack.gtpack");
+    let authorization = seen[0].1.as_deref().expect("request was not signed");
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=minio/"));
+    Ok(())
+}
+
+#[test]
+fn anonymous_requests_are_unsigned() -> Result<()> {
+    let signed = Arc::new(Mutex::new(None));
+    let record = Arc::clone(&signed);
+    let Some(addr) = serve(move |request| {
+        *record.lock().unwrap() = Some(
+            request
+                .headers()
+                .iter()
+                .any(|header| header.field.equiv("Authorization")),
+        );
+        Response::from_data(b"public".to_vec())
+    })?
+    else {
+        return Ok(());
+    };
+
+    resolver(addr, None)?.fetch("s3://public/pack.gtpack")?;
+    assert_eq!(*signed.lock().unwrap(), Some(false));
+    Ok(())
+}
+
+#[test]
+fn rejects_object_with_wrong_digest() -> Result<()> {
+    let digest = PackDigest::sha256_from_bytes(b"pack bytes");
+    let Some(addr) = serve(|_| Response::from_data(b"pack bytez".to_vec()))? else {
+        return Ok(());
+    };
+
+    let err = match resolver(addr, None)?.fetch_entry(
+        &FetchRequest::new("s3://packs/pack.gtpack").with_expected_digest(Some(&digest)),
+    ) {
+        Ok(_) => panic!("corrupted object was accepted"),
+        Err(err) => err,
+    };
+    assert!(err.to_string().contains("digest mismatch"), "{err:#}");
+    Ok(())
+}
+
+#[test]
+fn tells_missing_objects_from_denied_ones() -> Result<()> {
+    let Some(addr) = serve(|request| match request.url() {
+        "/packs/missing.gtpack" => s3_error("NoSuchKey", 404),
+        // S3 answers 403 for missing keys the caller may not list.
+        "/packs/hidden.gtpack" => s3_error("NoSuchKey", 403),
+        _ => s3_error("AccessDenied", 403),
+    })?
+    else {
+        return Ok(());
+    };
+
+    let resolver = resolver(addr, None)?;
+    let failure = |locator: &str| -> S3Error {
+        let err = match resolver.fetch(locator) {
+            Ok(_) => panic!("{locator} resolved"),
+            Err(err) => err,
+        };
+        err.downcast_ref::<S3Error>()
+            .unwrap_or_else(|| panic!("untyped failure: {err:#}"))
+            .clone()
+    };
+
+    assert!(matches!(
+        failure("s3://packs/missing.gtpack"),
+        S3Error::NotFound { .. }
+    ));
+    assert!(matches!(
+        failure("s3://packs/hidden.gtpack"),
+        S3Error::NotFound { .. }
+    ));
+    let denied = failure("s3://packs/private.gtpack");
+    assert_eq!(
+        denied,
+        S3Error::AccessDenied {
+            location: "s3://packs/private.gtpack".into(),
+            anonymous: true,
+        }
+    );
+    assert!(denied.to_string().contains("AWS_ACCESS_KEY_ID"));
+    Ok(())
+}
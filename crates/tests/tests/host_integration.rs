@@ -11,6 +11,9 @@ use std::time::Duration;
 use anyhow::{Context, Result, bail};
 use greentic_config_types::{PackSourceConfig, PacksConfig};
 use greentic_flow::flow_bundle::load_and_validate_bundle_with_flow;
+use greentic_runner_host::reload::{
+    ConfigReloader, ConfigSource, ReloadSignals, spawn_reload_listener,
+};
 use greentic_runner_host::watcher;
 use greentic_runner_host::{Activity, HostBuilder, HostConfig, RunnerHost};
 use greentic_types::{
@@ -251,6 +254,74 @@ async fn pack_watcher_handles_overlays() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+#[serial]
+async fn reload_signal_applies_new_bindings() -> Result<()> {
+    let temp = TempDir::new()?;
+    let cache_dir = temp.path().join("cache");
+    fs::create_dir_all(&cache_dir)?;
+    let index_path = temp.path().join("index.json");
+    write_tenant_index(&index_path, &["acme"])?;
+    let bindings_dir = temp.path().join("bindings");
+    fs::create_dir_all(&bindings_dir)?;
+    fs::write(bindings_dir.join("acme.yaml"), "tenant: acme\n")?;
+
+    let _backend_guard = EnvGuard::set("SECRETS_BACKEND", "env");
+
+    let pack_cfg = pack_config_with_index(cache_dir.as_path(), index_path.as_path());
+    let config = HostConfig::load_from_path(bindings_dir.join("acme.yaml"))?;
+    let host = Arc::new(HostBuilder::new().with_config(config).build()?);
+    host.start().await?;
+
+    let (watcher_guard, reload) =
+        watcher::start_pack_watcher(Arc::clone(&host), pack_cfg, Duration::from_secs(3600)).await?;
+
+    let source_dir = bindings_dir.clone();
+    let source: ConfigSource = Arc::new(move || {
+        let mut configs = Vec::new();
+        for entry in fs::read_dir(&source_dir)? {
+            configs.push(HostConfig::load_from_path(entry?.path())?);
+        }
+        Ok(configs)
+    });
+    let (signal, signals) = ReloadSignals::channel();
+    let listener = spawn_reload_listener(
+        ConfigReloader::new(Arc::clone(&host), reload, source),
+        signals,
+    );
+
+    assert!(host.tenant("beta").await.is_none());
+    fs::write(bindings_dir.join("beta.yaml"), "tenant: beta\n")?;
+    write_tenant_index(&index_path, &["acme", "beta"])?;
+    signal.send(()).await?;
+    let host_for_reload = Arc::clone(&host);
+    wait_for_async(
+        move || {
+            let host = Arc::clone(&host_for_reload);
+            async move { host.tenant("beta").await.is_some() }
+        },
+        Duration::from_secs(5),
+    )
+    .await?;
+
+    // A broken bindings file must leave the running tenants and configs untouched.
+    fs::write(bindings_dir.join("beta.yaml"), "tenant: [")?;
+    signal.send(()).await?;
+    wait_for(
+        || host.health_state().snapshot().last_error.is_some(),
+        Duration::from_secs(5),
+    )
+    .await?;
+    assert!(host.tenant("acme").await.is_some());
+    assert!(host.tenant("beta").await.is_some());
+    assert!(host.tenant_configs().contains_key("beta"));
+
+    listener.abort();
+    drop(watcher_guard);
+    host.stop().await?;
+    Ok(())
+}
+
 async fn wait_for<F>(mut predicate: F, timeout: Duration) -> Result<()>
 where
     F: FnMut() -> bool,
@@ -566,6 +637,29 @@ fn write_overlay_index(path: &std::path::Path, include_overlay: bool) -> Result<
     Ok(())
 }
 
+fn write_tenant_index(path: &std::path::Path, tenants: &[&str]) -> Result<()> {
+    const DEMO_DIGEST: &str =
+        "sha256:a3195ff0a9befb0192ef4fa7f5aa7fea944c9a9fa58aa25a4e80e9e80b5c36c1";
+    let pack_path = fixture_path("examples/packs/demo.gtpack");
+    let mut index = serde_json::Map::new();
+    for tenant in tenants {
+        index.insert(
+            tenant.to_string(),
+            serde_json::json!({
+                "main_pack": {
+                    "name": "runner.components",
+                    "version": "0.1.0",
+                    "locator": pack_path.display().to_string(),
+                    "digest": DEMO_DIGEST
+                },
+                "overlays": []
+            }),
+        );
+    }
+    fs::write(path, serde_json::to_vec_pretty(&index)?)?;
+    Ok(())
+}
+
 fn pack_config_with_index(cache_root: &Path, index: impl AsRef<Path>) -> PackConfig {
     PackConfig::from_packs(&PacksConfig {
         source: PackSourceConfig::LocalIndex {
@@ -1,13 +1,16 @@
 use std::env;
-use std::net::TcpListener;
-use std::process::Command;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
+use greentic_config::ConfigResolver;
+use greentic_config_types::{PackSourceConfig, PacksConfig};
+use greentic_runner_host::{ListenAddr, ListenerConfig, RouteGroup, RunnerConfig};
+use runner_core::env::PackConfig;
 use runner_core::packs::PackDigest;
 use serde_json::{Value, json};
 use tempfile::TempDir;
-use tokio::time::sleep;
 
 #[tokio::test]
 #[ignore = "requires a live weather pack + bindings + webhook-capable flow"]
@@ -49,50 +52,34 @@ async fn weather_smoke() -> Result<()> {
     });
     tokio::fs::write(&index_path, serde_json::to_vec_pretty(&index)?).await?;
 
-    let listener = TcpListener::bind("127.0.0.1:0")?;
-    let port = listener.local_addr()?.port();
-    drop(listener);
-
-    let mut cmd = Command::new("cargo");
-    cmd.args([
-        "run",
-        "-p",
-        "greentic-runner",
-        "--",
-        "--bindings",
-        &bindings_path,
-        "--port",
-        &port.to_string(),
-    ])
-    .env("PACK_SOURCE", "fs")
-    .env("PACK_INDEX_URL", index_path.display().to_string())
-    .env("PACK_CACHE_DIR", &cache_dir)
-    .env(
-        "SECRETS_BACKEND",
-        env::var("SECRETS_BACKEND").unwrap_or_else(|_| "env".into()),
-    );
-    if let Ok(token) = env::var("TELEGRAM_BOT_TOKEN") {
-        cmd.env("TELEGRAM_BOT_TOKEN", token);
+    let mut cfg = RunnerConfig::from_config(
+        ConfigResolver::new().load()?,
+        vec![PathBuf::from(&bindings_path)],
+    )?
+    .with_listeners(vec![ListenerConfig::new(
+        ListenAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], 0))),
+        RouteGroup::ALL,
+    )]);
+    cfg.pack = PackConfig::from_packs(&PacksConfig {
+        source: PackSourceConfig::LocalIndex { path: index_path },
+        cache_dir,
+        index_cache_ttl_secs: None,
+        trust: None,
+    })?;
+    let runner = greentic_runner_host::start(cfg)
+        .await
+        .context("start host")?;
+    let addr = match runner.local_addrs() {
+        [ListenAddr::Tcp(addr)] => *addr,
+        other => bail!("unexpected listeners {other:?}"),
+    };
+    if let Err(err) = runner.wait_ready(Duration::from_secs(10)).await {
+        runner.stop().await?;
+        return Err(err);
     }
-    let mut child = cmd.spawn().context("spawn host binary")?;
 
     let client = reqwest::Client::new();
-    let health_url = format!("http://127.0.0.1:{port}/healthz");
-    let mut ready = false;
-    for _ in 0..40 {
-        if client.get(&health_url).send().await.is_ok() {
-            ready = true;
-            break;
-        }
-        sleep(Duration::from_millis(250)).await;
-    }
-    if !ready {
-        let _ = child.kill();
-        let _ = child.wait();
-        bail!("host never became ready");
-    }
-
-    let webhook_url = format!("http://127.0.0.1:{port}/webhook/{flow_id}");
+    let webhook_url = format!("http://{addr}/webhook/{flow_id}");
     let response = client
         .post(webhook_url)
         .json(&payload)
@@ -101,13 +88,9 @@ async fn weather_smoke() -> Result<()> {
         .context("dispatch webhook")?;
     let status = response.status();
     let body: Value = response.json().await.context("decode webhook response")?;
+    runner.stop().await?;
     if !status.is_success() {
-        let _ = child.kill();
-        let _ = child.wait();
         bail!("webhook failed with {status}: {body}");
     }
-
-    let _ = child.kill();
-    let _ = child.wait();
     Ok(())
 }
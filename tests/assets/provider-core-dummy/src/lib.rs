@@ -9,11 +9,15 @@ use exports::greentic::provider_core::schema_core_api::{
     Guest as ProviderGuest, HealthStatus, InvokeResult, ValidationResult,
 };
 
+const SLOW_ECHO_MS: u64 = 1_000;
+/// Returned by `version`; host tests patch it in the built wasm to fake an upgraded build.
+const BUILD_MARKER: &str = "fixture-build:v1";
+
 struct ProviderCoreImpl;
 
 impl ProviderGuest for ProviderCoreImpl {
     fn describe() -> ValidationResult {
-        r#"{"provider_type":"example.dummy","ops":["echo"]}"#
+        r#"{"provider_type":"example.dummy","ops":["echo","slow_echo","grow_memory","spin","version","write_file","unreachable","log_echo","log_and_trap","leak_and_trap","now"]}"#
             .as_bytes()
             .to_vec()
     }
@@ -31,12 +35,77 @@ impl ProviderGuest for ProviderCoreImpl {
     }
 
     fn invoke(op: String, input_json: Vec<u8>) -> InvokeResult {
-        if op != "echo" {
-            return serde_json::to_vec(&serde_json::json!({ "error": format!("unsupported op {op}") }))
-                .unwrap_or_else(|_| b"{\"error\":\"unsupported\"}".to_vec());
+        match op.as_str() {
+            "echo" => input_json,
+            // Holds the invocation open so hosts can exercise concurrency limits.
+            "slow_echo" => {
+                std::thread::sleep(std::time::Duration::from_millis(SLOW_ECHO_MS));
+                input_json
+            }
+            "version" => serde_json::to_vec(&serde_json::json!({ "message": BUILD_MARKER }))
+                .unwrap_or_default(),
+            // Allocates until the host's memory limit stops the instance.
+            "grow_memory" => {
+                let mut blocks = Vec::new();
+                loop {
+                    blocks.push(vec![1u8; 1 << 20]);
+                    std::hint::black_box(&blocks);
+                }
+            }
+            // Writes the input back to the file named by `message`, through whatever
+            // directories the host preopened.
+            "write_file" => {
+                let request: serde_json::Value =
+                    serde_json::from_slice(&input_json).unwrap_or_default();
+                let path = request["message"].as_str().unwrap_or_default();
+                let result = match std::fs::write(path, &input_json) {
+                    Ok(()) => serde_json::json!({ "message": path }),
+                    Err(err) => serde_json::json!({ "error": err.to_string() }),
+                };
+                serde_json::to_vec(&result).unwrap_or_default()
+            }
+            // Busy-loops until the host's epoch deadline interrupts the instance.
+            "spin" => loop {
+                std::hint::black_box(&op);
+            },
+            // Traps so hosts can report structured trap details.
+            "unreachable" => trap_unreachable(),
+            // Writes to both streams so hosts can capture component stdio.
+            "log_echo" => {
+                println!("log_echo stdout: {} bytes", input_json.len());
+                eprintln!("log_echo stderr: done");
+                input_json
+            }
+            // Reports the WASI wall clock so hosts can check deterministic mode.
+            "now" => {
+                let unix_ms = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_millis() as u64)
+                    .unwrap_or_default();
+                serde_json::to_vec(&serde_json::json!({ "unix_ms": unix_ms })).unwrap_or_default()
+            }
+            "log_and_trap" => {
+                eprintln!("log_and_trap: giving up on purpose");
+                trap_unreachable()
+            }
+            // Echoes its input, attachments included, into stdout and a panic so hosts
+            // can check that secrets are redacted from what they report.
+            "leak_and_trap" => {
+                let input = String::from_utf8_lossy(&input_json);
+                println!("leak_and_trap stdout: {input}");
+                panic!("leak_and_trap rejected {input}")
+            }
+            _ => {
+                serde_json::to_vec(&serde_json::json!({ "error": format!("unsupported op {op}") }))
+                    .unwrap_or_else(|_| b"{\"error\":\"unsupported\"}".to_vec())
+            }
         }
-        input_json
     }
 }
 
+#[inline(never)]
+fn trap_unreachable() -> Vec<u8> {
+    core::arch::wasm32::unreachable()
+}
+
 export!(ProviderCoreImpl);
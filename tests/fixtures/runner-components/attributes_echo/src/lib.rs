@@ -0,0 +1,140 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    inline: r#"
+    package greentic:component@0.5.0;
+
+    interface control {
+      should-cancel: func() -> bool;
+      yield-now: func();
+    }
+
+    interface node {
+      type json = string;
+
+      record impersonation {
+        actor-id: string,
+        reason: option<string>,
+      }
+
+      record tenant-ctx {
+        env: string,
+        tenant: string,
+        tenant-id: string,
+        team: option<string>,
+        team-id: option<string>,
+        user: option<string>,
+        user-id: option<string>,
+        trace-id: option<string>,
+        i18n-id: option<string>,
+        correlation-id: option<string>,
+        attributes: list<tuple<string, string>>,
+        session-id: option<string>,
+        flow-id: option<string>,
+        node-id: option<string>,
+        provider-id: option<string>,
+        deadline-ms: option<s64>,
+        attempt: u32,
+        idempotency-key: option<string>,
+        impersonation: option<impersonation>,
+      }
+
+      record exec-ctx {
+        tenant: tenant-ctx,
+        i18n-id: option<string>,
+        flow-id: string,
+        node-id: option<string>,
+      }
+
+      record node-error {
+        code: string,
+        message: string,
+        retryable: bool,
+        backoff-ms: option<u64>,
+        details: option<json>,
+      }
+
+      variant invoke-result {
+        ok(json),
+        err(node-error),
+      }
+
+      variant stream-event {
+        data(json),
+        progress(u8),
+        done,
+        error(string),
+      }
+
+      enum lifecycle-status { ok }
+
+      get-manifest: func() -> json;
+      on-start: func(ctx: exec-ctx) -> result<lifecycle-status, string>;
+      on-stop: func(ctx: exec-ctx, reason: string) -> result<lifecycle-status, string>;
+      invoke: func(ctx: exec-ctx, op: string, input: json) -> invoke-result;
+      invoke-stream: func(ctx: exec-ctx, op: string, input: json) -> list<stream-event>;
+    }
+
+    world component {
+      import control;
+      export node;
+    }
+    "#,
+    world: "component",
+});
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+use serde_json::{Map, Value};
+
+/// Echoes the tenant attributes and impersonation it was invoked with, for host
+/// propagation tests.
+struct AttributesEcho;
+
+impl NodeGuest for AttributesEcho {
+    fn get_manifest() -> String {
+        r#"{"name":"attributes.echo","ops":["echo"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+        if op != "echo" {
+            return InvokeResult::Err(NodeError {
+                code: "INVALID_OP".into(),
+                message: format!("unsupported op {op}"),
+                retryable: false,
+                backoff_ms: None,
+                details: None,
+            });
+        }
+        let attributes = ctx
+            .tenant
+            .attributes
+            .into_iter()
+            .map(|(key, value)| (key, Value::String(value)))
+            .collect::<Map<_, _>>();
+        let impersonation = ctx
+            .tenant
+            .impersonation
+            .map(|imp| serde_json::json!({ "actor_id": imp.actor_id, "reason": imp.reason }));
+        let output = serde_json::json!({
+            "attributes": attributes,
+            "impersonation": impersonation,
+        });
+        InvokeResult::Ok(output.to_string())
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, _op: String, _input: String) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+export!(AttributesEcho);
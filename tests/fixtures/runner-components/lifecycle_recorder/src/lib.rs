@@ -0,0 +1,70 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    path: "wit/lifecycle-recorder",
+    world: "component",
+    generate_all,
+});
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+use serde_json::{Value, json};
+
+use crate::greentic::state::state_store;
+
+/// State key holding the ordered list of lifecycle calls.
+const HISTORY_KEY: &str = "lifecycle-history";
+
+struct LifecycleRecorder;
+
+impl NodeGuest for LifecycleRecorder {
+    fn get_manifest() -> String {
+        r#"{"name":"lifecycle.recorder","ops":["history"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        record("start".to_string())?;
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, reason: String) -> Result<LifecycleStatus, String> {
+        record(format!("stop:{reason}"))?;
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(_ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+        if op != "history" {
+            return InvokeResult::Err(NodeError {
+                code: "INVALID_OP".into(),
+                message: format!("unsupported op {op}"),
+                retryable: false,
+                backoff_ms: None,
+                details: None,
+            });
+        }
+        InvokeResult::Ok(json!({ "calls": history() }).to_string())
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, _op: String, _input: String) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+fn history() -> Vec<Value> {
+    state_store::read(HISTORY_KEY, None)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Vec<Value>>(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn record(call: String) -> Result<(), String> {
+    let mut calls = history();
+    calls.push(Value::String(call));
+    let bytes = serde_json::to_vec(&calls).map_err(|err| err.to_string())?;
+    state_store::write(HISTORY_KEY, &bytes, None)
+        .map(|_| ())
+        .map_err(|err| format!("{}: {}", err.code, err.message))
+}
+
+export!(LifecycleRecorder);
@@ -0,0 +1,74 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    path: "wit/seeded-reader",
+    world: "component",
+    generate_all,
+});
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+use serde_json::{Value, json};
+
+use crate::greentic::secrets_store::secrets_store;
+use crate::greentic::state::state_store;
+
+/// Reads the `GREETING_TOKEN` secret and the `profile` state document, and writes
+/// what it saw to `greeting` without echoing the secret itself.
+struct SeededReader;
+
+impl NodeGuest for SeededReader {
+    fn get_manifest() -> String {
+        r#"{"name":"seeded.reader","ops":["greet"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(_ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+        if op != "greet" {
+            return InvokeResult::Err(error("INVALID_OP", format!("unsupported op {op}")));
+        }
+        let token = match secrets_store::get("GREETING_TOKEN") {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) | Err(_) => {
+                return InvokeResult::Err(error("MISSING_SECRET", "GREETING_TOKEN".into()));
+            }
+        };
+        let profile: Value = match state_store::read("profile", None) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+            Err(err) => return InvokeResult::Err(error(&err.code, err.message)),
+        };
+        let greeting = json!({
+            "user": profile.get("name").cloned().unwrap_or(Value::Null),
+            "key_len": token.len(),
+        });
+        let bytes = serde_json::to_vec(&greeting).unwrap_or_default();
+        if let Err(err) = state_store::write("greeting", &bytes, None) {
+            return InvokeResult::Err(error(&err.code, err.message));
+        }
+        InvokeResult::Ok(greeting.to_string())
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, _op: String, _input: String) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+fn error(code: &str, message: String) -> NodeError {
+    NodeError {
+        code: code.into(),
+        message,
+        retryable: false,
+        backoff_ms: None,
+        details: None,
+    }
+}
+
+export!(SeededReader);
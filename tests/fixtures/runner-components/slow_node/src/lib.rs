@@ -0,0 +1,122 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    inline: r#"
+    package greentic:component@0.4.0;
+
+    interface node {
+      type json = string;
+
+      record tenant-ctx {
+        tenant: string,
+        team: option<string>,
+        user: option<string>,
+        trace-id: option<string>,
+        correlation-id: option<string>,
+        deadline-unix-ms: option<u64>,
+        attempt: u32,
+        idempotency-key: option<string>,
+      }
+
+      record exec-ctx {
+        tenant: tenant-ctx,
+        flow-id: string,
+        node-id: option<string>,
+      }
+
+      record node-error {
+        code: string,
+        message: string,
+        retryable: bool,
+        backoff-ms: option<u64>,
+        details: option<json>,
+      }
+
+      variant invoke-result {
+        ok(json),
+        err(node-error),
+      }
+
+      variant stream-event {
+        data(json),
+        progress(u8),
+        done,
+        error(string),
+      }
+
+      enum lifecycle-status { ok }
+
+      get-manifest: func() -> json;
+      on-start: func(ctx: exec-ctx) -> result<lifecycle-status, string>;
+      on-stop: func(ctx: exec-ctx, reason: string) -> result<lifecycle-status, string>;
+      invoke: func(ctx: exec-ctx, op: string, input: json) -> invoke-result;
+      invoke-stream: func(ctx: exec-ctx, op: string, input: json) -> list<stream-event>;
+    }
+
+    world component {
+      export node;
+    }
+    "#,
+    world: "component",
+});
+
+use std::time::Duration;
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+use serde_json::{json, Value};
+
+/// Sleeps for `ms` milliseconds of its input, then echoes its `tag`, to time flows.
+struct SlowNode;
+
+impl NodeGuest for SlowNode {
+    fn get_manifest() -> String {
+        r#"{"name":"slow.node","ops":["sleep"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(_ctx: ExecCtx, op: String, input: String) -> InvokeResult {
+        if op != "sleep" {
+            return InvokeResult::Err(NodeError {
+                code: "INVALID_OP".into(),
+                message: format!("unsupported op {op}"),
+                retryable: false,
+                backoff_ms: None,
+                details: None,
+            });
+        }
+        let parsed: Value = serde_json::from_str(&input).unwrap_or(Value::Null);
+        let payload = extract_payload(&parsed);
+        let ms = payload["ms"].as_u64().unwrap_or(0);
+        std::thread::sleep(Duration::from_millis(ms));
+        InvokeResult::Ok(json!({ "tag": payload["tag"], "slept_ms": ms }).to_string())
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, _op: String, _input: String) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+/// The flow input, decoded from the bytes of the invocation envelope's `payload`.
+fn extract_payload(value: &Value) -> Value {
+    if let Some(Value::Array(bytes)) = value.get("payload") {
+        let maybe_vec: Option<Vec<u8>> = bytes
+            .iter()
+            .map(|entry| entry.as_u64().map(|num| num as u8))
+            .collect();
+        if let Some(decoded) = maybe_vec.and_then(|vec| serde_json::from_slice(&vec).ok()) {
+            return decoded;
+        }
+    }
+    value.clone()
+}
+
+export!(SlowNode);
@@ -0,0 +1,130 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    inline: r#"
+    package greentic:component@0.4.0;
+
+    interface control {
+      should-cancel: func() -> bool;
+      yield-now: func();
+    }
+
+    interface node {
+      type json = string;
+
+      record tenant-ctx {
+        tenant: string,
+        team: option<string>,
+        user: option<string>,
+        trace-id: option<string>,
+        correlation-id: option<string>,
+        deadline-unix-ms: option<u64>,
+        attempt: u32,
+        idempotency-key: option<string>,
+      }
+
+      record exec-ctx {
+        tenant: tenant-ctx,
+        flow-id: string,
+        node-id: option<string>,
+      }
+
+      record node-error {
+        code: string,
+        message: string,
+        retryable: bool,
+        backoff-ms: option<u64>,
+        details: option<json>,
+      }
+
+      variant invoke-result {
+        ok(json),
+        err(node-error),
+      }
+
+      variant stream-event {
+        data(json),
+        progress(u8),
+        done,
+        error(string),
+      }
+
+      enum lifecycle-status { ok }
+
+      get-manifest: func() -> json;
+      on-start: func(ctx: exec-ctx) -> result<lifecycle-status, string>;
+      on-stop: func(ctx: exec-ctx, reason: string) -> result<lifecycle-status, string>;
+      invoke: func(ctx: exec-ctx, op: string, input: json) -> invoke-result;
+      invoke-stream: func(ctx: exec-ctx, op: string, input: json) -> list<stream-event>;
+    }
+
+    world component {
+      import control;
+      export node;
+    }
+    "#,
+    world: "component",
+});
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+
+struct StreamProgress;
+
+impl NodeGuest for StreamProgress {
+    fn get_manifest() -> String {
+        r#"{"name":"stream.progress","ops":["progress","count"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(_ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+        match op.as_str() {
+            "progress" => InvokeResult::Ok(r#"{"step":1}"#.to_string()),
+            "count" => InvokeResult::Ok(r#"{"step":3}"#.to_string()),
+            _ => invalid_op(&op),
+        }
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, op: String, _input: String) -> Vec<StreamEvent> {
+        match op.as_str() {
+            "progress" => vec![
+                StreamEvent::Progress(10),
+                StreamEvent::Progress(50),
+                StreamEvent::Data(r#"{"step":1}"#.to_string()),
+                StreamEvent::Progress(100),
+                StreamEvent::Done,
+            ],
+            // One data event per step, so consumers see partial output arrive.
+            "count" => {
+                let mut events = vec![StreamEvent::Progress(0)];
+                for step in 1..=3 {
+                    events.push(StreamEvent::Data(format!(r#"{{"step":{step}}}"#)));
+                }
+                events.push(StreamEvent::Progress(100));
+                events.push(StreamEvent::Done);
+                events
+            }
+            _ => vec![StreamEvent::Error(format!("unsupported op {op}"))],
+        }
+    }
+}
+
+fn invalid_op(op: &str) -> InvokeResult {
+    InvokeResult::Err(NodeError {
+        code: "INVALID_OP".into(),
+        message: format!("unsupported op {op}"),
+        retryable: false,
+        backoff_ms: None,
+        details: None,
+    })
+}
+
+export!(StreamProgress);
@@ -0,0 +1,73 @@
+#![allow(clippy::all)]
+
+wit_bindgen::generate!({
+    path: "wit/tick-counter",
+    world: "component",
+    generate_all,
+});
+
+use exports::greentic::component::node::{
+    ExecCtx, Guest as NodeGuest, InvokeResult, LifecycleStatus, NodeError, StreamEvent,
+};
+use serde_json::json;
+
+use crate::greentic::state::state_store;
+
+/// State key holding how many times `tick` ran.
+const TICKS_KEY: &str = "ticks";
+
+struct TickCounter;
+
+impl NodeGuest for TickCounter {
+    fn get_manifest() -> String {
+        r#"{"name":"tick.counter","ops":["tick","count"]}"#.to_string()
+    }
+
+    fn on_start(_ctx: ExecCtx) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn on_stop(_ctx: ExecCtx, _reason: String) -> Result<LifecycleStatus, String> {
+        Ok(LifecycleStatus::Ok)
+    }
+
+    fn invoke(_ctx: ExecCtx, op: String, _input: String) -> InvokeResult {
+        match op.as_str() {
+            "tick" => {
+                let ticks = ticks() + 1;
+                let bytes = serde_json::to_vec(&ticks).unwrap_or_default();
+                if let Err(err) = state_store::write(TICKS_KEY, &bytes, None) {
+                    return InvokeResult::Err(NodeError {
+                        code: err.code,
+                        message: err.message,
+                        retryable: false,
+                        backoff_ms: None,
+                        details: None,
+                    });
+                }
+                InvokeResult::Ok(json!({ "ticks": ticks }).to_string())
+            }
+            "count" => InvokeResult::Ok(json!({ "ticks": ticks() }).to_string()),
+            other => InvokeResult::Err(NodeError {
+                code: "INVALID_OP".into(),
+                message: format!("unsupported op {other}"),
+                retryable: false,
+                backoff_ms: None,
+                details: None,
+            }),
+        }
+    }
+
+    fn invoke_stream(_ctx: ExecCtx, _op: String, _input: String) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+fn ticks() -> u64 {
+    state_store::read(TICKS_KEY, None)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<u64>(&bytes).ok())
+        .unwrap_or(0)
+}
+
+export!(TickCounter);